@@ -0,0 +1,74 @@
+// Generates the bundled common_interfaces message types, see
+// src/common_interfaces.rs. Each package is behind a feature of the same
+// name, and the definitions are in interfaces/<package>/.
+
+use std::{env, path::Path};
+
+use ros2_client_codegen::Builder;
+
+const PACKAGES: &[&str] = &[
+  "std_msgs",
+  "geometry_msgs",
+  "sensor_msgs",
+  "nav_msgs",
+  "diagnostic_msgs",
+  "tf2_msgs",
+];
+
+// Types that are hand-written in this crate, because they have helper
+// methods and conversions. Generated code refers to these instead.
+const HAND_WRITTEN: &[(&str, &str)] = &[
+  ("std_msgs/Header", "crate::std_msgs::Header"),
+  ("geometry_msgs/Vector3", "crate::geometry_msgs::Vector3"),
+  ("geometry_msgs/Point", "crate::geometry_msgs::Point"),
+  (
+    "geometry_msgs/Quaternion",
+    "crate::geometry_msgs::Quaternion",
+  ),
+  ("geometry_msgs/Pose", "crate::geometry_msgs::Pose"),
+  ("geometry_msgs/Transform", "crate::geometry_msgs::Transform"),
+  ("geometry_msgs/Twist", "crate::geometry_msgs::Twist"),
+  (
+    "geometry_msgs/PointStamped",
+    "crate::geometry_msgs::PointStamped",
+  ),
+  (
+    "geometry_msgs/PoseStamped",
+    "crate::geometry_msgs::PoseStamped",
+  ),
+  (
+    "geometry_msgs/TransformStamped",
+    "crate::geometry_msgs::TransformStamped",
+  ),
+  ("sensor_msgs/Image", "crate::sensor_msgs::Image"),
+  (
+    "sensor_msgs/CompressedImage",
+    "crate::sensor_msgs::CompressedImage",
+  ),
+  ("sensor_msgs/PointCloud2", "crate::sensor_msgs::PointCloud2"),
+  ("sensor_msgs/PointField", "crate::sensor_msgs::PointField"),
+];
+
+fn main() {
+  let interfaces_dir = Path::new("interfaces");
+  let mut builder = Builder::new()
+    .crate_path("crate")
+    .emit_rerun_if_changed(true);
+
+  for package in PACKAGES {
+    let feature = format!("CARGO_FEATURE_{}", package.to_uppercase());
+    if env::var_os(feature).is_some() {
+      builder = builder.package(package, interfaces_dir.join(package));
+    }
+  }
+  for (ros_type, rust_path) in HAND_WRITTEN {
+    builder = builder.extern_type(ros_type, rust_path);
+  }
+
+  let out_dir = env::var_os("OUT_DIR").expect("OUT_DIR not set");
+  let out_file = Path::new(&out_dir).join("common_interfaces.rs");
+  if let Err(e) = builder.write_to_file(out_file) {
+    panic!("Generating common_interfaces failed: {}", e);
+  }
+  println!("cargo:rerun-if-changed=build.rs");
+}
@@ -1,6 +1,5 @@
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
-use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use ros2_client::{
   AService, Context, Message, Name, Node, NodeName, NodeOptions, ServiceMapping, ServiceTypeName,
@@ -52,24 +51,17 @@ fn main() {
 
   println!(">>> ros2_service server created");
 
-  let server_stream = server.receive_request_stream().then(|result| async {
-    match result {
-      Ok((req_id, req)) => {
-        println!("request: {} + {}", req.a, req.b);
-        let resp = AddTwoIntsResponse { sum: req.a + req.b };
-        let sr = server.async_send_response(req_id, resp).await;
-        if let Err(e) = sr {
-          println!("Send error {:?}", e);
-        }
-      }
-      Err(e) => println!("Receive request error: {:?}", e),
-    }
+  // serve() receives each request, runs the handler, and sends the response
+  // back to the right Client.
+  let serving = server.serve(&node, |req| async move {
+    println!("request: {} + {}", req.a, req.b);
+    AddTwoIntsResponse { sum: req.a + req.b }
   });
 
   // run it!
-  smol::block_on(server_stream.count());
-  // .count() here just converts Stream to ordinary Future.
-  // It would return the count of requestes processed, if the stream would end.
+  if let Err(e) = smol::block_on(serving) {
+    println!("Receive request error: {:?}", e);
+  }
 } // main
 
 fn create_qos() -> QosPolicies {
@@ -5,7 +5,7 @@ use log::{debug, error, info, warn};
 use futures::{pin_mut, FutureExt as StdFutureExt, StreamExt};
 use smol::future::FutureExt;
 use ros2_client::{
-  action, action_msgs, ActionTypeName, Context, Name, NodeName, NodeOptions, ServiceMapping,
+  action, ActionTypeName, Context, Name, NodeName, NodeOptions, ServiceMapping,
 };
 use rustdds::{dds::WriteError, policy, QosPolicies, QosPolicyBuilder};
 
@@ -117,69 +117,58 @@ fn main() {
               });
           match goal_response_or_timeout.await
           {
-            Ok((goal_id, goal_response)) => {
-              // Server responded to goal request.
-              println!("<<< Goal Response={:?} goal_id={:?}", goal_response, goal_id);
-              if goal_response.accepted {
-                // Now that we have a goal, we can ask for a result, feedback, and status.
-                let feedback_stream =
-                  fibonacci_action_client.feedback_stream(goal_id);
-                pin_mut!(feedback_stream);
-                let status_stream = fibonacci_action_client.all_statuses_stream();
-                pin_mut!(status_stream);
-                let mut goal_finish_timeout =
-                  futures::FutureExt::fuse(smol::Timer::interval(Duration::from_secs(30)));
-                let result_fut = fibonacci_action_client.async_request_result(goal_id)
-                                    .fuse();
-                pin_mut!(result_fut);
-
-                let mut goal_done = false;
-
-                while ! goal_done {
-                futures::select! {
-                    _ = stop => { run = false; goal_done=true; },
-
-                    _ = goal_finish_timeout => {
-                      goal_done=true;
-                      println!("Goal execution timeout. {:?}", goal_id);
-                    }
-
-                    // get action result
-                    action_result = result_fut => {
-                      goal_done = true;
-                      match action_result {
-                        Ok((goal_status, result)) => {
-                          println!("<<< Action Result: {:?} Status: {:?}", result, goal_status);
-                        }
-                        Err(e) => println!("<<< Action Result error {:?}", e),
-                      }
-                      println!("\n");
-                    }
-
-                    // get action feedback
-                    feedback = feedback_stream.select_next_some() => {
-                      println!("<<< Feedback: {:?}", feedback);
-                    }
-
-                    // get action status changes
-                    status = status_stream.select_next_some() => {
-                      print!("<<< Status: ");
-                      match status {
-                        Ok(status) =>
-                          match status.status_list.iter().find(|gs| gs.goal_info.goal_id == goal_id) {
-                            Some(action_msgs::GoalStatus{goal_info:_, status}) => println!("{:?}",status),
-                            None => println!("Our status is missing: {:?}", status.status_list),
-                          },
-                        Err(e) => println!("{:?}",e),
+            Ok(Some(goal_handle)) => {
+              // Server accepted the goal.
+              println!("<<< Goal accepted goal_id={:?}", goal_handle.goal_id());
+              // Now that we have a goal, we can ask for a result, feedback, and status.
+              let feedback_stream = goal_handle.feedback_stream();
+              pin_mut!(feedback_stream);
+              let status_stream = goal_handle.status_stream();
+              pin_mut!(status_stream);
+              let mut goal_finish_timeout =
+                futures::FutureExt::fuse(smol::Timer::interval(Duration::from_secs(30)));
+              let result_fut = goal_handle.await_result().fuse();
+              pin_mut!(result_fut);
+
+              let mut goal_done = false;
+
+              while ! goal_done {
+              futures::select! {
+                  _ = stop => { run = false; goal_done=true; },
+
+                  _ = goal_finish_timeout => {
+                    goal_done=true;
+                    println!("Goal execution timeout. {:?}", goal_handle.goal_id());
+                  }
+
+                  // get action result
+                  action_result = result_fut => {
+                    goal_done = true;
+                    match action_result {
+                      Ok((goal_status, result)) => {
+                        println!("<<< Action Result: {:?} Status: {:?}", result, goal_status);
                       }
+                      Err(e) => println!("<<< Action Result error {:?}", e),
                     }
-                  } // select!
-                } // while goal not done
-              } else {
-                println!("!!! Goal was not accepted. Sulking for a moment.");
-                smol::Timer::after(Duration::from_secs(5)).await;
-              }
+                    println!("\n");
+                  }
+
+                  // get action feedback
+                  feedback = feedback_stream.select_next_some() => {
+                    println!("<<< Feedback: {:?}", feedback);
+                  }
+
+                  // get action status changes
+                  status = status_stream.select_next_some() => {
+                    println!("<<< Status: {:?}", status);
+                  }
+                } // select!
+              } // while goal not done
             } // Ok(..)
+            Ok(None) => {
+              println!("!!! Goal was not accepted. Sulking for a moment.");
+              smol::Timer::after(Duration::from_secs(5)).await;
+            }
             Err(e) => println!("<<< Goal send error {:?}", e),
           } // match
         }
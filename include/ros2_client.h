@@ -0,0 +1,63 @@
+/*
+ * C API of ros2-client. Build the library with the "ffi" feature, e.g.
+ *   cargo rustc --release --features ffi --crate-type staticlib
+ *
+ * Messages are serialized CDR payloads, little-endian, without the 4-byte
+ * encapsulation header. Received payloads may end in up to 3 bytes of padding.
+ * See src/ffi.rs for the conventions.
+ */
+#ifndef ROS2_CLIENT_H
+#define ROS2_CLIENT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct Ros2Context Ros2Context;
+typedef struct Ros2Node Ros2Node;
+typedef struct Ros2Publisher Ros2Publisher;
+typedef struct Ros2Subscription Ros2Subscription;
+typedef struct Ros2Client Ros2Client;
+
+/* Description of the latest error on this thread, or NULL. */
+const char *ros2_last_error(void);
+
+Ros2Context *ros2_context_new(void);
+void ros2_context_free(Ros2Context *ctx);
+
+/* Also starts a background thread to run the Node's Spinner. */
+Ros2Node *ros2_node_new(Ros2Context *ctx, const char *namespace_, const char *name);
+void ros2_node_free(Ros2Node *node);
+
+/* Type is e.g. "std_msgs", "String" */
+Ros2Publisher *ros2_publisher_new(Ros2Node *node, const char *topic,
+                                  const char *type_package, const char *type_name);
+void ros2_publisher_free(Ros2Publisher *publisher);
+int ros2_publish(Ros2Publisher *publisher, const uint8_t *data, size_t len);
+
+Ros2Subscription *ros2_subscription_new(Ros2Node *node, const char *topic,
+                                        const char *type_package, const char *type_name);
+void ros2_subscription_free(Ros2Subscription *subscription);
+/* Returns 1 if a message was taken, 0 if there was none, negative on error.
+ * Release the message with ros2_bytes_free(). */
+int ros2_take(Ros2Subscription *subscription, uint8_t **data, size_t *len);
+
+void ros2_bytes_free(uint8_t *data, size_t len);
+
+/* Type is e.g. "example_interfaces", "AddTwoInts" */
+Ros2Client *ros2_client_new(Ros2Node *node, const char *service,
+                            const char *type_package, const char *type_name);
+void ros2_client_free(Ros2Client *client);
+/* Returns 0 on success, -2 on timeout, other negative values on error.
+ * Release the response with ros2_bytes_free(). */
+int ros2_call(Ros2Client *client, const uint8_t *request, size_t request_len,
+              uint32_t timeout_ms, uint8_t **response, size_t *response_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ROS2_CLIENT_H */
@@ -0,0 +1,645 @@
+//! Emits Rust code for parsed interface definitions.
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fmt::Write,
+};
+
+use crate::{
+  parser::{self, ArraySpecifier, BaseTypeName, Comment, Item, Line, TypeName, Value},
+  Error,
+};
+
+/// Kind of an interface definition file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InterfaceKind {
+  /// `.msg`
+  Message,
+  /// `.srv`
+  Service,
+  /// `.action`
+  Action,
+}
+
+impl InterfaceKind {
+  /// Kind by file name extension, e.g. `"msg"`
+  pub fn from_extension(extension: &str) -> Option<Self> {
+    match extension {
+      "msg" => Some(InterfaceKind::Message),
+      "srv" => Some(InterfaceKind::Service),
+      "action" => Some(InterfaceKind::Action),
+      _ => None,
+    }
+  }
+
+  /// Also the name of the generated module
+  pub fn subfolder(&self) -> &'static str {
+    match self {
+      InterfaceKind::Message => "msg",
+      InterfaceKind::Service => "srv",
+      InterfaceKind::Action => "action",
+    }
+  }
+
+  fn section_count(&self) -> usize {
+    match self {
+      InterfaceKind::Message => 1,
+      InterfaceKind::Service => 2,
+      InterfaceKind::Action => 3,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Interface {
+  pub kind: InterfaceKind,
+  pub name: String,
+  pub text: String,
+}
+
+/// How to refer to types outside the package being generated
+pub(crate) struct Resolver<'a> {
+  pub crate_path: &'a str,
+  pub generated: &'a BTreeSet<String>,
+  pub extern_packages: &'a BTreeMap<String, String>,
+  /// `(package, type name)` --> Rust path
+  pub extern_types: &'a BTreeMap<(String, String), String>,
+}
+
+impl Resolver<'_> {
+  fn complex_type(
+    &self,
+    own_package: &str,
+    package_name: Option<&str>,
+    type_name: &str,
+  ) -> Result<String, String> {
+    let package = package_name.unwrap_or(own_package);
+    if let Some(path) = self
+      .extern_types
+      .get(&(package.to_string(), type_name.to_string()))
+    {
+      return Ok(path.clone());
+    }
+    match package_name {
+      None => Ok(format!("super::msg::{}", type_name)),
+      Some(p) if p == own_package => Ok(format!("super::msg::{}", type_name)),
+      Some(p) if self.generated.contains(p) => {
+        Ok(format!("super::super::{}::msg::{}", p, type_name))
+      }
+      Some(p) => match self.extern_packages.get(p) {
+        Some(path) => Ok(format!("{}::{}", path, type_name)),
+        None => Err(format!(
+          "Unknown package {:?} of type {}/{}. Generate it too, or declare it with \
+           extern_package().",
+          p, p, type_name
+        )),
+      },
+    }
+  }
+
+  fn base_type(&self, own_package: &str, base: &BaseTypeName) -> Result<String, String> {
+    Ok(match base {
+      BaseTypeName::Primitive { name } => match name.as_str() {
+        "bool" => "bool".to_string(),
+        "byte" | "char" | "uint8" => "u8".to_string(),
+        "float32" => "f32".to_string(),
+        "float64" => "f64".to_string(),
+        "int8" => "i8".to_string(),
+        "int16" => "i16".to_string(),
+        "int32" => "i32".to_string(),
+        "int64" => "i64".to_string(),
+        "uint16" => "u16".to_string(),
+        "uint32" => "u32".to_string(),
+        "uint64" => "u64".to_string(),
+        // Not just `String`, as there is e.g. `std_msgs/String`
+        "string" => "::std::string::String".to_string(),
+        "wstring" => format!("{}::WString", self.crate_path),
+        other => return Err(format!("Unexpected primitive type {}", other)),
+      },
+      BaseTypeName::BoundedString { bound } => {
+        format!("{}::message::BoundedString<{}>", self.crate_path, bound)
+      }
+      // There is no bounded wide string type. The bound is in the doc comment.
+      BaseTypeName::BoundedWString { .. } => format!("{}::WString", self.crate_path),
+      BaseTypeName::ComplexType {
+        package_name,
+        type_name,
+      } => self.complex_type(own_package, package_name.as_deref(), type_name)?,
+    })
+  }
+
+  fn field_type(&self, own_package: &str, t: &TypeName) -> Result<String, String> {
+    let base = self.base_type(own_package, &t.base)?;
+    Ok(match t.array_spec {
+      None => base,
+      Some(ArraySpecifier::Static { size }) => format!("[{}; {}]", base, size),
+      Some(ArraySpecifier::Unbounded) => format!("::std::vec::Vec<{}>", base),
+      Some(ArraySpecifier::Bounded { bound }) => {
+        format!(
+          "{}::message::BoundedVec<{}, {}>",
+          self.crate_path, base, bound
+        )
+      }
+    })
+  }
+}
+
+// Generated code of a package, without the enclosing module
+pub(crate) fn package_contents(
+  resolver: &Resolver,
+  package: &str,
+  interfaces: &[Interface],
+) -> Result<String, Error> {
+  let mut out = String::new();
+  for kind in &[
+    InterfaceKind::Message,
+    InterfaceKind::Service,
+    InterfaceKind::Action,
+  ] {
+    // Messages that are defined elsewhere are not generated.
+    let of_kind: Vec<&Interface> = interfaces
+      .iter()
+      .filter(|i| i.kind == *kind)
+      .filter(|i| {
+        i.kind != InterfaceKind::Message
+          || !resolver
+            .extern_types
+            .contains_key(&(package.to_string(), i.name.clone()))
+      })
+      .collect();
+    if of_kind.is_empty() {
+      continue;
+    }
+    writeln!(out, "pub mod {} {{", kind.subfolder()).unwrap();
+    for (n, interface) in of_kind.into_iter().enumerate() {
+      if n > 0 {
+        out.push('\n');
+      }
+      let file = format!("{}/{}/{}", package, kind.subfolder(), interface.name);
+      let mut g = InterfaceGen {
+        resolver,
+        package,
+        file: &file,
+        out: &mut out,
+      };
+      g.interface(interface)?;
+    }
+    out.push_str("}\n");
+  }
+  Ok(out)
+}
+
+struct InterfaceGen<'a> {
+  resolver: &'a Resolver<'a>,
+  package: &'a str,
+  file: &'a str,
+  out: &'a mut String,
+}
+
+// A parsed section, i.e. a struct
+struct Section {
+  doc: Vec<String>,
+  constants: Vec<(TypeName, String, Value, Vec<String>)>,
+  fields: Vec<(TypeName, String, Option<Value>, Vec<String>)>,
+}
+
+impl InterfaceGen<'_> {
+  fn invalid(&self, message: String) -> Error {
+    Error::Invalid {
+      file: self.file.to_string(),
+      message,
+    }
+  }
+
+  fn interface(&mut self, interface: &Interface) -> Result<(), Error> {
+    let texts = parser::split_sections(&interface.text);
+    if texts.len() != interface.kind.section_count() {
+      return Err(self.invalid(format!(
+        "Expected {} sections separated by \"---\", found {}",
+        interface.kind.section_count(),
+        texts.len()
+      )));
+    }
+    let mut sections = Vec::new();
+    for text in texts {
+      let lines = parser::msg_spec(&text).map_err(|source| Error::Parse {
+        file: self.file.to_string(),
+        source,
+      })?;
+      sections.push(section(lines));
+    }
+
+    let name = &interface.name;
+    let ros_name = format!("`{}/{}/{}`", self.package, interface.kind.subfolder(), name);
+    let crate_path = self.resolver.crate_path;
+    let package = self.package;
+    match interface.kind {
+      InterfaceKind::Message => {
+        let section = &sections[0];
+        doc_comment(self.out, "  ", &section.doc);
+        if !section.doc.is_empty() {
+          self.out.push_str("  ///\n");
+        }
+        writeln!(self.out, "  /// {}", ros_name).unwrap();
+        let type_name_fn = format!(
+          "    pub fn message_type_name() -> {crate_path}::MessageTypeName {{\n      \
+             {crate_path}::MessageTypeName::new({package:?}, {name:?})\n    \
+           }}\n",
+          crate_path = crate_path,
+          package = package,
+          name = name,
+        );
+        self.message_struct(name, section, Some(&type_name_fn))?;
+      }
+      InterfaceKind::Service => {
+        let request = format!("{}Request", name);
+        let response = format!("{}Response", name);
+        writeln!(self.out, "  {}::ros2_service! {{", crate_path).unwrap();
+        doc_comment(self.out, "    ", &sections[0].doc);
+        if !sections[0].doc.is_empty() {
+          self.out.push_str("    ///\n");
+        }
+        writeln!(self.out, "    /// {}", ros_name).unwrap();
+        writeln!(self.out, "    pub {} in {:?} {{", name, package).unwrap();
+        writeln!(self.out, "      #[derive(PartialEq)]").unwrap();
+        writeln!(self.out, "      request {} {{", request).unwrap();
+        self.fields("        ", &sections[0])?;
+        writeln!(self.out, "      }}").unwrap();
+        doc_comment(self.out, "      ", &sections[1].doc);
+        writeln!(self.out, "      #[derive(PartialEq)]").unwrap();
+        writeln!(self.out, "      response {} {{", response).unwrap();
+        self.fields("        ", &sections[1])?;
+        writeln!(self.out, "      }}\n    }}\n  }}").unwrap();
+        self.constants_and_default(&request, &sections[0], None)?;
+        self.constants_and_default(&response, &sections[1], None)?;
+      }
+      InterfaceKind::Action => {
+        let goal = format!("{}Goal", name);
+        let result = format!("{}Result", name);
+        let feedback = format!("{}Feedback", name);
+        for (struct_name, section) in [&goal, &result, &feedback].iter().zip(&sections) {
+          doc_comment(self.out, "  ", &section.doc);
+          self.message_struct(struct_name, section, None)?;
+        }
+        writeln!(self.out, "  /// {}", ros_name).unwrap();
+        writeln!(self.out, "  pub struct {};", name).unwrap();
+        writeln!(
+          self.out,
+          "  impl {crate_path}::ActionTypes for {name} {{\n    \
+               type GoalType = {goal};\n    \
+               type ResultType = {result};\n    \
+               type FeedbackType = {feedback};\n\n    \
+               fn goal_type_name(&self) -> &str {{\n      \
+                 \"{package}::action::dds_::{name}_Goal_\"\n    \
+               }}\n\n    \
+               fn result_type_name(&self) -> &str {{\n      \
+                 \"{package}::action::dds_::{name}_Result_\"\n    \
+               }}\n\n    \
+               fn feedback_type_name(&self) -> &str {{\n      \
+                 \"{package}::action::dds_::{name}_Feedback_\"\n    \
+               }}\n  \
+             }}\n  \
+             impl {name} {{\n    \
+               pub fn action_type_name() -> {crate_path}::ActionTypeName {{\n      \
+                 {crate_path}::ActionTypeName::new({package:?}, {name:?})\n    \
+               }}\n  \
+             }}",
+          crate_path = crate_path,
+          name = name,
+          goal = goal,
+          result = result,
+          feedback = feedback,
+          package = package,
+        )
+        .unwrap();
+      }
+    }
+    Ok(())
+  }
+
+  // `methods` go into the same impl block as the constants.
+  fn message_struct(
+    &mut self,
+    name: &str,
+    section: &Section,
+    methods: Option<&str>,
+  ) -> Result<(), Error> {
+    writeln!(
+      self.out,
+      "  #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]"
+    )
+    .unwrap();
+    writeln!(self.out, "  pub struct {} {{", name).unwrap();
+    self.fields("    ", section)?;
+    writeln!(self.out, "  }}").unwrap();
+    writeln!(
+      self.out,
+      "  impl {}::Message for {} {{}}",
+      self.resolver.crate_path, name
+    )
+    .unwrap();
+    self.constants_and_default(name, section, methods)
+  }
+
+  fn fields(&mut self, indent: &str, section: &Section) -> Result<(), Error> {
+    if section.fields.is_empty() {
+      // Like the structs generated by rosidl
+      writeln!(
+        self.out,
+        "{}pub structure_needs_at_least_one_member: u8,",
+        indent
+      )
+      .unwrap();
+    }
+    for (type_name, field_name, _default, doc) in &section.fields {
+      let rust_type = self
+        .resolver
+        .field_type(self.package, type_name)
+        .map_err(|m| self.invalid(m))?;
+      doc_comment(self.out, indent, doc);
+      if let BaseTypeName::BoundedWString { bound } = type_name.base {
+        writeln!(self.out, "{}/// At most {} characters", indent, bound).unwrap();
+      }
+      if let Some(ArraySpecifier::Static { size }) = type_name.array_spec {
+        if size > 32 {
+          writeln!(
+            self.out,
+            "{}#[serde(with = \"{}::message::big_array\")]",
+            indent, self.resolver.crate_path
+          )
+          .unwrap();
+        }
+      }
+      writeln!(
+        self.out,
+        "{}pub {}: {},",
+        indent,
+        escape_keywords(field_name),
+        rust_type
+      )
+      .unwrap();
+    }
+    Ok(())
+  }
+
+  fn constants_and_default(
+    &mut self,
+    name: &str,
+    section: &Section,
+    methods: Option<&str>,
+  ) -> Result<(), Error> {
+    if !section.constants.is_empty() || methods.is_some() {
+      writeln!(self.out, "  impl {} {{", name).unwrap();
+      for (type_name, const_name, value, doc) in &section.constants {
+        let rust_type = match (&type_name.base, &type_name.array_spec) {
+          (_, Some(_)) | (BaseTypeName::ComplexType { .. }, _) => {
+            return Err(self.invalid(format!(
+              "Constant {} must have a primitive type",
+              const_name
+            )))
+          }
+          (base, None) if base.is_string() => "&'static str".to_string(),
+          (base, None) => self
+            .resolver
+            .base_type(self.package, base)
+            .map_err(|m| self.invalid(m))?,
+        };
+        let rust_value = match value {
+          Value::String(s) => format!("{:?}", String::from_utf8_lossy(s)),
+          other => self.scalar_literal(&type_name.base, other)?,
+        };
+        doc_comment(self.out, "    ", doc);
+        writeln!(
+          self.out,
+          "    pub const {}: {} = {};",
+          const_name, rust_type, rust_value
+        )
+        .unwrap();
+      }
+      if let Some(methods) = methods {
+        self.out.push_str(methods);
+      }
+      writeln!(self.out, "  }}").unwrap();
+    }
+
+    writeln!(self.out, "  impl ::core::default::Default for {} {{", name).unwrap();
+    writeln!(self.out, "    fn default() -> Self {{").unwrap();
+    writeln!(self.out, "      {} {{", name).unwrap();
+    if section.fields.is_empty() {
+      writeln!(self.out, "        structure_needs_at_least_one_member: 0,").unwrap();
+    }
+    for (type_name, field_name, default, _doc) in &section.fields {
+      let value = match default {
+        Some(value) => self.default_value(type_name, value)?,
+        None => zero_value(type_name),
+      };
+      writeln!(
+        self.out,
+        "        {}: {},",
+        escape_keywords(field_name),
+        value
+      )
+      .unwrap();
+    }
+    writeln!(self.out, "      }}\n    }}\n  }}").unwrap();
+    Ok(())
+  }
+
+  fn default_value(&self, type_name: &TypeName, value: &Value) -> Result<String, Error> {
+    let crate_path = self.resolver.crate_path;
+    match (&type_name.array_spec, value) {
+      (None, value) => self.element_value(&type_name.base, value),
+      (Some(spec), Value::Array(elements)) => {
+        let elements = elements
+          .iter()
+          .map(|e| self.element_value(&type_name.base, e))
+          .collect::<Result<Vec<String>, Error>>()?
+          .join(", ");
+        let count = match value {
+          Value::Array(v) => v.len() as u64,
+          _ => 0,
+        };
+        match spec {
+          ArraySpecifier::Static { size } if *size == count => Ok(format!("[{}]", elements)),
+          ArraySpecifier::Unbounded => Ok(format!("::std::vec![{}]", elements)),
+          ArraySpecifier::Bounded { bound } if count <= *bound => Ok(format!(
+            "<{}::message::BoundedVec<_, {}> as ::core::convert::TryFrom<_>>::try_from(::std::vec![{}])\
+             .unwrap()",
+            crate_path, bound, elements
+          )),
+          _ => Err(self.invalid(format!(
+            "Default value has {} elements, but the type is {:?}",
+            count, spec
+          ))),
+        }
+      }
+      (Some(_), _) => Err(self.invalid("Default value of an array must be an array".into())),
+    }
+  }
+
+  fn element_value(&self, base: &BaseTypeName, value: &Value) -> Result<String, Error> {
+    let crate_path = self.resolver.crate_path;
+    match (base, value) {
+      (BaseTypeName::Primitive { name }, Value::String(s)) if name == "string" => Ok(format!(
+        "::std::string::String::from({:?})",
+        String::from_utf8_lossy(s)
+      )),
+      (BaseTypeName::Primitive { name }, Value::String(s)) if name == "wstring" => Ok(format!(
+        "{}::WString::from({:?})",
+        crate_path,
+        String::from_utf8_lossy(s)
+      )),
+      (BaseTypeName::BoundedWString { .. }, Value::String(s)) => Ok(format!(
+        "{}::WString::from({:?})",
+        crate_path,
+        String::from_utf8_lossy(s)
+      )),
+      (BaseTypeName::BoundedString { bound }, Value::String(s)) if s.len() as u64 <= *bound => {
+        Ok(format!(
+          "<{}::message::BoundedString<{}> as ::core::convert::TryFrom<&str>>::try_from({:?})\
+           .unwrap()",
+          crate_path,
+          bound,
+          String::from_utf8_lossy(s)
+        ))
+      }
+      (BaseTypeName::ComplexType { .. }, _) => {
+        Err(self.invalid("Fields of complex types cannot have default values".into()))
+      }
+      (base, value) => self.scalar_literal(base, value),
+    }
+  }
+
+  fn scalar_literal(&self, base: &BaseTypeName, value: &Value) -> Result<String, Error> {
+    let primitive = match base {
+      BaseTypeName::Primitive { name } => name.as_str(),
+      _ => "",
+    };
+    match (primitive, value) {
+      ("bool", Value::Bool(b)) => Ok(b.to_string()),
+      ("float32", Value::Float(f)) | ("float64", Value::Float(f)) => Ok(format!("{:?}", f)),
+      ("float32", Value::Int(i)) | ("float64", Value::Int(i)) => Ok(format!("{:?}", *i as f64)),
+      ("float32", Value::Uint(u)) | ("float64", Value::Uint(u)) => Ok(format!("{:?}", *u as f64)),
+      ("bool", _) | ("float32", _) | ("float64", _) | ("string", _) | ("wstring", _) | ("", _) => {
+        Err(self.invalid(format!("Value {:?} does not fit type {:?}", value, base)))
+      }
+      // Integers. The compiler checks the range.
+      (_, Value::Int(i)) => Ok(i.to_string()),
+      (_, Value::Uint(u)) => Ok(u.to_string()),
+      _ => Err(self.invalid(format!("Value {:?} does not fit type {:?}", value, base))),
+    }
+  }
+}
+
+// Groups the lines of a section into fields and constants with their
+// comments. A comment block at the beginning, followed by an empty line,
+// documents the whole struct.
+fn section(lines: Vec<Line>) -> Section {
+  let mut section = Section {
+    doc: Vec::new(),
+    constants: Vec::new(),
+    fields: Vec::new(),
+  };
+  let mut pending: Vec<String> = Vec::new();
+  let mut seen_items = false;
+  for (item, comment) in lines {
+    let comment = comment.map(|Comment(c)| c.trim_start_matches('#').trim().to_string());
+    match (item, comment) {
+      (None, None) => {
+        if !seen_items && section.doc.is_empty() {
+          section.doc = std::mem::take(&mut pending);
+        } else {
+          pending.clear();
+        }
+      }
+      (None, Some(c)) => pending.push(c),
+      (Some(item), c) => {
+        seen_items = true;
+        let mut doc = std::mem::take(&mut pending);
+        doc.extend(c);
+        match item {
+          Item::Field {
+            type_name,
+            field_name,
+            default_value,
+          } => section
+            .fields
+            .push((type_name, field_name, default_value, doc)),
+          Item::Constant {
+            type_name,
+            const_name,
+            value,
+          } => section.constants.push((type_name, const_name, value, doc)),
+        }
+      }
+    }
+  }
+  if !seen_items && section.doc.is_empty() {
+    section.doc = pending;
+  }
+  section
+}
+
+// Comments in definition files are plain text, not Markdown. Escape what
+// rustdoc would take as links or HTML tags, e.g. units like "[m/s]", and make
+// URLs into links.
+fn doc_comment(out: &mut String, indent: &str, doc: &[String]) {
+  for line in doc {
+    if line.is_empty() {
+      writeln!(out, "{}///", indent).unwrap();
+      continue;
+    }
+    let words: Vec<String> = line
+      .split(' ')
+      .map(|word| {
+        if word.starts_with("http://") || word.starts_with("https://") {
+          format!("<{}>", word)
+        } else {
+          let mut escaped = String::with_capacity(word.len());
+          for c in word.chars() {
+            if matches!(c, '[' | ']' | '<' | '>') {
+              escaped.push('\\');
+            }
+            escaped.push(c);
+          }
+          escaped
+        }
+      })
+      .collect();
+    writeln!(out, "{}/// {}", indent, words.join(" ")).unwrap();
+  }
+}
+
+fn zero_value(type_name: &TypeName) -> String {
+  let zero = match &type_name.base {
+    BaseTypeName::Primitive { name } => match name.as_str() {
+      "bool" => Some("false"),
+      "float32" | "float64" => Some("0.0"),
+      "string" | "wstring" => None,
+      _ => Some("0"),
+    },
+    _ => None,
+  };
+  match (&type_name.array_spec, zero) {
+    (Some(ArraySpecifier::Static { size }), Some(zero)) => format!("[{}; {}]", zero, size),
+    (Some(ArraySpecifier::Static { .. }), None) => {
+      "::core::array::from_fn(|_| ::core::default::Default::default())".to_string()
+    }
+    _ => "::core::default::Default::default()".to_string(),
+  }
+}
+
+fn escape_keywords(id: &str) -> String {
+  match id {
+    // These cannot be raw identifiers.
+    "crate" | "self" | "super" | "Self" => format!("{}_", id),
+    "as" | "break" | "const" | "continue" | "else" | "enum" | "extern" | "false" | "fn" | "for"
+    | "if" | "impl" | "in" | "let" | "loop" | "match" | "mod" | "move" | "mut" | "pub" | "ref"
+    | "return" | "static" | "struct" | "trait" | "true" | "type" | "unsafe" | "use" | "where"
+    | "while" | "async" | "await" | "dyn" | "abstract" | "become" | "box" | "do" | "final"
+    | "macro" | "override" | "priv" | "typeof" | "unsized" | "virtual" | "yield" | "try" => {
+      format!("r#{}", id)
+    }
+    _ => id.to_string(),
+  }
+}
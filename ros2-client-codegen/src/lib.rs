@@ -0,0 +1,407 @@
+//! Generates [ros2-client](https://crates.io/crates/ros2-client) message,
+//! Service and Action types from ROS 2 interface definitions, i.e. `.msg`,
+//! `.srv` and `.action` files.
+//!
+//! The generated structs have the same CDR layout as those generated by
+//! rosidl, so there is no need to write message definitions by hand and keep
+//! them in sync with the `.msg` files. Constants become associated constants,
+//! default values in the definitions go into `Default` implementations, and
+//! bounded strings and sequences become
+//! [`BoundedString`](https://docs.rs/ros2-client/latest/ros2_client/message/struct.BoundedString.html)
+//! and `BoundedVec`. Services are defined with `ros2_service!`, and Actions
+//! implement `ActionTypes`.
+//!
+//! Use this as a build dependency. The calling crate must depend on
+//! ros2-client and serde. In `build.rs`:
+//!
+//! ```no_run
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! ros2_client_codegen::Builder::new()
+//!   .package_dir("interfaces/my_robot_msgs") // has msg/, srv/ or action/
+//!   .emit_rerun_if_changed(true)
+//!   .write_to_file(format!("{}/interfaces.rs", out_dir))
+//!   .unwrap();
+//! ```
+//!
+//! and in the crate:
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/interfaces.rs"));
+//!
+//! use my_robot_msgs::{msg::BatteryState, srv::SetSpeed};
+//! ```
+//!
+//! Each package becomes a module, with submodules `msg`, `srv` and `action`.
+//! Types from packages that are not generated must be declared with
+//! [`Builder::extern_package`]. Package `builtin_interfaces` is already
+//! declared, as ros2-client defines `Time` and `Duration`.
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs, io,
+  path::{Path, PathBuf},
+};
+
+mod generate;
+pub mod parser;
+mod stringparser;
+
+pub use generate::InterfaceKind;
+use generate::{Interface, Resolver};
+pub use parser::ParseError;
+
+/// What went wrong in generating code
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+  #[error("{path}: {source}")]
+  Io {
+    path: PathBuf,
+    #[source]
+    source: io::Error,
+  },
+
+  /// `file` is e.g. `"my_msgs/msg/Status"`.
+  #[error("{file}: {source}")]
+  Parse {
+    file: String,
+    #[source]
+    source: ParseError,
+  },
+
+  /// The definition parsed, but cannot be translated, e.g. because of an
+  /// unknown package or a default value that does not fit its type.
+  #[error("{file}: {message}")]
+  Invalid { file: String, message: String },
+}
+
+enum Source {
+  Dir(PathBuf),
+  Text(InterfaceKind, String, String),
+}
+
+/// Collects packages of interface definitions and generates code for them.
+pub struct Builder {
+  packages: BTreeMap<String, Vec<Source>>,
+  extern_packages: BTreeMap<String, String>,
+  extern_types: BTreeMap<(String, String), String>,
+  crate_path: String,
+  emit_rerun_if_changed: bool,
+}
+
+impl Default for Builder {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Builder {
+  pub fn new() -> Self {
+    Builder {
+      packages: BTreeMap::new(),
+      extern_packages: BTreeMap::new(),
+      extern_types: BTreeMap::new(),
+      crate_path: "::ros2_client".to_string(),
+      emit_rerun_if_changed: false,
+    }
+  }
+
+  /// Adds a package from its source directory, which contains subdirectories
+  /// `msg`, `srv` or `action`. The package name is the name of the directory.
+  pub fn package_dir<P: AsRef<Path>>(self, dir: P) -> Self {
+    let dir = dir.as_ref();
+    let name = dir
+      .file_name()
+      .map(|n| n.to_string_lossy().into_owned())
+      .unwrap_or_default();
+    self.package(&name, dir)
+  }
+
+  /// Like [`Self::package_dir`], but the package name is given, e.g. when the
+  /// directory is an install `share/<package>` directory.
+  pub fn package<P: AsRef<Path>>(mut self, name: &str, dir: P) -> Self {
+    self
+      .packages
+      .entry(name.to_string())
+      .or_default()
+      .push(Source::Dir(dir.as_ref().to_path_buf()));
+    self
+  }
+
+  /// Adds one interface definition from a string. `type_name` is e.g.
+  /// `"Status"`.
+  pub fn interface(
+    mut self,
+    package: &str,
+    kind: InterfaceKind,
+    type_name: &str,
+    text: &str,
+  ) -> Self {
+    self
+      .packages
+      .entry(package.to_string())
+      .or_default()
+      .push(Source::Text(kind, type_name.to_string(), text.to_string()));
+    self
+  }
+
+  /// Types of package `name` are referred to as `rust_path::TypeName`, e.g.
+  /// `extern_package("geometry_msgs", "::my_msgs_crate::geometry_msgs::msg")`.
+  pub fn extern_package(mut self, name: &str, rust_path: &str) -> Self {
+    self
+      .extern_packages
+      .insert(name.to_string(), rust_path.to_string());
+    self
+  }
+
+  /// Message type `ros_type`, e.g. `"std_msgs/Header"`, is referred to as
+  /// `rust_path`, e.g. `"::ros2_client::std_msgs::Header"`. It is not
+  /// generated, even if its package is.
+  ///
+  /// This is for using hand-written types, which may have more methods and
+  /// trait implementations than generated ones.
+  pub fn extern_type(mut self, ros_type: &str, rust_path: &str) -> Self {
+    let (package, type_name) = ros_type
+      .split_once('/')
+      .map(|(p, t)| (p, t.trim_start_matches("msg/")))
+      .unwrap_or(("", ros_type));
+    self.extern_types.insert(
+      (package.to_string(), type_name.to_string()),
+      rust_path.to_string(),
+    );
+    self
+  }
+
+  /// Path of the ros2-client crate in generated code. The default is
+  /// `::ros2_client`.
+  pub fn crate_path(mut self, path: &str) -> Self {
+    self.crate_path = path.to_string();
+    self
+  }
+
+  /// Prints `cargo:rerun-if-changed` for the definition directories and
+  /// files, so that a build script reruns when they change.
+  pub fn emit_rerun_if_changed(mut self, emit: bool) -> Self {
+    self.emit_rerun_if_changed = emit;
+    self
+  }
+
+  /// Generates the code, one module per package.
+  pub fn generate(&self) -> Result<String, Error> {
+    let mut out = String::from(HEADER);
+    for (package, contents) in self.generate_packages()? {
+      out.push('\n');
+      out.push_str(PACKAGE_ALLOW);
+      out.push_str(&format!("pub mod {} {{\n", package));
+      out.push_str(&indent(&contents));
+      out.push_str("}\n");
+    }
+    Ok(out)
+  }
+
+  /// Writes the generated code into a file, e.g. to be `include!`d.
+  pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+    let code = self.generate()?;
+    write_file(path.as_ref(), &code)
+  }
+
+  /// Writes each package into its own file `<package>.rs` in `dir`, and
+  /// `mod.rs` that declares them.
+  pub fn write_to_dir<P: AsRef<Path>>(&self, dir: P) -> Result<(), Error> {
+    let dir = dir.as_ref();
+    let mut mod_rs = String::from(HEADER);
+    for (package, contents) in self.generate_packages()? {
+      mod_rs.push_str(&format!("pub mod {};\n", package));
+      let code = format!(
+        "{}\n{}\n{}",
+        HEADER,
+        PACKAGE_ALLOW.replace("#[", "#!["),
+        contents
+      );
+      write_file(&dir.join(format!("{}.rs", package)), &code)?;
+    }
+    write_file(&dir.join("mod.rs"), &mod_rs)
+  }
+
+  fn generate_packages(&self) -> Result<Vec<(String, String)>, Error> {
+    let mut extern_packages = self.extern_packages.clone();
+    extern_packages
+      .entry("builtin_interfaces".to_string())
+      .or_insert_with(|| format!("{}::builtin_interfaces", self.crate_path));
+    let generated: BTreeSet<String> = self.packages.keys().cloned().collect();
+    let resolver = Resolver {
+      crate_path: &self.crate_path,
+      generated: &generated,
+      extern_packages: &extern_packages,
+      extern_types: &self.extern_types,
+    };
+
+    let mut result = Vec::new();
+    for (package, sources) in &self.packages {
+      let mut interfaces = Vec::new();
+      for source in sources {
+        match source {
+          Source::Dir(dir) => interfaces.extend(self.read_dir(dir)?),
+          Source::Text(kind, name, text) => interfaces.push(Interface {
+            kind: *kind,
+            name: name.clone(),
+            text: text.clone(),
+          }),
+        }
+      }
+      if interfaces.is_empty() {
+        return Err(Error::Invalid {
+          file: package.clone(),
+          message: "No .msg, .srv or .action files".to_string(),
+        });
+      }
+      let contents = generate::package_contents(&resolver, package, &interfaces)?;
+      result.push((package.clone(), contents));
+    }
+    Ok(result)
+  }
+
+  fn read_dir(&self, dir: &Path) -> Result<Vec<Interface>, Error> {
+    let io_error = |path: &Path| {
+      let path = path.to_path_buf();
+      move |source| Error::Io { path, source }
+    };
+    let mut interfaces = Vec::new();
+    for subfolder in &["msg", "srv", "action"] {
+      let sub_dir = dir.join(subfolder);
+      if !sub_dir.is_dir() {
+        continue;
+      }
+      if self.emit_rerun_if_changed {
+        println!("cargo:rerun-if-changed={}", sub_dir.display());
+      }
+      let mut paths = fs::read_dir(&sub_dir)
+        .map_err(io_error(&sub_dir))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<PathBuf>, io::Error>>()
+        .map_err(io_error(&sub_dir))?;
+      paths.sort();
+      for path in paths {
+        let kind = path
+          .extension()
+          .and_then(|e| InterfaceKind::from_extension(&e.to_string_lossy()));
+        let name = path.file_stem().map(|s| s.to_string_lossy().into_owned());
+        if let (Some(kind), Some(name)) = (kind, name) {
+          if self.emit_rerun_if_changed {
+            println!("cargo:rerun-if-changed={}", path.display());
+          }
+          let text = fs::read_to_string(&path).map_err(io_error(&path))?;
+          interfaces.push(Interface { kind, name, text });
+        }
+      }
+    }
+    Ok(interfaces)
+  }
+}
+
+const HEADER: &str = "// Generated by ros2-client-codegen. Do not edit.\n";
+
+const PACKAGE_ALLOW: &str =
+  "#[allow(dead_code, non_snake_case, non_upper_case_globals, clippy::all)]\n";
+
+fn indent(code: &str) -> String {
+  code
+    .lines()
+    .map(|l| {
+      if l.is_empty() {
+        "\n".to_string()
+      } else {
+        format!("  {}\n", l)
+      }
+    })
+    .collect()
+}
+
+fn write_file(path: &Path, code: &str) -> Result<(), Error> {
+  fs::write(path, code).map_err(|source| Error::Io {
+    path: path.to_path_buf(),
+    source,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn generate_interfaces() {
+    let code = Builder::new()
+      .interface(
+        "my_msgs",
+        InterfaceKind::Message,
+        "Status",
+        "# Robot status\n\nuint8 OK=0\n# Name [ascii]\nstring<=8 name \"bot\"\nfloat64[36] covariance\nint32 \
+         type\nbuiltin_interfaces/Time stamp\n",
+      )
+      .interface(
+        "my_msgs",
+        InterfaceKind::Service,
+        "Reset",
+        "---\nbool success\nStatus status\n",
+      )
+      .interface(
+        "my_msgs",
+        InterfaceKind::Action,
+        "Count",
+        "int32 to\n---\nint32[] counted\n---\nint32 current\n",
+      )
+      .generate()
+      .unwrap();
+    for expected in [
+      "/// Robot status",
+      "pub const OK: u8 = 0;",
+      "/// Name \\[ascii\\]",
+      "pub name: ::ros2_client::message::BoundedString<8>,",
+      "#[serde(with = \"::ros2_client::message::big_array\")]",
+      "pub covariance: [f64; 36],",
+      "pub r#type: i32,",
+      "pub stamp: ::ros2_client::builtin_interfaces::Time,",
+      "covariance: [0.0; 36],",
+      "pub Reset in \"my_msgs\"",
+      "pub structure_needs_at_least_one_member: u8,",
+      "pub status: super::msg::Status,",
+      "type GoalType = CountGoal;",
+      "\"my_msgs::action::dds_::Count_Goal_\"",
+    ]
+    .iter()
+    {
+      assert!(code.contains(expected), "{} not in\n{}", expected, code);
+    }
+
+    let with_extern = Builder::new()
+      .interface(
+        "my_msgs",
+        InterfaceKind::Message,
+        "A",
+        "B b\nother_msgs/C c\n",
+      )
+      .interface("my_msgs", InterfaceKind::Message, "B", "int32 x\n")
+      .interface("my_msgs", InterfaceKind::Message, "String", "string data\n")
+      .extern_type("my_msgs/B", "crate::B")
+      .extern_type("other_msgs/msg/C", "crate::C")
+      .generate()
+      .unwrap();
+    assert!(with_extern.contains("pub b: crate::B,"));
+    assert!(with_extern.contains("pub c: crate::C,"));
+    assert!(!with_extern.contains("pub struct B "));
+    assert!(with_extern.contains("pub data: ::std::string::String,"));
+
+    let unknown = Builder::new()
+      .interface("my_msgs", InterfaceKind::Message, "A", "other_msgs/B b\n")
+      .generate();
+    assert!(matches!(unknown, Err(Error::Invalid { .. })));
+    let bad_default = Builder::new()
+      .interface(
+        "my_msgs",
+        InterfaceKind::Message,
+        "A",
+        "int32[2] b [1, 2, 3]\n",
+      )
+      .generate();
+    assert!(matches!(bad_default, Err(Error::Invalid { .. })));
+  }
+}
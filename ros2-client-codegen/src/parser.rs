@@ -0,0 +1,468 @@
+//! Parser of ROS 2 interface definitions, i.e. `.msg` files and the sections
+//! of `.srv` and `.action` files.
+//!
+//! The syntax is described in
+//! [Interfaces](https://docs.ros.org/en/rolling/Concepts/Basic/About-Interfaces.html).
+
+use std::{fmt, str::FromStr};
+
+use nom::{
+  branch::alt,
+  bytes::complete::{is_not, tag},
+  character::complete::{alphanumeric1, char, digit1, not_line_ending, one_of, space0, space1},
+  combinator::{eof, map, not, opt, peek, recognize, value},
+  multi::{many0, many1, separated_list0},
+  sequence::{delimited, pair, preceded, terminated, tuple},
+  IResult,
+};
+
+use super::stringparser::parse_string;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment(pub String);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+  Field {
+    type_name: TypeName,
+    field_name: String,
+    default_value: Option<Value>,
+  },
+  Constant {
+    type_name: TypeName,
+    const_name: String,
+    value: Value,
+  },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BaseTypeName {
+  Primitive {
+    name: String,
+  },
+  BoundedString {
+    bound: u64,
+  },
+  BoundedWString {
+    bound: u64,
+  },
+  ComplexType {
+    package_name: Option<String>,
+    type_name: String,
+  },
+}
+
+impl BaseTypeName {
+  pub fn is_string(&self) -> bool {
+    match self {
+      BaseTypeName::Primitive { name } => name == "string" || name == "wstring",
+      BaseTypeName::BoundedString { .. } | BaseTypeName::BoundedWString { .. } => true,
+      BaseTypeName::ComplexType { .. } => false,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArraySpecifier {
+  Static { size: u64 },
+  Unbounded,
+  Bounded { bound: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeName {
+  pub base: BaseTypeName,
+  pub array_spec: Option<ArraySpecifier>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+  Bool(bool),
+  Float(f64), // Also can store a f32
+  Int(i64),
+  Uint(u64),
+  String(Vec<u8>),   // ROS does not do Unicode
+  Array(Vec<Value>), // default value of an array field
+}
+
+/// A line of an interface definition: an optional item, followed by an
+/// optional comment. Both are `None` for an empty line.
+pub type Line = (Option<Item>, Option<Comment>);
+
+/// A line of an interface definition could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+  /// Line number, starting from 1
+  pub line: usize,
+  pub text: String,
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "line {}: cannot parse {:?}", self.line, self.text)
+  }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a `.msg` file, or one section of a `.srv` or `.action` file.
+pub fn msg_spec(i: &str) -> Result<Vec<Line>, ParseError> {
+  i.lines()
+    .enumerate()
+    .map(|(n, text)| {
+      line(text.trim_end_matches('\r'))
+        .map(|(_rest, line)| line)
+        .map_err(|_| ParseError {
+          line: n + 1,
+          text: text.to_string(),
+        })
+    })
+    .collect()
+}
+
+/// Splits a `.srv` or `.action` file into its sections, which are separated
+/// by `---` lines. A `.msg` file is just one section.
+pub fn split_sections(i: &str) -> Vec<String> {
+  let mut sections = vec![String::new()];
+  for text in i.lines() {
+    if text.trim() == "---" {
+      sections.push(String::new());
+    } else if let Some(section) = sections.last_mut() {
+      section.push_str(text);
+      section.push('\n');
+    }
+  }
+  sections
+}
+
+fn line(i: &str) -> IResult<&str, Line> {
+  terminated(
+    pair(alt((item, just_space)), opt(comment)),
+    pair(space0, eof),
+  )(i)
+}
+
+fn item(i: &str) -> IResult<&str, Option<Item>> {
+  map(delimited(space0, alt((constant, field)), space0), Some)(i)
+}
+
+fn just_space(i: &str) -> IResult<&str, Option<Item>> {
+  value(None, space0)(i)
+}
+
+fn field(i: &str) -> IResult<&str, Item> {
+  let (i, type_name) = type_spec(i)?;
+  let (i, _) = space1(i)?;
+  let (i, field_name) = identifier(i)?;
+  let (i, default_value) = opt(preceded(space1, |i| default_spec(&type_name, i)))(i)?;
+  Ok((
+    i,
+    Item::Field {
+      type_name,
+      field_name,
+      default_value,
+    },
+  ))
+}
+
+fn constant(i: &str) -> IResult<&str, Item> {
+  let (i, type_name) = type_spec(i)?;
+  let (i, _) = space1(i)?;
+  let (i, const_name) = identifier(i)?;
+  let (i, _) = space0(i)?;
+  let (i, _) = tag("=")(i)?;
+  let (i, _) = space0(i)?;
+  let (i, value) = if type_name.base.is_string() {
+    // String constants may also be unquoted, up to the end of line.
+    alt((
+      string_value,
+      map(is_not("#"), |s: &str| Value::String(s.trim_end().into())),
+    ))(i)?
+  } else {
+    value_spec(i)?
+  };
+  Ok((
+    i,
+    Item::Constant {
+      type_name,
+      const_name,
+      value,
+    },
+  ))
+}
+
+fn default_spec<'a>(type_name: &TypeName, i: &'a str) -> IResult<&'a str, Value> {
+  let element = |i| {
+    if type_name.base.is_string() {
+      string_value(i)
+    } else {
+      value_spec(i)
+    }
+  };
+  match type_name.array_spec {
+    None => element(i),
+    Some(_) => map(
+      delimited(
+        pair(char('['), space0),
+        separated_list0(delimited(space0, char(','), space0), element),
+        pair(space0, char(']')),
+      ),
+      Value::Array,
+    )(i),
+  }
+}
+
+fn type_spec(i: &str) -> IResult<&str, TypeName> {
+  // components of type_spec:
+  // array spec: [10] , [<=10], or []
+  let array_specifier_inner = alt((
+    map(preceded(tag("<="), uint_value), |bound: u64| {
+      ArraySpecifier::Bounded { bound }
+    }),
+    map(uint_value, |size: u64| ArraySpecifier::Static { size }),
+    map(space0, |_| ArraySpecifier::Unbounded),
+  ));
+  let array_specifier = delimited(char('['), array_specifier_inner, char(']'));
+
+  // "string<=20" or "wstring<=20"
+  let bounded_string = alt((
+    map(preceded(tag("string<="), uint_value), |bound: u64| {
+      BaseTypeName::BoundedString { bound }
+    }),
+    map(preceded(tag("wstring<="), uint_value), |bound: u64| {
+      BaseTypeName::BoundedWString { bound }
+    }),
+  ));
+
+  // Must not be just the beginning of a complex type name, e.g. "bytes"
+  let primitive_type = map(
+    terminated(
+      alt((
+        tag("bool"),
+        tag("byte"),
+        tag("char"),
+        tag("float32"),
+        tag("float64"),
+        tag("int8"),
+        tag("int16"),
+        tag("int32"),
+        tag("int64"),
+        tag("uint8"),
+        tag("uint16"),
+        tag("uint32"),
+        tag("uint64"),
+        tag("string"),
+        tag("wstring"),
+      )),
+      not(peek(alt((alphanumeric1, tag("_"), tag("/"))))),
+    ),
+    |s: &str| BaseTypeName::Primitive {
+      name: s.to_string(),
+    },
+  );
+
+  // "package_name/typename" or "typename"
+  let complex_type = map(
+    pair(opt(terminated(identifier, tag("/"))), identifier),
+    |(package_name, type_name)| BaseTypeName::ComplexType {
+      package_name,
+      type_name,
+    },
+  );
+
+  // type spec:
+  let (i, (base, array_spec)) = pair(
+    alt((bounded_string, primitive_type, complex_type)),
+    opt(array_specifier),
+  )(i)?;
+  Ok((i, TypeName { base, array_spec }))
+}
+
+fn identifier(i: &str) -> IResult<&str, String> {
+  map(
+    recognize(many1(alt((alphanumeric1, tag("_"))))),
+    String::from,
+  )(i)
+}
+
+fn uint_value(i: &str) -> IResult<&str, u64> {
+  map(digit1, |s: &str| u64::from_str(s).expect("bad uint"))(i)
+}
+
+fn string_value(i: &str) -> IResult<&str, Value> {
+  let single_quoted = delimited(char('\''), opt(is_not("'")), char('\''));
+  alt((
+    map(parse_string, |s: String| Value::String(Vec::from(s))),
+    map(single_quoted, |s: Option<&str>| {
+      Value::String(s.unwrap_or_default().into())
+    }),
+  ))(i)
+}
+
+fn value_spec(i: &str) -> IResult<&str, Value> {
+  let bool_value = alt((
+    value(Value::Bool(false), tag("false")),
+    value(Value::Bool(true), tag("true")),
+  ));
+  let float_value = alt((
+    map(float, Value::Float),
+    map(preceded(char('-'), float), |f| Value::Float(-f)),
+  ));
+  let u_int_value = map(uint_value, Value::Uint);
+  let int_value = map(preceded(tag("-"), uint_value), |i| Value::Int(-(i as i64)));
+
+  alt((
+    bool_value,
+    float_value,
+    int_value,
+    u_int_value,
+    string_value,
+  ))(i)
+}
+
+fn comment(i: &str) -> IResult<&str, Comment> {
+  map(recognize(pair(tag("#"), not_line_ending)), |s: &str| {
+    Comment(s.to_string())
+  })(i)
+}
+
+// from "nom" cookbook
+fn float(input: &str) -> IResult<&str, f64> {
+  map(
+    alt((
+      // Case one: .42
+      recognize(tuple((
+        char('.'),
+        decimal,
+        opt(tuple((one_of("eE"), opt(one_of("+-")), decimal))),
+      ))), // Case two: 42e42 and 42.42e42
+      recognize(tuple((
+        decimal,
+        opt(preceded(char('.'), decimal)),
+        one_of("eE"),
+        opt(one_of("+-")),
+        decimal,
+      ))), // Case three: 42. and 42.42
+      recognize(tuple((decimal, char('.'), opt(decimal)))),
+    )),
+    // Failing here means that this nom parser and f64::from_str disagree on
+    // what is a valid float.
+    |f: &str| f64::from_str(f).expect("Failed to parse floating point value."),
+  )(input)
+}
+
+// from "nom" cookbook
+fn decimal(input: &str) -> IResult<&str, &str> {
+  recognize(many1(terminated(one_of("0123456789"), many0(char('_')))))(input)
+}
+
+#[test]
+fn comment_test() {
+  assert_eq!(comment("#"), Ok(("", Comment("#".to_string()))));
+  assert_eq!(comment("# "), Ok(("", Comment("# ".to_string()))));
+  assert_eq!(
+    comment("# This message:"),
+    Ok(("", Comment("# This message:".to_string())))
+  );
+}
+
+#[test]
+fn spec_test() {
+  assert_eq!(msg_spec("\n"), Ok(vec![(None, None)]));
+  assert_eq!(msg_spec(""), Ok(vec![]));
+  assert_eq!(
+    msg_spec("# \n"),
+    Ok(vec![(None, Some(Comment("# ".to_string())))])
+  );
+  assert_eq!(
+    msg_spec("int32 x\nint32 y z\n"),
+    Err(ParseError {
+      line: 2,
+      text: "int32 y z".to_string()
+    })
+  );
+}
+
+#[test]
+fn item_test() {
+  let parsed = msg_spec(
+    "uint8 RED=1 # red\n\
+     string NAME=some name # unquoted\n\
+     int32[<=3] values [1, -2, 3]\n\
+     string<=10 label \"hi\"\n\
+     float64[36] covariance\n\
+     bytes_pkg/Thing thing\r\n",
+  )
+  .unwrap();
+  let items: Vec<Item> = parsed.into_iter().filter_map(|(item, _)| item).collect();
+  let primitive = |name: &str| BaseTypeName::Primitive {
+    name: name.to_string(),
+  };
+  assert_eq!(
+    items,
+    vec![
+      Item::Constant {
+        type_name: TypeName {
+          base: primitive("uint8"),
+          array_spec: None
+        },
+        const_name: "RED".to_string(),
+        value: Value::Uint(1),
+      },
+      Item::Constant {
+        type_name: TypeName {
+          base: primitive("string"),
+          array_spec: None
+        },
+        const_name: "NAME".to_string(),
+        value: Value::String(b"some name".to_vec()),
+      },
+      Item::Field {
+        type_name: TypeName {
+          base: primitive("int32"),
+          array_spec: Some(ArraySpecifier::Bounded { bound: 3 })
+        },
+        field_name: "values".to_string(),
+        default_value: Some(Value::Array(vec![
+          Value::Uint(1),
+          Value::Int(-2),
+          Value::Uint(3)
+        ])),
+      },
+      Item::Field {
+        type_name: TypeName {
+          base: BaseTypeName::BoundedString { bound: 10 },
+          array_spec: None
+        },
+        field_name: "label".to_string(),
+        default_value: Some(Value::String(b"hi".to_vec())),
+      },
+      Item::Field {
+        type_name: TypeName {
+          base: primitive("float64"),
+          array_spec: Some(ArraySpecifier::Static { size: 36 })
+        },
+        field_name: "covariance".to_string(),
+        default_value: None,
+      },
+      Item::Field {
+        type_name: TypeName {
+          base: BaseTypeName::ComplexType {
+            package_name: Some("bytes_pkg".to_string()),
+            type_name: "Thing".to_string()
+          },
+          array_spec: None
+        },
+        field_name: "thing".to_string(),
+        default_value: None,
+      },
+    ]
+  );
+}
+
+#[test]
+fn sections_test() {
+  let sections = split_sections("int64 a\n---\nint64 sum\n");
+  assert_eq!(sections, vec!["int64 a\n", "int64 sum\n"]);
+  assert_eq!(split_sections("---\n---\n").len(), 3);
+}
@@ -0,0 +1,1041 @@
+//! CDR encoding and decoding without `std`
+//!
+//! This is plain CDR (XCDR version 1), as ROS 2 uses over DDS. The encoding is
+//! the same as produced and accepted by the `cdr-encoding` crate used by
+//! RustDDS, so data can be passed between this and a full ros2-client.
+//!
+//! [`to_payload`] and [`from_payload`] include the 4-byte encapsulation header
+//! used in DDS serialized payloads. [`to_vec`] and [`from_bytes`] handle just
+//! the CDR data.
+//!
+//! Decoding accepts also XCDR version 2 ([`Encapsulation`]), as far as it
+//! differs from version 1 for final types: 8-byte values are aligned to 4
+//! bytes, and a delimited payload starts with its length. Delimiter headers
+//! of nested appendable types and of sequences of non-primitive elements are
+//! not supported.
+
+use alloc::{
+  string::{String, ToString},
+  vec::Vec,
+};
+use core::{convert::TryInto, fmt, marker::PhantomData};
+
+use serde::{
+  de::{self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess},
+  ser, Deserialize, Serialize,
+};
+
+/// Byte order of CDR data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+  Little,
+  Big,
+}
+
+impl Endianness {
+  // Representation identifier in the encapsulation header
+  fn representation_id(self) -> [u8; 2] {
+    match self {
+      Endianness::Big => [0x00, 0x00],
+      Endianness::Little => [0x00, 0x01],
+    }
+  }
+}
+
+/// Encoding of CDR data, as told by the representation identifier in the
+/// encapsulation header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encapsulation {
+  /// Plain CDR, i.e. XCDR version 1: `CDR_BE` or `CDR_LE`
+  Cdr(Endianness),
+  /// XCDR version 2 of a final type: `CDR2_BE` or `CDR2_LE`
+  Cdr2(Endianness),
+  /// XCDR version 2 of an appendable type, which starts with a delimiter
+  /// header: `D_CDR2_BE` or `D_CDR2_LE`
+  DelimitedCdr2(Endianness),
+}
+
+impl Encapsulation {
+  /// Encapsulation for a representation identifier, or `None` if it is not
+  /// one of the supported ones.
+  pub fn from_representation_id(id: [u8; 2]) -> Option<Encapsulation> {
+    match id {
+      [0x00, 0x00] => Some(Encapsulation::Cdr(Endianness::Big)),
+      [0x00, 0x01] => Some(Encapsulation::Cdr(Endianness::Little)),
+      [0x00, 0x06] => Some(Encapsulation::Cdr2(Endianness::Big)),
+      [0x00, 0x07] => Some(Encapsulation::Cdr2(Endianness::Little)),
+      [0x00, 0x08] => Some(Encapsulation::DelimitedCdr2(Endianness::Big)),
+      [0x00, 0x09] => Some(Encapsulation::DelimitedCdr2(Endianness::Little)),
+      _ => None,
+    }
+  }
+
+  pub fn endianness(self) -> Endianness {
+    match self {
+      Encapsulation::Cdr(e) | Encapsulation::Cdr2(e) | Encapsulation::DelimitedCdr2(e) => e,
+    }
+  }
+
+  // XCDR2 aligns 8-byte values only to 4 bytes.
+  fn max_alignment(self) -> usize {
+    match self {
+      Encapsulation::Cdr(_) => usize::MAX,
+      Encapsulation::Cdr2(_) | Encapsulation::DelimitedCdr2(_) => 4,
+    }
+  }
+}
+
+/// Size of the encapsulation header at the start of a DDS payload
+pub const ENCAPSULATION_HEADER_SIZE: usize = 4;
+
+/// What went wrong in CDR encoding or decoding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CdrError {
+  /// Error reported by a Serialize or Deserialize implementation
+  Message(String),
+  /// Input ended before the value was complete.
+  Eof,
+  /// CDR sequences must have a known length.
+  SequenceLengthUnknown,
+  /// CDR cannot describe its own structure, so e.g. `deserialize_any` is not
+  /// supported.
+  NotSelfDescribing,
+  BadBoolean(u8),
+  BadChar(u32),
+  BadUtf8,
+  BadOption(u32),
+  /// Encapsulation is not plain CDR, e.g. it is parameter list CDR.
+  UnsupportedEncapsulation([u8; 2]),
+}
+
+impl fmt::Display for CdrError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CdrError::Message(msg) => write!(f, "{msg}"),
+      CdrError::Eof => write!(f, "Unexpected end of input"),
+      CdrError::SequenceLengthUnknown => write!(f, "Sequence length must be known"),
+      CdrError::NotSelfDescribing => write!(f, "CDR is not a self-describing format"),
+      CdrError::BadBoolean(b) => write!(f, "Expected 0 or 1 as Boolean, got {b}"),
+      CdrError::BadChar(c) => write!(f, "Bad Unicode code point {c:#x}"),
+      CdrError::BadUtf8 => write!(f, "String is not UTF-8"),
+      CdrError::BadOption(t) => write!(f, "Option tag must be 0 or 1, got {t}"),
+      CdrError::UnsupportedEncapsulation(id) => {
+        write!(f, "Unsupported encapsulation {:02x}{:02x}", id[0], id[1])
+      }
+    }
+  }
+}
+
+impl core::error::Error for CdrError {}
+
+impl ser::Error for CdrError {
+  fn custom<T: fmt::Display>(msg: T) -> Self {
+    CdrError::Message(msg.to_string())
+  }
+}
+
+impl de::Error for CdrError {
+  fn custom<T: fmt::Display>(msg: T) -> Self {
+    CdrError::Message(msg.to_string())
+  }
+}
+
+pub type Result<T> = core::result::Result<T, CdrError>;
+
+/// Encode `value` as CDR data.
+pub fn to_vec<T>(value: &T, endianness: Endianness) -> Result<Vec<u8>>
+where
+  T: Serialize + ?Sized,
+{
+  let mut serializer = Serializer {
+    output: Vec::new(),
+    endianness,
+  };
+  value.serialize(&mut serializer)?;
+  Ok(serializer.output)
+}
+
+/// Size of `value` encoded as CDR data, without encoding it.
+///
+/// The size does not depend on the byte order.
+pub fn serialized_size<T>(value: &T) -> Result<usize>
+where
+  T: Serialize + ?Sized,
+{
+  let mut serializer = Serializer {
+    output: SizeCounter(0),
+    endianness: Endianness::Little,
+  };
+  value.serialize(&mut serializer)?;
+  Ok(serializer.output.0)
+}
+
+/// Decode a value from CDR data.
+///
+/// Returns also the number of bytes consumed.
+pub fn from_bytes<'de, T>(input: &'de [u8], endianness: Endianness) -> Result<(T, usize)>
+where
+  T: Deserialize<'de>,
+{
+  let mut deserializer = Deserializer::new(input, Encapsulation::Cdr(endianness));
+  let value = T::deserialize(&mut deserializer)?;
+  Ok((value, deserializer.position))
+}
+
+/// Like [`from_bytes`], but decodes with a deserialization seed, e.g. for
+/// types known only at run time.
+pub fn from_bytes_seed<'de, S>(
+  input: &[u8],
+  endianness: Endianness,
+  seed: S,
+) -> Result<(S::Value, usize)>
+where
+  S: DeserializeSeed<'de>,
+{
+  from_encapsulated_bytes_seed(input, Encapsulation::Cdr(endianness), seed)
+}
+
+/// Like [`from_bytes_seed`], but `input` may be also XCDR version 2, as given
+/// by `encapsulation`.
+///
+/// A delimiter header is checked against the input length, and counted in the
+/// returned number of bytes consumed.
+pub fn from_encapsulated_bytes_seed<'de, S>(
+  input: &[u8],
+  encapsulation: Encapsulation,
+  seed: S,
+) -> Result<(S::Value, usize)>
+where
+  S: DeserializeSeed<'de>,
+{
+  let mut deserializer = Deserializer::new(input, encapsulation);
+  if let Encapsulation::DelimitedCdr2(_) = encapsulation {
+    let length = deserializer.next_u32()? as usize;
+    if length > deserializer.input.len() {
+      return Err(CdrError::Eof);
+    }
+    deserializer.input = &deserializer.input[..length];
+  }
+  let value = seed.deserialize(&mut deserializer)?;
+  Ok((value, deserializer.position))
+}
+
+/// Encode `value` as a DDS serialized payload, i.e. encapsulation header
+/// followed by CDR data.
+pub fn to_payload<T>(value: &T, endianness: Endianness) -> Result<Vec<u8>>
+where
+  T: Serialize + ?Sized,
+{
+  let mut payload = Vec::with_capacity(ENCAPSULATION_HEADER_SIZE);
+  payload.extend_from_slice(&endianness.representation_id());
+  payload.extend_from_slice(&[0, 0]); // options
+                                      // Alignment is counted from the end of the header.
+  payload.extend(to_vec(value, endianness)?);
+  Ok(payload)
+}
+
+/// Decode a value from a DDS serialized payload. The byte order and CDR
+/// version are taken from the encapsulation header.
+pub fn from_payload<'de, T>(payload: &'de [u8]) -> Result<T>
+where
+  T: Deserialize<'de>,
+{
+  if payload.len() < ENCAPSULATION_HEADER_SIZE {
+    return Err(CdrError::Eof);
+  }
+  let id = [payload[0], payload[1]];
+  let encapsulation =
+    Encapsulation::from_representation_id(id).ok_or(CdrError::UnsupportedEncapsulation(id))?;
+  from_encapsulated_bytes_seed(
+    &payload[ENCAPSULATION_HEADER_SIZE..],
+    encapsulation,
+    PhantomData,
+  )
+  .map(|(value, _)| value)
+}
+
+// ------------------------------------------------------------------------
+// Serializer
+
+// Where the Serializer writes. Counting only the size needs no buffer.
+trait Output {
+  fn len(&self) -> usize;
+  fn push(&mut self, byte: u8);
+  fn extend_from_slice(&mut self, bytes: &[u8]);
+}
+
+impl Output for Vec<u8> {
+  fn len(&self) -> usize {
+    Vec::len(self)
+  }
+  fn push(&mut self, byte: u8) {
+    Vec::push(self, byte)
+  }
+  fn extend_from_slice(&mut self, bytes: &[u8]) {
+    Vec::extend_from_slice(self, bytes)
+  }
+}
+
+struct SizeCounter(usize);
+
+impl Output for SizeCounter {
+  fn len(&self) -> usize {
+    self.0
+  }
+  fn push(&mut self, _byte: u8) {
+    self.0 += 1;
+  }
+  fn extend_from_slice(&mut self, bytes: &[u8]) {
+    self.0 += bytes.len();
+  }
+}
+
+struct Serializer<O> {
+  output: O,
+  endianness: Endianness,
+}
+
+macro_rules! serialize_number {
+  ($fn_name:ident, $t:ty) => {
+    fn $fn_name(self, v: $t) -> Result<()> {
+      let size = core::mem::size_of::<$t>();
+      self.pad(size);
+      match self.endianness {
+        Endianness::Little => self.output.extend_from_slice(&v.to_le_bytes()),
+        Endianness::Big => self.output.extend_from_slice(&v.to_be_bytes()),
+      }
+      Ok(())
+    }
+  };
+}
+
+impl<O: Output> Serializer<O> {
+  fn pad(&mut self, alignment: usize) {
+    while !self.output.len().is_multiple_of(alignment) {
+      self.output.push(0);
+    }
+  }
+}
+
+impl<O: Output> ser::Serializer for &mut Serializer<O> {
+  type Ok = ();
+  type Error = CdrError;
+
+  type SerializeSeq = Self;
+  type SerializeTuple = Self;
+  type SerializeTupleStruct = Self;
+  type SerializeTupleVariant = Self;
+  type SerializeMap = Self;
+  type SerializeStruct = Self;
+  type SerializeStructVariant = Self;
+
+  fn serialize_bool(self, v: bool) -> Result<()> {
+    self.output.push(v as u8);
+    Ok(())
+  }
+
+  fn serialize_u8(self, v: u8) -> Result<()> {
+    self.output.push(v);
+    Ok(())
+  }
+
+  fn serialize_i8(self, v: i8) -> Result<()> {
+    self.output.push(v as u8);
+    Ok(())
+  }
+
+  serialize_number!(serialize_u16, u16);
+  serialize_number!(serialize_u32, u32);
+  serialize_number!(serialize_u64, u64);
+  serialize_number!(serialize_u128, u128);
+  serialize_number!(serialize_i16, i16);
+  serialize_number!(serialize_i32, i32);
+  serialize_number!(serialize_i64, i64);
+  serialize_number!(serialize_i128, i128);
+  serialize_number!(serialize_f32, f32);
+  serialize_number!(serialize_f64, f64);
+
+  fn serialize_char(self, v: char) -> Result<()> {
+    self.serialize_u32(v as u32)
+  }
+
+  fn serialize_str(self, v: &str) -> Result<()> {
+    self.serialize_u32(v.len() as u32 + 1)?; // +1 for terminator
+    self.output.extend_from_slice(v.as_bytes());
+    self.output.push(0);
+    Ok(())
+  }
+
+  // Encoded as sequence<octet>, so that it decodes back.
+  fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+    self.serialize_u32(v.len() as u32)?;
+    self.output.extend_from_slice(v);
+    Ok(())
+  }
+
+  fn serialize_none(self) -> Result<()> {
+    self.serialize_u32(0)
+  }
+
+  fn serialize_some<T>(self, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    self.serialize_u32(1)?;
+    value.serialize(self)
+  }
+
+  fn serialize_unit(self) -> Result<()> {
+    Ok(())
+  }
+
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+    Ok(())
+  }
+
+  fn serialize_unit_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+  ) -> Result<()> {
+    self.serialize_u32(variant_index)
+  }
+
+  fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_variant<T>(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    value: &T,
+  ) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    self.serialize_u32(variant_index)?;
+    value.serialize(self)
+  }
+
+  fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+    let len = len.ok_or(CdrError::SequenceLengthUnknown)?;
+    self.serialize_u32(len as u32)?;
+    Ok(self)
+  }
+
+  fn serialize_tuple(self, _len: usize) -> Result<Self> {
+    Ok(self)
+  }
+
+  fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+    Ok(self)
+  }
+
+  fn serialize_tuple_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    _len: usize,
+  ) -> Result<Self> {
+    self.serialize_u32(variant_index)?;
+    Ok(self)
+  }
+
+  fn serialize_map(self, len: Option<usize>) -> Result<Self> {
+    self.serialize_seq(len)
+  }
+
+  fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+    Ok(self)
+  }
+
+  fn serialize_struct_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    _len: usize,
+  ) -> Result<Self> {
+    self.serialize_u32(variant_index)?;
+    Ok(self)
+  }
+
+  fn is_human_readable(&self) -> bool {
+    false
+  }
+}
+
+// All compound types are just their elements one after another.
+macro_rules! serialize_compound {
+  ($trait:ident, $fn_name:ident) => {
+    impl<O: Output> ser::$trait for &mut Serializer<O> {
+      type Ok = ();
+      type Error = CdrError;
+
+      fn $fn_name<T>(&mut self, value: &T) -> Result<()>
+      where
+        T: ?Sized + Serialize,
+      {
+        value.serialize(&mut **self)
+      }
+
+      fn end(self) -> Result<()> {
+        Ok(())
+      }
+    }
+  };
+}
+
+serialize_compound!(SerializeSeq, serialize_element);
+serialize_compound!(SerializeTuple, serialize_element);
+serialize_compound!(SerializeTupleStruct, serialize_field);
+serialize_compound!(SerializeTupleVariant, serialize_field);
+
+impl<O: Output> ser::SerializeMap for &mut Serializer<O> {
+  type Ok = ();
+  type Error = CdrError;
+
+  fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    key.serialize(&mut **self)
+  }
+
+  fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    value.serialize(&mut **self)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+impl<O: Output> ser::SerializeStruct for &mut Serializer<O> {
+  type Ok = ();
+  type Error = CdrError;
+
+  fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    value.serialize(&mut **self)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+impl<O: Output> ser::SerializeStructVariant for &mut Serializer<O> {
+  type Ok = ();
+  type Error = CdrError;
+
+  fn serialize_field<T>(&mut self, _key: &'static str, value: &T) -> Result<()>
+  where
+    T: ?Sized + Serialize,
+  {
+    value.serialize(&mut **self)
+  }
+
+  fn end(self) -> Result<()> {
+    Ok(())
+  }
+}
+
+// ------------------------------------------------------------------------
+// Deserializer
+
+// The input lifetime is independent of 'de, so that decoders need not borrow
+// from the input. Strings are therefore not borrowed.
+struct Deserializer<'i> {
+  input: &'i [u8],
+  position: usize, // counted from start of CDR data, for alignment
+  endianness: Endianness,
+  max_alignment: usize,
+}
+
+macro_rules! deserialize_number {
+  ($fn_name:ident, $visit:ident, $t:ty) => {
+    fn $fn_name<V>(self, visitor: V) -> Result<V::Value>
+    where
+      V: de::Visitor<'de>,
+    {
+      const SIZE: usize = core::mem::size_of::<$t>();
+      self.align(SIZE)?;
+      let bytes: [u8; SIZE] = self.next_bytes(SIZE)?.try_into().unwrap();
+      visitor.$visit(match self.endianness {
+        Endianness::Little => <$t>::from_le_bytes(bytes),
+        Endianness::Big => <$t>::from_be_bytes(bytes),
+      })
+    }
+  };
+}
+
+impl<'i> Deserializer<'i> {
+  fn new(input: &'i [u8], encapsulation: Encapsulation) -> Self {
+    Deserializer {
+      input,
+      position: 0,
+      endianness: encapsulation.endianness(),
+      max_alignment: encapsulation.max_alignment(),
+    }
+  }
+
+  fn next_bytes(&mut self, count: usize) -> Result<&'i [u8]> {
+    if count <= self.input.len() {
+      let (head, tail) = self.input.split_at(count);
+      self.input = tail;
+      self.position += count;
+      Ok(head)
+    } else {
+      Err(CdrError::Eof)
+    }
+  }
+
+  fn align(&mut self, alignment: usize) -> Result<()> {
+    let alignment = alignment.min(self.max_alignment);
+    let padding = (alignment - self.position % alignment) % alignment;
+    self.next_bytes(padding).map(|_| ())
+  }
+
+  fn next_u32(&mut self) -> Result<u32> {
+    self.align(4)?;
+    let bytes: [u8; 4] = self.next_bytes(4)?.try_into().unwrap();
+    Ok(match self.endianness {
+      Endianness::Little => u32::from_le_bytes(bytes),
+      Endianness::Big => u32::from_be_bytes(bytes),
+    })
+  }
+}
+
+impl<'de, 'i> de::Deserializer<'de> for &mut Deserializer<'i> {
+  type Error = CdrError;
+
+  fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    Err(CdrError::NotSelfDescribing)
+  }
+
+  fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    match self.next_bytes(1)?[0] {
+      0 => visitor.visit_bool(false),
+      1 => visitor.visit_bool(true),
+      x => Err(CdrError::BadBoolean(x)),
+    }
+  }
+
+  fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    visitor.visit_u8(self.next_bytes(1)?[0])
+  }
+
+  fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    visitor.visit_i8(self.next_bytes(1)?[0] as i8)
+  }
+
+  deserialize_number!(deserialize_u16, visit_u16, u16);
+  deserialize_number!(deserialize_u32, visit_u32, u32);
+  deserialize_number!(deserialize_u64, visit_u64, u64);
+  deserialize_number!(deserialize_u128, visit_u128, u128);
+  deserialize_number!(deserialize_i16, visit_i16, i16);
+  deserialize_number!(deserialize_i32, visit_i32, i32);
+  deserialize_number!(deserialize_i64, visit_i64, i64);
+  deserialize_number!(deserialize_i128, visit_i128, i128);
+  deserialize_number!(deserialize_f32, visit_f32, f32);
+  deserialize_number!(deserialize_f64, visit_f64, f64);
+
+  fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    let code_point = self.next_u32()?;
+    char::from_u32(code_point)
+      .ok_or(CdrError::BadChar(code_point))
+      .and_then(|c| visitor.visit_char(c))
+  }
+
+  fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    let len = self.next_u32()? as usize; // includes terminator
+    let bytes = self.next_bytes(len)?;
+    let without_terminator = bytes.split_last().map(|(_, s)| s).unwrap_or(bytes);
+    core::str::from_utf8(without_terminator)
+      .map_err(|_| CdrError::BadUtf8)
+      .and_then(|s| visitor.visit_str(s))
+  }
+
+  fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    self.deserialize_str(visitor)
+  }
+
+  fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    self.deserialize_seq(visitor)
+  }
+
+  fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    self.deserialize_seq(visitor)
+  }
+
+  fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    match self.next_u32()? {
+      0 => visitor.visit_none(),
+      1 => visitor.visit_some(self),
+      other => Err(CdrError::BadOption(other)),
+    }
+  }
+
+  fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    visitor.visit_unit()
+  }
+
+  fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    visitor.visit_newtype_struct(self)
+  }
+
+  fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    let count = self.next_u32()? as usize;
+    visitor.visit_seq(Elements { de: self, count })
+  }
+
+  fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    visitor.visit_seq(Elements {
+      de: self,
+      count: len,
+    })
+  }
+
+  fn deserialize_tuple_struct<V>(
+    self,
+    _name: &'static str,
+    len: usize,
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    self.deserialize_tuple(len, visitor)
+  }
+
+  fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    let count = self.next_u32()? as usize;
+    visitor.visit_map(Elements { de: self, count })
+  }
+
+  fn deserialize_struct<V>(
+    self,
+    _name: &'static str,
+    fields: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    self.deserialize_tuple(fields.len(), visitor)
+  }
+
+  fn deserialize_enum<V>(
+    self,
+    _name: &'static str,
+    _variants: &'static [&'static str],
+    visitor: V,
+  ) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    visitor.visit_enum(self)
+  }
+
+  fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    self.deserialize_u32(visitor)
+  }
+
+  fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    Err(CdrError::NotSelfDescribing)
+  }
+
+  fn is_human_readable(&self) -> bool {
+    false
+  }
+}
+
+// Access to a known number of consecutive elements
+struct Elements<'a, 'i> {
+  de: &'a mut Deserializer<'i>,
+  count: usize,
+}
+
+impl<'a, 'de, 'i> SeqAccess<'de> for Elements<'a, 'i> {
+  type Error = CdrError;
+
+  fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    if self.count == 0 {
+      Ok(None)
+    } else {
+      self.count -= 1;
+      seed.deserialize(&mut *self.de).map(Some)
+    }
+  }
+
+  fn size_hint(&self) -> Option<usize> {
+    Some(self.count)
+  }
+}
+
+impl<'a, 'de, 'i> MapAccess<'de> for Elements<'a, 'i> {
+  type Error = CdrError;
+
+  fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+  where
+    K: DeserializeSeed<'de>,
+  {
+    self.next_element_seed(seed)
+  }
+
+  fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    seed.deserialize(&mut *self.de)
+  }
+
+  fn size_hint(&self) -> Option<usize> {
+    Some(self.count)
+  }
+}
+
+impl<'de, 'i> EnumAccess<'de> for &mut Deserializer<'i> {
+  type Error = CdrError;
+  type Variant = Self;
+
+  fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+  where
+    V: DeserializeSeed<'de>,
+  {
+    let variant_index = self.next_u32()?;
+    let value = seed.deserialize(IntoDeserializer::<CdrError>::into_deserializer(
+      variant_index,
+    ))?;
+    Ok((value, self))
+  }
+}
+
+impl<'de, 'i> VariantAccess<'de> for &mut Deserializer<'i> {
+  type Error = CdrError;
+
+  fn unit_variant(self) -> Result<()> {
+    Ok(())
+  }
+
+  fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+  where
+    T: DeserializeSeed<'de>,
+  {
+    seed.deserialize(self)
+  }
+
+  fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    de::Deserializer::deserialize_tuple(self, len, visitor)
+  }
+
+  fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+  where
+    V: de::Visitor<'de>,
+  {
+    de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use alloc::{string::String, vec, vec::Vec};
+
+  use serde::{Deserialize, Serialize};
+
+  use super::*;
+
+  #[derive(Debug, PartialEq, Serialize, Deserialize)]
+  enum Mode {
+    Off,
+    Level(u16),
+  }
+
+  #[derive(Debug, PartialEq, Serialize, Deserialize)]
+  struct Sample {
+    flag: bool,
+    id: u8,
+    count: i32,
+    stamp: u64,
+    ratio: f32,
+    name: String,
+    values: Vec<i16>,
+    fixed: [u16; 3],
+    mode: Mode,
+    maybe: Option<f64>,
+  }
+
+  fn sample() -> Sample {
+    Sample {
+      flag: true,
+      id: 7,
+      count: -5000,
+      stamp: 1234567890123,
+      ratio: 0.5,
+      name: String::from("hello"),
+      values: vec![1, -2, 3],
+      fixed: [4, 5, 6],
+      mode: Mode::Level(3),
+      maybe: Some(2.5),
+    }
+  }
+
+  #[test]
+  fn encoding_layout() {
+    let bytes = to_vec(&(1u8, 2u32, 3u16, 4u64), Endianness::Little).unwrap();
+    assert_eq!(
+      bytes,
+      vec![1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0]
+    );
+    let bytes = to_vec("ab", Endianness::Big).unwrap();
+    assert_eq!(bytes, vec![0, 0, 0, 3, b'a', b'b', 0]);
+  }
+
+  #[test]
+  fn round_trip() {
+    for endianness in [Endianness::Little, Endianness::Big] {
+      let bytes = to_vec(&sample(), endianness).unwrap();
+      let (decoded, consumed) = from_bytes::<Sample>(&bytes, endianness).unwrap();
+      assert_eq!(decoded, sample());
+      assert_eq!(consumed, bytes.len());
+    }
+  }
+
+  #[test]
+  fn size_without_encoding() {
+    let bytes = to_vec(&sample(), Endianness::Little).unwrap();
+    assert_eq!(serialized_size(&sample()).unwrap(), bytes.len());
+  }
+
+  #[test]
+  fn payload() {
+    let payload = to_payload(&sample(), Endianness::Big).unwrap();
+    assert_eq!(payload[..4], [0, 0, 0, 0]);
+    assert_eq!(from_payload::<Sample>(&payload).unwrap(), sample());
+    assert_eq!(
+      from_payload::<Sample>(&[0, 2, 0, 0]),
+      Err(CdrError::UnsupportedEncapsulation([0, 2]))
+    );
+    assert_eq!(from_payload::<Sample>(&payload[..20]), Err(CdrError::Eof));
+  }
+
+  #[test]
+  fn xcdr2() {
+    // 8-byte values are aligned to 4
+    let data = [1, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 3, 0];
+    let (decoded, consumed) = from_encapsulated_bytes_seed(
+      &data,
+      Encapsulation::Cdr2(Endianness::Little),
+      PhantomData::<(u8, u64, u16)>,
+    )
+    .unwrap();
+    assert_eq!(decoded, (1, 2, 3));
+    assert_eq!(consumed, data.len());
+
+    // Delimiter header is also the first 4 bytes for alignment.
+    let mut payload = vec![0, 0x08, 0, 0, 0, 0, 0, 14];
+    payload.extend([0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 99]);
+    assert_eq!(
+      Encapsulation::from_representation_id([payload[0], payload[1]]),
+      Some(Encapsulation::DelimitedCdr2(Endianness::Big))
+    );
+    assert_eq!(from_payload::<(u16, u64, u8)>(&payload).unwrap(), (3, 2, 1));
+
+    // Delimiter header larger than the data
+    payload[7] = 15;
+    assert_eq!(from_payload::<(u16, u64, u8)>(&payload), Err(CdrError::Eof));
+  }
+
+  #[test]
+  fn same_as_cdr_encoding() {
+    use byteorder::{BigEndian, LittleEndian};
+
+    let ours = to_vec(&sample(), Endianness::Little).unwrap();
+    assert_eq!(
+      ours,
+      cdr_encoding::to_vec::<_, LittleEndian>(&sample()).unwrap()
+    );
+    let ours = to_vec(&sample(), Endianness::Big).unwrap();
+    assert_eq!(
+      ours,
+      cdr_encoding::to_vec::<_, BigEndian>(&sample()).unwrap()
+    );
+  }
+}
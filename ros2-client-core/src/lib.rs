@@ -0,0 +1,24 @@
+//! Core of [ros2-client](https://crates.io/crates/ros2-client) that builds
+//! without `std`
+//!
+//! This contains the [`Message`](message::Message) trait, ROS 2 names, the
+//! [`Gid`](gid::Gid) type, and [CDR](cdr) encoding, so that message types and
+//! their serialization can be shared between e.g. a microcontroller and an
+//! agent built on ros2-client. Only `alloc` is required.
+//!
+//! ros2-client re-exports all of these, so there is no need to depend on this
+//! crate directly when using ros2-client.
+//!
+//! Without the `rustdds` feature, this crate also compiles to
+//! `wasm32-unknown-unknown`, e.g. for browser dashboards. Feature `rosbridge`
+//! adds the [rosbridge](rosbridge) protocol messages for such clients.
+#![no_std]
+
+extern crate alloc;
+
+pub mod cdr;
+pub mod gid;
+pub mod message;
+pub mod names;
+#[cfg(feature = "rosbridge")]
+pub mod rosbridge;
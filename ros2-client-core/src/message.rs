@@ -0,0 +1,376 @@
+//! Defines [`Message`] trait, which defines data that is to be sent over
+//! Topics.
+
+use alloc::{string::String, vec::Vec};
+use core::{convert::TryFrom, fmt};
+
+use serde::{
+  de::{self, DeserializeOwned, Deserializer, SeqAccess, Visitor},
+  ser::{SerializeTuple, Serializer},
+  Deserialize, Serialize,
+};
+
+use crate::cdr;
+
+/// Trait to ensure Messages can be (de)serialized
+pub trait Message: Serialize + DeserializeOwned {
+  /// Size of this message as CDR data, in bytes, e.g. for preallocating
+  /// buffers or budgeting storage before serializing.
+  ///
+  /// The default computes the exact size by a serialization pass that stores
+  /// nothing. Types that know their size cheaply may override this.
+  fn serialized_size_hint(&self) -> usize {
+    // Only unrepresentable values fail to serialize, and they cannot be sent.
+    cdr::serialized_size(self).unwrap_or(0)
+  }
+}
+
+impl Message for () {}
+impl Message for String {}
+
+impl Message for i8 {}
+impl Message for i16 {}
+impl Message for i32 {}
+impl Message for i64 {}
+
+impl Message for u8 {}
+impl Message for u16 {}
+impl Message for u32 {}
+impl Message for u64 {}
+
+impl<T: Message> Message for Vec<T> {}
+
+/// A message that is already serialized, as raw CDR bytes without the
+/// encapsulation header.
+///
+/// This is written to the wire as-is. When received, it takes all of the
+/// remaining payload, so it can only be the whole message.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SerializedMessage(pub Vec<u8>);
+
+impl Message for SerializedMessage {
+  fn serialized_size_hint(&self) -> usize {
+    self.0.len()
+  }
+}
+
+impl Serialize for SerializedMessage {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    // A tuple has no length prefix in CDR, so the bytes go out verbatim.
+    let mut tuple = serializer.serialize_tuple(self.0.len())?;
+    for b in &self.0 {
+      tuple.serialize_element(b)?;
+    }
+    tuple.end()
+  }
+}
+
+impl<'de> Deserialize<'de> for SerializedMessage {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct RemainingBytes;
+
+    impl<'de> Visitor<'de> for RemainingBytes {
+      type Value = SerializedMessage;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("serialized message bytes")
+      }
+
+      fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::new();
+        // Reading a u8 only fails at the end of input.
+        while let Ok(Some(b)) = seq.next_element::<u8>() {
+          bytes.push(b);
+        }
+        Ok(SerializedMessage(bytes))
+      }
+    }
+
+    deserializer.deserialize_tuple(usize::MAX, RemainingBytes)
+  }
+}
+
+/// A bounded string, e.g. `string<=10` in ROS 2 IDL
+///
+/// On the wire this is the same as a `String`. Deserialization fails if the
+/// received string is longer than `N` bytes, like it does in rclcpp.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoundedString<const N: usize>(String);
+
+impl<const N: usize> BoundedString<N> {
+  pub fn new() -> Self {
+    BoundedString(String::new())
+  }
+
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+
+  pub fn into_inner(self) -> String {
+    self.0
+  }
+}
+
+impl<const N: usize> core::ops::Deref for BoundedString<N> {
+  type Target = str;
+  fn deref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl<const N: usize> TryFrom<String> for BoundedString<N> {
+  type Error = BoundExceeded;
+  fn try_from(s: String) -> Result<Self, BoundExceeded> {
+    BoundExceeded::check(s.len(), N)?;
+    Ok(BoundedString(s))
+  }
+}
+
+impl<const N: usize> TryFrom<&str> for BoundedString<N> {
+  type Error = BoundExceeded;
+  fn try_from(s: &str) -> Result<Self, BoundExceeded> {
+    Self::try_from(String::from(s))
+  }
+}
+
+impl<const N: usize> From<BoundedString<N>> for String {
+  fn from(s: BoundedString<N>) -> String {
+    s.0
+  }
+}
+
+impl<const N: usize> Serialize for BoundedString<N> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.0.serialize(serializer)
+  }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for BoundedString<N> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    Self::try_from(s).map_err(de::Error::custom)
+  }
+}
+
+/// A bounded sequence, e.g. `int32[<=5]` in ROS 2 IDL
+///
+/// On the wire this is the same as a `Vec`. Deserialization fails if more
+/// than `N` elements are received.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BoundedVec<T, const N: usize>(Vec<T>);
+
+impl<T, const N: usize> BoundedVec<T, N> {
+  pub fn new() -> Self {
+    BoundedVec(Vec::new())
+  }
+
+  /// Appends `value`, or gives it back if the sequence is full.
+  pub fn push(&mut self, value: T) -> Result<(), T> {
+    if self.0.len() < N {
+      self.0.push(value);
+      Ok(())
+    } else {
+      Err(value)
+    }
+  }
+
+  pub fn as_slice(&self) -> &[T] {
+    &self.0
+  }
+
+  pub fn into_inner(self) -> Vec<T> {
+    self.0
+  }
+}
+
+impl<T, const N: usize> Default for BoundedVec<T, N> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T, const N: usize> core::ops::Deref for BoundedVec<T, N> {
+  type Target = [T];
+  fn deref(&self) -> &[T] {
+    &self.0
+  }
+}
+
+impl<T, const N: usize> core::ops::DerefMut for BoundedVec<T, N> {
+  fn deref_mut(&mut self) -> &mut [T] {
+    &mut self.0
+  }
+}
+
+impl<T, const N: usize> TryFrom<Vec<T>> for BoundedVec<T, N> {
+  type Error = BoundExceeded;
+  fn try_from(v: Vec<T>) -> Result<Self, BoundExceeded> {
+    BoundExceeded::check(v.len(), N)?;
+    Ok(BoundedVec(v))
+  }
+}
+
+impl<T, const N: usize> From<BoundedVec<T, N>> for Vec<T> {
+  fn from(v: BoundedVec<T, N>) -> Vec<T> {
+    v.0
+  }
+}
+
+impl<T: Serialize, const N: usize> Serialize for BoundedVec<T, N> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.0.serialize(serializer)
+  }
+}
+
+impl<'de, T: Deserialize<'de>, const N: usize> Deserialize<'de> for BoundedVec<T, N> {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let v = Vec::deserialize(deserializer)?;
+    Self::try_from(v).map_err(de::Error::custom)
+  }
+}
+
+/// A [`BoundedString`] or [`BoundedVec`] would have been longer than its
+/// bound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundExceeded {
+  pub bound: usize,
+  pub len: usize,
+}
+
+impl BoundExceeded {
+  fn check(len: usize, bound: usize) -> Result<(), BoundExceeded> {
+    if len <= bound {
+      Ok(())
+    } else {
+      Err(BoundExceeded { bound, len })
+    }
+  }
+}
+
+impl fmt::Display for BoundExceeded {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "length {} exceeds bound {}", self.len, self.bound)
+  }
+}
+
+/// Serde `with` module for static arrays longer than 32 elements, e.g.
+/// `float64[36]`, which serde does not implement.
+///
+/// Like shorter arrays, these are a CDR tuple without a length.
+pub mod big_array {
+  use core::{convert::TryInto, fmt, marker::PhantomData};
+  use alloc::vec::Vec;
+
+  use serde::{
+    de::{self, Deserializer, SeqAccess, Visitor},
+    ser::{SerializeTuple, Serializer},
+    Deserialize, Serialize,
+  };
+
+  pub fn serialize<S, T, const N: usize>(array: &[T; N], serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+    T: Serialize,
+  {
+    let mut tuple = serializer.serialize_tuple(N)?;
+    for e in array {
+      tuple.serialize_element(e)?;
+    }
+    tuple.end()
+  }
+
+  pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+  where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+  {
+    struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>, const N: usize> Visitor<'de> for ArrayVisitor<T, N> {
+      type Value = [T; N];
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an array of {} elements", N)
+      }
+
+      fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[T; N], A::Error> {
+        let mut elements = Vec::with_capacity(N);
+        for i in 0..N {
+          match seq.next_element()? {
+            Some(e) => elements.push(e),
+            None => return Err(de::Error::invalid_length(i, &self)),
+          }
+        }
+        elements
+          .try_into()
+          .map_err(|_| de::Error::invalid_length(N, &self))
+      }
+    }
+
+    deserializer.deserialize_tuple(N, ArrayVisitor(PhantomData))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use alloc::string::ToString;
+
+  use super::*;
+  use crate::cdr::{from_bytes, to_vec, Endianness};
+
+  #[test]
+  fn serialized_message_is_verbatim() {
+    let bytes = to_vec(&"hello".to_string(), Endianness::Little).unwrap();
+
+    let raw = SerializedMessage(bytes.clone());
+    assert_eq!(to_vec(&raw, Endianness::Little).unwrap(), bytes);
+
+    let (received, _) = from_bytes::<SerializedMessage>(&bytes, Endianness::Little).unwrap();
+    assert_eq!(received, raw);
+    assert_eq!(
+      raw.serialized_size_hint(),
+      "hello".to_string().serialized_size_hint()
+    );
+  }
+
+  #[test]
+  fn bounded_and_big_arrays() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Bounded {
+      name: BoundedString<5>,
+      values: BoundedVec<i32, 2>,
+      #[serde(with = "big_array")]
+      covariance: [f64; 36],
+    }
+
+    let b = Bounded {
+      name: BoundedString::try_from("hello").unwrap(),
+      values: BoundedVec::try_from(alloc::vec![1, 2]).unwrap(),
+      covariance: [0.5; 36],
+    };
+    let bytes = to_vec(&b, Endianness::Little).unwrap();
+    // Same layout as String, Vec and a plain array
+    let plain = (
+      "hello".to_string(),
+      alloc::vec![1i32, 2],
+      [[0.5f64; 12], [0.5; 12], [0.5; 12]],
+    );
+    assert_eq!(bytes, to_vec(&plain, Endianness::Little).unwrap());
+    assert_eq!(
+      from_bytes::<Bounded>(&bytes, Endianness::Little).unwrap().0,
+      b
+    );
+
+    assert_eq!(
+      BoundedString::<4>::try_from("hello"),
+      Err(BoundExceeded { bound: 4, len: 5 })
+    );
+    let too_long = (
+      "hello".to_string(),
+      alloc::vec![1i32, 2, 3],
+      [[0.5f64; 12], [0.5; 12], [0.5; 12]],
+    );
+    let bytes = to_vec(&too_long, Endianness::Little).unwrap();
+    assert!(from_bytes::<Bounded>(&bytes, Endianness::Little).is_err());
+  }
+}
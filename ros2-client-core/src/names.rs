@@ -0,0 +1,635 @@
+//! This module defines types to represent ROS 2 names for
+//! * Message types, e.g. `std_msgs/String`
+//! * Service types, e.g. `turtlesim/Spawn`
+//! * action types, e.g. `turtlesim/RotateAbsolute`
+//! *
+
+use alloc::{
+  borrow::ToOwned,
+  string::{String, ToString},
+  vec::Vec,
+};
+use core::fmt;
+
+// Topic and Service names conform to
+// https://design.ros2.org/articles/topic_and_service_names.html
+
+/// Names for Nodes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NodeName {
+  namespace: String,
+  base_name: String,
+}
+
+impl NodeName {
+  pub fn new(namespace: &str, base_name: &str) -> Result<NodeName, NameError> {
+    match base_name.chars().next() {
+      None => return Err(NameError::Empty),
+      Some(c) if c.is_ascii_alphabetic() || c == '_' => { /*ok*/ }
+      Some(other) => return Err(NameError::BadChar(other)),
+    }
+
+    if let Some(bad) = base_name
+      .chars()
+      .find(|c| !(c.is_ascii_alphanumeric() || *c == '_'))
+    {
+      return Err(NameError::BadChar(bad));
+    }
+
+    match namespace.chars().next() {
+      None => {
+        return Err(NameError::BadSlash(
+          "<empty_namespace>".to_owned(),
+          base_name.to_owned(),
+        ))
+      }
+      Some(c) if c.is_ascii_alphabetic() || c == '/' => { /*ok*/ }
+      // Character '~' is not accepted, because we do not know what that would mean in a Node's
+      // name.
+      Some(other) => return Err(NameError::BadChar(other)),
+    }
+
+    if namespace.starts_with('/') {
+      // This is what we expect
+    } else {
+      return Err(NameError::BadSlash(
+        namespace.to_owned(),
+        base_name.to_owned(),
+      ));
+    }
+
+    // Otherwise, what would be the absolute node name?
+    if let Some(bad) = namespace
+      .chars()
+      .find(|c| !(c.is_ascii_alphanumeric() || *c == '_' || *c == '/'))
+    {
+      return Err(NameError::BadChar(bad));
+    }
+
+    if (namespace.ends_with('/') && namespace != "/") || namespace.contains("//") {
+      return Err(NameError::BadSlash(
+        namespace.to_owned(),
+        base_name.to_owned(),
+      ));
+    }
+
+    if let Some(bad) = namespace
+      .split('/')
+      .find(|tok| tok.starts_with(|c: char| c.is_ascii_digit()))
+    {
+      return Err(NameError::StartsWithDigit(bad.to_owned()));
+    }
+
+    Ok(NodeName {
+      namespace: namespace.to_owned(),
+      base_name: base_name.to_owned(),
+    })
+  }
+
+  pub fn namespace(&self) -> &str {
+    &self.namespace
+  }
+  pub fn base_name(&self) -> &str {
+    &self.base_name
+  }
+
+  pub fn fully_qualified_name(&self) -> String {
+    let mut fqn = self.namespace.clone();
+    if fqn.ends_with('/') {
+      // do nothing, it already ends with slash, i.e. is exactly "/"
+    } else {
+      fqn.push('/');
+    }
+    fqn.push_str(&self.base_name);
+    fqn
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+  Empty,
+  BadChar(char),
+  BadSlash(String, String),
+  /// A name token, e.g. `2d` in `/map/2d`, starts with a digit.
+  StartsWithDigit(String),
+  /// A name token contains `__`.
+  RepeatedUnderscore(String),
+  /// `~` is not alone at the start of a relative name, as in `~` or `~/foo`.
+  BadTilde,
+  /// Curly braces are unbalanced, nested or empty in a name token.
+  BadSubstitution(String),
+  /// Substitution `{name}` is not one of `{node}`, `{ns}` or `{namespace}`.
+  UnknownSubstitution(String),
+}
+
+impl fmt::Display for NameError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      NameError::Empty => write!(f, "Base name must not be empty"),
+      NameError::BadChar(c) => write!(f, "Bad chracters in Name: {c:?}"),
+      NameError::BadSlash(ns, n) => write!(
+        f,
+        "Invalid placement of seprator slashes. namespace={ns}  name={n}"
+      ),
+      NameError::StartsWithDigit(t) => write!(f, "Name token {t:?} starts with a digit"),
+      NameError::RepeatedUnderscore(t) => {
+        write!(f, "Name token {t:?} contains repeated underscores")
+      }
+      NameError::BadTilde => write!(f, "'~' is allowed only as in \"~\" or \"~/name\""),
+      NameError::BadSubstitution(t) => write!(f, "Bad curly braces in name token {t:?}"),
+      NameError::UnknownSubstitution(s) => write!(f, "Unknown substitution {{{s}}}"),
+    }
+  }
+}
+
+impl core::error::Error for NameError {}
+
+/// Names for Topics, Services
+///
+/// See [Names](https://wiki.ros.org/Names) for ROS 1.
+/// and [topic and Service name mapping to DDS](https://design.ros2.org/articles/topic_and_service_names.html)
+/// in ROS 2 documentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Name {
+  base_name: String, // The last part of the full name. Must not be empty.
+  preceeding_tokens: Vec<String>, // without separating slashes
+  absolute: bool,    // in string format, absolute names begin with a slash
+}
+
+impl Name {
+  /// Construct a new `Name` from namespace and base name.
+  ///
+  /// If the namespace begins with a slash (`/`) character, the Name will be
+  /// absolute, otherwise it will be relative.
+  /// The namespace may consist of several components, separated by slashes.
+  /// Tha namespace must not end in a slash, unless the namespace is just `"/"`.
+  ///
+  /// Do not put slashes in the `base_name`.
+  /// Base name is not allowed to be empty, but the namespace may be empty.
+  ///
+  /// A relative name may start with `~`, the private namespace of the Node,
+  /// e.g. namespace `"~"`. Tokens may contain substitutions `{node}`, `{ns}`
+  /// or `{namespace}`. These are expanded by [`Self::resolve`].
+  pub fn new(namespace: &str, base_name: &str) -> Result<Name, NameError> {
+    let (namespace_rel, absolute) = if let Some(rel) = namespace.strip_prefix('/') {
+      (rel, true)
+    } else {
+      (namespace, false)
+    };
+
+    if base_name.is_empty() {
+      return Err(NameError::Empty);
+    }
+    check_token(base_name, !absolute && namespace_rel.is_empty())?;
+
+    let preceeding_tokens = if namespace_rel.is_empty() {
+      // If the namespace is "" or "/", we want [] instead of [""]
+      Vec::new()
+    } else {
+      namespace_rel
+        .split('/')
+        .map(str::to_owned)
+        .collect::<Vec<String>>()
+      // Starting slash, ending slash, or repeated slash all
+      // produce empty strings.
+    };
+
+    if preceeding_tokens.iter().any(String::is_empty) {
+      return Err(NameError::BadSlash(
+        namespace_rel.to_owned(),
+        base_name.to_owned(),
+      ));
+    }
+
+    for (i, tok) in preceeding_tokens.iter().enumerate() {
+      check_token(tok, !absolute && i == 0)?;
+    }
+
+    Ok(Name {
+      base_name: base_name.to_owned(),
+      preceeding_tokens,
+      absolute,
+    })
+  }
+
+  /// Construct a new `Name` from slash-separated namespace and base name.
+  ///
+  /// e.g. `myspace/some_name`
+  pub fn parse(full_name: &str) -> Result<Name, NameError> {
+    match full_name.rsplit_once('/') {
+      // no slash, just a base name, so namespace is "".
+      None => Name::new("", full_name),
+
+      // Just a single slash, i.e. empty namespace and empty base name.
+      // Not acceptable.
+      Some(("", "")) => Err(NameError::Empty),
+
+      // Last character was slash => base name is empty => bad.
+      Some((bad, "")) => Err(NameError::BadSlash(bad.to_owned(), "".to_owned())),
+
+      // Input was "/foobar", so name is absolute
+      Some(("", base)) => Name::new("/", base),
+
+      // General case: <nonempty> "/" <base_name>
+      Some((prefix, base)) => {
+        if prefix.ends_with('/') {
+          // There was a double slash => Bad.
+          Err(NameError::BadSlash(prefix.to_owned(), base.to_owned()))
+        } else {
+          Name::new(prefix, base)
+        }
+      }
+    }
+  }
+
+  /// Expand `~` and substitutions, and make a relative name absolute, as seen
+  /// by Node `node`.
+  ///
+  /// `~` is the fully qualified name of the Node, `{node}` its base name, and
+  /// `{ns}` or `{namespace}` its namespace. E.g. for Node `/robot/camera`,
+  /// `image` is `/robot/image`, `~/info` is `/robot/camera/info`, and
+  /// `{node}_raw` is `/robot/camera_raw`.
+  pub fn resolve(&self, node: &NodeName) -> Result<Name, NameError> {
+    let name = self.to_string();
+    let mut expanded = String::new();
+    let mut rest = name.as_str();
+    // Braces are checked to be balanced in construction.
+    while let Some((before, after)) = rest.split_once('{') {
+      let (substitution, after) = after
+        .split_once('}')
+        .ok_or_else(|| NameError::BadSubstitution(name.clone()))?;
+      expanded.push_str(before);
+      match substitution {
+        "node" => expanded.push_str(node.base_name()),
+        "ns" | "namespace" => expanded.push_str(node.namespace().trim_end_matches('/')),
+        other => return Err(NameError::UnknownSubstitution(other.to_owned())),
+      }
+      rest = after;
+    }
+    expanded.push_str(rest);
+
+    let full_name = if expanded.starts_with('/') {
+      expanded
+    } else if expanded == "~" {
+      node.fully_qualified_name()
+    } else if let Some(private) = expanded.strip_prefix("~/") {
+      node.fully_qualified_name() + "/" + private
+    } else {
+      node.namespace().trim_end_matches('/').to_owned() + "/" + &expanded
+    };
+    Name::parse(&full_name)
+  }
+
+  /// DDS Topic name, e.g. `rt/robot/image` for prefix `rt` and Topic
+  /// `/robot/image`.
+  ///
+  /// The name is [resolved](Self::resolve) first. If that fails, the name is
+  /// used as is, so callers should check that it resolves.
+  pub fn to_dds_name(&self, kind_prefix: &str, node: &NodeName, suffix: &str) -> String {
+    let mut result = kind_prefix.to_owned();
+    assert!(!result.ends_with('/')); // "rt"
+    let resolved = self.resolve(node).unwrap_or_else(|_| self.clone());
+    if !resolved.absolute {
+      result.push('/');
+    }
+    result.push_str(&resolved.to_string()); // "rt/node_ns/name"
+    result.push_str(suffix);
+    result
+  }
+
+  /// Append a new last component, e.g. `/a/b` and `c` gives `/a/b/c`.
+  pub fn push(&self, new_suffix: &str) -> Name {
+    //TODO: Check that we still satisfy naming rules
+    let mut preceeding_tokens = self.preceeding_tokens.clone();
+    preceeding_tokens.push(self.base_name.to_string());
+    Name {
+      base_name: new_suffix.to_string(),
+      preceeding_tokens,
+      absolute: self.absolute,
+    }
+  }
+
+  pub fn is_absolute(&self) -> bool {
+    self.absolute
+  }
+}
+
+// Checks one token of a Topic or Service name, i.e. a part between slashes.
+fn check_token(token: &str, tilde_allowed: bool) -> Result<(), NameError> {
+  if token.is_empty() {
+    return Err(NameError::Empty);
+  }
+  if token == "~" {
+    return if tilde_allowed {
+      Ok(())
+    } else {
+      Err(NameError::BadTilde)
+    };
+  }
+  if token.starts_with(|c: char| c.is_ascii_digit()) {
+    return Err(NameError::StartsWithDigit(token.to_owned()));
+  }
+  if token.contains("__") {
+    return Err(NameError::RepeatedUnderscore(token.to_owned()));
+  }
+  let mut substitution: Option<usize> = None; // length so far, if inside braces
+  for c in token.chars() {
+    match (c, substitution) {
+      ('{', None) => substitution = Some(0),
+      ('}', Some(len)) if len > 0 => substitution = None,
+      ('{', _) | ('}', _) => return Err(NameError::BadSubstitution(token.to_owned())),
+      ('~', _) => return Err(NameError::BadTilde),
+      (c, _) if c.is_ascii_alphanumeric() || c == '_' => {
+        substitution = substitution.map(|len| len + 1);
+      }
+      (other, _) => return Err(NameError::BadChar(other)),
+    }
+  }
+  match substitution {
+    None => Ok(()),
+    Some(_) => Err(NameError::BadSubstitution(token.to_owned())),
+  }
+}
+
+impl fmt::Display for Name {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.absolute {
+      write!(f, "/")?;
+    }
+    for t in &self.preceeding_tokens {
+      write!(f, "{t}/")?;
+    }
+    write!(f, "{}", self.base_name)
+  }
+}
+
+/// Name for `.msg` type, or a data type carried over a Topic.
+///
+/// This would be called a "Pacakge Resource Name", at least in ROS 1.
+///
+/// Note that this is not for naming Topics, but data types of Topics.
+///
+/// See [Names](https://wiki.ros.org/Names) Section 1.2 Package Resource Names.
+#[derive(Clone, Debug)]
+pub struct MessageTypeName {
+  prefix: String, // typically "msg", but may be "action". What should this part be called?
+  //TODO: String is strictly UTF-8, but ROS2 uses just byte strings that are recommended to be
+  // UTF-8
+  ros2_package_name: String, // or should this be "namespace"?
+  ros2_type_name: String,
+}
+
+impl MessageTypeName {
+  pub fn new(package_name: &str, type_name: &str) -> Self {
+    //TODO: Ensure parameters have no leading/trailing slashes
+    MessageTypeName {
+      prefix: "msg".to_string(),
+      ros2_package_name: package_name.to_owned(),
+      ros2_type_name: type_name.to_owned(),
+    }
+  }
+
+  pub(crate) fn new_prefix(package_name: &str, type_name: &str, prefix: String) -> Self {
+    MessageTypeName {
+      prefix,
+      ros2_package_name: package_name.to_owned(),
+      ros2_type_name: type_name.to_owned(),
+    }
+  }
+
+  pub fn package_name(&self) -> &str {
+    self.ros2_package_name.as_str()
+  }
+
+  pub fn type_name(&self) -> &str {
+    self.ros2_type_name.as_str()
+  }
+
+  /// Convert to type name used over DDS
+  pub fn dds_msg_type(&self) -> String {
+    slash_to_colons(
+      self.ros2_package_name.clone() + "/" + &self.prefix + "/dds_/" + &self.ros2_type_name + "_",
+    )
+  }
+}
+
+fn slash_to_colons(s: String) -> String {
+  s.replace('/', "::")
+}
+
+/// Similar to [`MessageTypeName`], but names a Service type.
+#[derive(Clone, Debug)]
+pub struct ServiceTypeName {
+  prefix: String,
+  msg: MessageTypeName,
+}
+
+impl ServiceTypeName {
+  pub fn new(package_name: &str, type_name: &str) -> Self {
+    ServiceTypeName {
+      prefix: "srv".to_string(),
+      msg: MessageTypeName::new(package_name, type_name),
+    }
+  }
+
+  pub(crate) fn new_prefix(package_name: &str, type_name: &str, prefix: String) -> Self {
+    ServiceTypeName {
+      prefix,
+      msg: MessageTypeName::new(package_name, type_name),
+    }
+  }
+
+  pub fn package_name(&self) -> &str {
+    self.msg.package_name()
+  }
+
+  pub fn type_name(&self) -> &str {
+    self.msg.type_name()
+  }
+
+  /// Convert to DDS type name of the request message
+  pub fn dds_request_type(&self) -> String {
+    slash_to_colons(
+      self.package_name().to_owned()
+        + "/"
+        + &self.prefix
+        + "/dds_/"
+        + self.type_name()
+        + "_Request_",
+    )
+  }
+
+  /// Convert to DDS type name of the response message
+  pub fn dds_response_type(&self) -> String {
+    slash_to_colons(
+      self.package_name().to_owned()
+        + "/"
+        + &self.prefix
+        + "/dds_/"
+        + self.type_name()
+        + "_Response_",
+    )
+  }
+
+  /// Type of the Service introspection events, `<Service>_Event`
+  pub fn event_type(&self) -> MessageTypeName {
+    MessageTypeName::new_prefix(
+      self.package_name(),
+      &(self.type_name().to_owned() + "_Event"),
+      self.prefix.clone(),
+    )
+  }
+}
+
+/// Similar to [`MessageTypeName`], but names an Action type.
+#[derive(Clone, Debug)]
+pub struct ActionTypeName(MessageTypeName);
+
+impl ActionTypeName {
+  pub fn new(package_name: &str, type_name: &str) -> Self {
+    ActionTypeName(MessageTypeName::new(package_name, type_name))
+  }
+
+  pub fn package_name(&self) -> &str {
+    self.0.package_name()
+  }
+
+  pub fn type_name(&self) -> &str {
+    self.0.type_name()
+  }
+
+  /// Type name of an Action Topic, `topic` being e.g. `"_FeedbackMessage"`
+  pub fn dds_action_topic(&self, topic: &str) -> MessageTypeName {
+    MessageTypeName::new_prefix(
+      self.package_name(),
+      &(self.type_name().to_owned() + topic),
+      "action".to_owned(),
+    )
+    //slash_to_colons(self.package_name().to_owned() + "/action/dds_/" +
+    // &self.type_name())
+  }
+
+  /// Type name of an Action Service, `srv` being e.g. `"_SendGoal"`
+  pub fn dds_action_service(&self, srv: &str) -> ServiceTypeName {
+    ServiceTypeName::new_prefix(
+      self.package_name(),
+      &(self.type_name().to_owned() + srv),
+      "action".to_owned(),
+    )
+  }
+}
+
+// -------------------------------------------------------------------------------------
+// -------------------------------------------------------------------------------------
+
+#[test]
+fn test_name() {
+  assert!(Name::new("", "").is_err());
+  assert!(Name::new("", "/").is_err());
+  assert!(Name::new("a", "b").is_ok());
+  assert!(Name::new("a", "_b").is_ok());
+  assert!(Name::new("a", "b_b").is_ok()); // may contain [...] underscores (_), [...]
+  assert!(Name::new("a", "b__b").is_err()); // must not contain any number of repeated underscores (_)
+  assert!(Name::new("a2//a", "b").is_err()); // must not contain any number of
+                                             // repeated forward slashes (/)
+}
+
+#[test]
+fn test_name_parse() {
+  // https://design.ros2.org/articles/topic_and_service_names.html
+
+  assert!(Name::parse("").is_err()); // must not be empty
+  assert!(Name::parse("/").is_err()); // must not be empty
+  assert!(Name::parse("a/").is_err()); // must not be empty
+  assert!(Name::parse("a/b/").is_err());
+
+  assert!(Name::parse("2").is_err()); // must not start with a numeric character ([0-9])
+  assert!(Name::parse("2/a").is_err()); // must not start with a numeric character ([0-9])
+  assert!(Name::parse("a2/a").is_ok());
+  assert!(Name::parse("_a2/a").is_ok()); // may contain [...] underscores (_), [...]
+  assert!(Name::parse("some_name/a").is_ok()); // may contain [...] underscores (_), [...]
+  assert!(Name::parse("__a2/a").is_err()); // must not contain any number of repeated underscores (_)
+  assert!(Name::parse("a2//a").is_err()); // must not contain any number of repeated forward slashes (/)
+
+  assert_eq!(Name::parse("a/nn").unwrap(), Name::new("a", "nn").unwrap());
+  assert_eq!(
+    Name::parse("a/b/c/nn").unwrap(),
+    Name::new("a/b/c", "nn").unwrap()
+  );
+  assert_eq!(
+    Name::parse("/a/b/c/nn").unwrap(),
+    Name::new("/a/b/c", "nn").unwrap()
+  );
+
+  assert!(!Name::parse("a/nn").unwrap().is_absolute());
+  assert!(Name::parse("/a/nn").unwrap().is_absolute());
+}
+
+#[test]
+fn test_name_validation() {
+  // https://design.ros2.org/articles/topic_and_service_names.html
+  assert_eq!(
+    Name::parse("a/2d"),
+    Err(NameError::StartsWithDigit("2d".to_owned()))
+  );
+  assert_eq!(
+    Name::parse("a__b"),
+    Err(NameError::RepeatedUnderscore("a__b".to_owned()))
+  );
+  assert_eq!(Name::parse("a/b-c"), Err(NameError::BadChar('-')));
+
+  assert!(Name::parse("~").is_ok());
+  assert!(Name::parse("~/a").is_ok());
+  assert_eq!(Name::parse("~a"), Err(NameError::BadTilde));
+  assert_eq!(Name::parse("/~/a"), Err(NameError::BadTilde));
+  assert_eq!(Name::parse("a/~/b"), Err(NameError::BadTilde));
+
+  assert!(Name::parse("{node}/a").is_ok());
+  assert!(Name::parse("a/b_{ns}_c").is_ok());
+  for bad in ["{node", "node}", "{}", "{{node}}", "{no-de}"] {
+    assert!(Name::parse(bad).is_err(), "{}", bad);
+  }
+
+  assert_eq!(
+    NodeName::new("/a/2b", "n"),
+    Err(NameError::StartsWithDigit("2b".to_owned()))
+  );
+  assert!(NodeName::new("/a//b", "n").is_err());
+}
+
+#[test]
+fn test_name_resolve() {
+  let root = NodeName::new("/", "talker").unwrap();
+  let node = NodeName::new("/robot", "camera").unwrap();
+  let resolve = |name: &str, node: &NodeName| {
+    Name::parse(name)
+      .unwrap()
+      .resolve(node)
+      .map(|n| n.to_string())
+  };
+
+  assert_eq!(resolve("image", &node).unwrap(), "/robot/image");
+  assert_eq!(resolve("image", &root).unwrap(), "/image");
+  assert_eq!(resolve("/image", &node).unwrap(), "/image");
+  assert_eq!(resolve("~", &node).unwrap(), "/robot/camera");
+  assert_eq!(resolve("~/info", &node).unwrap(), "/robot/camera/info");
+  assert_eq!(resolve("~/info", &root).unwrap(), "/talker/info");
+  assert_eq!(resolve("{node}_raw", &node).unwrap(), "/robot/camera_raw");
+  assert_eq!(resolve("{ns}/x", &node).unwrap(), "/robot/x");
+  assert_eq!(resolve("{namespace}/x", &root).unwrap(), "/x");
+  assert_eq!(
+    resolve("{other}/x", &node),
+    Err(NameError::UnknownSubstitution("other".to_owned()))
+  );
+
+  assert_eq!(
+    Name::parse("image").unwrap().to_dds_name("rt", &root, ""),
+    "rt/image"
+  );
+  assert_eq!(
+    Name::parse("~/get")
+      .unwrap()
+      .to_dds_name("rq", &node, "Request"),
+    "rq/robot/camera/getRequest"
+  );
+}
@@ -0,0 +1,170 @@
+//! [rosbridge v2 protocol](https://github.com/RobotWebTools/rosbridge_suite/blob/ros2/ROSBRIDGE_PROTOCOL.md)
+//! messages
+//!
+//! This lets a browser client, e.g. compiled to `wasm32-unknown-unknown`, talk
+//! to a `rosbridge_server` using the same message structs as the robot side.
+//! Messages are carried as JSON with the ROS 2 field names, which is what
+//! `serde` derives produce for the message types of ros2-client.
+//!
+//! Only the operations needed for Topics and Services are defined.
+//! Fragmentation and compression are not supported.
+
+use alloc::string::String;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+/// One rosbridge protocol operation, i.e. one JSON object on the wire
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum RosbridgeOp {
+  Advertise {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    topic: String,
+    #[serde(rename = "type")]
+    type_name: String,
+  },
+  Unadvertise {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    topic: String,
+  },
+  Publish {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    topic: String,
+    msg: Value,
+  },
+  Subscribe {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    topic: String,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    type_name: Option<String>,
+    /// Minimum time between messages, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    throttle_rate: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    queue_length: Option<u32>,
+  },
+  Unsubscribe {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    topic: String,
+  },
+  CallService {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    service: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    args: Option<Value>,
+  },
+  ServiceResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    service: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    values: Option<Value>,
+    result: bool,
+  },
+  Status {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    level: String,
+    msg: String,
+  },
+}
+
+impl RosbridgeOp {
+  /// Publish `msg` on `topic`.
+  pub fn publish<M: Serialize>(topic: &str, msg: &M) -> serde_json::Result<RosbridgeOp> {
+    Ok(RosbridgeOp::Publish {
+      id: None,
+      topic: topic.into(),
+      msg: serde_json::to_value(msg)?,
+    })
+  }
+
+  /// Call `service` with `request`. The response will have the same `id`.
+  pub fn call_service<Q: Serialize>(
+    id: &str,
+    service: &str,
+    request: &Q,
+  ) -> serde_json::Result<RosbridgeOp> {
+    Ok(RosbridgeOp::CallService {
+      id: Some(id.into()),
+      service: service.into(),
+      args: Some(serde_json::to_value(request)?),
+    })
+  }
+
+  /// Decode the message of a `Publish`, or the values of a `ServiceResponse`.
+  /// Returns `None` for other operations.
+  pub fn decode<M: DeserializeOwned>(&self) -> Option<serde_json::Result<M>> {
+    match self {
+      RosbridgeOp::Publish { msg, .. } => Some(M::deserialize(msg)),
+      RosbridgeOp::ServiceResponse {
+        values: Some(values),
+        ..
+      } => Some(M::deserialize(values)),
+      _ => None,
+    }
+  }
+
+  pub fn to_json(&self) -> serde_json::Result<String> {
+    serde_json::to_string(self)
+  }
+
+  pub fn from_json(json: &str) -> serde_json::Result<RosbridgeOp> {
+    serde_json::from_str(json)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use alloc::string::ToString;
+
+  use super::*;
+
+  #[derive(Debug, PartialEq, Serialize, Deserialize)]
+  struct Vector3 {
+    x: f64,
+    y: f64,
+    z: f64,
+  }
+
+  #[test]
+  fn publish_round_trip() {
+    let v = Vector3 {
+      x: 1.0,
+      y: 2.0,
+      z: 3.0,
+    };
+    let json = RosbridgeOp::publish("/cmd", &v).unwrap().to_json().unwrap();
+    assert_eq!(
+      json,
+      r#"{"op":"publish","topic":"/cmd","msg":{"x":1.0,"y":2.0,"z":3.0}}"#
+    );
+    let op = RosbridgeOp::from_json(&json).unwrap();
+    assert_eq!(op.decode::<Vector3>().unwrap().unwrap(), v);
+  }
+
+  #[test]
+  fn parse_subscribe() {
+    let op = RosbridgeOp::from_json(
+      r#"{"op":"subscribe","topic":"/chatter","type":"std_msgs/msg/String","throttle_rate":100}"#,
+    )
+    .unwrap();
+    assert_eq!(
+      op,
+      RosbridgeOp::Subscribe {
+        id: None,
+        topic: "/chatter".to_string(),
+        type_name: Some("std_msgs/msg/String".to_string()),
+        throttle_rate: Some(100),
+        queue_length: None,
+      }
+    );
+  }
+}
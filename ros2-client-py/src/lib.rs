@@ -0,0 +1,418 @@
+//! Python bindings for ros2-client
+//!
+//! Build and install into the current Python environment with
+//! `maturin develop` in this directory. Then
+//!
+//! ```python
+//! import asyncio, ros2_client
+//!
+//! ctx = ros2_client.Context()
+//! node = ctx.new_node("/", "py_listener", {"rate": 10.0})
+//! sub = node.create_subscription("/chatter", "std_msgs", "String")
+//!
+//! async def main():
+//!     while True:
+//!         print(await sub.take_async())
+//!
+//! asyncio.run(main())
+//! ```
+//!
+//! Messages are `bytes` holding the serialized CDR payload without the
+//! encapsulation header, like in the C API of ros2-client. Parameter values are
+//! converted to and from the corresponding Python types.
+//!
+//! Methods ending in `_async` return an `asyncio.Future` of the running event
+//! loop. The Rust side runs them on a shared thread pool, which also runs the
+//! Node Spinners, so they do not block the event loop.
+
+// False positives from the code that #[pyfunction] and #[pymethods] generate
+#![allow(clippy::useless_conversion)]
+
+#[macro_use]
+extern crate lazy_static;
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use futures::{executor::ThreadPool, pin_mut, select, Future, FutureExt};
+use pyo3::{
+  exceptions::{PyRuntimeError, PyTimeoutError, PyTypeError, PyValueError},
+  prelude::*,
+  types::{PyBool, PyBytes, PyFloat, PyInt, PyList, PyString},
+};
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use ros2_client::{
+  message::SerializedMessage, AService, MessageTypeName, Name, NodeName, NodeOptions,
+  ParameterValue, ServiceMapping, ServiceTypeName, DEFAULT_PUBLISHER_QOS, DEFAULT_SUBSCRIPTION_QOS,
+};
+
+type RawService = AService<SerializedMessage, SerializedMessage>;
+
+lazy_static! {
+  static ref EXECUTOR: ThreadPool = ThreadPool::builder()
+    .name_prefix("ros2_client-")
+    .create()
+    .expect("Cannot start thread pool");
+}
+
+fn runtime_error(e: impl std::fmt::Display) -> PyErr {
+  PyRuntimeError::new_err(e.to_string())
+}
+
+// Sets the result of an asyncio.Future, unless it was cancelled meanwhile.
+// This runs in the event loop thread, via call_soon_threadsafe.
+#[pyfunction]
+fn _resolve(future: &Bound<'_, PyAny>, value: PyObject, is_error: bool) -> PyResult<()> {
+  if future.call_method0("done")?.is_truthy()? {
+    return Ok(());
+  }
+  let method = if is_error {
+    "set_exception"
+  } else {
+    "set_result"
+  };
+  future.call_method1(method, (value,))?;
+  Ok(())
+}
+
+// Runs `task` on the thread pool and returns an asyncio.Future for its result.
+fn to_asyncio<F, T>(py: Python<'_>, task: F) -> PyResult<PyObject>
+where
+  F: Future<Output = PyResult<T>> + Send + 'static,
+  T: IntoPy<PyObject> + Send + 'static,
+{
+  let event_loop = py
+    .import_bound("asyncio")?
+    .call_method0("get_running_loop")?;
+  let future = event_loop.call_method0("create_future")?;
+  let resolve = wrap_pyfunction_bound!(_resolve, py)?.unbind();
+  let (event_loop, py_future) = (event_loop.unbind(), future.clone().unbind());
+
+  EXECUTOR.spawn_ok(async move {
+    let result = task.await;
+    Python::with_gil(|py| {
+      let (value, is_error) = match result {
+        Ok(v) => (v.into_py(py), false),
+        Err(e) => (e.into_value(py).into_py(py), true),
+      };
+      event_loop
+        .call_method1(
+          py,
+          "call_soon_threadsafe",
+          (resolve, py_future, value, is_error),
+        )
+        .map(|_| ())
+        .unwrap_or_else(|e| error!("Cannot complete asyncio Future: {e}"));
+    })
+  });
+  Ok(future.unbind())
+}
+
+fn parameter_from_py(value: &Bound<'_, PyAny>) -> PyResult<ParameterValue> {
+  // bool must be tested before int, because Python bool is a subclass of int.
+  let pv = if value.is_none() {
+    ParameterValue::NotSet
+  } else if value.is_instance_of::<PyBool>() {
+    ParameterValue::Boolean(value.extract()?)
+  } else if value.is_instance_of::<PyInt>() {
+    ParameterValue::Integer(value.extract()?)
+  } else if value.is_instance_of::<PyFloat>() {
+    ParameterValue::Double(value.extract()?)
+  } else if value.is_instance_of::<PyString>() {
+    ParameterValue::String(value.extract()?)
+  } else if value.is_instance_of::<PyBytes>() {
+    ParameterValue::ByteArray(value.extract()?)
+  } else if let Ok(list) = value.downcast::<PyList>() {
+    match list.iter().next() {
+      None => ParameterValue::StringArray(Vec::new()),
+      Some(first) if first.is_instance_of::<PyBool>() => {
+        ParameterValue::BooleanArray(list.extract()?)
+      }
+      Some(first) if first.is_instance_of::<PyInt>() => {
+        ParameterValue::IntegerArray(list.extract()?)
+      }
+      Some(first) if first.is_instance_of::<PyFloat>() => {
+        ParameterValue::DoubleArray(list.extract()?)
+      }
+      Some(first) if first.is_instance_of::<PyString>() => {
+        ParameterValue::StringArray(list.extract()?)
+      }
+      Some(first) => {
+        return Err(PyTypeError::new_err(format!(
+          "Unsupported Parameter array element type {}",
+          first.get_type()
+        )))
+      }
+    }
+  } else {
+    return Err(PyTypeError::new_err(format!(
+      "Unsupported Parameter type {}",
+      value.get_type()
+    )));
+  };
+  Ok(pv)
+}
+
+fn parameter_to_py(py: Python<'_>, value: ParameterValue) -> PyObject {
+  match value {
+    ParameterValue::NotSet => py.None(),
+    ParameterValue::Boolean(b) => b.into_py(py),
+    ParameterValue::Integer(i) => i.into_py(py),
+    ParameterValue::Double(d) => d.into_py(py),
+    ParameterValue::String(s) => s.into_py(py),
+    ParameterValue::ByteArray(a) => PyBytes::new_bound(py, &a).into_py(py),
+    ParameterValue::BooleanArray(a) => a.into_py(py),
+    ParameterValue::IntegerArray(a) => a.into_py(py),
+    ParameterValue::DoubleArray(a) => a.into_py(py),
+    ParameterValue::StringArray(a) => a.into_py(py),
+  }
+}
+
+// Vec<u8> would become a list of int in Python
+struct Bytes(Vec<u8>);
+
+impl IntoPy<PyObject> for Bytes {
+  fn into_py(self, py: Python<'_>) -> PyObject {
+    PyBytes::new_bound(py, &self.0).into_py(py)
+  }
+}
+
+fn type_name(package: &str, name: &str) -> MessageTypeName {
+  MessageTypeName::new(package, name)
+}
+
+/// ROS 2 Context, i.e. a DDS DomainParticipant
+#[pyclass]
+struct Context {
+  context: ros2_client::Context,
+}
+
+#[pymethods]
+impl Context {
+  #[new]
+  #[pyo3(signature = (domain_id = 0))]
+  fn new(domain_id: u16) -> PyResult<Self> {
+    let options = ros2_client::ContextOptions::new().domain_id(domain_id);
+    ros2_client::Context::with_options(options)
+      .map(|context| Context { context })
+      .map_err(runtime_error)
+  }
+
+  /// Creates a Node and starts its Spinner. `parameters` are declared on the
+  /// Node with their initial values.
+  #[pyo3(signature = (namespace, name, parameters = None))]
+  fn new_node(
+    &self,
+    namespace: &str,
+    name: &str,
+    parameters: Option<HashMap<String, Bound<'_, PyAny>>>,
+  ) -> PyResult<Node> {
+    let node_name =
+      NodeName::new(namespace, name).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let mut options = NodeOptions::new();
+    for (name, value) in parameters.unwrap_or_default() {
+      options = options.declare_parameter(&name, parameter_from_py(&value)?);
+    }
+    let mut node = self
+      .context
+      .new_node(node_name, options)
+      .map_err(runtime_error)?;
+    let spinner = node.spinner().map_err(runtime_error)?;
+    EXECUTOR.spawn_ok(async move {
+      spinner
+        .spin()
+        .await
+        .unwrap_or_else(|e| error!("Spinner failed: {e:?}"));
+    });
+    Ok(Node {
+      node: Mutex::new(node),
+    })
+  }
+}
+
+/// ROS 2 Node
+#[pyclass]
+struct Node {
+  // Entity creation needs &mut Node
+  node: Mutex<ros2_client::Node>,
+}
+
+#[pymethods]
+impl Node {
+  fn fully_qualified_name(&self) -> String {
+    self.node.lock().unwrap().fully_qualified_name()
+  }
+
+  /// Creates a Publisher. The type is given as e.g. `"std_msgs", "String"`.
+  fn create_publisher(&self, topic: &str, package: &str, type_name_: &str) -> PyResult<Publisher> {
+    let mut node = self.node.lock().unwrap();
+    let name = Name::parse(topic).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let topic = node
+      .create_topic(
+        &name,
+        type_name(package, type_name_),
+        &DEFAULT_PUBLISHER_QOS,
+      )
+      .map_err(runtime_error)?;
+    node
+      .create_publisher(&topic, None)
+      .map(|publisher| Publisher { publisher })
+      .map_err(runtime_error)
+  }
+
+  fn create_subscription(
+    &self,
+    topic: &str,
+    package: &str,
+    type_name_: &str,
+  ) -> PyResult<Subscription> {
+    let mut node = self.node.lock().unwrap();
+    let name = Name::parse(topic).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let topic = node
+      .create_topic(
+        &name,
+        type_name(package, type_name_),
+        &DEFAULT_SUBSCRIPTION_QOS,
+      )
+      .map_err(runtime_error)?;
+    let subscription = node
+      .create_subscription(&topic, None)
+      .map_err(runtime_error)?;
+    Ok(Subscription {
+      subscription: std::sync::Arc::new(subscription),
+    })
+  }
+
+  /// Creates a Service Client. The type is given as e.g.
+  /// `"example_interfaces", "AddTwoInts"`.
+  fn create_client(&self, service: &str, package: &str, type_name_: &str) -> PyResult<Client> {
+    let mut node = self.node.lock().unwrap();
+    let name = Name::parse(service).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    let client = node
+      .create_client::<RawService>(
+        ServiceMapping::Enhanced,
+        &name,
+        &ServiceTypeName::new(package, type_name_),
+        DEFAULT_PUBLISHER_QOS.clone(),
+        DEFAULT_SUBSCRIPTION_QOS.clone(),
+      )
+      .map_err(runtime_error)?;
+    Ok(Client {
+      client: std::sync::Arc::new(client),
+    })
+  }
+
+  /// Value of a Parameter, or None if it is not set.
+  fn get_parameter(&self, py: Python<'_>, name: &str) -> PyObject {
+    let value = self.node.lock().unwrap().get_parameter(name);
+    value.map_or_else(|| py.None(), |v| parameter_to_py(py, v))
+  }
+
+  fn set_parameter(&self, name: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+    let value = parameter_from_py(value)?;
+    self
+      .node
+      .lock()
+      .unwrap()
+      .set_parameter(name, value)
+      .map_err(PyValueError::new_err)
+  }
+
+  fn list_parameters(&self) -> Vec<String> {
+    self.node.lock().unwrap().list_parameters()
+  }
+}
+
+/// ROS 2 Publisher of serialized messages
+#[pyclass]
+struct Publisher {
+  publisher: ros2_client::Publisher<SerializedMessage>,
+}
+
+#[pymethods]
+impl Publisher {
+  fn publish(&self, py: Python<'_>, data: Vec<u8>) -> PyResult<()> {
+    py.allow_threads(|| self.publisher.publish(SerializedMessage(data)))
+      .map_err(runtime_error)
+  }
+}
+
+/// ROS 2 Subscription of serialized messages
+#[pyclass]
+struct Subscription {
+  subscription: std::sync::Arc<ros2_client::Subscription<SerializedMessage>>,
+}
+
+#[pymethods]
+impl Subscription {
+  /// Takes a received message, or returns None if there is none.
+  fn take(&self) -> PyResult<Option<Bytes>> {
+    let message = self.subscription.take().map_err(runtime_error)?;
+    Ok(message.map(|(m, _info)| Bytes(m.0)))
+  }
+
+  /// Waits for the next message.
+  fn take_async(&self, py: Python<'_>) -> PyResult<PyObject> {
+    let subscription = self.subscription.clone();
+    to_asyncio(py, async move {
+      let (message, _info) = subscription.async_take().await.map_err(runtime_error)?;
+      Ok(Bytes(message.0))
+    })
+  }
+}
+
+/// ROS 2 Service Client with serialized requests and responses
+#[pyclass]
+struct Client {
+  client: std::sync::Arc<ros2_client::Client<RawService>>,
+}
+
+async fn call_with_timeout(
+  client: &ros2_client::Client<RawService>,
+  request: Vec<u8>,
+  timeout: Duration,
+) -> PyResult<Vec<u8>> {
+  let call = client.async_call_service(SerializedMessage(request)).fuse();
+  let timeout = async_io::Timer::after(timeout).fuse();
+  pin_mut!(call, timeout);
+  select! {
+    result = call => result.map(|response| response.0).map_err(|e| runtime_error(format!("{e:?}"))),
+    _ = timeout => Err(PyTimeoutError::new_err("Service call timed out")),
+  }
+}
+
+#[pymethods]
+impl Client {
+  /// Calls the Service and blocks until the response arrives. Raises
+  /// TimeoutError after `timeout` seconds.
+  #[pyo3(signature = (request, timeout = 10.0))]
+  fn call(&self, py: Python<'_>, request: Vec<u8>, timeout: f64) -> PyResult<Bytes> {
+    let timeout = Duration::from_secs_f64(timeout);
+    py.allow_threads(|| {
+      futures::executor::block_on(call_with_timeout(&self.client, request, timeout))
+    })
+    .map(Bytes)
+  }
+
+  #[pyo3(signature = (request, timeout = 10.0))]
+  fn call_async(&self, py: Python<'_>, request: Vec<u8>, timeout: f64) -> PyResult<PyObject> {
+    let client = self.client.clone();
+    let timeout = Duration::from_secs_f64(timeout);
+    to_asyncio(py, async move {
+      call_with_timeout(&client, request, timeout)
+        .await
+        .map(Bytes)
+    })
+  }
+}
+
+#[pymodule]
+#[pyo3(name = "ros2_client")]
+fn ros2_client_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+  m.add_class::<Context>()?;
+  m.add_class::<Node>()?;
+  m.add_class::<Publisher>()?;
+  m.add_class::<Subscription>()?;
+  m.add_class::<Client>()?;
+  Ok(())
+}
@@ -1,6 +1,10 @@
 use std::{
+  cell::Cell,
   collections::{btree_map::Entry, BTreeMap},
   marker::PhantomData,
+  panic::AssertUnwindSafe,
+  sync::Mutex,
+  time::{Duration, Instant},
 };
 
 use rustdds::{
@@ -13,16 +17,20 @@ use builtin_interfaces::Time;
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
 use futures::{
-  pin_mut,
-  stream::{FusedStream, StreamExt},
-  Future,
+  future, pin_mut,
+  stream::{self, FusedStream, FuturesUnordered, StreamExt},
+  Future, FutureExt,
 };
 
 use crate::{
   action_msgs, builtin_interfaces,
+  error::WaitTimeout,
   message::Message,
   names::Name,
-  service::{request_id::RmwRequestId, AService, CallServiceError, Client, Server},
+  node::Node,
+  ros2::LogLevel,
+  rosout,
+  service::{panic_message, request_id::RmwRequestId, AService, CallServiceError, Client, Server},
   unique_identifier_msgs, Publisher, Subscription,
 };
 
@@ -166,6 +174,68 @@ where
   pub(crate) my_status_subscription: Subscription<action_msgs::GoalStatusArray>,
 
   pub(crate) my_action_name: Name,
+
+  pub(crate) feedback_demux: FeedbackDemux<A::FeedbackType>,
+}
+
+// Routes feedback read from the shared feedback Subscription to the feedback
+// streams of the goals, or to the unclaimed feedback stream. Feedback that
+// no stream claims is dropped.
+pub(crate) struct FeedbackDemux<F> {
+  goals: Mutex<BTreeMap<GoalId, async_channel::Sender<F>>>,
+  unclaimed: Mutex<Option<async_channel::Sender<FeedbackMessage<F>>>>,
+}
+
+impl<F> FeedbackDemux<F> {
+  pub(crate) fn new() -> Self {
+    FeedbackDemux {
+      goals: Mutex::new(BTreeMap::new()),
+      unclaimed: Mutex::new(None),
+    }
+  }
+
+  // Replaces any previous receiver of the goal.
+  fn register(&self, goal_id: GoalId) -> async_channel::Receiver<F> {
+    let (sender, receiver) = async_channel::unbounded();
+    self.goals.lock().unwrap().insert(goal_id, sender);
+    receiver
+  }
+
+  fn register_unclaimed(&self) -> async_channel::Receiver<FeedbackMessage<F>> {
+    let (sender, receiver) = async_channel::unbounded();
+    *self.unclaimed.lock().unwrap() = Some(sender);
+    receiver
+  }
+
+  fn route(&self, message: FeedbackMessage<F>) {
+    let FeedbackMessage { goal_id, feedback } = message;
+    let mut goals = self.goals.lock().unwrap();
+    let feedback = match goals.get(&goal_id) {
+      Some(sender) => match sender.try_send(feedback) {
+        Ok(()) => return,
+        Err(e) => {
+          // The stream has been dropped.
+          goals.remove(&goal_id);
+          e.into_inner()
+        }
+      },
+      None => feedback,
+    };
+    drop(goals);
+
+    let mut unclaimed = self.unclaimed.lock().unwrap();
+    match unclaimed.as_ref() {
+      Some(sender) => {
+        if sender
+          .try_send(FeedbackMessage { goal_id, feedback })
+          .is_err()
+        {
+          *unclaimed = None;
+        }
+      }
+      None => debug!("Dropped feedback for unclaimed goal {:?}", goal_id),
+    }
+  }
 }
 
 impl<A> ActionClient<A>
@@ -201,6 +271,38 @@ where
     &mut self.my_status_subscription
   }
 
+  /// Waits until an Action Server is matched on all of the goal, cancel and
+  /// result Services and the feedback and status Topics, or until `timeout`
+  /// has passed.
+  ///
+  /// Goals sent before that may be lost. `my_node` must be the Node that
+  /// created this ActionClient, and it must have a Spinner running.
+  pub async fn wait_for_action_server(
+    &self,
+    my_node: &Node,
+    timeout: std::time::Duration,
+  ) -> Result<(), WaitTimeout>
+  where
+    A::GoalType: 'static,
+    A::ResultType: 'static,
+    A::FeedbackType: 'static,
+  {
+    futures::try_join!(
+      self
+        .my_goal_client
+        .wait_for_service_timeout(my_node, timeout),
+      self
+        .my_cancel_client
+        .wait_for_service_timeout(my_node, timeout),
+      self
+        .my_result_client
+        .wait_for_service_timeout(my_node, timeout),
+      my_node.wait_for_writers(self.my_feedback_subscription.guid(), 1, timeout),
+      my_node.wait_for_writers(self.my_status_subscription.guid(), 1, timeout),
+    )
+    .map(|_| ())
+  }
+
   /// Returns and id of the Request and id for the Goal.
   /// Request id can be used to recognize correct response from Action Server.
   /// Goal id is later used to communicate Goal status and result.
@@ -245,19 +347,32 @@ where
     // been received already.
   }
 
+  /// Sends a goal, and waits for the Action Server to accept or reject it.
+  ///
+  /// Returns a handle for following the accepted goal, or `None` if the goal
+  /// was rejected.
   pub async fn async_send_goal(
     &self,
     goal: A::GoalType,
-  ) -> Result<(GoalId, SendGoalResponse), CallServiceError<()>>
+  ) -> Result<Option<ClientGoalHandle<'_, A>>, CallServiceError<()>>
   where
     <A as ActionTypes>::GoalType: 'static,
   {
     let goal_id = unique_identifier_msgs::UUID::new_random();
-    let send_goal_response = self
+    let SendGoalResponse { accepted, stamp } = self
       .my_goal_client
       .async_call_service(SendGoalRequest { goal_id, goal })
       .await?;
-    Ok((goal_id, send_goal_response))
+    if !accepted {
+      debug!("Goal {:?} rejected", goal_id);
+      return Ok(None);
+    }
+    Ok(Some(ClientGoalHandle {
+      client: self,
+      goal_id,
+      stamp,
+      status: Cell::new(GoalStatusEnum::Accepted),
+    }))
   }
 
   // From ROS2 docs:
@@ -324,6 +439,33 @@ where
       .async_call_service(CancelGoalRequest { goal_info })
   }
 
+  /// Requests canceling goals, as selected by `goal_id` and `timestamp`
+  /// according to the policy above, and tells which goals are now canceling.
+  pub async fn async_cancel_goals(
+    &self,
+    goal_id: GoalId,
+    timestamp: Time,
+  ) -> Result<CancelResponse, CallServiceError<()>> {
+    self
+      .async_cancel_goal(goal_id, timestamp)
+      .await
+      .map(CancelResponse::from)
+  }
+
+  /// Requests canceling all goals of the Action Server, also those sent by
+  /// other Clients.
+  pub async fn async_cancel_all_goals(&self) -> Result<CancelResponse, CallServiceError<()>> {
+    self.async_cancel_goals(GoalId::ZERO, Time::ZERO).await
+  }
+
+  /// Requests canceling all goals accepted at or before `timestamp`.
+  pub async fn async_cancel_goals_before(
+    &self,
+    timestamp: Time,
+  ) -> Result<CancelResponse, CallServiceError<()>> {
+    self.async_cancel_goals(GoalId::ZERO, timestamp).await
+  }
+
   pub fn request_result(&self, goal_id: GoalId) -> WriteResult<RmwRequestId, ()>
   where
     <A as ActionTypes>::ResultType: 'static,
@@ -385,16 +527,17 @@ where
         }
         Ok(Some((fb_msg, _msg_info))) => {
           // feedback on some other goal
-          debug!(
-            "Feedback on another goal {:?} != {:?}",
-            fb_msg.goal_id, goal_id
-          )
+          self.feedback_demux.route(fb_msg)
         }
       }
     }
   }
 
   /// Receive asynchronous feedback stream of goal progress.
+  ///
+  /// Feedback of other goals is passed on to their own feedback streams, or
+  /// to the [`Self::unclaimed_feedback_stream`]. Only the latest stream of
+  /// each goal receives feedback.
   pub fn feedback_stream(
     &self,
     goal_id: GoalId,
@@ -402,23 +545,53 @@ where
   where
     <A as ActionTypes>::FeedbackType: 'static,
   {
-    let expected_goal_id = goal_id; // rename
-    self
-      .my_feedback_subscription
-      .async_stream()
-      .filter_map(move |result| async move {
-        match result {
-          Err(e) => Some(Err(e)),
-          Ok((FeedbackMessage { goal_id, feedback }, _msg_info)) => {
-            if goal_id == expected_goal_id {
-              Some(Ok(feedback))
-            } else {
-              debug!("Feedback for some other {:?}.", goal_id);
-              None
+    self.demuxed_feedback(self.feedback_demux.register(goal_id))
+  }
+
+  /// Stream of feedback on goals that have no [`Self::feedback_stream`],
+  /// e.g. goals of other Clients, for monitoring tools.
+  ///
+  /// Without this stream, such feedback is dropped. Only the latest of these
+  /// streams receives feedback.
+  pub fn unclaimed_feedback_stream(
+    &self,
+  ) -> impl FusedStream<Item = ReadResult<FeedbackMessage<A::FeedbackType>>> + '_
+  where
+    <A as ActionTypes>::FeedbackType: 'static,
+  {
+    self.demuxed_feedback(self.feedback_demux.register_unclaimed())
+  }
+
+  // Whichever stream is polled reads the feedback Subscription and routes
+  // the messages, then returns its own.
+  fn demuxed_feedback<T>(
+    &self,
+    receiver: async_channel::Receiver<T>,
+  ) -> impl FusedStream<Item = ReadResult<T>> + '_
+  where
+    <A as ActionTypes>::FeedbackType: 'static,
+    T: 'static,
+  {
+    stream::unfold(receiver, move |receiver| async move {
+      loop {
+        let item = futures::select_biased! {
+          routed = receiver.recv().fuse() => match routed {
+            Ok(item) => Ok(item),
+            // Replaced by a newer stream
+            Err(_closed) => return None,
+          },
+          received = self.my_feedback_subscription.async_take().fuse() => match received {
+            Ok((message, _msg_info)) => {
+              self.feedback_demux.route(message);
+              continue;
             }
-          }
-        }
-      })
+            Err(e) => Err(e),
+          },
+        };
+        return Some((item, receiver));
+      }
+    })
+    .fuse()
   }
 
   /// Note: This does not take GoalId and will therefore report status of all
@@ -465,6 +638,144 @@ where
   }
 } // impl
 
+/// Outcome of a cancel request, from e.g.
+/// [`ActionClient::async_cancel_goals`]
+///
+/// This is [`CancelGoalResponse`] with its return code and goal list
+/// combined, so that the list is there exactly when goals are canceling.
+#[derive(Clone, Debug)]
+pub enum CancelResponse {
+  /// These goals accepted cancellation and are now canceling. Not empty.
+  Canceling(Vec<GoalInfo>),
+  /// The Action Server rejected canceling all of the selected goals, or
+  /// none was selected.
+  Rejected,
+  /// The requested goal id is not known to the Action Server.
+  UnknownGoal,
+  /// The requested goal has already ended.
+  GoalTerminated,
+}
+
+impl CancelResponse {
+  /// Goals that are now canceling
+  pub fn goals_canceling(&self) -> &[GoalInfo] {
+    match self {
+      CancelResponse::Canceling(goals) => goals,
+      _ => &[],
+    }
+  }
+
+  pub fn is_canceling(&self, goal_id: GoalId) -> bool {
+    self
+      .goals_canceling()
+      .iter()
+      .any(|goal_info| goal_info.goal_id == goal_id)
+  }
+}
+
+impl From<CancelGoalResponse> for CancelResponse {
+  fn from(response: CancelGoalResponse) -> Self {
+    use action_msgs::CancelGoalResponseEnum::*;
+    match response.return_code {
+      // Some Servers may report no error, but no goals either.
+      None if response.goals_canceling.is_empty() => CancelResponse::Rejected,
+      None => CancelResponse::Canceling(response.goals_canceling),
+      Rejected => CancelResponse::Rejected,
+      UnknownGoal => CancelResponse::UnknownGoal,
+      GoalTerminated => CancelResponse::GoalTerminated,
+    }
+  }
+}
+
+/// A goal accepted by an Action Server, from
+/// [`ActionClient::async_send_goal`]
+///
+/// The handle knows the goal id, so that feedback, status and result of the
+/// goal can be followed without passing the id around.
+pub struct ClientGoalHandle<'a, A>
+where
+  A: ActionTypes,
+  A::GoalType: Message + Clone,
+  A::ResultType: Message + Clone,
+  A::FeedbackType: Message,
+{
+  client: &'a ActionClient<A>,
+  goal_id: GoalId,
+  stamp: Time,
+  // Latest status seen through this handle
+  status: Cell<GoalStatusEnum>,
+}
+
+impl<'a, A> ClientGoalHandle<'a, A>
+where
+  A: ActionTypes,
+  A::GoalType: Message + Clone,
+  A::ResultType: Message + Clone,
+  A::FeedbackType: Message,
+{
+  pub fn goal_id(&self) -> GoalId {
+    self.goal_id
+  }
+
+  /// Time when the Action Server accepted the goal
+  pub fn stamp(&self) -> Time {
+    self.stamp
+  }
+
+  /// The latest status of the goal seen through [`Self::status_stream`] or
+  /// [`Self::await_result`]. Initially `Accepted`.
+  pub fn status(&self) -> GoalStatusEnum {
+    self.status.get()
+  }
+
+  /// Feedback on this goal
+  pub fn feedback_stream(&self) -> impl FusedStream<Item = ReadResult<A::FeedbackType>> + 'a
+  where
+    <A as ActionTypes>::FeedbackType: 'static,
+  {
+    self.client.feedback_stream(self.goal_id)
+  }
+
+  /// Changes of the status of this goal. The Action Server publishes the
+  /// statuses of all its goals on every change, so repeats of the current
+  /// status are skipped.
+  pub fn status_stream(&self) -> impl FusedStream<Item = ReadResult<GoalStatusEnum>> + '_ {
+    self
+      .client
+      .status_stream(self.goal_id)
+      .filter_map(move |result| {
+        future::ready(match result {
+          Err(e) => Some(Err(e)),
+          Ok(goal_status) if goal_status.status == self.status.get() => None,
+          Ok(goal_status) => {
+            self.status.set(goal_status.status);
+            Some(Ok(goal_status.status))
+          }
+        })
+      })
+  }
+
+  /// Requests canceling this goal.
+  pub async fn cancel(&self) -> Result<CancelResponse, CallServiceError<()>> {
+    self
+      .client
+      .async_cancel_goals(self.goal_id, Time::ZERO)
+      .await
+  }
+
+  /// Waits for the goal to end, and returns its end status and result.
+  pub async fn await_result(
+    &self,
+  ) -> Result<(GoalStatusEnum, A::ResultType), CallServiceError<()>>
+  where
+    <A as ActionTypes>::ResultType: 'static,
+  {
+    let (status, result) = self.client.async_request_result(self.goal_id).await?;
+    self.status.set(status);
+    Ok((status, result))
+  }
+}
+
 // Example topic names and types at DDS level:
 
 // rq/turtle1/rotate_absolute/_action/send_goalRequest :
@@ -675,6 +986,106 @@ pub enum GoalEndStatus {
   Canceled,
 }
 
+/// What [`AsyncActionServer`] does to executing goals when it accepts a new
+/// goal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreemptPolicy {
+  /// Goals execute concurrently. Nothing is preempted.
+  #[default]
+  Concurrent,
+  /// Executing goals go to Canceling state, and end as Canceled.
+  CancelExecuting,
+  /// Executing goals go to Canceling state, and end as Aborted.
+  AbortExecuting,
+}
+
+/// Notifies goal execution that the goal was preempted by a newer goal. See
+/// [`AsyncActionServer::preemption_signal`].
+///
+/// The execution should stop, and end the goal with
+/// [`AsyncActionServer::send_result_response`].
+#[derive(Clone, Debug)]
+pub struct PreemptionSignal(async_channel::Receiver<()>);
+
+impl PreemptionSignal {
+  pub fn is_preempted(&self) -> bool {
+    self.0.is_closed()
+  }
+
+  /// Resolves when the goal is preempted.
+  pub async fn preempted(&self) {
+    // Nothing is ever sent. The channel is closed on preemption.
+    let _ = self.0.recv().await;
+  }
+}
+
+/// What `handle_goal` of [`AsyncActionServer::serve`] decides about a new
+/// goal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoalDecision {
+  Reject,
+  /// Accept the goal, and start executing it right away.
+  AcceptAndExecute,
+}
+
+/// What `handle_cancel` of [`AsyncActionServer::serve`] decides about
+/// canceling a goal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelDecision {
+  Reject,
+  /// The goal goes to Canceling state, and its execution is notified.
+  Accept,
+}
+
+/// An executing goal, given to the `execute` callback of
+/// [`AsyncActionServer::serve`]
+pub struct ServerGoalHandle<A>
+where
+  A: ActionTypes,
+{
+  goal_id: GoalId,
+  goal: A::GoalType,
+  feedback_sender: async_channel::Sender<FeedbackMessage<A::FeedbackType>>,
+  cancel_signal: PreemptionSignal,
+}
+
+impl<A> ServerGoalHandle<A>
+where
+  A: ActionTypes,
+{
+  pub fn goal_id(&self) -> GoalId {
+    self.goal_id
+  }
+
+  pub fn goal(&self) -> &A::GoalType {
+    &self.goal
+  }
+
+  /// Publishes feedback on this goal. The feedback is sent by the serving
+  /// loop, so this does not block.
+  pub fn publish_feedback(&self, feedback: A::FeedbackType) {
+    let message = FeedbackMessage {
+      goal_id: self.goal_id,
+      feedback,
+    };
+    // Fails only if serving has stopped.
+    let _ = self.feedback_sender.try_send(message);
+  }
+
+  /// Whether the goal is Canceling, because a Client canceled it or a newer
+  /// goal preempted it. The execution should then stop, and usually end the
+  /// goal as Canceled.
+  pub fn is_canceling(&self) -> bool {
+    self.cancel_signal.is_preempted()
+  }
+
+  /// Resolves when the goal goes to Canceling state, e.g. to `select`
+  /// against the execution.
+  pub async fn canceling(&self) {
+    self.cancel_signal.preempted().await
+  }
+}
+
 #[derive(Debug)]
 pub enum GoalError<T> {
   NoSuchGoal,
@@ -702,6 +1113,14 @@ where
   status: GoalStatusEnum,
   accepted_time: Option<builtin_interfaces::Time>,
   goal: A::GoalType,
+  // Closed when the goal is preempted
+  preemption: (async_channel::Sender<()>, async_channel::Receiver<()>),
+  // End status, if preempted
+  preempted_as: Option<GoalStatusEnum>,
+  // When the goal reached a terminal status
+  ended_at: Option<Instant>,
+  // Kept for later result requests, until the goal expires
+  result: Option<A::ResultType>,
 }
 
 pub struct AsyncActionServer<A>
@@ -714,8 +1133,13 @@ where
   actionserver: ActionServer<A>,
   goals: BTreeMap<GoalId, AsyncGoal<A>>,
   result_requests: BTreeMap<GoalId, RmwRequestId>,
+  preempt_policy: PreemptPolicy,
+  result_timeout: Duration,
 }
 
+/// Default for [`AsyncActionServer::set_result_timeout`], as in rcl_action
+pub const DEFAULT_RESULT_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
 impl<A> AsyncActionServer<A>
 where
   A: ActionTypes,
@@ -728,6 +1152,118 @@ where
       actionserver,
       goals: BTreeMap::new(),
       result_requests: BTreeMap::new(),
+      preempt_policy: PreemptPolicy::default(),
+      result_timeout: DEFAULT_RESULT_TIMEOUT,
+    }
+  }
+
+  /// Set how long goals are kept after they have ended.
+  ///
+  /// Until then, ended goals stay in the published status array, and in
+  /// [`Self::serve`] their results can be requested. After that, the goal is
+  /// forgotten, like in rcl_action. The default is
+  /// [`DEFAULT_RESULT_TIMEOUT`].
+  pub fn set_result_timeout(&mut self, timeout: Duration) {
+    self.result_timeout = timeout;
+  }
+
+  pub fn result_timeout(&self) -> Duration {
+    self.result_timeout
+  }
+
+  // Moves a goal to `status`, if the goal state machine allows that.
+  // Statuses are published by the caller.
+  fn set_goal_status(
+    &mut self,
+    goal_id: GoalId,
+    status: GoalStatusEnum,
+  ) -> Result<(), GoalError<()>> {
+    let ag = self.goals.get_mut(&goal_id).ok_or(GoalError::NoSuchGoal)?;
+    if !ag.status.can_transition_to(status) {
+      error!(
+        "Goal {:?} cannot go from status {:?} to {:?}.",
+        goal_id, ag.status, status
+      );
+      return Err(GoalError::WrongGoalState);
+    }
+    ag.status = status;
+    if status.is_terminal() {
+      ag.ended_at = Some(Instant::now());
+    }
+    Ok(())
+  }
+
+  // Forgets goals that ended more than `result_timeout` ago.
+  // Returns true if some goal was removed.
+  fn expire_goals(&mut self) -> bool {
+    let now = Instant::now();
+    let timeout = self.result_timeout;
+    let goal_count = self.goals.len();
+    self.goals.retain(|goal_id, ag| match ag.ended_at {
+      Some(ended_at) if now.saturating_duration_since(ended_at) >= timeout => {
+        debug!("Goal {:?} expired", goal_id);
+        false
+      }
+      _ => true,
+    });
+    let goals = &self.goals;
+    self
+      .result_requests
+      .retain(|goal_id, _| goals.contains_key(goal_id));
+    self.goals.len() != goal_count
+  }
+
+  // When the next ended goal is due to expire
+  fn next_expiry(&self) -> Option<Instant> {
+    self
+      .goals
+      .values()
+      .filter_map(|ag| ag.ended_at)
+      .min()
+      .map(|ended_at| ended_at + self.result_timeout)
+  }
+
+  /// Set what happens to executing goals when a new goal is accepted.
+  ///
+  /// Preempted goals go to Canceling state, and their
+  /// [`PreemptionSignal`]s fire. When the application then ends them with
+  /// [`Self::send_result_response`], they end as Canceled or Aborted
+  /// according to the policy, unless they Succeeded.
+  pub fn set_preempt_policy(&mut self, policy: PreemptPolicy) {
+    self.preempt_policy = policy;
+  }
+
+  pub fn preempt_policy(&self) -> PreemptPolicy {
+    self.preempt_policy
+  }
+
+  /// Signal for noticing that an executing goal was preempted, e.g. to
+  /// `select` against the execution. `None` if there is no such goal.
+  pub fn preemption_signal(
+    &self,
+    handle: ExecutingGoalHandle<A::GoalType>,
+  ) -> Option<PreemptionSignal> {
+    self
+      .goals
+      .get(&handle.inner.goal_id)
+      .map(|ag| PreemptionSignal(ag.preemption.1.clone()))
+  }
+
+  // Moves executing goals other than `new_goal_id` to Canceling state.
+  // Statuses are published by the caller.
+  fn preempt_executing_goals(&mut self, new_goal_id: GoalId) {
+    let preempted_as = match self.preempt_policy {
+      PreemptPolicy::Concurrent => return,
+      PreemptPolicy::CancelExecuting => GoalStatusEnum::Canceled,
+      PreemptPolicy::AbortExecuting => GoalStatusEnum::Aborted,
+    };
+    for (goal_id, ag) in self.goals.iter_mut() {
+      if *goal_id != new_goal_id && ag.status == GoalStatusEnum::Executing {
+        info!("Goal {:?} preempted by {:?}", goal_id, new_goal_id);
+        ag.status = GoalStatusEnum::Canceling;
+        ag.preempted_as = Some(preempted_as);
+        ag.preemption.0.close();
+      }
     }
   }
 
@@ -753,6 +1289,10 @@ where
             status: GoalStatusEnum::Unknown,
             goal: goal_request.goal,
             accepted_time: None,
+            preemption: async_channel::bounded(1),
+            preempted_as: None,
+            ended_at: None,
+            result: None,
           });
           break (req_id, goal_request.goal_id);
         }
@@ -783,6 +1323,8 @@ where
   where
     A::GoalType: 'static,
   {
+    // Statuses are published below, so expired goals disappear.
+    self.expire_goals();
     match self.goals.entry(handle.inner.goal_id) {
       Entry::Vacant(_) => Err(GoalError::NoSuchGoal),
       Entry::Occupied(o) => match o.get() {
@@ -794,6 +1336,7 @@ where
           let mut_o = o.into_mut();
           mut_o.status = GoalStatusEnum::Accepted;
           mut_o.accepted_time = Some(now);
+          self.preempt_executing_goals(handle.inner.goal_id);
           self.publish_statuses().await;
           self.actionserver.my_goal_server.send_response(
             handle.req_id,
@@ -979,54 +1522,327 @@ where
       }
     };
 
-    match self.goals.entry(handle.inner.goal_id) {
-      Entry::Vacant(_) => Err(GoalError::NoSuchGoal),
-      Entry::Occupied(o) => {
-        match o.get() {
-          // Accepted, executing, or canceling goal can be canceled or aborted
-          // TODO: Accepted goal cannot succeed, it must be executing before success.
-          AsyncGoal {
-            status: GoalStatusEnum::Accepted,
-            ..
-          }
-          | AsyncGoal {
-            status: GoalStatusEnum::Executing,
-            ..
+    let goal_id = handle.inner.goal_id;
+    let goal = self.goals.get(&goal_id).ok_or(GoalError::NoSuchGoal)?;
+    // A preempted goal that did not succeed ends as the PreemptPolicy says.
+    let result_status = match goal.preempted_as {
+      Some(preempted_as) if result_status != GoalStatusEnum::Succeeded => preempted_as,
+      _ => result_status,
+    };
+    // Only an executing goal can succeed, and only a canceling one can be
+    // canceled.
+    self.set_goal_status(goal_id, result_status)?;
+    if let Some(ag) = self.goals.get_mut(&goal_id) {
+      ag.result = Some(result.clone());
+    }
+    self.publish_statuses().await;
+    self.actionserver.send_result(
+      req_id,
+      GetResultResponse {
+        status: result_status,
+        result,
+      },
+    )?;
+    debug!("Send result for goal_id={:?}  req_id={:?}", goal_id, req_id);
+    Ok(())
+  }
+
+  /// Run `execution` to completion and send its outcome as the goal result.
+  ///
+  /// If `execution` panics, the panic is logged to rosout via `my_node` and
+  /// the goal ends as `Aborted` with `A::ResultType::default()` as the result,
+  /// so the Client is not left waiting and this server can go on serving
+  /// other goals.
+  pub async fn execute_goal<Fut>(
+    &mut self,
+    my_node: &Node,
+    handle: ExecutingGoalHandle<A::GoalType>,
+    execution: Fut,
+  ) -> Result<(), GoalError<()>>
+  where
+    Fut: Future<Output = (GoalEndStatus, A::ResultType)>,
+    A::ResultType: Default + 'static,
+  {
+    let (result_status, result) = match AssertUnwindSafe(execution).catch_unwind().await {
+      Ok(outcome) => outcome,
+      Err(payload) => {
+        rosout!(
+          my_node,
+          LogLevel::Error,
+          "Action goal {:?} execution panicked: {}",
+          handle.inner.goal_id,
+          panic_message(payload.as_ref())
+        );
+        (GoalEndStatus::Aborted, A::ResultType::default())
+      }
+    };
+    self
+      .send_result_response(handle, result_status, result)
+      .await
+  }
+
+  /// Runs this Action Server, until reading requests fails.
+  ///
+  /// This manages the goals on behalf of the application:
+  /// * `handle_goal` accepts or rejects each new goal.
+  /// * `execute` is called with each accepted goal. Its future executes the
+  ///   goal, possibly publishing feedback, and returns the end status and
+  ///   result. Executions run concurrently within this future.
+  /// * `handle_cancel` accepts or rejects canceling each goal selected by a
+  ///   cancel request. Executions of accepted ones are notified, see
+  ///   [`ServerGoalHandle::is_canceling`].
+  ///
+  /// Goal statuses are published on each change. Results are kept, so that
+  /// Clients can request them also after the goal has ended.
+  ///
+  /// If `execute` panics, the panic is logged to rosout via `my_node` and
+  /// the goal ends as Aborted with `A::ResultType::default()`, as in
+  /// [`Self::execute_goal`].
+  pub async fn serve<HG, HC, EX, Fut>(
+    mut self,
+    my_node: &Node,
+    mut handle_goal: HG,
+    mut handle_cancel: HC,
+    mut execute: EX,
+  ) -> ReadResult<()>
+  where
+    HG: FnMut(GoalId, &A::GoalType) -> GoalDecision,
+    HC: FnMut(GoalId) -> CancelDecision,
+    EX: FnMut(ServerGoalHandle<A>) -> Fut,
+    Fut: Future<Output = (GoalEndStatus, A::ResultType)>,
+    A::GoalType: 'static,
+    A::ResultType: Default + 'static,
+    A::FeedbackType: 'static,
+  {
+    enum Event<G, F, R> {
+      Goal((RmwRequestId, SendGoalRequest<G>)),
+      Cancel((RmwRequestId, CancelGoalRequest)),
+      ResultRequest((RmwRequestId, GetResultRequest)),
+      Feedback(FeedbackMessage<F>),
+      Done(GoalId, std::thread::Result<(GoalEndStatus, R)>),
+      Expiry,
+    }
+
+    let (feedback_sender, feedback_receiver) = async_channel::unbounded();
+    let mut executions = FuturesUnordered::new();
+    // Result requests of goals still executing
+    let mut waiting_for_result: BTreeMap<GoalId, Vec<RmwRequestId>> = self
+      .result_requests
+      .iter()
+      .map(|(goal_id, req_id)| (*goal_id, vec![*req_id]))
+      .collect();
+    self.result_requests.clear();
+
+    loop {
+      let expiry = match self.next_expiry() {
+        Some(at) => async_io::Timer::at(at).left_future(),
+        None => future::pending().right_future(),
+      };
+      let event = {
+        let server = &self.actionserver;
+        futures::select! {
+          r = server.my_goal_server.async_receive_request().fuse() => Event::Goal(r?),
+          r = server.my_cancel_server.async_receive_request().fuse() => Event::Cancel(r?),
+          r = server.my_result_server.async_receive_request().fuse() =>
+            Event::ResultRequest(r?),
+          f = feedback_receiver.recv().fuse() =>
+            // We keep a sender, so the channel stays open.
+            Event::Feedback(f.expect("feedback channel closed")),
+          (goal_id, outcome) = executions.select_next_some() => Event::Done(goal_id, outcome),
+          _ = expiry.fuse() => Event::Expiry,
+        }
+      };
+
+      match event {
+        Event::Goal((req_id, SendGoalRequest { goal_id, goal })) => {
+          if self.goals.contains_key(&goal_id) {
+            error!(
+              "Received duplicate goal_id {:?} , req_id={:?}",
+              goal_id, req_id
+            );
+            continue;
           }
-          | AsyncGoal {
-            status: GoalStatusEnum::Canceling,
-            ..
-          } => {
-            o.into_mut().status = result_status;
-            self.publish_statuses().await;
-            self.actionserver.send_result(
-              req_id,
-              GetResultResponse {
-                status: result_status,
-                result,
+          let accepted = handle_goal(goal_id, &goal) == GoalDecision::AcceptAndExecute;
+          let stamp = builtin_interfaces::Time::now();
+          if accepted {
+            let preemption = async_channel::bounded(1);
+            let handle = ServerGoalHandle {
+              goal_id,
+              goal: goal.clone(),
+              feedback_sender: feedback_sender.clone(),
+              cancel_signal: PreemptionSignal(preemption.1.clone()),
+            };
+            self.goals.insert(
+              goal_id,
+              AsyncGoal {
+                status: GoalStatusEnum::Accepted,
+                accepted_time: Some(stamp),
+                goal,
+                preemption,
+                preempted_as: None,
+                ended_at: None,
+                result: None,
               },
-            )?;
-            debug!(
-              "Send result for goal_id={:?}  req_id={:?}",
-              handle.inner.goal_id, req_id
             );
-            Ok(())
+            self.preempt_executing_goals(goal_id);
+            self.publish_statuses().await;
+            self.respond_to_goal(req_id, SendGoalResponse { accepted, stamp });
+
+            self
+              .set_goal_status(goal_id, GoalStatusEnum::Executing)
+              .unwrap_or_default();
+            self.publish_statuses().await;
+            let execution = match std::panic::catch_unwind(AssertUnwindSafe(|| execute(handle))) {
+              Ok(execution) => AssertUnwindSafe(execution).catch_unwind().left_future(),
+              Err(payload) => future::ready(Err(payload)).right_future(),
+            };
+            executions.push(execution.map(move |outcome| (goal_id, outcome)));
+          } else {
+            self.respond_to_goal(req_id, SendGoalResponse { accepted, stamp });
           }
-          AsyncGoal {
-            status: wrong_status,
-            ..
-          } => {
-            error!(
-              "Tried to finish goal {:?} but status was {:?}.",
-              handle.inner.goal_id, wrong_status
-            );
-            Err(GoalError::WrongGoalState)
+        }
+
+        Event::Cancel((req_id, CancelGoalRequest { goal_info })) => {
+          let mut goals_canceling = Vec::new();
+          for goal_id in self.cancel_candidates(goal_info.clone()) {
+            if handle_cancel(goal_id) == CancelDecision::Accept {
+              if let Some(ag) = self.goals.get_mut(&goal_id) {
+                ag.status = GoalStatusEnum::Canceling;
+                ag.preemption.0.close();
+                goals_canceling.push(GoalInfo {
+                  goal_id,
+                  stamp: ag.accepted_time.unwrap_or(builtin_interfaces::Time::ZERO),
+                });
+              }
+            }
+          }
+          let return_code = if !goals_canceling.is_empty() {
+            self.publish_statuses().await;
+            action_msgs::CancelGoalResponseEnum::None
+          } else if goal_info.goal_id == GoalId::ZERO {
+            action_msgs::CancelGoalResponseEnum::Rejected
+          } else {
+            match self.goals.get(&goal_info.goal_id).map(|ag| ag.status) {
+              None => action_msgs::CancelGoalResponseEnum::UnknownGoal,
+              Some(status) if status.is_terminal() => {
+                action_msgs::CancelGoalResponseEnum::GoalTerminated
+              }
+              Some(_) => action_msgs::CancelGoalResponseEnum::Rejected,
+            }
+          };
+          let response = CancelGoalResponse {
+            return_code,
+            goals_canceling,
+          };
+          self
+            .actionserver
+            .send_cancel_response(req_id, response)
+            .unwrap_or_else(|e| error!("Cannot send cancel response to {req_id:?}: {e:?}"));
+        }
+
+        Event::ResultRequest((req_id, GetResultRequest { goal_id })) => {
+          if let Some(ag) = self.goals.get(&goal_id) {
+            match &ag.result {
+              Some(result) => {
+                let response = GetResultResponse {
+                  status: ag.status,
+                  result: result.clone(),
+                };
+                self.respond_to_result_request(req_id, response);
+              }
+              None => waiting_for_result.entry(goal_id).or_default().push(req_id),
+            }
+          } else {
+            debug!("Result requested for unknown goal {:?}", goal_id);
+            let response = GetResultResponse {
+              status: GoalStatusEnum::Unknown,
+              result: A::ResultType::default(),
+            };
+            self.respond_to_result_request(req_id, response);
+          }
+        }
+
+        Event::Feedback(message) => {
+          self
+            .actionserver
+            .my_feedback_publisher
+            .publish(message)
+            .unwrap_or_else(|e| error!("Cannot publish feedback: {:?}", e.forget_data()));
+        }
+
+        Event::Done(goal_id, outcome) => {
+          let (end_status, result) = match outcome {
+            Ok(outcome) => outcome,
+            Err(payload) => {
+              rosout!(
+                my_node,
+                LogLevel::Error,
+                "Action goal {:?} execution panicked: {}",
+                goal_id,
+                panic_message(payload.as_ref())
+              );
+              (GoalEndStatus::Aborted, A::ResultType::default())
+            }
+          };
+          let mut status = match end_status {
+            GoalEndStatus::Succeeded => GoalStatusEnum::Succeeded,
+            GoalEndStatus::Aborted => GoalStatusEnum::Aborted,
+            GoalEndStatus::Canceled => GoalStatusEnum::Canceled,
+          };
+          // A preempted goal that did not succeed ends as the
+          // PreemptPolicy says.
+          if let Some(preempted_as) = self.goals.get(&goal_id).and_then(|ag| ag.preempted_as) {
+            if status != GoalStatusEnum::Succeeded {
+              status = preempted_as;
+            }
+          }
+          // E.g. Canceled without a cancel request is not a legal end.
+          if self.set_goal_status(goal_id, status).is_err() {
+            status = GoalStatusEnum::Aborted;
+            self.set_goal_status(goal_id, status).unwrap_or_default();
+          }
+          if let Some(ag) = self.goals.get_mut(&goal_id) {
+            ag.result = Some(result.clone());
+          }
+          self.publish_statuses().await;
+          let response = GetResultResponse { status, result };
+          for req_id in waiting_for_result.remove(&goal_id).unwrap_or_default() {
+            self.respond_to_result_request(req_id, response.clone());
+          }
+        }
+
+        Event::Expiry => {
+          if self.expire_goals() {
+            self.publish_statuses().await;
           }
         }
       }
     }
   }
 
+  fn respond_to_goal(&self, req_id: RmwRequestId, response: SendGoalResponse)
+  where
+    A::GoalType: 'static,
+  {
+    self
+      .actionserver
+      .send_goal_response(req_id, response)
+      .unwrap_or_else(|e| error!("Cannot send goal response to {req_id:?}: {e:?}"));
+  }
+
+  fn respond_to_result_request(
+    &self,
+    req_id: RmwRequestId,
+    response: GetResultResponse<A::ResultType>,
+  ) where
+    A::ResultType: 'static,
+  {
+    self
+      .actionserver
+      .send_result(req_id, response)
+      .unwrap_or_else(|e| error!("Cannot send result to {req_id:?}: {e:?}"));
+  }
+
   /// Abort goal execution, because action server has determined it
   /// cannot continue execution.
   pub async fn abort_executing_goal(
@@ -1056,8 +1872,12 @@ where
         | AsyncGoal {
           status: GoalStatusEnum::Executing,
           ..
+        }
+        | AsyncGoal {
+          status: GoalStatusEnum::Canceling,
+          ..
         } => {
-          o.into_mut().status = GoalStatusEnum::Aborted;
+          self.set_goal_status(handle.goal_id, GoalStatusEnum::Aborted)?;
           self.publish_statuses().await;
           Ok(())
         }
@@ -1066,7 +1886,8 @@ where
           ..
         } => {
           error!(
-            "Tried to abort goal {:?} but status was {:?}, expected Accepted or Executing. ",
+            "Tried to abort goal {:?} but status was {:?}, expected Accepted, Executing or \
+             Canceling.",
             handle.goal_id, wrong_status
           );
           Err(GoalError::WrongGoalState)
@@ -1086,6 +1907,19 @@ where
       .async_receive_request()
       .await?;
 
+    // TODO:
+    // Should check if the specified GoalId was unknown to us
+    // or already terminated.
+    // In those case outright send a negative response and not return to the
+    // application.
+    Ok(CancelHandle {
+      req_id,
+      goals: self.cancel_candidates(goal_info),
+    })
+  }
+
+  // Accepted and Executing goals that `goal_info` of a cancel request selects
+  fn cancel_candidates(&self, goal_info: GoalInfo) -> Vec<GoalId> {
     #[allow(clippy::type_complexity)] // How would you refactor this type?
     let goal_filter: Box<dyn FnMut(&(&GoalId, &AsyncGoal<A>)) -> bool> = match goal_info {
       GoalInfo {
@@ -1096,7 +1930,7 @@ where
       GoalInfo {
         goal_id: GoalId::ZERO,
         stamp,
-      } => Box::new(move |(_, ag)| ag.accepted_time.map(|at| at < stamp).unwrap_or(false)),
+      } => Box::new(move |(_, ag)| ag.accepted_time.map(|at| at <= stamp).unwrap_or(false)),
 
       GoalInfo {
         goal_id,
@@ -1104,32 +1938,22 @@ where
       } => Box::new(move |(g_id, _)| goal_id == **g_id),
 
       GoalInfo { goal_id, stamp } => Box::new(move |(g_id, ag)| {
-        goal_id == **g_id || ag.accepted_time.map(move |at| at < stamp).unwrap_or(false)
+        goal_id == **g_id || ag.accepted_time.map(move |at| at <= stamp).unwrap_or(false)
       }),
     };
 
-    // TODO:
-    // Should check if the specified GoalId was unknown to us
-    // or already terminated.
-    // In those case outright send a negative response and not return to the
-    // application.
-    let cancel_handle = CancelHandle {
-      req_id,
-      goals: self
-        .goals
-        .iter()
-        // only consider goals with status Executing or Accepted for Cancel
-        .filter(|(_, async_goal)| {
-          async_goal.status == GoalStatusEnum::Executing
-            || async_goal.status == GoalStatusEnum::Accepted
-        })
-        // and then filter those that were specified by the cancel request
-        .filter(goal_filter)
-        .map(|p| *p.0)
-        .collect(),
-    };
-
-    Ok(cancel_handle)
+    self
+      .goals
+      .iter()
+      // only consider goals with status Executing or Accepted for Cancel
+      .filter(|(_, async_goal)| {
+        async_goal.status == GoalStatusEnum::Executing
+          || async_goal.status == GoalStatusEnum::Accepted
+      })
+      // and then filter those that were specified by the cancel request
+      .filter(goal_filter)
+      .map(|p| *p.0)
+      .collect()
   }
 
   /// Respond to action client's cancel requests.
@@ -1146,6 +1970,7 @@ where
         self
           .goals
           .get(&goal_id)
+          .filter(|ag| ag.status.can_transition_to(GoalStatusEnum::Canceling))
           .and_then(|AsyncGoal { accepted_time, .. }| {
             accepted_time.map(|stamp| GoalInfo { goal_id, stamp })
           })
@@ -1154,9 +1979,8 @@ where
 
     for goal_info in &canceling_goals {
       self
-        .goals
-        .entry(goal_info.goal_id)
-        .and_modify(|gg| gg.status = GoalStatusEnum::Canceling);
+        .set_goal_status(goal_info.goal_id, GoalStatusEnum::Canceling)
+        .unwrap_or_default();
     }
     self.publish_statuses().await;
 
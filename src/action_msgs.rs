@@ -25,6 +25,36 @@ pub enum GoalStatusEnum {
   Aborted = 6,
 }
 
+impl GoalStatusEnum {
+  /// Whether the goal has ended: Succeeded, Canceled or Aborted
+  pub fn is_terminal(self) -> bool {
+    matches!(
+      self,
+      GoalStatusEnum::Succeeded | GoalStatusEnum::Canceled | GoalStatusEnum::Aborted
+    )
+  }
+
+  /// Whether a goal may go from this status to `next`, according to the goal
+  /// state machine of rcl_action. `Unknown` stands for a new goal, which may
+  /// only be accepted.
+  pub fn can_transition_to(self, next: GoalStatusEnum) -> bool {
+    use GoalStatusEnum::*;
+    matches!(
+      (self, next),
+      (Unknown, Accepted)
+        | (Accepted, Executing)
+        | (Accepted, Canceling)
+        | (Accepted, Aborted)
+        | (Executing, Canceling)
+        | (Executing, Succeeded)
+        | (Executing, Aborted)
+        | (Canceling, Canceled)
+        | (Canceling, Succeeded)
+        | (Canceling, Aborted)
+    )
+  }
+}
+
 /// From [GoalStatus](https://docs.ros2.org/foxy/api/action_msgs/msg/GoalStatus.html)
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct GoalStatus {
@@ -89,3 +119,26 @@ pub struct CancelGoalResponse {
   pub goals_canceling: Vec<GoalInfo>,
 }
 impl Message for CancelGoalResponse {}
+
+#[cfg(test)]
+mod tests {
+  use super::GoalStatusEnum::*;
+
+  #[test]
+  fn goal_status_transitions() {
+    assert!(Unknown.can_transition_to(Accepted));
+    assert!(Accepted.can_transition_to(Executing));
+    assert!(Executing.can_transition_to(Succeeded));
+    assert!(Canceling.can_transition_to(Canceled));
+    // Must execute before succeeding, and cancel before being canceled
+    assert!(!Accepted.can_transition_to(Succeeded));
+    assert!(!Executing.can_transition_to(Canceled));
+    // Terminal states are final
+    for end in [Succeeded, Canceled, Aborted] {
+      assert!(end.is_terminal());
+      assert!(!end.can_transition_to(Executing));
+      assert!(!end.can_transition_to(Aborted));
+    }
+    assert!(!Executing.is_terminal());
+  }
+}
@@ -0,0 +1,244 @@
+//! Opt-in QoS adaptation for Publishers and Subscriptions.
+//!
+//! DDS silently refuses to match a Reader and a Writer whose QoS policies are
+//! incompatible, e.g. a reliable Subscription and a best-effort Publisher.
+//! This is correct, but inconvenient for general-purpose tools, such as
+//! recorders or monitors, that should just receive whatever is out there.
+//!
+//! [`AdaptiveSubscription`] and [`AdaptivePublisher`] watch the Node status
+//! events. When they see that an otherwise matching remote endpoint was
+//! rejected because of incompatible QoS, they recreate themselves with QoS
+//! that is compatible with the remote, and log a warning.
+//!
+//! Adaptation only ever relaxes a Subscription (or strengthens a Publisher),
+//! so previously matched endpoints stay matched.
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use rustdds::{qos::QosPolicyId, *};
+use serde::Serialize;
+
+use crate::{
+  error::Error,
+  node::{Node, NodeEvent},
+  pubsub::{Publisher, Subscription},
+};
+
+// Copy one policy from `from` into `to`.
+fn adopt_policy(to: &QosPolicies, from: &QosPolicies, policy: QosPolicyId) -> QosPolicies {
+  let b = QosPolicies::builder();
+  let patch = match policy {
+    QosPolicyId::Durability => from.durability().map(|p| b.durability(p)),
+    QosPolicyId::Presentation => from.presentation().map(|p| b.presentation(p)),
+    QosPolicyId::Deadline => from.deadline().map(|p| b.deadline(p)),
+    QosPolicyId::LatencyBudget => from.latency_budget().map(|p| b.latency_budget(p)),
+    QosPolicyId::Ownership => from.ownership().map(|p| b.ownership(p)),
+    QosPolicyId::Liveliness => from.liveliness().map(|p| b.liveliness(p)),
+    QosPolicyId::Reliability => from.reliability().map(|p| b.reliability(p)),
+    QosPolicyId::DestinationOrder => from.destination_order().map(|p| b.destination_order(p)),
+    _ => None,
+  };
+  match patch {
+    Some(patch) => to.modify_by(&patch.build()),
+    None => to.clone(),
+  }
+}
+
+// Adopt policies from `other` until `offered` is compatible with `requested`.
+fn make_compatible(own: &QosPolicies, other: &QosPolicies, own_is_writer: bool) -> QosPolicies {
+  let mut own = own.clone();
+  // Each round fixes one policy, so the number of policies bounds the loop.
+  for _ in 0..16 {
+    let failure = if own_is_writer {
+      own.compliance_failure_wrt(other)
+    } else {
+      other.compliance_failure_wrt(&own)
+    };
+    match failure {
+      None => break,
+      Some(policy) => {
+        let adopted = adopt_policy(&own, other, policy);
+        if adopted == own {
+          // Cannot fix this one
+          break;
+        }
+        own = adopted;
+      }
+    }
+  }
+  own
+}
+
+/// Reader QoS that is compatible with the `offered` Writer QoS, but otherwise
+/// as in `requested`.
+pub fn compatible_reader_qos(requested: &QosPolicies, offered: &QosPolicies) -> QosPolicies {
+  make_compatible(requested, offered, false)
+}
+
+/// Writer QoS that is compatible with the `requested` Reader QoS, but
+/// otherwise as in `offered`.
+pub fn compatible_writer_qos(offered: &QosPolicies, requested: &QosPolicies) -> QosPolicies {
+  make_compatible(offered, requested, true)
+}
+
+/// A [`Subscription`] that relaxes its QoS to match incompatible Publishers.
+///
+/// Created by [`Node::create_adaptive_subscription`]. Feed it the events from
+/// [`Node::status_receiver`] using [`Self::handle_event`].
+pub struct AdaptiveSubscription<M> {
+  subscription: Subscription<M>,
+  topic: Topic,
+  qos: QosPolicies,
+}
+
+impl<M: 'static> AdaptiveSubscription<M> {
+  pub(crate) fn new(subscription: Subscription<M>, topic: Topic, qos: QosPolicies) -> Self {
+    AdaptiveSubscription {
+      subscription,
+      topic,
+      qos,
+    }
+  }
+
+  /// The current Subscription. This changes when QoS is adapted.
+  pub fn subscription(&self) -> &Subscription<M> {
+    &self.subscription
+  }
+
+  /// The QoS currently in use
+  pub fn qos(&self) -> &QosPolicies {
+    &self.qos
+  }
+
+  /// Process a Node status event.
+  ///
+  /// If the event reports a Publisher rejected by this Subscription because of
+  /// incompatible QoS, the Subscription is recreated with compatible QoS.
+  /// Returns `Ok(true)` if that happened. Samples not yet taken from the old
+  /// Subscription are lost.
+  ///
+  /// `my_node` must be the Node that created this.
+  pub fn handle_event(&mut self, my_node: &mut Node, event: &NodeEvent) -> Result<bool, Error> {
+    match event {
+      NodeEvent::DDS(DomainParticipantStatusEvent::RemoteWriterQosIncompatible {
+        local_reader,
+        remote_writer,
+        offered_qos,
+        ..
+      }) if *local_reader == self.subscription.guid() => {
+        let new_qos = compatible_reader_qos(&self.qos, offered_qos);
+        if new_qos == self.qos {
+          debug!(
+            "Cannot adapt QoS on {} to {remote_writer:?}",
+            self.topic.name()
+          );
+          return Ok(false);
+        }
+        warn!(
+          "Subscription on {}: Publisher {remote_writer:?} has incompatible QoS. Recreating \
+           Subscription with relaxed QoS {new_qos:?}",
+          self.topic.name()
+        );
+        self.subscription = my_node.create_subscription(&self.topic, Some(new_qos.clone()))?;
+        self.qos = new_qos;
+        Ok(true)
+      }
+      _ => Ok(false),
+    }
+  }
+}
+
+/// A [`Publisher`] that adapts its QoS to match incompatible Subscriptions.
+///
+/// Created by [`Node::create_adaptive_publisher`]. Feed it the events from
+/// [`Node::status_receiver`] using [`Self::handle_event`].
+pub struct AdaptivePublisher<M: Serialize> {
+  publisher: Publisher<M>,
+  topic: Topic,
+  qos: QosPolicies,
+}
+
+impl<M: Serialize> AdaptivePublisher<M> {
+  pub(crate) fn new(publisher: Publisher<M>, topic: Topic, qos: QosPolicies) -> Self {
+    AdaptivePublisher {
+      publisher,
+      topic,
+      qos,
+    }
+  }
+
+  /// The current Publisher. This changes when QoS is adapted.
+  pub fn publisher(&self) -> &Publisher<M> {
+    &self.publisher
+  }
+
+  /// The QoS currently in use
+  pub fn qos(&self) -> &QosPolicies {
+    &self.qos
+  }
+
+  /// Process a Node status event.
+  ///
+  /// If the event reports a Subscription rejected by this Publisher because
+  /// of incompatible QoS, the Publisher is recreated with compatible QoS.
+  /// Returns `Ok(true)` if that happened.
+  ///
+  /// `my_node` must be the Node that created this.
+  pub fn handle_event(&mut self, my_node: &mut Node, event: &NodeEvent) -> Result<bool, Error> {
+    match event {
+      NodeEvent::DDS(DomainParticipantStatusEvent::RemoteReaderQosIncompatible {
+        local_writer,
+        remote_reader,
+        requested_qos,
+        ..
+      }) if *local_writer == self.publisher.guid() => {
+        let new_qos = compatible_writer_qos(&self.qos, requested_qos);
+        if new_qos == self.qos {
+          debug!(
+            "Cannot adapt QoS on {} to {remote_reader:?}",
+            self.topic.name()
+          );
+          return Ok(false);
+        }
+        warn!(
+          "Publisher on {}: Subscription {remote_reader:?} has incompatible QoS. Recreating \
+           Publisher with QoS {new_qos:?}",
+          self.topic.name()
+        );
+        self.publisher = my_node.create_publisher(&self.topic, Some(new_qos.clone()))?;
+        self.qos = new_qos;
+        Ok(true)
+      }
+      _ => Ok(false),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rustdds::policy::{Durability, Reliability};
+
+  use super::*;
+
+  #[test]
+  fn relax_reader() {
+    let requested = QosPolicies::builder()
+      .reliable(Duration::from_millis(100))
+      .durability(Durability::TransientLocal)
+      .build();
+    let offered = QosPolicies::builder()
+      .best_effort()
+      .durability(Durability::Volatile)
+      .build();
+    assert!(offered.compliance_failure_wrt(&requested).is_some());
+
+    let relaxed = compatible_reader_qos(&requested, &offered);
+    assert_eq!(relaxed.reliability(), Some(Reliability::BestEffort));
+    assert_eq!(relaxed.durability(), Some(Durability::Volatile));
+    assert_eq!(offered.compliance_failure_wrt(&relaxed), None);
+
+    let strengthened = compatible_writer_qos(&offered, &requested);
+    assert!(strengthened.is_reliable());
+    assert_eq!(strengthened.compliance_failure_wrt(&requested), None);
+  }
+}
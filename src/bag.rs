@@ -0,0 +1,693 @@
+//! Recording Topics to rosbag2 files, like `ros2 bag record`
+//!
+//! A [`Recorder`] subscribes to Topics chosen by name or by regular expression,
+//! and writes their messages without deserializing them. The result is a bag
+//! in the rosbag2 SQLite3 storage format of ROS 2 Iron: a directory containing
+//! a `.db3` database and `metadata.yaml`. It can be played back with
+//! `ros2 bag play` and opened in Foxglove.
+//!
+//! Topics are found from the ROS 2 Graph, so the Spinner of the Node must be
+//! running. Topics that appear while recording are subscribed to when they are
+//! discovered.
+//!
+//! Limitations:
+//!
+//! * Discovery does not tell the QoS of the recorded Publishers, so the bag
+//!   offers the ROS 2 default QoS (reliable, volatile, keep last 10) for
+//!   playback.
+//! * Type hashes and message definitions are not recorded.
+//! * Compression and splitting into several files are not supported.
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  convert::TryFrom,
+  fs,
+  marker::PhantomData,
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use async_io::Timer;
+use futures::{stream, FutureExt, StreamExt};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use regex::Regex;
+use rusqlite::{params, Connection};
+use rustdds::{
+  no_key,
+  policy::{Durability, History, Reliability},
+  serialization::Error as DecodeError,
+  QosPolicies, QosPolicyBuilder, RepresentationIdentifier,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  cdr_adapter::REPR_IDS,
+  message::SerializedMessage,
+  names::{ActionTypeName, MessageTypeName, Name},
+  node::Node,
+  pubsub::Subscription,
+};
+
+/// What went wrong when recording a bag
+#[derive(Debug, thiserror::Error)]
+pub enum BagError {
+  /// E.g. the bag directory exists already.
+  #[error("Cannot write bag: {0}")]
+  Io(#[from] std::io::Error),
+  #[error("SQLite error: {0}")]
+  Sqlite(#[from] rusqlite::Error),
+  #[error("Cannot write bag metadata: {0}")]
+  Metadata(#[from] serde_yaml::Error),
+  /// A Topic or Subscription could not be created.
+  #[error(transparent)]
+  Ros(#[from] crate::Error),
+}
+
+/// Which Topics a [`Recorder`] records, and how
+///
+/// This is a builder-like struct. By default, no Topics are recorded.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct RecorderOptions {
+  pub(crate) topics: BTreeSet<String>,
+  pub(crate) regex: Option<Regex>,
+  pub(crate) qos: Option<QosPolicies>,
+  pub(crate) discovery_period: Duration,
+}
+
+impl Default for RecorderOptions {
+  fn default() -> Self {
+    RecorderOptions {
+      topics: BTreeSet::new(),
+      regex: None,
+      qos: None,
+      discovery_period: Duration::from_secs(1),
+    }
+  }
+}
+
+impl RecorderOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Record Topic `name`, which must be fully qualified, e.g. `/chatter`.
+  pub fn topic(mut self, name: &str) -> Self {
+    self.topics.insert(name.to_owned());
+    self
+  }
+
+  /// Record all Topics whose fully qualified name matches `regex` anywhere.
+  /// Anchor it with `^` and `$` to match whole names. `.*` records all
+  /// Topics, like `ros2 bag record --all`.
+  pub fn topics_matching(self, regex: Regex) -> Self {
+    RecorderOptions {
+      regex: Some(regex),
+      ..self
+    }
+  }
+
+  /// QoS of the Subscriptions. The default is best effort, so that both
+  /// reliable and best effort Publishers are recorded, with a history depth
+  /// of 100.
+  pub fn qos(self, qos: QosPolicies) -> Self {
+    RecorderOptions {
+      qos: Some(qos),
+      ..self
+    }
+  }
+
+  /// How often the ROS 2 Graph is checked for new Topics to record. The
+  /// default is one second.
+  pub fn discovery_period(self, period: Duration) -> Self {
+    RecorderOptions {
+      discovery_period: period,
+      ..self
+    }
+  }
+
+  fn selects(&self, topic_name: &str) -> bool {
+    self.topics.contains(topic_name)
+      || self
+        .regex
+        .as_ref()
+        .is_some_and(|regex| regex.is_match(topic_name))
+  }
+}
+
+/// Records Topics to a rosbag2 bag, see [module documentation](self).
+///
+/// Messages are recorded while [`Self::run`] is running. The bag is complete
+/// after [`Self::finish`], or when the Recorder is dropped.
+pub struct Recorder {
+  options: RecorderOptions,
+  writer: BagWriter,
+  // Topic name to topic id in the bag and the Subscription
+  subscriptions: BTreeMap<String, (i64, Subscription<SerializedMessage, EncapsulatedAdapter>)>,
+  // Selected Topics that cannot be recorded, so that they are warned about only
+  // once
+  ignored: BTreeSet<String>,
+}
+
+impl Recorder {
+  /// Creates the bag directory `directory`, which must not exist yet, and the
+  /// database in it.
+  pub fn new(directory: impl AsRef<Path>, options: RecorderOptions) -> Result<Recorder, BagError> {
+    Ok(Recorder {
+      options,
+      writer: BagWriter::create(directory.as_ref())?,
+      subscriptions: BTreeMap::new(),
+      ignored: BTreeSet::new(),
+    })
+  }
+
+  /// Subscribes to the selected Topics that are in the ROS 2 Graph but not
+  /// yet recorded. Returns the number of new Topics.
+  ///
+  /// This is done periodically by [`Self::run`].
+  pub fn update_subscriptions(&mut self, node: &mut Node) -> Result<usize, BagError> {
+    let qos = self.options.qos.clone().unwrap_or_else(default_qos);
+    let mut added = 0;
+    for (topic_name, type_names) in node.get_topic_names_and_types() {
+      if self.subscriptions.contains_key(&topic_name)
+        || self.ignored.contains(&topic_name)
+        || !self.options.selects(&topic_name)
+      {
+        continue;
+      }
+      let (type_name, message_type) = match &type_names[..] {
+        [t] => match message_type_name(t) {
+          Some(message_type) => (t, message_type),
+          None => {
+            warn!("Recorder: Topic {topic_name} has unknown kind of type {t}, not recording");
+            self.ignored.insert(topic_name);
+            continue;
+          }
+        },
+        _ => {
+          warn!("Recorder: Topic {topic_name} has several types {type_names:?}, not recording");
+          self.ignored.insert(topic_name);
+          continue;
+        }
+      };
+      let name = match Name::parse(&topic_name) {
+        Ok(name) => name,
+        Err(e) => {
+          warn!("Recorder: cannot record Topic {topic_name}: {e}");
+          self.ignored.insert(topic_name);
+          continue;
+        }
+      };
+      let topic = node.create_topic(&name, message_type, &qos)?;
+      let subscription = node
+        .create_subscription_with_adapter::<SerializedMessage, EncapsulatedAdapter>(
+          &topic,
+          Some(qos.clone()),
+        )?;
+      let topic_id = self.writer.add_topic(&topic_name, type_name)?;
+      info!("Recorder: recording Topic {topic_name} of type {type_name}");
+      self
+        .subscriptions
+        .insert(topic_name, (topic_id, subscription));
+      added += 1;
+    }
+    Ok(added)
+  }
+
+  /// Records messages. This returns only on error, so it should be run as a
+  /// task, or until some other future completes.
+  ///
+  /// Messages are timestamped on arrival with the Clock of `node`, so
+  /// simulated time is followed if `use_sim_time` is set. Receive errors are
+  /// logged and skipped.
+  pub async fn run(&mut self, node: &mut Node) -> Result<(), BagError> {
+    let clock = node.get_clock();
+    loop {
+      self.update_subscriptions(node)?;
+      // The Subscriptions are borrowed by the merged stream, so new Topics
+      // are subscribed to only after the period has ended.
+      let Recorder {
+        options,
+        writer,
+        subscriptions,
+        ..
+      } = self;
+      let mut messages = stream::select_all(subscriptions.values().map(|(topic_id, s)| {
+        let topic_id = *topic_id;
+        Box::pin(s.async_stream().map(move |result| (topic_id, result)))
+      }));
+      let mut period_end = FutureExt::fuse(Timer::after(options.discovery_period));
+      loop {
+        futures::select! {
+          _ = period_end => break,
+          item = messages.next() => match item {
+            Some((topic_id, Ok((message, _info)))) => {
+              writer.write(topic_id, clock.now().to_nanos(), &message.0)?;
+            }
+            Some((_, Err(e))) => warn!("Recorder: receive failed: {e:?}"),
+            // No Topics yet
+            None => {
+              (&mut period_end).await;
+              break;
+            }
+          }
+        }
+      }
+    }
+  }
+
+  /// Number of messages recorded so far
+  pub fn message_count(&self) -> u64 {
+    self.writer.topics.iter().map(|t| t.message_count).sum()
+  }
+
+  /// Names of the Topics being recorded
+  pub fn topic_names(&self) -> impl Iterator<Item = &str> + '_ {
+    self.subscriptions.keys().map(String::as_str)
+  }
+
+  /// Path of the bag directory
+  pub fn directory(&self) -> &Path {
+    &self.writer.directory
+  }
+
+  /// Writes the metadata and closes the bag.
+  pub fn finish(mut self) -> Result<(), BagError> {
+    self.writer.finish()
+  }
+}
+
+fn default_qos() -> QosPolicies {
+  QosPolicyBuilder::new()
+    .reliability(Reliability::BestEffort)
+    .durability(Durability::Volatile)
+    .history(History::KeepLast { depth: 100 })
+    .build()
+}
+
+// E.g. "std_msgs/msg/String". Action feedback Topics have types like
+// "example_interfaces/action/Fibonacci_FeedbackMessage".
+fn message_type_name(type_name: &str) -> Option<MessageTypeName> {
+  match type_name.split('/').collect::<Vec<_>>()[..] {
+    [package, "msg", t] => Some(MessageTypeName::new(package, t)),
+    [package, "action", t] => Some(ActionTypeName::new(package, "").dds_action_topic(t)),
+    _ => None,
+  }
+}
+
+// Receives messages as they are stored in a bag: the serialized payload
+// preceded by its encapsulation header, whatever the encoding.
+struct EncapsulatedAdapter;
+
+impl no_key::DeserializerAdapter<SerializedMessage> for EncapsulatedAdapter {
+  type Error = DecodeError;
+  type Decoded = SerializedMessage;
+
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &REPR_IDS
+  }
+
+  fn transform_decoded(decoded: Self::Decoded) -> SerializedMessage {
+    decoded
+  }
+}
+
+impl no_key::DefaultDecoder<SerializedMessage> for EncapsulatedAdapter {
+  type Decoder = EncapsulatedDecoder;
+  const DECODER: Self::Decoder = EncapsulatedDecoder(PhantomData);
+}
+
+#[derive(Clone)]
+struct EncapsulatedDecoder(PhantomData<()>);
+
+impl no_key::Decode<SerializedMessage> for EncapsulatedDecoder {
+  type Error = DecodeError;
+
+  fn decode_bytes(
+    self,
+    input_bytes: &[u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<SerializedMessage, DecodeError> {
+    // RustDDS does not pass on the options, i.e. the padding at the end.
+    let mut bytes = Vec::with_capacity(4 + input_bytes.len());
+    bytes.extend_from_slice(&encoding.to_bytes());
+    bytes.extend_from_slice(&[0, 0]);
+    bytes.extend_from_slice(input_bytes);
+    Ok(SerializedMessage(bytes))
+  }
+}
+
+// As created by rosbag2_storage_sqlite3 of ROS 2 Iron
+const SCHEMA_VERSION: i64 = 4;
+const SCHEMA: &str = "
+  CREATE TABLE schema(schema_version INTEGER PRIMARY KEY, ros_distro TEXT NOT NULL);
+  CREATE TABLE metadata(id INTEGER PRIMARY KEY, metadata_version INTEGER NOT NULL,
+    metadata TEXT NOT NULL);
+  CREATE TABLE topics(id INTEGER PRIMARY KEY, name TEXT NOT NULL, type TEXT NOT NULL,
+    serialization_format TEXT NOT NULL, offered_qos_profiles TEXT NOT NULL,
+    type_description_hash TEXT NOT NULL);
+  CREATE TABLE message_definitions(id INTEGER PRIMARY KEY, topic_type TEXT NOT NULL,
+    encoding TEXT NOT NULL, encoded_message_definition TEXT NOT NULL,
+    type_description_hash TEXT NOT NULL);
+  CREATE TABLE messages(id INTEGER PRIMARY KEY, topic_id INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL, data BLOB NOT NULL);
+  CREATE INDEX timestamp_idx ON messages (timestamp ASC);
+";
+
+const METADATA_VERSION: u32 = 8;
+const METADATA_FILE: &str = "metadata.yaml";
+const SERIALIZATION_FORMAT: &str = "cdr";
+
+// ROS 2 default QoS in the form written by rosbag2. Infinite durations are
+// RMW_DURATION_INFINITE.
+const OFFERED_QOS_PROFILES: &str = "- history: 1
+  depth: 10
+  reliability: 1
+  durability: 2
+  deadline:
+    sec: 9223372036
+    nsec: 854775807
+  lifespan:
+    sec: 9223372036
+    nsec: 854775807
+  liveliness: 1
+  liveliness_lease_duration:
+    sec: 9223372036
+    nsec: 854775807
+  avoid_ros_namespace_conventions: false";
+
+// metadata.yaml, version 8
+#[derive(Debug, Serialize, Deserialize)]
+struct MetadataFile {
+  rosbag2_bagfile_information: BagMetadata,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BagMetadata {
+  version: u32,
+  storage_identifier: String,
+  duration: Nanoseconds,
+  starting_time: NanosecondsSinceEpoch,
+  message_count: u64,
+  topics_with_message_count: Vec<TopicInformation>,
+  compression_format: String,
+  compression_mode: String,
+  relative_file_paths: Vec<String>,
+  files: Vec<FileInformation>,
+  custom_data: BTreeMap<String, String>,
+  ros_distro: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Nanoseconds {
+  nanoseconds: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NanosecondsSinceEpoch {
+  nanoseconds_since_epoch: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TopicInformation {
+  topic_metadata: TopicMetadata,
+  message_count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TopicMetadata {
+  name: String,
+  #[serde(rename = "type")]
+  type_name: String,
+  serialization_format: String,
+  offered_qos_profiles: String,
+  type_description_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FileInformation {
+  path: String,
+  starting_time: NanosecondsSinceEpoch,
+  duration: Nanoseconds,
+  message_count: u64,
+}
+
+struct RecordedTopic {
+  name: String,
+  type_name: String,
+  message_count: u64,
+}
+
+// Writes the database and metadata of a bag. Kept apart from the Recorder so
+// that it can be tested without DDS.
+struct BagWriter {
+  directory: PathBuf,
+  file_name: String,
+  db: Connection,
+  ros_distro: String,
+  // Index is topic id - 1.
+  topics: Vec<RecordedTopic>,
+  starting_time: Option<i64>,
+  ending_time: i64,
+  finished: bool,
+}
+
+impl BagWriter {
+  fn create(directory: &Path) -> Result<Self, BagError> {
+    // Like rosbag2, refuse to overwrite an existing bag.
+    fs::create_dir(directory)?;
+    let bag_name = directory
+      .file_name()
+      .map_or("rosbag2".into(), |n| n.to_string_lossy());
+    let file_name = format!("{bag_name}_0.db3");
+    let db = Connection::open(directory.join(&file_name))?;
+    // The defaults of rosbag2, favouring speed over resilience
+    db.pragma_update(None, "journal_mode", "MEMORY")?;
+    db.pragma_update(None, "synchronous", "OFF")?;
+    db.execute_batch(SCHEMA)?;
+    let ros_distro = std::env::var("ROS_DISTRO").unwrap_or_else(|_| "iron".to_owned());
+    db.execute(
+      "INSERT INTO schema (schema_version, ros_distro) VALUES (?1, ?2)",
+      params![SCHEMA_VERSION, ros_distro],
+    )?;
+    Ok(BagWriter {
+      directory: directory.to_owned(),
+      file_name,
+      db,
+      ros_distro,
+      topics: Vec::new(),
+      starting_time: None,
+      ending_time: 0,
+      finished: false,
+    })
+  }
+
+  // Returns the topic id.
+  fn add_topic(&mut self, name: &str, type_name: &str) -> Result<i64, BagError> {
+    self.db.execute(
+      "INSERT INTO topics (name, type, serialization_format, offered_qos_profiles, \
+       type_description_hash) VALUES (?1, ?2, ?3, ?4, '')",
+      params![name, type_name, SERIALIZATION_FORMAT, OFFERED_QOS_PROFILES],
+    )?;
+    self.topics.push(RecordedTopic {
+      name: name.to_owned(),
+      type_name: type_name.to_owned(),
+      message_count: 0,
+    });
+    Ok(self.db.last_insert_rowid())
+  }
+
+  // `timestamp` is in nanoseconds since the epoch, and `data` includes the
+  // encapsulation header.
+  fn write(&mut self, topic_id: i64, timestamp: i64, data: &[u8]) -> Result<(), BagError> {
+    self
+      .db
+      .prepare_cached("INSERT INTO messages (topic_id, timestamp, data) VALUES (?1, ?2, ?3)")?
+      .execute(params![topic_id, timestamp, data])?;
+    if let Some(topic) = usize::try_from(topic_id - 1)
+      .ok()
+      .and_then(|i| self.topics.get_mut(i))
+    {
+      topic.message_count += 1;
+    }
+    let starting_time = *self.starting_time.get_or_insert(timestamp);
+    self.starting_time = Some(starting_time.min(timestamp));
+    self.ending_time = self.ending_time.max(timestamp);
+    Ok(())
+  }
+
+  fn metadata(&self) -> MetadataFile {
+    let starting_time = self.starting_time.unwrap_or(0);
+    let duration = self.ending_time.saturating_sub(starting_time).max(0);
+    let message_count = self.topics.iter().map(|t| t.message_count).sum();
+    MetadataFile {
+      rosbag2_bagfile_information: BagMetadata {
+        version: METADATA_VERSION,
+        storage_identifier: "sqlite3".to_owned(),
+        duration: Nanoseconds {
+          nanoseconds: duration,
+        },
+        starting_time: NanosecondsSinceEpoch {
+          nanoseconds_since_epoch: starting_time,
+        },
+        message_count,
+        topics_with_message_count: self
+          .topics
+          .iter()
+          .map(|t| TopicInformation {
+            topic_metadata: TopicMetadata {
+              name: t.name.clone(),
+              type_name: t.type_name.clone(),
+              serialization_format: SERIALIZATION_FORMAT.to_owned(),
+              offered_qos_profiles: OFFERED_QOS_PROFILES.to_owned(),
+              type_description_hash: String::new(),
+            },
+            message_count: t.message_count,
+          })
+          .collect(),
+        compression_format: String::new(),
+        compression_mode: String::new(),
+        relative_file_paths: vec![self.file_name.clone()],
+        files: vec![FileInformation {
+          path: self.file_name.clone(),
+          starting_time: NanosecondsSinceEpoch {
+            nanoseconds_since_epoch: starting_time,
+          },
+          duration: Nanoseconds {
+            nanoseconds: duration,
+          },
+          message_count,
+        }],
+        custom_data: BTreeMap::new(),
+        ros_distro: self.ros_distro.clone(),
+      },
+    }
+  }
+
+  // Writes the metadata to the database and to metadata.yaml.
+  fn finish(&mut self) -> Result<(), BagError> {
+    self.finished = true;
+    let metadata = serde_yaml::to_string(&self.metadata())?;
+    self.db.execute(
+      "INSERT INTO metadata (metadata_version, metadata) VALUES (?1, ?2)",
+      params![METADATA_VERSION, metadata],
+    )?;
+    fs::write(self.directory.join(METADATA_FILE), metadata)?;
+    Ok(())
+  }
+}
+
+impl Drop for BagWriter {
+  fn drop(&mut self) {
+    if !self.finished {
+      if let Err(e) = self.finish() {
+        error!("Cannot finish bag {:?}: {e}", self.directory);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn writes_rosbag2_sqlite3() {
+    let dir = std::env::temp_dir().join(format!("ros2_client_bag_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let mut writer = BagWriter::create(&dir).unwrap();
+    assert!(BagWriter::create(&dir).is_err());
+    let chatter = writer.add_topic("/chatter", "std_msgs/msg/String").unwrap();
+    let count = writer.add_topic("/count", "std_msgs/msg/Int32").unwrap();
+    writer
+      .write(chatter, 2_000, &[0, 1, 0, 0, 1, 0, 0, 0, 0])
+      .unwrap();
+    writer
+      .write(count, 5_000, &[0, 1, 0, 0, 7, 0, 0, 0])
+      .unwrap();
+    writer
+      .write(chatter, 3_000, &[0, 1, 0, 0, 1, 0, 0, 0, 0])
+      .unwrap();
+    writer.finish().unwrap();
+    drop(writer);
+
+    let file = dir.join(format!("ros2_client_bag_{}_0.db3", std::process::id()));
+    let db = Connection::open(file).unwrap();
+    let topics: Vec<(i64, String, String)> = db
+      .prepare("SELECT id, name, type FROM topics ORDER BY id")
+      .unwrap()
+      .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+      .unwrap()
+      .collect::<Result<_, _>>()
+      .unwrap();
+    assert_eq!(
+      topics,
+      vec![
+        (
+          chatter,
+          "/chatter".to_owned(),
+          "std_msgs/msg/String".to_owned()
+        ),
+        (count, "/count".to_owned(), "std_msgs/msg/Int32".to_owned()),
+      ]
+    );
+    let (topic_id, data): (i64, Vec<u8>) = db
+      .query_row(
+        "SELECT topic_id, data FROM messages ORDER BY timestamp DESC LIMIT 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+      )
+      .unwrap();
+    assert_eq!((topic_id, data), (count, vec![0, 1, 0, 0, 7, 0, 0, 0]));
+    let schema_version: i64 = db
+      .query_row("SELECT schema_version FROM schema", [], |row| row.get(0))
+      .unwrap();
+    assert_eq!(schema_version, SCHEMA_VERSION);
+    let stored: String = db
+      .query_row("SELECT metadata FROM metadata", [], |row| row.get(0))
+      .unwrap();
+
+    let yaml = fs::read_to_string(dir.join(METADATA_FILE)).unwrap();
+    assert_eq!(yaml, stored);
+    let info = serde_yaml::from_str::<MetadataFile>(&yaml)
+      .unwrap()
+      .rosbag2_bagfile_information;
+    assert_eq!(info.version, METADATA_VERSION);
+    assert_eq!(info.storage_identifier, "sqlite3");
+    assert_eq!(info.message_count, 3);
+    assert_eq!(info.starting_time.nanoseconds_since_epoch, 2_000);
+    assert_eq!(info.duration.nanoseconds, 3_000);
+    assert_eq!(info.topics_with_message_count[0].message_count, 2);
+    assert_eq!(
+      info.topics_with_message_count[1].topic_metadata.name,
+      "/count"
+    );
+    assert_eq!(info.relative_file_paths, vec![info.files[0].path.clone()]);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn topic_selection() {
+    let options = RecorderOptions::new()
+      .topic("/chatter")
+      .topics_matching(Regex::new("^/robot/").unwrap());
+    assert!(options.selects("/chatter"));
+    assert!(options.selects("/robot/odom"));
+    assert!(!options.selects("/chatter2"));
+    assert!(!options.selects("/other/robot/odom"));
+    assert_eq!(
+      message_type_name("std_msgs/msg/String")
+        .unwrap()
+        .dds_msg_type(),
+      "std_msgs::msg::dds_::String_"
+    );
+    assert_eq!(
+      message_type_name("example_interfaces/action/Fibonacci_FeedbackMessage")
+        .unwrap()
+        .dds_msg_type(),
+      "example_interfaces::action::dds_::Fibonacci_FeedbackMessage_"
+    );
+    assert!(message_type_name("example_interfaces/srv/AddTwoInts").is_none());
+  }
+}
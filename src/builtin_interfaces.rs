@@ -35,7 +35,7 @@ use crate::{message::Message, ros_time::ROSTime};
 ///
 /// The most useful things to do with these is send in a [`Message`] or
 /// convert into a `ROSTime`.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 #[serde(from = "repr::Time", into = "repr::Time")]
 pub struct Time {
   /// Nanoseconds since the Unix epoch
@@ -225,7 +225,7 @@ impl From<Time> for ROSTime {
 /// To actually compute a time difference, use types [`ROSTime`] and
 /// [`ROSDuration`](crate::ros_time::ROSDuration), and convert to [`Duration`]
 /// for sending in a [`Message`].
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize, Debug)]
 pub struct Duration {
   sec: i32,     // ROS2: Seconds component, range is valid over any possible int32 value.
   nanosec: u32, /* ROS2:  Nanoseconds component in the range of [0, 10e9). */
@@ -321,7 +321,7 @@ mod test {
 
   #[test]
   fn repr_conversion() {
-    repr_conv_test(Time::from_nanos(0_999_999_999));
+    repr_conv_test(Time::from_nanos(999_999_999));
     repr_conv_test(Time::from_nanos(1_000_000_000));
     repr_conv_test(Time::from_nanos(1_000_000_001));
 
@@ -329,7 +329,7 @@ mod test {
     repr_conv_test(Time::from_nanos(2_000_000_000));
     repr_conv_test(Time::from_nanos(2_000_000_001));
 
-    repr_conv_test(Time::from_nanos(-0_999_999_999));
+    repr_conv_test(Time::from_nanos(-999_999_999));
     repr_conv_test(Time::from_nanos(-1_000_000_000));
     repr_conv_test(Time::from_nanos(-1_000_000_001));
 
@@ -34,10 +34,12 @@ pub mod parameter_events {
   use super::*;
 
   lazy_static! {
+    // Same as rmw_qos_profile_parameter_events. A TransientLocal Reader would
+    // not match ROS 2 Writers.
     pub static ref QOS: QosPolicies = QosPolicyBuilder::new()
-      .durability(Durability::TransientLocal)
+      .durability(Durability::Volatile)
       .reliable(Duration::ZERO)
-      .history(History::KeepLast { depth: 1 })
+      .history(History::KeepLast { depth: 1000 })
       .build();
   }
 
@@ -0,0 +1,115 @@
+//! Decoding received messages in any CDR encapsulation
+//!
+//! RustDDS `CDRDeserializerAdapter` reads only XCDR version 1. This reads also
+//! XCDR version 2, which newer DDS implementations may send. The byte order
+//! and CDR version are taken from the encapsulation header of each message, so
+//! Publishers may differ in them.
+
+use std::marker::PhantomData;
+
+use rustdds::{
+  no_key,
+  serialization::{deserialize_from_cdr_with_decoder_and_rep_id, Error},
+  RepresentationIdentifier,
+};
+use ros2_client_core::cdr::{self, Encapsulation};
+use serde::de::{Deserialize, DeserializeSeed};
+
+/// Default deserializer adapter of [`Subscription`](crate::Subscription)
+pub struct CdrAdapter<D> {
+  phantom: PhantomData<D>,
+}
+
+pub(crate) const REPR_IDS: [RepresentationIdentifier; 7] = [
+  RepresentationIdentifier::CDR_BE,
+  RepresentationIdentifier::CDR_LE,
+  // As in RustDDS CDRDeserializerAdapter, though ROS 2 does not use it
+  RepresentationIdentifier::PL_CDR_LE,
+  RepresentationIdentifier::XCDR2_BE,
+  RepresentationIdentifier::XCDR2_LE,
+  RepresentationIdentifier::D_CDR2_BE,
+  RepresentationIdentifier::D_CDR2_LE,
+];
+
+impl<D> no_key::DeserializerAdapter<D> for CdrAdapter<D> {
+  type Error = Error;
+  type Decoded = D;
+
+  fn supported_encodings() -> &'static [RepresentationIdentifier] {
+    &REPR_IDS
+  }
+
+  fn transform_decoded(decoded: Self::Decoded) -> D {
+    decoded
+  }
+}
+
+impl<'de, D> no_key::DefaultDecoder<D> for CdrAdapter<D>
+where
+  D: Deserialize<'de>,
+{
+  type Decoder = CdrDecoder<PhantomData<D>>;
+  const DECODER: Self::Decoder = CdrDecoder(PhantomData);
+}
+
+/// Decodes with a [`DeserializeSeed`]
+#[derive(Clone)]
+pub struct CdrDecoder<S>(S);
+
+impl<S> CdrDecoder<S> {
+  pub fn new(seed: S) -> Self {
+    CdrDecoder(seed)
+  }
+}
+
+impl<'de, D, S> no_key::Decode<D> for CdrDecoder<S>
+where
+  S: DeserializeSeed<'de, Value = D>,
+{
+  type Error = Error;
+
+  fn decode_bytes(
+    self,
+    input_bytes: &[u8],
+    encoding: RepresentationIdentifier,
+  ) -> Result<D, Error> {
+    match Encapsulation::from_representation_id(encoding.to_bytes()) {
+      Some(encapsulation @ Encapsulation::Cdr2(_))
+      | Some(encapsulation @ Encapsulation::DelimitedCdr2(_)) => {
+        cdr::from_encapsulated_bytes_seed(input_bytes, encapsulation, self.0)
+          .map(|(value, _)| value)
+          .map_err(|e| Error::Message(e.to_string()))
+      }
+      _ => deserialize_from_cdr_with_decoder_and_rep_id(input_bytes, encoding, self.0)
+        .map(|(value, _)| value),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rustdds::no_key::Decode;
+
+  use super::*;
+
+  #[test]
+  fn byte_order_and_version_per_message() {
+    let decode = |bytes: &[u8], encoding| {
+      CdrDecoder(PhantomData::<(u16, f64)>)
+        .decode_bytes(bytes, encoding)
+        .unwrap()
+    };
+    let le = [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xf0, 0x3f];
+    let be = [0, 1, 0, 0, 0, 0, 0, 0, 0x3f, 0xf0, 0, 0, 0, 0, 0, 0];
+    assert_eq!(decode(&le, RepresentationIdentifier::CDR_LE), (1, 1.0));
+    assert_eq!(decode(&be, RepresentationIdentifier::CDR_BE), (1, 1.0));
+    // XCDR2 aligns the f64 to 4 bytes.
+    let le2 = [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xf0, 0x3f];
+    assert_eq!(decode(&le2, RepresentationIdentifier::XCDR2_LE), (1, 1.0));
+    let delimited = [0, 0, 0, 12, 0, 1, 0, 0, 0x3f, 0xf0, 0, 0, 0, 0, 0, 0];
+    assert_eq!(
+      decode(&delimited, RepresentationIdentifier::D_CDR2_BE),
+      (1, 1.0)
+    );
+  }
+}
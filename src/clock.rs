@@ -0,0 +1,490 @@
+//! Clocks: ROS time, system time and steady time
+//!
+//! ROS time is simulated time, when the Node Parameter `use_sim_time` is set,
+//! and system time otherwise. Simulated time is received from the `/clock`
+//! Topic, e.g. from Gazebo or `ros2 bag play --clock`, by the
+//! [`TimeSource`] of a Node. Get the ROS time Clock of a Node with
+//! [`Node::get_clock`](crate::Node::get_clock).
+//!
+//! Code that must react to jumps of simulated time, e.g. a rosbag restarting
+//! from the beginning, can register callbacks with
+//! [`Clock::create_jump_callback`].
+
+use std::{
+  convert::TryFrom,
+  sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    Arc, Mutex, Weak,
+  },
+  time::{Duration, Instant},
+};
+
+use futures::{future::BoxFuture, pin_mut, FutureExt, StreamExt};
+use rustdds::{Topic, TopicDescription};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  builtin_interfaces,
+  context::Context,
+  ros_time::{ROSDuration, ROSTime},
+};
+
+lazy_static! {
+  // Origin of steady time
+  static ref STEADY_ORIGIN: Instant = Instant::now();
+}
+
+// How often waits in simulated time check the time. Simulated time is updated
+// by the Spinner, which does not notify the waiters.
+pub(crate) const SIM_TIME_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Called before a jump of a [`Clock`]
+pub type PreJumpCallback = dyn FnMut() + Send;
+/// Called after a jump of a [`Clock`]
+pub type PostJumpCallback = dyn FnMut(&TimeJump) + Send;
+
+/// Kinds of [`Clock`], as in rcl
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockType {
+  /// Simulated time when active, and system time otherwise
+  RosTime,
+  /// The system (wall-clock) time. This may jump, e.g. when adjusted by NTP.
+  SystemTime,
+  /// Monotonic time from an arbitrary origin. This is the process start, or
+  /// close to it.
+  SteadyTime,
+}
+
+/// Reads the current time of one [`ClockType`]
+///
+/// Clones read the same time.
+#[derive(Clone)]
+pub struct Clock {
+  clock_type: ClockType,
+  // Only for RosTime
+  time_source: Option<TimeSource>,
+}
+
+impl Clock {
+  /// A Clock that is not attached to a Node. Such a `RosTime` Clock always
+  /// reads system time.
+  pub fn new(clock_type: ClockType) -> Clock {
+    Clock {
+      clock_type,
+      time_source: None,
+    }
+  }
+
+  // A RosTime Clock that follows `time_source`.
+  pub(crate) fn ros_time(time_source: TimeSource) -> Clock {
+    Clock {
+      clock_type: ClockType::RosTime,
+      time_source: Some(time_source),
+    }
+  }
+
+  pub fn clock_type(&self) -> ClockType {
+    self.clock_type
+  }
+
+  /// Is this Clock reading simulated time?
+  pub fn ros_time_is_active(&self) -> bool {
+    self
+      .time_source
+      .as_ref()
+      .is_some_and(TimeSource::is_ros_time_active)
+  }
+
+  /// The current time. Steady time counts from its origin, not from the Unix
+  /// epoch.
+  pub fn now(&self) -> ROSTime {
+    match (self.clock_type, &self.time_source) {
+      (ClockType::RosTime, Some(time_source)) => time_source.now(),
+      (ClockType::RosTime, None) | (ClockType::SystemTime, _) => ROSTime::now(),
+      (ClockType::SteadyTime, _) => {
+        ROSTime::from_nanos(i64::try_from(STEADY_ORIGIN.elapsed().as_nanos()).unwrap_or(i64::MAX))
+      }
+    }
+  }
+
+  /// Waits until this Clock reads `until`, or later.
+  ///
+  /// Returns `false` if the time jumped backwards during the wait, e.g. when
+  /// a rosbag restarts from the beginning, and `true` otherwise.
+  pub async fn sleep_until(&self, until: ROSTime) -> bool {
+    let start = self.now();
+    loop {
+      let now = self.now();
+      if now >= until {
+        return true;
+      }
+      if now < start {
+        return false;
+      }
+      let wait = Duration::from_nanos((until.to_nanos() - now.to_nanos()) as u64);
+      let wait = if self.ros_time_is_active() {
+        wait.min(SIM_TIME_POLL_INTERVAL)
+      } else {
+        wait
+      };
+      async_io::Timer::after(wait).await;
+    }
+  }
+
+  /// Calls `pre_callback` just before, and `post_callback` just after, each
+  /// jump of this Clock that exceeds `threshold`.
+  ///
+  /// Only ROS time Clocks of a Node jump, when simulated time jumps, or when
+  /// the Parameter `use_sim_time` changes. For other Clocks, the callbacks
+  /// are never called. The callbacks are removed when the returned
+  /// [`JumpHandler`] is dropped.
+  pub fn create_jump_callback(
+    &self,
+    threshold: JumpThreshold,
+    pre_callback: Option<Box<PreJumpCallback>>,
+    post_callback: Option<Box<PostJumpCallback>>,
+  ) -> JumpHandler {
+    let callback = JumpCallback {
+      threshold,
+      pre_callback,
+      post_callback,
+    };
+    match &self.time_source {
+      Some(time_source) => time_source.add_jump_callback(callback),
+      None => JumpHandler {
+        id: 0,
+        time_source: Weak::new(),
+      },
+    }
+  }
+
+  /// Waits for `duration` in this Clock. See [`Self::sleep_until`].
+  pub async fn sleep_for(&self, duration: Duration) -> bool {
+    let duration = i64::try_from(duration.as_nanos()).unwrap_or(i64::MAX);
+    let until = ROSTime::from_nanos(self.now().to_nanos().saturating_add(duration));
+    self.sleep_until(until).await
+  }
+}
+
+/// Which jumps of a [`Clock`] call a jump callback
+///
+/// The default is no jumps at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct JumpThreshold {
+  /// Call on activation and deactivation of ROS time.
+  pub on_clock_change: bool,
+  /// Call on forward jumps of at least this much. `None` disables these.
+  pub min_forward: Option<Duration>,
+  /// Call on backward jumps of at least this much. `None` disables these.
+  pub min_backward: Option<Duration>,
+}
+
+impl JumpThreshold {
+  fn is_exceeded_by(&self, jump: &TimeJump) -> bool {
+    let delta = jump.delta.to_nanos();
+    let at_least = |min: Option<Duration>, delta: i64| {
+      min.is_some_and(|min| i128::from(delta) >= min.as_nanos() as i128)
+    };
+    match jump.clock_change {
+      ClockChange::RosTimeNoChange => {
+        (delta > 0 && at_least(self.min_forward, delta))
+          || (delta < 0 && at_least(self.min_backward, -delta))
+      }
+      ClockChange::RosTimeActivated | ClockChange::RosTimeDeactivated => self.on_clock_change,
+    }
+  }
+}
+
+/// What kind of jump a [`TimeJump`] is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockChange {
+  /// Simulated time jumped.
+  RosTimeNoChange,
+  /// The Clock switched from system time to simulated time.
+  RosTimeActivated,
+  /// The Clock switched from simulated time to system time.
+  RosTimeDeactivated,
+}
+
+/// A jump of a [`Clock`], given to post-jump callbacks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeJump {
+  pub clock_change: ClockChange,
+  /// New time minus the old time
+  pub delta: ROSDuration,
+}
+
+struct JumpCallback {
+  threshold: JumpThreshold,
+  pre_callback: Option<Box<PreJumpCallback>>,
+  post_callback: Option<Box<PostJumpCallback>>,
+}
+
+/// Keeps jump callbacks registered. Dropping this removes them.
+///
+/// See [`Clock::create_jump_callback`].
+pub struct JumpHandler {
+  id: u64,
+  time_source: Weak<TimeSourceInner>,
+}
+
+impl Drop for JumpHandler {
+  fn drop(&mut self) {
+    if let Some(inner) = self.time_source.upgrade() {
+      inner
+        .jump_callbacks
+        .lock()
+        .unwrap()
+        .retain(|(id, _)| *id != self.id);
+    }
+  }
+}
+
+/// Keeps the simulated time of a Node
+///
+/// While ROS time is active, i.e. the Parameter `use_sim_time` is set, the
+/// Spinner of the Node subscribes to `/clock`, and updates this from it.
+/// Until the first `/clock` message arrives, simulated time is zero.
+#[derive(Clone)]
+pub struct TimeSource {
+  inner: Arc<TimeSourceInner>,
+}
+
+struct TimeSourceInner {
+  ros_time_active: AtomicBool,
+  sim_time: Mutex<ROSTime>,
+  // Notifies the /clock task of changes to `ros_time_active`.
+  changed_sender: async_channel::Sender<()>,
+  changed_receiver: async_channel::Receiver<()>,
+  // Each callback has its own lock, so that callbacks can add and remove
+  // callbacks.
+  jump_callbacks: Mutex<Vec<(u64, Arc<Mutex<JumpCallback>>)>>,
+  next_jump_callback_id: AtomicU64,
+}
+
+impl TimeSource {
+  pub(crate) fn new() -> TimeSource {
+    let (changed_sender, changed_receiver) = async_channel::bounded(1);
+    TimeSource {
+      inner: Arc::new(TimeSourceInner {
+        ros_time_active: AtomicBool::new(false),
+        sim_time: Mutex::new(ROSTime::ZERO),
+        changed_sender,
+        changed_receiver,
+        jump_callbacks: Mutex::new(Vec::new()),
+        next_jump_callback_id: AtomicU64::new(1),
+      }),
+    }
+  }
+
+  pub fn is_ros_time_active(&self) -> bool {
+    self.inner.ros_time_active.load(Ordering::SeqCst)
+  }
+
+  /// Simulated time, if ROS time is active, and system time otherwise
+  pub fn now(&self) -> ROSTime {
+    if self.is_ros_time_active() {
+      *self.inner.sim_time.lock().unwrap()
+    } else {
+      ROSTime::now()
+    }
+  }
+
+  pub(crate) fn set_ros_time_active(&self, active: bool) {
+    if active == self.is_ros_time_active() {
+      return;
+    }
+    let sim_time = *self.inner.sim_time.lock().unwrap();
+    let system_time = ROSTime::now();
+    let jump = if active {
+      TimeJump {
+        clock_change: ClockChange::RosTimeActivated,
+        delta: sim_time - system_time,
+      }
+    } else {
+      TimeJump {
+        clock_change: ClockChange::RosTimeDeactivated,
+        delta: system_time - sim_time,
+      }
+    };
+    self.jump(jump, || {
+      self.inner.ros_time_active.store(active, Ordering::SeqCst);
+    });
+    // If a notification is already pending, that is enough.
+    let _ = self.inner.changed_sender.try_send(());
+  }
+
+  pub(crate) fn set_sim_time(&self, time: ROSTime) {
+    let previous = *self.inner.sim_time.lock().unwrap();
+    if !self.is_ros_time_active() || time == previous {
+      *self.inner.sim_time.lock().unwrap() = time;
+      return;
+    }
+    let jump = TimeJump {
+      clock_change: ClockChange::RosTimeNoChange,
+      delta: time - previous,
+    };
+    self.jump(jump, || *self.inner.sim_time.lock().unwrap() = time);
+  }
+
+  // Applies a jump with `apply`, calling the jump callbacks that it concerns
+  // around it.
+  fn jump(&self, jump: TimeJump, apply: impl FnOnce()) {
+    let callbacks = self
+      .inner
+      .jump_callbacks
+      .lock()
+      .unwrap()
+      .iter()
+      .filter(|(_, c)| c.lock().unwrap().threshold.is_exceeded_by(&jump))
+      .map(|(_, c)| Arc::clone(c))
+      .collect::<Vec<_>>();
+    for callback in &callbacks {
+      if let Some(pre_callback) = callback.lock().unwrap().pre_callback.as_mut() {
+        pre_callback();
+      }
+    }
+    apply();
+    for callback in &callbacks {
+      if let Some(post_callback) = callback.lock().unwrap().post_callback.as_mut() {
+        post_callback(&jump);
+      }
+    }
+  }
+
+  fn add_jump_callback(&self, callback: JumpCallback) -> JumpHandler {
+    let id = self
+      .inner
+      .next_jump_callback_id
+      .fetch_add(1, Ordering::Relaxed);
+    self
+      .inner
+      .jump_callbacks
+      .lock()
+      .unwrap()
+      .push((id, Arc::new(Mutex::new(callback))));
+    JumpHandler {
+      id,
+      time_source: Arc::downgrade(&self.inner),
+    }
+  }
+
+  // A task that subscribes to `clock_topic` while ROS time is active. This
+  // runs in the Spinner of the Node.
+  pub(crate) fn clock_task(
+    &self,
+    ros_context: Context,
+    clock_topic: Topic,
+  ) -> BoxFuture<'static, ()> {
+    let time_source = self.clone();
+    let changed = self.inner.changed_receiver.clone();
+    Box::pin(async move {
+      loop {
+        while !time_source.is_ros_time_active() {
+          // Cannot fail, as `time_source` holds the sender.
+          let _ = changed.recv().await;
+        }
+        let clock_reader =
+          match ros_context.create_subscription::<builtin_interfaces::Time>(&clock_topic, None) {
+            Ok(reader) => reader,
+            Err(e) => {
+              error!("Cannot subscribe to {}: {e:?}", clock_topic.name());
+              return;
+            }
+          };
+        debug!("ROS time active, subscribed to {}", clock_topic.name());
+        let clock_stream = clock_reader.async_stream();
+        pin_mut!(clock_stream);
+        while time_source.is_ros_time_active() {
+          futures::select! {
+            clock_msg = clock_stream.select_next_some() => match clock_msg {
+              Ok((time, _msg_info)) => time_source.set_sim_time(time.into()),
+              Err(e) => warn!("Simulated clock receive error {e:?}"),
+            },
+            _ = changed.recv().fuse() => {}
+          }
+        }
+        debug!(
+          "ROS time inactive, unsubscribing from {}",
+          clock_topic.name()
+        );
+      }
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn clock_types() {
+    let time_source = TimeSource::new();
+    let ros_clock = Clock::ros_time(time_source.clone());
+    assert!(!ros_clock.ros_time_is_active());
+    assert!(ros_clock.now() > ROSTime::from_nanos(1_600_000_000_000_000_000));
+
+    time_source.set_ros_time_active(true);
+    assert!(ros_clock.ros_time_is_active());
+    assert_eq!(ros_clock.now(), ROSTime::ZERO);
+    time_source.set_sim_time(ROSTime::from_nanos(42));
+    assert_eq!(ros_clock.now(), ROSTime::from_nanos(42));
+
+    let steady = Clock::new(ClockType::SteadyTime);
+    let t0 = steady.now();
+    assert!(steady.now() >= t0);
+    assert!(!Clock::new(ClockType::RosTime).ros_time_is_active());
+  }
+
+  #[test]
+  fn jump_callbacks() {
+    let time_source = TimeSource::new();
+    let clock = Clock::ros_time(time_source.clone());
+    let jumps = Arc::new(Mutex::new(Vec::new()));
+    let pre_jumps = Arc::new(AtomicU64::new(0));
+    let (jumps_in_callback, pre_jumps_in_callback) = (Arc::clone(&jumps), Arc::clone(&pre_jumps));
+    let handler = clock.create_jump_callback(
+      JumpThreshold {
+        on_clock_change: true,
+        min_forward: Some(Duration::from_secs(1)),
+        min_backward: Some(Duration::from_nanos(1)),
+      },
+      Some(Box::new(move || {
+        pre_jumps_in_callback.fetch_add(1, Ordering::SeqCst);
+      })),
+      Some(Box::new(move |jump: &TimeJump| {
+        jumps_in_callback
+          .lock()
+          .unwrap()
+          .push((jump.clock_change, jump.delta.to_nanos()));
+      })),
+    );
+
+    // Not active: simulated time changes are not jumps.
+    time_source.set_sim_time(ROSTime::from_nanos(10_000_000_000));
+    time_source.set_ros_time_active(true);
+    time_source.set_sim_time(ROSTime::from_nanos(10_500_000_000)); // below threshold
+    time_source.set_sim_time(ROSTime::from_nanos(12_000_000_000));
+    time_source.set_sim_time(ROSTime::from_nanos(11_000_000_000));
+    time_source.set_ros_time_active(false);
+
+    let jumps = std::mem::take(&mut *jumps.lock().unwrap());
+    assert_eq!(jumps.len(), 4);
+    assert_eq!(jumps[0].0, ClockChange::RosTimeActivated);
+    assert!(jumps[0].1 < 0);
+    assert_eq!(
+      jumps[1..3],
+      [
+        (ClockChange::RosTimeNoChange, 1_500_000_000),
+        (ClockChange::RosTimeNoChange, -1_000_000_000)
+      ]
+    );
+    assert_eq!(jumps[3].0, ClockChange::RosTimeDeactivated);
+    assert_eq!(pre_jumps.load(Ordering::SeqCst), 4);
+
+    drop(handler);
+    time_source.set_ros_time_active(true);
+    assert_eq!(pre_jumps.load(Ordering::SeqCst), 4);
+  }
+}
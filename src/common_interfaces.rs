@@ -0,0 +1,37 @@
+//! Message types generated from ROS 2 common_interfaces by build.rs.
+//!
+//! Each package is compiled in only if the feature of the same name is
+//! enabled. The types are re-exported from the package modules of this crate,
+//! e.g. [`crate::nav_msgs`], next to the hand-written ones.
+
+include!(concat!(env!("OUT_DIR"), "/common_interfaces.rs"));
+
+#[cfg(all(test, feature = "nav_msgs"))]
+mod tests {
+  use ros2_client_core::cdr::{from_bytes, to_vec, Endianness};
+
+  use crate::{builtin_interfaces::Time, nav_msgs::Odometry, std_msgs::Header};
+
+  #[test]
+  fn odometry_layout() {
+    let mut odom = Odometry {
+      header: Header::new(Time::ZERO, "odom"),
+      child_frame_id: "base".to_string(),
+      ..Odometry::default()
+    };
+    odom.pose.covariance[35] = 0.5;
+    // Quaternion defaults to identity
+    assert_eq!(odom.pose.pose.orientation.w, 1.0);
+    assert_eq!(
+      Odometry::message_type_name().dds_msg_type(),
+      "nav_msgs::msg::dds_::Odometry_"
+    );
+
+    let bytes = to_vec(&odom, Endianness::Little).unwrap();
+    // Header 17 bytes, child_frame_id 9 bytes, padding to 8 bytes,
+    // PoseWithCovariance 7 + 36 doubles and TwistWithCovariance 6 + 36.
+    assert_eq!(bytes.len(), 32 + 43 * 8 + 42 * 8);
+    let (back, _) = from_bytes::<Odometry>(&bytes, Endianness::Little).unwrap();
+    assert_eq!(back, odom);
+  }
+}
@@ -1,15 +1,16 @@
 use std::{
-  collections::HashMap,
+  collections::BTreeMap,
   sync::{Arc, Mutex},
 };
 //use futures::{pin_mut, StreamExt};
+use futures::future::BoxFuture;
 #[cfg(feature = "security")]
 use std::path::{Path, PathBuf};
 
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
 //use mio::Evented;
-use serde::Serialize;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use rustdds::{
   dds::CreateResult,
   no_key::{DeserializerAdapter, SerializerAdapter},
@@ -20,11 +21,14 @@ use rustdds::{
 use crate::{
   builtin_topics,
   entities_info::{NodeEntitiesInfo, ParticipantEntitiesInfo},
+  error::{create_error, EntityKind, Error},
   gid::Gid,
+  intra_process::IntraProcessManager,
+  keyed::{KeyedPublisher, KeyedSubscription},
   names::*,
   node::{Node, NodeOptions},
   pubsub::{Publisher, Subscription},
-  NodeCreateError,
+  ros_args::RemapRule,
 };
 
 lazy_static! {
@@ -132,12 +136,14 @@ pub struct Context {
 
 impl Context {
   /// Create a new Context with default settings.
-  pub fn new() -> CreateResult<Context> {
-    Self::from_domain_participant(DomainParticipant::new(0)?)
+  pub fn new() -> Result<Context, Error> {
+    let dp = DomainParticipant::new(0).map_err(create_error(EntityKind::Context, "domain 0"))?;
+    Self::from_domain_participant(dp)
   }
 
   /// Create a new Context.
-  pub fn with_options(opt: ContextOptions) -> CreateResult<Context> {
+  pub fn with_options(opt: ContextOptions) -> Result<Context, Error> {
+    let domain_name = format!("domain {}", opt.domain_id);
     #[allow(unused_mut)] // only mutated with security
     let mut dpb = DomainParticipantBuilder::new(opt.domain_id);
 
@@ -153,24 +159,66 @@ impl Context {
       }
     }
 
-    Self::from_domain_participant(dpb.build()?)
+    let dp = dpb
+      .build()
+      .map_err(create_error(EntityKind::Context, &domain_name))?;
+    Self::from_domain_participant(dp)
   }
 
   /// Create a new Context from an existing [`DomainParticipant`].
-  pub fn from_domain_participant(domain_participant: DomainParticipant) -> CreateResult<Context> {
-    let i = ContextInner::from_domain_participant(domain_participant)?;
+  pub fn from_domain_participant(domain_participant: DomainParticipant) -> Result<Context, Error> {
+    let domain_name = format!("domain {}", domain_participant.domain_id());
+    let i = ContextInner::from_domain_participant(domain_participant)
+      .map_err(create_error(EntityKind::Context, &domain_name))?;
     Ok(Context {
       inner: Arc::new(Mutex::new(i)),
     })
   }
 
   /// Create a new ROS2 [`Node`]
-  pub fn new_node(
+  pub fn new_node(&self, node_name: NodeName, options: NodeOptions) -> Result<Node, Error> {
+    Node::new(node_name, options, self.clone())
+  }
+
+  /// Create a new ROS2 [`Node`] that can be shared.
+  ///
+  /// Note that creating Publishers, Subscriptions, etc. requires `&mut Node`,
+  /// so those should be created before the `Arc` is cloned, e.g. via
+  /// [`Arc::get_mut`].
+  pub fn new_node_arc(
     &self,
     node_name: NodeName,
     options: NodeOptions,
-  ) -> Result<Node, NodeCreateError> {
-    Node::new(node_name, options, self.clone())
+  ) -> Result<Arc<Node>, Error> {
+    self.new_node(node_name, options).map(Arc::new)
+  }
+
+  /// Create a new shareable ROS2 [`Node`] and start its Spinner.
+  ///
+  /// The spin task is given to `spawn`, which should start it in an async
+  /// executor, e.g. `|task| { smol::spawn(task).detach(); }`. Spinner errors
+  /// are logged.
+  ///
+  /// This avoids the common mistake of forgetting to run the Spinner, in which
+  /// case e.g. matched counts and waits never update.
+  pub fn new_node_arc_spinning<F>(
+    &self,
+    node_name: NodeName,
+    options: NodeOptions,
+    spawn: F,
+  ) -> Result<Arc<Node>, Error>
+  where
+    F: FnOnce(BoxFuture<'static, ()>),
+  {
+    let mut node = self.new_node(node_name, options)?;
+    let spinner = node.spinner()?;
+    spawn(Box::pin(async move {
+      spinner
+        .spin()
+        .await
+        .unwrap_or_else(|e| error!("Spinner failed: {e:?}"));
+    }));
+    Ok(Arc::new(node))
   }
 
   /// Query which DDS Domain Id we are using.
@@ -214,6 +262,27 @@ impl Context {
     self.inner.lock().unwrap().domain_participant.clone()
   }
 
+  pub(crate) fn intra_process(&self) -> Arc<IntraProcessManager> {
+    Arc::clone(&self.inner.lock().unwrap().intra_process)
+  }
+
+  /// Remap Topic, Service or Action name `from` to `to` in all Nodes of this
+  /// Context, e.g. `add_remap("chatter", "/robot/talk")`.
+  ///
+  /// The rule is consulted when entities are created, also in existing
+  /// Nodes. Names are compared after resolving them, see
+  /// [`Node::resolve_name`]. The rules of a Node, see [`Node::add_remap`],
+  /// take precedence over these.
+  pub fn add_remap(&self, from: &str, to: &str) -> Result<(), Error> {
+    let rule = RemapRule::new(from, to)?;
+    self.inner.lock().unwrap().remap_rules.push(rule);
+    Ok(())
+  }
+
+  pub(crate) fn remap_rules(&self) -> Vec<RemapRule> {
+    self.inner.lock().unwrap().remap_rules.clone()
+  }
+
   // pub fn ros_discovery_stream(&self) -> impl Stream<Item =
   // ReadResult<(ParticipantEntitiesInfo, MessageInfo)>> + FusedStream + '_ {
   //   self.inner.lock().unwrap().node_reader.async_stream()
@@ -226,19 +295,41 @@ impl Context {
     topic_dds_name: String,
     type_name: MessageTypeName,
     qos: &QosPolicies,
-  ) -> CreateResult<Topic> {
+  ) -> Result<Topic, Error> {
     info!("Creating topic, DDS name: {}", topic_dds_name);
-    let topic = self.domain_participant().create_topic(
-      topic_dds_name,
-      type_name.dds_msg_type(),
-      qos,
-      TopicKind::NoKey,
-    )?;
+    let topic = self
+      .domain_participant()
+      .create_topic(
+        topic_dds_name.clone(),
+        type_name.dds_msg_type(),
+        qos,
+        TopicKind::NoKey,
+      )
+      .map_err(create_error(EntityKind::Topic, &topic_dds_name))?;
     // ROS2 does not use WithKey topics, so always NoKey
     info!("Created topic");
     Ok(topic)
   }
 
+  /// Like [`Self::create_topic`], but the Topic is WithKey.
+  pub fn create_keyed_topic(
+    &self,
+    topic_dds_name: String,
+    type_name: MessageTypeName,
+    qos: &QosPolicies,
+  ) -> Result<Topic, Error> {
+    info!("Creating keyed topic, DDS name: {}", topic_dds_name);
+    self
+      .domain_participant()
+      .create_topic(
+        topic_dds_name.clone(),
+        type_name.dds_msg_type(),
+        qos,
+        TopicKind::WithKey,
+      )
+      .map_err(create_error(EntityKind::Topic, &topic_dds_name))
+  }
+
   pub(crate) fn create_publisher<M>(
     &self,
     topic: &Topic,
@@ -246,6 +337,17 @@ impl Context {
   ) -> dds::CreateResult<Publisher<M>>
   where
     M: Serialize,
+  {
+    self.create_publisher_with_adapter(topic, qos)
+  }
+
+  pub(crate) fn create_publisher_with_adapter<M, SA>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> dds::CreateResult<Publisher<M, SA>>
+  where
+    SA: SerializerAdapter<M>,
   {
     let datawriter = self
       .get_ros_default_publisher()
@@ -261,6 +363,18 @@ impl Context {
   ) -> dds::CreateResult<Subscription<M>>
   where
     M: 'static,
+  {
+    self.create_subscription_with_adapter(topic, qos)
+  }
+
+  pub(crate) fn create_subscription_with_adapter<M, DA>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> dds::CreateResult<Subscription<M, DA>>
+  where
+    M: 'static,
+    DA: 'static + DeserializerAdapter<M>,
   {
     let datareader = self
       .get_ros_default_subscriber()
@@ -268,6 +382,40 @@ impl Context {
     Ok(Subscription::new(datareader))
   }
 
+  pub(crate) fn create_keyed_publisher<M>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> dds::CreateResult<KeyedPublisher<M>>
+  where
+    M: Keyed + Serialize,
+    M::K: Serialize,
+  {
+    // RustDDS checks this only for DataReaders.
+    if topic.kind() != TopicKind::WithKey {
+      return Err(dds::CreateError::TopicKind(TopicKind::WithKey));
+    }
+    let datawriter = self
+      .get_ros_default_publisher()
+      .create_datawriter_cdr(topic, qos)?;
+    Ok(KeyedPublisher::new(datawriter))
+  }
+
+  pub(crate) fn create_keyed_subscription<M>(
+    &self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> dds::CreateResult<KeyedSubscription<M>>
+  where
+    M: 'static + Keyed + DeserializeOwned,
+    for<'de> M::K: Deserialize<'de>,
+  {
+    let datareader = self
+      .get_ros_default_subscriber()
+      .create_datareader_cdr(topic, qos)?;
+    Ok(KeyedSubscription::new(datareader))
+  }
+
   pub(crate) fn create_datawriter<M, SA>(
     &self,
     topic: &Topic,
@@ -295,12 +443,24 @@ impl Context {
       .create_simple_datareader_no_key(topic, qos)
   }
 
-  pub(crate) fn update_node(&mut self, node_info: NodeEntitiesInfo) {
-    self.inner.lock().unwrap().update_node(node_info);
+  // Allocates the key under which a new Node keeps its NodeEntitiesInfo.
+  // Nodes are keyed by this, not by name, so that Nodes with the same name do
+  // not overwrite or remove each other.
+  pub(crate) fn new_node_key(&self, node_name: &NodeName) -> NodeKey {
+    self.inner.lock().unwrap().new_node_key(node_name)
   }
 
-  pub(crate) fn remove_node(&mut self, node_name: &str) {
-    self.inner.lock().unwrap().remove_node(node_name);
+  pub(crate) fn update_node(&mut self, key: NodeKey, node_info: NodeEntitiesInfo) {
+    self.inner.lock().unwrap().update_node(key, node_info);
+  }
+
+  pub(crate) fn remove_node(&mut self, key: NodeKey) {
+    self.inner.lock().unwrap().remove_node(key);
+  }
+
+  // Removes a Reader or Writer from a Node, e.g. when it is dropped.
+  pub(crate) fn remove_node_endpoint(&self, key: NodeKey, gid: Gid) {
+    self.inner.lock().unwrap().remove_node_endpoint(key, gid);
   }
 
   fn get_ros_default_publisher(&self) -> rustdds::Publisher {
@@ -316,8 +476,13 @@ impl Context {
   }
 }
 
+// Identifies a Node within its Context. Keys are allocated in increasing
+// order, so ros_discovery_info lists Nodes in creation order.
+pub(crate) type NodeKey = u64;
+
 struct ContextInner {
-  local_nodes: HashMap<String, NodeEntitiesInfo>,
+  local_nodes: BTreeMap<NodeKey, NodeEntitiesInfo>,
+  next_node_key: NodeKey,
 
   // ROS Discovery: topic, reader and writer
   ros_discovery_topic: Topic,
@@ -334,6 +499,11 @@ struct ContextInner {
 
   ros_parameter_events_topic: Topic,
   ros_rosout_topic: Topic,
+
+  intra_process: Arc<IntraProcessManager>,
+
+  // From Context::add_remap
+  remap_rules: Vec<RemapRule>,
 }
 
 impl ContextInner {
@@ -370,7 +540,8 @@ impl ContextInner {
       Publisher::new(ros_default_publisher.create_datawriter_no_key(&ros_discovery_topic, None)?);
 
     Ok(ContextInner {
-      local_nodes: HashMap::new(),
+      local_nodes: BTreeMap::new(),
+      next_node_key: 0,
       node_writer,
 
       domain_participant,
@@ -379,6 +550,8 @@ impl ContextInner {
       ros_default_subscriber,
       ros_parameter_events_topic,
       ros_rosout_topic,
+      intra_process: Arc::default(),
+      remap_rules: Vec::new(),
     })
   }
 
@@ -390,21 +563,43 @@ impl ContextInner {
     )
   }
 
+  fn new_node_key(&mut self, node_name: &NodeName) -> NodeKey {
+    let fqn = node_name.fully_qualified_name();
+    if self
+      .local_nodes
+      .values()
+      .any(|n| n.fully_qualified_name() == fqn)
+    {
+      // rclcpp allows this, too, but ROS 2 tools cannot tell such Nodes apart.
+      warn!("Node {fqn} already exists in this Context. Node names should be unique.");
+    }
+    let key = self.next_node_key;
+    self.next_node_key += 1;
+    key
+  }
+
   // Adds new NodeEntitiesInfo and updates our ContextInfo to ROS2 network
-  fn update_node(&mut self, mut node_info: NodeEntitiesInfo) {
+  fn update_node(&mut self, key: NodeKey, mut node_info: NodeEntitiesInfo) {
     // Each node connects also to the ROS discovery topic
     node_info.add_writer(Gid::from(self.node_writer.guid()));
 
-    self
-      .local_nodes
-      .insert(node_info.fully_qualified_name(), node_info);
+    self.local_nodes.insert(key, node_info);
     self.broadcast_node_infos();
   }
 
   /// Removes NodeEntitiesInfo and updates our ContextInfo to ROS2 network
-  fn remove_node(&mut self, node_fqn: &str) {
-    self.local_nodes.remove(node_fqn);
-    self.broadcast_node_infos();
+  fn remove_node(&mut self, key: NodeKey) {
+    if self.local_nodes.remove(&key).is_some() {
+      self.broadcast_node_infos();
+    }
+  }
+
+  fn remove_node_endpoint(&mut self, key: NodeKey, gid: Gid) {
+    if let Some(node_info) = self.local_nodes.get_mut(&key) {
+      node_info.remove_reader(gid);
+      node_info.remove_writer(gid);
+      self.broadcast_node_infos();
+    }
   }
 
   fn broadcast_node_infos(&self) {
@@ -440,3 +635,56 @@ fn test_node_create() {
     )
     .is_ok();
 }
+
+#[test]
+fn test_multiple_nodes() {
+  let context = Context::new().unwrap();
+  let node_names = |context: &Context| {
+    context
+      .participant_entities_info()
+      .nodes()
+      .iter()
+      .map(|n| n.fully_qualified_name())
+      .collect::<Vec<_>>()
+  };
+  let new_node = |name| {
+    context
+      .new_node(NodeName::new("/ns", name).unwrap(), NodeOptions::new())
+      .unwrap()
+  };
+
+  let a = new_node("a");
+  let b = new_node("b");
+  let mut twin = new_node("a");
+  assert_eq!(node_names(&context), ["/ns/a", "/ns/b", "/ns/a"]);
+
+  // Entities are listed under the Node that created them.
+  let topic = twin
+    .create_topic(
+      &Name::new("/", "multi").unwrap(),
+      MessageTypeName::new("std_msgs", "String"),
+      &DEFAULT_PUBLISHER_QOS,
+    )
+    .unwrap();
+  let publisher = twin.create_publisher::<String>(&topic, None).unwrap();
+  let writers = |context: &Context| {
+    context
+      .participant_entities_info()
+      .nodes()
+      .iter()
+      .map(|n| n.writers().len())
+      .collect::<Vec<_>>()
+  };
+  let before = writers(&context);
+  assert_eq!(before[2], before[0] + 1);
+
+  drop(publisher);
+  assert_eq!(writers(&context)[2], before[0]);
+
+  // Dropping a Node does not remove another one of the same name.
+  drop(a);
+  assert_eq!(node_names(&context), ["/ns/b", "/ns/a"]);
+  drop(twin);
+  drop(b);
+  assert!(node_names(&context).is_empty());
+}
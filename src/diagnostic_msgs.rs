@@ -0,0 +1,11 @@
+//! Corresponds to package [diagnostic_msgs](https://index.ros.org/p/diagnostic_msgs/).
+//!
+//! The types are generated from the ROS 2 definitions, see
+//! `interfaces/diagnostic_msgs/`.
+
+pub use crate::common_interfaces::diagnostic_msgs::msg::*;
+
+/// Services of diagnostic_msgs, e.g. [`srv::SelfTest`]
+pub mod srv {
+  pub use crate::common_interfaces::diagnostic_msgs::srv::*;
+}
@@ -0,0 +1,215 @@
+//! Processing received messages in parallel
+//!
+//! A [`WorkerPool`] runs message callbacks on a fixed set of threads, so that
+//! slow processing of one Topic does not hold up the others. Each dispatched
+//! Subscription chooses its [`DispatchOrdering`].
+
+use std::{
+  panic::{catch_unwind, AssertUnwindSafe},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+  thread,
+};
+
+use futures::{pin_mut, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{message_info::MessageInfo, pubsub::Subscription, service::panic_message};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// In which order the messages of one Subscription are processed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DispatchOrdering {
+  /// Messages are processed in parallel, in any order.
+  Unordered,
+  /// Messages are processed one at a time, in order of reception. Different
+  /// Subscriptions still run in parallel.
+  Sequential,
+}
+
+/// A bounded pool of worker threads for message callbacks
+///
+/// When all workers are busy and the queue is full, dispatching waits, so
+/// that Subscriptions are read no faster than messages are processed.
+/// Dropping the pool lets the workers finish queued jobs and exit.
+pub struct WorkerPool {
+  jobs: async_channel::Sender<Job>,
+  queue_capacity: usize,
+}
+
+impl WorkerPool {
+  /// Start `workers` threads, with a queue of `queue_capacity` jobs.
+  ///
+  /// # Panics
+  ///
+  /// If `workers` or `queue_capacity` is zero.
+  pub fn new(workers: usize, queue_capacity: usize) -> Self {
+    assert!(workers > 0, "WorkerPool needs at least one worker");
+    let (jobs, job_receiver) = async_channel::bounded::<Job>(queue_capacity);
+    for i in 0..workers {
+      let job_receiver = job_receiver.clone();
+      thread::Builder::new()
+        .name(format!("ros2 worker {i}"))
+        .spawn(move || {
+          while let Ok(job) = job_receiver.recv_blocking() {
+            if let Err(payload) = catch_unwind(AssertUnwindSafe(job)) {
+              error!(
+                "Message callback panicked: {}",
+                panic_message(payload.as_ref())
+              );
+            }
+          }
+        })
+        .expect("Cannot start WorkerPool thread");
+    }
+    WorkerPool {
+      jobs,
+      queue_capacity,
+    }
+  }
+
+  /// Receive messages from `subscription` and run `callback` on each in the
+  /// pool. This never returns, so it should be run as a task.
+  ///
+  /// Receive errors are logged and skipped.
+  pub async fn dispatch<M, F>(
+    &self,
+    subscription: &Subscription<M>,
+    ordering: DispatchOrdering,
+    callback: F,
+  ) where
+    M: DeserializeOwned + Send + 'static,
+    F: Fn(M, MessageInfo) + Send + Sync + 'static,
+  {
+    let messages = subscription.async_stream().filter_map(|result| async move {
+      result
+        .map_err(|e| warn!("WorkerPool: receive failed: {e:?}"))
+        .ok()
+    });
+    self
+      .dispatch_stream(messages, ordering, move |(m, info)| callback(m, info))
+      .await
+  }
+
+  // Runs `callback` on each item of `items` in the pool. Returns when
+  // `items` ends, possibly before all callbacks have run.
+  async fn dispatch_stream<T, S, F>(&self, items: S, ordering: DispatchOrdering, callback: F)
+  where
+    T: Send + 'static,
+    S: Stream<Item = T>,
+    F: Fn(T) + Send + Sync + 'static,
+  {
+    let callback = Arc::new(callback);
+    pin_mut!(items);
+    match ordering {
+      DispatchOrdering::Unordered => {
+        while let Some(item) = items.next().await {
+          let callback = Arc::clone(&callback);
+          self.submit(Box::new(move || callback(item))).await;
+        }
+      }
+      DispatchOrdering::Sequential => {
+        // Items wait in a queue of their own. At most one job at a time
+        // drains it, which keeps them in order.
+        let (sender, receiver) = async_channel::bounded::<T>(self.queue_capacity);
+        let draining = Arc::new(AtomicBool::new(false));
+        while let Some(item) = items.next().await {
+          if sender.send(item).await.is_err() {
+            return; // cannot happen, as we hold the receiver
+          }
+          if !draining.swap(true, Ordering::AcqRel) {
+            let (receiver, draining, callback) = (
+              receiver.clone(),
+              Arc::clone(&draining),
+              Arc::clone(&callback),
+            );
+            self
+              .submit(Box::new(move || drain(&receiver, &draining, &*callback)))
+              .await;
+          }
+        }
+      }
+    }
+  }
+
+  async fn submit(&self, job: Job) {
+    // The workers never close the channel.
+    let _ = self.jobs.send(job).await;
+  }
+}
+
+// Processes queued items until the queue is empty and `draining` is released.
+fn drain<T>(queue: &async_channel::Receiver<T>, draining: &AtomicBool, callback: &dyn Fn(T)) {
+  loop {
+    while let Ok(item) = queue.try_recv() {
+      // Keep draining even if the callback panics.
+      if let Err(payload) = catch_unwind(AssertUnwindSafe(|| callback(item))) {
+        error!(
+          "Message callback panicked: {}",
+          panic_message(payload.as_ref())
+        );
+      }
+    }
+    draining.store(false, Ordering::Release);
+    // An item may have arrived after the queue was seen empty, but before
+    // `draining` was released. Then its sender did not start a new drain.
+    if queue.is_empty() || draining.swap(true, Ordering::AcqRel) {
+      return;
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::{
+    sync::{mpsc, Mutex},
+    time::Duration,
+  };
+
+  use futures::{executor::block_on, stream};
+
+  use super::*;
+
+  #[test]
+  fn sequential_keeps_order() {
+    let pool = WorkerPool::new(4, 2);
+    let (done_sender, done) = mpsc::channel();
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let seen_in_callback = Arc::clone(&seen);
+    block_on(pool.dispatch_stream(
+      stream::iter(0..50),
+      DispatchOrdering::Sequential,
+      move |i| {
+        seen_in_callback.lock().unwrap().push(i);
+        done_sender.send(()).unwrap();
+      },
+    ));
+    for _ in 0..50 {
+      done.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+    assert_eq!(*seen.lock().unwrap(), (0..50).collect::<Vec<_>>());
+  }
+
+  #[test]
+  fn unordered_runs_in_parallel() {
+    let pool = WorkerPool::new(4, 4);
+    let (done_sender, done) = mpsc::channel();
+    block_on(
+      pool.dispatch_stream(stream::iter(0..4), DispatchOrdering::Unordered, move |_| {
+        thread::sleep(Duration::from_millis(200));
+        done_sender.send(()).unwrap();
+      }),
+    );
+    // Four 200 ms jobs on four workers take much less than 800 ms.
+    let started = std::time::Instant::now();
+    for _ in 0..4 {
+      done.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+    assert!(started.elapsed() < Duration::from_millis(600));
+  }
+}
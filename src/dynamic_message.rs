@@ -0,0 +1,920 @@
+//! Messages whose type is known only at run time.
+//!
+//! A [`DynamicMessageType`] is parsed from a message definition, i.e. the
+//! contents of a `.msg` file, and a [`DynamicMessage`] is a value of that type.
+//! This allows generic tools, such as topic echo, bridges, and bag recorders,
+//! to handle any message type without compile-time structs.
+//!
+//! A definition that refers to other message types must include them, in the
+//! format used by e.g. rosbag2 and ROS 1: the main type first, then each
+//! dependency after a separator line of `=` characters and a line
+//! `MSG: package/Type`. Types `builtin_interfaces/Time` and `Duration` are
+//! known without a definition.
+//!
+//! Types cannot be made from DDS XTypes TypeObjects of remote Topics, as
+//! RustDDS does not receive them in discovery.
+//!
+//! [`DynamicMessage`] implements [`Serialize`], so it can be published with a
+//! [`Publisher`](crate::Publisher). To receive, give the type as
+//! deserialization seed to
+//! [`Subscription::take_seed`](crate::Subscription::take_seed)
+//! or [`Subscription::async_stream_seed`](crate::Subscription::async_stream_seed).
+//! See [`json`](crate::json) for conversion to and from JSON.
+//!
+//! ```
+//! use ros2_client::dynamic_message::{DynamicMessage, DynamicMessageType, DynamicValue};
+//!
+//! let point_type = DynamicMessageType::from_definition(
+//!   "my_msgs/msg/Point2",
+//!   "float64 x\nfloat64 y 1.5\n",
+//! )
+//! .unwrap();
+//! let mut point = DynamicMessage::new(&point_type);
+//! assert_eq!(point.get("y"), Some(&DynamicValue::Float64(1.5)));
+//! point.set("x", DynamicValue::Float64(2.0)).unwrap();
+//! ```
+
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+use serde::{
+  de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor},
+  ser::{self, SerializeMap, SerializeSeq, SerializeTuple, Serializer},
+  Deserialize, Serialize,
+};
+use ros2_client_codegen::parser::{self, ArraySpecifier, BaseTypeName, Item, Value};
+
+use crate::{names::MessageTypeName, WString};
+
+/// Type of a single value, or of an element of an array
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScalarType {
+  Bool,
+  Byte,
+  Char,
+  Float32,
+  Float64,
+  Int8,
+  UInt8,
+  Int16,
+  UInt16,
+  Int32,
+  UInt32,
+  Int64,
+  UInt64,
+  /// String with an optional bound on its length in bytes
+  String(Option<usize>),
+  /// Wide string with an optional bound on its length in UTF-16 units
+  WString(Option<usize>),
+  /// Nested message
+  Message(DynamicMessageType),
+}
+
+/// Type of a message field
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+  Scalar(ScalarType),
+  /// Fixed size array, e.g. `float64[9]`
+  Array(ScalarType, usize),
+  /// Sequence, e.g. `float64[]`, with an optional bound, e.g. `float64[<=9]`
+  Sequence(ScalarType, Option<usize>),
+}
+
+/// Field of a [`DynamicMessageType`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+  pub name: String,
+  pub field_type: FieldType,
+  /// Default value given in the definition
+  pub default: Option<DynamicValue>,
+}
+
+/// Message type, parsed from a message definition at run time.
+///
+/// This is cheap to clone.
+#[derive(Clone)]
+pub struct DynamicMessageType {
+  inner: Arc<TypeInner>,
+}
+
+struct TypeInner {
+  name: MessageTypeName,
+  fields: Vec<Field>,
+  definition: String,
+}
+
+/// What went wrong when parsing a definition or accessing a
+/// [`DynamicMessage`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DynamicMessageError {
+  /// Type names are of the form `package/Type` or `package/msg/Type`.
+  BadTypeName(String),
+  /// Syntax error in the definition of a type
+  Parse {
+    type_name: String,
+    message: String,
+  },
+  /// Type is referred to, but its definition was not given.
+  UnknownType(String),
+  /// Type contains itself.
+  RecursiveType(String),
+  /// Default value does not fit the type of the field.
+  BadDefault {
+    type_name: String,
+    field: String,
+  },
+  NoSuchField(String),
+  /// Value does not have the type of the field, or exceeds its bounds.
+  TypeMismatch(String),
+}
+
+impl fmt::Display for DynamicMessageError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::BadTypeName(name) => write!(f, "Bad message type name {name:?}"),
+      Self::Parse { type_name, message } => write!(f, "Cannot parse {type_name}: {message}"),
+      Self::UnknownType(name) => write!(f, "No definition for message type {name}"),
+      Self::RecursiveType(name) => write!(f, "Message type {name} contains itself"),
+      Self::BadDefault { type_name, field } => {
+        write!(f, "Bad default value for field {field} of {type_name}")
+      }
+      Self::NoSuchField(name) => write!(f, "No field named {name:?}"),
+      Self::TypeMismatch(name) => write!(f, "Value does not match the type of field {name:?}"),
+    }
+  }
+}
+
+impl std::error::Error for DynamicMessageError {}
+
+impl DynamicMessageType {
+  /// Parses the definition of type `type_name`, e.g. `"geometry_msgs/Point"`
+  /// or `"geometry_msgs/msg/Point"`. See the [module](self) documentation
+  /// for the format of `definition`.
+  pub fn from_definition(type_name: &str, definition: &str) -> Result<Self, DynamicMessageError> {
+    let main = split_type_name(type_name)?;
+    let mut definitions = BTreeMap::new();
+    let mut current = main.clone();
+    let mut text = String::new();
+    let mut lines = definition.lines();
+    while let Some(line) = lines.next() {
+      if line.len() >= 3 && line.trim_end().chars().all(|c| c == '=') {
+        definitions.insert(current, std::mem::take(&mut text));
+        // Next non-empty line names the type
+        let header = lines
+          .by_ref()
+          .map(str::trim)
+          .find(|l| !l.is_empty())
+          .unwrap_or_default();
+        let name = header
+          .strip_prefix("MSG:")
+          .ok_or_else(|| DynamicMessageError::BadTypeName(header.to_string()))?;
+        current = split_type_name(name.trim())?;
+      } else {
+        text.push_str(line);
+        text.push('\n');
+      }
+    }
+    definitions.insert(current, text);
+
+    Resolver {
+      definitions,
+      resolved: BTreeMap::new(),
+      in_progress: Vec::new(),
+    }
+    .resolve(&main.0, &main.1)
+  }
+
+  pub fn name(&self) -> &MessageTypeName {
+    &self.inner.name
+  }
+
+  pub fn fields(&self) -> &[Field] {
+    &self.inner.fields
+  }
+
+  /// Definition of this type, without the types it refers to
+  pub fn definition(&self) -> &str {
+    &self.inner.definition
+  }
+
+  pub fn field(&self, name: &str) -> Option<&Field> {
+    self.inner.fields.iter().find(|f| f.name == name)
+  }
+
+  fn field_index(&self, name: &str) -> Result<usize, DynamicMessageError> {
+    self
+      .inner
+      .fields
+      .iter()
+      .position(|f| f.name == name)
+      .ok_or_else(|| DynamicMessageError::NoSuchField(name.to_string()))
+  }
+}
+
+impl PartialEq for DynamicMessageType {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.inner, &other.inner)
+      || (self.inner.name.package_name() == other.inner.name.package_name()
+        && self.inner.name.type_name() == other.inner.name.type_name()
+        && self.inner.fields == other.inner.fields)
+  }
+}
+
+impl fmt::Debug for DynamicMessageType {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    // Not the fields, as nested types would make this very long.
+    write!(
+      f,
+      "DynamicMessageType({}/{})",
+      self.inner.name.package_name(),
+      self.inner.name.type_name()
+    )
+  }
+}
+
+// (package, type name)
+type TypeKey = (String, String);
+
+fn split_type_name(name: &str) -> Result<TypeKey, DynamicMessageError> {
+  match name.split('/').collect::<Vec<_>>().as_slice() {
+    [package, type_name] | [package, "msg", type_name]
+      if !package.is_empty() && !type_name.is_empty() =>
+    {
+      Ok((package.to_string(), type_name.to_string()))
+    }
+    _ => Err(DynamicMessageError::BadTypeName(name.to_string())),
+  }
+}
+
+struct Resolver {
+  definitions: BTreeMap<TypeKey, String>,
+  resolved: BTreeMap<TypeKey, DynamicMessageType>,
+  in_progress: Vec<TypeKey>,
+}
+
+impl Resolver {
+  fn resolve(
+    &mut self,
+    package: &str,
+    name: &str,
+  ) -> Result<DynamicMessageType, DynamicMessageError> {
+    let key = (package.to_string(), name.to_string());
+    let full_name = format!("{package}/{name}");
+    if let Some(t) = self.resolved.get(&key) {
+      return Ok(t.clone());
+    }
+    if self.in_progress.contains(&key) {
+      return Err(DynamicMessageError::RecursiveType(full_name));
+    }
+    let text = match (self.definitions.get(&key), package, name) {
+      (Some(text), _, _) => text.clone(),
+      (None, "builtin_interfaces", "Time") | (None, "builtin_interfaces", "Duration") => {
+        "int32 sec\nuint32 nanosec\n".to_string()
+      }
+      (None, _, _) => return Err(DynamicMessageError::UnknownType(full_name)),
+    };
+    let lines = parser::msg_spec(&text).map_err(|e| DynamicMessageError::Parse {
+      type_name: full_name.clone(),
+      message: e.to_string(),
+    })?;
+
+    self.in_progress.push(key.clone());
+    let mut fields = Vec::new();
+    for item in lines.into_iter().filter_map(|(item, _comment)| item) {
+      if let Item::Field {
+        type_name,
+        field_name,
+        default_value,
+      } = item
+      {
+        let scalar = self.scalar_type(package, &type_name.base)?;
+        let field_type = match type_name.array_spec {
+          None => FieldType::Scalar(scalar),
+          Some(ArraySpecifier::Static { size }) => FieldType::Array(scalar, size as usize),
+          Some(ArraySpecifier::Unbounded) => FieldType::Sequence(scalar, None),
+          Some(ArraySpecifier::Bounded { bound }) => {
+            FieldType::Sequence(scalar, Some(bound as usize))
+          }
+        };
+        let default =
+          match default_value {
+            None => None,
+            Some(value) => Some(field_type.value_from(&value).ok_or_else(|| {
+              DynamicMessageError::BadDefault {
+                type_name: full_name.clone(),
+                field: field_name.clone(),
+              }
+            })?),
+          };
+        fields.push(Field {
+          name: field_name,
+          field_type,
+          default,
+        });
+      }
+    }
+    self.in_progress.pop();
+
+    let t = DynamicMessageType {
+      inner: Arc::new(TypeInner {
+        name: MessageTypeName::new(package, name),
+        fields,
+        definition: text,
+      }),
+    };
+    self.resolved.insert(key, t.clone());
+    Ok(t)
+  }
+
+  fn scalar_type(
+    &mut self,
+    package: &str,
+    base: &BaseTypeName,
+  ) -> Result<ScalarType, DynamicMessageError> {
+    Ok(match base {
+      BaseTypeName::Primitive { name } => match name.as_str() {
+        "bool" => ScalarType::Bool,
+        "byte" => ScalarType::Byte,
+        "char" => ScalarType::Char,
+        "float32" => ScalarType::Float32,
+        "float64" => ScalarType::Float64,
+        "int8" => ScalarType::Int8,
+        "uint8" => ScalarType::UInt8,
+        "int16" => ScalarType::Int16,
+        "uint16" => ScalarType::UInt16,
+        "int32" => ScalarType::Int32,
+        "uint32" => ScalarType::UInt32,
+        "int64" => ScalarType::Int64,
+        "uint64" => ScalarType::UInt64,
+        "string" => ScalarType::String(None),
+        "wstring" => ScalarType::WString(None),
+        other => return Err(DynamicMessageError::UnknownType(other.to_string())),
+      },
+      BaseTypeName::BoundedString { bound } => ScalarType::String(Some(*bound as usize)),
+      BaseTypeName::BoundedWString { bound } => ScalarType::WString(Some(*bound as usize)),
+      BaseTypeName::ComplexType {
+        package_name,
+        type_name,
+      } => {
+        ScalarType::Message(self.resolve(package_name.as_deref().unwrap_or(package), type_name)?)
+      }
+    })
+  }
+}
+
+impl ScalarType {
+  fn zero(&self) -> DynamicValue {
+    match self {
+      ScalarType::Bool => DynamicValue::Bool(false),
+      ScalarType::Byte => DynamicValue::Byte(0),
+      ScalarType::Char => DynamicValue::Char(0),
+      ScalarType::Float32 => DynamicValue::Float32(0.0),
+      ScalarType::Float64 => DynamicValue::Float64(0.0),
+      ScalarType::Int8 => DynamicValue::Int8(0),
+      ScalarType::UInt8 => DynamicValue::UInt8(0),
+      ScalarType::Int16 => DynamicValue::Int16(0),
+      ScalarType::UInt16 => DynamicValue::UInt16(0),
+      ScalarType::Int32 => DynamicValue::Int32(0),
+      ScalarType::UInt32 => DynamicValue::UInt32(0),
+      ScalarType::Int64 => DynamicValue::Int64(0),
+      ScalarType::UInt64 => DynamicValue::UInt64(0),
+      ScalarType::String(_) => DynamicValue::String(String::new()),
+      ScalarType::WString(_) => DynamicValue::WString(String::new()),
+      ScalarType::Message(t) => DynamicValue::Message(DynamicMessage::new(t)),
+    }
+  }
+
+  // Value of a default or constant from the definition
+  fn value_from(&self, value: &Value) -> Option<DynamicValue> {
+    use std::convert::TryFrom;
+
+    let int = |v: &Value| match v {
+      Value::Int(i) => Some(i128::from(*i)),
+      Value::Uint(u) => Some(i128::from(*u)),
+      _ => None,
+    };
+    Some(match (self, value) {
+      (ScalarType::Bool, Value::Bool(b)) => DynamicValue::Bool(*b),
+      (ScalarType::Float32, Value::Float(x)) => DynamicValue::Float32(*x as f32),
+      (ScalarType::Float64, Value::Float(x)) => DynamicValue::Float64(*x),
+      (ScalarType::Float32, v) => DynamicValue::Float32(int(v)? as f32),
+      (ScalarType::Float64, v) => DynamicValue::Float64(int(v)? as f64),
+      (ScalarType::Byte, v) => DynamicValue::Byte(u8::try_from(int(v)?).ok()?),
+      (ScalarType::Char, v) => DynamicValue::Char(u8::try_from(int(v)?).ok()?),
+      (ScalarType::Int8, v) => DynamicValue::Int8(i8::try_from(int(v)?).ok()?),
+      (ScalarType::UInt8, v) => DynamicValue::UInt8(u8::try_from(int(v)?).ok()?),
+      (ScalarType::Int16, v) => DynamicValue::Int16(i16::try_from(int(v)?).ok()?),
+      (ScalarType::UInt16, v) => DynamicValue::UInt16(u16::try_from(int(v)?).ok()?),
+      (ScalarType::Int32, v) => DynamicValue::Int32(i32::try_from(int(v)?).ok()?),
+      (ScalarType::UInt32, v) => DynamicValue::UInt32(u32::try_from(int(v)?).ok()?),
+      (ScalarType::Int64, v) => DynamicValue::Int64(i64::try_from(int(v)?).ok()?),
+      (ScalarType::UInt64, v) => DynamicValue::UInt64(u64::try_from(int(v)?).ok()?),
+      (ScalarType::String(_), Value::String(s)) => {
+        DynamicValue::String(String::from_utf8_lossy(s).into_owned())
+      }
+      (ScalarType::WString(_), Value::String(s)) => {
+        DynamicValue::WString(String::from_utf8_lossy(s).into_owned())
+      }
+      _ => return None,
+    })
+    .filter(|v| self.accepts(v))
+  }
+
+  fn accepts(&self, value: &DynamicValue) -> bool {
+    match (self, value) {
+      (ScalarType::Bool, DynamicValue::Bool(_))
+      | (ScalarType::Byte, DynamicValue::Byte(_))
+      | (ScalarType::Char, DynamicValue::Char(_))
+      | (ScalarType::Float32, DynamicValue::Float32(_))
+      | (ScalarType::Float64, DynamicValue::Float64(_))
+      | (ScalarType::Int8, DynamicValue::Int8(_))
+      | (ScalarType::UInt8, DynamicValue::UInt8(_))
+      | (ScalarType::Int16, DynamicValue::Int16(_))
+      | (ScalarType::UInt16, DynamicValue::UInt16(_))
+      | (ScalarType::Int32, DynamicValue::Int32(_))
+      | (ScalarType::UInt32, DynamicValue::UInt32(_))
+      | (ScalarType::Int64, DynamicValue::Int64(_))
+      | (ScalarType::UInt64, DynamicValue::UInt64(_)) => true,
+      (ScalarType::String(bound), DynamicValue::String(s)) => bound.is_none_or(|b| s.len() <= b),
+      (ScalarType::WString(bound), DynamicValue::WString(s)) => {
+        bound.is_none_or(|b| s.encode_utf16().count() <= b)
+      }
+      (ScalarType::Message(t), DynamicValue::Message(m)) => m.message_type == *t,
+      _ => false,
+    }
+  }
+}
+
+impl FieldType {
+  fn zero(&self) -> DynamicValue {
+    match self {
+      FieldType::Scalar(s) => s.zero(),
+      FieldType::Array(s, len) => DynamicValue::Array(vec![s.zero(); *len]),
+      FieldType::Sequence(..) => DynamicValue::Array(Vec::new()),
+    }
+  }
+
+  fn value_from(&self, value: &Value) -> Option<DynamicValue> {
+    match (self, value) {
+      (FieldType::Scalar(s), v) => s.value_from(v),
+      (FieldType::Array(s, _) | FieldType::Sequence(s, _), Value::Array(values)) => {
+        let values = values
+          .iter()
+          .map(|v| s.value_from(v))
+          .collect::<Option<Vec<_>>>()?;
+        Some(DynamicValue::Array(values)).filter(|v| self.accepts(v))
+      }
+      _ => None,
+    }
+  }
+
+  fn accepts(&self, value: &DynamicValue) -> bool {
+    match (self, value) {
+      (FieldType::Scalar(s), v) => s.accepts(v),
+      (FieldType::Array(s, len), DynamicValue::Array(values)) => {
+        values.len() == *len && values.iter().all(|v| s.accepts(v))
+      }
+      (FieldType::Sequence(s, bound), DynamicValue::Array(values)) => {
+        bound.is_none_or(|b| values.len() <= b) && values.iter().all(|v| s.accepts(v))
+      }
+      _ => false,
+    }
+  }
+}
+
+/// Value of a field of a [`DynamicMessage`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DynamicValue {
+  Bool(bool),
+  Byte(u8),
+  Char(u8),
+  Float32(f32),
+  Float64(f64),
+  Int8(i8),
+  UInt8(u8),
+  Int16(i16),
+  UInt16(u16),
+  Int32(i32),
+  UInt32(u32),
+  Int64(i64),
+  UInt64(u64),
+  String(String),
+  WString(String),
+  Message(DynamicMessage),
+  /// Both fixed size arrays and sequences
+  Array(Vec<DynamicValue>),
+}
+
+/// Message of a [`DynamicMessageType`].
+///
+/// The values of the fields always match their types, as [`set`](Self::set)
+/// checks them. Values changed via [`get_mut`](Self::get_mut) are checked
+/// only on serialization.
+#[derive(Clone, PartialEq)]
+pub struct DynamicMessage {
+  message_type: DynamicMessageType,
+  values: Vec<DynamicValue>,
+}
+
+impl DynamicMessage {
+  /// New message with default values from the definition, or zeros,
+  /// empty strings and sequences if there are none.
+  pub fn new(message_type: &DynamicMessageType) -> Self {
+    let values = message_type
+      .fields()
+      .iter()
+      .map(|f| f.default.clone().unwrap_or_else(|| f.field_type.zero()))
+      .collect();
+    DynamicMessage {
+      message_type: message_type.clone(),
+      values,
+    }
+  }
+
+  pub fn message_type(&self) -> &DynamicMessageType {
+    &self.message_type
+  }
+
+  pub fn get(&self, field: &str) -> Option<&DynamicValue> {
+    let index = self.message_type.field_index(field).ok()?;
+    self.values.get(index)
+  }
+
+  pub fn get_mut(&mut self, field: &str) -> Option<&mut DynamicValue> {
+    let index = self.message_type.field_index(field).ok()?;
+    self.values.get_mut(index)
+  }
+
+  /// Sets the value of `field`, if it matches the type of the field.
+  pub fn set(&mut self, field: &str, value: DynamicValue) -> Result<(), DynamicMessageError> {
+    let index = self.message_type.field_index(field)?;
+    if !self.message_type.fields()[index].field_type.accepts(&value) {
+      return Err(DynamicMessageError::TypeMismatch(field.to_string()));
+    }
+    self.values[index] = value;
+    Ok(())
+  }
+
+  /// Field names and values, in definition order
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &DynamicValue)> {
+    self
+      .message_type
+      .fields()
+      .iter()
+      .map(|f| f.name.as_str())
+      .zip(self.values.iter())
+  }
+}
+
+impl fmt::Debug for DynamicMessage {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let mut s = f.debug_struct(self.message_type.name().type_name());
+    for (name, value) in self.iter() {
+      s.field(name, value);
+    }
+    s.finish()
+  }
+}
+
+// Serialization
+//
+// Structures and fixed size arrays are tuples, i.e. no length is
+// serialized, and sequences are serialized with a length. In human-readable
+// formats, e.g. JSON, structures are maps from field names to values, like
+// the derived implementations of message structs.
+
+impl Serialize for DynamicMessage {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let fields = self.message_type.fields();
+    if serializer.is_human_readable() {
+      let mut map = serializer.serialize_map(Some(fields.len()))?;
+      for (field, value) in fields.iter().zip(&self.values) {
+        map.serialize_entry(&field.name, &FieldValue(field, value))?;
+      }
+      return map.end();
+    }
+    if fields.is_empty() {
+      // ROS 2 gives empty structures a dummy member, as DDS does not
+      // allow empty ones.
+      let mut tuple = serializer.serialize_tuple(1)?;
+      tuple.serialize_element(&0u8)?;
+      return tuple.end();
+    }
+    let mut tuple = serializer.serialize_tuple(fields.len())?;
+    for (field, value) in fields.iter().zip(&self.values) {
+      tuple.serialize_element(&FieldValue(field, value))?;
+    }
+    tuple.end()
+  }
+}
+
+struct FieldValue<'a>(&'a Field, &'a DynamicValue);
+
+impl Serialize for FieldValue<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let FieldValue(field, value) = self;
+    if !field.field_type.accepts(value) {
+      return Err(ser::Error::custom(DynamicMessageError::TypeMismatch(
+        field.name.clone(),
+      )));
+    }
+    match (&field.field_type, value) {
+      (FieldType::Scalar(_), v) => ScalarValue(v).serialize(serializer),
+      (FieldType::Array(..), DynamicValue::Array(values)) => {
+        let mut tuple = serializer.serialize_tuple(values.len())?;
+        for v in values {
+          tuple.serialize_element(&ScalarValue(v))?;
+        }
+        tuple.end()
+      }
+      (FieldType::Sequence(..), DynamicValue::Array(values)) => {
+        let mut seq = serializer.serialize_seq(Some(values.len()))?;
+        for v in values {
+          seq.serialize_element(&ScalarValue(v))?;
+        }
+        seq.end()
+      }
+      _ => unreachable!("accepts() checked the type"),
+    }
+  }
+}
+
+// A scalar value that has already been checked against its type
+struct ScalarValue<'a>(&'a DynamicValue);
+
+impl Serialize for ScalarValue<'_> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    match self.0 {
+      DynamicValue::Bool(b) => serializer.serialize_bool(*b),
+      DynamicValue::Byte(x) | DynamicValue::Char(x) | DynamicValue::UInt8(x) => {
+        serializer.serialize_u8(*x)
+      }
+      DynamicValue::Float32(x) => serializer.serialize_f32(*x),
+      DynamicValue::Float64(x) => serializer.serialize_f64(*x),
+      DynamicValue::Int8(x) => serializer.serialize_i8(*x),
+      DynamicValue::Int16(x) => serializer.serialize_i16(*x),
+      DynamicValue::UInt16(x) => serializer.serialize_u16(*x),
+      DynamicValue::Int32(x) => serializer.serialize_i32(*x),
+      DynamicValue::UInt32(x) => serializer.serialize_u32(*x),
+      DynamicValue::Int64(x) => serializer.serialize_i64(*x),
+      DynamicValue::UInt64(x) => serializer.serialize_u64(*x),
+      DynamicValue::String(s) => serializer.serialize_str(s),
+      DynamicValue::WString(s) => WString::from(s.as_str()).serialize(serializer),
+      DynamicValue::Message(m) => m.serialize(serializer),
+      DynamicValue::Array(_) => Err(ser::Error::custom("Nested arrays are not supported")),
+    }
+  }
+}
+
+// Deserialization
+
+impl<'de> DeserializeSeed<'de> for DynamicMessageType {
+  type Value = DynamicMessage;
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<DynamicMessage, D::Error> {
+    (&self).deserialize(deserializer)
+  }
+}
+
+impl<'de> DeserializeSeed<'de> for &DynamicMessageType {
+  type Value = DynamicMessage;
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<DynamicMessage, D::Error> {
+    if deserializer.is_human_readable() {
+      return deserializer.deserialize_map(MessageVisitor(self));
+    }
+    let len = self.fields().len().max(1);
+    deserializer.deserialize_tuple(len, MessageVisitor(self))
+  }
+}
+
+struct MessageVisitor<'a>(&'a DynamicMessageType);
+
+impl<'de> Visitor<'de> for MessageVisitor<'_> {
+  type Value = DynamicMessage;
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    write!(formatter, "message {:?}", self.0)
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DynamicMessage, A::Error> {
+    let fields = self.0.fields();
+    if fields.is_empty() {
+      // Dummy member
+      seq
+        .next_element::<u8>()?
+        .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+    }
+    let mut values = Vec::with_capacity(fields.len());
+    for (i, field) in fields.iter().enumerate() {
+      let value = seq
+        .next_element_seed(&field.field_type)?
+        .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+      if !field.field_type.accepts(&value) {
+        return Err(de::Error::custom(DynamicMessageError::TypeMismatch(
+          field.name.clone(),
+        )));
+      }
+      values.push(value);
+    }
+    Ok(DynamicMessage {
+      message_type: self.0.clone(),
+      values,
+    })
+  }
+
+  // Fields by name, from human-readable formats. Missing fields get their
+  // default values.
+  fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<DynamicMessage, A::Error> {
+    let mut message = DynamicMessage::new(self.0);
+    while let Some(name) = map.next_key::<String>()? {
+      let index = self.0.field_index(&name).map_err(de::Error::custom)?;
+      let field = &self.0.fields()[index];
+      let value = map.next_value_seed(&field.field_type)?;
+      if !field.field_type.accepts(&value) {
+        return Err(de::Error::custom(DynamicMessageError::TypeMismatch(
+          field.name.clone(),
+        )));
+      }
+      message.values[index] = value;
+    }
+    Ok(message)
+  }
+}
+
+impl<'de> DeserializeSeed<'de> for &FieldType {
+  type Value = DynamicValue;
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<DynamicValue, D::Error> {
+    match self {
+      FieldType::Scalar(s) => s.deserialize(deserializer),
+      FieldType::Array(s, len) => deserializer.deserialize_tuple(*len, ArrayVisitor(s)),
+      FieldType::Sequence(s, _) => deserializer.deserialize_seq(ArrayVisitor(s)),
+    }
+  }
+}
+
+struct ArrayVisitor<'a>(&'a ScalarType);
+
+impl<'de> Visitor<'de> for ArrayVisitor<'_> {
+  type Value = DynamicValue;
+
+  fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    write!(formatter, "array of {:?}", self.0)
+  }
+
+  fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DynamicValue, A::Error> {
+    // Do not trust the length in received data for preallocation.
+    let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+    while let Some(value) = seq.next_element_seed(self.0)? {
+      values.push(value);
+    }
+    Ok(DynamicValue::Array(values))
+  }
+}
+
+impl<'de> DeserializeSeed<'de> for &ScalarType {
+  type Value = DynamicValue;
+
+  fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<DynamicValue, D::Error> {
+    Ok(match self {
+      ScalarType::Bool => DynamicValue::Bool(bool::deserialize(deserializer)?),
+      ScalarType::Byte => DynamicValue::Byte(u8::deserialize(deserializer)?),
+      ScalarType::Char => DynamicValue::Char(u8::deserialize(deserializer)?),
+      ScalarType::Float32 => DynamicValue::Float32(f32::deserialize(deserializer)?),
+      ScalarType::Float64 => DynamicValue::Float64(f64::deserialize(deserializer)?),
+      ScalarType::Int8 => DynamicValue::Int8(i8::deserialize(deserializer)?),
+      ScalarType::UInt8 => DynamicValue::UInt8(u8::deserialize(deserializer)?),
+      ScalarType::Int16 => DynamicValue::Int16(i16::deserialize(deserializer)?),
+      ScalarType::UInt16 => DynamicValue::UInt16(u16::deserialize(deserializer)?),
+      ScalarType::Int32 => DynamicValue::Int32(i32::deserialize(deserializer)?),
+      ScalarType::UInt32 => DynamicValue::UInt32(u32::deserialize(deserializer)?),
+      ScalarType::Int64 => DynamicValue::Int64(i64::deserialize(deserializer)?),
+      ScalarType::UInt64 => DynamicValue::UInt64(u64::deserialize(deserializer)?),
+      ScalarType::String(_) => DynamicValue::String(String::deserialize(deserializer)?),
+      ScalarType::WString(_) => {
+        DynamicValue::WString(WString::deserialize(deserializer)?.to_string())
+      }
+      ScalarType::Message(t) => DynamicValue::Message(t.deserialize(deserializer)?),
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use ros2_client_core::cdr::{from_bytes, from_bytes_seed, to_vec, Endianness};
+
+  use super::*;
+  use crate::{
+    builtin_interfaces::Time,
+    geometry_msgs::{Point, Pose, PoseStamped, Quaternion},
+    std_msgs::Header,
+  };
+
+  const POSE_STAMPED: &str = "\
+# A Pose with reference coordinate frame and timestamp
+std_msgs/Header header
+Pose pose
+================================================================================
+MSG: std_msgs/Header
+builtin_interfaces/Time stamp
+string frame_id
+================================================================================
+MSG: geometry_msgs/Pose
+Point position
+Quaternion orientation
+================================================================================
+MSG: geometry_msgs/Point
+float64 x
+float64 y
+float64 z
+================================================================================
+MSG: geometry_msgs/Quaternion
+float64 x 0
+float64 y 0
+float64 z 0
+float64 w 1
+";
+
+  #[test]
+  fn same_layout_as_static_type() {
+    let t =
+      DynamicMessageType::from_definition("geometry_msgs/msg/PoseStamped", POSE_STAMPED).unwrap();
+    assert_eq!(
+      t.name().dds_msg_type(),
+      "geometry_msgs::msg::dds_::PoseStamped_"
+    );
+    let mut dynamic = DynamicMessage::new(&t);
+
+    let mut header = match dynamic.get("header") {
+      Some(DynamicValue::Message(m)) => m.clone(),
+      other => panic!("{:?}", other),
+    };
+    header
+      .set("frame_id", DynamicValue::String("map".to_string()))
+      .unwrap();
+    assert_eq!(
+      header.set("frame_id", DynamicValue::Int32(1)),
+      Err(DynamicMessageError::TypeMismatch("frame_id".to_string()))
+    );
+    dynamic
+      .set("header", DynamicValue::Message(header))
+      .unwrap();
+    if let Some(DynamicValue::Message(pose)) = dynamic.get_mut("pose") {
+      if let Some(DynamicValue::Message(position)) = pose.get_mut("position") {
+        position.set("x", DynamicValue::Float64(2.5)).unwrap();
+      }
+    }
+
+    let static_message = PoseStamped {
+      header: Header::new(Time::ZERO, "map"),
+      pose: Pose {
+        position: Point::new(2.5, 0.0, 0.0),
+        orientation: Quaternion::default(),
+      },
+    };
+    assert_eq!(static_message.pose.orientation.w, 1.0);
+
+    let bytes = to_vec(&dynamic, Endianness::Little).unwrap();
+    assert_eq!(bytes, to_vec(&static_message, Endianness::Little).unwrap());
+    let (back, _) = from_bytes_seed(&bytes, Endianness::Little, &t).unwrap();
+    assert_eq!(back, dynamic);
+    let (back, _) = from_bytes::<PoseStamped>(&bytes, Endianness::Little).unwrap();
+    assert_eq!(back, static_message);
+  }
+
+  #[test]
+  fn arrays_and_errors() {
+    let t = DynamicMessageType::from_definition(
+      "my_msgs/Arrays",
+      "int32[3] fixed [1, 2, 3]\nstring<=4[<=2] names\nEmpty e\n====\nMSG: my_msgs/Empty\n",
+    )
+    .unwrap();
+    let mut m = DynamicMessage::new(&t);
+    let ints = |v: &[i32]| DynamicValue::Array(v.iter().map(|i| DynamicValue::Int32(*i)).collect());
+    assert_eq!(m.get("fixed"), Some(&ints(&[1, 2, 3])));
+    assert!(m.set("fixed", ints(&[1, 2])).is_err());
+    let names = |v: &[&str]| {
+      DynamicValue::Array(
+        v.iter()
+          .map(|s| DynamicValue::String(s.to_string()))
+          .collect(),
+      )
+    };
+    assert!(m.set("names", names(&["a", "b", "c"])).is_err());
+    assert!(m.set("names", names(&["toolong"])).is_err());
+    m.set("names", names(&["a", "bc"])).unwrap();
+
+    let bytes = to_vec(&m, Endianness::Little).unwrap();
+    let (back, _) = from_bytes_seed(&bytes, Endianness::Little, &t).unwrap();
+    assert_eq!(back, m);
+
+    assert_eq!(
+      DynamicMessageType::from_definition("my_msgs/A", "B b\n").unwrap_err(),
+      DynamicMessageError::UnknownType("my_msgs/B".to_string())
+    );
+    assert_eq!(
+      DynamicMessageType::from_definition("A", "int32 x\n").unwrap_err(),
+      DynamicMessageError::BadTypeName("A".to_string())
+    );
+  }
+}
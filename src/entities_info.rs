@@ -84,6 +84,16 @@ impl NodeEntitiesInfo {
     self.name.fully_qualified_name()
   }
 
+  /// Readers (Subscriptions, and Service Clients and Servers) of this Node
+  pub fn readers(&self) -> &[Gid] {
+    &self.reader_gid_seq
+  }
+
+  /// Writers (Publishers, and Service Clients and Servers) of this Node
+  pub fn writers(&self) -> &[Gid] {
+    &self.writer_gid_seq
+  }
+
   pub fn add_writer(&mut self, gid: Gid) {
     if !self.writer_gid_seq.contains(&gid) {
       self.writer_gid_seq.push(gid);
@@ -95,6 +105,14 @@ impl NodeEntitiesInfo {
       self.reader_gid_seq.push(gid);
     }
   }
+
+  pub fn remove_writer(&mut self, gid: Gid) {
+    self.writer_gid_seq.retain(|w| *w != gid);
+  }
+
+  pub fn remove_reader(&mut self, gid: Gid) {
+    self.reader_gid_seq.retain(|r| *r != gid);
+  }
 }
 
 impl TryFrom<repr::NodeEntitiesInfo> for NodeEntitiesInfo {
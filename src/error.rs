@@ -0,0 +1,121 @@
+//! Error type of ros2-client
+//!
+//! Entity creation functions return [`Error`], which tells which entity failed
+//! to be created, on which Topic or Service, and why.
+
+use std::fmt;
+
+use rustdds::dds::{CreateError, ReadError, WriteError};
+
+use crate::{names::NameError, service::CallServiceError};
+
+/// Kind of entity whose creation failed. See [`Error::Create`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityKind {
+  Context,
+  Topic,
+  Publisher,
+  Subscription,
+  Client,
+  Server,
+}
+
+impl fmt::Display for EntityKind {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let s = match self {
+      EntityKind::Context => "Context",
+      EntityKind::Topic => "Topic",
+      EntityKind::Publisher => "Publisher",
+      EntityKind::Subscription => "Subscription",
+      EntityKind::Client => "Client",
+      EntityKind::Server => "Server",
+    };
+    f.write_str(s)
+  }
+}
+
+/// What went wrong in ros2-client
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+  /// DDS could not create an entity. `name` is the Topic or Service name the
+  /// entity was for, or the DDS Domain for a Context.
+  #[error("Cannot create {kind} {name:?}: {source}")]
+  Create {
+    kind: EntityKind,
+    name: String,
+    #[source]
+    source: CreateError,
+  },
+
+  /// A Node Parameter was rejected when creating a Node.
+  #[error("Bad Parameter: {0}")]
+  BadParameter(String),
+
+  /// ROS command line arguments, i.e. those after `--ros-args`, could not be
+  /// parsed.
+  #[error("Bad ROS arguments: {0}")]
+  BadArguments(String),
+
+  /// A Node, Topic, Service or Action name violates the ROS 2 naming rules.
+  #[error("Bad name: {0}")]
+  Name(#[from] NameError),
+
+  #[error("DDS read failed: {0}")]
+  Read(#[from] ReadError),
+
+  /// The data that could not be written is not carried along. Use the
+  /// [`WriteError`] returned by the write operation to get it back.
+  #[error("DDS write failed: {0}")]
+  Write(WriteError<()>),
+
+  /// A Service call got no response in time.
+  #[error("Service call timed out")]
+  CallTimeout,
+}
+
+/// A wait did not finish before its timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Timed out")]
+pub struct WaitTimeout;
+
+impl<D> From<WriteError<D>> for Error {
+  fn from(e: WriteError<D>) -> Self {
+    Error::Write(e.forget_data())
+  }
+}
+
+impl<D> From<CallServiceError<D>> for Error {
+  fn from(e: CallServiceError<D>) -> Self {
+    match e {
+      CallServiceError::WriteError(e) => e.into(),
+      CallServiceError::ReadError(e) => Error::Read(e),
+      CallServiceError::CallTimeout => Error::CallTimeout,
+    }
+  }
+}
+
+/// Result type of ros2-client
+pub type Result<T> = std::result::Result<T, Error>;
+
+// Adds context to a CreateError. Use as `.map_err(create_error(kind, name))`.
+pub(crate) fn create_error(kind: EntityKind, name: &str) -> impl FnOnce(CreateError) -> Error {
+  let name = name.to_owned();
+  move |source| Error::Create { kind, name, source }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn create_error_display() {
+    let e = create_error(EntityKind::Publisher, "/chatter")(CreateError::BadParameter {
+      reason: "no".to_string(),
+    });
+    assert_eq!(
+      e.to_string(),
+      "Cannot create Publisher \"/chatter\": Invalid call parameter: no"
+    );
+    assert!(std::error::Error::source(&e).is_some());
+  }
+}
@@ -0,0 +1,399 @@
+//! Running the Spinners of several Nodes together with their callbacks
+//!
+//! Instead of running each [`Spinner`](crate::Spinner) and message loop by
+//! hand, add Nodes, Subscriptions, Servers and timers to an [`Executor`]:
+//! * [`SingleThreadedExecutor`] runs everything in the thread that calls
+//!   [`spin`](SingleThreadedExecutor::spin). Callbacks never run concurrently.
+//! * [`MultiThreadedExecutor`] spawns each of them as a task of a
+//!   multi-threaded async runtime, e.g. tokio or async-std. Callbacks of
+//!   different entities may run in parallel, unless they are in the same
+//!   mutually exclusive [`CallbackGroup`]. The callbacks of one entity still
+//!   run one at a time.
+//!
+//! ```no_run
+//! use ros2_client::{executor::*, *};
+//!
+//! let context = Context::new().unwrap();
+//! let mut node = context
+//!   .new_node(NodeName::new("/", "listener").unwrap(), NodeOptions::new())
+//!   .unwrap();
+//! let topic = node
+//!   .create_topic(
+//!     &Name::new("/", "chatter").unwrap(),
+//!     MessageTypeName::new("std_msgs", "String"),
+//!     &DEFAULT_SUBSCRIPTION_QOS,
+//!   )
+//!   .unwrap();
+//! let subscription = node.create_subscription::<String>(&topic, None).unwrap();
+//!
+//! let mut executor = SingleThreadedExecutor::new();
+//! executor.add_node(&mut node).unwrap();
+//! executor.add_subscription(subscription, None, |msg, _info| {
+//!   println!("I heard: {msg}")
+//! });
+//! executor.add_timer(std::time::Duration::from_secs(1), None, || println!("tick"));
+//! executor.spin_blocking();
+//! ```
+
+use std::{
+  future::Future,
+  panic::{catch_unwind, AssertUnwindSafe},
+  sync::{Arc, Mutex},
+  task::Poll,
+  time::Duration,
+};
+
+use futures::{
+  future::{self, BoxFuture},
+  pin_mut,
+  stream::FuturesUnordered,
+  StreamExt,
+};
+use serde::de::DeserializeOwned;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  error::Error,
+  message::Message,
+  message_info::MessageInfo,
+  node::Node,
+  pubsub::Subscription,
+  service::{panic_message, Server, Service},
+};
+
+/// Runs Node Spinners and callbacks as tasks
+///
+/// Implementors only need to provide [`spawn`](Self::spawn).
+pub trait Executor {
+  /// Runs `task` in this Executor.
+  fn spawn(&mut self, task: BoxFuture<'static, ()>);
+
+  /// Runs the [`Spinner`](crate::Spinner) of `node`. The Spinner stops when
+  /// `node` is dropped. Spinner errors are logged.
+  fn add_node(&mut self, node: &mut Node) -> Result<(), Error> {
+    let name = node.fully_qualified_name();
+    let spinner = node.spinner()?;
+    self.spawn(Box::pin(async move {
+      spinner
+        .spin()
+        .await
+        .unwrap_or_else(|e| error!("Spinner of {name} failed: {e:?}"));
+    }));
+    Ok(())
+  }
+
+  /// Runs `callback` on each message of `subscription`, within `group`, if
+  /// given.
+  ///
+  /// Receive errors are logged and skipped. A panicking callback is logged,
+  /// and receives the next message as usual.
+  fn add_subscription<M, F>(
+    &mut self,
+    subscription: Subscription<M>,
+    group: Option<&CallbackGroup>,
+    mut callback: F,
+  ) where
+    Self: Sized,
+    M: 'static + DeserializeOwned + Send + Sync,
+    F: FnMut(M, MessageInfo) + Send + 'static,
+  {
+    let group = group.cloned();
+    self.spawn(Box::pin(async move {
+      subscription
+        .for_each(|message, info| {
+          run_callback(group.as_ref(), "Subscription", || callback(message, info));
+        })
+        .await
+    }));
+  }
+
+  /// Answers each request to `server` with `handler`, within `group`, if
+  /// given, until reading requests fails.
+  ///
+  /// If `handler` panics, the request gets no response, as in
+  /// [`Server::serve`].
+  fn add_server<S, F>(&mut self, server: Server<S>, group: Option<&CallbackGroup>, mut handler: F)
+  where
+    Self: Sized,
+    S: 'static + Service,
+    S::Request: Message + Send + Sync,
+    S::Response: Message + Send + Sync,
+    F: FnMut(S::Request) -> S::Response + Send + 'static,
+  {
+    let group = group.cloned();
+    self.spawn(Box::pin(async move {
+      loop {
+        let (req_id, request) = match server.async_receive_request().await {
+          Ok(r) => r,
+          Err(e) => {
+            error!("Executor: Server stops, as receiving requests failed: {e:?}");
+            return;
+          }
+        };
+        let mut response = None;
+        run_callback(group.as_ref(), "Server", || {
+          response = Some(handler(request))
+        });
+        if let Some(response) = response {
+          server
+            .async_send_response(req_id, response)
+            .await
+            .unwrap_or_else(|e| error!("Executor: Cannot send response to {req_id:?}: {e:?}"));
+        }
+      }
+    }));
+  }
+
+  /// Runs `callback` every `period` of wall-clock time, starting one `period`
+  /// from now, within `group`, if given. If a callback takes longer than
+  /// `period`, missed calls are skipped.
+  fn add_timer<F>(&mut self, period: Duration, group: Option<&CallbackGroup>, mut callback: F)
+  where
+    Self: Sized,
+    F: FnMut() + Send + 'static,
+  {
+    let group = group.cloned();
+    self.spawn(Box::pin(async move {
+      let ticks = async_io::Timer::interval(period);
+      pin_mut!(ticks);
+      while ticks.next().await.is_some() {
+        run_callback(group.as_ref(), "Timer", &mut callback);
+      }
+    }));
+  }
+}
+
+/// Kinds of [`CallbackGroup`], as in rclcpp
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallbackGroupType {
+  /// At most one callback of the group runs at a time.
+  MutuallyExclusive,
+  /// Callbacks of the group may run in parallel.
+  Reentrant,
+}
+
+/// Controls which callbacks of an [`Executor`] may run concurrently
+///
+/// Callbacks in the same mutually exclusive group never overlap, so they can
+/// share state without further locking. Callbacks in a reentrant group, or in
+/// no group, may run in parallel with any other callbacks.
+///
+/// This only matters for the [`MultiThreadedExecutor`], as the
+/// [`SingleThreadedExecutor`] runs one callback at a time anyway. Clones
+/// refer to the same group.
+#[derive(Clone, Debug)]
+pub struct CallbackGroup {
+  group_type: CallbackGroupType,
+  // Held while a callback of a mutually exclusive group runs
+  lock: Arc<Mutex<()>>,
+}
+
+impl CallbackGroup {
+  pub fn new(group_type: CallbackGroupType) -> Self {
+    CallbackGroup {
+      group_type,
+      lock: Arc::new(Mutex::new(())),
+    }
+  }
+
+  pub fn mutually_exclusive() -> Self {
+    Self::new(CallbackGroupType::MutuallyExclusive)
+  }
+
+  pub fn reentrant() -> Self {
+    Self::new(CallbackGroupType::Reentrant)
+  }
+
+  pub fn group_type(&self) -> CallbackGroupType {
+    self.group_type
+  }
+
+  // Runs `callback` as a member of this group. This blocks while another
+  // callback of a mutually exclusive group runs.
+  fn run<R>(&self, callback: impl FnOnce() -> R) -> R {
+    match self.group_type {
+      CallbackGroupType::MutuallyExclusive => {
+        // A panicking callback poisons the lock, but cannot leave the unit
+        // value inconsistent.
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        callback()
+      }
+      CallbackGroupType::Reentrant => callback(),
+    }
+  }
+}
+
+// Runs a callback within `group`, logging instead of propagating a panic.
+fn run_callback(group: Option<&CallbackGroup>, kind: &str, callback: impl FnOnce()) {
+  let callback = AssertUnwindSafe(callback);
+  let result = match group {
+    Some(group) => catch_unwind(|| group.run(callback)),
+    None => catch_unwind(callback),
+  };
+  if let Err(payload) = result {
+    error!(
+      "{kind} callback panicked: {}",
+      panic_message(payload.as_ref())
+    );
+  }
+}
+
+/// Runs all of its tasks in the thread calling [`spin`](Self::spin)
+///
+/// Tasks only make progress while one of the spin methods runs.
+#[derive(Default)]
+pub struct SingleThreadedExecutor {
+  tasks: FuturesUnordered<BoxFuture<'static, ()>>,
+}
+
+impl SingleThreadedExecutor {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Runs the tasks until all of them have finished. Node Spinners run until
+  /// their Node is dropped, and the other tasks until their entity fails, so
+  /// this usually runs forever.
+  pub async fn spin(&mut self) {
+    while self.tasks.next().await.is_some() {}
+  }
+
+  /// Like [`spin`](Self::spin), but blocks the calling thread.
+  pub fn spin_blocking(&mut self) {
+    futures::executor::block_on(self.spin())
+  }
+
+  /// Runs the tasks until `future` completes, and returns its output.
+  ///
+  /// Unfinished tasks are kept, and continue in the next spin call. This is
+  /// like `spin_until_future_complete` in rclcpp.
+  pub async fn spin_until<F: Future>(&mut self, future: F) -> F::Output {
+    let tasks = &mut self.tasks;
+    let run_tasks = future::poll_fn(|cx| {
+      // Finished tasks are dropped. When there are none left, wait forever.
+      while let Poll::Ready(Some(())) = tasks.poll_next_unpin(cx) {}
+      Poll::<()>::Pending
+    });
+    pin_mut!(future, run_tasks);
+    match future::select(future, run_tasks).await {
+      future::Either::Left((output, _)) => output,
+      future::Either::Right(((), _)) => unreachable!("run_tasks never completes"),
+    }
+  }
+
+  /// Number of tasks that have not finished yet
+  pub fn task_count(&self) -> usize {
+    self.tasks.len()
+  }
+}
+
+impl Executor for SingleThreadedExecutor {
+  fn spawn(&mut self, task: BoxFuture<'static, ()>) {
+    self.tasks.push(task);
+  }
+}
+
+/// Spawns each task to a multi-threaded async runtime
+///
+/// The runtime is given as a spawn function, e.g. for tokio
+/// ```ignore
+/// let handle = tokio::runtime::Handle::current();
+/// let executor = MultiThreadedExecutor::new(move |task| {
+///   handle.spawn(task);
+/// });
+/// ```
+/// or `|task| { async_std::task::spawn(task); }` for async-std. Tasks start
+/// as soon as they are added, and keep running after the Executor is dropped.
+pub struct MultiThreadedExecutor {
+  spawn: Box<dyn FnMut(BoxFuture<'static, ()>) + Send>,
+}
+
+impl MultiThreadedExecutor {
+  pub fn new<F>(spawn: F) -> Self
+  where
+    F: FnMut(BoxFuture<'static, ()>) + Send + 'static,
+  {
+    MultiThreadedExecutor {
+      spawn: Box::new(spawn),
+    }
+  }
+}
+
+impl Executor for MultiThreadedExecutor {
+  fn spawn(&mut self, task: BoxFuture<'static, ()>) {
+    (self.spawn)(task)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+  };
+
+  use super::*;
+
+  #[test]
+  fn spin_until_keeps_unfinished_tasks() {
+    let mut executor = SingleThreadedExecutor::new();
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let ticks_in_timer = Arc::clone(&ticks);
+    executor.add_timer(Duration::from_millis(10), None, move || {
+      ticks_in_timer.fetch_add(1, Ordering::SeqCst);
+    });
+    executor.add_timer(Duration::from_millis(10), None, || panic!("timer panic"));
+
+    let output = futures::executor::block_on(
+      executor.spin_until(async_io::Timer::after(Duration::from_millis(100))),
+    );
+    let seen = ticks.load(Ordering::SeqCst);
+    assert!(seen >= 3, "{}", seen);
+    assert!(output <= std::time::Instant::now());
+    // Both timers are still there, despite the panics.
+    assert_eq!(executor.task_count(), 2);
+
+    futures::executor::block_on(
+      executor.spin_until(async_io::Timer::after(Duration::from_millis(50))),
+    );
+    assert!(ticks.load(Ordering::SeqCst) > seen);
+  }
+
+  // Runs four overlapping callbacks in `group`. Returns how many of them ran
+  // at the same time, at most.
+  fn max_concurrency(group: &CallbackGroup) -> usize {
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+    let threads = (0..4)
+      .map(|_| {
+        let (group, running, max_running) = (
+          group.clone(),
+          Arc::clone(&running),
+          Arc::clone(&max_running),
+        );
+        thread::spawn(move || {
+          run_callback(Some(&group), "Test", || {
+            let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(now_running, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(50));
+            running.fetch_sub(1, Ordering::SeqCst);
+          })
+        })
+      })
+      .collect::<Vec<_>>();
+    threads.into_iter().for_each(|t| t.join().unwrap());
+    max_running.load(Ordering::SeqCst)
+  }
+
+  #[test]
+  fn callback_groups() {
+    assert_eq!(max_concurrency(&CallbackGroup::mutually_exclusive()), 1);
+    assert!(max_concurrency(&CallbackGroup::reentrant()) > 1);
+
+    // A panic does not block the rest of the group.
+    let group = CallbackGroup::mutually_exclusive();
+    run_callback(Some(&group), "Test", || panic!("callback panic"));
+    assert_eq!(max_concurrency(&group), 1);
+  }
+}
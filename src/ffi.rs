@@ -0,0 +1,419 @@
+//! C ABI for embedding ros2-client in C and C++ applications
+//!
+//! Enabled with the `ffi` feature. Messages cross the boundary as serialized
+//! CDR payloads, i.e. the bytes that follow the 4-byte encapsulation header in
+//! a DDS sample, little-endian. The C side is responsible for (de)serializing
+//! them, e.g. with code generated by its own message tooling. Received payloads
+//! may end in up to 3 bytes of padding, which CDR decoders ignore.
+//!
+//! The declarations are in `include/ros2_client.h`. To get a library to link
+//! against, build e.g.
+//! `cargo rustc --release --features ffi --crate-type staticlib`.
+//!
+//! Conventions:
+//! * Objects are opaque pointers. Each `*_new` function has a matching
+//!   `*_free`. A Node must outlive the Publishers, Subscriptions and Clients
+//!   created from it.
+//! * Functions returning `int` return 0 (or a positive value, where documented)
+//!   on success, and a negative value on failure. Constructors return NULL on
+//!   failure. In both cases [`ros2_last_error`] describes the failure.
+//! * Strings are NUL-terminated UTF-8.
+//! * The functions must not be called concurrently on the same object, except
+//!   on a Context.
+
+use std::{
+  cell::RefCell,
+  ffi::{CStr, CString},
+  fmt,
+  os::raw::{c_char, c_int},
+  ptr, slice,
+  time::Duration,
+};
+
+use futures::{pin_mut, FutureExt};
+
+use crate::{
+  context::{Context, DEFAULT_PUBLISHER_QOS, DEFAULT_SUBSCRIPTION_QOS},
+  message::SerializedMessage,
+  names::{MessageTypeName, Name, NodeName, ServiceTypeName},
+  node::{Node, NodeOptions},
+  pubsub::{Publisher, Subscription},
+  service::{AService, Client, ServiceMapping},
+};
+
+type RawService = AService<SerializedMessage, SerializedMessage>;
+
+/// Opaque Context handle
+pub struct Ros2Context(Context);
+/// Opaque Node handle
+pub struct Ros2Node(Node);
+/// Opaque Publisher handle
+pub struct Ros2Publisher(Publisher<SerializedMessage>);
+/// Opaque Subscription handle
+pub struct Ros2Subscription(Subscription<SerializedMessage>);
+/// Opaque Service Client handle
+pub struct Ros2Client(Client<RawService>);
+
+thread_local! {
+  static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl fmt::Display) {
+  // Interior NUL bytes cannot be represented. Drop them rather than the message.
+  let message = message.to_string().replace('\0', "");
+  LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(message).ok());
+}
+
+// Runs `f`, storing its error, or any panic, for ros2_last_error().
+fn guard<T>(f: impl FnOnce() -> Result<T, String>) -> Option<T> {
+  match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+    Ok(Ok(t)) => Some(t),
+    Ok(Err(e)) => {
+      set_last_error(e);
+      None
+    }
+    Err(panic) => {
+      set_last_error(format!(
+        "panic: {}",
+        crate::service::panic_message(panic.as_ref())
+      ));
+      None
+    }
+  }
+}
+
+fn guard_int(f: impl FnOnce() -> Result<c_int, String>) -> c_int {
+  guard(f).unwrap_or(-1)
+}
+
+fn guard_ptr<T>(f: impl FnOnce() -> Result<T, String>) -> *mut T {
+  guard(f).map_or(ptr::null_mut(), |t| Box::into_raw(Box::new(t)))
+}
+
+unsafe fn str_arg<'a>(s: *const c_char, what: &str) -> Result<&'a str, String> {
+  if s.is_null() {
+    return Err(format!("{what} is NULL"));
+  }
+  CStr::from_ptr(s)
+    .to_str()
+    .map_err(|e| format!("{what} is not UTF-8: {e}"))
+}
+
+unsafe fn ref_arg<'a, T>(p: *mut T, what: &str) -> Result<&'a mut T, String> {
+  p.as_mut().ok_or_else(|| format!("{what} is NULL"))
+}
+
+unsafe fn bytes_arg(data: *const u8, len: usize) -> Result<Vec<u8>, String> {
+  if len == 0 {
+    Ok(Vec::new())
+  } else if data.is_null() {
+    Err("data is NULL".to_string())
+  } else {
+    Ok(slice::from_raw_parts(data, len).to_vec())
+  }
+}
+
+// Hands a buffer over to C. It must be released with ros2_bytes_free().
+unsafe fn bytes_out(bytes: Vec<u8>, data: *mut *mut u8, len: *mut usize) {
+  let boxed = bytes.into_boxed_slice();
+  *len = boxed.len();
+  *data = Box::into_raw(boxed) as *mut u8;
+}
+
+unsafe fn free<T>(p: *mut T) {
+  if !p.is_null() {
+    drop(Box::from_raw(p));
+  }
+}
+
+/// Returns a description of the latest error on this thread, or NULL if there
+/// has been none. The string is valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn ros2_last_error() -> *const c_char {
+  LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+/// Creates a Context on DDS Domain 0.
+#[no_mangle]
+pub extern "C" fn ros2_context_new() -> *mut Ros2Context {
+  guard_ptr(|| Context::new().map(Ros2Context).map_err(|e| e.to_string()))
+}
+
+/// # Safety
+///
+/// `ctx` must be NULL or from `ros2_context_new`, and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_context_free(ctx: *mut Ros2Context) {
+  free(ctx)
+}
+
+/// Creates a Node, with a background thread running its Spinner.
+///
+/// # Safety
+///
+/// `ctx` must be a live Context. The strings must be NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_node_new(
+  ctx: *mut Ros2Context,
+  namespace: *const c_char,
+  name: *const c_char,
+) -> *mut Ros2Node {
+  guard_ptr(|| {
+    let ctx = ref_arg(ctx, "ctx")?;
+    let node_name = NodeName::new(str_arg(namespace, "namespace")?, str_arg(name, "name")?)
+      .map_err(|e| e.to_string())?;
+    let mut node = ctx
+      .0
+      .new_node(node_name, NodeOptions::new())
+      .map_err(|e| e.to_string())?;
+    let spinner = node.spinner().map_err(|e| e.to_string())?;
+    // The Spinner stops when the Node is dropped.
+    std::thread::spawn(move || futures::executor::block_on(spinner.spin()));
+    Ok(Ros2Node(node))
+  })
+}
+
+/// # Safety
+///
+/// `node` must be NULL or from `ros2_node_new`, and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_node_free(node: *mut Ros2Node) {
+  free(node)
+}
+
+unsafe fn create_topic(
+  node: &Node,
+  topic: *const c_char,
+  type_package: *const c_char,
+  type_name: *const c_char,
+  qos: &rustdds::QosPolicies,
+) -> Result<rustdds::Topic, String> {
+  let topic_name = Name::parse(str_arg(topic, "topic")?).map_err(|e| e.to_string())?;
+  let type_name = MessageTypeName::new(
+    str_arg(type_package, "type_package")?,
+    str_arg(type_name, "type_name")?,
+  );
+  node
+    .create_topic(&topic_name, type_name, qos)
+    .map_err(|e| e.to_string())
+}
+
+/// Creates a Publisher with default QoS. The type is given as e.g.
+/// `"std_msgs"`, `"String"`.
+///
+/// # Safety
+///
+/// `node` must be a live Node. The strings must be NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_publisher_new(
+  node: *mut Ros2Node,
+  topic: *const c_char,
+  type_package: *const c_char,
+  type_name: *const c_char,
+) -> *mut Ros2Publisher {
+  guard_ptr(|| {
+    let node = &mut ref_arg(node, "node")?.0;
+    let topic = create_topic(node, topic, type_package, type_name, &DEFAULT_PUBLISHER_QOS)?;
+    node
+      .create_publisher(&topic, None)
+      .map(Ros2Publisher)
+      .map_err(|e| e.to_string())
+  })
+}
+
+/// # Safety
+///
+/// `publisher` must be NULL or from `ros2_publisher_new`, and not freed
+/// already.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_publisher_free(publisher: *mut Ros2Publisher) {
+  free(publisher)
+}
+
+/// Publishes a serialized message of `len` bytes.
+///
+/// # Safety
+///
+/// `publisher` must be live, and `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_publish(
+  publisher: *mut Ros2Publisher,
+  data: *const u8,
+  len: usize,
+) -> c_int {
+  guard_int(|| {
+    let publisher = ref_arg(publisher, "publisher")?;
+    let message = SerializedMessage(bytes_arg(data, len)?);
+    publisher
+      .0
+      .publish(message)
+      .map(|()| 0)
+      .map_err(|e| e.to_string())
+  })
+}
+
+/// Creates a Subscription with default QoS.
+///
+/// # Safety
+///
+/// `node` must be a live Node. The strings must be NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_subscription_new(
+  node: *mut Ros2Node,
+  topic: *const c_char,
+  type_package: *const c_char,
+  type_name: *const c_char,
+) -> *mut Ros2Subscription {
+  guard_ptr(|| {
+    let node = &mut ref_arg(node, "node")?.0;
+    let topic = create_topic(
+      node,
+      topic,
+      type_package,
+      type_name,
+      &DEFAULT_SUBSCRIPTION_QOS,
+    )?;
+    node
+      .create_subscription(&topic, None)
+      .map(Ros2Subscription)
+      .map_err(|e| e.to_string())
+  })
+}
+
+/// # Safety
+///
+/// `subscription` must be NULL or from `ros2_subscription_new`, and not freed
+/// already.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_subscription_free(subscription: *mut Ros2Subscription) {
+  free(subscription)
+}
+
+/// Takes one received message without blocking.
+///
+/// Returns 1 and stores the message in `*data` and `*len` if there was one,
+/// or 0 if there was not. The message must be released with
+/// `ros2_bytes_free`.
+///
+/// # Safety
+///
+/// `subscription` must be live. `data` and `len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_take(
+  subscription: *mut Ros2Subscription,
+  data: *mut *mut u8,
+  len: *mut usize,
+) -> c_int {
+  guard_int(|| {
+    let subscription = ref_arg(subscription, "subscription")?;
+    if data.is_null() || len.is_null() {
+      return Err("data or len is NULL".to_string());
+    }
+    match subscription.0.take().map_err(|e| e.to_string())? {
+      None => Ok(0),
+      Some((message, _info)) => {
+        bytes_out(message.0, data, len);
+        Ok(1)
+      }
+    }
+  })
+}
+
+/// Releases a buffer returned by `ros2_take` or `ros2_call`.
+///
+/// # Safety
+///
+/// `data` and `len` must be as returned, and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_bytes_free(data: *mut u8, len: usize) {
+  if !data.is_null() {
+    drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+  }
+}
+
+/// Creates a Service Client with default QoS. The type is given as e.g.
+/// `"example_interfaces"`, `"AddTwoInts"`.
+///
+/// # Safety
+///
+/// `node` must be a live Node. The strings must be NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_client_new(
+  node: *mut Ros2Node,
+  service: *const c_char,
+  type_package: *const c_char,
+  type_name: *const c_char,
+) -> *mut Ros2Client {
+  guard_ptr(|| {
+    let node = &mut ref_arg(node, "node")?.0;
+    let service_name = Name::parse(str_arg(service, "service")?).map_err(|e| e.to_string())?;
+    let type_name = ServiceTypeName::new(
+      str_arg(type_package, "type_package")?,
+      str_arg(type_name, "type_name")?,
+    );
+    node
+      .create_client::<RawService>(
+        ServiceMapping::Enhanced,
+        &service_name,
+        &type_name,
+        DEFAULT_PUBLISHER_QOS.clone(),
+        DEFAULT_SUBSCRIPTION_QOS.clone(),
+      )
+      .map(Ros2Client)
+      .map_err(|e| e.to_string())
+  })
+}
+
+/// # Safety
+///
+/// `client` must be NULL or from `ros2_client_new`, and not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_client_free(client: *mut Ros2Client) {
+  free(client)
+}
+
+/// Calls the Service and blocks until the response arrives, or `timeout_ms`
+/// milliseconds have passed.
+///
+/// Returns 0 and stores the response in `*response` and `*response_len`, which
+/// must be released with `ros2_bytes_free`. Returns -2 on timeout.
+///
+/// # Safety
+///
+/// `client` must be live, and `request` must point to `request_len` readable
+/// bytes. `response` and `response_len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn ros2_call(
+  client: *mut Ros2Client,
+  request: *const u8,
+  request_len: usize,
+  timeout_ms: u32,
+  response: *mut *mut u8,
+  response_len: *mut usize,
+) -> c_int {
+  guard_int(|| {
+    let client = ref_arg(client, "client")?;
+    if response.is_null() || response_len.is_null() {
+      return Err("response or response_len is NULL".to_string());
+    }
+    let request = SerializedMessage(bytes_arg(request, request_len)?);
+    let call = client.0.async_call_service(request).fuse();
+    let timeout = async_io::Timer::after(Duration::from_millis(timeout_ms.into())).fuse();
+    pin_mut!(call, timeout);
+    futures::executor::block_on(async {
+      futures::select! {
+        result = call => match result {
+          Ok(message) => {
+            bytes_out(message.0, response, response_len);
+            Ok(0)
+          }
+          Err(e) => Err(format!("{e:?}")),
+        },
+        _ = timeout => {
+          set_last_error("Service call timed out");
+          Ok(-2)
+        }
+      }
+    })
+  })
+}
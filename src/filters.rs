@@ -0,0 +1,225 @@
+//! Adapters for streams of received messages
+//!
+//! These work on the streams from e.g. [`Subscription::async_stream`], i.e.
+//! streams of `ReadResult<(M, MessageInfo)>`. Read errors are passed through
+//! unchanged.
+//!
+//! [`Subscription::async_stream`]: crate::pubsub::Subscription::async_stream
+
+use std::{
+  cmp::Ordering,
+  collections::BinaryHeap,
+  task::{Context, Poll},
+  time::{Duration, Instant},
+};
+
+use async_io::Timer;
+use futures::{future, stream, stream::FusedStream, FutureExt, Stream, StreamExt};
+use rustdds::{dds::ReadResult, Timestamp};
+
+use crate::message_info::MessageInfo;
+
+/// Suppresses messages that are equal to the previous message.
+///
+/// This is useful for state topics that are republished periodically, when
+/// only the changes matter. The first message always passes.
+pub fn dedup<S, M, I, E>(stream: S) -> impl Stream<Item = Result<(M, I), E>>
+where
+  S: Stream<Item = Result<(M, I), E>>,
+  M: PartialEq + Clone,
+{
+  dedup_by_key(stream, M::clone)
+}
+
+/// Suppresses messages whose key is equal to the key of the previous message.
+///
+/// E.g. with key function `|s: &BatteryState| s.power_supply_status` only
+/// the messages where the supply status changed pass.
+pub fn dedup_by_key<S, M, I, E, K, F>(
+  stream: S,
+  mut key: F,
+) -> impl Stream<Item = Result<(M, I), E>>
+where
+  S: Stream<Item = Result<(M, I), E>>,
+  F: FnMut(&M) -> K,
+  K: PartialEq,
+{
+  let mut previous: Option<K> = None;
+  stream.filter(move |item| {
+    let pass = match item {
+      Ok((message, _info)) => {
+        let k = key(message);
+        let changed = previous.as_ref() != Some(&k);
+        previous = Some(k);
+        changed
+      }
+      Err(_) => true,
+    };
+    future::ready(pass)
+  })
+}
+
+/// Jitter buffer: holds messages for up to `window`, and releases them
+/// ordered by source timestamp.
+///
+/// This smooths out reordering on best-effort topics, at the cost of `window`
+/// of latency. Messages without a source timestamp are ordered by their
+/// arrival time. A message that arrives later than `window` after one with a
+/// later timestamp was released cannot be put in order, so it is released
+/// immediately.
+pub fn reorder_by_source_timestamp<S, M>(
+  stream: S,
+  window: Duration,
+) -> impl Stream<Item = ReadResult<(M, MessageInfo)>>
+where
+  S: Stream<Item = ReadResult<(M, MessageInfo)>>,
+{
+  reorder_by_key(stream, window, |_message, info| {
+    info.source_timestamp().unwrap_or_else(Timestamp::now)
+  })
+}
+
+/// Like [`reorder_by_source_timestamp`], but orders by any key.
+pub fn reorder_by_key<S, M, I, E, K, F>(
+  stream: S,
+  window: Duration,
+  mut key: F,
+) -> impl Stream<Item = Result<(M, I), E>>
+where
+  S: Stream<Item = Result<(M, I), E>>,
+  F: FnMut(&M, &I) -> K,
+  K: Ord,
+{
+  let mut inner = Box::pin(stream.fuse());
+  let mut buffer = BinaryHeap::new();
+  let mut arrivals: u64 = 0;
+  let mut timer: Option<Timer> = None;
+
+  stream::poll_fn(move |cx: &mut Context<'_>| loop {
+    // Buffer everything that has arrived.
+    while let Poll::Ready(item) = inner.poll_next_unpin(cx) {
+      match item {
+        Some(Ok((message, info))) => {
+          buffer.push(Held {
+            key: key(&message, &info),
+            arrival: arrivals,
+            deadline: Instant::now() + window,
+            item: (message, info),
+          });
+          arrivals += 1;
+        }
+        Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+        None => break,
+      }
+    }
+    let ended = inner.is_terminated();
+
+    // Release the first in order, if any message has waited long enough.
+    let now = Instant::now();
+    let earliest_deadline = buffer.iter().map(|h| h.deadline).min();
+    match earliest_deadline {
+      None if ended => return Poll::Ready(None),
+      None => return Poll::Pending,
+      Some(deadline) if ended || deadline <= now => {
+        return Poll::Ready(buffer.pop().map(|held| Ok(held.item)));
+      }
+      Some(deadline) => {
+        let t = timer.get_or_insert_with(|| Timer::at(deadline));
+        t.set_at(deadline);
+        if t.poll_unpin(cx).is_pending() {
+          return Poll::Pending;
+        }
+        // Timer expired. Go around again.
+      }
+    }
+  })
+}
+
+// A message in the reorder buffer. BinaryHeap is a max-heap, so the ordering
+// is reversed to pop the smallest key first. Equal keys are kept in arrival
+// order.
+struct Held<K, T> {
+  key: K,
+  arrival: u64,
+  deadline: Instant,
+  item: T,
+}
+
+impl<K: Ord, T> Ord for Held<K, T> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (&other.key, other.arrival).cmp(&(&self.key, self.arrival))
+  }
+}
+
+impl<K: Ord, T> PartialOrd for Held<K, T> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl<K: Ord, T> PartialEq for Held<K, T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl<K: Ord, T> Eq for Held<K, T> {}
+
+#[cfg(test)]
+mod test {
+  use futures::{executor::block_on, stream};
+
+  use super::*;
+
+  fn ok(m: i32) -> Result<(i32, ()), &'static str> {
+    Ok((m, ()))
+  }
+
+  fn messages<S>(s: S) -> Vec<Result<i32, &'static str>>
+  where
+    S: Stream<Item = Result<(i32, ()), &'static str>>,
+  {
+    block_on(s.map(|r| r.map(|(m, ())| m)).collect())
+  }
+
+  #[test]
+  fn dedup_consecutive() {
+    let s = stream::iter(vec![ok(1), ok(1), ok(2), Err("e"), ok(2), ok(1)]);
+    assert_eq!(messages(dedup(s)), vec![Ok(1), Ok(2), Err("e"), Ok(1)]);
+  }
+
+  #[test]
+  fn dedup_key() {
+    let s = stream::iter(vec![ok(10), ok(11), ok(20), ok(12)]);
+    assert_eq!(
+      messages(dedup_by_key(s, |m| m / 10)),
+      vec![Ok(10), Ok(20), Ok(12)]
+    );
+  }
+
+  #[test]
+  fn reorder_within_window() {
+    let s = stream::iter(vec![ok(3), ok(1), Err("e"), ok(2)]);
+    let reordered = reorder_by_key(s, Duration::from_millis(50), |m, _| *m);
+    // The error is not held back. The input ends, so the rest is flushed.
+    assert_eq!(messages(reordered), vec![Err("e"), Ok(1), Ok(2), Ok(3)]);
+  }
+
+  #[test]
+  fn reorder_releases_after_window() {
+    let (sender, receiver) = futures::channel::mpsc::unbounded();
+    let mut reordered = Box::pin(reorder_by_key(
+      receiver,
+      Duration::from_millis(20),
+      |m: &i32, _| *m,
+    ));
+    sender.unbounded_send(ok(2)).unwrap();
+    sender.unbounded_send(ok(1)).unwrap();
+    let started = Instant::now();
+    let first = block_on(reordered.next()).unwrap().map(|(m, ())| m);
+    assert_eq!(first, Ok(1));
+    assert!(started.elapsed() >= Duration::from_millis(20));
+    let second = block_on(reordered.next()).unwrap().map(|(m, ())| m);
+    assert_eq!(second, Ok(2));
+  }
+}
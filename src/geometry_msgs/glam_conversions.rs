@@ -0,0 +1,116 @@
+//! Conversions between geometry_msgs and [`glam`] double precision types.
+//!
+//! * `Vector3`, `Point` <-> `DVec3`
+//! * `Quaternion` <-> `DQuat`
+//! * `Pose`, `Transform` <-> `DAffine3`
+//! * `Twist` <-> `(DVec3, DVec3)` as (linear, angular)
+//!
+//! `DAffine3` can also represent scaling and shear, which a `Pose` or
+//! `Transform` cannot. Converting from `DAffine3` discards the scale.
+
+use glam::{DAffine3, DQuat, DVec3};
+
+use super::*;
+
+impl From<Vector3> for DVec3 {
+  fn from(v: Vector3) -> Self {
+    DVec3::new(v.x, v.y, v.z)
+  }
+}
+
+impl From<DVec3> for Vector3 {
+  fn from(v: DVec3) -> Self {
+    Vector3::new(v.x, v.y, v.z)
+  }
+}
+
+impl From<Point> for DVec3 {
+  fn from(p: Point) -> Self {
+    DVec3::new(p.x, p.y, p.z)
+  }
+}
+
+impl From<DVec3> for Point {
+  fn from(v: DVec3) -> Self {
+    Point::new(v.x, v.y, v.z)
+  }
+}
+
+impl From<Quaternion> for DQuat {
+  fn from(q: Quaternion) -> Self {
+    DQuat::from_xyzw(q.x, q.y, q.z, q.w)
+  }
+}
+
+impl From<DQuat> for Quaternion {
+  fn from(q: DQuat) -> Self {
+    Quaternion::new(q.x, q.y, q.z, q.w)
+  }
+}
+
+impl From<Pose> for DAffine3 {
+  fn from(p: Pose) -> Self {
+    DAffine3::from_rotation_translation(p.orientation.into(), p.position.into())
+  }
+}
+
+impl From<DAffine3> for Pose {
+  fn from(a: DAffine3) -> Self {
+    let (_scale, rotation, translation) = a.to_scale_rotation_translation();
+    Pose {
+      position: translation.into(),
+      orientation: rotation.into(),
+    }
+  }
+}
+
+impl From<Transform> for DAffine3 {
+  fn from(t: Transform) -> Self {
+    DAffine3::from_rotation_translation(t.rotation.into(), t.translation.into())
+  }
+}
+
+impl From<DAffine3> for Transform {
+  fn from(a: DAffine3) -> Self {
+    let (_scale, rotation, translation) = a.to_scale_rotation_translation();
+    Transform {
+      translation: translation.into(),
+      rotation: rotation.into(),
+    }
+  }
+}
+
+impl From<Twist> for (DVec3, DVec3) {
+  fn from(t: Twist) -> Self {
+    (t.linear.into(), t.angular.into())
+  }
+}
+
+impl From<(DVec3, DVec3)> for Twist {
+  fn from((linear, angular): (DVec3, DVec3)) -> Self {
+    Twist {
+      linear: linear.into(),
+      angular: angular.into(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn transform_round_trip() {
+    let t = Transform {
+      translation: Vector3::new(1.0, -2.0, 0.5),
+      rotation: DQuat::from_rotation_z(0.5).into(),
+    };
+    let a = DAffine3::from(t);
+    assert!(a
+      .transform_point3(DVec3::ZERO)
+      .abs_diff_eq(DVec3::new(1.0, -2.0, 0.5), 1e-12));
+    let back = Transform::from(a);
+    assert!(DVec3::from(back.translation).abs_diff_eq(t.translation.into(), 1e-12));
+    assert!(DQuat::from(back.rotation).abs_diff_eq(t.rotation.into(), 1e-12));
+  }
+}
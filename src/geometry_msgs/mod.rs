@@ -0,0 +1,169 @@
+//! Corresponds to package [geometry_msgs](https://index.ros.org/p/geometry_msgs/).
+//!
+//! With the `nalgebra` or `glam` features enabled, these types convert to and
+//! from the corresponding math library types using `From`/`Into`.
+//!
+//! Basic transform math (composition, inversion, interpolation, and applying
+//! transforms to points and poses) is available without any features, see
+//! [`Transform`] and [`TransformStamped`].
+//!
+//! The types above are always available. Feature `geometry_msgs` adds the
+//! rest of the package, generated from the ROS 2 definitions.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{message::Message, std_msgs::Header};
+
+#[cfg(feature = "glam")]
+mod glam_conversions;
+#[cfg(feature = "nalgebra")]
+mod nalgebra_conversions;
+mod transform_math;
+
+pub use transform_math::TransformError;
+
+// The rest of the package, with feature `geometry_msgs`
+#[cfg(feature = "geometry_msgs")]
+pub use crate::common_interfaces::geometry_msgs::msg::*;
+
+/// Represents a vector in free space.
+///
+/// [Vector3](https://github.com/ros2/common_interfaces/blob/rolling/geometry_msgs/msg/Vector3.msg)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Vector3 {
+  pub x: f64,
+  pub y: f64,
+  pub z: f64,
+}
+impl Message for Vector3 {}
+
+impl Vector3 {
+  pub const ZERO: Vector3 = Vector3 {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+  };
+
+  pub fn new(x: f64, y: f64, z: f64) -> Self {
+    Vector3 { x, y, z }
+  }
+}
+
+/// Position of a point in free space.
+///
+/// [Point](https://github.com/ros2/common_interfaces/blob/rolling/geometry_msgs/msg/Point.msg)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Point {
+  pub x: f64,
+  pub y: f64,
+  pub z: f64,
+}
+impl Message for Point {}
+
+impl Point {
+  pub fn new(x: f64, y: f64, z: f64) -> Self {
+    Point { x, y, z }
+  }
+}
+
+/// Orientation in free space in quaternion form.
+///
+/// [Quaternion](https://github.com/ros2/common_interfaces/blob/rolling/geometry_msgs/msg/Quaternion.msg)
+///
+/// The default value is the identity rotation, as in the message definition.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Quaternion {
+  pub x: f64,
+  pub y: f64,
+  pub z: f64,
+  pub w: f64,
+}
+impl Message for Quaternion {}
+
+impl Quaternion {
+  pub const IDENTITY: Quaternion = Quaternion {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+    w: 1.0,
+  };
+
+  pub fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
+    Quaternion { x, y, z, w }
+  }
+}
+
+impl Default for Quaternion {
+  fn default() -> Self {
+    Self::IDENTITY
+  }
+}
+
+/// A representation of pose in free space, composed of position and
+/// orientation.
+///
+/// [Pose](https://github.com/ros2/common_interfaces/blob/rolling/geometry_msgs/msg/Pose.msg)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Pose {
+  pub position: Point,
+  pub orientation: Quaternion,
+}
+impl Message for Pose {}
+
+/// Represents the transform between two coordinate frames in free space.
+///
+/// [Transform](https://github.com/ros2/common_interfaces/blob/rolling/geometry_msgs/msg/Transform.msg)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Transform {
+  pub translation: Vector3,
+  pub rotation: Quaternion,
+}
+impl Message for Transform {}
+
+/// Velocity in free space broken into its linear and angular parts.
+///
+/// [Twist](https://github.com/ros2/common_interfaces/blob/rolling/geometry_msgs/msg/Twist.msg)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Twist {
+  pub linear: Vector3,
+  pub angular: Vector3,
+}
+impl Message for Twist {}
+
+/// A Point with reference coordinate frame and timestamp.
+///
+/// [PointStamped](https://github.com/ros2/common_interfaces/blob/rolling/geometry_msgs/msg/PointStamped.msg)
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PointStamped {
+  pub header: Header,
+  pub point: Point,
+}
+impl Message for PointStamped {}
+
+/// A Pose with reference coordinate frame and timestamp.
+///
+/// [PoseStamped](https://github.com/ros2/common_interfaces/blob/rolling/geometry_msgs/msg/PoseStamped.msg)
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PoseStamped {
+  pub header: Header,
+  pub pose: Pose,
+}
+impl Message for PoseStamped {}
+
+/// Expresses a transform from coordinate frame `header.frame_id` to the
+/// coordinate frame `child_frame_id` at the time of `header.stamp`.
+///
+/// [TransformStamped](https://github.com/ros2/common_interfaces/blob/rolling/geometry_msgs/msg/TransformStamped.msg)
+///
+/// Applying the transform to data expressed in `child_frame_id` gives the
+/// data in `header.frame_id`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct TransformStamped {
+  pub header: Header,
+  /// The frame id of the child frame to which this transform points.
+  pub child_frame_id: String,
+  /// Translation and rotation in 3-dimensions of child_frame_id from
+  /// header.frame_id.
+  pub transform: Transform,
+}
+impl Message for TransformStamped {}
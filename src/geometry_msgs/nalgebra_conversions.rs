@@ -0,0 +1,127 @@
+//! Conversions between geometry_msgs and [`nalgebra`] types.
+//!
+//! * `Vector3` <-> `Vector3<f64>`
+//! * `Point` <-> `Point3<f64>`
+//! * `Quaternion` <-> `Quaternion<f64>`, `UnitQuaternion<f64>`
+//! * `Pose`, `Transform` <-> `Isometry3<f64>`
+//! * `Twist` <-> `(Vector3<f64>, Vector3<f64>)` as (linear, angular)
+//!
+//! Converting a message `Quaternion` to `UnitQuaternion` normalizes it.
+
+use nalgebra as na;
+
+use super::*;
+
+impl From<Vector3> for na::Vector3<f64> {
+  fn from(v: Vector3) -> Self {
+    na::Vector3::new(v.x, v.y, v.z)
+  }
+}
+
+impl From<na::Vector3<f64>> for Vector3 {
+  fn from(v: na::Vector3<f64>) -> Self {
+    Vector3::new(v.x, v.y, v.z)
+  }
+}
+
+impl From<Point> for na::Point3<f64> {
+  fn from(p: Point) -> Self {
+    na::Point3::new(p.x, p.y, p.z)
+  }
+}
+
+impl From<na::Point3<f64>> for Point {
+  fn from(p: na::Point3<f64>) -> Self {
+    Point::new(p.x, p.y, p.z)
+  }
+}
+
+impl From<Quaternion> for na::Quaternion<f64> {
+  fn from(q: Quaternion) -> Self {
+    na::Quaternion::new(q.w, q.x, q.y, q.z)
+  }
+}
+
+impl From<na::Quaternion<f64>> for Quaternion {
+  fn from(q: na::Quaternion<f64>) -> Self {
+    Quaternion::new(q.i, q.j, q.k, q.w)
+  }
+}
+
+impl From<Quaternion> for na::UnitQuaternion<f64> {
+  fn from(q: Quaternion) -> Self {
+    na::UnitQuaternion::new_normalize(q.into())
+  }
+}
+
+impl From<na::UnitQuaternion<f64>> for Quaternion {
+  fn from(q: na::UnitQuaternion<f64>) -> Self {
+    q.into_inner().into()
+  }
+}
+
+impl From<Pose> for na::Isometry3<f64> {
+  fn from(p: Pose) -> Self {
+    na::Isometry3::from_parts(
+      na::Translation3::new(p.position.x, p.position.y, p.position.z),
+      p.orientation.into(),
+    )
+  }
+}
+
+impl From<na::Isometry3<f64>> for Pose {
+  fn from(iso: na::Isometry3<f64>) -> Self {
+    Pose {
+      position: na::Point3::from(iso.translation.vector).into(),
+      orientation: iso.rotation.into(),
+    }
+  }
+}
+
+impl From<Transform> for na::Isometry3<f64> {
+  fn from(t: Transform) -> Self {
+    na::Isometry3::from_parts(
+      na::Translation3::from(na::Vector3::from(t.translation)),
+      t.rotation.into(),
+    )
+  }
+}
+
+impl From<na::Isometry3<f64>> for Transform {
+  fn from(iso: na::Isometry3<f64>) -> Self {
+    Transform {
+      translation: iso.translation.vector.into(),
+      rotation: iso.rotation.into(),
+    }
+  }
+}
+
+impl From<Twist> for (na::Vector3<f64>, na::Vector3<f64>) {
+  fn from(t: Twist) -> Self {
+    (t.linear.into(), t.angular.into())
+  }
+}
+
+impl From<(na::Vector3<f64>, na::Vector3<f64>)> for Twist {
+  fn from((linear, angular): (na::Vector3<f64>, na::Vector3<f64>)) -> Self {
+    Twist {
+      linear: linear.into(),
+      angular: angular.into(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn pose_round_trip() {
+    let rot = na::UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3);
+    let iso = na::Isometry3::from_parts(na::Translation3::new(1.0, 2.0, 3.0), rot);
+    let pose: Pose = iso.into();
+    assert_eq!(pose.position, Point::new(1.0, 2.0, 3.0));
+    assert_eq!(pose.orientation.w, rot.w);
+    assert_eq!(na::Isometry3::from(pose), iso);
+  }
+}
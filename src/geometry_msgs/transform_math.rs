@@ -0,0 +1,469 @@
+//! Transform math on geometry_msgs types, without external math libraries.
+//!
+//! Quaternions are assumed to be (approximately) unit length wherever they
+//! are used as rotations.
+
+use std::{fmt, ops::Mul};
+
+use super::*;
+use crate::builtin_interfaces::Time;
+
+impl Vector3 {
+  pub fn dot(&self, other: &Vector3) -> f64 {
+    self.x * other.x + self.y * other.y + self.z * other.z
+  }
+
+  pub fn cross(&self, other: &Vector3) -> Vector3 {
+    Vector3::new(
+      self.y * other.z - self.z * other.y,
+      self.z * other.x - self.x * other.z,
+      self.x * other.y - self.y * other.x,
+    )
+  }
+
+  pub fn length(&self) -> f64 {
+    self.dot(self).sqrt()
+  }
+
+  fn add(&self, o: &Vector3) -> Vector3 {
+    Vector3::new(self.x + o.x, self.y + o.y, self.z + o.z)
+  }
+
+  fn scale(&self, s: f64) -> Vector3 {
+    Vector3::new(self.x * s, self.y * s, self.z * s)
+  }
+
+  fn lerp(&self, o: &Vector3, t: f64) -> Vector3 {
+    self.add(&o.add(&self.scale(-1.0)).scale(t))
+  }
+}
+
+impl From<Point> for Vector3 {
+  fn from(p: Point) -> Vector3 {
+    Vector3::new(p.x, p.y, p.z)
+  }
+}
+
+impl From<Vector3> for Point {
+  fn from(v: Vector3) -> Point {
+    Point::new(v.x, v.y, v.z)
+  }
+}
+
+impl Quaternion {
+  /// Rotation of `angle` radians around `axis`. The axis need not be
+  /// normalized, but must be non-zero.
+  pub fn from_axis_angle(axis: Vector3, angle: f64) -> Quaternion {
+    let axis = axis.scale(1.0 / axis.length());
+    let (s, c) = (angle / 2.0).sin_cos();
+    Quaternion::new(axis.x * s, axis.y * s, axis.z * s, c)
+  }
+
+  /// Rotation from roll (x), pitch (y), and yaw (z) angles in radians, as in
+  /// tf2 `Quaternion::setRPY`.
+  pub fn from_rpy(roll: f64, pitch: f64, yaw: f64) -> Quaternion {
+    let (sr, cr) = (roll / 2.0).sin_cos();
+    let (sp, cp) = (pitch / 2.0).sin_cos();
+    let (sy, cy) = (yaw / 2.0).sin_cos();
+    Quaternion::new(
+      sr * cp * cy - cr * sp * sy,
+      cr * sp * cy + sr * cp * sy,
+      cr * cp * sy - sr * sp * cy,
+      cr * cp * cy + sr * sp * sy,
+    )
+  }
+
+  pub fn conjugate(&self) -> Quaternion {
+    Quaternion::new(-self.x, -self.y, -self.z, self.w)
+  }
+
+  pub fn dot(&self, o: &Quaternion) -> f64 {
+    self.x * o.x + self.y * o.y + self.z * o.z + self.w * o.w
+  }
+
+  pub fn norm(&self) -> f64 {
+    self.dot(self).sqrt()
+  }
+
+  /// Scale to unit length. A zero quaternion is returned as identity.
+  pub fn normalize(&self) -> Quaternion {
+    let n = self.norm();
+    if n == 0.0 {
+      Quaternion::IDENTITY
+    } else {
+      Quaternion::new(self.x / n, self.y / n, self.z / n, self.w / n)
+    }
+  }
+
+  /// Rotate a vector.
+  pub fn rotate(&self, v: &Vector3) -> Vector3 {
+    // v' = v + 2w(q x v) + 2 q x (q x v), where q is the vector part
+    let q = Vector3::new(self.x, self.y, self.z);
+    let t = q.cross(v).scale(2.0);
+    v.add(&t.scale(self.w)).add(&q.cross(&t))
+  }
+
+  /// Spherical linear interpolation. `t = 0` gives `self`, `t = 1` gives
+  /// `other`. Takes the shorter path.
+  pub fn slerp(&self, other: &Quaternion, t: f64) -> Quaternion {
+    let mut dot = self.dot(other);
+    let mut other = *other;
+    if dot < 0.0 {
+      other = Quaternion::new(-other.x, -other.y, -other.z, -other.w);
+      dot = -dot;
+    }
+    let (a, b) = if dot > 0.9995 {
+      // Nearly parallel: linear interpolation is accurate and avoids
+      // division by a tiny sine.
+      (1.0 - t, t)
+    } else {
+      let theta = dot.acos();
+      let sin_theta = theta.sin();
+      (
+        ((1.0 - t) * theta).sin() / sin_theta,
+        (t * theta).sin() / sin_theta,
+      )
+    };
+    Quaternion::new(
+      a * self.x + b * other.x,
+      a * self.y + b * other.y,
+      a * self.z + b * other.z,
+      a * self.w + b * other.w,
+    )
+    .normalize()
+  }
+}
+
+/// Hamilton product: `(a * b).rotate(v) == a.rotate(&b.rotate(v))`
+impl Mul for Quaternion {
+  type Output = Quaternion;
+
+  fn mul(self, b: Quaternion) -> Quaternion {
+    let a = self;
+    Quaternion::new(
+      a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+      a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+      a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+      a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+    )
+  }
+}
+
+impl Transform {
+  pub const IDENTITY: Transform = Transform {
+    translation: Vector3::ZERO,
+    rotation: Quaternion::IDENTITY,
+  };
+
+  pub fn new(translation: Vector3, rotation: Quaternion) -> Self {
+    Transform {
+      translation,
+      rotation,
+    }
+  }
+
+  /// The transform that undoes this one.
+  pub fn inverse(&self) -> Transform {
+    let rotation = self.rotation.conjugate();
+    Transform {
+      translation: rotation.rotate(&self.translation).scale(-1.0),
+      rotation,
+    }
+  }
+
+  /// Composition: the result applies `other` first, then `self`.
+  ///
+  /// If `self` is A→B and `other` is B→C, the result is A→C in tf terms.
+  pub fn compose(&self, other: &Transform) -> Transform {
+    Transform {
+      translation: self
+        .transform_vector(&other.translation)
+        .add(&self.translation),
+      rotation: (self.rotation * other.rotation).normalize(),
+    }
+  }
+
+  /// Rotate a vector. Translation does not apply to free vectors.
+  pub fn transform_vector(&self, v: &Vector3) -> Vector3 {
+    self.rotation.rotate(v)
+  }
+
+  pub fn transform_point(&self, p: &Point) -> Point {
+    self
+      .rotation
+      .rotate(&Vector3::from(*p))
+      .add(&self.translation)
+      .into()
+  }
+
+  pub fn transform_pose(&self, p: &Pose) -> Pose {
+    Pose {
+      position: self.transform_point(&p.position),
+      orientation: (self.rotation * p.orientation).normalize(),
+    }
+  }
+
+  /// Interpolate between two transforms: linear for translation, slerp for
+  /// rotation. `t = 0` gives `self`, `t = 1` gives `other`.
+  pub fn interpolate(&self, other: &Transform, t: f64) -> Transform {
+    Transform {
+      translation: self.translation.lerp(&other.translation, t),
+      rotation: self.rotation.slerp(&other.rotation, t),
+    }
+  }
+}
+
+/// Same as [`Transform::compose`]
+impl Mul for Transform {
+  type Output = Transform;
+
+  fn mul(self, other: Transform) -> Transform {
+    self.compose(&other)
+  }
+}
+
+impl From<Pose> for Transform {
+  fn from(p: Pose) -> Transform {
+    Transform::new(p.position.into(), p.orientation)
+  }
+}
+
+impl From<Transform> for Pose {
+  fn from(t: Transform) -> Pose {
+    Pose {
+      position: t.translation.into(),
+      orientation: t.rotation,
+    }
+  }
+}
+
+/// Errors from operations on stamped transforms
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransformError {
+  /// Data or transform is expressed in a different frame than required.
+  FrameMismatch { expected: String, actual: String },
+  /// Requested time is outside the interval covered by the given transforms.
+  Extrapolation {
+    requested: Time,
+    earliest: Time,
+    latest: Time,
+  },
+}
+
+impl fmt::Display for TransformError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      TransformError::FrameMismatch { expected, actual } => {
+        write!(f, "Expected frame {expected:?}, but got {actual:?}")
+      }
+      TransformError::Extrapolation {
+        requested,
+        earliest,
+        latest,
+      } => write!(
+        f,
+        "Extrapolation required: time {} ns is outside [{}, {}] ns",
+        requested.to_nanos(),
+        earliest.to_nanos(),
+        latest.to_nanos()
+      ),
+    }
+  }
+}
+
+impl std::error::Error for TransformError {}
+
+fn check_frame(expected: &str, actual: &str) -> Result<(), TransformError> {
+  if expected == actual {
+    Ok(())
+  } else {
+    Err(TransformError::FrameMismatch {
+      expected: expected.to_owned(),
+      actual: actual.to_owned(),
+    })
+  }
+}
+
+impl TransformStamped {
+  pub fn new(header: Header, child_frame_id: &str, transform: Transform) -> Self {
+    TransformStamped {
+      header,
+      child_frame_id: child_frame_id.to_owned(),
+      transform,
+    }
+  }
+
+  /// The inverse transform, from `child_frame_id` to `header.frame_id`.
+  pub fn inverse(&self) -> TransformStamped {
+    TransformStamped {
+      header: Header::new(self.header.stamp, &self.child_frame_id),
+      child_frame_id: self.header.frame_id.clone(),
+      transform: self.transform.inverse(),
+    }
+  }
+
+  /// Chain transforms A→B (`self`) and B→C (`other`) into A→C.
+  ///
+  /// `self.child_frame_id` must equal `other.header.frame_id`. The result
+  /// has the earlier of the two timestamps.
+  pub fn compose(&self, other: &TransformStamped) -> Result<TransformStamped, TransformError> {
+    check_frame(&self.child_frame_id, &other.header.frame_id)?;
+    Ok(TransformStamped {
+      header: Header::new(
+        self.header.stamp.min(other.header.stamp),
+        &self.header.frame_id,
+      ),
+      child_frame_id: other.child_frame_id.clone(),
+      transform: self.transform.compose(&other.transform),
+    })
+  }
+
+  /// Interpolate between two samples of the same transform at time `at`.
+  ///
+  /// Both samples must have the same parent and child frames, and `at` must
+  /// be between their timestamps.
+  pub fn interpolate(
+    &self,
+    other: &TransformStamped,
+    at: Time,
+  ) -> Result<TransformStamped, TransformError> {
+    check_frame(&self.header.frame_id, &other.header.frame_id)?;
+    check_frame(&self.child_frame_id, &other.child_frame_id)?;
+    let (a, b) = if self.header.stamp <= other.header.stamp {
+      (self, other)
+    } else {
+      (other, self)
+    };
+    let (t0, t1) = (a.header.stamp, b.header.stamp);
+    if at < t0 || at > t1 {
+      return Err(TransformError::Extrapolation {
+        requested: at,
+        earliest: t0,
+        latest: t1,
+      });
+    }
+    let span = t1.to_nanos() - t0.to_nanos();
+    let ratio = if span == 0 {
+      0.0
+    } else {
+      (at.to_nanos() - t0.to_nanos()) as f64 / span as f64
+    };
+    Ok(TransformStamped {
+      header: Header::new(at, &a.header.frame_id),
+      child_frame_id: a.child_frame_id.clone(),
+      transform: a.transform.interpolate(&b.transform, ratio),
+    })
+  }
+
+  /// Express a point given in `child_frame_id` in `header.frame_id`.
+  pub fn transform_point(&self, p: &PointStamped) -> Result<PointStamped, TransformError> {
+    check_frame(&self.child_frame_id, &p.header.frame_id)?;
+    Ok(PointStamped {
+      header: Header::new(p.header.stamp, &self.header.frame_id),
+      point: self.transform.transform_point(&p.point),
+    })
+  }
+
+  /// Express a pose given in `child_frame_id` in `header.frame_id`.
+  pub fn transform_pose(&self, p: &PoseStamped) -> Result<PoseStamped, TransformError> {
+    check_frame(&self.child_frame_id, &p.header.frame_id)?;
+    Ok(PoseStamped {
+      header: Header::new(p.header.stamp, &self.header.frame_id),
+      pose: self.transform.transform_pose(&p.pose),
+    })
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::f64::consts::FRAC_PI_2;
+
+  use super::*;
+
+  fn close(a: &Vector3, b: &Vector3) -> bool {
+    a.add(&b.scale(-1.0)).length() < 1e-9
+  }
+
+  fn stamped(parent: &str, child: &str, secs: i64, t: Transform) -> TransformStamped {
+    TransformStamped::new(
+      Header::new(Time::from_nanos(secs * 1_000_000_000), parent),
+      child,
+      t,
+    )
+  }
+
+  #[test]
+  fn compose_and_inverse() {
+    // map -> odom: shift x by 1, odom -> base: rotate 90 deg around z
+    let a = Transform::new(Vector3::new(1.0, 0.0, 0.0), Quaternion::IDENTITY);
+    let b = Transform::new(
+      Vector3::new(0.0, 2.0, 0.0),
+      Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), FRAC_PI_2),
+    );
+    let ab = a * b;
+    let p = Point::new(1.0, 0.0, 0.0);
+    let q = Vector3::from(ab.transform_point(&p));
+    assert!(close(&q, &Vector3::new(1.0, 3.0, 0.0)));
+
+    let back = Vector3::from(ab.inverse().transform_point(&q.into()));
+    assert!(close(&back, &Vector3::from(p)));
+
+    let rpy = Quaternion::from_rpy(0.0, 0.0, FRAC_PI_2);
+    assert!((rpy.dot(&b.rotation) - 1.0).abs() < 1e-12);
+  }
+
+  #[test]
+  fn stamped_frames() {
+    let map_odom = stamped("map", "odom", 1, Transform::IDENTITY);
+    let odom_base = stamped("odom", "base", 2, Transform::IDENTITY);
+    let map_base = map_odom.compose(&odom_base).unwrap();
+    assert_eq!(map_base.header.frame_id, "map");
+    assert_eq!(map_base.child_frame_id, "base");
+    assert_eq!(map_base.header.stamp, Time::from_nanos(1_000_000_000));
+    assert!(odom_base.compose(&map_odom).is_err());
+
+    let inv = map_base.inverse();
+    assert_eq!(inv.header.frame_id, "base");
+    assert_eq!(inv.child_frame_id, "map");
+
+    let p = PointStamped {
+      header: Header::new(Time::ZERO, "odom"),
+      point: Point::new(1.0, 2.0, 3.0),
+    };
+    assert!(map_base.transform_point(&p).is_err());
+    assert_eq!(map_odom.transform_point(&p).unwrap().header.frame_id, "map");
+  }
+
+  #[test]
+  fn interpolation() {
+    let t0 = stamped(
+      "map",
+      "base",
+      10,
+      Transform::new(Vector3::ZERO, Quaternion::IDENTITY),
+    );
+    let t1 = stamped(
+      "map",
+      "base",
+      12,
+      Transform::new(
+        Vector3::new(2.0, 0.0, 0.0),
+        Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), FRAC_PI_2),
+      ),
+    );
+    let mid = t1
+      .interpolate(&t0, Time::from_nanos(11_000_000_000))
+      .unwrap();
+    assert!(close(
+      &mid.transform.translation,
+      &Vector3::new(1.0, 0.0, 0.0)
+    ));
+    let expected = Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), FRAC_PI_2 / 2.0);
+    assert!((mid.transform.rotation.dot(&expected) - 1.0).abs() < 1e-12);
+
+    assert!(matches!(
+      t0.interpolate(&t1, Time::from_nanos(13_000_000_000)),
+      Err(TransformError::Extrapolation { .. })
+    ));
+  }
+}
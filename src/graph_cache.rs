@@ -0,0 +1,277 @@
+//! Saving and loading the ROS 2 Graph seen by a Node
+//!
+//! Discovery takes a few seconds to find everything in the network. Tools that
+//! start often, such as command line utilities, can save what they saw when
+//! they exit, and load it when they start. Loaded entries are provisional:
+//! those that live discovery does not confirm within a validation period are
+//! removed.
+//!
+//! See [`Node::save_graph_cache`](crate::Node::save_graph_cache) and
+//! [`Node::load_graph_cache`](crate::Node::load_graph_cache).
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fs, io,
+  path::Path,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use rustdds::{EndpointDescription, QosPolicies, GUID};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+  entities_info::{NodeEntitiesInfo, ParticipantEntitiesInfo},
+  gid::Gid,
+};
+
+// Bump this when the file contents change incompatibly.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct GraphCacheFile {
+  version: u32,
+  participants: Vec<ParticipantEntitiesInfo>,
+  readers: Vec<CachedEndpoint>,
+  writers: Vec<CachedEndpoint>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedEndpoint {
+  guid: Gid,
+  topic_name: String,
+  type_name: String,
+  qos: QosPolicies,
+}
+
+impl From<&EndpointDescription> for CachedEndpoint {
+  fn from(e: &EndpointDescription) -> Self {
+    CachedEndpoint {
+      guid: e.guid.into(),
+      topic_name: e.topic_name.clone(),
+      type_name: e.type_name.clone(),
+      qos: e.qos.clone(),
+    }
+  }
+}
+
+impl From<CachedEndpoint> for EndpointDescription {
+  fn from(e: CachedEndpoint) -> Self {
+    EndpointDescription {
+      updated_time: chrono::Utc::now(),
+      guid: e.guid.into(),
+      topic_name: e.topic_name,
+      type_name: e.type_name,
+      qos: e.qos,
+    }
+  }
+}
+
+/// The parts of the Graph that a Node tracks, as shared by the Node and its
+/// Spinner.
+pub(crate) struct GraphMaps<'a> {
+  pub external_nodes: &'a Mutex<BTreeMap<Gid, Vec<NodeEntitiesInfo>>>,
+  pub discovered_readers: &'a Mutex<BTreeMap<GUID, EndpointDescription>>,
+  pub discovered_writers: &'a Mutex<BTreeMap<GUID, EndpointDescription>>,
+}
+
+/// Loaded entries that live discovery has not confirmed yet
+#[derive(Default)]
+pub(crate) struct ProvisionalGraph {
+  deadline: Option<Instant>,
+  participants: BTreeSet<Gid>,
+  readers: BTreeSet<GUID>,
+  writers: BTreeSet<GUID>,
+}
+
+impl ProvisionalGraph {
+  pub fn confirm_participant(&mut self, gid: Gid) {
+    self.participants.remove(&gid);
+  }
+
+  pub fn confirm_reader(&mut self, guid: GUID) {
+    self.readers.remove(&guid);
+  }
+
+  pub fn confirm_writer(&mut self, guid: GUID) {
+    self.writers.remove(&guid);
+  }
+
+  /// Removes unconfirmed entries, if the validation period is over.
+  pub fn purge_if_expired(&mut self, now: Instant, maps: &GraphMaps) {
+    match self.deadline {
+      Some(deadline) if deadline <= now => {}
+      _ => return,
+    }
+    debug!(
+      "Graph cache: removing {} participants, {} readers and {} writers not confirmed by discovery",
+      self.participants.len(),
+      self.readers.len(),
+      self.writers.len()
+    );
+    let mut external_nodes = maps.external_nodes.lock().unwrap();
+    for gid in &self.participants {
+      external_nodes.remove(gid);
+    }
+    let mut readers = maps.discovered_readers.lock().unwrap();
+    for guid in &self.readers {
+      readers.remove(guid);
+    }
+    let mut writers = maps.discovered_writers.lock().unwrap();
+    for guid in &self.writers {
+      writers.remove(guid);
+    }
+    *self = ProvisionalGraph::default();
+  }
+}
+
+pub(crate) fn save(path: &Path, maps: &GraphMaps) -> io::Result<()> {
+  let file = GraphCacheFile {
+    version: FORMAT_VERSION,
+    participants: maps
+      .external_nodes
+      .lock()
+      .unwrap()
+      .iter()
+      .map(|(gid, nodes)| ParticipantEntitiesInfo::new(*gid, nodes.clone()))
+      .collect(),
+    readers: maps
+      .discovered_readers
+      .lock()
+      .unwrap()
+      .values()
+      .map(CachedEndpoint::from)
+      .collect(),
+    writers: maps
+      .discovered_writers
+      .lock()
+      .unwrap()
+      .values()
+      .map(CachedEndpoint::from)
+      .collect(),
+  };
+  let json = serde_json::to_vec(&file).map_err(io::Error::from)?;
+  fs::write(path, json)
+}
+
+/// Adds the entries in the file that are not already known, and are not from
+/// the DomainParticipant `own_participant`, i.e. ourselves. They are added to
+/// `provisional`, to be removed unless discovery confirms them within
+/// `validation_period`.
+pub(crate) fn load(
+  path: &Path,
+  own_participant: GUID,
+  validation_period: Duration,
+  maps: &GraphMaps,
+  provisional: &mut ProvisionalGraph,
+) -> io::Result<()> {
+  let file: GraphCacheFile = serde_json::from_slice(&fs::read(path)?).map_err(io::Error::from)?;
+  if file.version != FORMAT_VERSION {
+    return Err(io::Error::new(
+      io::ErrorKind::InvalidData,
+      format!("Unsupported graph cache version {}", file.version),
+    ));
+  }
+  let is_own = |guid: GUID| guid.prefix == own_participant.prefix;
+
+  let mut external_nodes = maps.external_nodes.lock().unwrap();
+  for p in file.participants {
+    if !is_own(p.gid().into()) && !external_nodes.contains_key(&p.gid()) {
+      provisional.participants.insert(p.gid());
+      external_nodes.insert(p.gid(), p.node_entities_info_seq);
+    }
+  }
+  let add_endpoints = |cached: Vec<CachedEndpoint>,
+                           discovered: &Mutex<BTreeMap<GUID, EndpointDescription>>,
+                           pending: &mut BTreeSet<GUID>| {
+    let mut discovered = discovered.lock().unwrap();
+    for e in cached {
+      let guid = e.guid.into();
+      if !is_own(guid) && !discovered.contains_key(&guid) {
+        pending.insert(guid);
+        discovered.insert(guid, e.into());
+      }
+    }
+  };
+  add_endpoints(
+    file.readers,
+    maps.discovered_readers,
+    &mut provisional.readers,
+  );
+  add_endpoints(
+    file.writers,
+    maps.discovered_writers,
+    &mut provisional.writers,
+  );
+
+  provisional.deadline = Some(Instant::now() + validation_period);
+  info!(
+    "Graph cache: loaded {} participants, {} readers and {} writers from {}",
+    provisional.participants.len(),
+    provisional.readers.len(),
+    provisional.writers.len(),
+    path.display()
+  );
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn endpoint(guid: GUID, topic_name: &str) -> EndpointDescription {
+    CachedEndpoint {
+      guid: guid.into(),
+      topic_name: topic_name.to_string(),
+      type_name: "std_msgs::msg::dds_::String_".to_string(),
+      qos: QosPolicies::qos_none(),
+    }
+    .into()
+  }
+
+  #[test]
+  fn save_load_purge() {
+    let own = GUID::new_participant_guid();
+    let reader = GUID::new_participant_guid();
+
+    let external_nodes = Mutex::new(BTreeMap::new());
+    let discovered_readers = Mutex::new(BTreeMap::from([
+      (reader, endpoint(reader, "rt/chatter")),
+      (own, endpoint(own, "rt/own")),
+    ]));
+    let discovered_writers = Mutex::new(BTreeMap::new());
+    let maps = GraphMaps {
+      external_nodes: &external_nodes,
+      discovered_readers: &discovered_readers,
+      discovered_writers: &discovered_writers,
+    };
+    let path = std::env::temp_dir().join(format!("graph_cache_test_{}.json", std::process::id()));
+    save(&path, &maps).unwrap();
+
+    let fresh_readers = Mutex::new(BTreeMap::new());
+    let fresh = GraphMaps {
+      discovered_readers: &fresh_readers,
+      ..maps
+    };
+    let mut provisional = ProvisionalGraph::default();
+    load(&path, own, Duration::from_secs(1), &fresh, &mut provisional).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // Own entries are not loaded.
+    assert_eq!(
+      fresh_readers.lock().unwrap().keys().collect::<Vec<_>>(),
+      vec![&reader]
+    );
+    assert_eq!(
+      fresh_readers.lock().unwrap()[&reader].topic_name,
+      "rt/chatter"
+    );
+
+    provisional.purge_if_expired(Instant::now(), &fresh);
+    assert_eq!(fresh_readers.lock().unwrap().len(), 1);
+    provisional.purge_if_expired(Instant::now() + Duration::from_secs(2), &fresh);
+    assert!(fresh_readers.lock().unwrap().is_empty());
+  }
+}
@@ -0,0 +1,257 @@
+//! Delivery of messages between Publishers and Subscriptions of the same
+//! Context, without DDS
+//!
+//! Enabled with
+//! [`NodeOptions::use_intra_process_comms`](crate::NodeOptions::use_intra_process_comms).
+//! A message is delivered in memory only if all Subscriptions matched to the
+//! Publisher are local intra-process ones. Otherwise it is published over
+//! DDS as usual, and the local Subscriptions receive it from there.
+
+use std::{
+  any::Any,
+  collections::{BTreeMap, BTreeSet},
+  sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc, Mutex,
+  },
+};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use rustdds::{policy, QosPolicies, Timestamp, GUID};
+use serde::Serialize;
+
+use crate::message_info::MessageInfo;
+
+// Matched Subscriptions of local Publishers, as maintained by the Node
+pub(crate) type MatchedReaders = Arc<Mutex<BTreeMap<GUID, BTreeSet<GUID>>>>;
+
+/// A message delivered in memory
+pub(crate) struct IntraProcessMessage<M> {
+  message: Arc<M>,
+  info: MessageInfo,
+  // For copying, if several Subscriptions received the message
+  to_cdr: fn(&M) -> Option<Vec<u8>>,
+}
+
+impl<M> IntraProcessMessage<M> {
+  /// The message, if nobody else has it. Otherwise a copy as CDR data, to be
+  /// deserialized by the receiver.
+  pub(crate) fn into_parts(self) -> (Result<M, Option<Vec<u8>>>, MessageInfo) {
+    let IntraProcessMessage {
+      message,
+      info,
+      to_cdr,
+    } = self;
+    let message = Arc::try_unwrap(message).map_err(|shared| to_cdr(&shared));
+    (message, info)
+  }
+}
+
+fn to_cdr<M: Serialize>(message: &M) -> Option<Vec<u8>> {
+  ros2_client_core::cdr::to_vec(message, ros2_client_core::cdr::Endianness::Little)
+    .map_err(|e| error!("Cannot copy intra-process message: {e}"))
+    .ok()
+}
+
+struct LocalSubscription {
+  reader: GUID,
+  // An async_channel::Sender<IntraProcessMessage<M>>, for the M of the
+  // Subscription
+  queue: Box<dyn Any + Send + Sync>,
+}
+
+/// Intra-process Subscriptions of a Context, by Topic
+#[derive(Default)]
+pub(crate) struct IntraProcessManager {
+  // Key is Topic name and type name.
+  subscriptions: Mutex<BTreeMap<(String, String), Vec<LocalSubscription>>>,
+}
+
+impl IntraProcessManager {
+  /// Register a Subscription, and get the queue its messages arrive in.
+  pub(crate) fn add_subscription<M>(
+    &self,
+    topic: (String, String),
+    reader: GUID,
+    qos: &QosPolicies,
+  ) -> async_channel::Receiver<IntraProcessMessage<M>>
+  where
+    M: Send + Sync + 'static,
+  {
+    let (sender, receiver) = match qos.history() {
+      Some(policy::History::KeepAll) => async_channel::unbounded(),
+      Some(policy::History::KeepLast { depth }) => async_channel::bounded(depth.max(1) as usize),
+      // The DDS default is KeepLast 1.
+      None => async_channel::bounded(1),
+    };
+    self
+      .subscriptions
+      .lock()
+      .unwrap()
+      .entry(topic)
+      .or_default()
+      .push(LocalSubscription {
+        reader,
+        queue: Box::new(sender),
+      });
+    receiver
+  }
+
+  pub(crate) fn publisher<M>(
+    self: &Arc<Self>,
+    topic: (String, String),
+    writer: GUID,
+    matched_readers: MatchedReaders,
+  ) -> IntraProcessPublisher<M> {
+    IntraProcessPublisher {
+      manager: Arc::clone(self),
+      topic,
+      writer,
+      matched_readers,
+      sequence_number: AtomicI64::new(0),
+      phantom: std::marker::PhantomData,
+    }
+  }
+}
+
+/// Publishing side of intra-process delivery
+pub(crate) trait IntraProcessDelivery<M>: Send + Sync {
+  /// Deliver `message` in memory, or give it back if it must go over DDS.
+  fn deliver(&self, message: M) -> Result<(), M>;
+}
+
+pub(crate) struct IntraProcessPublisher<M> {
+  manager: Arc<IntraProcessManager>,
+  topic: (String, String),
+  writer: GUID,
+  matched_readers: MatchedReaders,
+  sequence_number: AtomicI64,
+  phantom: std::marker::PhantomData<fn(M)>,
+}
+
+impl<M> IntraProcessDelivery<M> for IntraProcessPublisher<M>
+where
+  M: Serialize + Send + Sync + 'static,
+{
+  fn deliver(&self, message: M) -> Result<(), M> {
+    let mut subscriptions = self.manager.subscriptions.lock().unwrap();
+    let Some(local) = subscriptions.get_mut(&self.topic) else {
+      return Err(message);
+    };
+    local.retain(|s| {
+      s.queue
+        .downcast_ref::<async_channel::Sender<IntraProcessMessage<M>>>()
+        .is_none_or(|q| !q.is_closed())
+    });
+
+    let local_readers: BTreeSet<GUID> = local.iter().map(|s| s.reader).collect();
+    let all_matched_are_local = self
+      .matched_readers
+      .lock()
+      .unwrap()
+      .get(&self.writer)
+      .is_some_and(|matched| !matched.is_empty() && matched.is_subset(&local_readers));
+    let queues: Option<Vec<_>> = local
+      .iter()
+      .map(|s| {
+        s.queue
+          .downcast_ref::<async_channel::Sender<IntraProcessMessage<M>>>()
+      })
+      .collect();
+    // A Subscription with a different Rust type needs DDS to convert.
+    let queues = match queues {
+      Some(queues) if all_matched_are_local => queues,
+      _ => return Err(message),
+    };
+
+    let sequence_number = self.sequence_number.fetch_add(1, Ordering::Relaxed) + 1;
+    let now = Timestamp::now();
+    let message = Arc::new(message);
+    for queue in queues {
+      let m = IntraProcessMessage {
+        message: Arc::clone(&message),
+        info: MessageInfo::new(now, Some(now), sequence_number, self.writer),
+        to_cdr: to_cdr::<M>,
+      };
+      // A full queue drops its oldest message, like KeepLast History.
+      if queue.force_send(m).is_err() {
+        debug!("Intra-process Subscription closed during publish");
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rustdds::QosPolicyBuilder;
+
+  use super::*;
+
+  fn guid(n: u8) -> GUID {
+    GUID::from_bytes([n; 16])
+  }
+
+  #[test]
+  fn deliver() {
+    let manager = Arc::new(IntraProcessManager::default());
+    let topic = (
+      "rt/chatter".to_string(),
+      "std_msgs::msg::dds_::String_".to_string(),
+    );
+    let matched = MatchedReaders::default();
+    let publisher = manager.publisher::<String>(topic.clone(), guid(1), Arc::clone(&matched));
+    let qos = QosPolicyBuilder::new()
+      .history(policy::History::KeepLast { depth: 2 })
+      .build();
+    let a = manager.add_subscription::<String>(topic.clone(), guid(2), &qos);
+    let b = manager.add_subscription::<String>(topic, guid(3), &qos);
+
+    // Nothing matched yet, so nothing is known about remote Subscriptions.
+    assert_eq!(publisher.deliver("x".to_string()), Err("x".to_string()));
+
+    matched
+      .lock()
+      .unwrap()
+      .insert(guid(1), BTreeSet::from([guid(2), guid(3)]));
+    assert_eq!(publisher.deliver("hello".to_string()), Ok(()));
+    // Shared: the first receiver gets a copy, the last one the original.
+    let (copy, info) = a.try_recv().unwrap().into_parts();
+    let cdr = copy.unwrap_err().unwrap();
+    assert_eq!(
+      ros2_client_core::cdr::from_bytes::<String>(&cdr, ros2_client_core::cdr::Endianness::Little)
+        .unwrap()
+        .0,
+      "hello"
+    );
+    assert_eq!(info.writer_guid(), guid(1));
+    assert_eq!(
+      b.try_recv().unwrap().into_parts().0.ok(),
+      Some("hello".to_string())
+    );
+
+    // KeepLast 2 keeps the latest.
+    drop(a);
+    matched
+      .lock()
+      .unwrap()
+      .insert(guid(1), BTreeSet::from([guid(3)]));
+    for m in ["1", "2", "3"] {
+      assert_eq!(publisher.deliver(m.to_string()), Ok(()));
+    }
+    let received: Vec<_> = std::iter::from_fn(|| b.try_recv().ok())
+      .map(|m| m.into_parts().0.unwrap())
+      .collect();
+    assert_eq!(received, ["2", "3"]);
+
+    // A remote Subscription needs DDS.
+    matched
+      .lock()
+      .unwrap()
+      .get_mut(&guid(1))
+      .unwrap()
+      .insert(guid(4));
+    assert_eq!(publisher.deliver("y".to_string()), Err("y".to_string()));
+  }
+}
@@ -0,0 +1,150 @@
+//! Conversion of messages to and from JSON
+//!
+//! This is for web tools in the style of
+//! [rosbridge](https://github.com/RobotWebTools/rosbridge_suite), see also
+//! `ros2_client_core::rosbridge`, and for debug dumps of arbitrary Topics.
+//!
+//! Messages are JSON objects with the ROS 2 field names, which is what the
+//! derived `Serialize` and `Deserialize` of the message structs produce. A
+//! [`DynamicMessage`](crate::dynamic_message::DynamicMessage) converts the
+//! same way, so a message has the same JSON whether its type is known at
+//! compile time or not. The conventions are:
+//!
+//! * Strings and wide strings are JSON strings.
+//! * Arrays and sequences, also of `byte` and `uint8`, are JSON arrays.
+//! * `char` is a number.
+//! * Time and Duration are objects with `sec` and `nanosec`.
+//! * Non-finite floating point numbers are `null`, as JSON has no such numbers.
+//!   They cannot be converted back.
+//!
+//! When converting from JSON to a `DynamicMessage`, missing fields get their
+//! default values, and unknown fields are an error.
+//!
+//! ```
+//! use ros2_client::{
+//!   dynamic_message::{DynamicMessageType, DynamicValue},
+//!   json,
+//! };
+//!
+//! let point_type =
+//!   DynamicMessageType::from_definition("my_msgs/msg/Point2", "float64 x\nfloat64 y\n").unwrap();
+//! let point = json::from_json_seed(&point_type, serde_json::json!({"x": 1.0})).unwrap();
+//! assert_eq!(point.get("y"), Some(&DynamicValue::Float64(0.0)));
+//! assert_eq!(
+//!   json::to_json(&point).unwrap(),
+//!   serde_json::json!({"x": 1.0, "y": 0.0})
+//! );
+//! ```
+
+use serde::{
+  de::{DeserializeOwned, DeserializeSeed},
+  Serialize,
+};
+use serde_json::{Error, Value};
+
+/// Converts a message, e.g. a message struct or a
+/// [`DynamicMessage`](crate::dynamic_message::DynamicMessage), to JSON.
+pub fn to_json<M: Serialize + ?Sized>(message: &M) -> Result<Value, Error> {
+  serde_json::to_value(message)
+}
+
+/// Converts JSON to a message struct.
+pub fn from_json<M: DeserializeOwned>(value: Value) -> Result<M, Error> {
+  serde_json::from_value(value)
+}
+
+/// Converts JSON to a message with a deserialization seed, e.g. a
+/// [`DynamicMessageType`](crate::dynamic_message::DynamicMessageType).
+pub fn from_json_seed<'de, S: DeserializeSeed<'de>>(
+  seed: S,
+  value: Value,
+) -> Result<S::Value, Error> {
+  seed.deserialize(value)
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+  use crate::{
+    builtin_interfaces::Time,
+    dynamic_message::{DynamicMessageType, DynamicValue},
+    std_msgs::Header,
+    WString,
+  };
+
+  const DEFINITION: &str = "\
+std_msgs/Header header
+wstring label
+uint8[] data
+float32[2] pair
+================================================================================
+MSG: std_msgs/Header
+builtin_interfaces/Time stamp
+string frame_id
+";
+
+  #[test]
+  fn static_message() {
+    let header = Header {
+      stamp: Time::from_nanos(1_500_000_000),
+      frame_id: "map".to_string(),
+    };
+    let value = to_json(&header).unwrap();
+    assert_eq!(
+      value,
+      json!({"stamp": {"sec": 1, "nanosec": 500_000_000}, "frame_id": "map"})
+    );
+    assert_eq!(from_json::<Header>(value).unwrap(), header);
+
+    assert_eq!(to_json(&WString::from("ä")).unwrap(), json!("ä"));
+    assert_eq!(
+      from_json::<WString>(json!("ä")).unwrap(),
+      WString::from("ä")
+    );
+  }
+
+  #[test]
+  fn dynamic_message() {
+    let message_type =
+      DynamicMessageType::from_definition("my_msgs/msg/Tagged", DEFINITION).unwrap();
+    let value = json!({
+      "header": {"stamp": {"sec": 1, "nanosec": 2}, "frame_id": "map"},
+      "label": "ä",
+      "data": [1, 2, 3],
+      "pair": [0.5, 1.5],
+    });
+    let message = from_json_seed(&message_type, value.clone()).unwrap();
+    assert_eq!(
+      message.get("data"),
+      Some(&DynamicValue::Array(vec![
+        DynamicValue::UInt8(1),
+        DynamicValue::UInt8(2),
+        DynamicValue::UInt8(3),
+      ]))
+    );
+    assert_eq!(to_json(&message).unwrap(), value);
+
+    // Same as the struct
+    let header = Header {
+      stamp: Time::from_nanos(1_000_000_002),
+      frame_id: "map".to_string(),
+    };
+    assert_eq!(to_json(&header).unwrap(), value["header"]);
+
+    // Missing fields are defaults.
+    let message = from_json_seed(&message_type, json!({"label": "x"})).unwrap();
+    assert_eq!(
+      message.get("pair"),
+      Some(&DynamicValue::Array(vec![
+        DynamicValue::Float32(0.0),
+        DynamicValue::Float32(0.0),
+      ]))
+    );
+
+    assert!(from_json_seed(&message_type, json!({"nonexistent": 1})).is_err());
+    assert!(from_json_seed(&message_type, json!({"pair": [1.0]})).is_err());
+    assert!(from_json_seed(&message_type, json!({"data": [256]})).is_err());
+  }
+}
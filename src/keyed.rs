@@ -0,0 +1,163 @@
+//! Keyed (WithKey) Topics
+//!
+//! ROS 2 uses only NoKey Topics, but other DDS systems often key their data:
+//! each message belongs to an instance identified by its key, and the
+//! History QoS applies per instance. A [`KeyedPublisher`] can also dispose
+//! an instance, which a [`KeyedSubscription`] receives as
+//! [`Sample::Dispose`].
+//!
+//! Create the Topic with
+//! [`Node::create_keyed_topic`](crate::Node::create_keyed_topic). Keyed Topics
+//! do not support intra-process communication.
+
+use futures::{stream::StreamExt, Stream};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use rustdds::{
+  dds::{ReadResult, WriteResult},
+  with_key, RTPSEntity, Timestamp, GUID,
+};
+pub use rustdds::{with_key::Sample, Key, Keyed};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+  gid::Gid,
+  message_info::MessageInfo,
+  node::{EndpointRegistration, Node},
+};
+
+/// A received value of an instance, or the key of a disposed instance
+pub type KeyedSample<M> = Sample<M, <M as Keyed>::K>;
+
+/// Publisher of a keyed Topic
+///
+/// Create with
+/// [`Node::create_keyed_publisher`](crate::Node::create_keyed_publisher).
+pub struct KeyedPublisher<M>
+where
+  M: Keyed + Serialize,
+  M::K: Serialize,
+{
+  datawriter: with_key::DataWriterCdr<M>,
+  // Removes this from ROS Discovery when dropped
+  registration: Option<EndpointRegistration>,
+}
+
+impl<M> KeyedPublisher<M>
+where
+  M: Keyed + Serialize,
+  M::K: Serialize,
+{
+  pub(crate) fn new(datawriter: with_key::DataWriterCdr<M>) -> Self {
+    KeyedPublisher {
+      datawriter,
+      registration: None,
+    }
+  }
+
+  pub(crate) fn set_registration(&mut self, registration: EndpointRegistration) {
+    self.registration = Some(registration);
+  }
+
+  /// Publishes `message` as the new value of its instance.
+  pub fn publish(&self, message: M) -> WriteResult<(), M> {
+    self.datawriter.write(message, Some(Timestamp::now()))
+  }
+
+  pub async fn async_publish(&self, message: M) -> WriteResult<(), M> {
+    self
+      .datawriter
+      .async_write(message, Some(Timestamp::now()))
+      .await
+  }
+
+  /// Tells Subscriptions that the instance with `key` no longer exists.
+  pub fn dispose(&self, key: &M::K) -> WriteResult<(), ()> {
+    self.datawriter.dispose(key, Some(Timestamp::now()))
+  }
+
+  pub fn guid(&self) -> GUID {
+    self.datawriter.guid()
+  }
+
+  pub fn gid(&self) -> Gid {
+    self.guid().into()
+  }
+
+  /// Returns the count of currently matched subscribers.
+  ///
+  /// `my_node` must be the Node that created this Publisher, or the result is
+  /// undefined.
+  pub fn get_subscription_count(&self, my_node: &Node) -> usize {
+    my_node.get_subscription_count(self.guid())
+  }
+}
+
+/// Subscription of a keyed Topic
+///
+/// Received samples are either values of an instance, or disposals of one.
+/// Create with
+/// [`Node::create_keyed_subscription`](crate::Node::create_keyed_subscription).
+pub struct KeyedSubscription<M>
+where
+  M: 'static + Keyed + DeserializeOwned,
+  for<'de> M::K: serde::Deserialize<'de>,
+{
+  datareader: with_key::DataReaderCdr<M>,
+  // Removes this from ROS Discovery when dropped
+  registration: Option<EndpointRegistration>,
+}
+
+impl<M> KeyedSubscription<M>
+where
+  M: 'static + Keyed + DeserializeOwned,
+  for<'de> M::K: serde::Deserialize<'de>,
+{
+  pub(crate) fn new(datareader: with_key::DataReaderCdr<M>) -> Self {
+    KeyedSubscription {
+      datareader,
+      registration: None,
+    }
+  }
+
+  pub(crate) fn set_registration(&mut self, registration: EndpointRegistration) {
+    self.registration = Some(registration);
+  }
+
+  /// Take the next sample, if one is available now.
+  pub fn take(&mut self) -> ReadResult<Option<(KeyedSample<M>, MessageInfo)>> {
+    Ok(self.datareader.take_next_sample()?.map(|ds| {
+      let info = MessageInfo::from(ds.sample_info());
+      (ds.into_value(), info)
+    }))
+  }
+
+  /// Received samples as they arrive
+  ///
+  /// This consumes the Subscription, because the stream needs to own the DDS
+  /// DataReader.
+  pub fn into_async_stream(self) -> impl Stream<Item = ReadResult<(KeyedSample<M>, MessageInfo)>> {
+    self.datareader.async_sample_stream().map(|result| {
+      result.map(|ds| {
+        let info = MessageInfo::from(ds.sample_info());
+        (ds.into_value(), info)
+      })
+    })
+  }
+
+  pub fn guid(&self) -> GUID {
+    self.datareader.guid()
+  }
+
+  pub fn gid(&self) -> Gid {
+    self.guid().into()
+  }
+
+  /// Returns the count of currently matched Publishers.
+  ///
+  /// `my_node` must be the Node that created this Subscription, or the result
+  /// is undefined.
+  pub fn get_publisher_count(&self, my_node: &Node) -> usize {
+    my_node.get_publisher_count(self.guid())
+  }
+}
@@ -0,0 +1,76 @@
+//! Latched Topics, as in ROS 1
+//!
+//! A [`LatchedPublisher`] keeps its latest message available, so that
+//! Subscriptions created later still receive it. This suits Topics that
+//! change rarely, such as `/map` or `/robot_description`. A late-joining
+//! Subscription receives the message only if it also requests
+//! TransientLocal durability, e.g. with QoS from [`latched_qos`].
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use rustdds::{dds::WriteResult, policy, Duration, QosPolicies, QosPolicyBuilder};
+use serde::Serialize;
+
+use crate::pubsub::Publisher;
+
+/// `qos` modified for latching: Reliable, TransientLocal and KeepLast 1.
+///
+/// Use this for Subscriptions of latched Topics, too.
+pub fn latched_qos(qos: &QosPolicies) -> QosPolicies {
+  qos.modify_by(
+    &QosPolicyBuilder::new()
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: Duration::from_millis(100),
+      })
+      .durability(policy::Durability::TransientLocal)
+      .history(policy::History::KeepLast { depth: 1 })
+      .build(),
+  )
+}
+
+/// A [`Publisher`] whose latest message is delivered also to Subscriptions
+/// created after it was published
+///
+/// Create with
+/// [`Node::create_latched_publisher`](crate::Node::create_latched_publisher).
+pub struct LatchedPublisher<M: Serialize> {
+  publisher: Publisher<M>,
+}
+
+impl<M: Serialize> LatchedPublisher<M> {
+  pub(crate) fn new(publisher: Publisher<M>) -> Self {
+    LatchedPublisher { publisher }
+  }
+
+  /// Publishes `message`, replacing the previously latched one.
+  pub fn publish_latched(&self, message: M) -> WriteResult<(), M> {
+    self.publisher.publish(message)
+  }
+
+  pub async fn async_publish_latched(&self, message: M) -> WriteResult<(), M> {
+    self.publisher.async_publish(message).await
+  }
+
+  /// The latching Publisher
+  pub fn publisher(&self) -> &Publisher<M> {
+    &self.publisher
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn qos() {
+    let base = QosPolicyBuilder::new()
+      .history(policy::History::KeepAll)
+      .deadline(policy::Deadline(Duration::from_secs(1)))
+      .build();
+    let qos = latched_qos(&base);
+    assert!(qos.is_reliable());
+    assert!(!qos.is_volatile());
+    assert_eq!(qos.history(), Some(policy::History::KeepLast { depth: 1 }));
+    assert_eq!(qos.deadline(), base.deadline());
+  }
+}
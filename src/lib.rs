@@ -60,9 +60,20 @@ pub mod action_msgs; // action mechanism implementation
 /// Some builtin interfaces for ROS2 communication
 pub mod builtin_interfaces;
 
+/// Clocks for ROS time, including simulated time, system and steady time
+pub mod clock;
+pub mod cdr_adapter;
+mod common_interfaces;
+
 #[doc(hidden)]
 pub mod context;
 
+/// Diagnostic message types, see feature `diagnostic_msgs`
+#[cfg(feature = "diagnostic_msgs")]
+pub mod diagnostic_msgs;
+/// Messages whose type is known only at run time
+pub mod dynamic_message;
+
 #[doc(hidden)] // needed for actions implementation
 pub mod unique_identifier_msgs;
 
@@ -72,21 +83,92 @@ pub mod interfaces;
 
 /// ROS 2 Action machinery
 pub mod action;
+/// Publishers and Subscriptions that adapt to incompatible QoS
+pub mod adaptive_qos;
+/// Recording Topics to rosbag2 bags, see feature `bag`
+#[cfg(feature = "bag")]
+pub mod bag;
 pub mod entities_info;
+pub mod error;
+/// Executors, running the Spinners and callbacks of several Nodes
+pub mod executor;
+/// C ABI, for using ros2-client from C and C++
+#[cfg(feature = "ffi")]
+pub mod ffi;
+/// Adapters for streams of received messages, e.g. deduplication
+pub mod filters;
+/// Geometry message types, e.g. poses and transforms
+pub mod geometry_msgs;
 mod gid;
+mod intra_process;
+/// Saving and loading the discovered ROS 2 Graph, for faster startup
+pub mod graph_cache;
+/// Conversion of messages to and from JSON, e.g. for web tools
+pub mod json;
+/// Keyed (WithKey) Topics, for interoperation with keyed DDS systems
+pub mod keyed;
+/// Latched Topics, whose latest message reaches late Subscriptions
+pub mod latched;
+/// Managed (lifecycle) Node support, e.g. lifecycle-aware Publisher
+pub mod lifecycle;
+/// Messages and Services of managed (lifecycle) Nodes
+pub mod lifecycle_msgs;
 pub mod log;
 pub mod message;
+/// Synchronizing messages of several Topics by header stamps
+pub mod message_filters;
 pub mod message_info;
 pub mod names;
+/// Navigation message types, see feature `nav_msgs`
+#[cfg(feature = "nav_msgs")]
+pub mod nav_msgs;
 pub mod parameters;
 #[doc(hidden)]
 pub mod pubsub;
 pub mod rcl_interfaces;
+/// Keeping a loop at a fixed rate, following a Clock
+pub mod rate;
+/// Forwarding messages between Topics without deserializing them
+pub mod relay;
+/// ROS 2 command line arguments, e.g. remapping with `--ros-args -r`
+pub mod ros_args;
 pub mod ros_time;
+/// Sensor data message types, e.g. point clouds
+pub mod sensor_msgs;
 pub mod service;
+/// Service introspection message types
+pub mod service_msgs;
+/// Statistics message types, e.g. for Topic Statistics
+pub mod statistics_msgs;
+/// Common message types from ROS 2 std_msgs
+pub mod std_msgs;
 
 pub mod steady_time;
+/// Transform message types, see feature `tf2_msgs`
+#[cfg(feature = "tf2_msgs")]
+pub mod tf2_msgs;
+/// Rate-limited Publisher
+pub mod throttle;
+/// Timers, calling a callback periodically in the Spinner of a Node
+pub mod timer;
+/// Topic rate and bandwidth measurement, like `ros2 topic hz` and `bw`
+pub mod topic_monitor;
+/// ROS 2 Topic Statistics: message age and period, published periodically
+pub mod topic_statistics;
+/// Type descriptions, as served by `~/get_type_description`
+pub mod type_description_interfaces;
+/// Type hashes (RIHS01) for checking type compatibility
+pub mod type_hash;
+/// Named QoS profiles loaded from a file
+pub mod qos_profiles;
+/// Processing received messages in parallel on a worker pool
+pub mod dispatch;
+/// Waiting on many Subscriptions, Services, timers etc. at once
+pub mod wait_set;
 mod wide_string;
+/// Publish and subscribe over Zenoh, compatible with rmw_zenoh
+#[cfg(feature = "zenoh")]
+pub mod zenoh_transport;
 
 #[doc(hidden)]
 pub(crate) mod node;
@@ -95,6 +177,8 @@ pub(crate) mod node;
 #[doc(inline)]
 pub use context::*;
 #[doc(inline)]
+pub use error::{EntityKind, Error, WaitTimeout};
+#[doc(inline)]
 pub use message::Message;
 #[doc(inline)]
 pub use names::{ActionTypeName, MessageTypeName, Name, NodeName, ServiceTypeName};
@@ -103,11 +187,15 @@ pub use message_info::MessageInfo;
 #[doc(inline)]
 pub use node::*;
 #[doc(inline)]
-pub use parameters::{Parameter, ParameterValue};
+pub use parameters::{
+  AsyncParametersClient, Parameter, ParameterClient, ParameterUpdate, ParameterValue,
+};
 #[doc(inline)]
 pub use pubsub::*;
 #[doc(inline)]
-pub use service::{AService, Client, Server, Service, ServiceMapping};
+pub use service::{
+  AService, Client, Server, Service, ServiceEvent, ServiceMapping, TypedService,
+};
 #[doc(inline)]
 pub use action::{Action, ActionTypes};
 #[doc(inline)]
@@ -118,7 +206,7 @@ pub use ros_time::{ROSTime, SystemTime};
 /// Module for stuff we do not want to export from top level;
 pub mod ros2 {
   pub use rustdds::{qos::policy, Duration, QosPolicies, QosPolicyBuilder, Timestamp};
-  //TODO: re-export RustDDS error types until ros2-client defines its own
+  // RustDDS error types, as wrapped by crate::Error
   pub use rustdds::dds::{CreateError, ReadError, WaitError, WriteError};
 
   pub use crate::log::LogLevel;
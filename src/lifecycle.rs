@@ -0,0 +1,1103 @@
+//! Support for managed (lifecycle) Nodes
+//!
+//! See the ROS 2 [managed nodes](https://design.ros2.org/articles/node_lifecycle.html)
+//! design article. A managed Node must not produce output unless it is in the
+//! Active state. [`LifecyclePublisher`] enforces this for Topics.
+//!
+//! [`LifecycleNode`] wraps a [`Node`] with the managed Node state machine. It
+//! serves the standard Services, e.g. `~/change_state`, so that tools like
+//! `ros2 lifecycle` can drive it, and calls the hooks of
+//! [`LifecycleCallbacks`] on each transition. [`LifecycleServiceClient`]
+//! manages such Nodes remotely.
+
+use std::{
+  collections::VecDeque,
+  panic::{catch_unwind, AssertUnwindSafe},
+  sync::{Arc, Mutex, Weak},
+};
+
+use futures::{future::BoxFuture, pin_mut, stream::FusedStream, StreamExt};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use rustdds::{dds::WriteResult, QosPolicies, Topic};
+use serde::Serialize;
+
+use crate::{
+  clock::Clock,
+  context::{DEFAULT_PUBLISHER_QOS, DEFAULT_SUBSCRIPTION_QOS},
+  error::Error,
+  lifecycle_msgs::{
+    state::*, transition::*, ChangeState, ChangeStateRequest, ChangeStateResponse,
+    GetAvailableStates, GetAvailableStatesRequest, GetAvailableStatesResponse,
+    GetAvailableTransitions, GetAvailableTransitionsRequest, GetAvailableTransitionsResponse,
+    GetState, GetStateRequest, GetStateResponse, State, Transition, TransitionDescription,
+    TransitionEvent,
+  },
+  message::Message,
+  names::{MessageTypeName, Name, NodeName},
+  node::Node,
+  pubsub::{Publisher, Subscription},
+  service::{panic_message, Client, Server, ServiceMapping, TypedService},
+};
+
+/// An entity that follows the activation state of its managed Node
+///
+/// The managed Node calls these when it enters and leaves the Active state.
+pub trait ManagedEntity {
+  fn on_activate(&self);
+  fn on_deactivate(&self);
+  fn is_activated(&self) -> bool;
+}
+
+/// What [`LifecyclePublisher`] does with messages published while it is not
+/// activated
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InactiveMode {
+  /// Drop the messages. This is what rclcpp does.
+  Drop,
+  /// Keep up to this many latest messages, and publish them on activation.
+  Buffer(usize),
+}
+
+// Activation state and buffer. Kept apart from the Publisher so that it can be
+// tested without DDS.
+struct Gate<M> {
+  activated: bool,
+  mode: InactiveMode,
+  buffer: VecDeque<M>,
+}
+
+impl<M> Gate<M> {
+  fn new(mode: InactiveMode) -> Self {
+    Gate {
+      activated: false,
+      mode,
+      buffer: VecDeque::new(),
+    }
+  }
+
+  // Returns the message, if it should be sent now.
+  fn offer(&mut self, message: M) -> Option<M> {
+    if self.activated {
+      return Some(message);
+    }
+    if let InactiveMode::Buffer(depth) = self.mode {
+      if depth > 0 {
+        if self.buffer.len() >= depth {
+          self.buffer.pop_front();
+        }
+        self.buffer.push_back(message);
+      }
+    }
+    None
+  }
+
+  // Returns the buffered messages, to be sent now.
+  fn activate(&mut self) -> VecDeque<M> {
+    self.activated = true;
+    std::mem::take(&mut self.buffer)
+  }
+}
+
+/// A [`Publisher`] that publishes only while activated
+///
+/// Publishers of a managed Node should be wrapped in this, so that they stay
+/// silent while the Node is not Active. A new LifecyclePublisher is not
+/// activated.
+pub struct LifecyclePublisher<M: Serialize> {
+  publisher: Publisher<M>,
+  gate: Mutex<Gate<M>>,
+}
+
+impl<M: Serialize> LifecyclePublisher<M> {
+  pub fn new(publisher: Publisher<M>, mode: InactiveMode) -> Self {
+    LifecyclePublisher {
+      publisher,
+      gate: Mutex::new(Gate::new(mode)),
+    }
+  }
+
+  /// Publishes `message`, if activated. Otherwise the message is dropped or
+  /// buffered, according to the [`InactiveMode`].
+  ///
+  /// Returns `Ok(true)` if the message was sent.
+  pub fn publish(&self, message: M) -> WriteResult<bool, M> {
+    let to_send = self.gate.lock().unwrap().offer(message);
+    match to_send {
+      Some(message) => self.publisher.publish(message).map(|()| true),
+      None => {
+        trace!(
+          "LifecyclePublisher {:?} is not activated. Message not sent.",
+          self.publisher.guid()
+        );
+        Ok(false)
+      }
+    }
+  }
+
+  /// Changing to [`InactiveMode::Drop`] discards buffered messages.
+  pub fn set_inactive_mode(&self, mode: InactiveMode) {
+    let mut gate = self.gate.lock().unwrap();
+    gate.mode = mode;
+    match mode {
+      InactiveMode::Drop => gate.buffer.clear(),
+      InactiveMode::Buffer(depth) => {
+        let excess = gate.buffer.len().saturating_sub(depth);
+        gate.buffer.drain(..excess);
+      }
+    }
+  }
+
+  pub fn inactive_mode(&self) -> InactiveMode {
+    self.gate.lock().unwrap().mode
+  }
+
+  /// The wrapped Publisher
+  pub fn publisher(&self) -> &Publisher<M> {
+    &self.publisher
+  }
+}
+
+impl<M: Serialize> ManagedEntity for LifecyclePublisher<M> {
+  /// Activates, and publishes the buffered messages.
+  fn on_activate(&self) {
+    let buffered = self.gate.lock().unwrap().activate();
+    for message in buffered {
+      self.publisher.publish(message).unwrap_or_else(|e| {
+        warn!(
+          "LifecyclePublisher: publishing buffered message failed: {:?}",
+          e.forget_data()
+        )
+      });
+    }
+  }
+
+  fn on_deactivate(&self) {
+    self.gate.lock().unwrap().activated = false;
+  }
+
+  fn is_activated(&self) -> bool {
+    self.gate.lock().unwrap().activated
+  }
+}
+
+/// What a [`LifecycleCallbacks`] hook reports
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CallbackReturn {
+  /// The transition completes.
+  Success,
+  /// The transition is rolled back, to the state where it started.
+  Failure,
+  /// The Node goes to ErrorProcessing, see
+  /// [`on_error`](LifecycleCallbacks::on_error). A panicking hook counts as
+  /// this.
+  Error,
+}
+
+/// Hooks that a [`LifecycleNode`] calls on transitions
+///
+/// Each hook gets the state where the transition started. The defaults just
+/// succeed, so a Node that only uses [`LifecyclePublisher`]s needs to
+/// implement none.
+///
+/// Hooks run while the state machine is locked, so they must not call
+/// [`LifecycleNode`] methods.
+pub trait LifecycleCallbacks: Send {
+  /// Unconfigured to Inactive
+  fn on_configure(&mut self, _previous_state: &State) -> CallbackReturn {
+    CallbackReturn::Success
+  }
+
+  /// Inactive to Unconfigured
+  fn on_cleanup(&mut self, _previous_state: &State) -> CallbackReturn {
+    CallbackReturn::Success
+  }
+
+  /// Inactive to Active. The managed entities are already activated, so the
+  /// hook can publish.
+  fn on_activate(&mut self, _previous_state: &State) -> CallbackReturn {
+    CallbackReturn::Success
+  }
+
+  /// Active to Inactive. The managed entities are deactivated after the hook.
+  fn on_deactivate(&mut self, _previous_state: &State) -> CallbackReturn {
+    CallbackReturn::Success
+  }
+
+  /// Any primary state except Finalized, to Finalized
+  fn on_shutdown(&mut self, _previous_state: &State) -> CallbackReturn {
+    CallbackReturn::Success
+  }
+
+  /// Recovers after a hook returned [`CallbackReturn::Error`]. Success goes to
+  /// Unconfigured, anything else to Finalized.
+  fn on_error(&mut self, _previous_state: &State) -> CallbackReturn {
+    CallbackReturn::Success
+  }
+}
+
+/// The transitions of the managed Node state machine, as (transition, start
+/// state, goal state)
+const TRANSITIONS: &[(u8, u8, u8)] = &[
+  (
+    TRANSITION_CONFIGURE,
+    PRIMARY_STATE_UNCONFIGURED,
+    TRANSITION_STATE_CONFIGURING,
+  ),
+  (
+    TRANSITION_CLEANUP,
+    PRIMARY_STATE_INACTIVE,
+    TRANSITION_STATE_CLEANINGUP,
+  ),
+  (
+    TRANSITION_ACTIVATE,
+    PRIMARY_STATE_INACTIVE,
+    TRANSITION_STATE_ACTIVATING,
+  ),
+  (
+    TRANSITION_DEACTIVATE,
+    PRIMARY_STATE_ACTIVE,
+    TRANSITION_STATE_DEACTIVATING,
+  ),
+  (
+    TRANSITION_UNCONFIGURED_SHUTDOWN,
+    PRIMARY_STATE_UNCONFIGURED,
+    TRANSITION_STATE_SHUTTINGDOWN,
+  ),
+  (
+    TRANSITION_INACTIVE_SHUTDOWN,
+    PRIMARY_STATE_INACTIVE,
+    TRANSITION_STATE_SHUTTINGDOWN,
+  ),
+  (
+    TRANSITION_ACTIVE_SHUTDOWN,
+    PRIMARY_STATE_ACTIVE,
+    TRANSITION_STATE_SHUTTINGDOWN,
+  ),
+  (
+    TRANSITION_ON_CONFIGURE_SUCCESS,
+    TRANSITION_STATE_CONFIGURING,
+    PRIMARY_STATE_INACTIVE,
+  ),
+  (
+    TRANSITION_ON_CONFIGURE_FAILURE,
+    TRANSITION_STATE_CONFIGURING,
+    PRIMARY_STATE_UNCONFIGURED,
+  ),
+  (
+    TRANSITION_ON_CONFIGURE_ERROR,
+    TRANSITION_STATE_CONFIGURING,
+    TRANSITION_STATE_ERRORPROCESSING,
+  ),
+  (
+    TRANSITION_ON_CLEANUP_SUCCESS,
+    TRANSITION_STATE_CLEANINGUP,
+    PRIMARY_STATE_UNCONFIGURED,
+  ),
+  (
+    TRANSITION_ON_CLEANUP_FAILURE,
+    TRANSITION_STATE_CLEANINGUP,
+    PRIMARY_STATE_INACTIVE,
+  ),
+  (
+    TRANSITION_ON_CLEANUP_ERROR,
+    TRANSITION_STATE_CLEANINGUP,
+    TRANSITION_STATE_ERRORPROCESSING,
+  ),
+  (
+    TRANSITION_ON_ACTIVATE_SUCCESS,
+    TRANSITION_STATE_ACTIVATING,
+    PRIMARY_STATE_ACTIVE,
+  ),
+  (
+    TRANSITION_ON_ACTIVATE_FAILURE,
+    TRANSITION_STATE_ACTIVATING,
+    PRIMARY_STATE_INACTIVE,
+  ),
+  (
+    TRANSITION_ON_ACTIVATE_ERROR,
+    TRANSITION_STATE_ACTIVATING,
+    TRANSITION_STATE_ERRORPROCESSING,
+  ),
+  (
+    TRANSITION_ON_DEACTIVATE_SUCCESS,
+    TRANSITION_STATE_DEACTIVATING,
+    PRIMARY_STATE_INACTIVE,
+  ),
+  (
+    TRANSITION_ON_DEACTIVATE_FAILURE,
+    TRANSITION_STATE_DEACTIVATING,
+    PRIMARY_STATE_ACTIVE,
+  ),
+  (
+    TRANSITION_ON_DEACTIVATE_ERROR,
+    TRANSITION_STATE_DEACTIVATING,
+    TRANSITION_STATE_ERRORPROCESSING,
+  ),
+  (
+    TRANSITION_ON_SHUTDOWN_SUCCESS,
+    TRANSITION_STATE_SHUTTINGDOWN,
+    PRIMARY_STATE_FINALIZED,
+  ),
+  (
+    TRANSITION_ON_SHUTDOWN_FAILURE,
+    TRANSITION_STATE_SHUTTINGDOWN,
+    PRIMARY_STATE_FINALIZED,
+  ),
+  (
+    TRANSITION_ON_SHUTDOWN_ERROR,
+    TRANSITION_STATE_SHUTTINGDOWN,
+    TRANSITION_STATE_ERRORPROCESSING,
+  ),
+  (
+    TRANSITION_ON_ERROR_SUCCESS,
+    TRANSITION_STATE_ERRORPROCESSING,
+    PRIMARY_STATE_UNCONFIGURED,
+  ),
+  (
+    TRANSITION_ON_ERROR_FAILURE,
+    TRANSITION_STATE_ERRORPROCESSING,
+    PRIMARY_STATE_FINALIZED,
+  ),
+  (
+    TRANSITION_ON_ERROR_ERROR,
+    TRANSITION_STATE_ERRORPROCESSING,
+    PRIMARY_STATE_FINALIZED,
+  ),
+];
+
+const STATES: &[u8] = &[
+  PRIMARY_STATE_UNCONFIGURED,
+  PRIMARY_STATE_INACTIVE,
+  PRIMARY_STATE_ACTIVE,
+  PRIMARY_STATE_FINALIZED,
+  TRANSITION_STATE_CONFIGURING,
+  TRANSITION_STATE_CLEANINGUP,
+  TRANSITION_STATE_SHUTTINGDOWN,
+  TRANSITION_STATE_ACTIVATING,
+  TRANSITION_STATE_DEACTIVATING,
+  TRANSITION_STATE_ERRORPROCESSING,
+];
+
+fn state_msg(id: u8) -> State {
+  let label = match id {
+    PRIMARY_STATE_UNCONFIGURED => "unconfigured",
+    PRIMARY_STATE_INACTIVE => "inactive",
+    PRIMARY_STATE_ACTIVE => "active",
+    PRIMARY_STATE_FINALIZED => "finalized",
+    TRANSITION_STATE_CONFIGURING => "configuring",
+    TRANSITION_STATE_CLEANINGUP => "cleaningup",
+    TRANSITION_STATE_SHUTTINGDOWN => "shuttingdown",
+    TRANSITION_STATE_ACTIVATING => "activating",
+    TRANSITION_STATE_DEACTIVATING => "deactivating",
+    TRANSITION_STATE_ERRORPROCESSING => "errorprocessing",
+    _ => "unknown",
+  };
+  State {
+    id,
+    label: label.to_string(),
+  }
+}
+
+// Labels are those of rcl, so that transitions can be requested by label.
+fn transition_msg(id: u8) -> Transition {
+  let label = match id {
+    TRANSITION_CREATE => "create",
+    TRANSITION_CONFIGURE => "configure",
+    TRANSITION_CLEANUP => "cleanup",
+    TRANSITION_ACTIVATE => "activate",
+    TRANSITION_DEACTIVATE => "deactivate",
+    TRANSITION_UNCONFIGURED_SHUTDOWN
+    | TRANSITION_INACTIVE_SHUTDOWN
+    | TRANSITION_ACTIVE_SHUTDOWN => "shutdown",
+    TRANSITION_DESTROY => "destroy",
+    id if id >= TRANSITION_ON_CONFIGURE_SUCCESS => match id % 10 {
+      0 => "transition_success",
+      1 => "transition_failure",
+      _ => "transition_error",
+    },
+    _ => "unknown",
+  };
+  Transition {
+    id,
+    label: label.to_string(),
+  }
+}
+
+fn describe((transition, start, goal): (u8, u8, u8)) -> TransitionDescription {
+  TransitionDescription {
+    transition: transition_msg(transition),
+    start_state: state_msg(start),
+    goal_state: state_msg(goal),
+  }
+}
+
+// The transition from `start` selected by `request`, as (transition, goal)
+fn find_transition(start: u8, request: &Transition) -> Option<(u8, u8)> {
+  TRANSITIONS
+    .iter()
+    .find(|(id, from, _)| {
+      *from == start
+        && if request.id == TRANSITION_CREATE {
+          // rcl also accepts a label alone.
+          !request.label.is_empty() && transition_msg(*id).label == request.label
+        } else {
+          *id == request.id
+        }
+    })
+    .map(|(id, _, goal)| (*id, *goal))
+}
+
+// The transition that ends transition state `from`, when its hook returns
+// `result`, as (transition, goal)
+fn result_transition(from: u8, result: CallbackReturn) -> (u8, u8) {
+  let offset = match result {
+    CallbackReturn::Success => 0,
+    CallbackReturn::Failure => 1,
+    CallbackReturn::Error => 2,
+  };
+  TRANSITIONS
+    .iter()
+    .filter(|(id, start, _)| *start == from && *id >= TRANSITION_ON_CONFIGURE_SUCCESS)
+    .find(|(id, _, _)| id % 10 == offset)
+    .map(|(id, _, goal)| (*id, *goal))
+    .unwrap_or((TRANSITION_CALLBACK_ERROR, PRIMARY_STATE_FINALIZED))
+}
+
+type SharedEntity = Weak<dyn ManagedEntity + Send + Sync>;
+
+struct StateMachine {
+  state: u8,
+  callbacks: Box<dyn LifecycleCallbacks>,
+  entities: Vec<SharedEntity>,
+  // `None` in tests
+  transition_event_publisher: Option<Publisher<TransitionEvent>>,
+  clock: Clock,
+}
+
+impl StateMachine {
+  fn new(callbacks: Box<dyn LifecycleCallbacks>, clock: Clock) -> Self {
+    StateMachine {
+      state: PRIMARY_STATE_UNCONFIGURED,
+      callbacks,
+      entities: Vec::new(),
+      transition_event_publisher: None,
+      clock,
+    }
+  }
+
+  // Runs `request` through its transition state and hooks. Returns what the
+  // hook of the transition returned, and the new primary state, or `None` if
+  // the transition is not available.
+  fn trigger(&mut self, request: &Transition) -> Option<(CallbackReturn, u8)> {
+    let (transition, mut state) = find_transition(self.state, request)?;
+    let mut previous_state = state_msg(self.state);
+    self.enter(transition, state);
+    let mut first_result = None;
+    while !STATES[..4].contains(&state) {
+      let result = self.run_hook(state, &previous_state);
+      first_result.get_or_insert(result);
+      let (transition, goal) = result_transition(state, result);
+      previous_state = state_msg(state);
+      state = goal;
+      self.enter(transition, state);
+    }
+    first_result.map(|result| (result, state))
+  }
+
+  fn enter(&mut self, transition: u8, goal: u8) {
+    let start = std::mem::replace(&mut self.state, goal);
+    debug!(
+      "Lifecycle transition {:?}: {:?} -> {:?}",
+      transition_msg(transition).label,
+      state_msg(start).label,
+      state_msg(goal).label
+    );
+    // Entities stay active until the deactivate hook has run.
+    self.set_entities_active(matches!(
+      goal,
+      PRIMARY_STATE_ACTIVE | TRANSITION_STATE_ACTIVATING | TRANSITION_STATE_DEACTIVATING
+    ));
+    if let Some(ref publisher) = self.transition_event_publisher {
+      let event = TransitionEvent {
+        timestamp: self.clock.now().to_nanos().max(0) as u64,
+        transition: transition_msg(transition),
+        start_state: state_msg(start),
+        goal_state: state_msg(goal),
+      };
+      publisher.publish(event).unwrap_or_else(|e| {
+        warn!(
+          "Cannot publish lifecycle transition event: {:?}",
+          e.forget_data()
+        )
+      });
+    }
+  }
+
+  fn run_hook(&mut self, state: u8, previous_state: &State) -> CallbackReturn {
+    let callbacks = &mut self.callbacks;
+    let hook = AssertUnwindSafe(|| match state {
+      TRANSITION_STATE_CONFIGURING => callbacks.on_configure(previous_state),
+      TRANSITION_STATE_CLEANINGUP => callbacks.on_cleanup(previous_state),
+      TRANSITION_STATE_ACTIVATING => callbacks.on_activate(previous_state),
+      TRANSITION_STATE_DEACTIVATING => callbacks.on_deactivate(previous_state),
+      TRANSITION_STATE_SHUTTINGDOWN => callbacks.on_shutdown(previous_state),
+      _ => callbacks.on_error(previous_state),
+    });
+    catch_unwind(hook).unwrap_or_else(|payload| {
+      error!(
+        "Lifecycle hook of state {:?} panicked: {}",
+        state_msg(state).label,
+        panic_message(payload.as_ref())
+      );
+      CallbackReturn::Error
+    })
+  }
+
+  fn set_entities_active(&mut self, active: bool) {
+    self.entities.retain(|entity| match entity.upgrade() {
+      None => false,
+      Some(entity) => {
+        if active && !entity.is_activated() {
+          entity.on_activate();
+        } else if !active && entity.is_activated() {
+          entity.on_deactivate();
+        }
+        true
+      }
+    });
+  }
+
+  fn available_transitions(&self) -> Vec<TransitionDescription> {
+    TRANSITIONS
+      .iter()
+      .filter(|(_, start, _)| *start == self.state)
+      .copied()
+      .map(describe)
+      .collect()
+  }
+}
+
+/// A [`Node`] with the managed Node state machine
+///
+/// The Node starts Unconfigured. Transitions are requested either with the
+/// methods of this, or remotely with the Services `~/change_state`,
+/// `~/get_state`, `~/get_available_states`, `~/get_available_transitions`
+/// and `~/get_transition_graph`. Each state change is published on
+/// `~/transition_event`.
+///
+/// The Services are served by the Spinner of the Node, so it must be running
+/// for remote control. Entities added with
+/// [`add_managed_entity`](Self::add_managed_entity), e.g. those from
+/// [`create_lifecycle_publisher`](Self::create_lifecycle_publisher), are
+/// activated only while the Node is Active.
+pub struct LifecycleNode {
+  node: Node,
+  machine: Arc<Mutex<StateMachine>>,
+}
+
+impl LifecycleNode {
+  pub fn new<C>(mut node: Node, callbacks: C) -> Result<LifecycleNode, Error>
+  where
+    C: LifecycleCallbacks + 'static,
+  {
+    let mut machine = StateMachine::new(Box::new(callbacks), node.get_clock());
+    let node_name = node.fully_qualified_name();
+    let event_topic = node.create_topic(
+      &Name::new(&node_name, "transition_event")?,
+      MessageTypeName::new("lifecycle_msgs", "TransitionEvent"),
+      &DEFAULT_PUBLISHER_QOS,
+    )?;
+    machine.transition_event_publisher = Some(node.create_publisher(&event_topic, None)?);
+    let machine = Arc::new(Mutex::new(machine));
+
+    let change_state_server = create_server::<ChangeState>(&mut node, &node_name, "change_state")?;
+    let m = Arc::clone(&machine);
+    node.spawn_in_spinner(serve(change_state_server, move |request| {
+      let result = m.lock().unwrap().trigger(&request.transition);
+      ChangeStateResponse {
+        success: matches!(result, Some((CallbackReturn::Success, _))),
+      }
+    }));
+
+    let get_state_server = create_server::<GetState>(&mut node, &node_name, "get_state")?;
+    let m = Arc::clone(&machine);
+    node.spawn_in_spinner(serve(get_state_server, move |_| GetStateResponse {
+      current_state: state_msg(m.lock().unwrap().state),
+    }));
+
+    let get_available_states_server =
+      create_server::<GetAvailableStates>(&mut node, &node_name, "get_available_states")?;
+    node.spawn_in_spinner(serve(get_available_states_server, |_| {
+      GetAvailableStatesResponse {
+        available_states: STATES.iter().copied().map(state_msg).collect(),
+      }
+    }));
+
+    let get_available_transitions_server =
+      create_server::<GetAvailableTransitions>(&mut node, &node_name, "get_available_transitions")?;
+    let m = Arc::clone(&machine);
+    node.spawn_in_spinner(serve(get_available_transitions_server, move |_| {
+      GetAvailableTransitionsResponse {
+        available_transitions: m.lock().unwrap().available_transitions(),
+      }
+    }));
+
+    let get_transition_graph_server =
+      create_server::<GetAvailableTransitions>(&mut node, &node_name, "get_transition_graph")?;
+    node.spawn_in_spinner(serve(get_transition_graph_server, |_| {
+      GetAvailableTransitionsResponse {
+        available_transitions: TRANSITIONS.iter().copied().map(describe).collect(),
+      }
+    }));
+
+    Ok(LifecycleNode { node, machine })
+  }
+
+  pub fn node(&self) -> &Node {
+    &self.node
+  }
+
+  pub fn node_mut(&mut self) -> &mut Node {
+    &mut self.node
+  }
+
+  /// Unwraps the Node. Its lifecycle Services keep running.
+  pub fn into_node(self) -> Node {
+    self.node
+  }
+
+  pub fn current_state(&self) -> State {
+    state_msg(self.machine.lock().unwrap().state)
+  }
+
+  /// Transitions available from the current state
+  pub fn available_transitions(&self) -> Vec<TransitionDescription> {
+    self.machine.lock().unwrap().available_transitions()
+  }
+
+  /// Runs transition `transition_id`, one of the constants in
+  /// [`transition`](crate::lifecycle_msgs::transition), calling its hooks.
+  ///
+  /// Returns the resulting primary state, or `None` if the transition is not
+  /// available in the current state.
+  pub fn trigger_transition(&self, transition_id: u8) -> Option<State> {
+    let request = Transition {
+      id: transition_id,
+      label: String::new(),
+    };
+    self
+      .machine
+      .lock()
+      .unwrap()
+      .trigger(&request)
+      .map(|(_, state)| state_msg(state))
+  }
+
+  pub fn configure(&self) -> Option<State> {
+    self.trigger_transition(TRANSITION_CONFIGURE)
+  }
+
+  pub fn cleanup(&self) -> Option<State> {
+    self.trigger_transition(TRANSITION_CLEANUP)
+  }
+
+  pub fn activate(&self) -> Option<State> {
+    self.trigger_transition(TRANSITION_ACTIVATE)
+  }
+
+  pub fn deactivate(&self) -> Option<State> {
+    self.trigger_transition(TRANSITION_DEACTIVATE)
+  }
+
+  /// Shuts down from any primary state except Finalized.
+  pub fn shutdown(&self) -> Option<State> {
+    let transition = match self.machine.lock().unwrap().state {
+      PRIMARY_STATE_UNCONFIGURED => TRANSITION_UNCONFIGURED_SHUTDOWN,
+      PRIMARY_STATE_INACTIVE => TRANSITION_INACTIVE_SHUTDOWN,
+      PRIMARY_STATE_ACTIVE => TRANSITION_ACTIVE_SHUTDOWN,
+      _ => return None,
+    };
+    self.trigger_transition(transition)
+  }
+
+  /// Makes `entity` follow the Active state of this Node. Only a weak
+  /// reference is kept, so dropping the entity removes it.
+  pub fn add_managed_entity(&self, entity: &Arc<dyn ManagedEntity + Send + Sync>) {
+    let mut machine = self.machine.lock().unwrap();
+    if machine.state == PRIMARY_STATE_ACTIVE && !entity.is_activated() {
+      entity.on_activate();
+    }
+    machine.entities.push(Arc::downgrade(entity));
+  }
+
+  /// Creates a [`LifecyclePublisher`], which publishes only while this Node
+  /// is Active.
+  pub fn create_lifecycle_publisher<D>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+    mode: InactiveMode,
+  ) -> Result<Arc<LifecyclePublisher<D>>, Error>
+  where
+    D: Serialize + Send + Sync + 'static,
+  {
+    let publisher = Arc::new(LifecyclePublisher::new(
+      self.node.create_publisher(topic, qos)?,
+      mode,
+    ));
+    let entity: Arc<dyn ManagedEntity + Send + Sync> = publisher.clone();
+    self.add_managed_entity(&entity);
+    Ok(publisher)
+  }
+}
+
+/// Manages the lifecycle of another Node, like `ros2 lifecycle` does
+///
+/// This uses the lifecycle Services of the remote Node, e.g. a
+/// [`LifecycleNode`] or an rclcpp LifecycleNode, and follows its
+/// `~/transition_event` Topic. Create with
+/// [`Node::create_lifecycle_client`](crate::Node::create_lifecycle_client).
+pub struct LifecycleServiceClient {
+  remote_node: String, // fully qualified name
+  change_state_client: Client<ChangeState>,
+  get_state_client: Client<GetState>,
+  get_available_states_client: Client<GetAvailableStates>,
+  get_available_transitions_client: Client<GetAvailableTransitions>,
+  get_transition_graph_client: Client<GetAvailableTransitions>,
+  transition_events: Subscription<TransitionEvent>,
+}
+
+impl LifecycleServiceClient {
+  pub(crate) fn new(my_node: &mut Node, remote_node: &NodeName) -> Result<Self, Error> {
+    let remote_fqn = remote_node.fully_qualified_name();
+    let event_topic = my_node.create_topic(
+      &Name::new(&remote_fqn, "transition_event")?,
+      MessageTypeName::new("lifecycle_msgs", "TransitionEvent"),
+      &DEFAULT_SUBSCRIPTION_QOS,
+    )?;
+    Ok(LifecycleServiceClient {
+      change_state_client: create_client(my_node, &remote_fqn, "change_state")?,
+      get_state_client: create_client(my_node, &remote_fqn, "get_state")?,
+      get_available_states_client: create_client(my_node, &remote_fqn, "get_available_states")?,
+      get_available_transitions_client: create_client(
+        my_node,
+        &remote_fqn,
+        "get_available_transitions",
+      )?,
+      get_transition_graph_client: create_client(my_node, &remote_fqn, "get_transition_graph")?,
+      transition_events: my_node.create_subscription(&event_topic, None)?,
+      remote_node: remote_fqn,
+    })
+  }
+
+  /// Fully qualified name of the remote Node
+  pub fn remote_node(&self) -> &str {
+    &self.remote_node
+  }
+
+  /// Wait until the lifecycle Services of the remote Node are connected.
+  /// Requests sent before that may be lost.
+  ///
+  /// `my_node` must be the Node that created this LifecycleServiceClient.
+  pub async fn wait_for_service(&self, my_node: &Node) {
+    futures::join!(
+      self.change_state_client.wait_for_service(my_node),
+      self.get_state_client.wait_for_service(my_node),
+      self.get_available_states_client.wait_for_service(my_node),
+      self
+        .get_available_transitions_client
+        .wait_for_service(my_node),
+      self.get_transition_graph_client.wait_for_service(my_node),
+    );
+  }
+
+  pub async fn get_state(&self) -> Result<State, Error> {
+    let response = self
+      .get_state_client
+      .async_call_service(GetStateRequest::default())
+      .await?;
+    Ok(response.current_state)
+  }
+
+  pub async fn get_available_states(&self) -> Result<Vec<State>, Error> {
+    let response = self
+      .get_available_states_client
+      .async_call_service(GetAvailableStatesRequest::default())
+      .await?;
+    Ok(response.available_states)
+  }
+
+  /// Transitions available from the current state of the remote Node
+  pub async fn get_available_transitions(&self) -> Result<Vec<TransitionDescription>, Error> {
+    let response = self
+      .get_available_transitions_client
+      .async_call_service(GetAvailableTransitionsRequest::default())
+      .await?;
+    Ok(response.available_transitions)
+  }
+
+  /// All transitions of the state machine of the remote Node
+  pub async fn get_transition_graph(&self) -> Result<Vec<TransitionDescription>, Error> {
+    let response = self
+      .get_transition_graph_client
+      .async_call_service(GetAvailableTransitionsRequest::default())
+      .await?;
+    Ok(response.available_transitions)
+  }
+
+  /// Requests transition `transition_id`, one of the constants in
+  /// [`transition`](crate::lifecycle_msgs::transition). The response comes
+  /// after the hook of the remote Node has run.
+  ///
+  /// Returns `Ok(false)` if the transition is not available in the current
+  /// state, or its hook did not succeed.
+  pub async fn change_state(&self, transition_id: u8) -> Result<bool, Error> {
+    self
+      .request_transition(Transition {
+        id: transition_id,
+        label: String::new(),
+      })
+      .await
+  }
+
+  /// Like [`Self::change_state`], but selects the transition by its label,
+  /// e.g. `"configure"` or `"shutdown"`, as `ros2 lifecycle set` does.
+  pub async fn change_state_by_label(&self, label: &str) -> Result<bool, Error> {
+    self
+      .request_transition(Transition {
+        id: TRANSITION_CREATE,
+        label: label.to_string(),
+      })
+      .await
+  }
+
+  pub async fn configure(&self) -> Result<bool, Error> {
+    self.change_state(TRANSITION_CONFIGURE).await
+  }
+
+  pub async fn cleanup(&self) -> Result<bool, Error> {
+    self.change_state(TRANSITION_CLEANUP).await
+  }
+
+  pub async fn activate(&self) -> Result<bool, Error> {
+    self.change_state(TRANSITION_ACTIVATE).await
+  }
+
+  pub async fn deactivate(&self) -> Result<bool, Error> {
+    self.change_state(TRANSITION_DEACTIVATE).await
+  }
+
+  /// Shuts down from any primary state except Finalized.
+  pub async fn shutdown(&self) -> Result<bool, Error> {
+    self.change_state_by_label("shutdown").await
+  }
+
+  async fn request_transition(&self, transition: Transition) -> Result<bool, Error> {
+    let response = self
+      .change_state_client
+      .async_call_service(ChangeStateRequest { transition })
+      .await?;
+    Ok(response.success)
+  }
+
+  /// Transition events of the remote Node, from now on
+  ///
+  /// Each transition gives two events: into the transition state, e.g.
+  /// Configuring, and out of it.
+  pub fn transition_events(&self) -> impl FusedStream<Item = Result<TransitionEvent, Error>> + '_ {
+    // Only events from now on
+    while let Ok(Some(_)) = self.transition_events.take() {}
+    self
+      .transition_events
+      .async_stream()
+      .map(|event| event.map(|(event, _info)| event).map_err(Error::from))
+  }
+
+  /// Waits until the remote Node is in state `state_id`, one of the
+  /// constants in [`state`](crate::lifecycle_msgs::state), e.g. after a
+  /// transition requested by some other Node.
+  ///
+  /// Returns immediately if the remote Node already is in the state.
+  pub async fn wait_for_state(&self, state_id: u8) -> Result<State, Error> {
+    let events = self.transition_events();
+    pin_mut!(events);
+    // Events from now on are in the stream, so none is missed while asking.
+    let state = self.get_state().await?;
+    if state.id == state_id {
+      return Ok(state);
+    }
+    loop {
+      let event = events.select_next_some().await?;
+      if event.goal_state.id == state_id {
+        return Ok(event.goal_state);
+      }
+    }
+  }
+}
+
+fn create_client<S>(my_node: &mut Node, remote_fqn: &str, service: &str) -> Result<Client<S>, Error>
+where
+  S: TypedService + 'static,
+  S::Request: Clone,
+{
+  my_node.create_typed_client(ServiceMapping::Enhanced, &Name::new(remote_fqn, service)?)
+}
+
+fn create_server<S>(node: &mut Node, node_name: &str, service: &str) -> Result<Server<S>, Error>
+where
+  S: TypedService + 'static,
+  S::Request: Clone,
+{
+  node.create_typed_server(ServiceMapping::Enhanced, &Name::new(node_name, service)?)
+}
+
+// Answers requests to `server` with `handler` until reading requests fails.
+fn serve<S, F>(server: Server<S>, mut handler: F) -> BoxFuture<'static, ()>
+where
+  S: TypedService + 'static,
+  S::Request: Message + Send + Sync,
+  S::Response: Message + Send + Sync,
+  F: FnMut(S::Request) -> S::Response + Send + 'static,
+{
+  Box::pin(async move {
+    loop {
+      let (req_id, request) = match server.async_receive_request().await {
+        Ok(r) => r,
+        Err(e) => {
+          error!("Lifecycle Server stops, as receiving requests failed: {e:?}");
+          return;
+        }
+      };
+      server
+        .async_send_response(req_id, handler(request))
+        .await
+        .unwrap_or_else(|e| error!("Lifecycle Server cannot respond to {req_id:?}: {e:?}"));
+    }
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn drop_while_inactive() {
+    let mut g = Gate::new(InactiveMode::Drop);
+    assert_eq!(g.offer(1), None);
+    assert!(g.activate().is_empty());
+    assert_eq!(g.offer(2), Some(2));
+  }
+
+  #[test]
+  fn buffer_while_inactive() {
+    let mut g = Gate::new(InactiveMode::Buffer(2));
+    assert_eq!(g.offer(1), None);
+    assert_eq!(g.offer(2), None);
+    assert_eq!(g.offer(3), None);
+    // Only the latest are kept.
+    assert_eq!(g.activate(), VecDeque::from([2, 3]));
+    assert_eq!(g.offer(4), Some(4));
+  }
+
+  struct Entity(std::sync::atomic::AtomicBool);
+
+  impl ManagedEntity for Entity {
+    fn on_activate(&self) {
+      self.0.store(true, std::sync::atomic::Ordering::SeqCst)
+    }
+    fn on_deactivate(&self) {
+      self.0.store(false, std::sync::atomic::Ordering::SeqCst)
+    }
+    fn is_activated(&self) -> bool {
+      self.0.load(std::sync::atomic::Ordering::SeqCst)
+    }
+  }
+
+  // Returns `activate_result` from on_activate, and records whether the
+  // entity was active then.
+  struct Hooks {
+    activate_result: Arc<Mutex<CallbackReturn>>,
+    entity: Arc<Entity>,
+    active_in_hook: Arc<Mutex<Option<bool>>>,
+  }
+
+  impl LifecycleCallbacks for Hooks {
+    fn on_activate(&mut self, previous_state: &State) -> CallbackReturn {
+      assert_eq!(previous_state.id, PRIMARY_STATE_INACTIVE);
+      *self.active_in_hook.lock().unwrap() = Some(self.entity.is_activated());
+      *self.activate_result.lock().unwrap()
+    }
+    fn on_shutdown(&mut self, _previous_state: &State) -> CallbackReturn {
+      panic!("on_shutdown fails")
+    }
+  }
+
+  fn request(id: u8) -> Transition {
+    Transition {
+      id,
+      label: String::new(),
+    }
+  }
+
+  #[test]
+  fn state_machine() {
+    let activate_result = Arc::new(Mutex::new(CallbackReturn::Failure));
+    let entity = Arc::new(Entity(Default::default()));
+    let active_in_hook = Arc::new(Mutex::new(None));
+    let hooks = Hooks {
+      activate_result: Arc::clone(&activate_result),
+      entity: Arc::clone(&entity),
+      active_in_hook: Arc::clone(&active_in_hook),
+    };
+    let mut m = StateMachine::new(
+      Box::new(hooks),
+      Clock::new(crate::clock::ClockType::SystemTime),
+    );
+    let shared: Arc<dyn ManagedEntity + Send + Sync> = entity.clone();
+    m.entities.push(Arc::downgrade(&shared));
+
+    assert_eq!(m.trigger(&request(TRANSITION_ACTIVATE)), None);
+    // By label
+    let configure = Transition {
+      id: 0,
+      label: "configure".to_string(),
+    };
+    assert_eq!(
+      m.trigger(&configure),
+      Some((CallbackReturn::Success, PRIMARY_STATE_INACTIVE))
+    );
+
+    // Failure rolls back, and deactivates the entity.
+    assert_eq!(
+      m.trigger(&request(TRANSITION_ACTIVATE)),
+      Some((CallbackReturn::Failure, PRIMARY_STATE_INACTIVE))
+    );
+    assert_eq!(*active_in_hook.lock().unwrap(), Some(true));
+    assert!(!entity.is_activated());
+
+    *activate_result.lock().unwrap() = CallbackReturn::Success;
+    assert_eq!(
+      m.trigger(&request(TRANSITION_ACTIVATE)),
+      Some((CallbackReturn::Success, PRIMARY_STATE_ACTIVE))
+    );
+    assert!(entity.is_activated());
+    let available: Vec<u8> = m
+      .available_transitions()
+      .iter()
+      .map(|t| t.transition.id)
+      .collect();
+    assert_eq!(
+      available,
+      vec![TRANSITION_DEACTIVATE, TRANSITION_ACTIVE_SHUTDOWN]
+    );
+
+    // Error in the panicking on_shutdown goes through ErrorProcessing, which
+    // recovers to Unconfigured.
+    assert_eq!(
+      m.trigger(&request(TRANSITION_ACTIVE_SHUTDOWN)),
+      Some((CallbackReturn::Error, PRIMARY_STATE_UNCONFIGURED))
+    );
+    assert!(!entity.is_activated());
+  }
+}
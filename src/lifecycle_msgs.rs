@@ -0,0 +1,162 @@
+//! Corresponds to package
+//! [lifecycle_msgs](https://index.ros.org/p/lifecycle_msgs/). Defines the
+//! messages and Services of managed Nodes, see [`lifecycle`](crate::lifecycle).
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ros2_service, Message};
+
+/// Ids of [`State`]s
+///
+/// [State](https://github.com/ros2/rcl_interfaces/blob/rolling/lifecycle_msgs/msg/State.msg)
+pub mod state {
+  pub const PRIMARY_STATE_UNKNOWN: u8 = 0;
+  pub const PRIMARY_STATE_UNCONFIGURED: u8 = 1;
+  pub const PRIMARY_STATE_INACTIVE: u8 = 2;
+  pub const PRIMARY_STATE_ACTIVE: u8 = 3;
+  pub const PRIMARY_STATE_FINALIZED: u8 = 4;
+  pub const TRANSITION_STATE_CONFIGURING: u8 = 10;
+  pub const TRANSITION_STATE_CLEANINGUP: u8 = 11;
+  pub const TRANSITION_STATE_SHUTTINGDOWN: u8 = 12;
+  pub const TRANSITION_STATE_ACTIVATING: u8 = 13;
+  pub const TRANSITION_STATE_DEACTIVATING: u8 = 14;
+  pub const TRANSITION_STATE_ERRORPROCESSING: u8 = 15;
+}
+
+/// Ids of [`Transition`]s
+///
+/// [Transition](https://github.com/ros2/rcl_interfaces/blob/rolling/lifecycle_msgs/msg/Transition.msg)
+pub mod transition {
+  pub const TRANSITION_CREATE: u8 = 0;
+  pub const TRANSITION_CONFIGURE: u8 = 1;
+  pub const TRANSITION_CLEANUP: u8 = 2;
+  pub const TRANSITION_ACTIVATE: u8 = 3;
+  pub const TRANSITION_DEACTIVATE: u8 = 4;
+  pub const TRANSITION_UNCONFIGURED_SHUTDOWN: u8 = 5;
+  pub const TRANSITION_INACTIVE_SHUTDOWN: u8 = 6;
+  pub const TRANSITION_ACTIVE_SHUTDOWN: u8 = 7;
+  pub const TRANSITION_DESTROY: u8 = 8;
+
+  pub const TRANSITION_ON_CONFIGURE_SUCCESS: u8 = 10;
+  pub const TRANSITION_ON_CONFIGURE_FAILURE: u8 = 11;
+  pub const TRANSITION_ON_CONFIGURE_ERROR: u8 = 12;
+  pub const TRANSITION_ON_CLEANUP_SUCCESS: u8 = 20;
+  pub const TRANSITION_ON_CLEANUP_FAILURE: u8 = 21;
+  pub const TRANSITION_ON_CLEANUP_ERROR: u8 = 22;
+  pub const TRANSITION_ON_ACTIVATE_SUCCESS: u8 = 30;
+  pub const TRANSITION_ON_ACTIVATE_FAILURE: u8 = 31;
+  pub const TRANSITION_ON_ACTIVATE_ERROR: u8 = 32;
+  pub const TRANSITION_ON_DEACTIVATE_SUCCESS: u8 = 40;
+  pub const TRANSITION_ON_DEACTIVATE_FAILURE: u8 = 41;
+  pub const TRANSITION_ON_DEACTIVATE_ERROR: u8 = 42;
+  pub const TRANSITION_ON_SHUTDOWN_SUCCESS: u8 = 50;
+  pub const TRANSITION_ON_SHUTDOWN_FAILURE: u8 = 51;
+  pub const TRANSITION_ON_SHUTDOWN_ERROR: u8 = 52;
+  pub const TRANSITION_ON_ERROR_SUCCESS: u8 = 60;
+  pub const TRANSITION_ON_ERROR_FAILURE: u8 = 61;
+  pub const TRANSITION_ON_ERROR_ERROR: u8 = 62;
+  pub const TRANSITION_CALLBACK_SUCCESS: u8 = 97;
+  pub const TRANSITION_CALLBACK_FAILURE: u8 = 98;
+  pub const TRANSITION_CALLBACK_ERROR: u8 = 99;
+}
+
+/// A state of a managed Node
+///
+/// [State](https://github.com/ros2/rcl_interfaces/blob/rolling/lifecycle_msgs/msg/State.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct State {
+  /// One of the constants in [`state`]
+  pub id: u8,
+  pub label: String,
+}
+impl Message for State {}
+
+/// A transition between states of a managed Node
+///
+/// [Transition](https://github.com/ros2/rcl_interfaces/blob/rolling/lifecycle_msgs/msg/Transition.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Transition {
+  /// One of the constants in [`transition`]
+  pub id: u8,
+  pub label: String,
+}
+impl Message for Transition {}
+
+/// [TransitionDescription](https://github.com/ros2/rcl_interfaces/blob/rolling/lifecycle_msgs/msg/TransitionDescription.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TransitionDescription {
+  pub transition: Transition,
+  pub start_state: State,
+  pub goal_state: State,
+}
+impl Message for TransitionDescription {}
+
+/// Published on `~/transition_event` by a managed Node on each state change
+///
+/// [TransitionEvent](https://github.com/ros2/rcl_interfaces/blob/rolling/lifecycle_msgs/msg/TransitionEvent.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransitionEvent {
+  /// Nanoseconds since epoch
+  pub timestamp: u64,
+  pub transition: Transition,
+  pub start_state: State,
+  pub goal_state: State,
+}
+impl Message for TransitionEvent {}
+
+ros2_service! {
+  /// Requests a transition of a managed Node, served on `~/change_state`
+  ///
+  /// [ChangeState](https://github.com/ros2/rcl_interfaces/blob/rolling/lifecycle_msgs/srv/ChangeState.srv)
+  pub ChangeState in "lifecycle_msgs" {
+    #[derive(Default)]
+    request ChangeStateRequest {
+      /// Either `id`, or `label` with id 0, selects the transition.
+      pub transition: Transition,
+    }
+    #[derive(Default)]
+    response ChangeStateResponse { pub success: bool }
+  }
+}
+
+// Requests without fields still have a dummy member on the wire, like the
+// structs generated by rosidl.
+
+ros2_service! {
+  /// Served on `~/get_state`
+  ///
+  /// [GetState](https://github.com/ros2/rcl_interfaces/blob/rolling/lifecycle_msgs/srv/GetState.srv)
+  pub GetState in "lifecycle_msgs" {
+    #[derive(Default)]
+    request GetStateRequest { pub structure_needs_at_least_one_member: u8 }
+    #[derive(Default)]
+    response GetStateResponse { pub current_state: State }
+  }
+}
+
+ros2_service! {
+  /// Served on `~/get_available_states`
+  ///
+  /// [GetAvailableStates](https://github.com/ros2/rcl_interfaces/blob/rolling/lifecycle_msgs/srv/GetAvailableStates.srv)
+  pub GetAvailableStates in "lifecycle_msgs" {
+    #[derive(Default)]
+    request GetAvailableStatesRequest { pub structure_needs_at_least_one_member: u8 }
+    #[derive(Default)]
+    response GetAvailableStatesResponse { pub available_states: Vec<State> }
+  }
+}
+
+ros2_service! {
+  /// Served on `~/get_available_transitions`, and on `~/get_transition_graph`
+  /// for all transitions
+  ///
+  /// [GetAvailableTransitions](https://github.com/ros2/rcl_interfaces/blob/rolling/lifecycle_msgs/srv/GetAvailableTransitions.srv)
+  pub GetAvailableTransitions in "lifecycle_msgs" {
+    #[derive(Default)]
+    request GetAvailableTransitionsRequest { pub structure_needs_at_least_one_member: u8 }
+    #[derive(Default)]
+    response GetAvailableTransitionsResponse {
+      pub available_transitions: Vec<TransitionDescription>,
+    }
+  }
+}
@@ -1,8 +1,17 @@
 //! `rosout` logging data types
 
+use std::{
+  cell::Cell,
+  collections::BTreeMap,
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
 use serde::{Deserialize, Serialize};
 use rustdds::*;
 
+use crate::pubsub::Publisher;
+
 /// Log message structure, communicated over the rosout Topic.
 ///
 /// [Log](https://github.com/ros2/rcl_interfaces/blob/master/rcl_interfaces/msg/Log.msg)
@@ -58,6 +67,49 @@ impl Log {
   pub fn get_line(&self) -> u32 {
     self.line
   }
+
+  /// The [`log`] crate level that corresponds to this message's level.
+  ///
+  /// Levels between the defined ones are rounded down, and both `ERROR` and
+  /// `FATAL` map to `Error`.
+  pub fn log_crate_level(&self) -> log::Level {
+    match self.level {
+      l if l >= Self::ERROR => log::Level::Error,
+      l if l >= Self::WARN => log::Level::Warn,
+      l if l >= Self::INFO => log::Level::Info,
+      l if l >= Self::DEBUG => log::Level::Debug,
+      _ => log::Level::Trace,
+    }
+  }
+
+  /// Emit this message via the [`log`] crate facade.
+  ///
+  /// The log target is the name of the logger (usually a Node name) that
+  /// produced the message, so that e.g. `env_logger` filters can select
+  /// Nodes.
+  pub fn forward_to_log(&self) {
+    let level = self.log_crate_level();
+    if level > log::max_level() {
+      return;
+    }
+    let target = if self.name.is_empty() {
+      "rosout"
+    } else {
+      &self.name
+    };
+    // A RosoutLogger must not publish this again.
+    without_rosout(|| {
+      log::logger().log(
+        &log::Record::builder()
+          .level(level)
+          .target(target)
+          .file(Some(&self.file))
+          .line(Some(self.line))
+          .args(format_args!("{}", self.msg))
+          .build(),
+      )
+    });
+  }
 }
 
 #[repr(u8)]
@@ -71,3 +123,305 @@ pub enum LogLevel {
 }
 
 //impl From<u8> for Level
+
+impl LogLevel {
+  /// The level of a severity number, as in `rcl_interfaces/msg/LoggerLevel`.
+  /// `None` if the number is not one of the levels, e.g. 0 for "unset".
+  pub fn from_severity(severity: u32) -> Option<LogLevel> {
+    match severity {
+      10 => Some(LogLevel::Debug),
+      20 => Some(LogLevel::Info),
+      30 => Some(LogLevel::Warn),
+      40 => Some(LogLevel::Error),
+      50 => Some(LogLevel::Fatal),
+      _ => None,
+    }
+  }
+
+  /// Parses a level name, as in `--log-level`, e.g. `"debug"` or `"WARN"`.
+  pub fn from_name(name: &str) -> Option<LogLevel> {
+    match name.to_ascii_lowercase().as_str() {
+      "debug" => Some(LogLevel::Debug),
+      "info" => Some(LogLevel::Info),
+      "warn" | "warning" => Some(LogLevel::Warn),
+      "error" => Some(LogLevel::Error),
+      "fatal" => Some(LogLevel::Fatal),
+      _ => None,
+    }
+  }
+}
+
+/// Severity thresholds of hierarchical loggers
+///
+/// Logger names are separated by dots, e.g. `talker.planner` is a child of
+/// `talker`. A logger without a level of its own has the level of its nearest
+/// ancestor that has one, or the default level.
+#[derive(Clone, Debug)]
+pub(crate) struct LoggerLevels {
+  default_level: LogLevel,
+  levels: BTreeMap<String, LogLevel>,
+}
+
+impl Default for LoggerLevels {
+  fn default() -> Self {
+    LoggerLevels {
+      default_level: LogLevel::Info,
+      levels: BTreeMap::new(),
+    }
+  }
+}
+
+impl LoggerLevels {
+  pub(crate) fn set_default(&mut self, level: LogLevel) {
+    self.default_level = level;
+  }
+
+  // `None` unsets the level, so that it is inherited again.
+  pub(crate) fn set(&mut self, logger: &str, level: Option<LogLevel>) {
+    match level {
+      Some(level) => self.levels.insert(logger.to_string(), level),
+      None => self.levels.remove(logger),
+    };
+  }
+
+  // The level set for exactly this logger
+  pub(crate) fn get(&self, logger: &str) -> Option<LogLevel> {
+    self.levels.get(logger).copied()
+  }
+
+  pub(crate) fn effective(&self, logger: &str) -> LogLevel {
+    let mut name = logger;
+    loop {
+      if let Some(level) = self.levels.get(name) {
+        return *level;
+      }
+      match name.rfind('.') {
+        Some(dot) => name = &name[..dot],
+        None => return self.default_level,
+      }
+    }
+  }
+}
+
+/// When a call site of [`rosout_throttle!`](crate::rosout_throttle) last
+/// wrote
+#[doc(hidden)]
+pub struct CallSiteThrottle(Mutex<Option<Instant>>);
+
+impl CallSiteThrottle {
+  #[allow(clippy::new_without_default)] // Must be const for statics
+  pub const fn new() -> Self {
+    CallSiteThrottle(Mutex::new(None))
+  }
+
+  /// Whether to write now, i.e. `period` has passed since the last write
+  pub fn pass(&self, period: Duration) -> bool {
+    let now = Instant::now();
+    let mut last = self.0.lock().unwrap();
+    match *last {
+      Some(last) if now.duration_since(last) < period => false,
+      _ => {
+        *last = Some(now);
+        true
+      }
+    }
+  }
+}
+
+thread_local! {
+  // Set while something on this thread must not be logged to rosout
+  static ROSOUT_SUPPRESSED: Cell<bool> = const { Cell::new(false) };
+}
+
+// Runs `f` so that RosoutLogger ignores what it logs. Publishing to rosout
+// may log, which must not recurse, and messages forwarded from rosout must
+// not be published again.
+fn without_rosout<R>(f: impl FnOnce() -> R) -> R {
+  let was_suppressed = ROSOUT_SUPPRESSED.with(|s| s.replace(true));
+  let result = f();
+  ROSOUT_SUPPRESSED.with(|s| s.set(was_suppressed));
+  result
+}
+
+/// A [`log`] crate backend that publishes to rosout
+///
+/// Once installed with [`Self::init`], `info!`, `warn!` etc. from any crate in
+/// the process are published via the rosout writer of the Node, with the
+/// Node name as the logger name, and file, line and module path of the log
+/// call. Create with [`Node::rosout_logger`](crate::Node::rosout_logger).
+///
+/// With the `tracing` feature, this is also a `tracing_subscriber` Layer for
+/// events of the `tracing` crate.
+///
+/// Levels can be set per module, like in `env_logger`. Trace messages are
+/// published at Debug level.
+pub struct RosoutLogger {
+  writer: Arc<Publisher<Log>>,
+  name: String,
+  default_level: log::LevelFilter,
+  // Sorted with the longest module path first
+  module_levels: Vec<(String, log::LevelFilter)>,
+}
+
+impl RosoutLogger {
+  pub(crate) fn new(writer: Arc<Publisher<Log>>, name: &str) -> Self {
+    RosoutLogger {
+      writer,
+      name: name.to_string(),
+      default_level: log::LevelFilter::Info,
+      module_levels: Vec::new(),
+    }
+  }
+
+  /// Level for modules without a level of their own. The default is Info.
+  pub fn level(self, level: log::LevelFilter) -> Self {
+    RosoutLogger {
+      default_level: level,
+      ..self
+    }
+  }
+
+  /// Level for `module` and its submodules, e.g. `"rustdds"` or
+  /// `"my_crate::planner"`. The most specific module wins.
+  pub fn module_level(mut self, module: &str, level: log::LevelFilter) -> Self {
+    self.module_levels.retain(|(m, _)| m != module);
+    self.module_levels.push((module.to_string(), level));
+    self
+      .module_levels
+      .sort_by_key(|(m, _)| std::cmp::Reverse(m.len()));
+    self
+  }
+
+  /// Installs this as the [`log`] crate logger of the process.
+  ///
+  /// Fails if some logger has already been installed.
+  pub fn init(self) -> Result<(), log::SetLoggerError> {
+    let max_level = self
+      .module_levels
+      .iter()
+      .map(|(_, level)| *level)
+      .fold(self.default_level, std::cmp::max);
+    log::set_boxed_logger(Box::new(self))?;
+    log::set_max_level(max_level);
+    Ok(())
+  }
+
+  fn level_for(&self, target: &str) -> log::LevelFilter {
+    self
+      .module_levels
+      .iter()
+      .find(|(module, _)| {
+        target
+          .strip_prefix(module.as_str())
+          .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+      })
+      .map_or(self.default_level, |(_, level)| *level)
+  }
+
+  fn passes(&self, level: log::Level, target: &str) -> bool {
+    !ROSOUT_SUPPRESSED.with(Cell::get) && level <= self.level_for(target)
+  }
+
+  fn publish(&self, level: log::Level, msg: String, file: &str, function: &str, line: u32) {
+    let level = match level {
+      log::Level::Error => LogLevel::Error,
+      log::Level::Warn => LogLevel::Warn,
+      log::Level::Info => LogLevel::Info,
+      log::Level::Debug | log::Level::Trace => LogLevel::Debug,
+    };
+    let message = Log {
+      timestamp: Timestamp::now(),
+      level: level as u8,
+      name: self.name.clone(),
+      msg,
+      file: file.to_string(),
+      function: function.to_string(),
+      line,
+    };
+    without_rosout(|| {
+      // Cannot log the error, because this is the logger.
+      let _ = self.writer.publish(message);
+    });
+  }
+}
+
+impl log::Log for RosoutLogger {
+  fn enabled(&self, metadata: &log::Metadata) -> bool {
+    self.passes(metadata.level(), metadata.target())
+  }
+
+  fn log(&self, record: &log::Record) {
+    if self.enabled(record.metadata()) {
+      self.publish(
+        record.level(),
+        record.args().to_string(),
+        record.file().unwrap_or_default(),
+        record.module_path().unwrap_or_default(),
+        record.line().unwrap_or_default(),
+      );
+    }
+  }
+
+  fn flush(&self) {}
+}
+
+#[cfg(feature = "tracing")]
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for RosoutLogger {
+  fn on_event(&self, event: &tracing::Event<'_>, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+    let metadata = event.metadata();
+    let level = match *metadata.level() {
+      tracing::Level::ERROR => log::Level::Error,
+      tracing::Level::WARN => log::Level::Warn,
+      tracing::Level::INFO => log::Level::Info,
+      tracing::Level::DEBUG => log::Level::Debug,
+      tracing::Level::TRACE => log::Level::Trace,
+    };
+    if !self.passes(level, metadata.target()) {
+      return;
+    }
+    // The message, followed by the other fields as "name=value"
+    struct Fields(String);
+    impl tracing::field::Visit for Fields {
+      fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        use std::fmt::Write;
+        let _ = match (field.name(), self.0.is_empty()) {
+          ("message", true) => write!(self.0, "{value:?}"),
+          ("message", false) => write!(self.0, " {value:?}"),
+          (name, true) => write!(self.0, "{name}={value:?}"),
+          (name, false) => write!(self.0, " {name}={value:?}"),
+        };
+      }
+    }
+    let mut fields = Fields(String::new());
+    event.record(&mut fields);
+    self.publish(
+      level,
+      fields.0,
+      metadata.file().unwrap_or_default(),
+      metadata.module_path().unwrap_or_default(),
+      metadata.line().unwrap_or_default(),
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn logger_hierarchy() {
+    let mut levels = LoggerLevels::default();
+    assert_eq!(levels.effective("talker.planner"), LogLevel::Info);
+    levels.set("talker", Some(LogLevel::Warn));
+    levels.set("talker.planner.search", Some(LogLevel::Debug));
+    assert_eq!(levels.effective("talker"), LogLevel::Warn);
+    assert_eq!(levels.effective("talker.planner"), LogLevel::Warn);
+    assert_eq!(levels.effective("talker.planner.search.x"), LogLevel::Debug);
+    // Not a child, just a similar name
+    assert_eq!(levels.effective("talker2"), LogLevel::Info);
+    levels.set("talker", None);
+    levels.set_default(LogLevel::Error);
+    assert_eq!(levels.effective("talker.planner"), LogLevel::Error);
+    assert_eq!(levels.get("talker"), None);
+  }
+}
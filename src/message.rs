@@ -1,22 +1,6 @@
 //! Defines [`Message`] trait, which defines data that is to be sent over
 //! Topics.
+//!
+//! These are defined in the no-std `ros2-client-core` crate.
 
-use serde::{de::DeserializeOwned, Serialize};
-
-/// Trait to ensure Messages can be (de)serialized
-pub trait Message: Serialize + DeserializeOwned {}
-
-impl Message for () {}
-impl Message for String {}
-
-impl Message for i8 {}
-impl Message for i16 {}
-impl Message for i32 {}
-impl Message for i64 {}
-
-impl Message for u8 {}
-impl Message for u16 {}
-impl Message for u32 {}
-impl Message for u64 {}
-
-impl<T: Message> Message for Vec<T> {}
+pub use ros2_client_core::message::*;
@@ -0,0 +1,380 @@
+//! Synchronizing messages of several Topics by their header stamps
+//!
+//! This is a port of the ROS
+//! [message_filters](https://github.com/ros2/message_filters) synchronizers,
+//! for e.g. sensor fusion, where a camera image should be processed together
+//! with the point cloud taken at the same time.
+//!
+//! The synchronizers join 2 to 9 streams of received messages, such as those
+//! from [`Subscription::async_stream`], into a stream of tuples:
+//!
+//! ```ignore
+//! let synchronized = ApproximateTimeSynchronizer::new(10, Duration::from_millis(20))
+//!   .stream((images.async_stream(), clouds.async_stream()));
+//! pin_mut!(synchronized);
+//! while let Some(Ok(((image, _), (cloud, _)))) = synchronized.next().await {
+//!   // ...
+//! }
+//! ```
+//!
+//! The messages must implement [`HasHeader`]. Read errors are passed through.
+//!
+//! [`Subscription::async_stream`]: crate::pubsub::Subscription::async_stream
+
+use std::{any::Any, collections::VecDeque, convert::TryFrom, time::Duration};
+
+use futures::{future, stream, Stream, StreamExt};
+use rustdds::dds::ReadResult;
+
+use crate::{
+  builtin_interfaces::Time,
+  geometry_msgs::{PointStamped, PoseStamped, TransformStamped},
+  message_info::MessageInfo,
+  sensor_msgs::{CompressedImage, Image, PointCloud2},
+  std_msgs::Header,
+};
+
+/// Messages with a [`Header`], whose stamp is used for synchronization
+pub trait HasHeader {
+  fn header(&self) -> &Header;
+}
+
+macro_rules! has_header {
+  ($($t:ty),+) => {
+    $(
+      impl HasHeader for $t {
+        fn header(&self) -> &Header {
+          &self.header
+        }
+      }
+    )+
+  };
+}
+
+has_header!(
+  Image,
+  CompressedImage,
+  PointCloud2,
+  PointStamped,
+  PoseStamped,
+  TransformStamped
+);
+
+impl HasHeader for Header {
+  fn header(&self) -> &Header {
+    self
+  }
+}
+
+/// Joins messages whose header stamps are exactly equal
+///
+/// Like `message_filters::TimeSynchronizer` in ROS.
+#[derive(Clone, Copy, Debug)]
+pub struct ExactTimeSynchronizer {
+  queue_size: usize,
+}
+
+impl ExactTimeSynchronizer {
+  /// Up to `queue_size` unmatched messages are kept per input stream.
+  pub fn new(queue_size: usize) -> Self {
+    ExactTimeSynchronizer { queue_size }
+  }
+
+  /// Synchronize `inputs`, a tuple of 2 to 9 message streams.
+  pub fn stream<I: SyncInputs>(&self, inputs: I) -> impl Stream<Item = ReadResult<I::Output>> {
+    synchronized(
+      inputs,
+      Synchronizer::new(I::LEN, self.queue_size, Duration::ZERO),
+    )
+  }
+}
+
+/// Joins messages whose header stamps differ by at most `slop`
+///
+/// Like `message_filters::ApproximateTimeSynchronizer` in ROS 2 Python. When
+/// a message arrives, the closest message within `slop` is looked up in each
+/// of the other queues. If all are found, they are output together, and
+/// older messages are dropped.
+#[derive(Clone, Copy, Debug)]
+pub struct ApproximateTimeSynchronizer {
+  queue_size: usize,
+  slop: Duration,
+}
+
+impl ApproximateTimeSynchronizer {
+  /// Up to `queue_size` unmatched messages are kept per input stream.
+  pub fn new(queue_size: usize, slop: Duration) -> Self {
+    ApproximateTimeSynchronizer { queue_size, slop }
+  }
+
+  /// Synchronize `inputs`, a tuple of 2 to 9 message streams.
+  pub fn stream<I: SyncInputs>(&self, inputs: I) -> impl Stream<Item = ReadResult<I::Output>> {
+    synchronized(
+      inputs,
+      Synchronizer::new(I::LEN, self.queue_size, self.slop),
+    )
+  }
+}
+
+// A received message and its MessageInfo, of any type
+type Entry = Box<dyn Any + Send>;
+
+/// Tuples of 2 to 9 streams of received messages, which can be synchronized.
+///
+/// The output of the synchronizer is a tuple of `(message, MessageInfo)`
+/// pairs, in the order of the inputs.
+pub trait SyncInputs {
+  type Output;
+
+  #[doc(hidden)]
+  const LEN: usize;
+
+  #[doc(hidden)]
+  fn tagged(self) -> impl Stream<Item = ReadResult<(usize, Time, Entry)>>;
+
+  #[doc(hidden)]
+  fn untag(entries: Vec<Entry>) -> Self::Output;
+}
+
+fn tag<M>(result: ReadResult<(M, MessageInfo)>, input: usize) -> ReadResult<(usize, Time, Entry)>
+where
+  M: HasHeader + Send + 'static,
+{
+  result.map(|(message, info)| {
+    let stamp = message.header().stamp;
+    (input, stamp, Box::new((message, info)) as Entry)
+  })
+}
+
+fn untag<M: 'static>(entry: Option<Entry>) -> (M, MessageInfo) {
+  // Entries are in input order, so the types match.
+  *entry
+    .and_then(|e| e.downcast().ok())
+    .expect("message_filters: entry type mismatch")
+}
+
+macro_rules! merge {
+  ($s:expr) => { $s };
+  ($s:expr, $($rest:expr),+) => { stream::select($s, merge!($($rest),+)) };
+}
+
+macro_rules! sync_inputs {
+  ($(($S:ident, $M:ident, $i:tt)),+) => {
+    impl<$($S, $M),+> SyncInputs for ($($S,)+)
+    where
+      $(
+        $S: Stream<Item = ReadResult<($M, MessageInfo)>>,
+        $M: HasHeader + Send + 'static,
+      )+
+    {
+      type Output = ($(($M, MessageInfo),)+);
+
+      const LEN: usize = [$($i),+].len();
+
+      fn tagged(self) -> impl Stream<Item = ReadResult<(usize, Time, Entry)>> {
+        merge!($(self.$i.map(|r| tag(r, $i))),+)
+      }
+
+      fn untag(entries: Vec<Entry>) -> Self::Output {
+        let mut entries = entries.into_iter();
+        ($(untag::<$M>(entries.next()),)+)
+      }
+    }
+  };
+}
+
+sync_inputs!((S0, M0, 0), (S1, M1, 1));
+sync_inputs!((S0, M0, 0), (S1, M1, 1), (S2, M2, 2));
+sync_inputs!((S0, M0, 0), (S1, M1, 1), (S2, M2, 2), (S3, M3, 3));
+sync_inputs!(
+  (S0, M0, 0),
+  (S1, M1, 1),
+  (S2, M2, 2),
+  (S3, M3, 3),
+  (S4, M4, 4)
+);
+sync_inputs!(
+  (S0, M0, 0),
+  (S1, M1, 1),
+  (S2, M2, 2),
+  (S3, M3, 3),
+  (S4, M4, 4),
+  (S5, M5, 5)
+);
+sync_inputs!(
+  (S0, M0, 0),
+  (S1, M1, 1),
+  (S2, M2, 2),
+  (S3, M3, 3),
+  (S4, M4, 4),
+  (S5, M5, 5),
+  (S6, M6, 6)
+);
+sync_inputs!(
+  (S0, M0, 0),
+  (S1, M1, 1),
+  (S2, M2, 2),
+  (S3, M3, 3),
+  (S4, M4, 4),
+  (S5, M5, 5),
+  (S6, M6, 6),
+  (S7, M7, 7)
+);
+sync_inputs!(
+  (S0, M0, 0),
+  (S1, M1, 1),
+  (S2, M2, 2),
+  (S3, M3, 3),
+  (S4, M4, 4),
+  (S5, M5, 5),
+  (S6, M6, 6),
+  (S7, M7, 7),
+  (S8, M8, 8)
+);
+
+fn synchronized<I: SyncInputs>(
+  inputs: I,
+  mut synchronizer: Synchronizer<Entry>,
+) -> impl Stream<Item = ReadResult<I::Output>> {
+  inputs.tagged().filter_map(move |result| {
+    future::ready(match result {
+      Ok((input, stamp, entry)) => synchronizer
+        .add(input, stamp, entry)
+        .map(|entries| Ok(I::untag(entries))),
+      Err(e) => Some(Err(e)),
+    })
+  })
+}
+
+// Per-input queues of unmatched messages, ordered by stamp
+struct Synchronizer<T> {
+  queues: Vec<VecDeque<(Time, T)>>,
+  queue_size: usize,
+  slop: i64, // nanoseconds
+}
+
+impl<T> Synchronizer<T> {
+  fn new(inputs: usize, queue_size: usize, slop: Duration) -> Self {
+    Synchronizer {
+      queues: (0..inputs).map(|_| VecDeque::new()).collect(),
+      queue_size: queue_size.max(1),
+      slop: i64::try_from(slop.as_nanos()).unwrap_or(i64::MAX),
+    }
+  }
+
+  // Adds a message to the queue of `input`. Returns one message per input,
+  // if the new message completes a set.
+  fn add(&mut self, input: usize, stamp: Time, item: T) -> Option<Vec<T>> {
+    let queue = &mut self.queues[input];
+    let position = queue.partition_point(|(t, _)| *t <= stamp);
+    queue.insert(position, (stamp, item));
+    let mut position = Some(position);
+    if queue.len() > self.queue_size {
+      queue.pop_front();
+      position = position.and_then(|p| p.checked_sub(1));
+    }
+    let position = position?; // The new message was the oldest one.
+
+    // Closest message within slop in each queue
+    let matches: Vec<usize> = self
+      .queues
+      .iter()
+      .enumerate()
+      .map(|(i, queue)| {
+        if i == input {
+          return Some(position);
+        }
+        queue
+          .iter()
+          .enumerate()
+          .map(|(j, (t, _))| (j, (t.to_nanos() - stamp.to_nanos()).abs()))
+          .filter(|(_, distance)| *distance <= self.slop)
+          .min_by_key(|(_, distance)| *distance)
+          .map(|(j, _)| j)
+      })
+      .collect::<Option<_>>()?;
+
+    // Older messages can no longer be part of a set.
+    Some(
+      self
+        .queues
+        .iter_mut()
+        .zip(matches)
+        .map(|(queue, j)| queue.drain(..=j).last().unwrap().1)
+        .collect(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn t(millis: i64) -> Time {
+    Time::from_nanos(millis * 1_000_000)
+  }
+
+  #[test]
+  fn exact() {
+    let mut s = Synchronizer::new(2, 5, Duration::ZERO);
+    assert_eq!(s.add(0, t(10), "a10"), None);
+    assert_eq!(s.add(0, t(20), "a20"), None);
+    assert_eq!(s.add(1, t(11), "b11"), None);
+    assert_eq!(s.add(1, t(20), "b20"), Some(vec!["a20", "b20"]));
+    // a10 and b11 were dropped, as older than the set.
+    assert!(s.queues.iter().all(VecDeque::is_empty));
+  }
+
+  #[test]
+  fn approximate() {
+    let mut s = Synchronizer::new(3, 5, Duration::from_millis(3));
+    assert_eq!(s.add(0, t(10), "a10"), None);
+    assert_eq!(s.add(0, t(20), "a20"), None);
+    assert_eq!(s.add(1, t(21), "b21"), None);
+    // b21 waits for the third input.
+    assert_eq!(s.add(2, t(18), "c18"), Some(vec!["a20", "b21", "c18"]));
+    assert_eq!(s.add(2, t(30), "c30"), None);
+    assert_eq!(s.add(0, t(34), "a34"), None);
+    assert_eq!(s.add(1, t(31), "b31"), Some(vec!["a34", "b31", "c30"]));
+  }
+
+  #[test]
+  fn tuples() {
+    use futures::executor::block_on;
+
+    let info = || {
+      MessageInfo::new(
+        rustdds::Timestamp::now(),
+        None,
+        1,
+        rustdds::GUID::GUID_UNKNOWN,
+      )
+    };
+    let headers = stream::iter(vec![
+      Ok((Header::new(t(1), "a"), info())),
+      Ok((Header::new(t(2), "a"), info())),
+    ]);
+    let point = PointStamped {
+      header: Header::new(t(2), "b"),
+      ..PointStamped::default()
+    };
+    let points = stream::iter(vec![Ok((point, info()))]);
+
+    let synchronized = ExactTimeSynchronizer::new(5).stream((headers, points));
+    let sets: Vec<_> = block_on(synchronized.collect());
+    assert_eq!(sets.len(), 1);
+    let ((header, _), (point, _)) = sets.into_iter().next().unwrap().unwrap();
+    assert_eq!(header.stamp, point.header.stamp);
+  }
+
+  #[test]
+  fn queue_size() {
+    let mut s = Synchronizer::new(2, 2, Duration::ZERO);
+    assert_eq!(s.add(0, t(1), 1), None);
+    assert_eq!(s.add(0, t(2), 2), None);
+    assert_eq!(s.add(0, t(3), 3), None);
+    // Message 1 no longer fits.
+    assert_eq!(s.add(1, t(1), 10), None);
+    assert_eq!(s.add(1, t(2), 20), Some(vec![2, 20]));
+  }
+}
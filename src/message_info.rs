@@ -1,6 +1,11 @@
 //! Metadata for received `Message`s, such as `Timestamp`s and publisher id.
+//!
+//! The publisher id and sequence number identify a message, e.g. for
+//! detecting duplicates. The timestamps give its latency.
 use rustdds::{rpc::SampleIdentity, *};
 
+use crate::gid::Gid;
+
 /// Message metadata
 #[derive(Debug, Clone)]
 pub struct MessageInfo {
@@ -12,6 +17,21 @@ pub struct MessageInfo {
 }
 
 impl MessageInfo {
+  pub(crate) fn new(
+    received_timestamp: Timestamp,
+    source_timestamp: Option<Timestamp>,
+    sequence_number: i64,
+    publisher: GUID,
+  ) -> Self {
+    MessageInfo {
+      received_timestamp,
+      source_timestamp,
+      sequence_number: SequenceNumber::from(sequence_number),
+      publisher,
+      related_sample_identity: None,
+    }
+  }
+
   pub fn received_timestamp(&self) -> Timestamp {
     self.received_timestamp
   }
@@ -20,10 +40,35 @@ impl MessageInfo {
     self.source_timestamp
   }
 
+  /// Time from publishing to reception, if the publisher sent a timestamp.
+  ///
+  /// This is meaningful only if the clocks of the publishing and receiving
+  /// computers are synchronized. `None` if the message appears to be received
+  /// before it was sent.
+  pub fn latency(&self) -> Option<std::time::Duration> {
+    let latency = self.received_timestamp - self.source_timestamp?;
+    if latency.to_nanoseconds() < 0 {
+      None
+    } else {
+      Some(latency.to_std())
+    }
+  }
+
   pub fn writer_guid(&self) -> GUID {
     self.publisher
   }
 
+  /// The publisher, as identified in the ROS 2 Graph
+  pub fn publisher_gid(&self) -> Gid {
+    Gid::from(self.publisher)
+  }
+
+  /// Sequence number of the message from its publisher. It increases with
+  /// each message published.
+  pub fn sequence_number(&self) -> i64 {
+    self.sequence_number.into()
+  }
+
   pub fn sample_identity(&self) -> rustdds::rpc::SampleIdentity {
     rustdds::rpc::SampleIdentity {
       writer_guid: self.writer_guid(),
@@ -51,7 +96,7 @@ impl From<&SampleInfo> for MessageInfo {
 impl<M> From<&rustdds::no_key::DeserializedCacheChange<M>> for MessageInfo {
   fn from(dcc: &rustdds::no_key::DeserializedCacheChange<M>) -> MessageInfo {
     MessageInfo {
-      received_timestamp: Timestamp::ZERO, // TODO!
+      received_timestamp: dcc.receive_instant,
       source_timestamp: dcc.source_timestamp(),
       sequence_number: dcc.sequence_number,
       publisher: dcc.writer_guid(),
@@ -59,3 +104,27 @@ impl<M> From<&rustdds::no_key::DeserializedCacheChange<M>> for MessageInfo {
     }
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn latency() {
+    let sent = Timestamp::now();
+    let received = sent + Duration::from_millis(5);
+    let guid = GUID::from_bytes([7; 16]);
+    let info = MessageInfo::new(received, Some(sent), 42, guid);
+    // Timestamps have a resolution of 2^-32 seconds.
+    let latency = info.latency().unwrap().as_secs_f64();
+    assert!((latency - 0.005).abs() < 1e-6, "{}", latency);
+    assert_eq!(info.sequence_number(), 42);
+    assert_eq!(info.publisher_gid(), Gid::from(guid));
+
+    assert_eq!(
+      MessageInfo::new(sent, Some(received), 1, guid).latency(),
+      None
+    );
+    assert_eq!(MessageInfo::new(received, None, 1, guid).latency(), None);
+  }
+}
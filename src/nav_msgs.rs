@@ -0,0 +1,11 @@
+//! Corresponds to package [nav_msgs](https://index.ros.org/p/nav_msgs/).
+//!
+//! The types are generated from the ROS 2 definitions, see
+//! `interfaces/nav_msgs/`.
+
+pub use crate::common_interfaces::nav_msgs::msg::*;
+
+/// Services of nav_msgs, e.g. [`srv::GetMap`]
+pub mod srv {
+  pub use crate::common_interfaces::nav_msgs::srv::*;
+}
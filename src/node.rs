@@ -1,44 +1,67 @@
 use std::{
-  collections::{BTreeMap, BTreeSet},
-  error::Error,
-  fmt,
+  collections::{BTreeMap, BTreeSet, VecDeque},
+  convert::TryFrom,
+  path::PathBuf,
   pin::Pin,
   sync::{
     atomic::{AtomicBool, Ordering},
-    Arc, Mutex,
+    Arc, Mutex, Weak,
   },
 };
 
 use futures::{
-  pin_mut, stream::FusedStream, task, task::Poll, Future, FutureExt, Stream, StreamExt,
+  future::BoxFuture,
+  pin_mut,
+  stream::{FusedStream, FuturesUnordered},
+  task, task::Poll, Future, FutureExt, Stream, StreamExt,
   stream,
 };
 use async_channel::Receiver;
+use async_io::Timer;
 #[allow(unused_imports)]
 use log::{debug, error, info, trace, warn};
-use serde::Serialize;
-use rustdds::{
-  dds::{CreateError, CreateResult},
-  *,
-};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use rustdds::{dds::CreateResult, *};
 
 use crate::{
   action::*,
-  builtin_interfaces,
-  context::{Context, DEFAULT_SUBSCRIPTION_QOS},
+  adaptive_qos::{AdaptivePublisher, AdaptiveSubscription},
+  clock::{Clock, ClockType, TimeSource},
+  context::{Context, NodeKey, DEFAULT_PUBLISHER_QOS, DEFAULT_SUBSCRIPTION_QOS},
   entities_info::{NodeEntitiesInfo, ParticipantEntitiesInfo},
+  error::{create_error, EntityKind, Error, WaitTimeout},
   gid::Gid,
+  graph_cache,
+  graph_cache::{GraphMaps, ProvisionalGraph},
+  keyed::{KeyedPublisher, KeyedSubscription},
+  latched::{latched_qos, LatchedPublisher},
+  lifecycle::LifecycleServiceClient,
   log as ros_log,
-  log::Log,
+  log::{Log, LogLevel, LoggerLevels, RosoutLogger},
+  message_info::MessageInfo,
   names::*,
   parameters::*,
-  pubsub::{Publisher, Subscription},
+  pubsub::{Publisher, Subscription, TopicEvent},
+  relay::{Relay, RelayOptions},
+  qos_profiles::QosProfiles,
   rcl_interfaces,
+  ros_args::{self, RemapRule, RosArguments},
   ros_time::ROSTime,
-  service::{Client, Server, Service, ServiceMapping},
+  service::{
+    panic_message, Client, ClientEvent, Server, Service, ServiceEvent, ServiceMapping,
+    TypedService,
+  },
+  statistics_msgs::MetricsMessage,
+  timer,
+  topic_statistics::{publish_statistics, StatisticsCollector, TopicStatisticsOptions},
+  type_description_interfaces::{
+    GetTypeDescription, GetTypeDescriptionRequest, GetTypeDescriptionResponse, TypeDescription,
+    TypeSource,
+  },
+  type_hash::TypeHash,
 };
 
-type ParameterFunc = dyn Fn(&str, &ParameterValue) -> SetParametersResult + Send;
+type ParameterFunc = dyn Fn(&str, &ParameterValue) -> SetParametersResult + Send + Sync;
 
 /// Configuration of [Node]
 /// This is a builder-like struct.
@@ -48,17 +71,23 @@ type ParameterFunc = dyn Fn(&str, &ParameterValue) -> SetParametersResult + Send
 /// they ae always needed and have no reasonable default.
 #[must_use]
 pub struct NodeOptions {
-  #[allow(dead_code)]
   cli_args: Vec<String>,
-  #[allow(dead_code)]
   use_global_arguments: bool, // process-wide command line args
   enable_rosout: bool, // use rosout topic for logging?
   enable_rosout_reading: bool,
+  forward_rosout_to_log: bool,
   start_parameter_services: bool,
+  start_logger_services: bool,
+  start_type_description_service: bool,
   declared_parameters: Vec<Parameter>,
+  parameter_files: Vec<PathBuf>,
+  parameter_overrides: Vec<Parameter>,
+  automatically_declare_parameters_from_overrides: bool,
   allow_undeclared_parameters: bool,
   parameter_validator: Option<Box<ParameterFunc>>,
   parameter_set_action: Option<Box<ParameterFunc>>,
+  qos_profiles: QosProfiles,
+  use_intra_process_comms: bool,
 }
 
 impl NodeOptions {
@@ -71,13 +100,45 @@ impl NodeOptions {
       use_global_arguments: true,
       enable_rosout: true,
       enable_rosout_reading: false,
+      forward_rosout_to_log: false,
       start_parameter_services: true,
+      start_logger_services: false,
+      start_type_description_service: false,
       declared_parameters: Vec::new(),
+      parameter_files: Vec::new(),
+      parameter_overrides: Vec::new(),
+      automatically_declare_parameters_from_overrides: false,
       allow_undeclared_parameters: false,
       parameter_validator: None,
       parameter_set_action: None,
+      qos_profiles: QosProfiles::default(),
+      use_intra_process_comms: false,
+    }
+  }
+  /// Command line arguments for this Node only, e.g.
+  /// `["--ros-args", "-r", "chatter:=talk"]`. See [`ros_args`](crate::ros_args).
+  ///
+  /// These take precedence over the arguments of the process.
+  pub fn cli_args<I, S>(self, args: I) -> NodeOptions
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    NodeOptions {
+      cli_args: args.into_iter().map(Into::into).collect(),
+      ..self
+    }
+  }
+
+  /// Whether to use the `--ros-args` of the process command line. On by
+  /// default.
+  pub fn use_global_arguments(self, use_global_arguments: bool) -> NodeOptions {
+    NodeOptions {
+      use_global_arguments,
+      ..self
     }
   }
+
   pub fn enable_rosout(self, enable_rosout: bool) -> NodeOptions {
     NodeOptions {
       enable_rosout,
@@ -92,6 +153,45 @@ impl NodeOptions {
     }
   }
 
+  /// Forward rosout messages from other Nodes to the [`log`] crate.
+  ///
+  /// Received messages are logged at the corresponding level, with the
+  /// sending Node's logger name as the log target. Messages written by this
+  /// Node are not forwarded. This requires a running Spinner.
+  ///
+  /// This is independent of [`Self::read_rosout`].
+  pub fn forward_rosout_to_log(self, forward_rosout_to_log: bool) -> NodeOptions {
+    NodeOptions {
+      forward_rosout_to_log,
+      ..self
+    }
+  }
+
+  /// Serve `~/get_logger_levels` and `~/set_logger_levels`, so that e.g.
+  /// rqt_logger_level can change the levels of this Node's loggers, see
+  /// [`Node::set_logger_level`]. Off by default, like in rclcpp. The Services
+  /// are run by the Spinner.
+  pub fn enable_logger_service(self, start_logger_services: bool) -> NodeOptions {
+    NodeOptions {
+      start_logger_services,
+      ..self
+    }
+  }
+
+  /// Serve `~/get_type_description`, so that Nodes of Iron and later can
+  /// get the descriptions of the types that this Node uses. Only types
+  /// registered with [`Node::add_type_description`] are described. Off by
+  /// default. The Service is run by the Spinner.
+  pub fn enable_type_description_service(
+    self,
+    start_type_description_service: bool,
+  ) -> NodeOptions {
+    NodeOptions {
+      start_type_description_service,
+      ..self
+    }
+  }
+
   pub fn declare_parameter(mut self, name: &str, value: ParameterValue) -> NodeOptions {
     self.declared_parameters.push(Parameter {
       name: name.to_owned(),
@@ -101,6 +201,38 @@ impl NodeOptions {
     self
   }
 
+  /// Read Parameter values for this Node from a ROS 2 parameters YAML file,
+  /// when the Node is created. See [`ParameterFile`] for the format.
+  ///
+  /// The values override the defaults of declared Parameters, including
+  /// those declared later with [`Node::declare_parameter`]. If there are
+  /// several files, the later ones take precedence. Parameter files take
+  /// precedence over command line arguments.
+  pub fn parameter_file(mut self, path: impl Into<PathBuf>) -> NodeOptions {
+    self.parameter_files.push(path.into());
+    self
+  }
+
+  /// Override the default value of a Parameter. These take precedence over
+  /// [parameter files](Self::parameter_file) and command line arguments.
+  pub fn parameter_override(mut self, name: &str, value: ParameterValue) -> NodeOptions {
+    self.parameter_overrides.push(Parameter {
+      name: name.to_owned(),
+      value,
+    });
+    self
+  }
+
+  /// Declare all overridden Parameters, also those that are not otherwise
+  /// declared. Off by default, so that overrides of unknown Parameters are
+  /// ignored.
+  pub fn automatically_declare_parameters_from_overrides(self, enable: bool) -> NodeOptions {
+    NodeOptions {
+      automatically_declare_parameters_from_overrides: enable,
+      ..self
+    }
+  }
+
   pub fn parameter_validator(mut self, validator: Box<ParameterFunc>) -> NodeOptions {
     self.parameter_validator = Some(validator);
     self
@@ -110,6 +242,39 @@ impl NodeOptions {
     self.parameter_set_action = Some(action);
     self
   }
+
+  /// Named QoS profiles, e.g. loaded with [`QosProfiles::load`]. See
+  /// [`Node::qos_profile`].
+  pub fn qos_profiles(self, qos_profiles: QosProfiles) -> NodeOptions {
+    NodeOptions {
+      qos_profiles,
+      ..self
+    }
+  }
+
+  /// Deliver messages between Publishers and Subscriptions of this Node's
+  /// Context in memory, without serializing them. Off by default.
+  ///
+  /// This applies to Publishers and Subscriptions created by this Node with
+  /// [`Node::create_intra_process_publisher`] and
+  /// [`Node::create_intra_process_subscription`], and only between those
+  /// created with this option. Other Publishers and Subscriptions always use
+  /// DDS, so their message types need not be `Send + Sync`.
+  ///
+  /// A message is delivered in memory only if all Subscriptions matched to the
+  /// Publisher are such, and have the same message type. Otherwise it goes over
+  /// DDS, e.g. to other processes, as usual. Matching is tracked by the
+  /// Spinner, so it must be running.
+  ///
+  /// A message received by several Subscriptions is shared, and copied only
+  /// when taken. Messages published with
+  /// [`Publisher::publish_tracked`] always go over DDS.
+  pub fn use_intra_process_comms(self, use_intra_process_comms: bool) -> NodeOptions {
+    NodeOptions {
+      use_intra_process_comms,
+      ..self
+    }
+  }
 }
 
 impl Default for NodeOptions {
@@ -120,11 +285,249 @@ impl Default for NodeOptions {
 // ----------------------------------------------------------------------------------------------------
 // ----------------------------------------------------------------------------------------------------
 
-/// DDS or ROS 2 Discovery events.
+/// DDS or ROS 2 Discovery events, or application events.
 #[derive(Clone, Debug)]
 pub enum NodeEvent {
   DDS(DomainParticipantStatusEvent),
   ROS(ParticipantEntitiesInfo),
+  /// A ROS 2 Node, given by its fully qualified name, has left the ROS 2
+  /// Graph. Either it was removed, or its DomainParticipant was lost, e.g. the
+  /// process crashed and its DDS lease expired.
+  NodeDeparted(String),
+  /// Posted by the application with [`Node::post_event`]
+  User(UserEvent),
+}
+
+impl NodeEvent {
+  // Does this tell of a change in the DDS or ROS 2 Graph, i.e. participants,
+  // topics, endpoints or Nodes appearing or leaving
+  fn is_graph_change(&self) -> bool {
+    match self {
+      NodeEvent::DDS(event) => matches!(
+        event,
+        DomainParticipantStatusEvent::ParticipantDiscovered { .. }
+          | DomainParticipantStatusEvent::ParticipantLost { .. }
+          | DomainParticipantStatusEvent::TopicDetected { .. }
+          | DomainParticipantStatusEvent::TopicLost { .. }
+          | DomainParticipantStatusEvent::ReaderDetected { .. }
+          | DomainParticipantStatusEvent::WriterDetected { .. }
+          | DomainParticipantStatusEvent::ReaderLost { .. }
+          | DomainParticipantStatusEvent::WriterLost { .. }
+      ),
+      NodeEvent::ROS(_) | NodeEvent::NodeDeparted(_) => true,
+      NodeEvent::User(_) => false,
+    }
+  }
+}
+
+/// An application-defined event, delivered along with the other
+/// [`NodeEvent`]s
+///
+/// The payload can be of any type. Receivers recover it with
+/// [`UserEvent::downcast_ref`].
+#[derive(Clone)]
+pub struct UserEvent {
+  type_name: &'static str,
+  payload: Arc<dyn std::any::Any + Send + Sync>,
+}
+
+impl UserEvent {
+  pub fn new<T: std::any::Any + Send + Sync>(payload: T) -> Self {
+    UserEvent {
+      type_name: std::any::type_name::<T>(),
+      payload: Arc::new(payload),
+    }
+  }
+
+  /// The payload, if it is of type `T`
+  pub fn downcast_ref<T: std::any::Any>(&self) -> Option<&T> {
+    self.payload.downcast_ref()
+  }
+
+  pub fn is<T: std::any::Any>(&self) -> bool {
+    self.payload.is::<T>()
+  }
+}
+
+impl std::fmt::Debug for UserEvent {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "UserEvent({})", self.type_name)
+  }
+}
+
+/// Change in the ROS 2 Graph, produced by [`Node::graph_event_stream`].
+///
+/// Node names are fully qualified, e.g. `/ns/talker`. Topic and Service names
+/// and types are as in ROS 2, e.g. `/chatter` and `std_msgs/msg/String`.
+/// `guid` identifies the DDS Reader or Writer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GraphEvent {
+  NodeAppeared(String),
+  NodeDisappeared(String),
+  PublisherAdded {
+    topic: String,
+    type_name: String,
+    guid: GUID,
+  },
+  PublisherRemoved {
+    topic: String,
+    type_name: String,
+    guid: GUID,
+  },
+  SubscriberAdded {
+    topic: String,
+    type_name: String,
+    guid: GUID,
+  },
+  SubscriberRemoved {
+    topic: String,
+    type_name: String,
+    guid: GUID,
+  },
+  /// A Service Server, recognized from its request Reader
+  ServerAdded {
+    service: String,
+    type_name: String,
+    guid: GUID,
+  },
+  ServerRemoved {
+    service: String,
+    type_name: String,
+    guid: GUID,
+  },
+  /// A Service Client, recognized from its request Writer
+  ClientAdded {
+    service: String,
+    type_name: String,
+    guid: GUID,
+  },
+  ClientRemoved {
+    service: String,
+    type_name: String,
+    guid: GUID,
+  },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum EndpointKind {
+  Publisher,
+  Subscriber,
+  Server,
+  Client,
+}
+
+// An endpoint in the ROS 2 Graph, as seen by graph_event_stream
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct GraphEndpoint {
+  kind: EndpointKind,
+  name: String,
+  type_name: String,
+}
+
+impl GraphEndpoint {
+  fn from_reader(e: &EndpointDescription) -> Option<Self> {
+    Self::new(e, EndpointKind::Subscriber, EndpointKind::Server)
+  }
+
+  fn from_writer(e: &EndpointDescription) -> Option<Self> {
+    Self::new(e, EndpointKind::Publisher, EndpointKind::Client)
+  }
+
+  fn new(
+    e: &EndpointDescription,
+    on_topic: EndpointKind,
+    on_service: EndpointKind,
+  ) -> Option<Self> {
+    let (kind, (name, type_name)) = match demangle_topic(&e.topic_name, &e.type_name) {
+      Some(n) => (on_topic, n),
+      None => (
+        on_service,
+        demangle_service_request(&e.topic_name, &e.type_name)?,
+      ),
+    };
+    Some(GraphEndpoint {
+      kind,
+      name,
+      type_name,
+    })
+  }
+
+  fn event(self, guid: GUID, added: bool) -> GraphEvent {
+    let GraphEndpoint {
+      kind,
+      name,
+      type_name,
+    } = self;
+    match (kind, added) {
+      (EndpointKind::Publisher, true) => GraphEvent::PublisherAdded {
+        topic: name,
+        type_name,
+        guid,
+      },
+      (EndpointKind::Publisher, false) => GraphEvent::PublisherRemoved {
+        topic: name,
+        type_name,
+        guid,
+      },
+      (EndpointKind::Subscriber, true) => GraphEvent::SubscriberAdded {
+        topic: name,
+        type_name,
+        guid,
+      },
+      (EndpointKind::Subscriber, false) => GraphEvent::SubscriberRemoved {
+        topic: name,
+        type_name,
+        guid,
+      },
+      (EndpointKind::Server, true) => GraphEvent::ServerAdded {
+        service: name,
+        type_name,
+        guid,
+      },
+      (EndpointKind::Server, false) => GraphEvent::ServerRemoved {
+        service: name,
+        type_name,
+        guid,
+      },
+      (EndpointKind::Client, true) => GraphEvent::ClientAdded {
+        service: name,
+        type_name,
+        guid,
+      },
+      (EndpointKind::Client, false) => GraphEvent::ClientRemoved {
+        service: name,
+        type_name,
+        guid,
+      },
+    }
+  }
+}
+
+// Delivers `event` to all status receivers. Closed receivers are removed.
+fn send_status_event(senders: &Mutex<Vec<async_channel::Sender<NodeEvent>>>, event: &NodeEvent) {
+  let mut closed = Vec::new();
+  let mut sender_array = senders.lock().unwrap();
+  for (i, sender) in sender_array.iter().enumerate() {
+    match sender.try_send(event.clone()) {
+      Ok(()) => {
+        // expected result
+      }
+      Err(async_channel::TrySendError::Closed(_)) => {
+        // trace!("Closing {i}");
+        closed.push(i) // mark for deletion
+      }
+      Err(e) => {
+       debug!("send_status_event: Send error for {i}: {e:?}");
+       // We do not do anything about the error. It may be that the receiver
+       // is not interested and the channel is full.
+      }
+    }
+  }
+
+  // remove senders that reported they were closed
+  for c in closed.iter().rev() {
+    sender_array.swap_remove(*c);
+  }
 }
 
 struct ParameterServers {
@@ -136,6 +539,104 @@ struct ParameterServers {
   describe_parameters_server: Server<rcl_interfaces::DescribeParametersService>,
 }
 
+// ----------------------------------------------------------------------------------------------------
+// ----------------------------------------------------------------------------------------------------
+/// Supervisor for a [`Spinner`] running in the background.
+///
+/// If the Spinner fails, it is restarted after a delay, which doubles on each
+/// consecutive failure up to [`SpinHandle::MAX_RESTART_DELAY`].
+/// The Spinner stops when the Node is dropped or [`SpinHandle::stop`] is
+/// called.
+pub struct SpinHandle {
+  running: Arc<AtomicBool>,
+  stop_sender: async_channel::Sender<()>,
+  // Never sent to. Closed when the supervisor task exits.
+  done_receiver: async_channel::Receiver<()>,
+}
+
+impl SpinHandle {
+  pub const INITIAL_RESTART_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+  pub const MAX_RESTART_DELAY: std::time::Duration = std::time::Duration::from_secs(10);
+
+  /// Start supervising `spinner`.
+  ///
+  /// The supervisor task is given to `spawn`, which should start it in an
+  /// async executor, e.g. `|task| { smol::spawn(task).detach(); }`.
+  pub fn new<F>(spinner: Spinner, spawn: F) -> SpinHandle
+  where
+    F: FnOnce(futures::future::BoxFuture<'static, ()>),
+  {
+    let running = Arc::new(AtomicBool::new(false));
+    let (stop_sender, stop_receiver) = async_channel::bounded(1);
+    let (done_sender, done_receiver) = async_channel::bounded::<()>(1);
+    let task_running = Arc::clone(&running);
+
+    spawn(Box::pin(async move {
+      let mut delay = Self::INITIAL_RESTART_DELAY;
+      loop {
+        let started = std::time::Instant::now();
+        task_running.store(true, Ordering::SeqCst);
+        let result = futures::select! {
+          r = spinner.run().fuse() => Some(r),
+          _ = stop_receiver.recv().fuse() => None,
+        };
+        task_running.store(false, Ordering::SeqCst);
+        // run() may have been cancelled, so it could not clear this, nor
+        // signal a waiting Node::close() or Node::drop().
+        spinner.spinner_running.store(false, Ordering::SeqCst);
+        let _ = spinner.spin_stopped_sender.try_send(());
+
+        match result {
+          None => break,         // stop requested
+          Some(Ok(())) => break, // Node was dropped
+          Some(Err(e)) => {
+            if started.elapsed() > Self::MAX_RESTART_DELAY {
+              // It ran fine for a while, so this is not a repeating failure.
+              delay = Self::INITIAL_RESTART_DELAY;
+            }
+            error!("Spinner failed: {e:?}. Restarting in {delay:?}.");
+            futures::select! {
+              _ = FutureExt::fuse(Timer::after(delay)) => {}
+              _ = stop_receiver.recv().fuse() => break,
+            }
+            delay = std::cmp::min(delay * 2, Self::MAX_RESTART_DELAY);
+          }
+        }
+      }
+      drop(done_sender);
+    }));
+
+    SpinHandle {
+      running,
+      stop_sender,
+      done_receiver,
+    }
+  }
+
+  /// Is the Spinner currently running?
+  ///
+  /// This is `false` before the supervisor task has started, while waiting
+  /// for a restart, and after stopping.
+  pub fn is_running(&self) -> bool {
+    self.running.load(Ordering::SeqCst)
+  }
+
+  /// Stop the Spinner and wait for the supervisor task to finish.
+  pub async fn stop(self) {
+    // Error means that the task has already exited.
+    let _ = self.stop_sender.try_send(());
+    self.join().await
+  }
+
+  /// Wait for the supervisor task to finish without stopping it, i.e. until
+  /// the Node is dropped.
+  pub async fn join(&self) {
+    // This returns an error when the channel is closed, which is what we
+    // wait for.
+    let _ = self.done_receiver.recv().await;
+  }
+}
+
 // ----------------------------------------------------------------------------------------------------
 // ----------------------------------------------------------------------------------------------------
 /// Spinner implements Node's background event loop.
@@ -146,25 +647,40 @@ struct ParameterServers {
 pub struct Spinner {
   ros_context: Context,
   stop_spin_receiver: async_channel::Receiver<()>,
+  // Teardown handshake with Node::drop()
+  spinner_running: Arc<AtomicBool>,
+  spin_stopped_sender: async_channel::Sender<()>,
 
   readers_to_remote_writers: Arc<Mutex<BTreeMap<GUID, BTreeSet<GUID>>>>,
   writers_to_remote_readers: Arc<Mutex<BTreeMap<GUID, BTreeSet<GUID>>>>,
   // Keep track of ros_discovery_info
   external_nodes: Arc<Mutex<BTreeMap<Gid, Vec<NodeEntitiesInfo>>>>,
+  // All Readers and Writers seen in DDS Discovery
+  discovered_readers: Arc<Mutex<BTreeMap<GUID, EndpointDescription>>>,
+  discovered_writers: Arc<Mutex<BTreeMap<GUID, EndpointDescription>>>,
+  // Entries loaded from a graph cache, not yet seen in Discovery
+  provisional_graph: Arc<Mutex<ProvisionalGraph>>,
   //suppress_node_info_updates: Arc<AtomicBool>, // temporarily suppress sending updates
   status_event_senders: Arc<Mutex<Vec<async_channel::Sender<NodeEvent>>>>,
 
-  use_sim_time: Arc<AtomicBool>,
-  sim_time: Arc<Mutex<ROSTime>>,
-  clock_topic: Topic,
+  time_source: TimeSource,
   allow_undeclared_parameters: bool,
 
+  // Forward rosout to log crate, excluding messages from our own writer.
+  forward_rosout_to_log: bool,
+  rosout_writer_guid: Option<GUID>,
+
   parameter_servers: Option<ParameterServers>,
   parameter_events_writer: Arc<Publisher<raw::ParameterEvent>>,
   parameters: Arc<Mutex<BTreeMap<String, ParameterValue>>>,
   parameter_validator: Option<Arc<Mutex<Box<ParameterFunc>>>>,
   parameter_set_action: Option<Arc<Mutex<Box<ParameterFunc>>>>,
+  parameter_declarations: Arc<Mutex<ParameterDeclarations>>,
   fully_qualified_node_name: String,
+
+  // Callbacks from Node::register_callback(), and the running ones
+  callback_receiver: async_channel::Receiver<BoxFuture<'static, ()>>,
+  callbacks: Mutex<FuturesUnordered<BoxFuture<'static, ()>>>,
 }
 
 async fn next_if_some<S>(s: &mut Option<S>) -> S::Item
@@ -178,7 +694,27 @@ where
 }
 
 impl Spinner {
-  pub async fn spin(self) -> CreateResult<()> {
+  /// Run the Spinner until the Node is dropped.
+  ///
+  /// To get automatic restarts on error and a way to stop the Spinner, see
+  /// [`SpinHandle`].
+  pub async fn spin(self) -> Result<(), Error> {
+    self.run().await
+  }
+
+  // Runs the registered callbacks. Resolves when one of them finishes.
+  fn run_callbacks(&self) -> impl Future<Output = ()> + '_ {
+    futures::future::poll_fn(move |cx| {
+      match self.callbacks.lock().unwrap().poll_next_unpin(cx) {
+        Poll::Ready(Some(())) => Poll::Ready(()),
+        // Nothing to run. New callbacks arrive in another branch of the loop.
+        Poll::Ready(None) | Poll::Pending => Poll::Pending,
+      }
+    })
+  }
+
+  // The actual event loop. This can be called again after it returns an error.
+  async fn run(&self) -> Result<(), Error> {
     let dds_status_listener = self.ros_context.domain_participant().status_listener();
     let dds_status_stream = dds_status_listener.as_async_status_stream();
     pin_mut!(dds_status_stream);
@@ -186,15 +722,25 @@ impl Spinner {
     let ros_discovery_topic = self.ros_context.ros_discovery_topic();
     let ros_discovery_reader = self
       .ros_context
-      .create_subscription::<ParticipantEntitiesInfo>(&ros_discovery_topic, None)?;
+      .create_subscription::<ParticipantEntitiesInfo>(&ros_discovery_topic, None)
+      .map_err(create_error(EntityKind::Subscription, &ros_discovery_topic.name()))?;
     let ros_discovery_stream = ros_discovery_reader.async_stream();
     pin_mut!(ros_discovery_stream);
 
-    let ros_clock_reader = self
-      .ros_context
-      .create_subscription::<builtin_interfaces::Time>(&self.clock_topic, None)?;
-    let ros_clock_stream = ros_clock_reader.async_stream();
-    pin_mut!(ros_clock_stream);
+    let rosout_reader_opt = if self.forward_rosout_to_log {
+      let rosout_topic = self.ros_context.get_rosout_topic();
+      Some(
+        self
+          .ros_context
+          .create_subscription::<Log>(&rosout_topic, None)
+          .map_err(create_error(EntityKind::Subscription, &rosout_topic.name()))?,
+      )
+    } else {
+      None
+    };
+    let mut rosout_stream_opt = rosout_reader_opt
+      .as_ref()
+      .map(|r| Box::pin(r.async_stream()));
 
     // These are Option< impl Stream<_>>
     let mut get_parameters_stream_opt = self
@@ -222,24 +768,43 @@ impl Spinner {
       .as_ref()
       .map(|s| s.describe_parameters_server.receive_request_stream());
 
+    // Drops graph cache entries that Discovery did not confirm.
+    let mut graph_cache_purge_timer = StreamExt::fuse(Timer::interval(GRAPH_CACHE_PURGE_INTERVAL));
+
+    let new_callbacks = self.callback_receiver.clone();
+    pin_mut!(new_callbacks);
+
+    self.spinner_running.store(true, Ordering::SeqCst);
+
     loop {
       futures::select! {
         _ = self.stop_spin_receiver.recv().fuse() => {
+          // Acknowledge to Node::drop(), which may be waiting.
+          self.spinner_running.store(false, Ordering::SeqCst);
+          let _ = self.spin_stopped_sender.try_send(());
           break;
         }
 
-        clock_msg = ros_clock_stream.select_next_some() => {
-          match clock_msg {
-            Ok((time,_msg_info)) => {
-              // Simulated time is updated internally unconditionally.
-              // The logic in Node decides if it is used.
-              *self.sim_time.lock().unwrap() = time.into();
+        callback = new_callbacks.select_next_some() => {
+          self.callbacks.lock().unwrap().push(callback);
+        }
+
+        _ = self.run_callbacks().fuse() => {
+          debug!("A task registered with the Spinner finished.");
+        }
+
+
+        rosout_msg = next_if_some(&mut rosout_stream_opt).fuse() => {
+          match rosout_msg {
+            Ok((log_msg, msg_info)) => {
+              if Some(msg_info.writer_guid()) != self.rosout_writer_guid {
+                log_msg.forward_to_log();
+              }
             }
-            Err(e) => warn!("Simulated clock receive error {e:?}")
+            Err(e) => warn!("rosout receive error {e:?}"),
           }
         }
 
-
         get_parameters_request = next_if_some(&mut get_parameters_stream_opt).fuse() => {
           match get_parameters_request {
             Ok( (req_id, req) ) => {
@@ -268,7 +833,7 @@ impl Spinner {
         get_parameter_types_request = next_if_some(&mut get_parameter_types_stream_opt).fuse() => {
           match get_parameter_types_request {
             Ok( (req_id, req) ) => {
-              warn!("Get parameter types request");
+              info!("Get parameter types request");
               let values = {
                 let param_db = self.parameters.lock().unwrap();
                 req.names.iter()
@@ -313,21 +878,14 @@ impl Spinner {
         set_parameters_atomically_request = next_if_some(&mut set_parameters_atomically_stream_opt).fuse() => {
           match set_parameters_atomically_request {
             Ok( (req_id, req) ) => {
-              warn!("Set parameters atomically request {req:?}");
-              let results =
-                req.parameter.iter()
-                  .cloned()
-                  .map( Parameter::from ) // convert from "raw::Parameter"
-                  .map( |Parameter{ .. } |
-                      // TODO: Implement atomic setting.
-                      Err("Setting parameters atomically is not implemented.".to_owned())
-                    )
-                  .map(|r| r.into()) // to "raw" Result for serialization
-                  .collect();
-              warn!("Set parameters atomically response: {results:?}");
+              info!("Set parameters atomically request {req:?}");
+              let result = self.set_parameters_atomically(
+                  req.parameter.into_iter().map( Parameter::from ).collect() )
+                .into(); // to "raw" Result for serialization
+              info!("Set parameters atomically response: {result:?}");
               // .unwrap() below should be safe, as we would not be here if the Server did not exist
               self.parameter_servers.as_ref().unwrap().set_parameters_atomically_server
-                .async_send_response(req_id, rcl_interfaces::SetParametersAtomicallyResponse{ results })
+                .async_send_response(req_id, rcl_interfaces::SetParametersAtomicallyResponse{ result })
                 .await
                 .unwrap_or_else(|e| warn!("SetParameters response error {e:?}"));
             }
@@ -339,22 +897,8 @@ impl Spinner {
           match list_parameter_request {
             Ok( (req_id, req) ) => {
               info!("List parameters request");
-              let prefixes = req.prefixes;
-              // TODO: We only generate the "names" part of the ListParametersResponse
-              // What should we put into `prefixes` ?
-              let names = {
-                let param_db = self.parameters.lock().unwrap();
-                param_db.keys()
-                  .filter_map(|name|
-                    if prefixes.is_empty() ||
-                      prefixes.iter().any(|prefix| name.starts_with(prefix))
-                    {
-                      Some(name.clone())
-                    } else { None }
-                  )
-                  .collect()
-              };
-              let result = rcl_interfaces::ListParametersResult{ names, prefixes: vec![] };
+              let result = list_parameters(
+                self.parameters.lock().unwrap().keys(), &req.prefixes, req.depth);
               // .unwrap() below should be safe, as we would not be here if the Server did not exist
               info!("List parameters response: {result:?}");
               self.parameter_servers.as_ref().unwrap().list_parameters_server
@@ -372,15 +916,9 @@ impl Spinner {
               info!("Describe parameters request {req:?}");
               let values = {
                 let parameters = self.parameters.lock().unwrap();
+                let declarations = self.parameter_declarations.lock().unwrap();
                 req.names.iter()
-                  .map( |name|
-                    {
-                      if let Some(value) = parameters.get(name) {
-                        ParameterDescriptor::from_value(name, value)
-                      } else {
-                        ParameterDescriptor::unknown(name)
-                      }
-                    })
+                  .map( |name| declarations.descriptor(name, parameters.get(name)) )
                   .map(|r| r.into()) // to "raw" Result for serialization
                   .collect()
               };
@@ -400,10 +938,16 @@ impl Spinner {
           match participant_info_update {
             Ok((part_update, _msg_info)) => {
               // insert to Node-local ros_discovery_info bookkeeping
-              let mut info_map = self.external_nodes.lock().unwrap();
-              info_map.insert( part_update.gid, part_update.node_entities_info_seq.clone());
+              let previous = self.external_nodes.lock().unwrap()
+                .insert( part_update.gid, part_update.node_entities_info_seq.clone());
+              self.provisional_graph.lock().unwrap().confirm_participant(part_update.gid);
+              // Nodes that are no longer listed have been removed.
+              let departed = departed_nodes(previous.unwrap_or_default(), part_update.nodes());
               // also notify any status listeneners
               self.send_status_event( &NodeEvent::ROS(part_update) );
+              for fqn in departed {
+                self.send_status_event( &NodeEvent::NodeDeparted(fqn) );
+              }
             }
             Err(e) => {
               warn!("ros_discovery_info error {e:?}");
@@ -414,6 +958,8 @@ impl Spinner {
         dp_status_event = dds_status_stream.select_next_some() => {
           //println!("{:?}", dp_status_event );
 
+          let mut departed = Vec::new();
+
           // update remote reader/writer databases
           match dp_status_event {
             DomainParticipantStatusEvent::RemoteReaderMatched { local_writer, remote_reader } => {
@@ -428,17 +974,42 @@ impl Spinner {
                 .and_modify(|s| {s.insert(remote_writer);} )
                 .or_insert(BTreeSet::from([remote_writer]));
             }
+            DomainParticipantStatusEvent::ReaderDetected { ref reader } => {
+              self.discovered_readers.lock().unwrap()
+                .insert(reader.guid, reader.clone());
+              self.provisional_graph.lock().unwrap().confirm_reader(reader.guid);
+            }
+            DomainParticipantStatusEvent::WriterDetected { ref writer } => {
+              self.discovered_writers.lock().unwrap()
+                .insert(writer.guid, writer.clone());
+              self.provisional_graph.lock().unwrap().confirm_writer(writer.guid);
+            }
             DomainParticipantStatusEvent::ReaderLost {guid, ..} => {
               for ( _local, readers)
               in self.writers_to_remote_readers.lock().unwrap().iter_mut() {
                 readers.remove(&guid);
               }
+              self.discovered_readers.lock().unwrap().remove(&guid);
             }
             DomainParticipantStatusEvent::WriterLost {guid, ..} => {
               for ( _local, writers)
               in self.readers_to_remote_writers.lock().unwrap().iter_mut() {
                 writers.remove(&guid);
               }
+              self.discovered_writers.lock().unwrap().remove(&guid);
+            }
+            DomainParticipantStatusEvent::ParticipantLost { id, .. } => {
+              // Endpoints cannot outlive their Participant.
+              self.discovered_readers.lock().unwrap().retain(|g, _| g.prefix != id);
+              self.discovered_writers.lock().unwrap().retain(|g, _| g.prefix != id);
+              // So cannot its Nodes.
+              self.external_nodes.lock().unwrap().retain(|gid, nodes| {
+                let lost = GUID::from(*gid).prefix == id;
+                if lost {
+                  departed.extend(departed_nodes(std::mem::take(nodes), &[]));
+                }
+                !lost
+              });
             }
 
             _ => {}
@@ -446,38 +1017,32 @@ impl Spinner {
 
           // also notify any status listeneners
           self.send_status_event( &NodeEvent::DDS(dp_status_event) );
+          for fqn in departed {
+            self.send_status_event( &NodeEvent::NodeDeparted(fqn) );
+          }
+        }
+
+        _ = graph_cache_purge_timer.select_next_some() => {
+          let maps = GraphMaps {
+            external_nodes: &self.external_nodes,
+            discovered_readers: &self.discovered_readers,
+            discovered_writers: &self.discovered_writers,
+          };
+          self.provisional_graph.lock().unwrap().purge_if_expired(std::time::Instant::now(), &maps);
         }
       }
     }
-    info!("Spinner exiting .spin()");
+    info!("Spinner exiting .run()");
     Ok(())
     //}
   } // fn
 
   fn send_status_event(&self, event: &NodeEvent) {
-    let mut closed = Vec::new();
-    let mut sender_array = self.status_event_senders.lock().unwrap();
-    for (i, sender) in sender_array.iter().enumerate() {
-      match sender.try_send(event.clone()) {
-        Ok(()) => {
-          // expected result
-        }
-        Err(async_channel::TrySendError::Closed(_)) => {
-          // trace!("Closing {i}");
-          closed.push(i) // mark for deletion
-        }
-        Err(e) => {
-         debug!("send_status_event: Send error for {i}: {e:?}");
-         // We do not do anything about the error. It may be that the receiver
-         // is not interested and the channel is full.
-        }
-      }
-    }
+    send_status_event(&self.status_event_senders, event);
+  }
 
-    // remove senders that reported they were closed
-    for c in closed.iter().rev() {
-      sender_array.swap_remove(*c);
-    }
+  fn time_now(&self) -> ROSTime {
+    self.time_source.now()
   }
 
   // Keep this function in sync with the same function in Node.
@@ -507,7 +1072,7 @@ impl Spinner {
     match name {
       "use_sim_time" => match value {
         ParameterValue::Boolean(s) => {
-          self.use_sim_time.store(*s, Ordering::SeqCst);
+          self.time_source.set_ros_time_active(*s);
           Ok(())
         }
         _ => Err("Parameter 'use_sim_time' must be Boolean.".to_owned()),
@@ -525,6 +1090,10 @@ impl Spinner {
   pub fn set_parameter(&self, name: &str, value: ParameterValue) -> Result<(), String> {
     let already_set = self.parameters.lock().unwrap().contains_key(name);
     if self.allow_undeclared_parameters || already_set {
+      self.parameter_declarations.lock().unwrap().check_set(&[Parameter {
+        name: name.to_owned(),
+        value: value.clone(),
+      }])?;
       self.validate_parameter_on_set(name, &value)?;
       self.execute_parameter_set_actions(name, &value)?;
 
@@ -549,77 +1118,196 @@ impl Spinner {
       self
         .parameter_events_writer
         .publish(raw::ParameterEvent {
-          timestamp: rustdds::Timestamp::now(), // differs from version in Node!!!
+          timestamp: self.time_now().into(),
           node: self.fully_qualified_node_name.clone(),
           new_parameters,
           changed_parameters,
           deleted_parameters: vec![],
         })
-        .unwrap_or_else(|e| warn!("undeclare_parameter: {e:?}"));
+        .unwrap_or_else(|e| warn!("set_parameter: {e:?}"));
       Ok(())
     } else {
       Err("Setting undeclared parameter '".to_owned() + name + "' is not allowed.")
     }
   }
+
+  // Sets either all or none of `parameters`. Set actions are executed only if
+  // all Parameters pass validation. If a set action still fails, the actions
+  // already executed are executed again with the old values, in reverse order.
+  fn set_parameters_atomically(&self, parameters: Vec<Parameter>) -> SetParametersResult {
+    for Parameter { name, value } in &parameters {
+      let already_set = self.parameters.lock().unwrap().contains_key(name);
+      if !(self.allow_undeclared_parameters || already_set) {
+        return Err("Setting undeclared parameter '".to_owned() + name + "' is not allowed.");
+      }
+      self.validate_parameter_on_set(name, value)?;
+    }
+    self
+      .parameter_declarations
+      .lock()
+      .unwrap()
+      .check_set(&parameters)?;
+    let old_values: Vec<ParameterValue> = {
+      let param_db = self.parameters.lock().unwrap();
+      parameters
+        .iter()
+        .map(|p| param_db.get(&p.name).cloned().unwrap_or(ParameterValue::NotSet))
+        .collect()
+    };
+    for (i, Parameter { name, value }) in parameters.iter().enumerate() {
+      if let Err(e) = self.execute_parameter_set_actions(name, value) {
+        for (p, old_value) in parameters[..i].iter().zip(&old_values).rev() {
+          self
+            .execute_parameter_set_actions(&p.name, old_value)
+            .unwrap_or_else(|e| warn!("set_parameters_atomically: rollback of {}: {e}", p.name));
+        }
+        return Err(e);
+      }
+    }
+
+    let mut new_parameters = vec![];
+    let mut changed_parameters = vec![];
+    {
+      let mut param_db = self.parameters.lock().unwrap();
+      for Parameter { name, value } in parameters {
+        let p = raw::Parameter {
+          name: name.clone(),
+          value: value.clone().into(),
+        };
+        match param_db.insert(name, value) {
+          Some(_) => changed_parameters.push(p),
+          None => new_parameters.push(p),
+        }
+      }
+    }
+    self
+      .parameter_events_writer
+      .publish(raw::ParameterEvent {
+        timestamp: self.time_now().into(),
+        node: self.fully_qualified_node_name.clone(),
+        new_parameters,
+        changed_parameters,
+        deleted_parameters: vec![],
+      })
+      .unwrap_or_else(|e| warn!("set_parameters_atomically: {e:?}"));
+    Ok(())
+  }
 } // impl Spinner
 
 // ----------------------------------------------------------------------------------------------------
 // ----------------------------------------------------------------------------------------------------
 
 /// What went wrong in `Node` creation
-#[derive(Debug)]
-pub enum NodeCreateError {
-  DDS(CreateError),
-  BadParameter(String),
+#[deprecated(note = "Node creation now returns ros2_client::Error")]
+pub type NodeCreateError = Error;
+
+/// Error when setting `Parameter`s
+pub enum ParameterError {
+  AlreadyDeclared,
+  InvalidName,
 }
 
-impl From<CreateError> for NodeCreateError {
-  fn from(c: CreateError) -> NodeCreateError {
-    NodeCreateError::DDS(c)
-  }
+// How long Node::drop() waits for the Spinner to stop.
+const NODE_TEARDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+// How often the Spinner checks if graph cache validation period is over.
+const GRAPH_CACHE_PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+// Readers and Writers created via a Node
+#[derive(Default)]
+struct NodeEndpoints {
+  readers: BTreeSet<Gid>,
+  writers: BTreeSet<Gid>,
 }
 
-impl fmt::Display for NodeCreateError {
-  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    match self {
-      Self::DDS(create_error) => write!(f, "NodeCreateError::DDS : {create_error}"),
-      Self::BadParameter(s) => write!(f, "NodeCreateError::BadParameter : {s}"),
-    }
+// Held by entities created via a Node. Dropping it removes the Reader or
+// Writer from the Node and from ROS Discovery, so that Graph tools do not show
+// it any longer.
+pub(crate) struct EndpointRegistration {
+  gid: Gid,
+  node_key: NodeKey,
+  endpoints: Weak<Mutex<NodeEndpoints>>,
+  readers_to_remote_writers: Weak<Mutex<BTreeMap<GUID, BTreeSet<GUID>>>>,
+  ros_context: Context,
+}
+
+impl EndpointRegistration {
+  // Count of Publishers matched to Reader `reader`, like
+  // Node::get_publisher_count(), but without the Node. Zero if the Node is gone.
+  pub(crate) fn publisher_count(&self, reader: GUID) -> usize {
+    self
+      .readers_to_remote_writers
+      .upgrade()
+      .and_then(|matches| matches.lock().unwrap().get(&reader).map(BTreeSet::len))
+      .unwrap_or(0)
   }
 }
 
-impl Error for NodeCreateError {
-  fn source(&self) -> Option<&(dyn Error + 'static)> {
-    match self {
-      Self::DDS(create_error) => Some(create_error),
-      Self::BadParameter(_) => None,
+impl Drop for EndpointRegistration {
+  fn drop(&mut self) {
+    // If the Node is gone, it has already removed itself from ROS Discovery.
+    if let Some(endpoints) = self.endpoints.upgrade() {
+      let removed = {
+        let mut endpoints = endpoints.lock().unwrap();
+        endpoints.readers.remove(&self.gid) | endpoints.writers.remove(&self.gid)
+      };
+      if removed {
+        self
+          .ros_context
+          .remove_node_endpoint(self.node_key, self.gid);
+      }
     }
   }
 }
 
-/// Error when setting `Parameter`s
-pub enum ParameterError {
-  AlreadyDeclared,
-  InvalidName,
+// Type registered with Node::add_type_description
+struct TypeDescriptionEntry {
+  type_description: TypeDescription,
+  type_sources: Vec<TypeSource>,
+  type_hash: TypeHash,
+}
+
+impl TypeDescriptionEntry {
+  fn response(&self, request: &GetTypeDescriptionRequest) -> GetTypeDescriptionResponse {
+    // An empty hash in the request matches any.
+    if !request.type_hash.is_empty() && request.type_hash != self.type_hash.to_string() {
+      return GetTypeDescriptionResponse {
+        failure_reason: format!(
+          "Type hash {} does not match {}",
+          request.type_hash, self.type_hash
+        ),
+        ..GetTypeDescriptionResponse::default()
+      };
+    }
+    GetTypeDescriptionResponse {
+      successful: true,
+      type_description: self.type_description.clone(),
+      type_sources: if request.include_type_sources {
+        self.type_sources.clone()
+      } else {
+        Vec::new()
+      },
+      ..GetTypeDescriptionResponse::default()
+    }
+  }
 }
 
 /// Node in ROS2 network. Holds necessary readers and writers for rosout and
 /// parameter events topics internally.
 ///
 /// These are produced by a [`Context`].
-
-// TODO: We should notify ROS discovery when readers or writers are removed, but
-// now we do not do that.
 pub struct Node {
   node_name: NodeName,
   options: NodeOptions,
 
   pub(crate) ros_context: Context,
+  // Our entry in the ros_discovery_info of the Context
+  node_key: NodeKey,
 
   // sets of Readers and Writers belonging to ( = created via) this Node
-  // These indicate what has been created locally.
-  readers: BTreeSet<Gid>,
-  writers: BTreeSet<Gid>,
+  // These indicate what has been created locally. Shared with the
+  // EndpointRegistrations of the entities.
+  endpoints: Arc<Mutex<NodeEndpoints>>,
 
   suppress_node_info_updates: Arc<AtomicBool>,
   // temporarily suppress sending updates
@@ -633,14 +1321,27 @@ pub struct Node {
 
   // Keep track of ros_discovery_info
   external_nodes: Arc<Mutex<BTreeMap<Gid, Vec<NodeEntitiesInfo>>>>,
+
+  // Keep track of all Readers and Writers seen in DDS Discovery, including
+  // local ones. Map key is the Reader/Writer GUID.
+  discovered_readers: Arc<Mutex<BTreeMap<GUID, EndpointDescription>>>,
+  discovered_writers: Arc<Mutex<BTreeMap<GUID, EndpointDescription>>>,
+  // Entries loaded by load_graph_cache(), not yet confirmed by Discovery
+  provisional_graph: Arc<Mutex<ProvisionalGraph>>,
+
   stop_spin_sender: Option<async_channel::Sender<()>>,
+  // Teardown handshake: Spinner reports here that it has stopped.
+  spinner_running: Arc<AtomicBool>,
+  spin_stopped_receiver: Option<async_channel::Receiver<()>>,
 
   // Channels to report discovery events to
   status_event_senders: Arc<Mutex<Vec<async_channel::Sender<NodeEvent>>>>,
 
   // builtin writers and readers
-  rosout_writer: Option<Publisher<Log>>,
+  rosout_writer: Option<Arc<Publisher<Log>>>,
   rosout_reader: Option<Subscription<Log>>,
+  // Thresholds for writing to rosout
+  logger_levels: Arc<Mutex<LoggerLevels>>,
 
   // Parameter events (rcl_interfaces)
   // Parameter Services are inside Spinner
@@ -651,10 +1352,21 @@ pub struct Node {
   // allow_undeclared_parameters: bool, // this is inside "options"
   parameter_validator: Option<Arc<Mutex<Box<ParameterFunc>>>>,
   parameter_set_action: Option<Arc<Mutex<Box<ParameterFunc>>>>,
+  parameter_declarations: Arc<Mutex<ParameterDeclarations>>,
+  // From command line arguments, parameter files and NodeOptions
+  parameter_overrides: BTreeMap<String, ParameterValue>,
+  // From command line arguments, then those from add_remap
+  remap_rules: Vec<RemapRule>,
 
   // simulated ROSTime
-  use_sim_time: Arc<AtomicBool>,
-  sim_time: Arc<Mutex<ROSTime>>,
+  time_source: TimeSource,
+
+  // Served on ~/get_type_description, by type name
+  type_descriptions: Arc<Mutex<BTreeMap<String, TypeDescriptionEntry>>>,
+
+  // Callbacks to be run by the Spinner
+  callback_sender: async_channel::Sender<BoxFuture<'static, ()>>,
+  callback_receiver: async_channel::Receiver<BoxFuture<'static, ()>>,
 }
 
 impl Node {
@@ -662,14 +1374,44 @@ impl Node {
     node_name: NodeName,
     mut options: NodeOptions,
     ros_context: Context,
-  ) -> Result<Node, NodeCreateError> {
+  ) -> Result<Node, Error> {
+    let global_arguments = if options.use_global_arguments {
+      RosArguments::from_env()?
+    } else {
+      RosArguments::default()
+    };
+    let local_arguments = RosArguments::parse(&options.cli_args)?;
+    let node_name =
+      local_arguments.remap_node_name(&global_arguments.remap_node_name(&node_name)?)?;
+    // Local rules are tried first.
+    let remap_rules = local_arguments
+      .remap_rules()
+      .iter()
+      .chain(global_arguments.remap_rules())
+      .cloned()
+      .collect();
+
+    let mut logger_levels = LoggerLevels::default();
+    for (logger, level) in global_arguments
+      .log_levels()
+      .iter()
+      .chain(local_arguments.log_levels())
+    {
+      match logger {
+        Some(logger) => logger_levels.set(logger, Some(*level)),
+        None => logger_levels.set_default(*level),
+      }
+    }
+
     let paramtopic = ros_context.get_parameter_events_topic();
     let rosout_topic = ros_context.get_rosout_topic();
 
     let enable_rosout = options.enable_rosout;
     let rosout_reader = options.enable_rosout_reading;
 
-    let parameter_events_writer = ros_context.create_publisher(&paramtopic, None)?;
+    let parameter_events_writer = ros_context
+      .create_publisher(&paramtopic, None)
+      .map_err(create_error(EntityKind::Publisher, &paramtopic.name()))?;
 
     // TODO: If there are duplicates, the later one will overwrite the earlier, but
     // there is no warning or error.
@@ -677,12 +1419,45 @@ impl Node {
       name: "use_sim_time".to_string(),
       value: ParameterValue::Boolean(false),
     });
-    let parameters = options
+    let mut parameter_overrides = BTreeMap::new();
+    for arguments in [&global_arguments, &local_arguments] {
+      parameter_overrides.extend(
+        arguments
+          .parameters_for(&node_name)?
+          .into_iter()
+          .map(|Parameter { name, value }| (name, value)),
+      );
+    }
+    for path in &options.parameter_files {
+      let file = ParameterFile::load(path)
+        .map_err(|e| Error::BadParameter(format!("{}: {e}", path.display())))?;
+      parameter_overrides.extend(
+        file
+          .parameters_for(&node_name.fully_qualified_name())
+          .into_iter()
+          .map(|Parameter { name, value }| (name, value)),
+      );
+    }
+    parameter_overrides.extend(
+      options
+        .parameter_overrides
+        .iter()
+        .cloned()
+        .map(|Parameter { name, value }| (name, value)),
+    );
+
+    let mut parameters = options
       .declared_parameters
       .iter()
       .cloned()
-      .map(|Parameter { name, value }| (name, value))
+      .map(|Parameter { name, value }| {
+        let value = parameter_overrides.get(&name).cloned().unwrap_or(value);
+        (name, value)
+      })
       .collect::<BTreeMap<String, ParameterValue>>();
+    if options.automatically_declare_parameters_from_overrides {
+      parameters.extend(parameter_overrides.clone());
+    }
 
     let parameter_validator = options
       .parameter_validator
@@ -693,35 +1468,50 @@ impl Node {
       .take()
       .map(|b| Arc::new(Mutex::new(b)));
 
+    let (callback_sender, callback_receiver) = async_channel::unbounded();
+
+    let node_key = ros_context.new_node_key(&node_name);
+
     let mut node = Node {
       node_name,
       options,
       ros_context,
-      readers: BTreeSet::new(),
-      writers: BTreeSet::new(),
+      node_key,
+      endpoints: Arc::new(Mutex::new(NodeEndpoints::default())),
       readers_to_remote_writers: Arc::new(Mutex::new(BTreeMap::new())),
       writers_to_remote_readers: Arc::new(Mutex::new(BTreeMap::new())),
       external_nodes: Arc::new(Mutex::new(BTreeMap::new())),
+      discovered_readers: Arc::new(Mutex::new(BTreeMap::new())),
+      discovered_writers: Arc::new(Mutex::new(BTreeMap::new())),
+      provisional_graph: Arc::new(Mutex::new(ProvisionalGraph::default())),
       suppress_node_info_updates: Arc::new(AtomicBool::new(false)),
       stop_spin_sender: None,
+      spinner_running: Arc::new(AtomicBool::new(false)),
+      spin_stopped_receiver: None,
       status_event_senders: Arc::new(Mutex::new(Vec::new())),
+      callback_sender,
+      callback_receiver,
       rosout_writer: None, // Set below
       rosout_reader: None,
+      logger_levels: Arc::new(Mutex::new(logger_levels)),
       parameter_events_writer: Arc::new(parameter_events_writer),
       parameters: Arc::new(Mutex::new(parameters)),
       parameter_validator,
       parameter_set_action,
-      use_sim_time: Arc::new(AtomicBool::new(false)),
-      sim_time: Arc::new(Mutex::new(ROSTime::ZERO)),
+      parameter_declarations: Arc::new(Mutex::new(ParameterDeclarations::default())),
+      parameter_overrides,
+      remap_rules,
+      time_source: TimeSource::new(),
+      type_descriptions: Arc::new(Mutex::new(BTreeMap::new())),
     };
 
     node.suppress_node_info_updates(true);
 
     node.rosout_writer = if enable_rosout {
-      Some(
+      Some(Arc::new(
         // topic already has QoS defined
         node.create_publisher(&rosout_topic, None)?,
-      )
+      ))
     } else {
       None
     };
@@ -742,7 +1532,29 @@ impl Node {
         node.execute_parameter_set_actions(name, value)?;
         Ok(())
       })
-      .map_err(NodeCreateError::BadParameter)?;
+      .map_err(Error::BadParameter)?;
+
+    // Announce the initial Parameters
+    let new_parameters = node
+      .parameters
+      .lock()
+      .unwrap()
+      .iter()
+      .map(|(name, value)| raw::Parameter {
+        name: name.clone(),
+        value: value.clone().into(),
+      })
+      .collect();
+    node
+      .parameter_events_writer
+      .publish(raw::ParameterEvent {
+        timestamp: node.time_now().into(),
+        node: node.fully_qualified_name(),
+        new_parameters,
+        changed_parameters: vec![],
+        deleted_parameters: vec![],
+      })
+      .unwrap_or_else(|e| warn!("Cannot publish initial Parameters: {e:?}"));
 
     node.suppress_node_info_updates(false);
 
@@ -751,13 +1563,16 @@ impl Node {
 
   /// Return the ROSTime
   ///
-  /// It is either the system clock time
+  /// It is either the system clock time, or simulated time if the Parameter
+  /// `use_sim_time` is set. See [`Self::get_clock`].
   pub fn time_now(&self) -> ROSTime {
-    if self.use_sim_time.load(Ordering::SeqCst) {
-      *self.sim_time.lock().unwrap()
-    } else {
-      ROSTime::now()
-    }
+    self.time_source.now()
+  }
+
+  /// The ROS time Clock of this Node. It follows the `/clock` Topic when the
+  /// Parameter `use_sim_time` is set, provided that the Spinner is running.
+  pub fn get_clock(&self) -> Clock {
+    Clock::ros_time(self.time_source.clone())
   }
 
   pub fn time_now_not_simulated(&self) -> ROSTime {
@@ -772,20 +1587,16 @@ impl Node {
   /// E.g. `executor.spawn(node.spinner().spin())`
   ///
   /// The `.spin()` task runs until `Node` is dropped.
-  pub fn spinner(&mut self) -> CreateResult<Spinner> {
+  pub fn spinner(&mut self) -> Result<Spinner, Error> {
     if self.stop_spin_sender.is_some() {
       panic!("Attempted to crate a second spinner.");
     }
     let (stop_spin_sender, stop_spin_receiver) = async_channel::bounded(1);
     self.stop_spin_sender = Some(stop_spin_sender);
+    let (spin_stopped_sender, spin_stopped_receiver) = async_channel::bounded(1);
+    self.spin_stopped_receiver = Some(spin_stopped_receiver);
 
-    //TODO: Check QoS policies against ROS 2 specs or some refernce.
-    let service_qos = QosPolicyBuilder::new()
-      .reliability(policy::Reliability::Reliable {
-        max_blocking_time: Duration::from_millis(100),
-      })
-      .history(policy::History::KeepLast { depth: 1 })
-      .build();
+    let service_qos = parameter_service_qos();
 
     let node_name = self.node_name.fully_qualified_name();
 
@@ -848,31 +1659,55 @@ impl Node {
       None // No parameter services
     };
 
+    if self.options.start_logger_services {
+      let logger_services_task = self.logger_services_task()?;
+      // The Spinner below receives this.
+      let _ = self.callback_sender.try_send(logger_services_task);
+    }
+
+    if self.options.start_type_description_service {
+      let type_description_task = self.type_description_task()?;
+      let _ = self.callback_sender.try_send(type_description_task);
+    }
+
     let clock_topic = self.create_topic(
       &Name::new("/", "clock").unwrap(),
       MessageTypeName::new("builtin_interfaces", "Time"),
       &DEFAULT_SUBSCRIPTION_QOS,
     )?;
+    let clock_task = self
+      .time_source
+      .clock_task(self.ros_context.clone(), clock_topic);
+    // The Spinner below receives this.
+    let _ = self.callback_sender.try_send(clock_task);
 
     self.suppress_node_info_updates(false);
 
     Ok(Spinner {
       ros_context: self.ros_context.clone(),
       stop_spin_receiver,
+      spinner_running: Arc::clone(&self.spinner_running),
+      spin_stopped_sender,
       readers_to_remote_writers: Arc::clone(&self.readers_to_remote_writers),
       writers_to_remote_readers: Arc::clone(&self.writers_to_remote_readers),
       external_nodes: Arc::clone(&self.external_nodes),
+      discovered_readers: Arc::clone(&self.discovered_readers),
+      discovered_writers: Arc::clone(&self.discovered_writers),
+      provisional_graph: Arc::clone(&self.provisional_graph),
       status_event_senders: Arc::clone(&self.status_event_senders),
-      use_sim_time: Arc::clone(&self.use_sim_time),
-      sim_time: Arc::clone(&self.sim_time),
-      clock_topic,
+      time_source: self.time_source.clone(),
       parameter_servers,
       parameter_events_writer: Arc::clone(&self.parameter_events_writer),
       parameters: Arc::clone(&self.parameters),
       allow_undeclared_parameters: self.options.allow_undeclared_parameters,
+      forward_rosout_to_log: self.options.forward_rosout_to_log,
+      rosout_writer_guid: self.rosout_writer.as_ref().map(|w| w.guid()),
       parameter_validator: self.parameter_validator.as_ref().map(Arc::clone),
       parameter_set_action: self.parameter_set_action.as_ref().map(Arc::clone),
+      parameter_declarations: Arc::clone(&self.parameter_declarations),
       fully_qualified_node_name: self.fully_qualified_name(),
+      callback_receiver: self.callback_receiver.clone(),
+      callbacks: Mutex::new(FuturesUnordered::new()),
     })
   }
 
@@ -893,11 +1728,12 @@ impl Node {
       node_info.add_writer(Gid::from(row.guid()));
     }
 
-    for reader in &self.readers {
+    let endpoints = self.endpoints.lock().unwrap();
+    for reader in &endpoints.readers {
       node_info.add_reader(*reader);
     }
 
-    for writer in &self.writers {
+    for writer in &endpoints.writers {
       node_info.add_writer(*writer);
     }
 
@@ -911,21 +1747,57 @@ impl Node {
 
     // Send updates when suppression ends
     if !suppress {
-      self.ros_context.update_node(self.generate_node_info());
+      self
+        .ros_context
+        .update_node(self.node_key, self.generate_node_info());
     }
   }
 
   fn add_reader(&mut self, reader: Gid) {
-    self.readers.insert(reader);
+    self.endpoints.lock().unwrap().readers.insert(reader);
     if !self.suppress_node_info_updates.load(Ordering::SeqCst) {
-      self.ros_context.update_node(self.generate_node_info());
+      self
+        .ros_context
+        .update_node(self.node_key, self.generate_node_info());
     }
   }
 
   fn add_writer(&mut self, writer: Gid) {
-    self.writers.insert(writer);
+    self.endpoints.lock().unwrap().writers.insert(writer);
     if !self.suppress_node_info_updates.load(Ordering::SeqCst) {
-      self.ros_context.update_node(self.generate_node_info());
+      self
+        .ros_context
+        .update_node(self.node_key, self.generate_node_info());
+    }
+  }
+
+  pub(crate) fn remove_reader(&mut self, reader: Gid) {
+    self.endpoints.lock().unwrap().readers.remove(&reader);
+    if !self.suppress_node_info_updates.load(Ordering::SeqCst) {
+      self
+        .ros_context
+        .update_node(self.node_key, self.generate_node_info());
+    }
+  }
+
+  pub(crate) fn remove_writer(&mut self, writer: Gid) {
+    self.endpoints.lock().unwrap().writers.remove(&writer);
+    if !self.suppress_node_info_updates.load(Ordering::SeqCst) {
+      self
+        .ros_context
+        .update_node(self.node_key, self.generate_node_info());
+    }
+  }
+
+  // For an entity to hold, so that dropping it removes Reader or Writer `gid`
+  // from this Node.
+  pub(crate) fn endpoint_registration(&self, gid: Gid) -> EndpointRegistration {
+    EndpointRegistration {
+      gid,
+      node_key: self.node_key,
+      endpoints: Arc::downgrade(&self.endpoints),
+      readers_to_remote_writers: Arc::downgrade(&self.readers_to_remote_writers),
+      ros_context: self.ros_context.clone(),
     }
   }
 
@@ -941,6 +1813,34 @@ impl Node {
     self.node_name.fully_qualified_name()
   }
 
+  /// Resolves a Topic, Service or Action name as used by this Node.
+  ///
+  /// The name is made absolute, with `~` and substitutions expanded (see
+  /// [`Name::resolve`]), and then the first matching remapping rule is
+  /// applied. Entity creation does this to all names.
+  ///
+  /// Rules from command line arguments (see [`NodeOptions::cli_args`]) are
+  /// tried first, then those from [`Self::add_remap`], and last those from
+  /// [`Context::add_remap`].
+  pub fn resolve_name(&self, name: &Name) -> Result<Name, Error> {
+    let context_rules = self.ros_context.remap_rules();
+    ros_args::resolve_name(
+      self.remap_rules.iter().chain(&context_rules),
+      name,
+      &self.node_name,
+    )
+  }
+
+  /// Remap Topic, Service or Action name `from` to `to` in this Node, e.g.
+  /// `add_remap("~/image", "/camera/image_raw")`.
+  ///
+  /// The rule applies to entities created after this. See
+  /// [`Self::resolve_name`].
+  pub fn add_remap(&mut self, from: &str, to: &str) -> Result<(), Error> {
+    self.remap_rules.push(RemapRule::new(from, to)?);
+    Ok(())
+  }
+
   pub fn options(&self) -> &NodeOptions {
     &self.options
   }
@@ -952,7 +1852,106 @@ impl Node {
   // ///////////////////////////////////////////////
   // Parameters
 
+  /// Declares a Parameter with a default value and a descriptor.
+  ///
+  /// Unless `descriptor.dynamic_typing` is set, the type of `default` becomes
+  /// the type of the Parameter. Later changes must have that type, be within
+  /// the range of the descriptor, and pass the callbacks added with
+  /// [`Self::add_on_set_parameters_callback`]. If the descriptor is
+  /// `read_only`, there can be no changes.
+  ///
+  /// If there is an override for the Parameter, from
+  /// [`NodeOptions::parameter_override`] or a parameter file, that is used
+  /// instead of `default`.
+  ///
+  /// Returns the value of the Parameter, or `Err` if it is already declared
+  /// or the value does not satisfy the descriptor.
+  pub fn declare_parameter(
+    &self,
+    name: &str,
+    default: ParameterValue,
+    descriptor: ParameterDescriptor,
+  ) -> Result<ParameterValue, String> {
+    if self.has_parameter(name) {
+      return Err("Parameter '".to_owned() + name + "' is already declared.");
+    }
+    let default = self.parameter_overrides.get(name).cloned().unwrap_or(default);
+    self.validate_parameter_on_set(name, &default)?;
+    self
+      .parameter_declarations
+      .lock()
+      .unwrap()
+      .declare(name, &default, descriptor)?;
+    if let Err(e) = self.execute_parameter_set_actions(name, &default) {
+      self.parameter_declarations.lock().unwrap().undeclare(name);
+      return Err(e);
+    }
+
+    self
+      .parameters
+      .lock()
+      .unwrap()
+      .insert(name.to_owned(), default.clone());
+    self
+      .parameter_events_writer
+      .publish(raw::ParameterEvent {
+        timestamp: self.time_now().into(),
+        node: self.fully_qualified_name(),
+        new_parameters: vec![raw::Parameter {
+          name: name.to_owned(),
+          value: default.clone().into(),
+        }],
+        changed_parameters: vec![],
+        deleted_parameters: vec![],
+      })
+      .unwrap_or_else(|e| warn!("declare_parameter: {e:?}"));
+    Ok(default)
+  }
+
+  /// Descriptor of the Parameter, or None if there is no such Parameter.
+  ///
+  /// Parameters declared without a descriptor get one made up from their
+  /// value.
+  pub fn describe_parameter(&self, name: &str) -> Option<ParameterDescriptor> {
+    let value = self.get_parameter(name)?;
+    Some(
+      self
+        .parameter_declarations
+        .lock()
+        .unwrap()
+        .descriptor(name, Some(&value)),
+    )
+  }
+
+  /// Adds a callback to validate Parameter changes, whether made locally or
+  /// through the Parameter Services.
+  ///
+  /// All callbacks must accept a change, otherwise it is rejected. The
+  /// callbacks run after the descriptor checks, but before the
+  /// [`NodeOptions::parameter_validator`].
+  pub fn add_on_set_parameters_callback(
+    &self,
+    callback: Box<OnSetParametersCallback>,
+  ) -> OnSetParametersCallbackHandle {
+    self
+      .parameter_declarations
+      .lock()
+      .unwrap()
+      .add_on_set_callback(callback)
+  }
+
+  /// Removes a callback added with [`Self::add_on_set_parameters_callback`].
+  /// Returns false if it was already removed.
+  pub fn remove_on_set_parameters_callback(&self, handle: OnSetParametersCallbackHandle) -> bool {
+    self
+      .parameter_declarations
+      .lock()
+      .unwrap()
+      .remove_on_set_callback(handle)
+  }
+
   pub fn undeclare_parameter(&self, name: &str) {
+    self.parameter_declarations.lock().unwrap().undeclare(name);
     let prev_value = self.parameters.lock().unwrap().remove(name);
 
     if let Some(deleted_param) = prev_value {
@@ -990,6 +1989,10 @@ impl Node {
   pub fn set_parameter(&self, name: &str, value: ParameterValue) -> Result<(), String> {
     let already_set = self.parameters.lock().unwrap().contains_key(name);
     if self.options.allow_undeclared_parameters || already_set {
+      self.parameter_declarations.lock().unwrap().check_set(&[Parameter {
+        name: name.to_owned(),
+        value: value.clone(),
+      }])?;
       self.validate_parameter_on_set(name, &value)?;
       self.execute_parameter_set_actions(name, &value)?;
 
@@ -1020,7 +2023,7 @@ impl Node {
           changed_parameters,
           deleted_parameters: vec![],
         })
-        .unwrap_or_else(|e| warn!("undeclare_parameter: {e:?}"));
+        .unwrap_or_else(|e| warn!("set_parameter: {e:?}"));
       Ok(())
     } else {
       Err("Setting undeclared parameter '".to_owned() + name + "' is not allowed.")
@@ -1041,6 +2044,18 @@ impl Node {
       .map(|p| p.to_owned())
   }
 
+  /// Gets the value of a parameter as type `T`, e.g. `i64` or `Vec<String>`.
+  ///
+  /// Returns None if there is no such Parameter, or it is of some other type.
+  pub fn get_parameter_as<T>(&self, name: &str) -> Option<T>
+  where
+    T: TryFrom<ParameterValue>,
+  {
+    self
+      .get_parameter(name)
+      .and_then(|value| T::try_from(value).ok())
+  }
+
   pub fn list_parameters(&self) -> Vec<String> {
     self
       .parameters
@@ -1083,7 +2098,7 @@ impl Node {
     match name {
       "use_sim_time" => match value {
         ParameterValue::Boolean(s) => {
-          self.use_sim_time.store(*s, Ordering::SeqCst);
+          self.time_source.set_ros_time_active(*s);
           Ok(())
         }
         _ => Err("Parameter 'use_sim_time' must be Boolean.".to_owned()),
@@ -1117,6 +2132,520 @@ impl Node {
     }
   }
 
+  /// QoS profile `name` from [`NodeOptions::qos_profiles`], for creating
+  /// Publishers, Subscriptions etc.
+  ///
+  /// If there is no such profile, this logs a warning and returns `None`, so
+  /// that the defaults are used.
+  pub fn qos_profile(&self, name: &str) -> Option<QosPolicies> {
+    let qos = self.options.qos_profiles.get(name).cloned();
+    if qos.is_none() {
+      warn!("QoS profile {name:?} not found. Using defaults.");
+    }
+    qos
+  }
+
+  /// Deliver an application event to all [`Self::status_receiver`]s, as
+  /// [`NodeEvent::User`].
+  ///
+  /// This way application state changes can be handled in the same loop as
+  /// discovery events. Like other events, this is dropped for receivers
+  /// whose queue is full.
+  pub fn post_event(&self, event: UserEvent) {
+    send_status_event(&self.status_event_senders, &NodeEvent::User(event));
+  }
+
+  /// Wait until discovery has converged.
+  ///
+  /// The returned future resolves to `true` once no discovery events (DDS or
+  /// ROS 2 Graph) have arrived for `quiet_period`, or to `false` if that does
+  /// not happen before `timeout`. This is useful e.g. in tests and in startup
+  /// sequences that should run only after the other Nodes have been seen.
+  ///
+  /// Only participants, topics, endpoints and Nodes appearing or leaving
+  /// count as discovery. Other events, such as QoS incompatibilities,
+  /// endpoint matches and [`NodeEvent::User`], do not restart `quiet_period`.
+  ///
+  /// There must be a Spinner running, see [`Self::status_receiver`].
+  pub fn wait_for_graph_settle(
+    &self,
+    quiet_period: std::time::Duration,
+    timeout: std::time::Duration,
+  ) -> impl Future<Output = bool> {
+    let status_receiver = self.status_receiver();
+    async move {
+      let mut deadline = FutureExt::fuse(Timer::after(timeout));
+      let mut quiet = FutureExt::fuse(Timer::after(quiet_period));
+      loop {
+        futures::select! {
+          _ = quiet => return true,
+          _ = deadline => return false,
+          event = status_receiver.recv().fuse() => match event {
+            // Spinner has stopped, so there will be no more events.
+            Err(_) => return true,
+            Ok(event) if event.is_graph_change() => {
+              trace!("wait_for_graph_settle: discovery event, restart quiet period");
+              quiet = FutureExt::fuse(Timer::after(quiet_period));
+            }
+            // e.g. QoS incompatibility or a User event
+            Ok(_) => {}
+          }
+        }
+      }
+    }
+  }
+
+  /// Save the discovered ROS 2 Graph to a file, e.g. before exiting.
+  ///
+  /// This saves the ROS 2 Nodes from other DomainParticipants and all the
+  /// discovered Readers and Writers. See [`Self::load_graph_cache`].
+  pub fn save_graph_cache(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+    graph_cache::save(path.as_ref(), &self.graph_maps())
+  }
+
+  /// Load a ROS 2 Graph saved by [`Self::save_graph_cache`], e.g. right after
+  /// creating the Node.
+  ///
+  /// This avoids waiting for Discovery in short-lived programs. The loaded
+  /// entries are provisional: those that Discovery has not confirmed within
+  /// `validation_period` are removed. Entries already discovered are kept as
+  /// they are.
+  ///
+  /// Removing unconfirmed entries requires a Spinner running.
+  pub fn load_graph_cache(
+    &self,
+    path: impl AsRef<std::path::Path>,
+    validation_period: std::time::Duration,
+  ) -> std::io::Result<()> {
+    graph_cache::load(
+      path.as_ref(),
+      self.ros_context.domain_participant().guid(),
+      validation_period,
+      &self.graph_maps(),
+      &mut self.provisional_graph.lock().unwrap(),
+    )
+  }
+
+  fn graph_maps(&self) -> GraphMaps<'_> {
+    GraphMaps {
+      external_nodes: &self.external_nodes,
+      discovered_readers: &self.discovered_readers,
+      discovered_writers: &self.discovered_writers,
+    }
+  }
+
+  /// Is Node `fully_qualified_name`, e.g. `/ns/talker`, present in the ROS 2
+  /// Graph?
+  ///
+  /// A Node is present from when its DomainParticipant announces it until it
+  /// is removed, or the DomainParticipant is lost. See
+  /// [`NodeEvent::NodeDeparted`].
+  pub fn is_node_alive(&self, fully_qualified_name: &str) -> bool {
+    self.fully_qualified_name() == fully_qualified_name
+      || self
+        .external_nodes
+        .lock()
+        .unwrap()
+        .values()
+        .flatten()
+        .any(|n| n.fully_qualified_name() == fully_qualified_name)
+  }
+
+  /// Fully qualified names of all Nodes in the ROS 2 Graph, including this
+  /// one, e.g. `/ns/talker`.
+  ///
+  /// Nodes are known from ROS Discovery, so there must be a Spinner running
+  /// to see Nodes in other DomainParticipants.
+  pub fn get_node_names(&self) -> Vec<String> {
+    let local = self.ros_context.participant_entities_info();
+    let external = self.external_nodes.lock().unwrap();
+    let names: BTreeSet<String> = local
+      .nodes()
+      .iter()
+      .chain(external.values().flatten())
+      .map(NodeEntitiesInfo::fully_qualified_name)
+      .chain(std::iter::once(self.fully_qualified_name()))
+      .collect();
+    names.into_iter().collect()
+  }
+
+  /// Names and types of all Topics in the ROS 2 Graph, i.e. that have a
+  /// Publisher or Subscription.
+  ///
+  /// Map keys are Topic names, e.g. `/chatter`, and values are type names,
+  /// e.g. `std_msgs/msg/String`. Usually there is just one type.
+  pub fn get_topic_names_and_types(&self) -> BTreeMap<String, Vec<String>> {
+    let readers = self.discovered_readers.lock().unwrap();
+    let writers = self.discovered_writers.lock().unwrap();
+    names_and_types(readers.values().chain(writers.values()), demangle_topic)
+  }
+
+  /// Names and types of all Services in the ROS 2 Graph, i.e. that have a
+  /// Server or Client.
+  ///
+  /// Map keys are Service names, e.g. `/add_two_ints`, and values are type
+  /// names, e.g. `example_interfaces/srv/AddTwoInts`. The Services of Actions
+  /// are included, e.g. `/fibonacci/_action/send_goal`.
+  pub fn get_service_names_and_types(&self) -> BTreeMap<String, Vec<String>> {
+    let readers = self.discovered_readers.lock().unwrap();
+    let writers = self.discovered_writers.lock().unwrap();
+    names_and_types(readers.values().chain(writers.values()), demangle_service)
+  }
+
+  /// Like [`Self::get_service_names_and_types`], but only Services that have a
+  /// Server in Node `node_name`.
+  pub fn get_service_names_and_types_by_node(
+    &self,
+    node_name: &NodeName,
+  ) -> BTreeMap<String, Vec<String>> {
+    // Servers read requests.
+    let gids = self.node_endpoint_gids(node_name, NodeEntitiesInfo::readers);
+    let readers = self.discovered_readers.lock().unwrap();
+    names_and_types(
+      readers
+        .values()
+        .filter(|e| gids.contains(&Gid::from(e.guid))),
+      demangle_service_request,
+    )
+  }
+
+  /// Like [`Self::get_service_names_and_types`], but only Services that have a
+  /// Client in Node `node_name`.
+  pub fn get_client_names_and_types_by_node(
+    &self,
+    node_name: &NodeName,
+  ) -> BTreeMap<String, Vec<String>> {
+    // Clients write requests.
+    let gids = self.node_endpoint_gids(node_name, NodeEntitiesInfo::writers);
+    let writers = self.discovered_writers.lock().unwrap();
+    names_and_types(
+      writers
+        .values()
+        .filter(|e| gids.contains(&Gid::from(e.guid))),
+      demangle_service_request,
+    )
+  }
+
+  /// Names and types of all Actions in the ROS 2 Graph, i.e. that have an
+  /// Action Server or Client.
+  ///
+  /// Actions are recognized from their feedback Topics. Map keys are Action
+  /// names, e.g. `/fibonacci`, and values are type names, e.g.
+  /// `example_interfaces/action/Fibonacci`. Usually there is just one type.
+  pub fn get_action_names_and_types(&self) -> BTreeMap<String, Vec<String>> {
+    let readers = self.discovered_readers.lock().unwrap();
+    let writers = self.discovered_writers.lock().unwrap();
+    names_and_types(readers.values().chain(writers.values()), demangle_action)
+  }
+
+  /// Like [`Self::get_action_names_and_types`], but only Actions that have an
+  /// Action Server, in any Node.
+  pub fn get_action_servers(&self) -> BTreeMap<String, Vec<String>> {
+    // Action Servers write feedback.
+    let writers = self.discovered_writers.lock().unwrap();
+    names_and_types(writers.values(), demangle_action)
+  }
+
+  /// Like [`Self::get_action_names_and_types`], but only Actions that have an
+  /// Action Server in Node `node_name`.
+  pub fn get_action_server_names_and_types_by_node(
+    &self,
+    node_name: &NodeName,
+  ) -> BTreeMap<String, Vec<String>> {
+    // Action Servers write feedback.
+    let gids = self.node_endpoint_gids(node_name, NodeEntitiesInfo::writers);
+    let writers = self.discovered_writers.lock().unwrap();
+    names_and_types(
+      writers
+        .values()
+        .filter(|e| gids.contains(&Gid::from(e.guid))),
+      demangle_action,
+    )
+  }
+
+  /// Like [`Self::get_action_names_and_types`], but only Actions that have an
+  /// Action Client in Node `node_name`.
+  pub fn get_action_client_names_and_types_by_node(
+    &self,
+    node_name: &NodeName,
+  ) -> BTreeMap<String, Vec<String>> {
+    // Action Clients read feedback.
+    let gids = self.node_endpoint_gids(node_name, NodeEntitiesInfo::readers);
+    let readers = self.discovered_readers.lock().unwrap();
+    names_and_types(
+      readers
+        .values()
+        .filter(|e| gids.contains(&Gid::from(e.guid))),
+      demangle_action,
+    )
+  }
+
+  // Readers or Writers of a Node, as announced in ROS Discovery. Nodes of this
+  // Context are looked up locally.
+  fn node_endpoint_gids(
+    &self,
+    node_name: &NodeName,
+    endpoints: fn(&NodeEntitiesInfo) -> &[Gid],
+  ) -> BTreeSet<Gid> {
+    let local = self.ros_context.participant_entities_info();
+    let mut gids: BTreeSet<Gid> = self
+      .external_nodes
+      .lock()
+      .unwrap()
+      .values()
+      .flatten()
+      .chain(local.nodes())
+      .filter(|n| n.fully_qualified_name() == node_name.fully_qualified_name())
+      .flat_map(|n| endpoints(n).iter().copied())
+      .collect();
+    if *node_name == self.node_name {
+      gids.extend(endpoints(&self.generate_node_info()).iter().copied());
+    }
+    gids
+  }
+
+  /// Watch availability of a Service in the ROS 2 Graph.
+  ///
+  /// The stream yields [`ServiceEvent::Available`] when some Server for
+  /// `service_name` appears, and [`ServiceEvent::Unavailable`] when the last
+  /// one disappears. The first item describes the current state.
+  ///
+  /// A Server is considered present when both its request Reader and response
+  /// Writer have been discovered. Servers in this Node are also counted.
+  ///
+  /// There must be a Spinner running, see [`Self::status_receiver`].
+  pub fn watch_service(&self, service_name: &Name) -> impl Stream<Item = ServiceEvent> {
+    // A name that does not resolve cannot have been used to create a Server.
+    let service_name = &self
+      .resolve_name(service_name)
+      .unwrap_or_else(|_| service_name.clone());
+    let request_topic = service_name.to_dds_name("rq", &self.node_name, "Request");
+    let response_topic = service_name.to_dds_name("rr", &self.node_name, "Reply");
+    let discovered_readers = Arc::clone(&self.discovered_readers);
+    let discovered_writers = Arc::clone(&self.discovered_writers);
+
+    // Events are used only as a wakeup signal. The actual state is read from
+    // the discovery caches, so that we do not go wrong even if the status
+    // channel overflows and drops some events.
+    let is_available = move || {
+      !endpoints_on_topic(&discovered_readers, &request_topic).is_empty()
+        && !endpoints_on_topic(&discovered_writers, &response_topic).is_empty()
+    };
+
+    let status_receiver = self.status_receiver();
+    stream::unfold(
+      (status_receiver, None),
+      move |(status_receiver, previous)| {
+        let is_available = is_available.clone();
+        async move {
+          let mut previous: Option<bool> = previous;
+          loop {
+            let available = is_available();
+            if previous != Some(available) {
+              let event = if available {
+                ServiceEvent::Available
+              } else {
+                ServiceEvent::Unavailable
+              };
+              return Some((event, (status_receiver, Some(available))));
+            }
+            previous = Some(available);
+            // Wait for something to happen in discovery.
+            status_receiver.recv().await.ok()?;
+          }
+        }
+      },
+    )
+  }
+
+  /// Watch Publishers and Subscriptions of a Topic in the ROS 2 Graph.
+  ///
+  /// The stream yields a [`TopicEvent`] each time a Publisher or Subscription
+  /// on `topic_name` appears or disappears. The events carry the updated
+  /// counts. The stream starts with "appeared" events for the
+  /// currently known ones. Publishers and Subscriptions in this Node are also
+  /// reported.
+  ///
+  /// There must be a Spinner running, see [`Self::status_receiver`].
+  pub fn watch_topic(&self, topic_name: &Name) -> impl Stream<Item = TopicEvent> {
+    let topic_name = &self
+      .resolve_name(topic_name)
+      .unwrap_or_else(|_| topic_name.clone());
+    let dds_topic = topic_name.to_dds_name("rt", &self.node_name, "");
+    let discovered_readers = Arc::clone(&self.discovered_readers);
+    let discovered_writers = Arc::clone(&self.discovered_writers);
+    let status_receiver = self.status_receiver();
+
+    struct WatchState {
+      status_receiver: Receiver<NodeEvent>,
+      publishers: BTreeSet<GUID>,
+      subscriptions: BTreeSet<GUID>,
+      pending: VecDeque<TopicEvent>,
+    }
+    let state = WatchState {
+      status_receiver,
+      publishers: BTreeSet::new(),
+      subscriptions: BTreeSet::new(),
+      pending: VecDeque::new(),
+    };
+
+    // As in watch_service, events only tell us to look at the discovery caches
+    // again.
+    stream::unfold(state, move |mut state| {
+      let dds_topic = dds_topic.clone();
+      let discovered_readers = Arc::clone(&discovered_readers);
+      let discovered_writers = Arc::clone(&discovered_writers);
+      async move {
+        loop {
+          if let Some(event) = state.pending.pop_front() {
+            return Some((event, state));
+          }
+
+          let publishers = endpoints_on_topic(&discovered_writers, &dds_topic);
+          let subscriptions = endpoints_on_topic(&discovered_readers, &dds_topic);
+
+          let mut publisher_count = state.publishers.len();
+          for guid in state.publishers.difference(&publishers) {
+            publisher_count -= 1;
+            state.pending.push_back(TopicEvent::PublisherLost {
+              guid: *guid,
+              publisher_count,
+            });
+          }
+          for guid in publishers.difference(&state.publishers) {
+            publisher_count += 1;
+            state.pending.push_back(TopicEvent::PublisherAppeared {
+              guid: *guid,
+              publisher_count,
+            });
+          }
+
+          let mut subscription_count = state.subscriptions.len();
+          for guid in state.subscriptions.difference(&subscriptions) {
+            subscription_count -= 1;
+            state.pending.push_back(TopicEvent::SubscriptionLost {
+              guid: *guid,
+              subscription_count,
+            });
+          }
+          for guid in subscriptions.difference(&state.subscriptions) {
+            subscription_count += 1;
+            state.pending.push_back(TopicEvent::SubscriptionAppeared {
+              guid: *guid,
+              subscription_count,
+            });
+          }
+
+          state.publishers = publishers;
+          state.subscriptions = subscriptions;
+
+          if state.pending.is_empty() {
+            // Wait for something to happen in discovery.
+            state.status_receiver.recv().await.ok()?;
+          }
+        }
+      }
+    })
+  }
+
+  /// Watch changes in the whole ROS 2 Graph.
+  ///
+  /// The stream yields a [`GraphEvent`] each time a Node appears or
+  /// disappears, or a Publisher, Subscription, Service Server or Client is
+  /// added or removed. It starts with "appeared" and "added" events for the
+  /// currently known ones. Entities in this Context are also reported.
+  ///
+  /// The events are found by comparing successive states of ROS and DDS
+  /// Discovery, so changes that are quickly undone may be missed.
+  ///
+  /// There must be a Spinner running, see [`Self::status_receiver`].
+  pub fn graph_event_stream(&self) -> impl Stream<Item = GraphEvent> {
+    let external_nodes = Arc::clone(&self.external_nodes);
+    let discovered_readers = Arc::clone(&self.discovered_readers);
+    let discovered_writers = Arc::clone(&self.discovered_writers);
+    let ros_context = self.ros_context.clone();
+    let my_name = self.fully_qualified_name();
+    let status_receiver = self.status_receiver();
+
+    struct GraphState {
+      status_receiver: Receiver<NodeEvent>,
+      nodes: BTreeSet<String>,
+      endpoints: BTreeMap<GUID, GraphEndpoint>,
+      pending: VecDeque<GraphEvent>,
+    }
+    let state = GraphState {
+      status_receiver,
+      nodes: BTreeSet::new(),
+      endpoints: BTreeMap::new(),
+      pending: VecDeque::new(),
+    };
+
+    // As in watch_topic, events only tell us to look at the discovery caches
+    // again.
+    stream::unfold(state, move |mut state| {
+      let external_nodes = Arc::clone(&external_nodes);
+      let discovered_readers = Arc::clone(&discovered_readers);
+      let discovered_writers = Arc::clone(&discovered_writers);
+      let ros_context = ros_context.clone();
+      let my_name = my_name.clone();
+      async move {
+        loop {
+          if let Some(event) = state.pending.pop_front() {
+            return Some((event, state));
+          }
+
+          let local = ros_context.participant_entities_info();
+          let nodes: BTreeSet<String> = external_nodes
+            .lock()
+            .unwrap()
+            .values()
+            .flatten()
+            .chain(local.nodes())
+            .map(NodeEntitiesInfo::fully_qualified_name)
+            .chain(std::iter::once(my_name.clone()))
+            .collect();
+          let mut endpoints = BTreeMap::new();
+          for (guid, e) in discovered_readers.lock().unwrap().iter() {
+            if let Some(endpoint) = GraphEndpoint::from_reader(e) {
+              endpoints.insert(*guid, endpoint);
+            }
+          }
+          for (guid, e) in discovered_writers.lock().unwrap().iter() {
+            if let Some(endpoint) = GraphEndpoint::from_writer(e) {
+              endpoints.insert(*guid, endpoint);
+            }
+          }
+
+          // Nodes appear before their endpoints, and disappear after them.
+          for name in nodes.difference(&state.nodes) {
+            state.pending.push_back(GraphEvent::NodeAppeared(name.clone()));
+          }
+          for (guid, endpoint) in &endpoints {
+            if state.endpoints.get(guid) != Some(endpoint) {
+              state.pending.push_back(endpoint.clone().event(*guid, true));
+            }
+          }
+          for (guid, endpoint) in &state.endpoints {
+            if !endpoints.contains_key(guid) {
+              state.pending.push_back(endpoint.clone().event(*guid, false));
+            }
+          }
+          for name in state.nodes.difference(&nodes) {
+            state.pending.push_back(GraphEvent::NodeDisappeared(name.clone()));
+          }
+
+          state.nodes = nodes;
+          state.endpoints = endpoints;
+
+          if state.pending.is_empty() {
+            // Wait for something to happen in discovery.
+            state.status_receiver.recv().await.ok()?;
+          }
+        }
+      }
+    })
+  }
+
   // reader waits for at least one writer to be present
   pub(crate) fn wait_for_writer(&self, reader: GUID) -> impl Future<Output = ()> {
     // TODO: This may contain some synchrnoization hazard
@@ -1166,6 +2695,60 @@ impl Node {
     }
   }
 
+  // Resolves when `writer` has matched at least `min_count` readers, to the
+  // number of matched readers.
+  pub(crate) fn wait_for_readers(
+    &self,
+    writer: GUID,
+    min_count: usize,
+    timeout: std::time::Duration,
+  ) -> impl Future<Output = Result<usize, WaitTimeout>> {
+    self.wait_for_matches(&self.writers_to_remote_readers, writer, min_count, timeout)
+  }
+
+  // Resolves when `reader` has matched at least `min_count` writers, to the
+  // number of matched writers.
+  pub(crate) fn wait_for_writers(
+    &self,
+    reader: GUID,
+    min_count: usize,
+    timeout: std::time::Duration,
+  ) -> impl Future<Output = Result<usize, WaitTimeout>> {
+    self.wait_for_matches(&self.readers_to_remote_writers, reader, min_count, timeout)
+  }
+
+  fn wait_for_matches(
+    &self,
+    matches: &Arc<Mutex<BTreeMap<GUID, BTreeSet<GUID>>>>,
+    local: GUID,
+    min_count: usize,
+    timeout: std::time::Duration,
+  ) -> impl Future<Output = Result<usize, WaitTimeout>> {
+    // Subscribe to events before checking the count, so that no match is
+    // missed in between.
+    let status_receiver = self.status_receiver();
+    let matches = Arc::clone(matches);
+    async move {
+      let matched_count = || matches.lock().unwrap().get(&local).map_or(0, BTreeSet::len);
+      let mut deadline = FutureExt::fuse(Timer::after(timeout));
+      loop {
+        let count = matched_count();
+        if count >= min_count {
+          return Ok(count);
+        }
+        futures::select! {
+          _ = deadline => return Err(WaitTimeout),
+          event = status_receiver.recv().fuse() => {
+            if event.is_err() {
+              // Spinner has stopped, so the count will not change.
+              return Err(WaitTimeout);
+            }
+          }
+        }
+      }
+    }
+  }
+
   pub(crate) fn get_publisher_count(&self, subscription_guid: GUID) -> usize {
     self
       .readers_to_remote_writers
@@ -1179,6 +2762,76 @@ impl Node {
       })
   }
 
+  // Clients of a Server, identified by their request Writer GUIDs.
+  //
+  // A Client is counted only if it has both a request Writer matched to
+  // `request_reader` and a response Reader matched to `response_writer`. These
+  // are paired by Participant, because the GUIDs of a Client's Writer and
+  // Reader are otherwise unrelated.
+  pub(crate) fn get_server_clients(
+    &self,
+    request_reader: GUID,
+    response_writer: GUID,
+  ) -> BTreeSet<GUID> {
+    paired_clients(
+      &self.readers_to_remote_writers,
+      &self.writers_to_remote_readers,
+      request_reader,
+      response_writer,
+    )
+  }
+
+  pub(crate) fn server_client_events(
+    &self,
+    request_reader: GUID,
+    response_writer: GUID,
+  ) -> impl Stream<Item = ClientEvent> {
+    let readers_to_remote_writers = Arc::clone(&self.readers_to_remote_writers);
+    let writers_to_remote_readers = Arc::clone(&self.writers_to_remote_readers);
+    let status_receiver = self.status_receiver();
+
+    // Start from empty set, so that current Clients are reported first.
+    let state = (status_receiver, BTreeSet::new(), VecDeque::new());
+
+    stream::unfold(state, move |(status_receiver, mut known, mut pending)| {
+      let readers_to_remote_writers = Arc::clone(&readers_to_remote_writers);
+      let writers_to_remote_readers = Arc::clone(&writers_to_remote_readers);
+      async move {
+        loop {
+          if let Some(event) = pending.pop_front() {
+            return Some((event, (status_receiver, known, pending)));
+          }
+          let current = paired_clients(
+            &readers_to_remote_writers,
+            &writers_to_remote_readers,
+            request_reader,
+            response_writer,
+          );
+          let mut client_count = known.len();
+          for guid in known.difference(&current) {
+            client_count -= 1;
+            pending.push_back(ClientEvent::Disconnected {
+              guid: *guid,
+              client_count,
+            });
+          }
+          for guid in current.difference(&known) {
+            client_count += 1;
+            pending.push_back(ClientEvent::Connected {
+              guid: *guid,
+              client_count,
+            });
+          }
+          known = current;
+
+          if pending.is_empty() {
+            status_receiver.recv().await.ok()?;
+          }
+        }
+      }
+    })
+  }
+
   pub(crate) fn get_subscription_count(&self, publisher_guid: GUID) -> usize {
     self
       .writers_to_remote_readers
@@ -1192,11 +2845,227 @@ impl Node {
       })
   }
 
-  /// Borrow the Subscription to our ROSOut Reader.
+  // Like get_subscription_count(), but only reliable Subscriptions. Those with
+  // unknown QoS are counted in.
+  pub(crate) fn get_reliable_subscription_count(&self, publisher_guid: GUID) -> usize {
+    let discovered_readers = self.discovered_readers.lock().unwrap();
+    self
+      .writers_to_remote_readers
+      .lock()
+      .unwrap()
+      .get(&publisher_guid)
+      .map_or(0, |readers| {
+        readers
+          .iter()
+          .filter(|r| discovered_readers.get(r).is_none_or(|d| d.qos.is_reliable()))
+          .count()
+      })
+  }
+
+  /// Borrow the Subscription to our ROSOut Reader.
+  ///
+  /// Availability depends on Node configuration.
+  pub fn rosout_subscription(&self) -> Option<&Subscription<Log>> {
+    self.rosout_reader.as_ref()
+  }
+
+  /// Stream of rosout messages from all Nodes, for monitoring tools.
+  ///
+  /// Only messages at `min_level` or above are passed. With `name_filter`,
+  /// only messages of that logger and its descendants are passed, e.g.
+  /// `"ns.talker"` also passes `"ns.talker.child"`.
+  ///
+  /// The stream has its own Subscription, so this does not need
+  /// [`NodeOptions::read_rosout`], and there can be several of them.
+  pub fn rosout_stream(
+    &mut self,
+    min_level: crate::ros2::LogLevel,
+    name_filter: Option<&str>,
+  ) -> Result<impl FusedStream<Item = dds::ReadResult<Log>>, Error> {
+    let rosout_topic = self.ros_context.get_rosout_topic();
+    let subscription = self.create_subscription::<Log>(&rosout_topic, None)?;
+    let name_filter = name_filter.map(str::to_owned);
+    let passes = move |log: &Log| {
+      log.level >= min_level as u8
+        && name_filter.as_ref().is_none_or(|filter| {
+          log
+            .name
+            .strip_prefix(filter.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+    };
+    Ok(
+      stream::unfold(subscription, |subscription| async move {
+        let received = subscription.async_take().await;
+        Some((received, subscription))
+      })
+      .filter_map(move |received| {
+        futures::future::ready(match received {
+          Ok((log, _msg_info)) if passes(&log) => Some(Ok(log)),
+          Ok(_) => None,
+          Err(e) => Some(Err(e)),
+        })
+      })
+      .fuse(),
+    )
+  }
+
+  /// A [`log`] crate backend that publishes to the rosout writer of this
+  /// Node, see [`RosoutLogger`].
+  ///
+  /// `None` if rosout is not enabled, see [`NodeOptions::enable_rosout`].
+  pub fn rosout_logger(&self) -> Option<RosoutLogger> {
+    self
+      .rosout_writer
+      .as_ref()
+      .map(|writer| RosoutLogger::new(Arc::clone(writer), self.base_name()))
+  }
+
+  // Serves ~/get_logger_levels and ~/set_logger_levels until receiving
+  // requests fails.
+  fn logger_services_task(&mut self) -> Result<BoxFuture<'static, ()>, Error> {
+    let node_name = self.fully_qualified_name();
+    let get_server: Server<rcl_interfaces::GetLoggerLevels> = self.create_typed_server(
+      ServiceMapping::Enhanced,
+      &Name::new(&node_name, "get_logger_levels")?,
+    )?;
+    let set_server: Server<rcl_interfaces::SetLoggerLevels> = self.create_typed_server(
+      ServiceMapping::Enhanced,
+      &Name::new(&node_name, "set_logger_levels")?,
+    )?;
+    let logger_levels = Arc::clone(&self.logger_levels);
+
+    Ok(Box::pin(async move {
+      loop {
+        futures::select! {
+          request = get_server.async_receive_request().fuse() => {
+            let (req_id, rcl_interfaces::GetLoggerLevelsRequest { names }) = match request {
+              Ok(r) => r,
+              Err(e) => return error!("Logger level Server stops: {e:?}"),
+            };
+            let levels = {
+              let logger_levels = logger_levels.lock().unwrap();
+              names
+                .into_iter()
+                .map(|name| rcl_interfaces::LoggerLevel {
+                  level: logger_levels.get(&name).map_or(0, |l| l as u32),
+                  name,
+                })
+                .collect()
+            };
+            get_server
+              .async_send_response(req_id, rcl_interfaces::GetLoggerLevelsResponse { levels })
+              .await
+              .unwrap_or_else(|e| error!("Cannot respond to {req_id:?}: {e:?}"));
+          }
+          request = set_server.async_receive_request().fuse() => {
+            let (req_id, rcl_interfaces::SetLoggerLevelsRequest { levels }) = match request {
+              Ok(r) => r,
+              Err(e) => return error!("Logger level Server stops: {e:?}"),
+            };
+            let results = {
+              let mut logger_levels = logger_levels.lock().unwrap();
+              levels
+                .into_iter()
+                .map(|rcl_interfaces::LoggerLevel { name, level }| {
+                  // 0 is "unset"
+                  match (level, LogLevel::from_severity(level)) {
+                    (0, _) => logger_levels.set(&name, None),
+                    (_, Some(level)) => logger_levels.set(&name, Some(level)),
+                    (_, None) => {
+                      return rcl_interfaces::SetLoggerLevelsResult {
+                        successful: false,
+                        reason: format!("Unknown logger level {level}"),
+                      }
+                    }
+                  }
+                  rcl_interfaces::SetLoggerLevelsResult {
+                    successful: true,
+                    reason: String::new(),
+                  }
+                })
+                .collect()
+            };
+            set_server
+              .async_send_response(req_id, rcl_interfaces::SetLoggerLevelsResponse { results })
+              .await
+              .unwrap_or_else(|e| error!("Cannot respond to {req_id:?}: {e:?}"));
+          }
+        }
+      }
+    }))
+  }
+
+  /// Registers the description of a type used by this Node, to be served on
+  /// `~/get_type_description`. `type_sources` may be empty. Returns the
+  /// hash of the type.
+  ///
+  /// For a message type, the description and sources are available from
+  /// [`DynamicMessageType`](crate::dynamic_message::DynamicMessageType).
+  pub fn add_type_description(
+    &self,
+    type_description: TypeDescription,
+    type_sources: Vec<TypeSource>,
+  ) -> TypeHash {
+    let type_hash = type_description.type_hash();
+    self.type_descriptions.lock().unwrap().insert(
+      type_description.type_description.type_name.clone(),
+      TypeDescriptionEntry {
+        type_description,
+        type_sources,
+        type_hash,
+      },
+    );
+    type_hash
+  }
+
+  // Serves ~/get_type_description until receiving requests fails.
+  fn type_description_task(&mut self) -> Result<BoxFuture<'static, ()>, Error> {
+    let server: Server<GetTypeDescription> = self.create_typed_server(
+      ServiceMapping::Enhanced,
+      &Name::new(&self.fully_qualified_name(), "get_type_description")?,
+    )?;
+    let type_descriptions = Arc::clone(&self.type_descriptions);
+
+    Ok(Box::pin(async move {
+      loop {
+        let (req_id, request) = match server.async_receive_request().await {
+          Ok(r) => r,
+          Err(e) => return error!("Type description Server stops: {e:?}"),
+        };
+        let response = type_descriptions
+          .lock()
+          .unwrap()
+          .get(&request.type_name)
+          .map_or_else(
+            || GetTypeDescriptionResponse {
+              failure_reason: "Type not currently in use by this node".to_string(),
+              ..GetTypeDescriptionResponse::default()
+            },
+            |entry| entry.response(&request),
+          );
+        server
+          .async_send_response(req_id, response)
+          .await
+          .unwrap_or_else(|e| error!("Cannot respond to {req_id:?}: {e:?}"));
+      }
+    }))
+  }
+
+  /// Level of logger `logger`, e.g. the Node base name or its child
+  /// `talker.planner`. Messages below it are not written to rosout.
   ///
-  /// Availability depends on Node configuration.
-  pub fn rosout_subscription(&self) -> Option<&Subscription<Log>> {
-    self.rosout_reader.as_ref()
+  /// A logger without a level of its own has the level of its parent, and
+  /// the top level ones have the default level: Info, unless changed with
+  /// `--log-level`.
+  pub fn logger_level(&self, logger: &str) -> LogLevel {
+    self.logger_levels.lock().unwrap().effective(logger)
+  }
+
+  /// Sets the level of logger `logger` and its descendants, unless they have
+  /// their own. `None` unsets the level, so that it is inherited again.
+  pub fn set_logger_level(&self, logger: &str, level: Option<LogLevel>) {
+    self.logger_levels.lock().unwrap().set(logger, level);
   }
 
   #[allow(clippy::too_many_arguments)]
@@ -1210,6 +3079,9 @@ impl Node {
     source_function: &str,
     source_line: u32,
   ) {
+    if (level as u8) < self.logger_level(log_name) as u8 {
+      return;
+    }
     match &self.rosout_writer {
       None => debug!("Rosout not enabled. msg: {log_msg}"),
       Some(writer) => {
@@ -1261,7 +3133,8 @@ impl Node {
     topic_name: &Name,
     type_name: MessageTypeName,
     qos: &QosPolicies,
-  ) -> CreateResult<Topic> {
+  ) -> Result<Topic, Error> {
+    let topic_name = self.resolve_name(topic_name)?;
     let dds_name = topic_name.to_dds_name("rt", &self.node_name, "");
     self.ros_context.create_topic(dds_name, type_name, qos)
   }
@@ -1277,9 +3150,158 @@ impl Node {
     &mut self,
     topic: &Topic,
     qos: Option<QosPolicies>,
-  ) -> CreateResult<Subscription<D>> {
-    let sub = self.ros_context.create_subscription(topic, qos)?;
+  ) -> Result<Subscription<D>, Error> {
+    self.create_subscription_with_adapter(topic, qos)
+  }
+
+  /// Like [`Self::create_subscription`], but receives messages also in memory
+  /// from Publishers of the same Context, if
+  /// [`NodeOptions::use_intra_process_comms`] is set. See there.
+  pub fn create_intra_process_subscription<D: 'static + Send + Sync>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<Subscription<D>, Error> {
+    let mut sub = self.create_subscription(topic, qos.clone())?;
+    if self.options.use_intra_process_comms {
+      let qos = qos.unwrap_or_else(|| qos::HasQoSPolicy::qos(topic));
+      let receiver = self.ros_context.intra_process().add_subscription(
+        intra_process_key(topic),
+        sub.guid(),
+        &qos,
+      );
+      sub.set_intra_process(receiver);
+    }
+    Ok(sub)
+  }
+
+  /// Runs `callback` on each message of `subscription` in the Spinner of
+  /// this Node, like a subscription callback in rclcpp.
+  ///
+  /// The Spinner owns `subscription` from now on. Callbacks run one at a
+  /// time, between the other work of the Spinner, so they should return
+  /// quickly. For slow processing, see [`WorkerPool`](crate::dispatch::WorkerPool).
+  /// A panicking callback is logged, and receives the next message as usual.
+  ///
+  /// Callbacks run only while a Spinner is running, see [`Self::spinner`].
+  /// Receive errors are logged and skipped.
+  pub fn register_callback<M, F>(&self, subscription: Subscription<M>, mut callback: F)
+  where
+    M: 'static + DeserializeOwned + Send + Sync,
+    F: FnMut(M, MessageInfo) + Send + 'static,
+  {
+    let task = async move {
+      subscription
+        .for_each(|message, info| {
+          let run = std::panic::AssertUnwindSafe(|| callback(message, info));
+          if let Err(payload) = std::panic::catch_unwind(run) {
+            error!("Subscription callback panicked: {}",
+              panic_message(payload.as_ref()));
+          }
+        })
+        .await
+    };
+    // The Spinner holds a receiver as long as the Node exists.
+    let _ = self.callback_sender.try_send(Box::pin(task));
+  }
+
+  /// Calls `callback` every `period` of ROS time, starting one `period` from
+  /// now.
+  ///
+  /// ROS time is simulated time from the `/clock` Topic when the Parameter
+  /// `use_sim_time` is set, and system time otherwise. See [`Self::time_now`].
+  ///
+  /// Callbacks run in the Spinner, like those of [`Self::register_callback`],
+  /// so only while a Spinner is running. The Timer stops when dropped.
+  pub fn create_timer<F>(&self, period: std::time::Duration, callback: F) -> timer::Timer
+  where
+    F: FnMut() + Send + 'static,
+  {
+    self.add_timer(self.get_clock(), period, callback)
+  }
+
+  /// Like [`Self::create_timer`], but follows the monotonic system clock,
+  /// also when using simulated time.
+  pub fn create_wall_timer<F>(&self, period: std::time::Duration, callback: F) -> timer::Timer
+  where
+    F: FnMut() + Send + 'static,
+  {
+    self.add_timer(Clock::new(ClockType::SteadyTime), period, callback)
+  }
+
+  fn add_timer<F>(
+    &self,
+    clock: Clock,
+    period: std::time::Duration,
+    callback: F,
+  ) -> timer::Timer
+  where
+    F: FnMut() + Send + 'static,
+  {
+    let (new_timer, task) = timer::Timer::new(clock, period, callback);
+    self.spawn_in_spinner(task);
+    new_timer
+  }
+
+  // Runs `task` in the Spinner, once it runs.
+  pub(crate) fn spawn_in_spinner(&self, task: BoxFuture<'static, ()>) {
+    // The Spinner holds a receiver as long as the Node exists.
+    let _ = self.callback_sender.try_send(task);
+  }
+
+  /// Enables ROS 2 Topic Statistics on `subscription`.
+  ///
+  /// The age of received messages and the period between them are published
+  /// as `statistics_msgs/msg/MetricsMessage`, see
+  /// [`topic_statistics`](crate::topic_statistics). Publishing is done by the
+  /// Spinner, and stops when `subscription` is dropped.
+  pub fn enable_topic_statistics<M: 'static>(
+    &mut self,
+    subscription: &mut Subscription<M>,
+    options: TopicStatisticsOptions,
+  ) -> Result<(), Error> {
+    let topic = self.create_topic(
+      &options.publish_topic,
+      MessageTypeName::new("statistics_msgs", "MetricsMessage"),
+      &DEFAULT_PUBLISHER_QOS,
+    )?;
+    let publisher = self.create_publisher::<MetricsMessage>(&topic, None)?;
+    let collector = Arc::new(StatisticsCollector::new());
+    subscription.set_statistics(Arc::clone(&collector));
+    let task = publish_statistics(
+      Arc::downgrade(&collector),
+      publisher,
+      self.base_name().to_string(),
+      options.publish_period,
+    );
+    // The Spinner holds a receiver as long as the Node exists.
+    let _ = self.callback_sender.try_send(Box::pin(task));
+    Ok(())
+  }
+
+  /// Creates a Subscription that decodes messages with `DA` instead of the
+  /// default CDR decoding, e.g. a zero-copy deserializer or a non-CDR format
+  /// on a bridge Topic.
+  ///
+  /// Received messages whose representation identifier is not in
+  /// [`supported_encodings`](no_key::DeserializerAdapter::supported_encodings)
+  /// of `DA` are rejected. Messages always arrive over DDS, also with
+  /// intra-process communication.
+  pub fn create_subscription_with_adapter<D, DA>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<Subscription<D, DA>, Error>
+  where
+    D: 'static,
+    DA: no_key::DeserializerAdapter<D> + 'static,
+  {
+    let mut sub = self
+      .ros_context
+      .create_subscription_with_adapter(topic, qos)
+      .map_err(create_error(EntityKind::Subscription, &topic.name()))?;
     self.add_reader(sub.guid().into());
+    sub.set_registration(self.endpoint_registration(sub.guid().into()));
     Ok(sub)
   }
 
@@ -1295,12 +3317,180 @@ impl Node {
     &mut self,
     topic: &Topic,
     qos: Option<QosPolicies>,
-  ) -> CreateResult<Publisher<D>> {
-    let p = self.ros_context.create_publisher(topic, qos)?;
+  ) -> Result<Publisher<D>, Error> {
+    self.create_publisher_with_adapter(topic, qos)
+  }
+
+  /// Like [`Self::create_publisher`], but delivers messages in memory to
+  /// Subscriptions of the same Context, if
+  /// [`NodeOptions::use_intra_process_comms`] is set. See there.
+  pub fn create_intra_process_publisher<D: Serialize + 'static + Send + Sync>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<Publisher<D>, Error> {
+    let mut p = self.create_publisher(topic, qos)?;
+    if self.options.use_intra_process_comms {
+      let delivery = self.ros_context.intra_process().publisher::<D>(
+        intra_process_key(topic),
+        p.guid(),
+        Arc::clone(&self.writers_to_remote_readers),
+      );
+      p.set_intra_process(Box::new(delivery));
+    }
+    Ok(p)
+  }
+
+  /// Creates a Publisher that encodes messages with `SA` instead of CDR, e.g.
+  /// a non-CDR format on a bridge Topic.
+  ///
+  /// Messages are sent with the representation identifier given by
+  /// [`output_encoding`](no_key::SerializerAdapter::output_encoding) of `SA`,
+  /// so only Subscriptions that accept it can read them. They are always
+  /// published over DDS, also with intra-process communication.
+  pub fn create_publisher_with_adapter<D, SA>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<Publisher<D, SA>, Error>
+  where
+    SA: no_key::SerializerAdapter<D>,
+  {
+    let mut p = self
+      .ros_context
+      .create_publisher_with_adapter(topic, qos)
+      .map_err(create_error(EntityKind::Publisher, &topic.name()))?;
+    self.add_writer(p.guid().into());
+    p.set_registration(self.endpoint_registration(p.guid().into()));
+    Ok(p)
+  }
+
+  /// Creates a Subscription that adapts its QoS to incompatible Publishers.
+  ///
+  /// See [`AdaptiveSubscription`]. If `qos` is `None`, the Topic QoS is used
+  /// initially.
+  pub fn create_adaptive_subscription<D: 'static>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<AdaptiveSubscription<D>, Error> {
+    let qos = qos.unwrap_or_else(|| qos::HasQoSPolicy::qos(topic));
+    let sub = self.create_subscription(topic, Some(qos.clone()))?;
+    Ok(AdaptiveSubscription::new(sub, topic.clone(), qos))
+  }
+
+  /// Creates a Publisher that adapts its QoS to incompatible Subscriptions.
+  ///
+  /// See [`AdaptivePublisher`]. If `qos` is `None`, the Topic QoS is used
+  /// initially.
+  pub fn create_adaptive_publisher<D: Serialize>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<AdaptivePublisher<D>, Error> {
+    let qos = qos.unwrap_or_else(|| qos::HasQoSPolicy::qos(topic));
+    let p = self.create_publisher(topic, Some(qos.clone()))?;
+    Ok(AdaptivePublisher::new(p, topic.clone(), qos))
+  }
+
+  /// Creates a [`Relay`] that forwards messages from `input_topic` to
+  /// `output_topic`, optionally rate limited and with different QoS.
+  ///
+  /// The Relay does nothing until [`Relay::run`] is running.
+  ///
+  /// # Panics
+  ///
+  /// If a maximum frequency is given and it is not positive.
+  pub fn create_relay(
+    &mut self,
+    input_topic: &Topic,
+    output_topic: &Topic,
+    options: RelayOptions,
+  ) -> Result<Relay, Error> {
+    if input_topic.get_type() != output_topic.get_type() {
+      warn!("Relay from {} to {} changes message type from {:?} to {:?}",
+        input_topic.name(), output_topic.name(), input_topic.get_type(), output_topic.get_type());
+    }
+    let subscription = self.create_subscription(input_topic, options.input_qos)?;
+    let publisher = self.create_publisher(output_topic, options.output_qos)?;
+    Ok(Relay::new(subscription, publisher, options.max_frequency))
+  }
+
+  /// Creates a Publisher whose latest message is delivered also to
+  /// Subscriptions created later, like a latched Publisher in ROS 1.
+  ///
+  /// `qos`, or the Topic QoS if `None`, is modified with
+  /// [`latched_qos`](crate::latched::latched_qos). Messages always go over
+  /// DDS, as intra-process delivery does not keep them for later
+  /// Subscriptions.
+  pub fn create_latched_publisher<D: Serialize>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<LatchedPublisher<D>, Error> {
+    let qos = latched_qos(&qos.unwrap_or_else(|| qos::HasQoSPolicy::qos(topic)));
+    let p = self.create_publisher(topic, Some(qos))?;
+    Ok(LatchedPublisher::new(p))
+  }
+
+  /// Creates a WithKey Topic, for interoperation with keyed DDS data. See
+  /// [`keyed`](crate::keyed).
+  ///
+  /// The name is mapped to DDS like in [`Self::create_topic`]. ROS 2 Topics
+  /// are NoKey, so a keyed Topic does not match them.
+  pub fn create_keyed_topic(
+    &self,
+    topic_name: &Name,
+    type_name: MessageTypeName,
+    qos: &QosPolicies,
+  ) -> Result<Topic, Error> {
+    let topic_name = self.resolve_name(topic_name)?;
+    let dds_name = topic_name.to_dds_name("rt", &self.node_name, "");
+    self.ros_context.create_keyed_topic(dds_name, type_name, qos)
+  }
+
+  /// Creates a Publisher of a Topic from [`Self::create_keyed_topic`].
+  ///
+  /// `qos` `None` indicates the use of Topic QoS.
+  pub fn create_keyed_publisher<D>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<KeyedPublisher<D>, Error>
+  where
+    D: Keyed + Serialize,
+    D::K: Serialize,
+  {
+    let mut p = self
+      .ros_context
+      .create_keyed_publisher(topic, qos)
+      .map_err(create_error(EntityKind::Publisher, &topic.name()))?;
     self.add_writer(p.guid().into());
+    p.set_registration(self.endpoint_registration(p.guid().into()));
     Ok(p)
   }
 
+  /// Creates a Subscription of a Topic from [`Self::create_keyed_topic`].
+  ///
+  /// `qos` `None` indicates the use of Topic QoS.
+  pub fn create_keyed_subscription<D>(
+    &mut self,
+    topic: &Topic,
+    qos: Option<QosPolicies>,
+  ) -> Result<KeyedSubscription<D>, Error>
+  where
+    D: 'static + Keyed + DeserializeOwned,
+    for<'de> D::K: Deserialize<'de>,
+  {
+    let mut sub = self
+      .ros_context
+      .create_keyed_subscription(topic, qos)
+      .map_err(create_error(EntityKind::Subscription, &topic.name()))?;
+    self.add_reader(sub.guid().into());
+    sub.set_registration(self.endpoint_registration(sub.guid().into()));
+    Ok(sub)
+  }
+
   pub(crate) fn create_simpledatareader<D, DA>(
     &mut self,
     topic: &Topic,
@@ -1342,11 +3532,14 @@ impl Node {
     service_type_name: &ServiceTypeName,
     request_qos: QosPolicies,
     response_qos: QosPolicies,
-  ) -> CreateResult<Client<S>>
+  ) -> Result<Client<S>, Error>
   where
     S: Service + 'static,
     S::Request: Clone,
   {
+    let service_name = &self.resolve_name(service_name)?;
+    let error_context = || create_error(EntityKind::Client, &service_name.to_string());
+
     // Add rq/ and rr/ prefixes as documented in
     // https://design.ros2.org/articles/topic_and_service_names.html
     // Where are the suffixes documented?
@@ -1358,23 +3551,28 @@ impl Node {
       service_type_name.dds_request_type(),
       &request_qos,
       TopicKind::NoKey,
-    )?;
+    )
+    .map_err(error_context())?;
     let rs_topic = self.ros_context.domain_participant().create_topic(
       service_name.to_dds_name("rr", &self.node_name, "Reply"),
       //rs_name,
       service_type_name.dds_response_type(),
       &response_qos,
       TopicKind::NoKey,
-    )?;
+    )
+    .map_err(error_context())?;
 
     let c = Client::<S>::new(
       service_mapping,
       self,
+      service_name,
+      service_type_name,
       &rq_topic,
       &rs_topic,
       Some(request_qos),
       Some(response_qos),
-    )?;
+    )
+    .map_err(error_context())?;
 
     Ok(c)
   }
@@ -1394,15 +3592,13 @@ impl Node {
     service_type_name: &ServiceTypeName,
     request_qos: QosPolicies,
     response_qos: QosPolicies,
-  ) -> CreateResult<Server<S>>
+  ) -> Result<Server<S>, Error>
   where
     S: Service + 'static,
     S::Request: Clone,
   {
-    // let rq_name = Self::check_name_and_add_prefix("rq/",
-    // &(service_name.to_owned() + "Request"))?; let rs_name =
-    // Self::check_name_and_add_prefix("rr/", &(service_name.to_owned() +
-    // "Reply"))?;
+    let service_name = &self.resolve_name(service_name)?;
+    let error_context = || create_error(EntityKind::Server, &service_name.to_string());
 
     let rq_topic = self.ros_context.domain_participant().create_topic(
       //rq_name,
@@ -1410,38 +3606,100 @@ impl Node {
       service_type_name.dds_request_type(),
       &request_qos,
       TopicKind::NoKey,
-    )?;
+    )
+    .map_err(error_context())?;
     let rs_topic = self.ros_context.domain_participant().create_topic(
       service_name.to_dds_name("rr", &self.node_name, "Reply"),
       service_type_name.dds_response_type(),
       &response_qos,
       TopicKind::NoKey,
-    )?;
+    )
+    .map_err(error_context())?;
 
     let s = Server::<S>::new(
       service_mapping,
       self,
+      service_name,
+      service_type_name,
       &rq_topic,
       &rs_topic,
       Some(request_qos),
       Some(response_qos),
-    )?;
+    )
+    .map_err(error_context())?;
 
     Ok(s)
   }
 
+  /// Creates a Client of a [`TypedService`], e.g. one defined with
+  /// [`ros2_service!`](crate::ros2_service), with its type name and default
+  /// QoS.
+  pub fn create_typed_client<S>(
+    &mut self,
+    service_mapping: ServiceMapping,
+    service_name: &Name,
+  ) -> Result<Client<S>, Error>
+  where
+    S: TypedService + 'static,
+    S::Request: Clone,
+  {
+    self.create_client(
+      service_mapping,
+      service_name,
+      &S::service_type_name(),
+      S::default_qos(),
+      S::default_qos(),
+    )
+  }
+
+  /// Creates a Server of a [`TypedService`], with its type name and default
+  /// QoS.
+  pub fn create_typed_server<S>(
+    &mut self,
+    service_mapping: ServiceMapping,
+    service_name: &Name,
+  ) -> Result<Server<S>, Error>
+  where
+    S: TypedService + 'static,
+    S::Request: Clone,
+  {
+    self.create_server(
+      service_mapping,
+      service_name,
+      &S::service_type_name(),
+      S::default_qos(),
+      S::default_qos(),
+    )
+  }
+
+  /// Creates a [`ParameterClient`] for accessing the Parameters of
+  /// `remote_node`.
+  pub fn create_parameter_client(&mut self, remote_node: &NodeName) -> Result<ParameterClient, Error> {
+    ParameterClient::new(self, remote_node)
+  }
+
+  /// Creates a [`LifecycleServiceClient`] for managing the lifecycle of
+  /// `remote_node`.
+  pub fn create_lifecycle_client(
+    &mut self,
+    remote_node: &NodeName,
+  ) -> Result<LifecycleServiceClient, Error> {
+    LifecycleServiceClient::new(self, remote_node)
+  }
+
   pub fn create_action_client<A>(
     &mut self,
     service_mapping: ServiceMapping,
     action_name: &Name,
     action_type_name: &ActionTypeName,
     action_qos: ActionClientQosPolicies,
-  ) -> CreateResult<ActionClient<A>>
+  ) -> Result<ActionClient<A>, Error>
   where
     A: ActionTypes + 'static,
   {
     // action name is e.g. "/turtle1/rotate_absolute"
     // action type name is e.g. "turtlesim/action/RotateAbsolute"
+    let action_name = &self.resolve_name(action_name)?;
     let services_base_name = action_name.push("_action");
 
     //let goal_service_name = action_name.to_owned() + "/_action/send_goal";
@@ -1504,6 +3762,7 @@ impl Node {
       my_feedback_subscription,
       my_status_subscription,
       my_action_name: action_name.clone(),
+      feedback_demux: FeedbackDemux::new(),
     })
   }
 
@@ -1513,10 +3772,11 @@ impl Node {
     action_name: &Name,
     action_type_name: &ActionTypeName,
     action_qos: ActionServerQosPolicies,
-  ) -> CreateResult<ActionServer<A>>
+  ) -> Result<ActionServer<A>, Error>
   where
     A: ActionTypes + 'static,
   {
+    let action_name = &self.resolve_name(action_name)?;
     let services_base_name = action_name.push("_action");
 
     //let goal_service_name = action_name.to_owned() + "/_action/send_goal";
@@ -1581,6 +3841,69 @@ impl Node {
       my_action_name: action_name.clone(),
     })
   }
+
+  // Waits until the Spinner acknowledges a stop request, at most
+  // NODE_TEARDOWN_TIMEOUT.
+  async fn wait_for_spinner_stop(&self) {
+    if !self.spinner_running.load(Ordering::SeqCst) {
+      return;
+    }
+    if let Some(ref spin_stopped_receiver) = self.spin_stopped_receiver {
+      futures::select! {
+        result = spin_stopped_receiver.recv().fuse() => match result {
+          Ok(()) => debug!("Spinner acknowledged stop."),
+          // The Spinner was dropped, so it is not running either.
+          Err(e) => debug!("Spinner is gone: {e:?}"),
+        },
+        _ = FutureExt::fuse(Timer::after(NODE_TEARDOWN_TIMEOUT)) => {
+          warn!("Spinner did not acknowledge stop.");
+        }
+      }
+    }
+  }
+
+  /// Shuts down the Node without blocking the thread.
+  ///
+  /// Stops the Spinner and waits for it to acknowledge, flushes the rosout
+  /// and Parameter event writers, and then removes the Node from ROS
+  /// discovery. Dropping the Node does the same, except that it may block
+  /// waiting for the Spinner, and does not flush. Each wait is limited to
+  /// half a second.
+  ///
+  /// Entities created from this Node are not closed. Close them first, if
+  /// needed.
+  pub async fn close(mut self) {
+    if let Some(stop_spin_sender) = self.stop_spin_sender.take() {
+      stop_spin_sender
+        .send(())
+        .await
+        .unwrap_or_else(|e| debug!("Spinner already stopped: {e:?}"));
+      self.wait_for_spinner_stop().await;
+    }
+
+    // Do not wait for acknowledgements indefinitely, because there may be
+    // e.g. rosout readers that have gone away without notice.
+    let flush_builtin_writers = async {
+      if let Some(ref rosout_writer) = self.rosout_writer {
+        rosout_writer
+          .flush()
+          .await
+          .unwrap_or_else(|e| warn!("Cannot flush rosout: {e:?}"));
+      }
+      self
+        .parameter_events_writer
+        .flush()
+        .await
+        .unwrap_or_else(|e| warn!("Cannot flush parameter events: {e:?}"));
+    };
+    futures::select! {
+      () = flush_builtin_writers.fuse() => {}
+      _ = FutureExt::fuse(Timer::after(NODE_TEARDOWN_TIMEOUT)) => {
+        warn!("Timed out flushing rosout and parameter events.");
+      }
+    }
+    // Drop removes the Node from discovery.
+  }
 } // impl Node
 
 impl Drop for Node {
@@ -1589,12 +3912,149 @@ impl Drop for Node {
       stop_spin_sender
         .try_send(())
         .unwrap_or_else(|e| error!("Cannot notify spin task to stop: {e:?}"));
+
+      // Wait for the Spinner to stop before our entities go away, so that it
+      // does not operate on them while we publish the final discovery info.
+      //
+      // The wait is bounded, because the Spinner may be unable to run, e.g.
+      // if it is in a single-threaded executor that we are now blocking.
+      // async_io::block_on, unlike futures::executor::block_on, may be called
+      // from within another executor.
+      async_io::block_on(self.wait_for_spinner_stop());
     }
 
-    self
-      .ros_context
-      .remove_node(self.fully_qualified_name().as_str());
+    // This publishes the updated ros_discovery_info without this Node.
+    self.ros_context.remove_node(self.node_key);
+  }
+}
+
+// See Node::get_server_clients()
+// Intra-process Publishers and Subscriptions are matched by Topic name and
+// type.
+fn intra_process_key(topic: &Topic) -> (String, String) {
+  (topic.name(), topic.get_type().name().to_string())
+}
+
+fn paired_clients(
+  readers_to_remote_writers: &Mutex<BTreeMap<GUID, BTreeSet<GUID>>>,
+  writers_to_remote_readers: &Mutex<BTreeMap<GUID, BTreeSet<GUID>>>,
+  request_reader: GUID,
+  response_writer: GUID,
+) -> BTreeSet<GUID> {
+  let response_participants: BTreeSet<_> = writers_to_remote_readers
+    .lock()
+    .unwrap()
+    .get(&response_writer)
+    .map(|readers| readers.iter().map(|r| r.prefix).collect())
+    .unwrap_or_default();
+  readers_to_remote_writers
+    .lock()
+    .unwrap()
+    .get(&request_reader)
+    .map(|writers| {
+      writers
+        .iter()
+        .filter(|w| response_participants.contains(&w.prefix))
+        .copied()
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+// Fully qualified names of Nodes that are in `previous` but not in `current`.
+fn departed_nodes(previous: Vec<NodeEntitiesInfo>, current: &[NodeEntitiesInfo]) -> Vec<String> {
+  previous
+    .into_iter()
+    .map(|n| n.fully_qualified_name())
+    .filter(|fqn| !current.iter().any(|n| n.fully_qualified_name() == *fqn))
+    .collect()
+}
+
+// GUIDs of the endpoints in a discovery cache that are on `dds_topic_name`.
+fn endpoints_on_topic(
+  discovered: &Mutex<BTreeMap<GUID, EndpointDescription>>,
+  dds_topic_name: &str,
+) -> BTreeSet<GUID> {
+  discovered
+    .lock()
+    .unwrap()
+    .values()
+    .filter(|e| e.topic_name == dds_topic_name)
+    .map(|e| e.guid)
+    .collect()
+}
+
+// Topic, Service or Action names and types of DDS endpoints. `demangle` maps
+// a DDS Topic name and type name to a ROS 2 name and type name, or None if the
+// endpoint is not of the kind we are looking for.
+fn names_and_types<'a>(
+  endpoints: impl Iterator<Item = &'a EndpointDescription>,
+  demangle: fn(&str, &str) -> Option<(String, String)>,
+) -> BTreeMap<String, Vec<String>> {
+  let mut names: BTreeMap<String, Vec<String>> = BTreeMap::new();
+  for e in endpoints {
+    if let Some((name, type_name)) = demangle(&e.topic_name, &e.type_name) {
+      let types = names.entry(name).or_default();
+      if !types.contains(&type_name) {
+        types.push(type_name);
+      }
+    }
   }
+  names
+}
+
+// E.g. "example_interfaces::srv::dds_::AddTwoInts_Request_" with suffix
+// "_Request_" gives "example_interfaces/srv/AddTwoInts".
+fn demangle_type(dds_type_name: &str, suffix: &str) -> Option<String> {
+  match dds_type_name.split("::").collect::<Vec<_>>()[..] {
+    [package, kind, "dds_", t] => t
+      .strip_suffix(suffix)
+      .map(|t| format!("{package}/{kind}/{t}")),
+    _ => None,
+  }
+}
+
+// E.g. Topic "rt/chatter" with type "std_msgs::msg::dds_::String_" gives Topic
+// "/chatter" of type "std_msgs/msg/String".
+fn demangle_topic(topic_name: &str, type_name: &str) -> Option<(String, String)> {
+  let name = topic_name.strip_prefix("rt")?;
+  Some((name.to_owned(), demangle_type(type_name, "_")?))
+}
+
+// E.g. Topic "rq/add_two_intsRequest" with type
+// "example_interfaces::srv::dds_::AddTwoInts_Request_" gives Service
+// "/add_two_ints" of type "example_interfaces/srv/AddTwoInts". Response
+// Topics "rr/...Reply" are recognized likewise.
+fn demangle_service(topic_name: &str, type_name: &str) -> Option<(String, String)> {
+  let (name, type_name) = if let Some(rq) = topic_name.strip_prefix("rq") {
+    (rq.strip_suffix("Request")?, demangle_type(type_name, "_Request_")?)
+  } else {
+    let rr = topic_name.strip_prefix("rr")?;
+    (rr.strip_suffix("Reply")?, demangle_type(type_name, "_Response_")?)
+  };
+  Some((name.to_owned(), type_name))
+}
+
+// Service request Topics only, so that Servers and Clients can be told apart.
+fn demangle_service_request(topic_name: &str, type_name: &str) -> Option<(String, String)> {
+  topic_name
+    .starts_with("rq")
+    .then(|| demangle_service(topic_name, type_name))
+    .flatten()
+}
+
+// Actions are recognized from their feedback Topics. E.g. Topic
+// "rt/fibonacci/_action/feedback" with type
+// "example_interfaces::action::dds_::Fibonacci_FeedbackMessage_" gives Action
+// "/fibonacci" of type "example_interfaces/action/Fibonacci".
+fn demangle_action(topic_name: &str, type_name: &str) -> Option<(String, String)> {
+  let name = topic_name
+    .strip_prefix("rt")?
+    .strip_suffix("/_action/feedback")?;
+  Some((
+    name.to_owned(),
+    demangle_type(type_name, "_FeedbackMessage_")?,
+  ))
 }
 
 /// Macro for writing to [rosout](https://wiki.ros.org/rosout) topic.
@@ -1614,9 +4074,29 @@ impl Drop for Node {
 /// let kind = "silly";
 ///
 /// rosout!(node, ros2::LogLevel::Info, "A {} event was seen.", kind);
+///
+/// // Logger "some_node.planner", a child of the Node logger
+/// rosout!(node, logger = "planner", ros2::LogLevel::Debug, "Planning...");
 /// ```
+///
+/// Messages below the level of the logger are not written, see
+/// [`Node::logger_level`](crate::Node::logger_level).
 #[macro_export]
 macro_rules! rosout {
+    // rosout!(node, logger = "child", Level::Info, "a {} event", event.kind);
+
+    ($node:expr, logger = $child:expr, $lvl:expr, $($arg:tt)+) => (
+        $node.rosout_raw(
+            $crate::ros2::Timestamp::now(),
+            $lvl,
+            &std::format!("{}.{}", $node.base_name(), $child),
+            &std::format!($($arg)+), // msg
+            std::file!(),
+            "<unknown_func>",
+            std::line!(),
+        );
+    );
+
     // rosout!(node, Level::Info, "a {} event", event.kind);
 
     ($node:expr, $lvl:expr, $($arg:tt)+) => (
@@ -1632,6 +4112,62 @@ macro_rules! rosout {
     );
 }
 
+/// Like [`rosout!`], but writes at most once per `period` from each call site.
+///
+/// This is for logging in loops, like `RCLCPP_INFO_THROTTLE`. The period is
+/// measured in monotonic time, not ROS time.
+///
+/// # Example
+///
+/// ```
+/// # use ros2_client::*;
+/// # use std::time::Duration;
+/// #
+/// # let context = Context::new().unwrap();
+/// # let mut node = context
+/// #     .new_node(
+/// #       NodeName::new("/", "some_node").unwrap(),
+/// #       NodeOptions::new().enable_rosout(true),
+/// #     )
+/// #     .unwrap();
+/// for i in 0..1000 {
+///   // Written only on the first round
+///   rosout_throttle!(node, ros2::LogLevel::Warn, Duration::from_secs(5), "Round {}", i);
+/// }
+/// ```
+#[macro_export]
+macro_rules! rosout_throttle {
+    ($node:expr, $lvl:expr, $period:expr, $($arg:tt)+) => ({
+        static THROTTLE: $crate::log::CallSiteThrottle = $crate::log::CallSiteThrottle::new();
+        if THROTTLE.pass($period) {
+            $crate::rosout!($node, $lvl, $($arg)+);
+        }
+    });
+}
+
+/// Like [`rosout!`], but writes only the first time this call site is reached.
+#[macro_export]
+macro_rules! rosout_once {
+    ($node:expr, $lvl:expr, $($arg:tt)+) => ({
+        static DONE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+        if !DONE.swap(true, std::sync::atomic::Ordering::Relaxed) {
+            $crate::rosout!($node, $lvl, $($arg)+);
+        }
+    });
+}
+
+/// Like [`rosout!`], but does not write the first time this call site is
+/// reached, only after that.
+#[macro_export]
+macro_rules! rosout_skip_first {
+    ($node:expr, $lvl:expr, $($arg:tt)+) => ({
+        static SKIPPED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+        if SKIPPED.swap(true, std::sync::atomic::Ordering::Relaxed) {
+            $crate::rosout!($node, $lvl, $($arg)+);
+        }
+    });
+}
+
 /// Future type for waiting Readers to appear over ROS2 Topic.
 ///
 /// Produced by `node.wait_for_reader(writer_guid)`
@@ -1639,7 +4175,6 @@ macro_rules! rosout {
 // This is implemented as a separate struct instead of just async function in
 // Node so that it does not borrow the node and thus can be Send.
 //use pin_project::pin_project;
-
 pub enum ReaderWait<'a> {
   // We need to wait for an event that is for us
   Wait {
@@ -1747,3 +4282,47 @@ impl Future for WriterWait<'_> {
     }
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::Context;
+
+  #[test]
+  fn set_parameters_atomically_rolls_back() {
+    let actions = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&actions);
+    let options = NodeOptions::new()
+      .declare_parameter("a", ParameterValue::Integer(1))
+      .declare_parameter("b", ParameterValue::Integer(1))
+      .parameter_set_action(Box::new(move |name, value| {
+        let ParameterValue::Integer(i) = *value else {
+          return Err("Integer expected".to_owned());
+        };
+        recorded.lock().unwrap().push((name.to_owned(), i));
+        match (name, i) {
+          ("b", 2) => Err("b cannot be 2".to_owned()),
+          _ => Ok(()),
+        }
+      }));
+    let context = Context::new().unwrap();
+    let mut node = context
+      .new_node(NodeName::new("/", "atomic_parameters").unwrap(), options)
+      .unwrap();
+    let spinner = node.spinner().unwrap();
+    actions.lock().unwrap().clear();
+
+    let p = |name: &str, i| Parameter {
+      name: name.to_owned(),
+      value: ParameterValue::Integer(i),
+    };
+    assert!(spinner
+      .set_parameters_atomically(vec![p("a", 2), p("b", 2)])
+      .is_err());
+    // The action of "a" was executed again with the old value.
+    let a = |name: &str, i| (name.to_owned(), i);
+    assert_eq!(*actions.lock().unwrap(), [a("a", 2), a("b", 2), a("a", 1)]);
+    assert_eq!(node.get_parameter_as::<i64>("a"), Some(1));
+    assert_eq!(node.get_parameter_as::<i64>("b"), Some(1));
+  }
+}
@@ -1,12 +1,28 @@
 //! Rust-like representation of ROS2 Parameters
 //!
-//! Parameters are key-value paris that can be set in application code, on the
-//! command line (not yet implemented), from environment variables (not
+//! Parameters are key-value paris that can be set in application code, from
+//! parameter files (see [`ParameterFile`]), on the command line (see
+//! [`ros_args`](crate::ros_args)), from environment variables (not
 //! implemented), or remotely.
 //!
 //! Paramters can be queried and set remotely using e.g. the `ros2 param` or
 //! `rqt` tools from ROS 2. This only works for [`Node`](crate::Node)s that have
-//! enabled Parameter Services and are running a `Spinner`.
+//! enabled Parameter Services and are running a `Spinner`. To access the
+//! Parameters of another Node, see [`ParameterClient`].
+
+use std::{collections::BTreeMap, convert::TryFrom, fs, io, path::Path, sync::OnceLock};
+
+use futures::{future, stream, Stream, StreamExt};
+use rustdds::{policy, QosPolicies, QosPolicyBuilder};
+
+use crate::{
+  error,
+  names::{Name, NodeName, ServiceTypeName},
+  pubsub::Subscription,
+  rcl_interfaces,
+  service::{Client, Service, ServiceMapping},
+  Node,
+};
 
 /// Named parameter
 #[derive(Debug, Clone)]
@@ -33,6 +49,7 @@ pub enum ParameterValue {
 
 /// List of Parameter types supported by ROS 2.
 /// <https://github.com/ros2/rcl_interfaces/blob/humble/rcl_interfaces/msg/ParameterType.msg>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ParameterType {
   NotSet = 0,
   Bool = 1,
@@ -46,6 +63,23 @@ pub enum ParameterType {
   StringArray = 9,
 }
 
+impl From<u8> for ParameterType {
+  fn from(t: u8) -> ParameterType {
+    match t {
+      raw::ParameterType::BOOL => ParameterType::Bool,
+      raw::ParameterType::INTEGER => ParameterType::Integer,
+      raw::ParameterType::DOUBLE => ParameterType::Double,
+      raw::ParameterType::STRING => ParameterType::String,
+      raw::ParameterType::BYTE_ARRAY => ParameterType::ByteArray,
+      raw::ParameterType::BOOL_ARRAY => ParameterType::BoolArray,
+      raw::ParameterType::INTEGER_ARRAY => ParameterType::IntegerArray,
+      raw::ParameterType::DOUBLE_ARRAY => ParameterType::DoubleArray,
+      raw::ParameterType::STRING_ARRAY => ParameterType::StringArray,
+      _ => ParameterType::NotSet, // also unknown types
+    }
+  }
+}
+
 impl ParameterValue {
   // https://github.com/ros2/rcl_interfaces/blob/rolling/rcl_interfaces/msg/ParameterType.msg
   pub fn to_parameter_type(&self) -> ParameterType {
@@ -102,6 +136,49 @@ impl From<raw::ParameterValue> for ParameterValue {
   }
 }
 
+// Conversions between ParameterValue and the Rust types of its variants
+macro_rules! parameter_value_conversions {
+  ($($variant:ident($t:ty)),* $(,)?) => {
+    $(
+      impl From<$t> for ParameterValue {
+        fn from(v: $t) -> ParameterValue {
+          ParameterValue::$variant(v)
+        }
+      }
+
+      impl TryFrom<ParameterValue> for $t {
+        type Error = ParameterValue;
+
+        /// Fails with the original value, if it is of some other type.
+        fn try_from(v: ParameterValue) -> Result<$t, ParameterValue> {
+          match v {
+            ParameterValue::$variant(x) => Ok(x),
+            other => Err(other),
+          }
+        }
+      }
+    )*
+  };
+}
+
+parameter_value_conversions!(
+  Boolean(bool),
+  Integer(i64),
+  Double(f64),
+  String(String),
+  ByteArray(Vec<u8>),
+  BooleanArray(Vec<bool>),
+  IntegerArray(Vec<i64>),
+  DoubleArray(Vec<f64>),
+  StringArray(Vec<String>),
+);
+
+impl From<&str> for ParameterValue {
+  fn from(s: &str) -> ParameterValue {
+    ParameterValue::String(s.to_owned())
+  }
+}
+
 impl From<Parameter> for raw::Parameter {
   fn from(p: Parameter) -> raw::Parameter {
     raw::Parameter {
@@ -186,7 +263,18 @@ impl From<SetParametersResult> for raw::SetParametersResult {
   }
 }
 
+impl From<raw::SetParametersResult> for SetParametersResult {
+  fn from(r: raw::SetParametersResult) -> SetParametersResult {
+    if r.successful {
+      Ok(())
+    } else {
+      Err(r.reason)
+    }
+  }
+}
+
 /// Documentation and constraints for a [`Parameter`]
+#[derive(Debug, Clone)]
 pub struct ParameterDescriptor {
   pub name: String,
   pub param_type: ParameterType, // ParameterType.msg defines enum
@@ -199,7 +287,83 @@ pub struct ParameterDescriptor {
   pub range: NumericRange,
 }
 
+/// The default descriptor places no constraints, except that the type is
+/// fixed when the Parameter is declared.
+impl Default for ParameterDescriptor {
+  fn default() -> Self {
+    ParameterDescriptor {
+      name: String::new(),
+      param_type: ParameterType::NotSet,
+      description: String::new(),
+      additional_constraints: String::new(),
+      read_only: false,
+      dynamic_typing: false,
+      range: NumericRange::NotSpecified,
+    }
+  }
+}
+
 impl ParameterDescriptor {
+  /// Checks that `value` has the right type and is within the range.
+  ///
+  /// This does not check `read_only`, because that applies only to changing
+  /// values.
+  pub fn validate(&self, value: &ParameterValue) -> SetParametersResult {
+    let value_type = value.to_parameter_type();
+    if !self.dynamic_typing
+      && self.param_type != ParameterType::NotSet
+      && value_type != self.param_type
+    {
+      return Err(format!(
+        "Parameter '{}' must be of type {:?}, not {:?}.",
+        self.name, self.param_type, value_type
+      ));
+    }
+    match (&self.range, value) {
+      (
+        NumericRange::IntegerRange {
+          from_value,
+          to_value,
+          step,
+        },
+        ParameterValue::Integer(v),
+      ) => {
+        let on_step = *step == 0 || *v == *to_value || (v - from_value) % step == 0;
+        if v < from_value || v > to_value || !on_step {
+          return Err(format!(
+            "Parameter '{}' must be from {} to {} in steps of {}.",
+            self.name, from_value, to_value, step
+          ));
+        }
+      }
+      (
+        NumericRange::FloatingPointRange {
+          from_value,
+          to_value,
+          step,
+        },
+        ParameterValue::Double(v),
+      ) => {
+        // Allow for rounding errors, as rclcpp does.
+        let close = |a: f64, b: f64| (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0);
+        let in_range =
+          (from_value..=to_value).contains(&v) || close(*v, *from_value) || close(*v, *to_value);
+        let on_step = *step == 0.0 || close(*v, *to_value) || {
+          let steps = ((v - from_value) / step).round();
+          close(*v, from_value + steps * step)
+        };
+        if !in_range || !on_step {
+          return Err(format!(
+            "Parameter '{}' must be from {} to {} in steps of {}.",
+            self.name, from_value, to_value, step
+          ));
+        }
+      }
+      _ => {} // Ranges only apply to scalars of their own type.
+    }
+    Ok(())
+  }
+
   pub fn unknown(name: &str) -> Self {
     ParameterDescriptor {
       name: name.to_string(),
@@ -226,6 +390,7 @@ impl ParameterDescriptor {
 }
 
 /// Optional Limits for a numeric [`Parameter`]
+#[derive(Debug, Clone)]
 pub enum NumericRange {
   NotSpecified,
   IntegerRange {
@@ -285,16 +450,758 @@ impl From<ParameterDescriptor> for raw::ParameterDescriptor {
   }
 }
 
+impl From<raw::ParameterDescriptor> for ParameterDescriptor {
+  fn from(p: raw::ParameterDescriptor) -> ParameterDescriptor {
+    let range = match (p.integer_range.first(), p.floating_point_range.first()) {
+      (Some(r), _) => NumericRange::IntegerRange {
+        from_value: r.from_value,
+        to_value: r.to_value,
+        step: r.step,
+      },
+      (None, Some(r)) => NumericRange::FloatingPointRange {
+        from_value: r.from_value,
+        to_value: r.to_value,
+        step: r.step,
+      },
+      (None, None) => NumericRange::NotSpecified,
+    };
+    ParameterDescriptor {
+      name: p.name,
+      param_type: p.r#type.into(),
+      description: p.description,
+      additional_constraints: p.additional_constraints,
+      read_only: p.read_only,
+      dynamic_typing: p.dynamic_typing,
+      range,
+    }
+  }
+}
+
+// QoS of the Parameter Services, both Servers and Clients
+pub(crate) fn parameter_service_qos() -> QosPolicies {
+  //TODO: Check QoS policies against ROS 2 specs or some refernce.
+  QosPolicyBuilder::new()
+    .reliability(policy::Reliability::Reliable {
+      max_blocking_time: rustdds::Duration::from_millis(100),
+    })
+    .history(policy::History::KeepLast { depth: 1 })
+    .build()
+}
+
+/// Validation callback for Parameter changes, see
+/// [`Node::add_on_set_parameters_callback`].
+///
+/// It gets all Parameters being set together, and rejects all of them by
+/// returning `Err`. It must not set Parameters itself.
+pub type OnSetParametersCallback = dyn Fn(&[Parameter]) -> SetParametersResult + Send + Sync;
+
+/// Identifies a callback added with [`Node::add_on_set_parameters_callback`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OnSetParametersCallbackHandle(u64);
+
+// Descriptors of declared Parameters and the on-set callbacks. This is shared
+// by a Node and its Spinner, as both can set Parameters.
+#[derive(Default)]
+pub(crate) struct ParameterDeclarations {
+  descriptors: BTreeMap<String, ParameterDescriptor>,
+  on_set_callbacks: Vec<(OnSetParametersCallbackHandle, Box<OnSetParametersCallback>)>,
+  next_callback_id: u64,
+}
+
+impl ParameterDeclarations {
+  // Fixes the type of a declared Parameter, unless dynamic typing is allowed.
+  pub(crate) fn declare(
+    &mut self,
+    name: &str,
+    default: &ParameterValue,
+    mut descriptor: ParameterDescriptor,
+  ) -> SetParametersResult {
+    descriptor.name = name.to_owned();
+    if !descriptor.dynamic_typing {
+      descriptor.param_type = default.to_parameter_type();
+    }
+    descriptor.validate(default)?;
+    self.descriptors.insert(name.to_owned(), descriptor);
+    Ok(())
+  }
+
+  pub(crate) fn undeclare(&mut self, name: &str) {
+    self.descriptors.remove(name);
+  }
+
+  // Descriptor of a declared Parameter, or one made up from its value
+  pub(crate) fn descriptor(
+    &self,
+    name: &str,
+    value: Option<&ParameterValue>,
+  ) -> ParameterDescriptor {
+    match (self.descriptors.get(name), value) {
+      (Some(descriptor), _) => descriptor.clone(),
+      (None, Some(value)) => ParameterDescriptor::from_value(name, value),
+      (None, None) => ParameterDescriptor::unknown(name),
+    }
+  }
+
+  // Checks new values against the descriptors, and then asks the callbacks.
+  pub(crate) fn check_set(&self, parameters: &[Parameter]) -> SetParametersResult {
+    for Parameter { name, value } in parameters {
+      if let Some(descriptor) = self.descriptors.get(name) {
+        if descriptor.read_only {
+          return Err(format!("Parameter '{name}' is read-only."));
+        }
+        descriptor.validate(value)?;
+      }
+    }
+    self
+      .on_set_callbacks
+      .iter()
+      .try_for_each(|(_, callback)| callback(parameters))
+  }
+
+  pub(crate) fn add_on_set_callback(
+    &mut self,
+    callback: Box<OnSetParametersCallback>,
+  ) -> OnSetParametersCallbackHandle {
+    let handle = OnSetParametersCallbackHandle(self.next_callback_id);
+    self.next_callback_id += 1;
+    self.on_set_callbacks.push((handle, callback));
+    handle
+  }
+
+  pub(crate) fn remove_on_set_callback(&mut self, handle: OnSetParametersCallbackHandle) -> bool {
+    let count = self.on_set_callbacks.len();
+    self.on_set_callbacks.retain(|(h, _)| *h != handle);
+    self.on_set_callbacks.len() < count
+  }
+}
+
+/// Item of [`ParameterClient::monitor`]
+#[derive(Debug, Clone)]
+pub enum ParameterUpdate {
+  /// All Parameters of the remote Node. This is always the first item.
+  Snapshot(Vec<Parameter>),
+  New(Parameter),
+  Changed(Parameter),
+  /// Name of the deleted Parameter
+  Deleted(String),
+}
+
+/// Access to the Parameters of another Node
+///
+/// This uses the Parameter Services of the remote Node, so it must have them
+/// enabled. Create with [`Node::create_parameter_client`].
+pub struct ParameterClient {
+  remote_node: String, // fully qualified name
+  get_parameters_client: Client<rcl_interfaces::GetParametersService>,
+  get_parameter_types_client: Client<rcl_interfaces::GetParameterTypesService>,
+  list_parameters_client: Client<rcl_interfaces::ListParametersService>,
+  set_parameters_client: Client<rcl_interfaces::SetParametersService>,
+  set_parameters_atomically_client: Client<rcl_interfaces::SetParametersAtomicallyService>,
+  describe_parameters_client: Client<rcl_interfaces::DescribeParametersService>,
+  // Created on the first monitor() call, so that ParameterClients that do not
+  // monitor do not receive the events of all Nodes.
+  parameter_events: OnceLock<Subscription<raw::ParameterEvent>>,
+}
+
+/// The name of [`ParameterClient`] in rclcpp
+pub type AsyncParametersClient = ParameterClient;
+
+impl ParameterClient {
+  pub(crate) fn new(my_node: &mut Node, remote_node: &NodeName) -> error::Result<Self> {
+    let remote_fqn = remote_node.fully_qualified_name();
+    let get_parameters_client =
+      parameter_service_client(my_node, &remote_fqn, "get_parameters", "GetParameters")?;
+    let get_parameter_types_client = parameter_service_client(
+      my_node,
+      &remote_fqn,
+      "get_parameter_types",
+      "GetParameterTypes",
+    )?;
+    let list_parameters_client =
+      parameter_service_client(my_node, &remote_fqn, "list_parameters", "ListParameters")?;
+    let set_parameters_client =
+      parameter_service_client(my_node, &remote_fqn, "set_parameters", "SetParameters")?;
+    let set_parameters_atomically_client = parameter_service_client(
+      my_node,
+      &remote_fqn,
+      "set_parameters_atomically",
+      "SetParametersAtomically",
+    )?;
+    let describe_parameters_client = parameter_service_client(
+      my_node,
+      &remote_fqn,
+      "describe_parameters",
+      "DescribeParameters",
+    )?;
+    Ok(ParameterClient {
+      remote_node: remote_fqn,
+      get_parameters_client,
+      get_parameter_types_client,
+      list_parameters_client,
+      set_parameters_client,
+      set_parameters_atomically_client,
+      describe_parameters_client,
+      parameter_events: OnceLock::new(),
+    })
+  }
+
+  /// Fully qualified name of the remote Node
+  pub fn remote_node(&self) -> &str {
+    &self.remote_node
+  }
+
+  /// Wait until the Parameter Services of the remote Node are connected.
+  /// Requests sent before that may be lost.
+  ///
+  /// `my_node` must be the Node that created this ParameterClient.
+  pub async fn wait_for_service(&self, my_node: &Node) {
+    futures::join!(
+      self.get_parameters_client.wait_for_service(my_node),
+      self.get_parameter_types_client.wait_for_service(my_node),
+      self.list_parameters_client.wait_for_service(my_node),
+      self.set_parameters_client.wait_for_service(my_node),
+      self
+        .set_parameters_atomically_client
+        .wait_for_service(my_node),
+      self.describe_parameters_client.wait_for_service(my_node),
+    );
+  }
+
+  /// Names of all Parameters of the remote Node
+  pub async fn list_parameters(&self) -> error::Result<Vec<String>> {
+    let response = self
+      .list_parameters_client
+      .async_call_service(rcl_interfaces::ListParametersRequest {
+        prefixes: vec![],
+        depth: 0, // unlimited
+      })
+      .await?;
+    Ok(response.result.names)
+  }
+
+  /// Values of the named Parameters. Undefined Parameters get value
+  /// [`ParameterValue::NotSet`].
+  pub async fn get_parameters(&self, names: &[String]) -> error::Result<Vec<Parameter>> {
+    let response = self
+      .get_parameters_client
+      .async_call_service(rcl_interfaces::GetParametersRequest {
+        names: names.to_vec(),
+      })
+      .await?;
+    Ok(
+      names
+        .iter()
+        .cloned()
+        .zip(response.values)
+        .map(|(name, value)| Parameter {
+          name,
+          value: value.into(),
+        })
+        .collect(),
+    )
+  }
+
+  /// Types of the named Parameters. Undefined Parameters have type
+  /// [`ParameterType::NotSet`].
+  pub async fn get_parameter_types(&self, names: &[String]) -> error::Result<Vec<ParameterType>> {
+    let response = self
+      .get_parameter_types_client
+      .async_call_service(rcl_interfaces::GetParameterTypesRequest {
+        names: names.to_vec(),
+      })
+      .await?;
+    Ok(
+      response
+        .values
+        .into_iter()
+        .map(ParameterType::from)
+        .collect(),
+    )
+  }
+
+  /// Descriptors of the named Parameters
+  pub async fn describe_parameters(
+    &self,
+    names: &[String],
+  ) -> error::Result<Vec<ParameterDescriptor>> {
+    let response = self
+      .describe_parameters_client
+      .async_call_service(rcl_interfaces::DescribeParametersRequest {
+        names: names.to_vec(),
+      })
+      .await?;
+    Ok(
+      response
+        .values
+        .into_iter()
+        .map(ParameterDescriptor::from)
+        .collect(),
+    )
+  }
+
+  /// Sets Parameters of the remote Node one by one.
+  ///
+  /// The outer `Result` tells if the Service call succeeded, and the inner
+  /// results, in the order of `parameters`, if the remote Node accepted each
+  /// value.
+  pub async fn set_parameters(
+    &self,
+    parameters: &[Parameter],
+  ) -> error::Result<Vec<SetParametersResult>> {
+    let response = self
+      .set_parameters_client
+      .async_call_service(rcl_interfaces::SetParametersRequest {
+        parameter: parameters
+          .iter()
+          .cloned()
+          .map(raw::Parameter::from)
+          .collect(),
+      })
+      .await?;
+    Ok(
+      response
+        .results
+        .into_iter()
+        .map(SetParametersResult::from)
+        .collect(),
+    )
+  }
+
+  /// Sets either all or none of `parameters` on the remote Node.
+  pub async fn set_parameters_atomically(
+    &self,
+    parameters: &[Parameter],
+  ) -> error::Result<SetParametersResult> {
+    let response = self
+      .set_parameters_atomically_client
+      .async_call_service(rcl_interfaces::SetParametersRequest {
+        parameter: parameters
+          .iter()
+          .cloned()
+          .map(raw::Parameter::from)
+          .collect(),
+      })
+      .await?;
+    Ok(response.result.into())
+  }
+
+  /// Follow the Parameters of the remote Node.
+  ///
+  /// The first item is a [`ParameterUpdate::Snapshot`] of all current
+  /// Parameters, and the rest are changes from the `/parameter_events` Topic
+  /// after that. Changes that happen while the snapshot is fetched are
+  /// delivered after it, so applying the items in order always gives the
+  /// current state, although a change may also be included in the snapshot.
+  ///
+  /// The first call subscribes to `/parameter_events`. `my_node` must be the
+  /// Node that created this ParameterClient.
+  ///
+  /// Call [`Self::wait_for_service`] first. There must be a Spinner running
+  /// in the remote Node.
+  pub fn monitor(
+    &self,
+    my_node: &mut Node,
+  ) -> error::Result<impl Stream<Item = error::Result<ParameterUpdate>> + '_> {
+    if self.parameter_events.get().is_none() {
+      let parameter_events_topic = my_node.ros_context.get_parameter_events_topic();
+      let parameter_events = my_node.create_subscription(&parameter_events_topic, None)?;
+      let _ = self.parameter_events.set(parameter_events);
+    }
+    let parameter_events = self.parameter_events.get().unwrap();
+
+    // Events already received are covered by the snapshot.
+    while let Ok(Some(_)) = parameter_events.take() {}
+
+    let snapshot = stream::once(async move {
+      let names = self.list_parameters().await?;
+      let parameters = self.get_parameters(&names).await?;
+      Ok(ParameterUpdate::Snapshot(parameters))
+    });
+
+    let updates = parameter_events
+      .async_stream()
+      .filter_map(move |event| {
+        future::ready(match event {
+          Ok((event, _info)) if event.node == self.remote_node => Some(Ok(event)),
+          Ok(_) => None, // some other Node
+          Err(e) => Some(Err(e.into())),
+        })
+      })
+      .flat_map(|event| {
+        stream::iter(match event {
+          Ok(event) => event_to_updates(event).into_iter().map(Ok).collect(),
+          Err(e) => vec![Err(e)],
+        })
+      });
+
+    Ok(snapshot.chain(updates))
+  }
+
+  /// All Parameters of the remote Node as a ROS 2 parameters YAML document,
+  /// like `ros2 param dump` produces. See [`parameters_to_yaml`].
+  ///
+  /// Call [`Self::wait_for_service`] first.
+  pub async fn dump_yaml(&self) -> error::Result<String> {
+    let names = self.list_parameters().await?;
+    let parameters = self.get_parameters(&names).await?;
+    Ok(parameters_to_yaml(&self.remote_node, &parameters))
+  }
+}
+
+fn parameter_service_client<S>(
+  my_node: &mut Node,
+  remote_fqn: &str,
+  service: &str,
+  service_type: &str,
+) -> error::Result<Client<S>>
+where
+  S: Service + 'static,
+  S::Request: Clone,
+{
+  my_node.create_client(
+    ServiceMapping::Enhanced, // same as in Node's Servers
+    &Name::new(remote_fqn, service)?,
+    &ServiceTypeName::new("rcl_interfaces", service_type),
+    parameter_service_qos(),
+    parameter_service_qos(),
+  )
+}
+
+/// Parameter values from a ROS 2 parameters YAML document
+///
+/// The document has a section for each Node, or a set of Nodes, with the
+/// Parameters under `ros__parameters`:
+///
+/// ```yaml
+/// /**:
+///   ros__parameters:
+///     use_sim_time: true
+/// /robot/camera:
+///   ros__parameters:
+///     rate: 30
+///     qos:
+///       depth: 5 # Parameter "qos.depth"
+/// ```
+///
+/// Node names may use wildcards: `*` matches within one token of the name,
+/// and `**` matches any number of tokens. Namespaces may also be nested
+/// mappings, e.g. `robot:` with `camera:` under it. Names without a leading
+/// slash are in the root namespace.
+///
+/// See [`NodeOptions::parameter_file`](crate::NodeOptions::parameter_file).
+#[derive(Clone, Debug, Default)]
+pub struct ParameterFile {
+  // Node name pattern, as tokens, and its Parameters, in document order
+  sections: Vec<(Vec<String>, Vec<Parameter>)>,
+}
+
+impl ParameterFile {
+  /// Parse a parameters YAML document.
+  pub fn from_yaml(yaml: &str) -> io::Result<Self> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let document: serde_yaml::Value =
+      serde_yaml::from_str(yaml).map_err(|e| invalid(e.to_string()))?;
+    let mut sections = Vec::new();
+    collect_sections(&document, &mut vec![], &mut sections).map_err(invalid)?;
+    Ok(ParameterFile { sections })
+  }
+
+  /// Load a parameters YAML file.
+  pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+    Self::from_yaml(&fs::read_to_string(path)?)
+  }
+
+  /// The Parameters for Node `node_fqn`, from all matching sections
+  ///
+  /// If a Parameter is in several sections, the last one wins, and the result
+  /// has it only once.
+  pub fn parameters_for(&self, node_fqn: &str) -> Vec<Parameter> {
+    let node_tokens: Vec<&str> = node_fqn.split('/').filter(|t| !t.is_empty()).collect();
+    let mut parameters = BTreeMap::new();
+    for (pattern, section) in &self.sections {
+      if node_name_matches(pattern, &node_tokens) {
+        for p in section {
+          parameters.insert(p.name.clone(), p.value.clone());
+        }
+      }
+    }
+    parameters
+      .into_iter()
+      .map(|(name, value)| Parameter { name, value })
+      .collect()
+  }
+}
+
+// Walks the Node name keys down to "ros__parameters" sections.
+fn collect_sections(
+  value: &serde_yaml::Value,
+  path: &mut Vec<String>,
+  sections: &mut Vec<(Vec<String>, Vec<Parameter>)>,
+) -> Result<(), String> {
+  let mapping = value
+    .as_mapping()
+    .ok_or_else(|| format!("Expected a mapping under {:?}", path.join("/")))?;
+  for (key, value) in mapping {
+    let key = key
+      .as_str()
+      .ok_or_else(|| format!("Bad Node name {key:?}"))?;
+    if key == "ros__parameters" {
+      let mut parameters = Vec::new();
+      collect_parameters(value, "", &mut parameters)?;
+      sections.push((path.clone(), parameters));
+    } else {
+      let depth = path.len();
+      path.extend(key.split('/').filter(|t| !t.is_empty()).map(String::from));
+      collect_sections(value, path, sections)?;
+      path.truncate(depth);
+    }
+  }
+  Ok(())
+}
+
+// Flattens nested mappings of Parameters to dotted names.
+fn collect_parameters(
+  value: &serde_yaml::Value,
+  prefix: &str,
+  parameters: &mut Vec<Parameter>,
+) -> Result<(), String> {
+  use serde_yaml::Value;
+
+  let scalar = |v: &Value| match v {
+    Value::Bool(b) => Some(ParameterValue::Boolean(*b)),
+    Value::Number(n) => Some(match n.as_i64() {
+      Some(i) => ParameterValue::Integer(i),
+      None => ParameterValue::Double(n.as_f64().unwrap_or(f64::NAN)),
+    }),
+    Value::String(s) => Some(ParameterValue::String(s.clone())),
+    _ => None,
+  };
+
+  match value {
+    Value::Mapping(mapping) => {
+      for (key, value) in mapping {
+        let key = key
+          .as_str()
+          .ok_or_else(|| format!("Bad Parameter name {key:?}"))?;
+        let name = if prefix.is_empty() {
+          key.to_owned()
+        } else {
+          prefix.to_owned() + "." + key
+        };
+        collect_parameters(value, &name, parameters)?;
+      }
+    }
+    Value::Sequence(items) => {
+      let items: Option<Vec<ParameterValue>> = items.iter().map(scalar).collect();
+      let value = match items.as_deref() {
+        Some([]) | None => None,
+        Some(items) => array_value(items),
+      }
+      .ok_or_else(|| format!("Parameter {prefix:?} must be a non-empty array of one type"))?;
+      parameters.push(Parameter {
+        name: prefix.to_owned(),
+        value,
+      });
+    }
+    other => {
+      let value = scalar(other).ok_or_else(|| format!("Bad value for Parameter {prefix:?}"))?;
+      parameters.push(Parameter {
+        name: prefix.to_owned(),
+        value,
+      });
+    }
+  }
+  Ok(())
+}
+
+// Parses a Parameter value in YAML syntax, as in `-p name:=value`.
+pub(crate) fn parse_parameter_value(yaml: &str) -> Result<ParameterValue, String> {
+  let value: serde_yaml::Value = serde_yaml::from_str(yaml).map_err(|e| e.to_string())?;
+  if value.is_mapping() {
+    return Err("Expected a scalar or an array".to_owned());
+  }
+  let mut parameters = Vec::new();
+  collect_parameters(&value, "", &mut parameters)?;
+  parameters
+    .pop()
+    .map(|p| p.value)
+    .ok_or_else(|| "Missing value".to_owned())
+}
+
+// Array of scalars, all of the same type. Integers are accepted in arrays of
+// Doubles.
+fn array_value(items: &[ParameterValue]) -> Option<ParameterValue> {
+  macro_rules! all {
+    ($variant:ident) => {
+      items
+        .iter()
+        .map(|v| match v {
+          ParameterValue::$variant(x) => Some(x.clone()),
+          _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+    };
+  }
+  if let Some(a) = all!(Boolean) {
+    Some(ParameterValue::BooleanArray(a))
+  } else if let Some(a) = all!(Integer) {
+    Some(ParameterValue::IntegerArray(a))
+  } else if let Some(a) = all!(String) {
+    Some(ParameterValue::StringArray(a))
+  } else {
+    items
+      .iter()
+      .map(|v| match v {
+        ParameterValue::Double(d) => Some(*d),
+        ParameterValue::Integer(i) => Some(*i as f64),
+        _ => None,
+      })
+      .collect::<Option<Vec<_>>>()
+      .map(ParameterValue::DoubleArray)
+  }
+}
+
+// Matches Node name tokens against a pattern, where "**" matches any number of
+// tokens, and "*" any part of a token.
+fn node_name_matches(pattern: &[String], name: &[&str]) -> bool {
+  match pattern.split_first() {
+    None => name.is_empty(),
+    Some((first, rest)) if first == "**" => {
+      (0..=name.len()).any(|skip| node_name_matches(rest, &name[skip..]))
+    }
+    Some((first, rest)) => match name.split_first() {
+      Some((token, name_rest)) => {
+        token_matches(first.as_bytes(), token.as_bytes()) && node_name_matches(rest, name_rest)
+      }
+      None => false,
+    },
+  }
+}
+
+fn token_matches(pattern: &[u8], token: &[u8]) -> bool {
+  match pattern.split_first() {
+    None => token.is_empty(),
+    Some((b'*', rest)) => (0..=token.len()).any(|skip| token_matches(rest, &token[skip..])),
+    Some((c, rest)) => token.first() == Some(c) && token_matches(rest, &token[1..]),
+  }
+}
+
+/// Formats Parameters of Node `node_fqn` as a ROS 2 parameters YAML document.
+///
+/// The Parameters are under `node_fqn` and `ros__parameters`, and dotted names
+/// are nested, e.g. `qos.depth` becomes `depth` under `qos`. Parameters that
+/// are [`ParameterValue::NotSet`] are left out.
+pub fn parameters_to_yaml(node_fqn: &str, parameters: &[Parameter]) -> String {
+  use serde_yaml::{Mapping, Value};
+
+  let mut sorted: Vec<&Parameter> = parameters.iter().collect();
+  sorted.sort_by(|a, b| a.name.cmp(&b.name));
+
+  let mut root = Mapping::new();
+  for p in sorted {
+    let value = match &p.value {
+      ParameterValue::NotSet => continue,
+      ParameterValue::Boolean(b) => Value::from(*b),
+      ParameterValue::Integer(i) => Value::from(*i),
+      ParameterValue::Double(d) => Value::from(*d),
+      ParameterValue::String(s) => Value::from(s.as_str()),
+      ParameterValue::ByteArray(a) => Value::from(a.clone()),
+      ParameterValue::BooleanArray(a) => Value::from(a.clone()),
+      ParameterValue::IntegerArray(a) => Value::from(a.clone()),
+      ParameterValue::DoubleArray(a) => Value::from(a.clone()),
+      ParameterValue::StringArray(a) => Value::from(a.clone()),
+    };
+    // Walk down the dotted name, creating Mappings as needed. If a Parameter
+    // name is a prefix of another, the longer one is kept flat.
+    let mut tokens: Vec<&str> = p.name.split('.').collect();
+    let mut map = &mut root;
+    while tokens.len() > 1 {
+      let key = Value::from(tokens[0]);
+      if !map.get(&key).is_none_or(Value::is_mapping) {
+        break;
+      }
+      map = map
+        .entry(key)
+        .or_insert_with(|| Value::Mapping(Mapping::new()))
+        .as_mapping_mut()
+        .unwrap(); // checked above
+      tokens.remove(0);
+    }
+    map.insert(Value::from(tokens.join(".")), value);
+  }
+
+  let mut ros_parameters = Mapping::new();
+  ros_parameters.insert(Value::from("ros__parameters"), Value::Mapping(root));
+  let mut document = Mapping::new();
+  document.insert(Value::from(node_fqn), Value::Mapping(ros_parameters));
+  // Serializing a Mapping of plain values cannot fail.
+  serde_yaml::to_string(&document).unwrap()
+}
+
+// Answers a ListParameters request the way rclcpp does: A name matches a
+// prefix if it is the prefix, or continues it after a dot. `depth` limits how
+// many dots may follow the prefix, zero meaning no limit. The result also has
+// the distinct prefixes of the listed names, i.e. the parts before their last
+// dots.
+pub(crate) fn list_parameters<'a>(
+  names: impl Iterator<Item = &'a String>,
+  prefixes: &[String],
+  depth: u64,
+) -> rcl_interfaces::ListParametersResult {
+  let dots = |s: &str| s.matches('.').count() as u64;
+  let within_depth = |s: &str| depth == 0 || dots(s) < depth;
+  let mut result = rcl_interfaces::ListParametersResult {
+    names: vec![],
+    prefixes: vec![],
+  };
+  for name in names {
+    let listed = if prefixes.is_empty() {
+      within_depth(name)
+    } else {
+      prefixes.iter().any(|prefix| {
+        name == prefix
+          || (name.starts_with(&(prefix.clone() + ".")) && within_depth(&name[prefix.len()..]))
+      })
+    };
+    if listed {
+      if let Some((prefix, _)) = name.rsplit_once('.') {
+        if !result.prefixes.iter().any(|p| p == prefix) {
+          result.prefixes.push(prefix.to_owned());
+        }
+      }
+      result.names.push(name.clone());
+    }
+  }
+  result
+}
+
+fn event_to_updates(event: raw::ParameterEvent) -> Vec<ParameterUpdate> {
+  let new = event
+    .new_parameters
+    .into_iter()
+    .map(|p| ParameterUpdate::New(p.into()));
+  let changed = event
+    .changed_parameters
+    .into_iter()
+    .map(|p| ParameterUpdate::Changed(p.into()));
+  let deleted = event
+    .deleted_parameters
+    .into_iter()
+    .map(|p| ParameterUpdate::Deleted(p.name));
+  new.chain(changed).chain(deleted).collect()
+}
+
 /// Raw, ROS2-compatible Parameters for sending over the wire.
 /// Not for use in a Rust application.
 pub mod raw {
-  use rustdds::*;
   use serde::{Deserialize, Serialize};
 
+  use crate::builtin_interfaces::Time;
+
   /// ROS2 [ParameterEvent](https://github.com/ros2/rcl_interfaces/blob/master/rcl_interfaces/msg/ParameterEvent.msg)
   #[derive(Debug, Clone, Serialize, Deserialize)]
   pub struct ParameterEvent {
-    pub timestamp: Timestamp,
+    pub timestamp: Time,
     // fully qualified path
     pub node: String,
     pub new_parameters: Vec<Parameter>,
@@ -380,3 +1287,218 @@ pub mod raw {
     pub step: f64,
   }
 }
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn yaml_dump() {
+    let p = |name: &str, value| Parameter {
+      name: name.to_string(),
+      value,
+    };
+    let yaml = parameters_to_yaml(
+      "/turtlesim",
+      &[
+        p("use_sim_time", ParameterValue::Boolean(false)),
+        p("qos.depth", ParameterValue::Integer(10)),
+        p("background_b", ParameterValue::Integer(255)),
+        p("unset", ParameterValue::NotSet),
+        p("qos.reliable", ParameterValue::Boolean(true)),
+        p("scale", ParameterValue::Double(1.0)),
+        p(
+          "frames",
+          ParameterValue::StringArray(vec!["map".to_string()]),
+        ),
+      ],
+    );
+    assert_eq!(
+      yaml,
+      "/turtlesim:
+  ros__parameters:
+    background_b: 255
+    frames:
+    - map
+    qos:
+      depth: 10
+      reliable: true
+    scale: 1.0
+    use_sim_time: false
+"
+    );
+  }
+
+  #[test]
+  fn parameter_file() {
+    let file = ParameterFile::from_yaml(
+      "
+/**:
+  ros__parameters:
+    use_sim_time: true
+    rate: 1
+/robot/camera:
+  ros__parameters:
+    rate: 30
+    qos:
+      depth: 5
+    frames: [map, odom]
+    gains: [1, 2.5]
+robot:
+  '*':
+    ros__parameters:
+      in_robot: true
+/robot/**/lidar_*:
+  ros__parameters:
+    lidar: true
+",
+    )
+    .unwrap();
+    let names_values = |fqn| {
+      file
+        .parameters_for(fqn)
+        .into_iter()
+        .map(|p| format!("{}={:?}", p.name, p.value))
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+      names_values("/robot/camera"),
+      vec![
+        "frames=StringArray([\"map\", \"odom\"])",
+        "gains=DoubleArray([1.0, 2.5])",
+        "in_robot=Boolean(true)",
+        "qos.depth=Integer(5)",
+        "rate=Integer(30)",
+        "use_sim_time=Boolean(true)",
+      ]
+    );
+    assert_eq!(
+      names_values("/robot/front/lidar_left"),
+      vec![
+        "lidar=Boolean(true)",
+        "rate=Integer(1)",
+        "use_sim_time=Boolean(true)"
+      ]
+    );
+    assert_eq!(
+      names_values("/other"),
+      vec!["rate=Integer(1)", "use_sim_time=Boolean(true)"]
+    );
+
+    assert!(ParameterFile::from_yaml("/n:\n  ros__parameters:\n    a: [1, x]\n").is_err());
+    assert!(ParameterFile::from_yaml("/n:\n  ros__parameters:\n    a: []\n").is_err());
+  }
+
+  #[test]
+  fn descriptor_validation() {
+    let int_range = ParameterDescriptor {
+      name: "i".to_string(),
+      param_type: ParameterType::Integer,
+      range: NumericRange::IntegerRange {
+        from_value: 0,
+        to_value: 10,
+        step: 3,
+      },
+      ..ParameterDescriptor::default()
+    };
+    assert!(int_range.validate(&ParameterValue::Integer(6)).is_ok());
+    assert!(int_range.validate(&ParameterValue::Integer(10)).is_ok()); // end is allowed
+    assert!(int_range.validate(&ParameterValue::Integer(7)).is_err());
+    assert!(int_range.validate(&ParameterValue::Integer(12)).is_err());
+    assert!(int_range.validate(&ParameterValue::Double(6.0)).is_err());
+
+    let float_range = ParameterDescriptor {
+      name: "f".to_string(),
+      param_type: ParameterType::Double,
+      range: NumericRange::FloatingPointRange {
+        from_value: 0.0,
+        to_value: 1.0,
+        step: 0.1,
+      },
+      ..ParameterDescriptor::default()
+    };
+    assert!(float_range.validate(&ParameterValue::Double(0.3)).is_ok());
+    assert!(float_range.validate(&ParameterValue::Double(0.35)).is_err());
+    assert!(float_range.validate(&ParameterValue::Double(-0.1)).is_err());
+
+    let dynamic = ParameterDescriptor {
+      dynamic_typing: true,
+      ..int_range
+    };
+    assert!(dynamic
+      .validate(&ParameterValue::String("x".into()))
+      .is_ok());
+  }
+
+  #[test]
+  fn declarations() {
+    let mut declarations = ParameterDeclarations::default();
+    let read_only = ParameterDescriptor {
+      read_only: true,
+      ..ParameterDescriptor::default()
+    };
+    declarations
+      .declare("fixed", &ParameterValue::Integer(1), read_only)
+      .unwrap();
+    declarations
+      .declare(
+        "typed",
+        &ParameterValue::Integer(1),
+        ParameterDescriptor::default(),
+      )
+      .unwrap();
+    let p = |name: &str, value| Parameter {
+      name: name.to_string(),
+      value,
+    };
+
+    assert!(declarations.check_set(&[p("fixed", 2i64.into())]).is_err());
+    assert!(declarations.check_set(&[p("typed", 2.0.into())]).is_err());
+    assert!(declarations.check_set(&[p("typed", 2i64.into())]).is_ok());
+
+    let handle = declarations.add_on_set_callback(Box::new(|params| {
+      if params.iter().any(|p| p.name == "typed") {
+        Err("no".to_string())
+      } else {
+        Ok(())
+      }
+    }));
+    assert_eq!(
+      declarations.check_set(&[p("typed", 2i64.into())]),
+      Err("no".to_string())
+    );
+    assert!(declarations.remove_on_set_callback(handle));
+    assert!(!declarations.remove_on_set_callback(handle));
+    assert!(declarations.check_set(&[p("typed", 2i64.into())]).is_ok());
+  }
+
+  #[test]
+  fn list_by_prefix_and_depth() {
+    let names: Vec<String> = ["a", "a.b", "a.b.c", "ab", "x.y"]
+      .iter()
+      .map(|s| s.to_string())
+      .collect();
+    let list = |prefixes: &[&str], depth| {
+      let prefixes: Vec<String> = prefixes.iter().map(|s| s.to_string()).collect();
+      let result = list_parameters(names.iter(), &prefixes, depth);
+      (result.names, result.prefixes)
+    };
+
+    assert_eq!(
+      list(&[], 0),
+      (
+        vec!["a", "a.b", "a.b.c", "ab", "x.y"]
+          .into_iter()
+          .map(String::from)
+          .collect(),
+        vec!["a".to_string(), "a.b".to_string(), "x".to_string()]
+      )
+    );
+    assert_eq!(list(&[], 1).0, vec!["a", "ab"]);
+    assert_eq!(list(&["a"], 0).0, vec!["a", "a.b", "a.b.c"]);
+    assert_eq!(list(&["a"], 1).0, vec!["a"]);
+    assert_eq!(list(&["a"], 2).0, vec!["a", "a.b"]);
+    assert_eq!(list(&["a.b", "x"], 0).0, vec!["a.b", "a.b.c", "x.y"]);
+  }
+}
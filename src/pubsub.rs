@@ -1,36 +1,210 @@
-use std::{io, marker::PhantomData};
+use std::{
+  collections::VecDeque,
+  io,
+  marker::PhantomData,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+  },
+  task::{Context, Poll as TaskPoll, Waker},
+  time::Duration,
+};
 
+use async_io::Timer;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
 use mio::{Evented, Poll, PollOpt, Ready, Token};
 use futures::{
+  future::{self, BoxFuture, LocalBoxFuture},
   pin_mut,
-  stream::{FusedStream, StreamExt},
-  Future,
+  stream::{self, FusedStream, LocalBoxStream, Stream, StreamExt},
+  Future, FutureExt, TryFutureExt, TryStreamExt,
 };
 use rustdds::{
-  dds::{ReadError, ReadResult, WriteResult},
-  serialization::CdrDeserializeSeedDecoder,
+  dds::{ReadError, ReadResult, WriteError, WriteResult},
   *,
 };
-use serde::{de::DeserializeOwned, Serialize};
+use serde::de::DeserializeOwned;
 
-use super::{gid::Gid, message_info::MessageInfo, node::Node};
+use super::{
+  cdr_adapter::{CdrAdapter, CdrDecoder},
+  clock::Clock,
+  error::{Error, WaitTimeout},
+  filters,
+  gid::Gid,
+  intra_process::{IntraProcessDelivery, IntraProcessMessage},
+  message_info::MessageInfo,
+  node::{EndpointRegistration, Node},
+  topic_statistics::StatisticsCollector,
+};
 
 /// A ROS2 Publisher
 ///
 /// Corresponds to a simplified [`DataWriter`](rustdds::no_key::DataWriter)in
 /// DDS
-pub struct Publisher<M: Serialize> {
-  datawriter: no_key::DataWriterCdr<M>,
+///
+/// `SA` encodes the messages, see
+/// [`Node::create_publisher_with_adapter`].
+pub struct Publisher<M, SA: no_key::SerializerAdapter<M> = CDRSerializerAdapter<M>> {
+  datawriter: no_key::DataWriter<M, SA>,
+  // Sequence numbers from publish_tracked(), waiting for ack_stream()
+  tracked: (async_channel::Sender<i64>, async_channel::Receiver<i64>),
+  intra_process: Option<Box<dyn IntraProcessDelivery<M>>>,
+  // Removes this from ROS Discovery when dropped
+  registration: Option<EndpointRegistration>,
+  // Source of source timestamps. System time, if not set.
+  clock: Option<Clock>,
+}
+
+/// Delivery acknowledgment of a message published with
+/// [`Publisher::publish_tracked`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AckEvent {
+  /// As returned by [`Publisher::publish_tracked`]
+  pub sequence_number: i64,
+  /// How many matched reliable Subscriptions have acknowledged the message.
+  /// Zero means that nobody is known to have received it.
+  pub acknowledged_by: usize,
+}
+
+/// A QoS event of a [`Publisher`] or [`Subscription`]. See
+/// [`Publisher::qos_event_stream`] and [`Subscription::qos_event_stream`].
+///
+/// `total` is the number of such events so far, and `change` how many
+/// happened since the previous report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QosEvent {
+  /// A message was not published or received within the Deadline QoS period.
+  DeadlineMissed { total: i32, change: i32 },
+  /// For a Publisher: it did not assert its liveliness in time. For a
+  /// Subscription: matched Publishers were lost, `total` counts those not
+  /// alive now.
+  LivelinessLost { total: i32, change: i32 },
+  /// A Subscription or Publisher on the Topic has QoS incompatible with
+  /// ours, so they do not communicate.
+  IncompatibleQos {
+    total: i32,
+    change: i32,
+    /// The last incompatible policy
+    policy: qos::QosPolicyId,
+    /// The other side
+    remote: GUID,
+  },
+  /// Messages were lost on the way to a Subscription.
+  SampleLost { total: i32, change: i32 },
+}
+
+impl QosEvent {
+  fn from_writer_status(status: DataWriterStatus) -> Option<Self> {
+    match status {
+      DataWriterStatus::OfferedDeadlineMissed { count } => Some(QosEvent::DeadlineMissed {
+        total: count.count(),
+        change: count.count_change(),
+      }),
+      DataWriterStatus::LivelinessLost { count } => Some(QosEvent::LivelinessLost {
+        total: count.count(),
+        change: count.count_change(),
+      }),
+      DataWriterStatus::OfferedIncompatibleQos {
+        count,
+        last_policy_id,
+        reader,
+        ..
+      } => Some(QosEvent::IncompatibleQos {
+        total: count.count(),
+        change: count.count_change(),
+        policy: last_policy_id,
+        remote: reader,
+      }),
+      DataWriterStatus::PublicationMatched { .. } => None,
+    }
+  }
+
+  fn from_reader_status(status: DataReaderStatus) -> Option<Self> {
+    match status {
+      DataReaderStatus::RequestedDeadlineMissed { count } => Some(QosEvent::DeadlineMissed {
+        total: count.count(),
+        change: count.count_change(),
+      }),
+      // Publishers becoming alive are not violations.
+      DataReaderStatus::LivelinessChanged {
+        not_alive_total, ..
+      } if not_alive_total.count_change() > 0 => Some(QosEvent::LivelinessLost {
+        total: not_alive_total.count(),
+        change: not_alive_total.count_change(),
+      }),
+      DataReaderStatus::RequestedIncompatibleQos {
+        count,
+        last_policy_id,
+        writer,
+        ..
+      } => Some(QosEvent::IncompatibleQos {
+        total: count.count(),
+        change: count.count_change(),
+        policy: last_policy_id,
+        remote: writer,
+      }),
+      DataReaderStatus::SampleLost { count } => Some(QosEvent::SampleLost {
+        total: count.count(),
+        change: count.count_change(),
+      }),
+      _ => None,
+    }
+  }
 }
 
-impl<M: Serialize> Publisher<M> {
+impl<M, SA: no_key::SerializerAdapter<M>> Publisher<M, SA> {
+  /// How many messages published with [`Self::publish_tracked`] are kept
+  /// waiting for [`Self::ack_stream`]
+  pub const MAX_TRACKED: usize = 1024;
+
   // These must be created from Node
-  pub(crate) fn new(datawriter: no_key::DataWriterCdr<M>) -> Publisher<M> {
-    Publisher { datawriter }
+  pub(crate) fn new(datawriter: no_key::DataWriter<M, SA>) -> Publisher<M, SA> {
+    Publisher {
+      datawriter,
+      tracked: async_channel::bounded(Self::MAX_TRACKED),
+      intra_process: None,
+      registration: None,
+      clock: None,
+    }
+  }
+
+  pub(crate) fn set_intra_process(&mut self, delivery: Box<dyn IntraProcessDelivery<M>>) {
+    self.intra_process = Some(delivery);
+  }
+
+  pub(crate) fn set_registration(&mut self, registration: EndpointRegistration) {
+    self.registration = Some(registration);
+  }
+
+  /// Takes the source timestamps of published messages from `clock`, e.g.
+  /// from [`Node::get_clock`], so that they follow simulated time. By
+  /// default, they are system time.
+  pub fn set_clock(&mut self, clock: Clock) {
+    self.clock = Some(clock);
+  }
+
+  fn source_timestamp(&self) -> Timestamp {
+    self
+      .clock
+      .as_ref()
+      .map_or_else(Timestamp::now, |clock| clock.now().into())
+  }
+
+  // Delivers `message` in memory, if possible. Otherwise gives it back for
+  // DDS.
+  fn deliver_intra_process(&self, message: M) -> Result<(), M> {
+    match &self.intra_process {
+      Some(delivery) => delivery.deliver(message),
+      None => Err(message),
+    }
   }
 
   pub fn publish(&self, message: M) -> WriteResult<(), M> {
-    self.datawriter.write(message, Some(Timestamp::now()))
+    match self.deliver_intra_process(message) {
+      Ok(()) => Ok(()),
+      Err(message) => self.datawriter.write(message, Some(self.source_timestamp())),
+    }
   }
 
   // pub(crate) fn publish_with_options(
@@ -41,10 +215,78 @@ impl<M: Serialize> Publisher<M> {
   //   self.datawriter.write_with_options(message, wo)
   // }
 
+  /// Publishes `message`, and reports its delivery in [`Self::ack_stream`].
+  /// This always publishes over DDS, even with intra-process communication.
+  ///
+  /// Returns the sequence number of the message.
+  ///
+  /// At most [`Self::MAX_TRACKED`] messages wait to be reported. If nobody
+  /// reads the `ack_stream`, the oldest ones are dropped and never reported,
+  /// so that tracking does not grow memory use without bound.
+  pub fn publish_tracked(&self, message: M) -> WriteResult<i64, M> {
+    let sample_identity = self
+      .datawriter
+      .write_with_options(message, WriteOptions::from(Some(self.source_timestamp())))?;
+    let sequence_number = i64::from(sample_identity.sequence_number);
+    // Cannot be closed, because we hold both ends. Full drops the oldest.
+    let _ = self.tracked.0.force_send(sequence_number);
+    Ok(sequence_number)
+  }
+
+  /// Acknowledgments of messages published with [`Self::publish_tracked`],
+  /// in publishing order
+  ///
+  /// A message is acknowledged when all reliable Subscriptions matched at the
+  /// time have acknowledged it. This is meaningful only if this Publisher is
+  /// Reliable. If there is an error, the messages waiting for acknowledgment
+  /// are not reported.
+  ///
+  /// `my_node` must be the Node that created this Publisher, and its Spinner
+  /// must be running, or the acknowledging Subscriptions are not counted.
+  pub fn ack_stream<'a>(
+    &'a self,
+    my_node: &'a Node,
+  ) -> impl Stream<Item = WriteResult<AckEvent, ()>> + 'a {
+    stream::unfold(VecDeque::new(), move |mut acked| async move {
+      if acked.is_empty() {
+        let mut pending = vec![self.tracked.1.recv().await.ok()?];
+        while let Ok(sequence_number) = self.tracked.1.try_recv() {
+          pending.push(sequence_number);
+        }
+        // This covers everything written so far.
+        if let Err(e) = self.wait_for_acknowledgments().await {
+          return Some((Err(e), acked));
+        }
+        let acknowledged_by = if qos::HasQoSPolicy::qos(&self.datawriter).is_reliable() {
+          my_node.get_reliable_subscription_count(self.guid())
+        } else {
+          0
+        };
+        acked.extend(pending.into_iter().map(|sequence_number| AckEvent {
+          sequence_number,
+          acknowledged_by,
+        }));
+      }
+      acked.pop_front().map(|event| (Ok(event), acked))
+    })
+  }
+
   pub fn assert_liveliness(&self) -> WriteResult<(), ()> {
     self.datawriter.assert_liveliness()
   }
 
+  /// QoS events of this Publisher: missed deadlines, lost liveliness and
+  /// incompatible Subscriptions.
+  ///
+  /// The events are consumed by whoever reads them, so there should be only
+  /// one such stream at a time.
+  pub fn qos_event_stream(&self) -> impl FusedStream<Item = QosEvent> + '_ {
+    self
+      .datawriter
+      .as_async_status_stream()
+      .filter_map(|status| future::ready(QosEvent::from_writer_status(status)))
+  }
+
   pub fn guid(&self) -> rustdds::GUID {
     self.datawriter.guid()
   }
@@ -70,11 +312,94 @@ impl<M: Serialize> Publisher<M> {
     my_node.wait_for_reader(self.guid())
   }
 
+  /// Waits until at least `min_count` Subscriptions are matched, or until
+  /// `timeout` has passed. Returns the number of matched Subscriptions.
+  ///
+  /// This is useful e.g. in tests and startup sequences, which should not
+  /// publish before the expected Subscriptions are there.
+  ///
+  /// `my_node` must be the Node that created this Publisher, and it must have
+  /// a Spinner running.
+  pub fn wait_for_subscriptions(
+    &self,
+    my_node: &Node,
+    min_count: usize,
+    timeout: Duration,
+  ) -> impl Future<Output = Result<usize, WaitTimeout>> + Send {
+    my_node.wait_for_readers(self.guid(), min_count, timeout)
+  }
+
   pub async fn async_publish(&self, message: M) -> WriteResult<(), M> {
+    match self.deliver_intra_process(message) {
+      Ok(()) => Ok(()),
+      Err(message) => {
+        self
+          .datawriter
+          .async_write(message, Some(self.source_timestamp()))
+          .await
+      }
+    }
+  }
+
+  /// Publishes `message` and waits until all currently matched reliable
+  /// Subscriptions have acknowledged it, or until `timeout` has passed.
+  ///
+  /// Returns `true` if the message was acknowledged, and `false` on timeout.
+  /// This always publishes over DDS, even with intra-process communication.
+  /// If this Publisher is not Reliable, there are no acknowledgments, and the
+  /// result is `true` as soon as the message has been written.
+  pub async fn async_publish_acked(&self, message: M, timeout: Duration) -> WriteResult<bool, M> {
     self
       .datawriter
-      .async_write(message, Some(Timestamp::now()))
-      .await
+      .async_write(message, Some(self.source_timestamp()))
+      .await?;
+    futures::select! {
+      acked = FutureExt::fuse(self.wait_for_acknowledgments()) => {
+        acked.map_err(|e| WriteError::Internal {
+          reason: format!("Waiting for acknowledgments failed: {e}"),
+        })
+      }
+      _ = FutureExt::fuse(Timer::after(timeout)) => Ok(false),
+    }
+  }
+
+  // Waits until all matched reliable Subscriptions have acknowledged what we
+  // have published. There is no timeout.
+  pub(crate) async fn flush(&self) -> WriteResult<(), ()> {
+    self.wait_for_acknowledgments().await.map(|_| ())
+  }
+
+  async fn wait_for_acknowledgments(&self) -> WriteResult<bool, ()> {
+    // RustDDS returns Pending without registering the waker right after it
+    // has sent the wait command to the writer. Polling again registers it.
+    let mut wait = Box::pin(self.datawriter.async_wait_for_acknowledgments());
+    let mut first_poll = true;
+    future::poll_fn(|cx| {
+      let poll = wait.as_mut().poll(cx);
+      if poll.is_pending() && first_poll {
+        first_poll = false;
+        cx.waker().wake_by_ref();
+      }
+      poll
+    })
+    .await
+  }
+
+  /// Flushes pending messages and deletes this Publisher.
+  ///
+  /// This waits until all matched reliable Subscriptions have acknowledged
+  /// the published messages, which may take forever if one of them stops
+  /// responding. Add a timeout, if needed. Then the Publisher is removed from
+  /// DDS and ROS discovery before returning. Dropping the Publisher also
+  /// removes it, but does not flush.
+  ///
+  /// `my_node` must be the Node that created this Publisher.
+  pub async fn close(self, my_node: &mut Node) -> WriteResult<(), ()> {
+    self.flush().await?;
+    let gid = self.gid();
+    drop(self);
+    my_node.remove_writer(gid);
+    Ok(())
   }
 
   #[allow(dead_code)] // This is for async Service implementation. Remove this when it is implemented.
@@ -92,35 +417,321 @@ impl<M: Serialize> Publisher<M> {
 // ----------------------------------------------------
 // ----------------------------------------------------
 
+/// What a paused [`Subscription`] does with arriving messages
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PauseMode {
+  /// Leave messages in the DDS reader. The History QoS decides how many are
+  /// kept, and they are received after `resume()`.
+  Buffer,
+  /// Take and drop messages as they arrive.
+  Discard,
+}
+
+// Pause state shared by a Subscription and its async streams.
+struct PauseState {
+  mode: Mutex<Option<PauseMode>>,
+  // Tasks waiting for resume()
+  wakers: Mutex<Vec<Waker>>,
+}
+
+impl PauseState {
+  fn mode(&self) -> Option<PauseMode> {
+    *self.mode.lock().unwrap()
+  }
+
+  fn register(&self, waker: &Waker) {
+    let mut wakers = self.wakers.lock().unwrap();
+    if !wakers.iter().any(|w| w.will_wake(waker)) {
+      wakers.push(waker.clone());
+    }
+  }
+}
+
+/// What a [`Subscription`] queue does when a message arrives and the queue is
+/// full. See [`Subscription::with_queue`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QueueOverflow {
+  /// Drop the oldest queued message to make room.
+  DropOldest,
+  /// Drop the arriving message.
+  DropNewest,
+  /// Stop receiving until there is room. Messages then wait in the DDS reader,
+  /// where the History QoS decides how many are kept.
+  Block,
+}
+
+// Application-level queue of a Subscription, filled by a background task
+struct SubscriptionQueue<M> {
+  messages: async_channel::Receiver<ReadResult<(M, MessageInfo)>>,
+  dropped: Arc<AtomicU64>,
+  // Dropping this stops the task.
+  _stop: async_channel::Sender<()>,
+}
+
 /// A ROS2 Subscription
 ///
 /// Corresponds to a (simplified) [`DataReader`](rustdds::no_key::DataReader) in
 /// DDS
-pub struct Subscription<M> {
-  datareader: no_key::SimpleDataReaderCdr<M>,
+///
+/// `DA` decodes the messages, see
+/// [`Node::create_subscription_with_adapter`].
+pub struct Subscription<M, DA: no_key::DeserializerAdapter<M> = CdrAdapter<M>> {
+  datareader: Arc<no_key::SimpleDataReader<M, DA>>,
+  pause_state: PauseState,
+  // Messages from Publishers of the same Context, if intra-process
+  // communication is enabled
+  intra_process: Option<async_channel::Receiver<IntraProcessMessage<M>>>,
+  queue: Option<SubscriptionQueue<M>>,
+  statistics: Option<Arc<StatisticsCollector>>,
+  // Removes this from ROS Discovery when dropped
+  registration: Option<EndpointRegistration>,
 }
 
-impl<M> Subscription<M>
+impl<M, DA> Subscription<M, DA>
 where
   M: 'static,
+  DA: no_key::DeserializerAdapter<M> + 'static,
 {
   // These must be created from Node
-  pub(crate) fn new(datareader: no_key::SimpleDataReaderCdr<M>) -> Subscription<M> {
-    Subscription { datareader }
+  pub(crate) fn new(datareader: no_key::SimpleDataReader<M, DA>) -> Subscription<M, DA> {
+    Subscription {
+      datareader: Arc::new(datareader),
+      pause_state: PauseState {
+        mode: Mutex::new(None),
+        wakers: Mutex::new(Vec::new()),
+      },
+      intra_process: None,
+      queue: None,
+      statistics: None,
+      registration: None,
+    }
   }
 
-  pub fn take_seed<'de, S>(&self, seed: S) -> ReadResult<Option<(M, MessageInfo)>>
+  pub(crate) fn set_registration(&mut self, registration: EndpointRegistration) {
+    self.registration = Some(registration);
+  }
+
+  pub(crate) fn set_statistics(&mut self, collector: Arc<StatisticsCollector>) {
+    self.statistics = Some(collector);
+  }
+
+  // Records received messages for Topic Statistics, if enabled.
+  fn observed(
+    &self,
+    result: ReadResult<Option<(M, MessageInfo)>>,
+  ) -> ReadResult<Option<(M, MessageInfo)>> {
+    if let (Some(statistics), Ok(Some((_, info)))) = (&self.statistics, &result) {
+      statistics.record(info);
+    }
+    result
+  }
+
+  fn observed_stream<'a, S>(&'a self, inner: S) -> impl FusedStream<Item = S::Item> + 'a
   where
-    S: serde::de::DeserializeSeed<'de, Value = M> + Clone,
-    M: 'static,
+    S: FusedStream<Item = ReadResult<(M, MessageInfo)>> + 'a,
+  {
+    inner.inspect(move |result| {
+      if let (Some(statistics), Ok((_, info))) = (&self.statistics, result) {
+        statistics.record(info);
+      }
+    })
+  }
+
+  pub(crate) fn set_intra_process(
+    &mut self,
+    receiver: async_channel::Receiver<IntraProcessMessage<M>>,
+  ) {
+    self.intra_process = Some(receiver);
+  }
+
+  /// Stop receiving messages until [`resume`](Self::resume) is called.
+  ///
+  /// The DDS reader stays alive, so matching with Publishers continues as
+  /// usual. While paused, `take()` returns `Ok(None)` and async streams do
+  /// not yield. `mode` decides what happens to messages arriving meanwhile.
+  pub fn pause(&self, mode: PauseMode) {
+    *self.pause_state.mode.lock().unwrap() = Some(mode);
+  }
+
+  /// Continue receiving messages after [`pause`](Self::pause).
+  pub fn resume(&self) {
+    *self.pause_state.mode.lock().unwrap() = None;
+    for waker in self.pause_state.wakers.lock().unwrap().drain(..) {
+      waker.wake();
+    }
+  }
+
+  pub fn is_paused(&self) -> bool {
+    self.pause_state.mode().is_some()
+  }
+
+  // Wraps a stream of received messages so that it honours pause().
+  fn pausable<'a, S>(&'a self, inner: S) -> impl FusedStream<Item = S::Item> + 'a
+  where
+    S: Stream + 'a,
+  {
+    let mut inner = Box::pin(inner);
+    stream::poll_fn(move |cx: &mut Context<'_>| loop {
+      match self.pause_state.mode() {
+        None => return inner.as_mut().poll_next(cx),
+        Some(PauseMode::Buffer) => {
+          self.pause_state.register(cx.waker());
+          // resume() may have run before we registered.
+          if self.is_paused() {
+            return TaskPoll::Pending;
+          }
+        }
+        Some(PauseMode::Discard) => {
+          self.pause_state.register(cx.waker());
+          match inner.as_mut().poll_next(cx) {
+            TaskPoll::Ready(Some(_)) => {} // dropped
+            other => return other,
+          }
+        }
+      }
+    })
+    .fuse()
+  }
+
+  // Implements the pause for the non-async take functions. Returns true if
+  // the caller should not take anything. `take_one` is used to discard.
+  fn paused_take<T>(&self, mut take_one: impl FnMut() -> ReadResult<Option<T>>) -> bool {
+    match self.pause_state.mode() {
+      None => false,
+      Some(PauseMode::Buffer) => true,
+      Some(PauseMode::Discard) => {
+        while let Ok(Some(_)) = take_one() {}
+        if let Some(queue) = &self.intra_process {
+          while queue.try_recv().is_ok() {}
+        }
+        if let Some(queue) = &self.queue {
+          while queue.messages.try_recv().is_ok() {}
+        }
+        true
+      }
+    }
+  }
+
+  // Takes from the application-level queue, if there is one.
+  fn take_queued(&self) -> Option<ReadResult<Option<(M, MessageInfo)>>> {
+    self
+      .queue
+      .as_ref()
+      .map(|queue| queue.messages.try_recv().ok().transpose())
+  }
+
+  // Messages from the application-level queue, if there is one, otherwise
+  // `inner`.
+  fn queued_or<'a, S>(&'a self, inner: S) -> impl Stream<Item = ReadResult<(M, MessageInfo)>> + 'a
+  where
+    S: Stream<Item = ReadResult<(M, MessageInfo)>> + 'a,
+  {
+    match &self.queue {
+      Some(queue) => queue.messages.clone().left_stream(),
+      None => inner.right_stream(),
+    }
+  }
+
+  /// Number of messages dropped because the queue set with
+  /// [`with_queue`](Self::with_queue) was full. Always zero without a queue.
+  pub fn dropped_count(&self) -> u64 {
+    self
+      .queue
+      .as_ref()
+      .map_or(0, |queue| queue.dropped.load(Ordering::Relaxed))
+  }
+
+  // Takes a message delivered in memory, if any. `decoder` is for copying a
+  // shared message.
+  fn take_intra_process<Dec>(&self, decoder: Dec) -> ReadResult<Option<(M, MessageInfo)>>
+  where
+    Dec: no_key::Decode<M>,
+  {
+    match self.intra_process.as_ref().map(|queue| queue.try_recv()) {
+      Some(Ok(message)) => intra_process_value(message, decoder).map(Some),
+      _ => Ok(None),
+    }
+  }
+
+  // Merges messages delivered in memory into `inner`.
+  fn with_intra_process<'a, S, Dec>(
+    &'a self,
+    inner: S,
+    decoder: Dec,
+  ) -> impl Stream<Item = ReadResult<(M, MessageInfo)>> + 'a
+  where
+    S: Stream<Item = ReadResult<(M, MessageInfo)>> + 'a,
+    Dec: no_key::Decode<M> + Clone + 'a,
+  {
+    merge_intra_process(inner, self.intra_process.clone(), decoder)
+  }
+
+  // Takes with `decoder` from DDS, and decodes copies of intra-process
+  // messages, which are always CDR, with `copy_decoder`.
+  fn take_with<Dec, CopyDec>(
+    &self,
+    decoder: Dec,
+    copy_decoder: CopyDec,
+  ) -> ReadResult<Option<(M, MessageInfo)>>
+  where
+    Dec: no_key::Decode<DA::Decoded> + Clone,
+    CopyDec: no_key::Decode<M>,
   {
     self.datareader.drain_read_notifications();
-    let decoder = CdrDeserializeSeedDecoder::new(seed, PhantomData::<()>);
+    if self.paused_take(|| self.datareader.try_take_one_with(decoder.clone())) {
+      return Ok(None);
+    }
+    if let Some(result) = self.take_queued() {
+      return result;
+    }
+    if let Some(message) = self.take_intra_process(copy_decoder)? {
+      return Ok(Some(message));
+    }
     let ds: Option<no_key::DeserializedCacheChange<M>> =
       self.datareader.try_take_one_with(decoder)?;
     Ok(ds.map(dcc_to_value_and_messageinfo))
   }
 
+  // Messages from DDS decoded with `decoder`, merged with intra-process
+  // messages, as returned by the async stream functions.
+  fn stream_with<'a, Dec, CopyDec>(
+    &'a self,
+    decoder: Dec,
+    copy_decoder: CopyDec,
+  ) -> impl FusedStream<Item = ReadResult<(M, MessageInfo)>> + 'a
+  where
+    Dec: no_key::Decode<DA::Decoded> + Clone + 'a,
+    CopyDec: no_key::Decode<M> + Clone + 'a,
+  {
+    self.observed_stream(
+      self.pausable(
+        self.queued_or(
+          self.with_intra_process(
+            self
+              .datareader
+              .as_async_stream_with(decoder)
+              .map(|result| result.map(dcc_to_value_and_messageinfo)),
+            copy_decoder,
+          ),
+        ),
+      ),
+    )
+  }
+}
+
+impl<M> Subscription<M>
+where
+  M: 'static,
+{
+  pub fn take_seed<'de, S>(&self, seed: S) -> ReadResult<Option<(M, MessageInfo)>>
+  where
+    S: serde::de::DeserializeSeed<'de, Value = M> + Clone,
+    M: 'static,
+  {
+    let decoder = CdrDecoder::new(seed);
+    self.observed(self.take_with(decoder.clone(), decoder))
+  }
+
   // Returns an async Stream of messages with MessageInfo metadata
   pub fn async_stream_seed<'a, 'de, S>(
     &'a self,
@@ -130,27 +741,43 @@ where
     S: serde::de::DeserializeSeed<'de, Value = M> + Clone + 'a,
     M: 'static,
   {
-    let decoder = CdrDeserializeSeedDecoder::new(seed, PhantomData::<()>);
-    self
-      .datareader
-      .as_async_stream_with(decoder)
-      .map(|result| result.map(dcc_to_value_and_messageinfo))
+    let decoder = CdrDecoder::new(seed);
+    self.stream_with(decoder.clone(), decoder)
   }
 }
 
-impl<M: 'static + DeserializeOwned> Subscription<M> {
+impl<M, DA> Subscription<M, DA>
+where
+  M: 'static + DeserializeOwned,
+  DA: no_key::DefaultDecoder<M> + 'static,
+{
   pub fn take(&self) -> ReadResult<Option<(M, MessageInfo)>> {
-    self.datareader.drain_read_notifications();
-    let ds: Option<no_key::DeserializedCacheChange<M>> = self.datareader.try_take_one()?;
-    Ok(ds.map(dcc_to_value_and_messageinfo))
+    self.observed(self.take_with(DA::DECODER, default_decoder()))
+  }
+
+  /// Takes all messages that are available now, oldest first.
+  ///
+  /// This is meant for TransientLocal Subscriptions, e.g. to latched Topics:
+  /// after [`wait_for_publisher`](Self::wait_for_publisher), the samples
+  /// retained by the Publisher arrive as one batch, instead of being
+  /// interleaved with new messages in [`async_stream`](Self::async_stream).
+  /// Note that the history is sent by the Publisher after matching, so it may
+  /// still be on its way right after `wait_for_publisher` returns.
+  ///
+  /// If an error occurs, the messages taken so far are lost.
+  pub fn take_history(&self) -> ReadResult<Vec<(M, MessageInfo)>> {
+    let mut history = Vec::new();
+    while let Some(message) = self.take()? {
+      history.push(message);
+    }
+    Ok(history)
   }
 
   pub async fn async_take(&self) -> ReadResult<(M, MessageInfo)> {
-    let async_stream = self.datareader.as_async_stream();
+    let async_stream = self.async_stream();
     pin_mut!(async_stream);
     match async_stream.next().await {
-      Some(Err(e)) => Err(e),
-      Some(Ok(ds)) => Ok(dcc_to_value_and_messageinfo(ds)),
+      Some(result) => result,
       // Stream from SimpleDataReader is not supposed to ever end.
       None => {
         read_error_internal!("async_take(): SimpleDataReader value stream unexpectedly ended!")
@@ -160,21 +787,74 @@ impl<M: 'static + DeserializeOwned> Subscription<M> {
 
   // Returns an async Stream of messages with MessageInfo metadata
   pub fn async_stream(&self) -> impl FusedStream<Item = ReadResult<(M, MessageInfo)>> + '_ {
-    self
-      .datareader
-      .as_async_stream()
-      .map(|result| result.map(dcc_to_value_and_messageinfo))
+    self.stream_with(DA::DECODER, default_decoder())
+  }
+
+  /// Runs `callback` on each received message. This returns only if the
+  /// stream of messages ends, so it should be run as a task.
+  ///
+  /// Receive errors are logged and skipped. See also
+  /// [`Node::register_callback`], which runs this in the Spinner.
+  pub async fn for_each<F>(&self, mut callback: F)
+  where
+    F: FnMut(M, MessageInfo),
+  {
+    let messages = self.async_stream();
+    pin_mut!(messages);
+    while let Some(result) = messages.next().await {
+      match result {
+        Ok((message, info)) => callback(message, info),
+        Err(e) => warn!("Subscription {:?} receive failed: {e:?}", self.guid()),
+      }
+    }
+  }
+
+  /// Like [`async_stream`](Self::async_stream), but messages are held for up
+  /// to `window` and released in source timestamp order. This is meant for
+  /// best-effort topics, where UDP may reorder messages.
+  ///
+  /// See [`reorder_by_source_timestamp`](crate::filters::reorder_by_source_timestamp).
+  pub fn async_stream_reordered(
+    &self,
+    window: Duration,
+  ) -> impl Stream<Item = ReadResult<(M, MessageInfo)>> + '_ {
+    filters::reorder_by_source_timestamp(self.async_stream(), window)
   }
 }
 
-impl<M> Subscription<M>
+impl<M, DA> Subscription<M, DA>
 where
   M: 'static,
+  DA: no_key::DeserializerAdapter<M> + 'static,
 {
   pub fn guid(&self) -> rustdds::GUID {
     self.datareader.guid()
   }
 
+  /// QoS events of this Subscription: missed deadlines, lost Publishers,
+  /// incompatible Publishers and lost messages.
+  ///
+  /// The events are consumed by whoever reads them, so there should be only
+  /// one such stream at a time.
+  pub fn qos_event_stream(&self) -> impl FusedStream<Item = QosEvent> + '_ {
+    self
+      .datareader
+      .as_async_status_stream()
+      .filter_map(|status| future::ready(QosEvent::from_reader_status(status)))
+  }
+
+  /// Deletes this Subscription from DDS and ROS discovery before returning.
+  ///
+  /// Dropping the Subscription does the same. Unlike the `close` of a
+  /// Publisher, this is not async, as a Subscription has nothing to flush.
+  ///
+  /// `my_node` must be the Node that created this Subscription.
+  pub fn close(self, my_node: &mut Node) {
+    let gid = self.gid();
+    drop(self);
+    my_node.remove_reader(gid);
+  }
+
   pub fn gid(&self) -> Gid {
     self.guid().into()
   }
@@ -197,6 +877,144 @@ where
   }
 }
 
+impl<M, DA> Subscription<M, DA>
+where
+  M: 'static + DeserializeOwned + Send + Sync,
+  DA: no_key::DefaultDecoder<M> + Send + Sync + 'static,
+  DA::Decoder: Send,
+{
+  /// Receive messages into an application-level queue of at most `capacity`
+  /// messages, independent of the DDS History QoS. `overflow` decides what
+  /// happens when the queue is full, and
+  /// [`dropped_count`](Self::dropped_count) counts the dropped messages.
+  ///
+  /// The queue is filled by a background task, which is given to `spawn`.
+  /// It should start the task in an async executor, e.g.
+  /// `|task| { smol::spawn(task).detach(); }`. The task ends when the
+  /// Subscription is dropped. Messages are then received from the queue, so
+  /// that `Evented` readiness of the Subscription is no longer meaningful.
+  ///
+  /// # Panics
+  ///
+  /// If `capacity` is zero.
+  pub fn with_queue<F>(mut self, capacity: usize, overflow: QueueOverflow, spawn: F) -> Self
+  where
+    F: FnOnce(BoxFuture<'static, ()>),
+  {
+    assert!(capacity > 0, "Subscription queue needs room for a message");
+    let (sender, messages) = async_channel::bounded(capacity);
+    let (stop, stop_receiver) = async_channel::bounded(1);
+    let dropped = Arc::new(AtomicU64::new(0));
+    let datareader = Arc::clone(&self.datareader);
+    let intra_process = self.intra_process.take();
+    let task_dropped = Arc::clone(&dropped);
+    spawn(Box::pin(async move {
+      let received = merge_intra_process(
+        datareader
+          .as_async_stream()
+          .map(|result| result.map(dcc_to_value_and_messageinfo)),
+        intra_process,
+        default_decoder(),
+      );
+      let filling = fill_queue(received, &sender, overflow, &task_dropped).fuse();
+      let stopped = stop_receiver.recv().fuse();
+      pin_mut!(filling, stopped);
+      futures::select! {
+        _ = filling => {}
+        _ = stopped => {}
+      }
+    }));
+    self.queue = Some(SubscriptionQueue {
+      messages,
+      dropped,
+      _stop: stop,
+    });
+    self
+  }
+}
+
+// Moves messages from `received` to `queue`, until either one ends.
+async fn fill_queue<T, S>(
+  received: S,
+  queue: &async_channel::Sender<T>,
+  overflow: QueueOverflow,
+  dropped: &AtomicU64,
+) where
+  S: Stream<Item = T>,
+{
+  pin_mut!(received);
+  while let Some(item) = received.next().await {
+    let is_open = match overflow {
+      QueueOverflow::DropOldest => match queue.force_send(item) {
+        Ok(Some(_oldest)) => {
+          dropped.fetch_add(1, Ordering::Relaxed);
+          true
+        }
+        Ok(None) => true,
+        Err(_) => false,
+      },
+      QueueOverflow::DropNewest => match queue.try_send(item) {
+        Err(async_channel::TrySendError::Full(_)) => {
+          dropped.fetch_add(1, Ordering::Relaxed);
+          true
+        }
+        result => result.is_ok(),
+      },
+      QueueOverflow::Block => queue.send(item).await.is_ok(),
+    };
+    if !is_open {
+      return;
+    }
+  }
+}
+
+// Merges messages delivered in memory into `inner`.
+fn merge_intra_process<'a, M, S, Dec>(
+  inner: S,
+  intra_process: Option<async_channel::Receiver<IntraProcessMessage<M>>>,
+  decoder: Dec,
+) -> impl Stream<Item = ReadResult<(M, MessageInfo)>> + 'a
+where
+  M: 'a,
+  S: Stream<Item = ReadResult<(M, MessageInfo)>> + 'a,
+  Dec: no_key::Decode<M> + Clone + 'a,
+{
+  let intra_process = match intra_process {
+    Some(queue) => queue
+      .map(move |message| intra_process_value(message, decoder.clone()))
+      .left_stream(),
+    None => stream::empty().right_stream(),
+  };
+  stream::select(inner, intra_process)
+}
+
+// Decoder for copies of intra-process messages of Subscriptions without a
+// seed
+fn default_decoder<M: DeserializeOwned>() -> CdrDecoder<PhantomData<M>> {
+  CdrDecoder::new(PhantomData)
+}
+
+fn intra_process_value<M, Dec>(
+  message: IntraProcessMessage<M>,
+  decoder: Dec,
+) -> ReadResult<(M, MessageInfo)>
+where
+  Dec: no_key::Decode<M>,
+{
+  match message.into_parts() {
+    (Ok(message), info) => Ok((message, info)),
+    (Err(Some(cdr)), info) => decoder
+      .decode_bytes(&cdr, RepresentationIdentifier::CDR_LE)
+      .map(|message| (message, info))
+      .map_err(|e| ReadError::Deserialization {
+        reason: e.to_string(),
+      }),
+    (Err(None), _) => Err(ReadError::Deserialization {
+      reason: "Cannot copy intra-process message".to_string(),
+    }),
+  }
+}
+
 // helper
 #[inline]
 fn dcc_to_value_and_messageinfo<M>(dcc: no_key::DeserializedCacheChange<M>) -> (M, MessageInfo) {
@@ -204,9 +1022,95 @@ fn dcc_to_value_and_messageinfo<M>(dcc: no_key::DeserializedCacheChange<M>) -> (
   (dcc.into_value(), mi)
 }
 
-impl<D> Evented for Subscription<D>
+/// Receiving side of a Topic, independent of the middleware
+///
+/// [`Subscription`] implements this. Code written against this trait, e.g.
+/// `&dyn Subscriber<M>`, does not depend on Subscriptions being DDS
+/// DataReaders, nor on a DDS [`Node`].
+//
+// The traits are here next to the structs that implement them, as pubsub.rs
+// is a single module, rather than in a pubsub/ directory with a file per
+// trait.
+pub trait Subscriber<M> {
+  /// Take the next message, if one is available now.
+  fn take(&self) -> Result<Option<(M, MessageInfo)>, Error>;
+
+  /// Wait for the next message.
+  fn async_take(&self) -> LocalBoxFuture<'_, Result<(M, MessageInfo), Error>>;
+
+  /// Messages as they arrive
+  fn async_stream(&self) -> LocalBoxStream<'_, Result<(M, MessageInfo), Error>>;
+
+  fn gid(&self) -> Gid;
+
+  /// Count of currently matched Publishers
+  fn get_publisher_count(&self) -> usize;
+}
+
+impl<M, DA> Subscriber<M> for Subscription<M, DA>
+where
+  M: 'static + DeserializeOwned,
+  DA: no_key::DefaultDecoder<M> + 'static,
+{
+  fn take(&self) -> Result<Option<(M, MessageInfo)>, Error> {
+    Ok(Subscription::take(self)?)
+  }
+
+  fn async_take(&self) -> LocalBoxFuture<'_, Result<(M, MessageInfo), Error>> {
+    Subscription::async_take(self).err_into().boxed_local()
+  }
+
+  fn async_stream(&self) -> LocalBoxStream<'_, Result<(M, MessageInfo), Error>> {
+    Subscription::async_stream(self).err_into().boxed_local()
+  }
+
+  fn gid(&self) -> Gid {
+    Subscription::gid(self)
+  }
+
+  /// Zero for a Subscription not created by a Node
+  fn get_publisher_count(&self) -> usize {
+    self
+      .registration
+      .as_ref()
+      .map_or(0, |r| r.publisher_count(self.guid()))
+  }
+}
+
+/// Sending side of a Topic, independent of the middleware
+///
+/// This is the counterpart of [`Subscriber`]. It is object-safe, so
+/// Publishers of different transports can be kept together, e.g. in a
+/// `Vec<Box<dyn AnyPublisher<M>>>`.
+pub trait AnyPublisher<M> {
+  fn publish(&self, message: M) -> WriteResult<(), M>;
+
+  fn async_publish(&self, message: M) -> LocalBoxFuture<'_, WriteResult<(), M>>;
+
+  fn gid(&self) -> Gid;
+}
+
+impl<M, SA> AnyPublisher<M> for Publisher<M, SA>
+where
+  M: 'static,
+  SA: no_key::SerializerAdapter<M>,
+{
+  fn publish(&self, message: M) -> WriteResult<(), M> {
+    Publisher::publish(self, message)
+  }
+
+  fn async_publish(&self, message: M) -> LocalBoxFuture<'_, WriteResult<(), M>> {
+    Publisher::async_publish(self, message).boxed_local()
+  }
+
+  fn gid(&self) -> Gid {
+    Publisher::gid(self)
+  }
+}
+
+impl<D, DA> Evented for Subscription<D, DA>
 where
-  D: DeserializeOwned,
+  DA: no_key::DeserializerAdapter<D>,
 {
   // We just delegate all the operations to datareader, since it
   // already implements Evented
@@ -228,3 +1132,115 @@ where
     self.datareader.deregister(poll)
   }
 }
+
+/// Change in the Publishers or Subscriptions of a Topic in the ROS 2 Graph.
+///
+/// Produced by [`Node::watch_topic`]. The counts are the numbers of
+/// Publishers or Subscriptions after this change.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TopicEvent {
+  PublisherAppeared {
+    guid: GUID,
+    publisher_count: usize,
+  },
+  PublisherLost {
+    guid: GUID,
+    publisher_count: usize,
+  },
+  SubscriptionAppeared {
+    guid: GUID,
+    subscription_count: usize,
+  },
+  SubscriptionLost {
+    guid: GUID,
+    subscription_count: usize,
+  },
+}
+
+#[cfg(test)]
+mod test {
+  use futures::executor::block_on;
+
+  use super::*;
+
+  fn fill(overflow: QueueOverflow) -> (Vec<i32>, u64) {
+    let (sender, receiver) = async_channel::bounded(3);
+    let dropped = AtomicU64::new(0);
+    block_on(fill_queue(stream::iter(1..=5), &sender, overflow, &dropped));
+    let queued = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+    (queued, dropped.load(Ordering::Relaxed))
+  }
+
+  #[test]
+  fn queue_overflow() {
+    assert_eq!(fill(QueueOverflow::DropOldest), (vec![3, 4, 5], 2));
+    assert_eq!(fill(QueueOverflow::DropNewest), (vec![1, 2, 3], 2));
+
+    // Block waits for the reader.
+    let (sender, receiver) = async_channel::bounded(1);
+    let dropped = AtomicU64::new(0);
+    let reader = std::thread::spawn(move || {
+      std::iter::from_fn(|| receiver.recv_blocking().ok()).collect::<Vec<_>>()
+    });
+    block_on(fill_queue(
+      stream::iter(1..=5),
+      &sender,
+      QueueOverflow::Block,
+      &dropped,
+    ));
+    drop(sender);
+    assert_eq!(reader.join().unwrap(), [1, 2, 3, 4, 5]);
+    assert_eq!(dropped.load(Ordering::Relaxed), 0);
+  }
+
+  // Encodes big-endian CDR, unlike the default adapter.
+  struct BigEndianCdr<M>(PhantomData<M>);
+
+  impl<M: serde::Serialize> no_key::SerializerAdapter<M> for BigEndianCdr<M> {
+    type Error = ros2_client_core::cdr::CdrError;
+
+    fn output_encoding() -> RepresentationIdentifier {
+      RepresentationIdentifier::CDR_BE
+    }
+
+    fn to_bytes(value: &M) -> Result<bytes::Bytes, Self::Error> {
+      ros2_client_core::cdr::to_vec(value, ros2_client_core::cdr::Endianness::Big)
+        .map(bytes::Bytes::from)
+    }
+  }
+
+  #[test]
+  fn adapters() {
+    use crate::{
+      latched::latched_qos, Context, MessageTypeName, Name, NodeName, NodeOptions,
+      DEFAULT_PUBLISHER_QOS,
+    };
+
+    let context = Context::new().unwrap();
+    let mut node = context
+      .new_node(NodeName::new("/", "adapters").unwrap(), NodeOptions::new())
+      .unwrap();
+    let topic = node
+      .create_topic(
+        &Name::new("/", "adapters").unwrap(),
+        MessageTypeName::new("std_msgs", "String"),
+        &DEFAULT_PUBLISHER_QOS,
+      )
+      .unwrap();
+    // The message waits for the Subscriptions to match.
+    let qos = Some(latched_qos(&DEFAULT_PUBLISHER_QOS));
+    let publisher = node
+      .create_publisher_with_adapter::<String, BigEndianCdr<String>>(&topic, qos.clone())
+      .unwrap();
+    publisher.publish("big".to_string()).unwrap();
+
+    let subscription = node
+      .create_subscription::<String>(&topic, qos.clone())
+      .unwrap();
+    let cdr_subscription = node
+      .create_subscription_with_adapter::<String, CDRDeserializerAdapter<String>>(&topic, qos)
+      .unwrap();
+    assert_eq!(block_on(subscription.async_take()).unwrap().0, "big");
+    assert_eq!(block_on(cdr_subscription.async_take()).unwrap().0, "big");
+  }
+}
@@ -0,0 +1,237 @@
+//! Named QoS profiles loaded from a file
+//!
+//! QoS can be tuned without rebuilding the application, by referring to
+//! profiles by name in the code, and defining them in a YAML file, e.g.
+//!
+//! ```yaml
+//! sensor_data:
+//!   reliability: best_effort
+//!   history: keep_last
+//!   depth: 5
+//! commands:
+//!   reliability: reliable
+//!   durability: transient_local
+//!   deadline: 0.5
+//! ```
+//!
+//! All keys are optional. Durations are in seconds:
+//!
+//! * `reliability`: `reliable` or `best_effort`
+//! * `max_blocking_time`: for `reliable`
+//! * `durability`: `volatile`, `transient_local`, `transient` or `persistent`
+//! * `history`: `keep_last` or `keep_all`
+//! * `depth`: for `keep_last`, which is then the default `history`
+//! * `deadline`, `lifespan`
+//! * `liveliness`: `automatic`, `manual_by_participant` or `manual_by_topic`
+//! * `lease_duration`: for `liveliness`, which defaults to `automatic`
+//!
+//! A profile sets only the given policies. The rest come from the Topic, as
+//! when QoS is given in code. Use profiles with
+//! [`NodeOptions::qos_profiles`](crate::NodeOptions::qos_profiles) and
+//! [`Node::qos_profile`](crate::Node::qos_profile).
+
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use rustdds::{policy, Duration, QosPolicies, QosPolicyBuilder};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ReliabilityKind {
+  Reliable,
+  BestEffort,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum DurabilityKind {
+  Volatile,
+  TransientLocal,
+  Transient,
+  Persistent,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum HistoryKind {
+  KeepLast,
+  KeepAll,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum LivelinessKind {
+  Automatic,
+  ManualByParticipant,
+  ManualByTopic,
+}
+
+// A profile as written in the file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileSpec {
+  reliability: Option<ReliabilityKind>,
+  max_blocking_time: Option<f64>,
+  durability: Option<DurabilityKind>,
+  history: Option<HistoryKind>,
+  depth: Option<i32>,
+  deadline: Option<f64>,
+  lifespan: Option<f64>,
+  liveliness: Option<LivelinessKind>,
+  lease_duration: Option<f64>,
+}
+
+impl ProfileSpec {
+  fn to_qos(&self) -> Result<QosPolicies, String> {
+    let mut qos = QosPolicyBuilder::new();
+
+    match (&self.reliability, self.max_blocking_time) {
+      (Some(ReliabilityKind::Reliable), t) => {
+        qos = qos.reliability(policy::Reliability::Reliable {
+          max_blocking_time: t.map_or(Duration::ZERO, Duration::from_frac_seconds),
+        })
+      }
+      (Some(ReliabilityKind::BestEffort), None) => {
+        qos = qos.reliability(policy::Reliability::BestEffort)
+      }
+      (_, Some(_)) => return Err("max_blocking_time requires reliability: reliable".into()),
+      (None, None) => {}
+    }
+
+    if let Some(durability) = &self.durability {
+      qos = qos.durability(match durability {
+        DurabilityKind::Volatile => policy::Durability::Volatile,
+        DurabilityKind::TransientLocal => policy::Durability::TransientLocal,
+        DurabilityKind::Transient => policy::Durability::Transient,
+        DurabilityKind::Persistent => policy::Durability::Persistent,
+      });
+    }
+
+    match (&self.history, self.depth) {
+      (Some(HistoryKind::KeepAll), Some(_)) => {
+        return Err("depth cannot be used with history: keep_all".into())
+      }
+      (Some(HistoryKind::KeepAll), None) => qos = qos.history(policy::History::KeepAll),
+      (_, Some(depth)) if depth < 1 => return Err(format!("depth must be positive, got {depth}")),
+      (_, Some(depth)) => qos = qos.history(policy::History::KeepLast { depth }),
+      (Some(HistoryKind::KeepLast), None) => {
+        return Err("history: keep_last requires depth".into())
+      }
+      (None, None) => {}
+    }
+
+    if let Some(deadline) = self.deadline {
+      qos = qos.deadline(policy::Deadline(Duration::from_frac_seconds(deadline)));
+    }
+    if let Some(lifespan) = self.lifespan {
+      qos = qos.lifespan(policy::Lifespan {
+        duration: Duration::from_frac_seconds(lifespan),
+      });
+    }
+
+    if self.liveliness.is_some() || self.lease_duration.is_some() {
+      let lease_duration = self
+        .lease_duration
+        .map_or(Duration::INFINITE, Duration::from_frac_seconds);
+      qos = qos.liveliness(match self.liveliness {
+        None | Some(LivelinessKind::Automatic) => policy::Liveliness::Automatic { lease_duration },
+        Some(LivelinessKind::ManualByParticipant) => {
+          policy::Liveliness::ManualByParticipant { lease_duration }
+        }
+        Some(LivelinessKind::ManualByTopic) => policy::Liveliness::ManualByTopic { lease_duration },
+      });
+    }
+
+    Ok(qos.build())
+  }
+}
+
+/// A set of named QoS profiles. See the [module documentation](self) for the
+/// file format.
+#[derive(Clone, Debug, Default)]
+pub struct QosProfiles {
+  profiles: BTreeMap<String, QosPolicies>,
+}
+
+impl QosProfiles {
+  /// Parse profiles from YAML text.
+  pub fn from_yaml(yaml: &str) -> io::Result<Self> {
+    let specs: BTreeMap<String, ProfileSpec> =
+      serde_yaml::from_str(yaml).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let profiles = specs
+      .into_iter()
+      .map(|(name, spec)| {
+        spec.to_qos().map(|qos| (name.clone(), qos)).map_err(|e| {
+          io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("QoS profile {name:?}: {e}"),
+          )
+        })
+      })
+      .collect::<io::Result<_>>()?;
+    Ok(QosProfiles { profiles })
+  }
+
+  /// Load profiles from a YAML file.
+  pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+    Self::from_yaml(&fs::read_to_string(path)?)
+  }
+
+  pub fn get(&self, name: &str) -> Option<&QosPolicies> {
+    self.profiles.get(name)
+  }
+
+  pub fn names(&self) -> impl Iterator<Item = &str> {
+    self.profiles.keys().map(String::as_str)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn from_yaml() {
+    let profiles = QosProfiles::from_yaml(
+      "
+sensor_data:
+  reliability: best_effort
+  depth: 5
+commands:
+  reliability: reliable
+  durability: transient_local
+  deadline: 0.5
+",
+    )
+    .unwrap();
+    assert_eq!(
+      profiles.names().collect::<Vec<_>>(),
+      ["commands", "sensor_data"]
+    );
+
+    let sensor_data = profiles.get("sensor_data").unwrap();
+    assert_eq!(
+      sensor_data.reliability(),
+      Some(policy::Reliability::BestEffort)
+    );
+    assert_eq!(
+      sensor_data.history(),
+      Some(policy::History::KeepLast { depth: 5 })
+    );
+    assert!(!sensor_data.is_volatile());
+
+    let commands = QosPolicyBuilder::new()
+      .reliability(policy::Reliability::Reliable {
+        max_blocking_time: Duration::ZERO,
+      })
+      .durability(policy::Durability::TransientLocal)
+      .deadline(policy::Deadline(Duration::from_millis(500)))
+      .build();
+    assert_eq!(profiles.get("commands"), Some(&commands));
+
+    assert!(QosProfiles::from_yaml("bad:\n  history: keep_all\n  depth: 1\n").is_err());
+    assert!(QosProfiles::from_yaml("typo:\n  reliabilty: reliable\n").is_err());
+  }
+}
@@ -0,0 +1,163 @@
+//! Keeping a loop at a fixed rate, like `rclcpp::Rate`
+//!
+//! ```no_run
+//! # use ros2_client::{rate::Rate, *};
+//! # let context = Context::new().unwrap();
+//! # let node = context
+//! #   .new_node(NodeName::new("/", "looper").unwrap(), NodeOptions::new())
+//! #   .unwrap();
+//! let mut rate = Rate::new(node.get_clock(), 10.0);
+//! smol::block_on(async {
+//!   loop {
+//!     // do the work of one cycle
+//!     rate.sleep().await;
+//!   }
+//! });
+//! ```
+
+use std::{convert::TryFrom, time::Duration};
+
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{clock::Clock, ros_time::ROSTime};
+
+/// Sleeps for the rest of each period of a loop
+///
+/// The time spent in the loop body is subtracted from the sleep. The Rate
+/// follows its [`Clock`], so with a ROS time Clock it follows simulated time,
+/// and restarts its schedule if the time jumps backwards.
+pub struct Rate {
+  clock: Clock,
+  // Nanoseconds
+  period: i64,
+  // Start of the current period
+  last_interval: ROSTime,
+}
+
+impl Rate {
+  /// A Rate of `frequency` cycles per second of `clock`.
+  ///
+  /// # Panics
+  ///
+  /// If `frequency` is not positive.
+  pub fn new(clock: Clock, frequency: f64) -> Rate {
+    assert!(
+      frequency > 0.0,
+      "Rate frequency must be positive, got {}",
+      frequency
+    );
+    Self::from_period(clock, Duration::from_secs_f64(1.0 / frequency))
+  }
+
+  /// A Rate of one cycle per `period` of `clock`.
+  pub fn from_period(clock: Clock, period: Duration) -> Rate {
+    let last_interval = clock.now();
+    Rate {
+      clock,
+      period: i64::try_from(period.as_nanos()).unwrap_or(i64::MAX),
+      last_interval,
+    }
+  }
+
+  /// Sleeps until the end of the current period.
+  ///
+  /// Returns `false` without sleeping if the period is already over. If the
+  /// loop is behind by more than a full period, the schedule restarts from
+  /// now, instead of trying to catch up with short cycles. A backward jump of
+  /// the Clock also restarts the schedule.
+  pub async fn sleep(&mut self) -> bool {
+    let now = self.clock.now();
+    let mut next_interval = self.next_after(self.last_interval);
+    if now < self.last_interval {
+      debug!("Rate: time jumped backwards, restarting.");
+      next_interval = self.next_after(now);
+    }
+    self.last_interval = next_interval;
+    if now >= next_interval {
+      if now > self.next_after(next_interval) {
+        self.last_interval = now;
+      }
+      return false;
+    }
+    if !self.clock.sleep_until(next_interval).await {
+      // Time jumped backwards during the sleep.
+      self.last_interval = self.clock.now();
+    }
+    true
+  }
+
+  /// Restarts the current period from now.
+  pub fn reset(&mut self) {
+    self.last_interval = self.clock.now();
+  }
+
+  pub fn period(&self) -> Duration {
+    Duration::from_nanos(self.period as u64)
+  }
+
+  fn next_after(&self, time: ROSTime) -> ROSTime {
+    ROSTime::from_nanos(time.to_nanos().saturating_add(self.period))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::time::Instant;
+
+  use futures::executor::block_on;
+
+  use super::*;
+  use crate::clock::{ClockType, TimeSource};
+
+  #[test]
+  fn subtracts_loop_time() {
+    let mut rate = Rate::new(Clock::new(ClockType::SteadyTime), 20.0);
+    let start = Instant::now();
+    for _ in 0..5 {
+      std::thread::sleep(Duration::from_millis(20));
+      assert!(block_on(rate.sleep()));
+    }
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(240), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(400), "{:?}", elapsed);
+
+    // Late by several periods: no sleep, and the schedule restarts.
+    std::thread::sleep(Duration::from_millis(120));
+    assert!(!block_on(rate.sleep()));
+    let start = Instant::now();
+    assert!(block_on(rate.sleep()));
+    assert!(start.elapsed() >= Duration::from_millis(40));
+  }
+
+  #[test]
+  fn follows_simulated_time() {
+    let time_source = TimeSource::new();
+    time_source.set_ros_time_active(true);
+    time_source.set_sim_time(ROSTime::from_nanos(100_000_000_000));
+    let mut rate = Rate::new(Clock::ros_time(time_source.clone()), 1.0);
+
+    // Advances simulated time in steps after a while.
+    let advance = |times: Vec<i64>| {
+      let time_source = time_source.clone();
+      std::thread::spawn(move || {
+        for t in times {
+          std::thread::sleep(Duration::from_millis(30));
+          time_source.set_sim_time(ROSTime::from_nanos(t));
+        }
+      })
+    };
+
+    let stepper = advance(vec![100_500_000_000, 101_000_000_000]);
+    assert!(block_on(rate.sleep()));
+    stepper.join().unwrap();
+
+    // Restart of a rosbag: the wait ends, and the schedule restarts.
+    let stepper = advance(vec![5_000_000_000]);
+    assert!(block_on(rate.sleep()));
+    stepper.join().unwrap();
+    let stepper = advance(vec![5_500_000_000, 6_000_000_000]);
+    assert!(block_on(rate.sleep()));
+    stepper.join().unwrap();
+  }
+}
@@ -1,10 +1,10 @@
-//! Corresponds to package [rcl_interfaces](https://index.ros.org/p/rcl_interfaces/). Defines message types for Parameter manipulation.
+//! Corresponds to package [rcl_interfaces](https://index.ros.org/p/rcl_interfaces/). Defines message types for Parameter manipulation, and for setting logger levels.
 //!
 //! For logging, see [`log`](crate::log).
 
 use serde::{Deserialize, Serialize};
 
-use crate::{parameters, service::AService, Message};
+use crate::{parameters, ros2_service, service::AService, Message};
 
 pub type ListParametersService = AService<ListParametersRequest, ListParametersResponse>;
 
@@ -19,7 +19,8 @@ pub type DescribeParametersService =
 
 // This is structurally identical to SetParamtersService, but the operation
 // of the service is slightly different.
-pub type SetParametersAtomicallyService = AService<SetParametersRequest, SetParametersResponse>;
+pub type SetParametersAtomicallyService =
+  AService<SetParametersRequest, SetParametersAtomicallyResponse>;
 
 #[allow(non_snake_case)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,7 +79,13 @@ pub struct SetParametersResponse {
 }
 impl Message for SetParametersResponse {}
 
-pub type SetParametersAtomicallyResponse = SetParametersResponse;
+// https://github.com/ros2/rcl_interfaces/blob/humble/rcl_interfaces/srv/SetParametersAtomically.srv
+// There is only one result, for all of the Parameters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetParametersAtomicallyResponse {
+  pub result: parameters::raw::SetParametersResult,
+}
+impl Message for SetParametersAtomicallyResponse {}
 
 // https://github.com/ros2/rcl_interfaces/blob/humble/rcl_interfaces/srv/DescribeParameters.srv
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,3 +99,44 @@ pub struct DescribeParametersResponse {
   pub values: Vec<parameters::raw::ParameterDescriptor>,
 }
 impl Message for DescribeParametersResponse {}
+
+// https://github.com/ros2/rcl_interfaces/blob/rolling/rcl_interfaces/msg/LoggerLevel.msg
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoggerLevel {
+  pub name: String,
+  /// Severity as in [`LogLevel`](crate::ros2::LogLevel), or 0 for unset
+  pub level: u32,
+}
+impl Message for LoggerLevel {}
+
+// https://github.com/ros2/rcl_interfaces/blob/rolling/rcl_interfaces/msg/SetLoggerLevelsResult.msg
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetLoggerLevelsResult {
+  pub successful: bool,
+  pub reason: String,
+}
+impl Message for SetLoggerLevelsResult {}
+
+ros2_service! {
+  /// Served on `~/get_logger_levels`
+  ///
+  /// [GetLoggerLevels](https://github.com/ros2/rcl_interfaces/blob/rolling/rcl_interfaces/srv/GetLoggerLevels.srv)
+  pub GetLoggerLevels in "rcl_interfaces" {
+    #[derive(Default)]
+    request GetLoggerLevelsRequest { pub names: Vec<String> }
+    #[derive(Default)]
+    response GetLoggerLevelsResponse { pub levels: Vec<LoggerLevel> }
+  }
+}
+
+ros2_service! {
+  /// Served on `~/set_logger_levels`
+  ///
+  /// [SetLoggerLevels](https://github.com/ros2/rcl_interfaces/blob/rolling/rcl_interfaces/srv/SetLoggerLevels.srv)
+  pub SetLoggerLevels in "rcl_interfaces" {
+    #[derive(Default)]
+    request SetLoggerLevelsRequest { pub levels: Vec<LoggerLevel> }
+    #[derive(Default)]
+    response SetLoggerLevelsResponse { pub results: Vec<SetLoggerLevelsResult> }
+  }
+}
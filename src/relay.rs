@@ -0,0 +1,130 @@
+//! Forwarding messages from one Topic to another
+//!
+//! A [`Relay`] republishes messages without deserializing them, so it works
+//! for any message type. This is useful e.g. for moving a Topic to another
+//! namespace, or for bridging Publishers and Subscriptions with incompatible
+//! QoS. Create one with [`Node::create_relay`](crate::Node::create_relay).
+//!
+//! The payload is forwarded as is. Both Topics should have the same message
+//! type, and the Publishers are assumed to use little-endian CDR, as ROS 2
+//! does on common platforms.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::{pin_mut, StreamExt};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use rustdds::QosPolicies;
+
+use crate::{
+  message::SerializedMessage,
+  pubsub::{Publisher, Subscription},
+  throttle::{ThrottleMode, ThrottledPublisher},
+};
+
+/// Configuration of a [`Relay`]
+///
+/// This is a builder-like struct. By default, messages are forwarded at any
+/// rate, with the QoS of the respective Topics.
+#[must_use]
+#[derive(Clone, Debug, Default)]
+pub struct RelayOptions {
+  pub(crate) max_frequency: Option<f64>,
+  pub(crate) input_qos: Option<QosPolicies>,
+  pub(crate) output_qos: Option<QosPolicies>,
+}
+
+impl RelayOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Forward at most `max_frequency` messages per second. Messages arriving
+  /// faster are dropped.
+  pub fn max_frequency(self, max_frequency: f64) -> Self {
+    RelayOptions {
+      max_frequency: Some(max_frequency),
+      ..self
+    }
+  }
+
+  /// QoS of the Subscription to the input Topic
+  pub fn input_qos(self, qos: QosPolicies) -> Self {
+    RelayOptions {
+      input_qos: Some(qos),
+      ..self
+    }
+  }
+
+  /// QoS of the Publisher to the output Topic
+  pub fn output_qos(self, qos: QosPolicies) -> Self {
+    RelayOptions {
+      output_qos: Some(qos),
+      ..self
+    }
+  }
+}
+
+/// Republishes messages from one Topic to another
+pub struct Relay {
+  subscription: Subscription<SerializedMessage>,
+  publisher: ThrottledPublisher<SerializedMessage>,
+  forwarded: AtomicU64,
+}
+
+impl Relay {
+  /// # Panics
+  ///
+  /// If `max_frequency` is given and not positive.
+  pub(crate) fn new(
+    subscription: Subscription<SerializedMessage>,
+    publisher: Publisher<SerializedMessage>,
+    max_frequency: Option<f64>,
+  ) -> Self {
+    Relay {
+      subscription,
+      publisher: ThrottledPublisher::new(
+        publisher,
+        max_frequency.unwrap_or(f64::INFINITY),
+        ThrottleMode::Drop,
+      ),
+      forwarded: AtomicU64::new(0),
+    }
+  }
+
+  /// Forwards messages. This returns only if the input stream ends, so it
+  /// should be run as a task.
+  ///
+  /// Receive and publish errors are logged and skipped.
+  pub async fn run(&self) {
+    let messages = self.subscription.async_stream();
+    pin_mut!(messages);
+    while let Some(result) = messages.next().await {
+      match result {
+        Ok((message, _info)) => match self.publisher.publish(message) {
+          Ok(true) => {
+            self.forwarded.fetch_add(1, Ordering::Relaxed);
+          }
+          Ok(false) => trace!("Relay: message dropped by rate limit"),
+          Err(e) => warn!("Relay: publish failed: {:?}", e.forget_data()),
+        },
+        Err(e) => warn!("Relay: receive failed: {e:?}"),
+      }
+    }
+  }
+
+  /// Number of messages forwarded so far
+  pub fn forwarded_count(&self) -> u64 {
+    self.forwarded.load(Ordering::Relaxed)
+  }
+
+  /// Subscription to the input Topic
+  pub fn subscription(&self) -> &Subscription<SerializedMessage> {
+    &self.subscription
+  }
+
+  /// Publisher to the output Topic
+  pub fn publisher(&self) -> &Publisher<SerializedMessage> {
+    self.publisher.publisher()
+  }
+}
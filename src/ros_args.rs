@@ -0,0 +1,333 @@
+//! ROS 2 command line arguments, i.e. those between `--ros-args` and `--`
+//!
+//! Supported are
+//! * `-r [node:]from:=to` or `--remap`, remapping a Topic, Service or Action
+//!   name,
+//! * `-r [node:]__ns:=/namespace` and `-r [node:]__node:=name` (or `__name`),
+//!   renaming the Node,
+//! * `-p [node:]name:=value` or `--param`, where `value` is in YAML syntax, and
+//! * `--params-file path`, see [`ParameterFile`], and
+//! * `--log-level [logger:=]level`, setting the default logger level, or the
+//!   level of a logger, e.g. `talker.planner:=debug`.
+//!
+//! The optional `node:` prefix limits the rule to Nodes of that name. The
+//! other logging options and the security options of `rcl` are accepted, but
+//! ignored.
+//!
+//! Nodes use these through
+//! [`NodeOptions::cli_args`](crate::NodeOptions::cli_args)
+//! and [`NodeOptions::use_global_arguments`](crate::NodeOptions::use_global_arguments).
+
+use std::path::PathBuf;
+
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+
+use crate::{
+  error::Error,
+  log::LogLevel,
+  names::{Name, NodeName},
+  parameters::{parse_parameter_value, Parameter, ParameterFile},
+};
+
+/// A name remapping rule, given with `-r`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemapRule {
+  /// Base name of the Node the rule applies to. `None` applies to all Nodes.
+  pub node: Option<String>,
+  pub from: String,
+  pub to: String,
+}
+
+impl RemapRule {
+  /// A rule for all Nodes. Both names are checked to be valid.
+  pub fn new(from: &str, to: &str) -> Result<RemapRule, Error> {
+    Name::parse(from)?;
+    Name::parse(to)?;
+    Ok(RemapRule {
+      node: None,
+      from: from.to_owned(),
+      to: to.to_owned(),
+    })
+  }
+
+  fn applies_to(&self, node_name: &str) -> bool {
+    self.node.as_deref().is_none_or(|n| n == node_name)
+  }
+}
+
+#[derive(Clone, Debug)]
+enum ParameterRule {
+  Value {
+    node: Option<String>,
+    parameter: Parameter,
+  },
+  File(PathBuf),
+}
+
+/// Parsed ROS 2 command line arguments
+#[derive(Clone, Debug, Default)]
+pub struct RosArguments {
+  remap_rules: Vec<RemapRule>,
+  parameter_rules: Vec<ParameterRule>,
+  log_levels: Vec<(Option<String>, LogLevel)>,
+}
+
+impl RosArguments {
+  /// Parse command line arguments. Arguments outside `--ros-args` sections
+  /// are skipped.
+  pub fn parse<I>(args: I) -> Result<RosArguments, Error>
+  where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+  {
+    let bad = |s: String| Error::BadArguments(s);
+    let mut result = RosArguments::default();
+    let mut in_ros_args = false;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+      let arg = arg.as_ref();
+      if !in_ros_args {
+        in_ros_args = arg == "--ros-args";
+        continue;
+      }
+      let mut value_of = |flag: &str| {
+        args
+          .next()
+          .map(|v| v.as_ref().to_owned())
+          .ok_or_else(|| bad(format!("{flag} requires a value")))
+      };
+      match arg {
+        "--" => in_ros_args = false,
+        "--ros-args" => {}
+        "-r" | "--remap" => {
+          let (node, from, to) = split_rule(&value_of(arg)?).map_err(bad)?;
+          if !from.starts_with("__") {
+            for name in [&from, &to] {
+              Name::parse(name).map_err(|e| bad(format!("{name:?}: {e}")))?;
+            }
+          }
+          result.remap_rules.push(RemapRule { node, from, to });
+        }
+        "-p" | "--param" => {
+          let (node, name, value) = split_rule(&value_of(arg)?).map_err(bad)?;
+          let value =
+            parse_parameter_value(&value).map_err(|e| bad(format!("Parameter {name:?}: {e}")))?;
+          result.parameter_rules.push(ParameterRule::Value {
+            node,
+            parameter: Parameter { name, value },
+          });
+        }
+        "--params-file" => {
+          let path = value_of(arg)?;
+          result
+            .parameter_rules
+            .push(ParameterRule::File(path.into()));
+        }
+        "--log-level" => {
+          let value = value_of(arg)?;
+          let (logger, level) = match value.split_once(":=") {
+            Some((logger, level)) => (Some(logger.to_owned()), level),
+            None => (None, value.as_str()),
+          };
+          let level = LogLevel::from_name(level)
+            .ok_or_else(|| bad(format!("Unknown log level {level:?}")))?;
+          result.log_levels.push((logger, level));
+        }
+        "-e" | "--enclave" | "--log-config-file" | "--log-file-name" => {
+          let value = value_of(arg)?;
+          debug!("Ignoring ROS argument {arg} {value}");
+        }
+        flag if flag.starts_with("--enable-") || flag.starts_with("--disable-") => {
+          debug!("Ignoring ROS argument {flag}");
+        }
+        other => return Err(bad(format!("Unknown ROS argument {other:?}"))),
+      }
+    }
+    Ok(result)
+  }
+
+  /// Parse the command line arguments of this process.
+  pub fn from_env() -> Result<RosArguments, Error> {
+    Self::parse(std::env::args().skip(1))
+  }
+
+  /// Name remapping rules, in command line order. Rules for `__ns` and
+  /// `__node` are included.
+  pub fn remap_rules(&self) -> &[RemapRule] {
+    &self.remap_rules
+  }
+
+  /// Applies the first matching `__node` and `__ns` rules to `node_name`.
+  pub fn remap_node_name(&self, node_name: &NodeName) -> Result<NodeName, Error> {
+    let find = |keys: &[&str]| {
+      self
+        .remap_rules
+        .iter()
+        .find(|r| keys.contains(&r.from.as_str()) && r.applies_to(node_name.base_name()))
+        .map(|r| r.to.as_str())
+    };
+    let base_name = find(&["__node", "__name"]).unwrap_or(node_name.base_name());
+    let namespace = find(&["__ns"]).unwrap_or(node_name.namespace());
+    Ok(NodeName::new(namespace, base_name)?)
+  }
+
+  /// Logger levels from `--log-level` arguments, in command line order.
+  /// Without a logger name, the level is the default level of all loggers.
+  pub fn log_levels(&self) -> &[(Option<String>, LogLevel)] {
+    &self.log_levels
+  }
+
+  /// Parameters from `-p` and `--params-file` arguments for `node_name`.
+  ///
+  /// If a Parameter is given several times, the last one wins.
+  pub fn parameters_for(&self, node_name: &NodeName) -> Result<Vec<Parameter>, Error> {
+    let mut parameters = Vec::new();
+    for rule in &self.parameter_rules {
+      match rule {
+        ParameterRule::Value { node, parameter } => {
+          if node.as_deref().is_none_or(|n| n == node_name.base_name()) {
+            parameters.push(parameter.clone());
+          }
+        }
+        ParameterRule::File(path) => {
+          let file = ParameterFile::load(path)
+            .map_err(|e| Error::BadParameter(format!("{}: {e}", path.display())))?;
+          parameters.extend(file.parameters_for(&node_name.fully_qualified_name()));
+        }
+      }
+    }
+    Ok(parameters)
+  }
+}
+
+/// Resolves `name`, as used by `node_name`, and applies the first matching
+/// rule of `rules` to it.
+///
+/// Names are compared after [resolving](Name::resolve) them, so e.g. rule
+/// `chatter:=talk` matches `/ns/chatter` for a Node in namespace `/ns`.
+pub(crate) fn resolve_name<'a>(
+  rules: impl IntoIterator<Item = &'a RemapRule>,
+  name: &Name,
+  node_name: &NodeName,
+) -> Result<Name, Error> {
+  let name = name.resolve(node_name)?;
+  for rule in rules {
+    if rule.from.starts_with("__") || !rule.applies_to(node_name.base_name()) {
+      continue;
+    }
+    if Name::parse(&rule.from)?.resolve(node_name)? == name {
+      return Ok(Name::parse(&rule.to)?.resolve(node_name)?);
+    }
+  }
+  Ok(name)
+}
+
+// Splits "[node:]key:=value".
+fn split_rule(rule: &str) -> Result<(Option<String>, String, String), String> {
+  let (lhs, value) = rule
+    .split_once(":=")
+    .ok_or_else(|| format!("Expected name:=value, got {rule:?}"))?;
+  let (node, key) = match lhs.split_once(':') {
+    Some((node, key)) => (Some(node.to_owned()), key),
+    None => (None, lhs),
+  };
+  if key.is_empty() {
+    return Err(format!("Empty name in {rule:?}"));
+  }
+  Ok((node, key.to_owned(), value.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn parse_and_apply() {
+    let args = RosArguments::parse([
+      "program",
+      "--flag",
+      "--ros-args",
+      "-r",
+      "chatter:=/talk",
+      "-r",
+      "other:~/x:=y",
+      "-r",
+      "__ns:=/robot",
+      "-p",
+      "rate:=2.5",
+      "--log-level",
+      "debug",
+      "--log-level",
+      "talker.planner:=WARN",
+      "-p",
+      "talker:names:=[a, b]",
+      "--",
+      "-p",
+    ])
+    .unwrap();
+
+    let node = args
+      .remap_node_name(&NodeName::new("/", "talker").unwrap())
+      .unwrap();
+    assert_eq!(node.fully_qualified_name(), "/robot/talker");
+
+    let resolve = |name: &str| {
+      resolve_name(args.remap_rules(), &Name::parse(name).unwrap(), &node)
+        .unwrap()
+        .to_string()
+    };
+    assert_eq!(resolve("chatter"), "/talk");
+    assert_eq!(resolve("/robot/chatter"), "/talk");
+    assert_eq!(resolve("/chatter"), "/chatter");
+    assert_eq!(resolve("~/x"), "/robot/talker/x");
+
+    let parameters = args
+      .parameters_for(&node)
+      .unwrap()
+      .into_iter()
+      .map(|p| format!("{}={:?}", p.name, p.value))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      parameters,
+      vec!["rate=Double(2.5)", "names=StringArray([\"a\", \"b\"])"]
+    );
+    let other = NodeName::new("/", "other").unwrap();
+    assert_eq!(args.parameters_for(&other).unwrap().len(), 1);
+    assert_eq!(
+      args.log_levels(),
+      [
+        (None, LogLevel::Debug),
+        (Some("talker.planner".to_owned()), LogLevel::Warn)
+      ]
+    );
+
+    assert!(RosArguments::parse(["--ros-args", "--unknown"]).is_err());
+    assert!(RosArguments::parse(["--ros-args", "-r", "no_value"]).is_err());
+    assert!(RosArguments::parse(["--ros-args", "-r", "a:=b//c"]).is_err());
+    assert!(RosArguments::parse(["--ros-args", "--log-level", "loud"]).is_err());
+    assert!(RosArguments::parse(["--unknown", "-r"]).is_ok());
+  }
+
+  #[test]
+  fn first_matching_rule_wins() {
+    let node = NodeName::new("/ns", "n").unwrap();
+    let rules = [
+      RemapRule::new("a", "b").unwrap(),
+      RemapRule::new("/ns/a", "c").unwrap(),
+      RemapRule::new("~/x", "/y").unwrap(),
+    ];
+    let resolve = |name: &str| {
+      resolve_name(&rules, &Name::parse(name).unwrap(), &node)
+        .unwrap()
+        .to_string()
+    };
+    assert_eq!(resolve("a"), "/ns/b");
+    assert_eq!(resolve("/n/x"), "/n/x");
+    assert_eq!(resolve("/ns/n/x"), "/y");
+
+    assert!(RemapRule::new("a", "b/").is_err());
+    assert!(RemapRule::new("", "b").is_err());
+  }
+}
@@ -0,0 +1,584 @@
+//! [Image](https://github.com/ros2/common_interfaces/blob/rolling/sensor_msgs/msg/Image.msg)
+//! and [CompressedImage](https://github.com/ros2/common_interfaces/blob/rolling/sensor_msgs/msg/CompressedImage.msg)
+//! with pixel format and stride helpers.
+//!
+//! With the `image` feature enabled, images can be converted to and from the
+//! [`image`](https://docs.rs/image) crate's `DynamicImage`, and
+//! `CompressedImage` can be decoded and encoded (PNG and JPEG).
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{message::Message, std_msgs::Header};
+
+/// An uncompressed image.
+///
+/// Pixel `(x,y)` starts at byte offset `y * step + x * bytes_per_pixel` in
+/// `data`. Rows may be padded, i.e. `step` may be larger than
+/// `width * bytes_per_pixel`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Image {
+  pub header: Header,
+  /// Image height, that is, number of rows
+  pub height: u32,
+  /// Image width, that is, number of columns
+  pub width: u32,
+  /// Encoding of pixels -- channel meaning, ordering, size. See [`Encoding`].
+  pub encoding: String,
+  /// Is this data bigendian? (uint8 on the wire)
+  pub is_bigendian: u8,
+  /// Full row length in bytes
+  pub step: u32,
+  /// Actual matrix data, size is (step * rows)
+  pub data: Vec<u8>,
+}
+impl Message for Image {}
+
+/// A compressed image, e.g. PNG or JPEG.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressedImage {
+  pub header: Header,
+  /// Specifies the format of the data, e.g. "jpeg" or "png". `image_transport`
+  /// also uses forms like "bgr8; jpeg compressed bgr8".
+  pub format: String,
+  /// Compressed image buffer
+  pub data: Vec<u8>,
+}
+impl Message for CompressedImage {}
+
+/// Pixel encodings, as defined in
+/// [sensor_msgs/image_encodings.hpp](https://github.com/ros2/common_interfaces/blob/rolling/sensor_msgs/include/sensor_msgs/image_encodings.hpp)
+///
+/// Only the commonly used ones have their own variant. Anything else is
+/// carried in `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Encoding {
+  Rgb8,
+  Rgba8,
+  Rgb16,
+  Rgba16,
+  Bgr8,
+  Bgra8,
+  Bgr16,
+  Bgra16,
+  Mono8,
+  Mono16,
+  /// 32-bit float, single channel, e.g. depth in meters
+  F32C1,
+  BayerRggb8,
+  BayerBggr8,
+  BayerGbrg8,
+  BayerGrbg8,
+  /// a.k.a. UYVY
+  Yuv422,
+  /// a.k.a. YUYV
+  Yuv422Yuy2,
+  Other(String),
+}
+
+impl Encoding {
+  pub fn as_str(&self) -> &str {
+    match self {
+      Encoding::Rgb8 => "rgb8",
+      Encoding::Rgba8 => "rgba8",
+      Encoding::Rgb16 => "rgb16",
+      Encoding::Rgba16 => "rgba16",
+      Encoding::Bgr8 => "bgr8",
+      Encoding::Bgra8 => "bgra8",
+      Encoding::Bgr16 => "bgr16",
+      Encoding::Bgra16 => "bgra16",
+      Encoding::Mono8 => "mono8",
+      Encoding::Mono16 => "mono16",
+      Encoding::F32C1 => "32FC1",
+      Encoding::BayerRggb8 => "bayer_rggb8",
+      Encoding::BayerBggr8 => "bayer_bggr8",
+      Encoding::BayerGbrg8 => "bayer_gbrg8",
+      Encoding::BayerGrbg8 => "bayer_grbg8",
+      Encoding::Yuv422 => "yuv422",
+      Encoding::Yuv422Yuy2 => "yuv422_yuy2",
+      Encoding::Other(s) => s,
+    }
+  }
+
+  /// Number of bytes per pixel, or `None` if the encoding is unknown.
+  ///
+  /// For the YUV 4:2:2 encodings this is the average, 2 bytes.
+  pub fn bytes_per_pixel(&self) -> Option<u32> {
+    match self {
+      Encoding::Mono8
+      | Encoding::BayerRggb8
+      | Encoding::BayerBggr8
+      | Encoding::BayerGbrg8
+      | Encoding::BayerGrbg8 => Some(1),
+      Encoding::Mono16 | Encoding::Yuv422 | Encoding::Yuv422Yuy2 => Some(2),
+      Encoding::Rgb8 | Encoding::Bgr8 => Some(3),
+      Encoding::Rgba8 | Encoding::Bgra8 | Encoding::F32C1 => Some(4),
+      Encoding::Rgb16 | Encoding::Bgr16 => Some(6),
+      Encoding::Rgba16 | Encoding::Bgra16 => Some(8),
+      Encoding::Other(_) => None,
+    }
+  }
+
+  /// Number of channels, or `None` if the encoding is unknown.
+  pub fn channels(&self) -> Option<u32> {
+    match self {
+      Encoding::Mono8
+      | Encoding::Mono16
+      | Encoding::F32C1
+      | Encoding::BayerRggb8
+      | Encoding::BayerBggr8
+      | Encoding::BayerGbrg8
+      | Encoding::BayerGrbg8 => Some(1),
+      Encoding::Yuv422 | Encoding::Yuv422Yuy2 => Some(2),
+      Encoding::Rgb8 | Encoding::Bgr8 | Encoding::Rgb16 | Encoding::Bgr16 => Some(3),
+      Encoding::Rgba8 | Encoding::Bgra8 | Encoding::Rgba16 | Encoding::Bgra16 => Some(4),
+      Encoding::Other(_) => None,
+    }
+  }
+}
+
+impl FromStr for Encoding {
+  type Err = std::convert::Infallible;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Ok(match s {
+      "rgb8" => Encoding::Rgb8,
+      "rgba8" => Encoding::Rgba8,
+      "rgb16" => Encoding::Rgb16,
+      "rgba16" => Encoding::Rgba16,
+      "bgr8" => Encoding::Bgr8,
+      "bgra8" => Encoding::Bgra8,
+      "bgr16" => Encoding::Bgr16,
+      "bgra16" => Encoding::Bgra16,
+      "mono8" | "8UC1" => Encoding::Mono8,
+      "mono16" | "16UC1" => Encoding::Mono16,
+      "32FC1" => Encoding::F32C1,
+      "bayer_rggb8" => Encoding::BayerRggb8,
+      "bayer_bggr8" => Encoding::BayerBggr8,
+      "bayer_gbrg8" => Encoding::BayerGbrg8,
+      "bayer_grbg8" => Encoding::BayerGrbg8,
+      "yuv422" | "uyvy" => Encoding::Yuv422,
+      "yuv422_yuy2" | "yuyv" => Encoding::Yuv422Yuy2,
+      other => Encoding::Other(other.to_owned()),
+    })
+  }
+}
+
+impl fmt::Display for Encoding {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+/// What went wrong when accessing or converting an image
+#[derive(Debug)]
+pub enum ImageError {
+  UnknownEncoding(String),
+  /// The encoding is known, but the requested conversion does not support it.
+  UnsupportedEncoding(String),
+  /// `step`, dimensions and data length are not consistent.
+  BadLayout(String),
+  OutOfBounds {
+    x: u32,
+    y: u32,
+  },
+  #[cfg(feature = "image")]
+  Codec(image::ImageError),
+}
+
+impl fmt::Display for ImageError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      ImageError::UnknownEncoding(e) => write!(f, "Unknown image encoding {e:?}"),
+      ImageError::UnsupportedEncoding(e) => {
+        write!(f, "Image encoding {e:?} is not supported here")
+      }
+      ImageError::BadLayout(reason) => write!(f, "Inconsistent image layout: {reason}"),
+      ImageError::OutOfBounds { x, y } => write!(f, "Pixel ({x},{y}) is outside image"),
+      #[cfg(feature = "image")]
+      ImageError::Codec(e) => write!(f, "Image codec error: {e}"),
+    }
+  }
+}
+
+impl std::error::Error for ImageError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      #[cfg(feature = "image")]
+      ImageError::Codec(e) => Some(e),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for ImageError {
+  fn from(e: image::ImageError) -> Self {
+    ImageError::Codec(e)
+  }
+}
+
+impl Image {
+  /// Create a zero-filled image with tightly packed rows.
+  pub fn new(
+    header: Header,
+    width: u32,
+    height: u32,
+    encoding: Encoding,
+  ) -> Result<Self, ImageError> {
+    let bpp = encoding
+      .bytes_per_pixel()
+      .ok_or_else(|| ImageError::UnknownEncoding(encoding.to_string()))?;
+    let step = width.checked_mul(bpp).ok_or_else(|| {
+      ImageError::BadLayout(format!(
+        "width {width} * bytes per pixel {bpp} does not fit in step"
+      ))
+    })?;
+    Ok(Image {
+      header,
+      height,
+      width,
+      encoding: encoding.to_string(),
+      is_bigendian: 0,
+      step,
+      data: vec![0; step as usize * height as usize],
+    })
+  }
+
+  /// Parsed `encoding` field
+  pub fn encoding(&self) -> Encoding {
+    // Parsing is infallible
+    self.encoding.parse().unwrap()
+  }
+
+  pub fn is_bigendian(&self) -> bool {
+    self.is_bigendian != 0
+  }
+
+  pub fn bytes_per_pixel(&self) -> Result<u32, ImageError> {
+    self
+      .encoding()
+      .bytes_per_pixel()
+      .ok_or_else(|| ImageError::UnknownEncoding(self.encoding.clone()))
+  }
+
+  /// Length of the pixel data in a row, excluding padding.
+  pub fn row_bytes(&self) -> Result<usize, ImageError> {
+    Ok(self.width as usize * self.bytes_per_pixel()? as usize)
+  }
+
+  /// Check that `step` fits a row of pixels and that `data` holds `height`
+  /// rows.
+  pub fn validate(&self) -> Result<(), ImageError> {
+    let row_bytes = self.row_bytes()?;
+    if row_bytes > self.step as usize {
+      return Err(ImageError::BadLayout(format!(
+        "step {} is less than width {} * bytes per pixel",
+        self.step, self.width
+      )));
+    }
+    if self.data.len() < self.step as usize * self.height as usize {
+      return Err(ImageError::BadLayout(format!(
+        "data length {} is less than step {} * height {}",
+        self.data.len(),
+        self.step,
+        self.height
+      )));
+    }
+    Ok(())
+  }
+
+  /// Pixel data of row `y`, without padding.
+  pub fn row(&self, y: u32) -> Result<&[u8], ImageError> {
+    self.validate()?;
+    if y >= self.height {
+      return Err(ImageError::OutOfBounds { x: 0, y });
+    }
+    let start = y as usize * self.step as usize;
+    Ok(&self.data[start..start + self.row_bytes()?])
+  }
+
+  pub fn row_mut(&mut self, y: u32) -> Result<&mut [u8], ImageError> {
+    self.validate()?;
+    if y >= self.height {
+      return Err(ImageError::OutOfBounds { x: 0, y });
+    }
+    let start = y as usize * self.step as usize;
+    let len = self.row_bytes()?;
+    Ok(&mut self.data[start..start + len])
+  }
+
+  /// Iterate over rows, without padding.
+  pub fn rows(&self) -> Result<impl Iterator<Item = &[u8]>, ImageError> {
+    self.validate()?;
+    let row_bytes = self.row_bytes()?;
+    Ok(
+      self
+        .data
+        .chunks(self.step.max(1) as usize)
+        .take(self.height as usize)
+        .map(move |row| &row[..row_bytes]),
+    )
+  }
+
+  /// Bytes of pixel `(x,y)`
+  pub fn pixel(&self, x: u32, y: u32) -> Result<&[u8], ImageError> {
+    if x >= self.width {
+      return Err(ImageError::OutOfBounds { x, y });
+    }
+    let bpp = self.bytes_per_pixel()? as usize;
+    let start = x as usize * bpp;
+    self
+      .row(y)
+      .map_err(|e| match e {
+        ImageError::OutOfBounds { .. } => ImageError::OutOfBounds { x, y },
+        e => e,
+      })
+      .map(|row| &row[start..start + bpp])
+  }
+
+  /// Pixel data with row padding removed.
+  pub fn to_packed(&self) -> Result<Vec<u8>, ImageError> {
+    Ok(self.rows()?.flatten().copied().collect())
+  }
+}
+
+impl CompressedImage {
+  pub fn new(header: Header, format: &str, data: Vec<u8>) -> Self {
+    CompressedImage {
+      header,
+      format: format.to_owned(),
+      data,
+    }
+  }
+
+  /// The compression format, e.g. "jpeg" or "png", in lower case.
+  ///
+  /// This understands both the plain form and the `image_transport` form
+  /// "bgr8; jpeg compressed bgr8".
+  pub fn compression_format(&self) -> String {
+    let f = match self.format.split_once(';') {
+      Some((_, rest)) => rest.split_whitespace().next().unwrap_or(""),
+      None => self.format.trim(),
+    };
+    f.to_lowercase()
+  }
+}
+
+#[cfg(feature = "image")]
+mod image_conversions {
+  use std::convert::TryFrom;
+
+  use image::{DynamicImage, ImageBuffer, ImageFormat};
+
+  use super::*;
+
+  fn swap_red_blue<T: Copy>(data: &mut [T], channels: usize) {
+    for px in data.chunks_exact_mut(channels) {
+      px.swap(0, 2);
+    }
+  }
+
+  fn to_u16(bytes: &[u8], big_endian: bool) -> Vec<u16> {
+    bytes
+      .chunks_exact(2)
+      .map(|b| {
+        if big_endian {
+          u16::from_be_bytes([b[0], b[1]])
+        } else {
+          u16::from_le_bytes([b[0], b[1]])
+        }
+      })
+      .collect()
+  }
+
+  impl Image {
+    /// Convert to an `image` crate image.
+    ///
+    /// BGR(A) encodings are converted to RGB(A).
+    pub fn to_dynamic_image(&self) -> Result<DynamicImage, ImageError> {
+      let packed = self.to_packed()?;
+      let (w, h) = (self.width, self.height);
+      let bad = || ImageError::BadLayout("buffer size mismatch".to_owned());
+      let img = match self.encoding() {
+        Encoding::Mono8 => {
+          DynamicImage::ImageLuma8(ImageBuffer::from_raw(w, h, packed).ok_or_else(bad)?)
+        }
+        Encoding::Rgb8 => {
+          DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, packed).ok_or_else(bad)?)
+        }
+        Encoding::Rgba8 => {
+          DynamicImage::ImageRgba8(ImageBuffer::from_raw(w, h, packed).ok_or_else(bad)?)
+        }
+        Encoding::Bgr8 => {
+          let mut packed = packed;
+          swap_red_blue(&mut packed, 3);
+          DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, packed).ok_or_else(bad)?)
+        }
+        Encoding::Bgra8 => {
+          let mut packed = packed;
+          swap_red_blue(&mut packed, 4);
+          DynamicImage::ImageRgba8(ImageBuffer::from_raw(w, h, packed).ok_or_else(bad)?)
+        }
+        Encoding::Mono16 => {
+          let d = to_u16(&packed, self.is_bigendian());
+          DynamicImage::ImageLuma16(ImageBuffer::from_raw(w, h, d).ok_or_else(bad)?)
+        }
+        Encoding::Rgb16 => {
+          let d = to_u16(&packed, self.is_bigendian());
+          DynamicImage::ImageRgb16(ImageBuffer::from_raw(w, h, d).ok_or_else(bad)?)
+        }
+        Encoding::Rgba16 => {
+          let d = to_u16(&packed, self.is_bigendian());
+          DynamicImage::ImageRgba16(ImageBuffer::from_raw(w, h, d).ok_or_else(bad)?)
+        }
+        Encoding::Bgr16 => {
+          let mut d = to_u16(&packed, self.is_bigendian());
+          swap_red_blue(&mut d, 3);
+          DynamicImage::ImageRgb16(ImageBuffer::from_raw(w, h, d).ok_or_else(bad)?)
+        }
+        Encoding::Bgra16 => {
+          let mut d = to_u16(&packed, self.is_bigendian());
+          swap_red_blue(&mut d, 4);
+          DynamicImage::ImageRgba16(ImageBuffer::from_raw(w, h, d).ok_or_else(bad)?)
+        }
+        Encoding::Other(e) => return Err(ImageError::UnknownEncoding(e)),
+        e => return Err(ImageError::UnsupportedEncoding(e.to_string())),
+      };
+      Ok(img)
+    }
+
+    /// Convert from an `image` crate image.
+    ///
+    /// Grayscale and RGB(A) images with 8 or 16 bit channels are stored as
+    /// such. Other pixel types are converted to `rgba8` or `rgba16`.
+    pub fn from_dynamic_image(header: Header, img: &DynamicImage) -> Self {
+      fn u16_bytes(d: &[u16]) -> Vec<u8> {
+        d.iter().flat_map(|v| v.to_le_bytes()).collect()
+      }
+      let (encoding, data) = match img {
+        DynamicImage::ImageLuma8(b) => (Encoding::Mono8, b.as_raw().clone()),
+        DynamicImage::ImageRgb8(b) => (Encoding::Rgb8, b.as_raw().clone()),
+        DynamicImage::ImageRgba8(b) => (Encoding::Rgba8, b.as_raw().clone()),
+        DynamicImage::ImageLuma16(b) => (Encoding::Mono16, u16_bytes(b.as_raw())),
+        DynamicImage::ImageRgb16(b) => (Encoding::Rgb16, u16_bytes(b.as_raw())),
+        DynamicImage::ImageRgba16(b) => (Encoding::Rgba16, u16_bytes(b.as_raw())),
+        DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb32F(_)
+        | DynamicImage::ImageRgba32F(_) => (Encoding::Rgba16, u16_bytes(img.to_rgba16().as_raw())),
+        _ => (Encoding::Rgba8, img.to_rgba8().into_raw()),
+      };
+      // Encoding is known, so unwrap is safe
+      let step = img.width() * encoding.bytes_per_pixel().unwrap();
+      Image {
+        header,
+        height: img.height(),
+        width: img.width(),
+        encoding: encoding.to_string(),
+        is_bigendian: 0,
+        step,
+        data,
+      }
+    }
+  }
+
+  impl TryFrom<&Image> for DynamicImage {
+    type Error = ImageError;
+    fn try_from(img: &Image) -> Result<Self, ImageError> {
+      img.to_dynamic_image()
+    }
+  }
+
+  impl CompressedImage {
+    /// Decode the compressed data.
+    pub fn decode(&self) -> Result<DynamicImage, ImageError> {
+      let image = match ImageFormat::from_extension(self.compression_format()) {
+        Some(format) => image::load_from_memory_with_format(&self.data, format)?,
+        None => image::load_from_memory(&self.data)?,
+      };
+      Ok(image)
+    }
+
+    /// Compress an image. `format` should be `ImageFormat::Png` or
+    /// `ImageFormat::Jpeg`.
+    pub fn encode(
+      header: Header,
+      img: &DynamicImage,
+      format: ImageFormat,
+    ) -> Result<Self, ImageError> {
+      let mut data = std::io::Cursor::new(Vec::new());
+      img.write_to(&mut data, format)?;
+      let format_name = format.extensions_str().first().copied().unwrap_or("");
+      Ok(CompressedImage::new(header, format_name, data.into_inner()))
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn encodings() {
+    for e in [Encoding::Bgr8, Encoding::Mono16, Encoding::F32C1] {
+      assert_eq!(e.as_str().parse::<Encoding>().unwrap(), e);
+    }
+    assert_eq!(
+      "weird".parse::<Encoding>().unwrap(),
+      Encoding::Other("weird".to_owned())
+    );
+    assert_eq!(Encoding::Rgba16.bytes_per_pixel(), Some(8));
+    assert_eq!(Encoding::Rgba16.channels(), Some(4));
+  }
+
+  #[test]
+  fn stride() {
+    let mut img = Image::new(Header::default(), 2, 2, Encoding::Rgb8).unwrap();
+    assert_eq!(img.step, 6);
+    // pad each row with 2 bytes
+    img.step = 8;
+    img.data = vec![1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12, 0, 0];
+    assert_eq!(img.pixel(1, 1).unwrap(), &[10, 11, 12]);
+    assert_eq!(
+      img.to_packed().unwrap(),
+      vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]
+    );
+    assert!(matches!(
+      img.pixel(2, 0),
+      Err(ImageError::OutOfBounds { .. })
+    ));
+    img.data.truncate(14);
+    assert!(matches!(img.row(0), Err(ImageError::BadLayout(_))));
+
+    // step would overflow u32
+    assert!(matches!(
+      Image::new(Header::default(), u32::MAX, 0, Encoding::Rgb8),
+      Err(ImageError::BadLayout(_))
+    ));
+  }
+
+  #[test]
+  fn compression_format() {
+    let c = CompressedImage::new(Header::default(), "bgr8; jpeg compressed bgr8", vec![]);
+    assert_eq!(c.compression_format(), "jpeg");
+    let c = CompressedImage::new(Header::default(), "PNG", vec![]);
+    assert_eq!(c.compression_format(), "png");
+  }
+
+  #[cfg(feature = "image")]
+  #[test]
+  fn image_crate_round_trip() {
+    let mut img = Image::new(Header::default(), 2, 1, Encoding::Bgr8).unwrap();
+    img.data = vec![1, 2, 3, 4, 5, 6];
+    let dynamic = img.to_dynamic_image().unwrap();
+    assert_eq!(dynamic.as_rgb8().unwrap().as_raw(), &vec![3, 2, 1, 6, 5, 4]);
+
+    let png =
+      CompressedImage::encode(Header::default(), &dynamic, image::ImageFormat::Png).unwrap();
+    assert_eq!(png.compression_format(), "png");
+    let back = Image::from_dynamic_image(Header::default(), &png.decode().unwrap());
+    assert_eq!(back.encoding(), Encoding::Rgb8);
+    assert_eq!(back.data, vec![3, 2, 1, 6, 5, 4]);
+  }
+}
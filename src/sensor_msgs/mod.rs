@@ -0,0 +1,20 @@
+//! Corresponds to package [sensor_msgs](https://index.ros.org/p/sensor_msgs/).
+//!
+//! Besides the wire types, this module contains helpers for the messages that
+//! are hard to get right by hand, such as [`PointCloud2`].
+
+pub mod image;
+pub mod point_cloud2;
+
+pub use image::{CompressedImage, Encoding, Image, ImageError};
+pub use point_cloud2::{PointCloud2, PointCloud2Builder, PointCloudError, PointField};
+
+// The rest of the package, with feature `sensor_msgs`
+#[cfg(feature = "sensor_msgs")]
+pub use crate::common_interfaces::sensor_msgs::msg::*;
+
+/// Services of sensor_msgs, e.g. [`srv::SetCameraInfo`]
+#[cfg(feature = "sensor_msgs")]
+pub mod srv {
+  pub use crate::common_interfaces::sensor_msgs::srv::*;
+}
@@ -0,0 +1,785 @@
+//! [PointCloud2](https://github.com/ros2/common_interfaces/blob/rolling/sensor_msgs/msg/PointCloud2.msg)
+//! and helpers to access its contents.
+//!
+//! A `PointCloud2` is a binary blob with a self-describing layout: the
+//! `fields` tell at which byte offset within each point a named value is
+//! found, and what is its type. Getting the offsets, `point_step`, and
+//! `row_step` consistent by hand is error-prone, so this module provides
+//!
+//! * typed field iterators, e.g. `cloud.iter::<f32>("x")`,
+//! * random access to single points via [`PointView`],
+//! * mapping between Rust structs and points via [`PointStruct`], and
+//! * [`PointCloud2Builder`], which computes the layout.
+//!
+//! # Example
+//!
+//! ```
+//! use ros2_client::sensor_msgs::point_cloud2::*;
+//!
+//! let points = vec![
+//!   PointXYZ { x: 1.0, y: 2.0, z: 3.0 },
+//!   PointXYZ { x: 4.0, y: 5.0, z: 6.0 },
+//! ];
+//! let cloud = PointCloud2Builder::for_point_type::<PointXYZ>()
+//!   .build_from_points(&points)
+//!   .unwrap();
+//!
+//! let xs: Vec<f32> = cloud.iter::<f32>("x").unwrap().collect();
+//! assert_eq!(xs, vec![1.0, 4.0]);
+//! assert_eq!(cloud.read_points::<PointXYZ>().unwrap(), points);
+//! ```
+
+use std::{
+  convert::{TryFrom, TryInto},
+  fmt,
+  marker::PhantomData,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{message::Message, std_msgs::Header};
+
+/// Describes one named field in the points of a [`PointCloud2`].
+///
+/// [PointField](https://github.com/ros2/common_interfaces/blob/rolling/sensor_msgs/msg/PointField.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PointField {
+  /// Name of field
+  pub name: String,
+  /// Offset from start of point struct
+  pub offset: u32,
+  /// Datatype enumeration, see constants below
+  pub datatype: u8,
+  /// How many elements in the field
+  pub count: u32,
+}
+impl Message for PointField {}
+
+impl PointField {
+  pub const INT8: u8 = 1;
+  pub const UINT8: u8 = 2;
+  pub const INT16: u8 = 3;
+  pub const UINT16: u8 = 4;
+  pub const INT32: u8 = 5;
+  pub const UINT32: u8 = 6;
+  pub const FLOAT32: u8 = 7;
+  pub const FLOAT64: u8 = 8;
+
+  pub fn new(name: &str, offset: u32, datatype: u8, count: u32) -> Self {
+    PointField {
+      name: name.to_owned(),
+      offset,
+      datatype,
+      count,
+    }
+  }
+
+  /// Size in bytes of one element of the given datatype, or `None` if the
+  /// datatype is not one of the defined constants.
+  pub fn datatype_size(datatype: u8) -> Option<usize> {
+    match datatype {
+      Self::INT8 | Self::UINT8 => Some(1),
+      Self::INT16 | Self::UINT16 => Some(2),
+      Self::INT32 | Self::UINT32 | Self::FLOAT32 => Some(4),
+      Self::FLOAT64 => Some(8),
+      _ => None,
+    }
+  }
+
+  /// Total size of this field in bytes, i.e. element size times `count`.
+  pub fn byte_size(&self) -> Option<usize> {
+    Self::datatype_size(self.datatype).map(|s| s * self.count as usize)
+  }
+}
+
+/// A collection of N-dimensional points, which may contain additional
+/// information such as normals, intensity, etc.
+///
+/// [PointCloud2](https://github.com/ros2/common_interfaces/blob/rolling/sensor_msgs/msg/PointCloud2.msg)
+///
+/// If the cloud is unordered, `height` is 1 and `width` is the length of the
+/// point cloud.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PointCloud2 {
+  pub header: Header,
+  /// 2D structure of the point cloud.
+  pub height: u32,
+  pub width: u32,
+  /// Describes the channels and their layout in the binary data blob.
+  pub fields: Vec<PointField>,
+  /// Is this data bigendian?
+  pub is_bigendian: bool,
+  /// Length of a point in bytes
+  pub point_step: u32,
+  /// Length of a row in bytes
+  pub row_step: u32,
+  /// Actual point data, size is (row_step*height)
+  pub data: Vec<u8>,
+  /// True if there are no invalid points
+  pub is_dense: bool,
+}
+impl Message for PointCloud2 {}
+
+/// What went wrong when accessing or building a [`PointCloud2`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointCloudError {
+  NoSuchField(String),
+  DuplicateField(String),
+  TypeMismatch {
+    field: String,
+    expected: u8,
+    actual: u8,
+  },
+  IndexOutOfRange {
+    field: String,
+    index: u32,
+    count: u32,
+  },
+  /// Offsets, steps, dimensions and data length are not consistent.
+  BadLayout(String),
+}
+
+impl fmt::Display for PointCloudError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Self::NoSuchField(name) => write!(f, "No field named {name:?} in point cloud"),
+      Self::DuplicateField(name) => write!(f, "Field {name:?} defined more than once"),
+      Self::TypeMismatch {
+        field,
+        expected,
+        actual,
+      } => write!(
+        f,
+        "Field {field:?} has datatype {actual}, but datatype {expected} was requested"
+      ),
+      Self::IndexOutOfRange {
+        field,
+        index,
+        count,
+      } => write!(
+        f,
+        "Element index {index} out of range for field {field:?} with count {count}"
+      ),
+      Self::BadLayout(reason) => write!(f, "Inconsistent point cloud layout: {reason}"),
+    }
+  }
+}
+
+impl std::error::Error for PointCloudError {}
+
+/// Rust types that can be stored in a [`PointField`].
+///
+/// This is implemented for all the primitive types that have a corresponding
+/// `PointField` datatype constant.
+pub trait PointFieldType: Copy {
+  /// The corresponding `PointField` datatype constant
+  const DATATYPE: u8;
+  /// Size in bytes
+  const SIZE: usize;
+
+  /// Decode from the beginning of `bytes`, which must be at least `SIZE` long.
+  fn read_from(bytes: &[u8], big_endian: bool) -> Self;
+  /// Encode to the beginning of `bytes`, which must be at least `SIZE` long.
+  fn write_to(self, bytes: &mut [u8], big_endian: bool);
+}
+
+macro_rules! impl_point_field_type {
+  ($t:ty, $datatype:expr) => {
+    impl PointFieldType for $t {
+      const DATATYPE: u8 = $datatype;
+      const SIZE: usize = std::mem::size_of::<$t>();
+
+      fn read_from(bytes: &[u8], big_endian: bool) -> Self {
+        // Slice length is checked by callers, so unwrap cannot fail.
+        let b = bytes[..Self::SIZE].try_into().unwrap();
+        if big_endian {
+          <$t>::from_be_bytes(b)
+        } else {
+          <$t>::from_le_bytes(b)
+        }
+      }
+
+      fn write_to(self, bytes: &mut [u8], big_endian: bool) {
+        let b = if big_endian {
+          self.to_be_bytes()
+        } else {
+          self.to_le_bytes()
+        };
+        bytes[..Self::SIZE].copy_from_slice(&b);
+      }
+    }
+  };
+}
+
+impl_point_field_type!(i8, PointField::INT8);
+impl_point_field_type!(u8, PointField::UINT8);
+impl_point_field_type!(i16, PointField::INT16);
+impl_point_field_type!(u16, PointField::UINT16);
+impl_point_field_type!(i32, PointField::INT32);
+impl_point_field_type!(u32, PointField::UINT32);
+impl_point_field_type!(f32, PointField::FLOAT32);
+impl_point_field_type!(f64, PointField::FLOAT64);
+
+// Finds the byte offset of element `index` of field `name` within a point,
+// checking that the requested type matches the declared datatype.
+fn locate_field<T: PointFieldType>(
+  fields: &[PointField],
+  name: &str,
+  index: u32,
+) -> Result<usize, PointCloudError> {
+  let field = fields
+    .iter()
+    .find(|f| f.name == name)
+    .ok_or_else(|| PointCloudError::NoSuchField(name.to_owned()))?;
+  if field.datatype != T::DATATYPE {
+    return Err(PointCloudError::TypeMismatch {
+      field: name.to_owned(),
+      expected: T::DATATYPE,
+      actual: field.datatype,
+    });
+  }
+  if index >= field.count {
+    return Err(PointCloudError::IndexOutOfRange {
+      field: name.to_owned(),
+      index,
+      count: field.count,
+    });
+  }
+  Ok(field.offset as usize + index as usize * T::SIZE)
+}
+
+impl PointCloud2 {
+  /// Number of points, i.e. `width * height`
+  pub fn point_count(&self) -> usize {
+    self.width as usize * self.height as usize
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.point_count() == 0
+  }
+
+  /// Look up a field description by name.
+  pub fn field(&self, name: &str) -> Option<&PointField> {
+    self.fields.iter().find(|f| f.name == name)
+  }
+
+  /// Check that the layout description is consistent with the data.
+  ///
+  /// All fields must fit inside `point_step`, a row of points must fit inside
+  /// `row_step`, and `data` must contain `height` rows.
+  pub fn validate(&self) -> Result<(), PointCloudError> {
+    for field in &self.fields {
+      let size = field.byte_size().ok_or_else(|| {
+        PointCloudError::BadLayout(format!(
+          "field {:?} has unknown datatype {}",
+          field.name, field.datatype
+        ))
+      })?;
+      if field.offset as usize + size > self.point_step as usize {
+        return Err(PointCloudError::BadLayout(format!(
+          "field {:?} does not fit in point_step {}",
+          field.name, self.point_step
+        )));
+      }
+    }
+    if (self.width as usize) * (self.point_step as usize) > self.row_step as usize {
+      return Err(PointCloudError::BadLayout(format!(
+        "width {} * point_step {} exceeds row_step {}",
+        self.width, self.point_step, self.row_step
+      )));
+    }
+    if self.data.len() < self.height as usize * self.row_step as usize {
+      return Err(PointCloudError::BadLayout(format!(
+        "data length {} is less than height {} * row_step {}",
+        self.data.len(),
+        self.height,
+        self.row_step
+      )));
+    }
+    Ok(())
+  }
+
+  // Byte offset of a point in `data`. Points are numbered row by row.
+  fn point_offset(&self, point_index: usize) -> usize {
+    let width = self.width.max(1) as usize;
+    let row = point_index / width;
+    let col = point_index % width;
+    row * self.row_step as usize + col * self.point_step as usize
+  }
+
+  /// Iterate over the values of a single field, one value per point.
+  ///
+  /// For fields with `count > 1`, this yields the first element only. Use
+  /// [`Self::iter_element`] for the other elements.
+  pub fn iter<T: PointFieldType>(&self, name: &str) -> Result<FieldIter<'_, T>, PointCloudError> {
+    self.iter_element(name, 0)
+  }
+
+  /// Iterate over element `index` of a multi-element field.
+  pub fn iter_element<T: PointFieldType>(
+    &self,
+    name: &str,
+    index: u32,
+  ) -> Result<FieldIter<'_, T>, PointCloudError> {
+    self.validate()?;
+    let offset_in_point = locate_field::<T>(&self.fields, name, index)?;
+    Ok(FieldIter {
+      cloud: self,
+      offset_in_point,
+      next_point: 0,
+      phantom: PhantomData,
+    })
+  }
+
+  /// Access a single point.
+  pub fn point(&self, point_index: usize) -> Result<PointView<'_>, PointCloudError> {
+    self.validate()?;
+    if point_index >= self.point_count() {
+      return Err(PointCloudError::BadLayout(format!(
+        "point index {point_index} out of range, point count is {}",
+        self.point_count()
+      )));
+    }
+    Ok(self.point_unchecked(point_index))
+  }
+
+  fn point_unchecked(&self, point_index: usize) -> PointView<'_> {
+    let start = self.point_offset(point_index);
+    PointView {
+      fields: &self.fields,
+      bytes: &self.data[start..start + self.point_step as usize],
+      big_endian: self.is_bigendian,
+    }
+  }
+
+  /// Iterate over all points.
+  pub fn points(&self) -> Result<impl ExactSizeIterator<Item = PointView<'_>>, PointCloudError> {
+    self.validate()?;
+    Ok((0..self.point_count()).map(move |i| self.point_unchecked(i)))
+  }
+
+  /// Decode all points into a Rust struct type.
+  pub fn read_points<P: PointStruct>(&self) -> Result<Vec<P>, PointCloudError> {
+    self.points()?.map(|p| P::read_point(&p)).collect()
+  }
+}
+
+/// Iterator over the values of one field in a [`PointCloud2`]
+///
+/// Produced by [`PointCloud2::iter`].
+pub struct FieldIter<'a, T> {
+  cloud: &'a PointCloud2,
+  offset_in_point: usize,
+  next_point: usize,
+  phantom: PhantomData<T>,
+}
+
+impl<T: PointFieldType> Iterator for FieldIter<'_, T> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<T> {
+    if self.next_point >= self.cloud.point_count() {
+      None
+    } else {
+      let start = self.cloud.point_offset(self.next_point) + self.offset_in_point;
+      self.next_point += 1;
+      // Bounds were checked by validate() when the iterator was created.
+      Some(T::read_from(
+        &self.cloud.data[start..],
+        self.cloud.is_bigendian,
+      ))
+    }
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let remaining = self.cloud.point_count() - self.next_point;
+    (remaining, Some(remaining))
+  }
+}
+
+impl<T: PointFieldType> ExactSizeIterator for FieldIter<'_, T> {}
+
+/// Read access to one point in a [`PointCloud2`]
+pub struct PointView<'a> {
+  fields: &'a [PointField],
+  bytes: &'a [u8],
+  big_endian: bool,
+}
+
+impl PointView<'_> {
+  /// Get the (first element of the) named field.
+  pub fn get<T: PointFieldType>(&self, name: &str) -> Result<T, PointCloudError> {
+    self.get_element(name, 0)
+  }
+
+  pub fn get_element<T: PointFieldType>(
+    &self,
+    name: &str,
+    index: u32,
+  ) -> Result<T, PointCloudError> {
+    let offset = locate_field::<T>(self.fields, name, index)?;
+    Ok(T::read_from(&self.bytes[offset..], self.big_endian))
+  }
+
+  /// Raw bytes of this point, `point_step` long.
+  pub fn bytes(&self) -> &[u8] {
+    self.bytes
+  }
+}
+
+/// Write access to one point, used when building a [`PointCloud2`]
+pub struct PointMut<'a> {
+  fields: &'a [PointField],
+  bytes: &'a mut [u8],
+}
+
+impl PointMut<'_> {
+  pub fn set<T: PointFieldType>(&mut self, name: &str, value: T) -> Result<(), PointCloudError> {
+    self.set_element(name, 0, value)
+  }
+
+  pub fn set_element<T: PointFieldType>(
+    &mut self,
+    name: &str,
+    index: u32,
+    value: T,
+  ) -> Result<(), PointCloudError> {
+    let offset = locate_field::<T>(self.fields, name, index)?;
+    value.write_to(&mut self.bytes[offset..], false);
+    Ok(())
+  }
+}
+
+/// Mapping between a Rust struct and the points of a [`PointCloud2`].
+///
+/// Implement this for your own point types to use
+/// [`PointCloud2::read_points`] and
+/// [`PointCloud2Builder::build_from_points`].
+pub trait PointStruct: Sized {
+  /// Declare the fields of this point type, in order.
+  fn declare_fields(builder: PointCloud2Builder) -> PointCloud2Builder;
+  fn read_point(point: &PointView<'_>) -> Result<Self, PointCloudError>;
+  fn write_point(&self, point: &mut PointMut<'_>) -> Result<(), PointCloudError>;
+}
+
+/// Plain 3D point, as in PCL `pcl::PointXYZ` (without padding)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PointXYZ {
+  pub x: f32,
+  pub y: f32,
+  pub z: f32,
+}
+
+impl PointStruct for PointXYZ {
+  fn declare_fields(builder: PointCloud2Builder) -> PointCloud2Builder {
+    builder
+      .field::<f32>("x")
+      .field::<f32>("y")
+      .field::<f32>("z")
+  }
+
+  fn read_point(point: &PointView<'_>) -> Result<Self, PointCloudError> {
+    Ok(PointXYZ {
+      x: point.get("x")?,
+      y: point.get("y")?,
+      z: point.get("z")?,
+    })
+  }
+
+  fn write_point(&self, point: &mut PointMut<'_>) -> Result<(), PointCloudError> {
+    point.set("x", self.x)?;
+    point.set("y", self.y)?;
+    point.set("z", self.z)
+  }
+}
+
+/// 3D point with intensity, as in PCL `pcl::PointXYZI` (without padding)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PointXYZI {
+  pub x: f32,
+  pub y: f32,
+  pub z: f32,
+  pub intensity: f32,
+}
+
+impl PointStruct for PointXYZI {
+  fn declare_fields(builder: PointCloud2Builder) -> PointCloud2Builder {
+    builder
+      .field::<f32>("x")
+      .field::<f32>("y")
+      .field::<f32>("z")
+      .field::<f32>("intensity")
+  }
+
+  fn read_point(point: &PointView<'_>) -> Result<Self, PointCloudError> {
+    Ok(PointXYZI {
+      x: point.get("x")?,
+      y: point.get("y")?,
+      z: point.get("z")?,
+      intensity: point.get("intensity")?,
+    })
+  }
+
+  fn write_point(&self, point: &mut PointMut<'_>) -> Result<(), PointCloudError> {
+    point.set("x", self.x)?;
+    point.set("y", self.y)?;
+    point.set("z", self.z)?;
+    point.set("intensity", self.intensity)
+  }
+}
+
+/// Builder for [`PointCloud2`] messages.
+///
+/// Fields are laid out in declaration order without padding, and
+/// `point_step` and `row_step` are computed accordingly. Data is written in
+/// little-endian byte order.
+#[must_use]
+#[derive(Clone, Debug, Default)]
+pub struct PointCloud2Builder {
+  header: Header,
+  fields: Vec<PointField>,
+  point_step: u32,
+  dimensions: Option<(u32, u32)>, // (width, height)
+  is_dense: bool,
+}
+
+impl PointCloud2Builder {
+  pub fn new() -> Self {
+    PointCloud2Builder {
+      is_dense: true,
+      ..Default::default()
+    }
+  }
+
+  /// Builder with the fields of a [`PointStruct`] type already declared.
+  pub fn for_point_type<P: PointStruct>() -> Self {
+    P::declare_fields(Self::new())
+  }
+
+  pub fn header(self, header: Header) -> Self {
+    PointCloud2Builder { header, ..self }
+  }
+
+  /// Declare a single-element field.
+  pub fn field<T: PointFieldType>(self, name: &str) -> Self {
+    self.field_array::<T>(name, 1)
+  }
+
+  /// Declare a field with `count` elements.
+  pub fn field_array<T: PointFieldType>(mut self, name: &str, count: u32) -> Self {
+    self
+      .fields
+      .push(PointField::new(name, self.point_step, T::DATATYPE, count));
+    self.point_step += T::SIZE as u32 * count;
+    self
+  }
+
+  /// Skip `bytes` bytes in the point layout, e.g. to match an existing
+  /// padded layout.
+  pub fn padding(mut self, bytes: u32) -> Self {
+    self.point_step += bytes;
+    self
+  }
+
+  /// Make an organized (2D) point cloud. The number of points given to
+  /// `build_*` must then be `width * height`.
+  ///
+  /// By default, the cloud is unorganized, i.e. `height` is 1.
+  pub fn dimensions(self, width: u32, height: u32) -> Self {
+    PointCloud2Builder {
+      dimensions: Some((width, height)),
+      ..self
+    }
+  }
+
+  pub fn is_dense(self, is_dense: bool) -> Self {
+    PointCloud2Builder { is_dense, ..self }
+  }
+
+  /// Build a point cloud of `point_count` points, using `fill` to write the
+  /// contents of each point. Unwritten fields are zero.
+  pub fn build_with<F>(
+    self,
+    point_count: usize,
+    mut fill: F,
+  ) -> Result<PointCloud2, PointCloudError>
+  where
+    F: FnMut(usize, &mut PointMut<'_>) -> Result<(), PointCloudError>,
+  {
+    for (i, f) in self.fields.iter().enumerate() {
+      if self.fields[..i].iter().any(|g| g.name == f.name) {
+        return Err(PointCloudError::DuplicateField(f.name.clone()));
+      }
+    }
+
+    let (width, height) = match self.dimensions {
+      Some((w, h)) if w as usize * h as usize == point_count => (w, h),
+      Some((w, h)) => {
+        return Err(PointCloudError::BadLayout(format!(
+          "{point_count} points do not fill dimensions {w} x {h}"
+        )))
+      }
+      None => {
+        let w = u32::try_from(point_count)
+          .map_err(|_| PointCloudError::BadLayout("too many points".to_owned()))?;
+        (w, 1)
+      }
+    };
+
+    let row_step = self.point_step.checked_mul(width).ok_or_else(|| {
+      PointCloudError::BadLayout(format!(
+        "point_step {} * width {width} does not fit in row_step",
+        self.point_step
+      ))
+    })?;
+
+    let point_step = self.point_step as usize;
+    let mut data = vec![0; point_count * point_step];
+    if point_step > 0 {
+      for (i, chunk) in data.chunks_exact_mut(point_step).enumerate() {
+        let mut point = PointMut {
+          fields: &self.fields,
+          bytes: chunk,
+        };
+        fill(i, &mut point)?;
+      }
+    }
+
+    Ok(PointCloud2 {
+      header: self.header,
+      height,
+      width,
+      fields: self.fields,
+      is_bigendian: false,
+      point_step: self.point_step,
+      row_step,
+      data,
+      is_dense: self.is_dense,
+    })
+  }
+
+  /// Build a point cloud from a slice of [`PointStruct`]s.
+  pub fn build_from_points<P: PointStruct>(
+    self,
+    points: &[P],
+  ) -> Result<PointCloud2, PointCloudError> {
+    self.build_with(points.len(), |i, point| points[i].write_point(point))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn builder_layout() {
+    let cloud = PointCloud2Builder::new()
+      .field::<f32>("x")
+      .field::<u8>("ring")
+      .field_array::<f64>("cov", 2)
+      .build_with(3, |i, p| {
+        p.set("x", i as f32)?;
+        p.set("ring", (10 + i) as u8)?;
+        p.set_element("cov", 1, -(i as f64))
+      })
+      .unwrap();
+
+    assert_eq!(cloud.point_step, 4 + 1 + 16);
+    assert_eq!(cloud.row_step, 3 * cloud.point_step);
+    assert_eq!(cloud.field("cov").unwrap().offset, 5);
+    assert_eq!(
+      cloud.iter::<f32>("x").unwrap().collect::<Vec<_>>(),
+      vec![0.0, 1.0, 2.0]
+    );
+    assert_eq!(
+      cloud.iter::<u8>("ring").unwrap().collect::<Vec<_>>(),
+      vec![10, 11, 12]
+    );
+    assert_eq!(
+      cloud
+        .iter_element::<f64>("cov", 1)
+        .unwrap()
+        .collect::<Vec<_>>(),
+      vec![0.0, -1.0, -2.0]
+    );
+  }
+
+  #[test]
+  fn access_errors() {
+    let cloud = PointCloud2Builder::for_point_type::<PointXYZ>()
+      .build_from_points(&[PointXYZ::default()])
+      .unwrap();
+
+    assert!(matches!(
+      cloud.iter::<f64>("x"),
+      Err(PointCloudError::TypeMismatch { .. })
+    ));
+    assert!(matches!(
+      cloud.iter::<f32>("rgb"),
+      Err(PointCloudError::NoSuchField(_))
+    ));
+    assert!(matches!(
+      cloud.iter_element::<f32>("x", 1),
+      Err(PointCloudError::IndexOutOfRange { .. })
+    ));
+
+    let mut truncated = cloud.clone();
+    truncated.data.pop();
+    assert!(matches!(
+      truncated.iter::<f32>("x"),
+      Err(PointCloudError::BadLayout(_))
+    ));
+
+    let duplicate = PointCloud2Builder::new()
+      .field::<f32>("x")
+      .field::<f32>("x")
+      .build_with(0, |_, _| Ok(()));
+    assert!(matches!(duplicate, Err(PointCloudError::DuplicateField(_))));
+
+    // row_step would overflow u32. Nothing is allocated.
+    let too_wide = PointCloud2Builder::new()
+      .field::<f64>("x")
+      .dimensions(1 << 30, 1)
+      .build_with(1 << 30, |_, _| Ok(()));
+    assert!(matches!(too_wide, Err(PointCloudError::BadLayout(_))));
+  }
+
+  #[test]
+  fn organized_with_row_padding() {
+    // 2 x 2 cloud where each row has 4 extra padding bytes
+    let mut cloud = PointCloud2Builder::new()
+      .field::<u16>("v")
+      .dimensions(2, 2)
+      .build_with(4, |i, p| p.set("v", i as u16))
+      .unwrap();
+    let mut padded = Vec::new();
+    for row in cloud.data.chunks(cloud.row_step as usize) {
+      padded.extend_from_slice(row);
+      padded.extend_from_slice(&[0xff; 4]);
+    }
+    cloud.row_step += 4;
+    cloud.data = padded;
+
+    assert_eq!(
+      cloud.iter::<u16>("v").unwrap().collect::<Vec<_>>(),
+      vec![0, 1, 2, 3]
+    );
+    assert_eq!(cloud.point(3).unwrap().get::<u16>("v"), Ok(3));
+  }
+
+  #[test]
+  fn big_endian() {
+    let cloud = PointCloud2 {
+      header: Header::default(),
+      height: 1,
+      width: 1,
+      fields: vec![PointField::new("i", 0, PointField::INT32, 1)],
+      is_bigendian: true,
+      point_step: 4,
+      row_step: 4,
+      data: vec![0, 0, 1, 2],
+      is_dense: true,
+    };
+    assert_eq!(cloud.iter::<i32>("i").unwrap().next(), Some(0x0102));
+  }
+}
@@ -1,16 +1,24 @@
-use std::{io, sync::atomic};
+use std::{io, sync::atomic, time::Duration};
 
 use mio::{Evented, Poll, PollOpt, Ready, Token};
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
-use futures::{join, pin_mut, StreamExt};
+use async_io::Timer;
+use futures::{join, pin_mut, try_join, FutureExt, StreamExt};
 use rustdds::{
   dds::{CreateResult, ReadError, ReadResult, WriteError, WriteResult},
   rpc::*,
   *,
 };
 
-use crate::{message_info::MessageInfo, node::Node, service::*};
+use crate::{
+  error::{Error, WaitTimeout},
+  message_info::MessageInfo,
+  names::{Name, ServiceTypeName},
+  node::{EndpointRegistration, Node},
+  service::{introspection::Introspection, *},
+  service_msgs::event_type,
+};
 
 /// Client end of a ROS2 Service
 pub struct Client<S>
@@ -24,15 +32,25 @@ where
   response_receiver: SimpleDataReaderR<ResponseWrapper<S::Response>>,
   sequence_number_gen: atomic::AtomicI64, // used by basic and cyclone
   client_guid: GUID,                      // used by the Cyclone ServiceMapping
+  service_name: Name,
+  service_type_name: ServiceTypeName,
+  introspection: Option<Introspection>,
+  // Remove the request Writer and response Reader from ROS Discovery when
+  // dropped
+  _registrations: [EndpointRegistration; 2],
 }
 
 impl<S> Client<S>
 where
   S: 'static + Service,
+  S::Request: Clone,
 {
+  #[allow(clippy::too_many_arguments)]
   pub(crate) fn new(
     service_mapping: ServiceMapping,
     node: &mut Node,
+    service_name: &Name,
+    service_type_name: &ServiceTypeName,
     request_topic: &Topic,
     response_topic: &Topic,
     qos_request: Option<QosPolicies>,
@@ -53,18 +71,41 @@ where
       response_topic.name()
     );
     let client_guid = request_sender.guid();
+    let _registrations = [
+      node.endpoint_registration(request_sender.guid().into()),
+      node.endpoint_registration(response_receiver.guid().into()),
+    ];
     Ok(Client::<S> {
       service_mapping,
       request_sender,
       response_receiver,
       sequence_number_gen: atomic::AtomicI64::new(SequenceNumber::default().into()),
       client_guid,
+      service_name: service_name.clone(),
+      service_type_name: service_type_name.clone(),
+      introspection: None,
+      _registrations,
     })
   }
 
+  /// Enables or disables publishing introspection events of this Client.
+  ///
+  /// See [`introspection`](crate::service::introspection). `my_node` must be
+  /// the Node that created this Client.
+  pub fn configure_introspection(
+    &mut self,
+    my_node: &mut Node,
+    level: IntrospectionLevel,
+  ) -> Result<(), Error> {
+    self.introspection =
+      Introspection::new(my_node, &self.service_name, &self.service_type_name, level)?;
+    Ok(())
+  }
+
   /// Send a request to Service Server.
   /// The returned `RmwRequestId` is a token to identify the correct response.
   pub fn send_request(&self, request: S::Request) -> WriteResult<RmwRequestId, ()> {
+    let event_request = self.event_request(&request);
     self.increment_sequence_number();
     let gen_rmw_req_id = RmwRequestId {
       writer_guid: self.client_guid,
@@ -89,10 +130,17 @@ where
       .map(RmwRequestId::from)
       .map_err(|e| e.forget_data())?;
 
-    match self.service_mapping {
-      ServiceMapping::Enhanced => Ok(sent_rmw_req_id),
-      ServiceMapping::Basic | ServiceMapping::Cyclone => Ok(gen_rmw_req_id),
-    }
+    let req_id = match self.service_mapping {
+      ServiceMapping::Enhanced => sent_rmw_req_id,
+      ServiceMapping::Basic | ServiceMapping::Cyclone => gen_rmw_req_id,
+    };
+    self.publish_event(
+      event_type::REQUEST_SENT,
+      req_id,
+      event_request.as_ref(),
+      None,
+    );
+    Ok(req_id)
   }
 
   /// Receive a response from Server
@@ -112,6 +160,7 @@ where
         let mi = MessageInfo::from(&dcc);
         let res_wrapper = dcc.into_value();
         let (ri, res) = res_wrapper.unwrap(self.service_mapping, mi, self.client_guid)?;
+        self.publish_event(event_type::RESPONSE_RECEIVED, ri, None, Some(&res));
         Ok(Some((ri, res)))
       }
     } // match
@@ -120,6 +169,7 @@ where
   /// Send a request to Service Server asynchronously.
   /// The returned `RmwRequestId` is a token to identify the correct response.
   pub async fn async_send_request(&self, request: S::Request) -> WriteResult<RmwRequestId, ()> {
+    let event_request = self.event_request(&request);
     let gen_rmw_req_id =
       // we do the req_id generation in an async block so that we do not generate
       // multiple sequence numbers if there are multiple polls to this function
@@ -160,6 +210,12 @@ where
       req_id,
       self.request_sender.topic().name()
     );
+    self.publish_event(
+      event_type::REQUEST_SENT,
+      req_id,
+      event_request.as_ref(),
+      None,
+    );
     Ok(req_id)
   }
 
@@ -167,6 +223,18 @@ where
   /// The returned Future does not complete until the response has been
   /// received.
   pub async fn async_receive_response(&self, request_id: RmwRequestId) -> ReadResult<S::Response> {
+    self
+      .receive_response_to(&[request_id])
+      .await
+      .map(|(_, response)| response)
+  }
+
+  // Waits for a response to any of `request_ids`. Responses to other requests
+  // are discarded.
+  async fn receive_response_to(
+    &self,
+    request_ids: &[RmwRequestId],
+  ) -> ReadResult<(RmwRequestId, S::Response)> {
     let dcc_stream = self.response_receiver.as_async_stream();
     pin_mut!(dcc_stream);
 
@@ -179,12 +247,13 @@ where
             dcc
               .into_value()
               .unwrap(self.service_mapping, mi, self.client_guid)?;
-          if req_id == request_id {
-            return Ok(response);
+          if request_ids.contains(&req_id) {
+            self.publish_event(event_type::RESPONSE_RECEIVED, req_id, None, Some(&response));
+            return Ok((req_id, response));
           } else {
             debug!(
               "Received response for someone else. expected={:?}  received={:?}",
-              request_id, req_id
+              request_ids, req_id
             );
             continue; //
           }
@@ -206,6 +275,44 @@ where
       .map_err(CallServiceError::from)
   }
 
+  /// Calls the Service, waiting at most `options.timeout` for each attempt.
+  ///
+  /// If there is no response in time, the request is sent again, up to
+  /// `options.retries` times. A late response to an earlier attempt is
+  /// accepted, too, so the Server may process the request more than once.
+  /// Returns [`CallServiceError::CallTimeout`] if no attempt was answered.
+  ///
+  /// Nothing is left waiting after this returns: responses to abandoned
+  /// attempts are discarded when they arrive.
+  pub async fn async_call_service_with(
+    &self,
+    request: S::Request,
+    options: CallOptions,
+  ) -> Result<S::Response, CallServiceError<()>> {
+    let mut sent = Vec::new();
+    let mut backoff = options.backoff;
+    for attempt in 0..=options.retries {
+      if attempt > 0 {
+        debug!(
+          "Service call timed out on {:?}, retry {attempt} in {backoff:?}",
+          self.request_sender.topic().name()
+        );
+        // Responses may still come in during the backoff.
+        futures::select! {
+          result = self.receive_response_to(&sent).fuse() => return Ok(result?.1),
+          _ = FutureExt::fuse(Timer::after(backoff)) => {}
+        }
+        backoff = backoff.saturating_mul(2);
+      }
+      sent.push(self.async_send_request(request.clone()).await?);
+      futures::select! {
+        result = self.receive_response_to(&sent).fuse() => return Ok(result?.1),
+        _ = FutureExt::fuse(Timer::after(options.timeout)) => {}
+      }
+    }
+    Err(CallServiceError::CallTimeout)
+  }
+
   /// Wait for a Server to be connected to the Request and Response topics.
   ///
   /// This does not distinguish between diagnostinc tools and actual servers.
@@ -220,6 +327,61 @@ where
     );
   }
 
+  /// Like [`Self::wait_for_service`], but gives up after `timeout`.
+  ///
+  /// Returns `Ok` when both the Request and Response topics have a matched
+  /// Server side. The Node must have a Spinner running, or this times out.
+  pub async fn wait_for_service_timeout(
+    &self,
+    my_node: &Node,
+    timeout: std::time::Duration,
+  ) -> Result<(), WaitTimeout> {
+    try_join!(
+      my_node.wait_for_readers(self.request_sender.guid(), 1, timeout),
+      my_node.wait_for_writers(self.response_receiver.guid(), 1, timeout)
+    )
+    .map(|_| ())
+  }
+
+  /// Flushes pending requests and deletes this Client.
+  ///
+  /// This waits until the requests have been acknowledged by the Servers,
+  /// which may take forever if one of them stops responding. Add a timeout,
+  /// if needed. Then the Client is removed from DDS and ROS discovery before
+  /// returning.
+  ///
+  /// `my_node` must be the Node that created this Client.
+  pub async fn close(self, my_node: &mut Node) -> WriteResult<(), ()> {
+    self.request_sender.async_wait_for_acknowledgments().await?;
+    let reader_gid = self.response_receiver.guid().into();
+    let writer_gid = self.request_sender.guid().into();
+    drop(self);
+    my_node.remove_reader(reader_gid);
+    my_node.remove_writer(writer_gid);
+    Ok(())
+  }
+
+  // Copy of the request for an introspection event, if it is going to be
+  // published
+  fn event_request(&self, request: &S::Request) -> Option<S::Request> {
+    match self.introspection {
+      Some(ref i) if i.contents() => Some(request.clone()),
+      _ => None,
+    }
+  }
+
+  fn publish_event(
+    &self,
+    event_type: u8,
+    request_id: RmwRequestId,
+    request: Option<&S::Request>,
+    response: Option<&S::Response>,
+  ) {
+    if let Some(ref introspection) = self.introspection {
+      introspection.publish(event_type, request_id, request, response);
+    }
+  }
+
   fn increment_sequence_number(&self) {
     self
       .sequence_number_gen
@@ -234,10 +396,55 @@ where
   }
 }
 
+/// Timeout and retry policy for [`Client::async_call_service_with`]
+///
+/// This is a builder-like struct. By default, a call waits 5 seconds for a
+/// response, and is not retried.
+#[must_use]
+#[derive(Clone, Copy, Debug)]
+pub struct CallOptions {
+  pub(crate) timeout: Duration,
+  pub(crate) retries: u32,
+  pub(crate) backoff: Duration,
+}
+
+impl CallOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// How long to wait for a response to each attempt
+  pub fn timeout(self, timeout: Duration) -> Self {
+    CallOptions { timeout, ..self }
+  }
+
+  /// How many times to send the request again after a timeout
+  pub fn retries(self, retries: u32) -> Self {
+    CallOptions { retries, ..self }
+  }
+
+  /// Delay before the first retry. It doubles for each further retry.
+  pub fn backoff(self, backoff: Duration) -> Self {
+    CallOptions { backoff, ..self }
+  }
+}
+
+impl Default for CallOptions {
+  fn default() -> Self {
+    CallOptions {
+      timeout: Duration::from_secs(5),
+      retries: 0,
+      backoff: Duration::from_millis(100),
+    }
+  }
+}
+
 #[derive(Debug)]
 pub enum CallServiceError<T> {
   WriteError(WriteError<T>),
   ReadError(ReadError),
+  /// No response within the timeout. See [`Client::async_call_service_with`].
+  CallTimeout,
 }
 impl<T> From<WriteError<T>> for CallServiceError<T> {
   fn from(value: WriteError<T>) -> Self {
@@ -0,0 +1,110 @@
+//! Service introspection events, as seen by `ros2 service echo`
+//!
+//! When enabled with
+//! [`Client::configure_introspection`](super::Client::configure_introspection)
+//! or
+//! [`Server::configure_introspection`](super::Server::configure_introspection),
+//! every request and response is reported as a
+//! [`ServiceEventMessage`](crate::service_msgs::ServiceEventMessage) on the
+//! Topic `<service name>/_service_event`.
+
+#[allow(unused_imports)]
+use log::{debug, error, info, warn};
+use ros2_client_core::cdr::{self, Endianness};
+use serde::Serialize;
+
+use crate::{
+  builtin_interfaces::Time,
+  error::Error,
+  message::SerializedMessage,
+  names::{Name, ServiceTypeName},
+  node::Node,
+  pubsub::Publisher,
+  service::{default_service_qos, RmwRequestId},
+  service_msgs::ServiceEventInfo,
+  ROSTime,
+};
+
+/// How much of each request and response is published as introspection
+/// events
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum IntrospectionLevel {
+  /// No events are published.
+  #[default]
+  Off,
+  /// Events only tell what happened, to which request and when.
+  Metadata,
+  /// Events also contain the request or response message.
+  Contents,
+}
+
+pub(crate) struct Introspection {
+  publisher: Publisher<SerializedMessage>,
+  level: IntrospectionLevel,
+}
+
+// Same layout as ServiceEventMessage, but borrows the messages.
+#[derive(Serialize)]
+struct EventRef<'a, Q, S> {
+  info: ServiceEventInfo,
+  request: &'a [Q],
+  response: &'a [S],
+}
+
+impl Introspection {
+  // Returns None if `level` is Off.
+  pub(crate) fn new(
+    node: &mut Node,
+    service_name: &Name,
+    service_type_name: &ServiceTypeName,
+    level: IntrospectionLevel,
+  ) -> Result<Option<Self>, Error> {
+    if level == IntrospectionLevel::Off {
+      return Ok(None);
+    }
+    let topic = node.create_topic(
+      &service_name.push("_service_event"),
+      service_type_name.event_type(),
+      &default_service_qos(),
+    )?;
+    let publisher = node.create_publisher(&topic, None)?;
+    Ok(Some(Introspection { publisher, level }))
+  }
+
+  pub(crate) fn contents(&self) -> bool {
+    self.level == IntrospectionLevel::Contents
+  }
+
+  // Publishing failures are only logged, as they should not fail the Service
+  // call itself.
+  pub(crate) fn publish<Q: Serialize, S: Serialize>(
+    &self,
+    event_type: u8,
+    request_id: RmwRequestId,
+    request: Option<&Q>,
+    response: Option<&S>,
+  ) {
+    let contents = self.contents();
+    let event = EventRef {
+      info: ServiceEventInfo {
+        event_type,
+        stamp: Time::from(ROSTime::now()),
+        client_gid: request_id.writer_guid.to_bytes(),
+        sequence_number: request_id.sequence_number.into(),
+      },
+      request: request
+        .filter(|_| contents)
+        .map_or(&[], std::slice::from_ref),
+      response: response
+        .filter(|_| contents)
+        .map_or(&[], std::slice::from_ref),
+    };
+    match cdr::to_vec(&event, Endianness::Little) {
+      Ok(bytes) => self
+        .publisher
+        .publish(SerializedMessage(bytes))
+        .unwrap_or_else(|e| warn!("Cannot publish Service event: {e:?}")),
+      Err(e) => warn!("Cannot serialize Service event: {e:?}"),
+    }
+  }
+}
@@ -1,12 +1,14 @@
 //! Implementation of ROS 2 [Services](https://docs.ros.org/en/rolling/Tutorials/Beginner-CLI-Tools/Understanding-ROS2-Services/Understanding-ROS2-Services.html)
-use std::marker::PhantomData;
+use std::{any::Any, marker::PhantomData};
 
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
+use rustdds::{policy, QosPolicies, QosPolicyBuilder};
 
-use crate::message::Message;
+use crate::{message::Message, names::ServiceTypeName};
 
 pub mod client;
+pub mod introspection;
 pub mod request_id;
 pub mod server;
 pub(super) mod wrappers;
@@ -15,6 +17,7 @@ pub use request_id::*;
 use wrappers::*;
 pub use server::*;
 pub use client::*;
+pub use introspection::IntrospectionLevel;
 
 // --------------------------------------------
 // --------------------------------------------
@@ -78,6 +81,104 @@ where
   }
 }
 
+/// A [`Service`] that knows its ROS 2 type name, so that Clients and Servers
+/// can be created without spelling it out
+///
+/// Usually implemented with [`ros2_service!`](crate::ros2_service). See
+/// [`Node::create_typed_client`](crate::Node::create_typed_client) and
+/// [`Node::create_typed_server`](crate::Node::create_typed_server).
+pub trait TypedService: Service {
+  fn service_type_name() -> ServiceTypeName;
+
+  /// QoS of both Requests and Responses. The default is that of ROS 2
+  /// Services: Reliable, Volatile and KeepLast 10.
+  fn default_qos() -> QosPolicies {
+    default_service_qos()
+  }
+}
+
+pub(crate) fn default_service_qos() -> QosPolicies {
+  QosPolicyBuilder::new()
+    .reliability(policy::Reliability::Reliable {
+      max_blocking_time: rustdds::Duration::from_millis(100),
+    })
+    .durability(policy::Durability::Volatile)
+    .history(policy::History::KeepLast { depth: 10 })
+    .build()
+}
+
+/// Defines a Service type with its Request and Response messages.
+///
+/// This generates the Request and Response structs, with `Debug`, `Clone`,
+/// `Serialize` and `Deserialize` derived, so the calling crate must depend on
+/// `serde`. It also generates a unit struct for the Service, which implements
+/// [`Service`] and [`TypedService`].
+///
+/// ```
+/// use ros2_client::{ros2_service, service::TypedService};
+///
+/// ros2_service! {
+///   /// Corresponds to `example_interfaces/srv/AddTwoInts`
+///   pub AddTwoInts in "example_interfaces" {
+///     #[derive(Default)]
+///     request AddTwoIntsRequest { pub a: i64, pub b: i64 }
+///     #[derive(Default)]
+///     response AddTwoIntsResponse { pub sum: i64 }
+///   }
+/// }
+///
+/// assert_eq!(
+///   AddTwoInts::service_type_name().dds_request_type(),
+///   "example_interfaces::srv::dds_::AddTwoInts_Request_"
+/// );
+/// ```
+#[macro_export]
+macro_rules! ros2_service {
+  (
+    $(#[$service_attr:meta])*
+    $vis:vis $service:ident in $package:literal {
+      $(#[$request_attr:meta])*
+      request $request:ident { $($request_fields:tt)* }
+      $(#[$response_attr:meta])*
+      response $response:ident { $($response_fields:tt)* }
+    }
+  ) => {
+    $(#[$service_attr])*
+    $vis struct $service;
+
+    $(#[$request_attr])*
+    #[derive(Debug, Clone, ::serde::Serialize, ::serde::Deserialize)]
+    $vis struct $request { $($request_fields)* }
+
+    impl $crate::Message for $request {}
+
+    $(#[$response_attr])*
+    #[derive(Debug, Clone, ::serde::Serialize, ::serde::Deserialize)]
+    $vis struct $response { $($response_fields)* }
+
+    impl $crate::Message for $response {}
+
+    impl $crate::Service for $service {
+      type Request = $request;
+      type Response = $response;
+
+      fn request_type_name(&self) -> &str {
+        concat!($package, "::srv::dds_::", stringify!($service), "_Request_")
+      }
+
+      fn response_type_name(&self) -> &str {
+        concat!($package, "::srv::dds_::", stringify!($service), "_Response_")
+      }
+    }
+
+    impl $crate::service::TypedService for $service {
+      fn service_type_name() -> $crate::ServiceTypeName {
+        $crate::ServiceTypeName::new($package, stringify!($service))
+      }
+    }
+  };
+}
+
 // --------------------------------------------
 // --------------------------------------------
 
@@ -116,3 +217,28 @@ pub enum ServiceMapping {
   ///   over actual network.
   Cyclone,
 }
+
+// --------------------------------------------
+// --------------------------------------------
+
+/// Service availability change in the ROS 2 Graph.
+///
+/// Produced by [`Node::watch_service`](crate::Node::watch_service).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ServiceEvent {
+  /// At least one Server is present.
+  Available,
+  /// No Servers are present.
+  Unavailable,
+}
+
+// Extracts the message from a panic payload, if it has one.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+  if let Some(s) = payload.downcast_ref::<&str>() {
+    s
+  } else if let Some(s) = payload.downcast_ref::<String>() {
+    s
+  } else {
+    "<non-string panic payload>"
+  }
+}
@@ -1,16 +1,25 @@
-use std::io;
+use std::{io, panic::AssertUnwindSafe, sync::Arc};
 
 use mio::{Evented, Poll, PollOpt, Ready, Token};
 #[allow(unused_imports)]
 use log::{debug, error, info, warn};
-use futures::{pin_mut, stream::FusedStream, StreamExt};
+use futures::{pin_mut, stream::FusedStream, Future, FutureExt, Stream, StreamExt};
 use rustdds::{
   dds::{CreateResult, ReadError, ReadResult, WriteResult},
   rpc::*,
   *,
 };
 
-use crate::{message_info::MessageInfo, node::Node, service::*};
+use crate::{
+  error::Error,
+  message_info::MessageInfo,
+  names::{Name, ServiceTypeName},
+  node::{EndpointRegistration, Node},
+  ros2::LogLevel,
+  rosout,
+  service::{introspection::Introspection, *},
+  service_msgs::event_type,
+};
 
 // --------------------------------------------
 // --------------------------------------------
@@ -23,16 +32,26 @@ where
 {
   service_mapping: ServiceMapping,
   request_receiver: SimpleDataReaderR<RequestWrapper<S::Request>>,
-  response_sender: DataWriterR<ResponseWrapper<S::Response>>,
+  // Shared with RequestHandles
+  response_sender: Arc<DataWriterR<ResponseWrapper<S::Response>>>,
+  service_name: Name,
+  service_type_name: ServiceTypeName,
+  introspection: Option<Arc<Introspection>>,
+  // Remove the request Reader and response Writer from ROS Discovery when
+  // dropped
+  _registrations: [EndpointRegistration; 2],
 }
 
 impl<S> Server<S>
 where
   S: 'static + Service,
 {
+  #[allow(clippy::too_many_arguments)]
   pub(crate) fn new(
     service_mapping: ServiceMapping,
     node: &mut Node,
+    service_name: &Name,
+    service_type_name: &ServiceTypeName,
     request_topic: &Topic,
     response_topic: &Topic,
     qos_request: Option<QosPolicies>,
@@ -53,13 +72,81 @@ where
       response_topic.name()
     );
 
+    let _registrations = [
+      node.endpoint_registration(request_receiver.guid().into()),
+      node.endpoint_registration(response_sender.guid().into()),
+    ];
+
     Ok(Server::<S> {
       service_mapping,
       request_receiver,
-      response_sender,
+      response_sender: Arc::new(response_sender),
+      service_name: service_name.clone(),
+      service_type_name: service_type_name.clone(),
+      introspection: None,
+      _registrations,
     })
   }
 
+  /// Enables or disables publishing introspection events of this Server.
+  ///
+  /// See [`introspection`](crate::service::introspection). This does not
+  /// affect responses sent through previously taken [`RequestHandle`]s.
+  /// `my_node` must be the Node that created this Server.
+  pub fn configure_introspection(
+    &mut self,
+    my_node: &mut Node,
+    level: IntrospectionLevel,
+  ) -> Result<(), Error> {
+    self.introspection =
+      Introspection::new(my_node, &self.service_name, &self.service_type_name, level)?
+        .map(Arc::new);
+    Ok(())
+  }
+
+  /// Returns the count of currently connected Clients.
+  ///
+  /// A Client is connected when both its request Writer and response Reader
+  /// are matched with this Server.
+  ///
+  /// `my_node` must be the Node that created this Server, or the result is
+  /// undefined.
+  pub fn get_client_count(&self, my_node: &Node) -> usize {
+    my_node
+      .get_server_clients(self.request_receiver.guid(), self.response_sender.guid())
+      .len()
+  }
+
+  /// Stream of Client connect and disconnect events.
+  ///
+  /// The stream starts with `Connected` events for the currently connected
+  /// Clients. There must be a Spinner running in `my_node`, see
+  /// [`Node::status_receiver`].
+  ///
+  /// `my_node` must be the Node that created this Server, or the result is
+  /// undefined.
+  pub fn client_events(&self, my_node: &Node) -> impl Stream<Item = ClientEvent> {
+    my_node.server_client_events(self.request_receiver.guid(), self.response_sender.guid())
+  }
+
+  /// Flushes pending responses and deletes this Server.
+  ///
+  /// This waits until the responses have been acknowledged by the Clients,
+  /// which may take forever if one of them stops responding. Add a timeout,
+  /// if needed. Then the Server is removed from DDS and ROS discovery before
+  /// returning.
+  ///
+  /// `my_node` must be the Node that created this Server.
+  pub async fn close(self, my_node: &mut Node) -> WriteResult<(), ()> {
+    self.response_sender.async_wait_for_acknowledgments().await?;
+    let reader_gid = self.request_receiver.guid().into();
+    let writer_gid = self.response_sender.guid().into();
+    drop(self);
+    my_node.remove_reader(reader_gid);
+    my_node.remove_writer(writer_gid);
+    Ok(())
+  }
+
   /// Receive a request from Client.
   /// Returns `Ok(None)` if no new requests have arrived.
   pub fn receive_request(&self) -> ReadResult<Option<(RmwRequestId, S::Request)>> {
@@ -73,6 +160,7 @@ where
         let mi = MessageInfo::from(&dcc);
         let req_wrapper = dcc.into_value();
         let (ri, req) = req_wrapper.unwrap(self.service_mapping, &mi)?;
+        self.request_received(ri, &req);
         Ok(Some((ri, req)))
       }
     } // match
@@ -85,25 +173,13 @@ where
     rmw_req_id: RmwRequestId,
     response: S::Response,
   ) -> WriteResult<(), ()> {
-    let resp_wrapper = ResponseWrapper::<S::Response>::new(
+    send_response(
+      &self.response_sender,
       self.service_mapping,
+      self.introspection.as_deref(),
       rmw_req_id,
-      RepresentationIdentifier::CDR_LE,
       response,
-    )?;
-    let write_opts = WriteOptionsBuilder::new()
-      .source_timestamp(Timestamp::now()) // always add source timestamp
-      .related_sample_identity(SampleIdentity::from(rmw_req_id))
-      // TODO: Check if this is right. Cyclone mapping does not send
-      // Related Sample Identity in
-      // WriteOptions (QoS ParameterList), but within data payload.
-      // But maybe it is not harmful to send it in both?
-      .build();
-    self
-      .response_sender
-      .write_with_options(resp_wrapper, write_opts)
-      .map(|_| ())
-      .map_err(|e| e.forget_data()) // lose SampleIdentity result
+    )
   }
 
   /// The request_id must be sent back with the response to identify which
@@ -118,6 +194,7 @@ where
         let mi = MessageInfo::from(&dcc);
         let req_wrapper = dcc.into_value();
         let (ri, req) = req_wrapper.unwrap(self.service_mapping, &mi)?;
+        self.request_received(ri, &req);
         Ok((ri, req))
       }
       // This should never occur, because topic do not "end".
@@ -138,7 +215,9 @@ where
           Ok(dcc) => {
             let mi = MessageInfo::from(&dcc);
             let req_wrapper = dcc.into_value();
-            req_wrapper.unwrap(self.service_mapping, &mi)
+            let (ri, req) = req_wrapper.unwrap(self.service_mapping, &mi)?;
+            self.request_received(ri, &req);
+            Ok((ri, req))
           }
         } // match
       }, // async
@@ -151,27 +230,235 @@ where
     rmw_req_id: RmwRequestId,
     response: S::Response,
   ) -> dds::WriteResult<(), ()> {
-    let resp_wrapper = ResponseWrapper::<S::Response>::new(
+    async_send_response(
+      &self.response_sender,
       self.service_mapping,
+      self.introspection.as_deref(),
       rmw_req_id,
-      RepresentationIdentifier::CDR_LE,
       response,
-    )?;
-    let write_opts = WriteOptionsBuilder::new()
-      .source_timestamp(Timestamp::now()) // always add source timestamp
-      .related_sample_identity(SampleIdentity::from(rmw_req_id))
-      // TODO: Check if this is right. Cyclone mapping does not send
-      // Related Sample Identity in
-      // WriteOptions (QoS ParameterList), but within data payload.
-      // But maybe it is not harmful to send it in both?
-      .build();
+    )
+    .await
+  }
+
+  /// Like [`receive_request`](Self::receive_request), but the request id
+  /// comes in a [`RequestHandle`], which can respond later, e.g. from another
+  /// task. This allows processing requests in parallel, and responding in any
+  /// order.
+  pub fn take_request_handle(&self) -> ReadResult<Option<(RequestHandle<S>, S::Request)>> {
+    Ok(
+      self
+        .receive_request()?
+        .map(|(request_id, request)| (self.request_handle(request_id), request)),
+    )
+  }
+
+  /// Async version of [`take_request_handle`](Self::take_request_handle)
+  pub async fn async_take_request_handle(&self) -> ReadResult<(RequestHandle<S>, S::Request)> {
+    let (request_id, request) = self.async_receive_request().await?;
+    Ok((self.request_handle(request_id), request))
+  }
+
+  fn request_handle(&self, request_id: RmwRequestId) -> RequestHandle<S> {
+    RequestHandle {
+      request_id,
+      service_mapping: self.service_mapping,
+      response_sender: Arc::clone(&self.response_sender),
+      introspection: self.introspection.clone(),
+    }
+  }
+
+  fn request_received(&self, request_id: RmwRequestId, request: &S::Request) {
+    if let Some(ref introspection) = self.introspection {
+      introspection.publish::<_, S::Response>(
+        event_type::REQUEST_RECEIVED,
+        request_id,
+        Some(request),
+        None,
+      );
+    }
+  }
+
+  /// Serve requests with `handler` until reading requests fails.
+  ///
+  /// If `handler` panics, the panic is logged to rosout via `my_node`, the
+  /// request gets no response, and serving continues with the next request.
+  /// The Client then sees the call time out, as ROS 2 Services have no
+  /// separate error channel. See [`Self::serve_with_panic_response`] for
+  /// responding anyway.
+  ///
+  /// Failures to send a response are logged, but do not stop serving.
+  pub async fn serve<F, Fut>(&self, my_node: &Node, handler: F) -> ReadResult<()>
+  where
+    F: FnMut(S::Request) -> Fut,
+    Fut: Future<Output = S::Response>,
+  {
     self
-      .response_sender
-      .async_write_with_options(resp_wrapper, write_opts)
+      .serve_with_panic_response(my_node, handler, |_| None)
       .await
-      .map(|_| ())
-      .map_err(|e| e.forget_data()) // lose SampleIdentity result
   }
+
+  /// Like [`Self::serve`], but when `handler` panics, `on_panic` may give a
+  /// response to send instead, e.g. one whose error field is set.
+  pub async fn serve_with_panic_response<F, Fut, P>(
+    &self,
+    my_node: &Node,
+    mut handler: F,
+    mut on_panic: P,
+  ) -> ReadResult<()>
+  where
+    F: FnMut(S::Request) -> Fut,
+    Fut: Future<Output = S::Response>,
+    P: FnMut(&RmwRequestId) -> Option<S::Response>,
+  {
+    let requests = self.receive_request_stream();
+    pin_mut!(requests);
+    loop {
+      let (req_id, request) = requests.select_next_some().await?;
+      // The handler may panic either when called or when polled.
+      let result = match std::panic::catch_unwind(AssertUnwindSafe(|| handler(request))) {
+        Ok(response_future) => AssertUnwindSafe(response_future).catch_unwind().await,
+        Err(payload) => Err(payload),
+      };
+      let response = match result {
+        Ok(response) => response,
+        Err(payload) => {
+          rosout!(
+            my_node,
+            LogLevel::Error,
+            "Service handler panicked on request {:?}: {}",
+            req_id,
+            panic_message(payload.as_ref())
+          );
+          match on_panic(&req_id) {
+            Some(response) => response,
+            None => continue,
+          }
+        }
+      };
+      self
+        .async_send_response(req_id, response)
+        .await
+        .unwrap_or_else(|e| error!("Server::serve: Cannot send response to {req_id:?}: {e:?}"));
+    }
+  }
+}
+
+/// Responds to one request received by a [`Server`]
+///
+/// Get this from [`Server::take_request_handle`]. It does not borrow the
+/// Server, so it can be moved to another task. Responding after the Server
+/// has been dropped still works, as the response Writer lives as long as its
+/// RequestHandles.
+pub struct RequestHandle<S>
+where
+  S: Service,
+  S::Request: Message,
+  S::Response: Message,
+{
+  request_id: RmwRequestId,
+  service_mapping: ServiceMapping,
+  response_sender: Arc<DataWriterR<ResponseWrapper<S::Response>>>,
+  introspection: Option<Arc<Introspection>>,
+}
+
+impl<S> RequestHandle<S>
+where
+  S: 'static + Service,
+{
+  pub fn request_id(&self) -> RmwRequestId {
+    self.request_id
+  }
+
+  /// Send `response` to the Client that made the request.
+  pub fn respond(self, response: S::Response) -> WriteResult<(), ()> {
+    send_response(
+      &self.response_sender,
+      self.service_mapping,
+      self.introspection.as_deref(),
+      self.request_id,
+      response,
+    )
+  }
+
+  pub async fn async_respond(self, response: S::Response) -> WriteResult<(), ()> {
+    async_send_response(
+      &self.response_sender,
+      self.service_mapping,
+      self.introspection.as_deref(),
+      self.request_id,
+      response,
+    )
+    .await
+  }
+}
+
+// Response data and the WriteOptions that relate it to the request. Also
+// publishes the introspection event, while the response is still at hand.
+fn response_sample<R: Message>(
+  service_mapping: ServiceMapping,
+  introspection: Option<&Introspection>,
+  rmw_req_id: RmwRequestId,
+  response: R,
+) -> WriteResult<(ResponseWrapper<R>, WriteOptions), ()> {
+  if let Some(introspection) = introspection {
+    introspection.publish::<(), _>(event_type::RESPONSE_SENT, rmw_req_id, None, Some(&response));
+  }
+  let resp_wrapper = ResponseWrapper::<R>::new(
+    service_mapping,
+    rmw_req_id,
+    RepresentationIdentifier::CDR_LE,
+    response,
+  )?;
+  let write_opts = WriteOptionsBuilder::new()
+    .source_timestamp(Timestamp::now()) // always add source timestamp
+    .related_sample_identity(SampleIdentity::from(rmw_req_id))
+    // TODO: Check if this is right. Cyclone mapping does not send
+    // Related Sample Identity in
+    // WriteOptions (QoS ParameterList), but within data payload.
+    // But maybe it is not harmful to send it in both?
+    .build();
+  Ok((resp_wrapper, write_opts))
+}
+
+fn send_response<R: Message>(
+  response_sender: &DataWriterR<ResponseWrapper<R>>,
+  service_mapping: ServiceMapping,
+  introspection: Option<&Introspection>,
+  rmw_req_id: RmwRequestId,
+  response: R,
+) -> WriteResult<(), ()> {
+  let (resp_wrapper, write_opts) =
+    response_sample(service_mapping, introspection, rmw_req_id, response)?;
+  response_sender
+    .write_with_options(resp_wrapper, write_opts)
+    .map(|_| ())
+    .map_err(|e| e.forget_data()) // lose SampleIdentity result
+}
+
+async fn async_send_response<R: Message>(
+  response_sender: &DataWriterR<ResponseWrapper<R>>,
+  service_mapping: ServiceMapping,
+  introspection: Option<&Introspection>,
+  rmw_req_id: RmwRequestId,
+  response: R,
+) -> WriteResult<(), ()> {
+  let (resp_wrapper, write_opts) =
+    response_sample(service_mapping, introspection, rmw_req_id, response)?;
+  response_sender
+    .async_write_with_options(resp_wrapper, write_opts)
+    .await
+    .map(|_| ())
+    .map_err(|e| e.forget_data()) // lose SampleIdentity result
+}
+
+/// Client connection change at a [`Server`]
+///
+/// `guid` identifies the request Writer of the Client. `client_count` is the
+/// number of connected Clients after this change.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClientEvent {
+  Connected { guid: GUID, client_count: usize },
+  Disconnected { guid: GUID, client_count: usize },
 }
 
 impl<S> Evented for Server<S>
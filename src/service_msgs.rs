@@ -0,0 +1,41 @@
+//! Corresponds to package
+//! [service_msgs](https://index.ros.org/p/service_msgs/).
+
+use serde::{Deserialize, Serialize};
+
+use crate::{builtin_interfaces::Time, message::Message};
+
+/// Values of [`ServiceEventInfo::event_type`]
+pub mod event_type {
+  pub const REQUEST_SENT: u8 = 0;
+  pub const REQUEST_RECEIVED: u8 = 1;
+  pub const RESPONSE_SENT: u8 = 2;
+  pub const RESPONSE_RECEIVED: u8 = 3;
+}
+
+/// What happened in a Service introspection event
+///
+/// [ServiceEventInfo](https://github.com/ros2/rcl_interfaces/blob/rolling/service_msgs/msg/ServiceEventInfo.msg)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceEventInfo {
+  /// One of the constants in [`event_type`]
+  pub event_type: u8,
+  pub stamp: Time,
+  /// GUID of the request Writer of the Client
+  pub client_gid: [u8; 16],
+  pub sequence_number: i64,
+}
+impl Message for ServiceEventInfo {}
+
+/// Service introspection event, i.e. `<package>/srv/<Service>_Event`
+///
+/// `request` and `response` contain at most one message, and only if the
+/// introspection level is
+/// [`Contents`](crate::service::IntrospectionLevel::Contents).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceEventMessage<Q, S> {
+  pub info: ServiceEventInfo,
+  pub request: Vec<Q>,
+  pub response: Vec<S>,
+}
+impl<Q: Message, S: Message> Message for ServiceEventMessage<Q, S> {}
@@ -0,0 +1,46 @@
+//! Corresponds to package
+//! [statistics_msgs](https://index.ros.org/p/statistics_msgs/).
+
+use serde::{Deserialize, Serialize};
+
+use crate::{builtin_interfaces::Time, message::Message};
+
+/// Kinds of [`StatisticDataPoint`]s
+///
+/// [StatisticDataType](https://github.com/ros2/rcl_interfaces/blob/rolling/statistics_msgs/msg/StatisticDataType.msg)
+pub mod statistic_data_type {
+  pub const STATISTICS_DATA_TYPE_UNINITIALIZED: u8 = 0;
+  pub const STATISTICS_DATA_TYPE_AVERAGE: u8 = 1;
+  pub const STATISTICS_DATA_TYPE_MINIMUM: u8 = 2;
+  pub const STATISTICS_DATA_TYPE_MAXIMUM: u8 = 3;
+  pub const STATISTICS_DATA_TYPE_STDDEV: u8 = 4;
+  pub const STATISTICS_DATA_TYPE_SAMPLE_COUNT: u8 = 5;
+}
+
+/// One statistic, e.g. the average, of a metric
+///
+/// [StatisticDataPoint](https://github.com/ros2/rcl_interfaces/blob/rolling/statistics_msgs/msg/StatisticDataPoint.msg)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StatisticDataPoint {
+  /// One of the constants in [`statistic_data_type`]
+  pub data_type: u8,
+  pub data: f64,
+}
+impl Message for StatisticDataPoint {}
+
+/// Statistics of a metric over a time window
+///
+/// [MetricsMessage](https://github.com/ros2/rcl_interfaces/blob/rolling/statistics_msgs/msg/MetricsMessage.msg)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricsMessage {
+  /// Name of the Node that measured the metric
+  pub measurement_source_name: String,
+  /// Name of the metric, e.g. `message_age`
+  pub metrics_source: String,
+  /// Unit of the metric, e.g. `ms`
+  pub unit: String,
+  pub window_start: Time,
+  pub window_stop: Time,
+  pub statistics: Vec<StatisticDataPoint>,
+}
+impl Message for MetricsMessage {}
@@ -0,0 +1,42 @@
+//! Corresponds to package [std_msgs](https://index.ros.org/p/std_msgs/).
+//!
+//! [`Header`] is always defined here. The rest of the package is generated
+//! from the ROS 2 definitions with feature `std_msgs`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{builtin_interfaces::Time, message::Message};
+// Note that this includes `std_msgs::String`, so the standard one must be
+// spelled out below.
+#[cfg(feature = "std_msgs")]
+pub use crate::common_interfaces::std_msgs::msg::*;
+
+/// Standard metadata for higher-level stamped data types.
+///
+/// [Header](https://github.com/ros2/common_interfaces/blob/rolling/std_msgs/msg/Header.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Header {
+  /// Two-integer timestamp that is expressed as seconds and nanoseconds.
+  pub stamp: Time,
+  /// Transform frame with which this data is associated.
+  pub frame_id: std::string::String,
+}
+impl Message for Header {}
+
+impl Header {
+  pub fn new(stamp: Time, frame_id: &str) -> Self {
+    Header {
+      stamp,
+      frame_id: frame_id.to_owned(),
+    }
+  }
+}
+
+impl Default for Header {
+  fn default() -> Self {
+    Header {
+      stamp: Time::ZERO,
+      frame_id: std::string::String::new(),
+    }
+  }
+}
@@ -0,0 +1,17 @@
+//! Corresponds to package [tf2_msgs](https://index.ros.org/p/tf2_msgs/).
+//!
+//! The types are generated from the ROS 2 definitions, see
+//! `interfaces/tf2_msgs/`. [`TFMessage`] is the type of Topics `/tf` and
+//! `/tf_static`.
+
+pub use crate::common_interfaces::tf2_msgs::msg::*;
+
+/// Services of tf2_msgs
+pub mod srv {
+  pub use crate::common_interfaces::tf2_msgs::srv::*;
+}
+
+/// Actions of tf2_msgs, e.g. [`action::LookupTransform`]
+pub mod action {
+  pub use crate::common_interfaces::tf2_msgs::action::*;
+}
@@ -0,0 +1,284 @@
+//! Rate-limited Publisher
+//!
+//! [`ThrottledPublisher`] limits how often messages are actually sent, e.g. to
+//! downsample a high-rate sensor stream for visualization. Messages offered
+//! faster than that are either dropped, or coalesced so that the latest one is
+//! sent when the next slot is available.
+//!
+//! The limit is a token bucket: a burst of messages may go out at once, as long
+//! as the average rate stays below the maximum frequency. The default burst is
+//! one message, i.e. a minimum interval between messages.
+
+use std::{
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use async_io::Timer;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use rustdds::dds::WriteResult;
+use serde::Serialize;
+
+use crate::pubsub::Publisher;
+
+/// What [`ThrottledPublisher`] does with messages that arrive too early
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ThrottleMode {
+  /// Drop the message.
+  Drop,
+  /// Keep the latest message, replacing any previously kept one, and send it
+  /// when the minimum interval has passed. This requires
+  /// [`ThrottledPublisher::spin`] to be running.
+  Coalesce,
+}
+
+// Decides which messages go out. Kept apart from the Publisher so that the
+// timing logic can be tested without DDS.
+struct Throttle<M> {
+  min_interval: Duration,
+  burst: u32,
+  mode: ThrottleMode,
+  // Tokens in the bucket, as time: each message costs `min_interval`.
+  credit: Duration,
+  refilled_at: Option<Instant>,
+  pending: Option<M>,
+}
+
+impl<M> Throttle<M> {
+  fn new(max_frequency: f64, mode: ThrottleMode) -> Self {
+    Throttle {
+      min_interval: interval_from_frequency(max_frequency),
+      burst: 1,
+      mode,
+      credit: Duration::ZERO,
+      refilled_at: None,
+      pending: None,
+    }
+  }
+
+  fn capacity(&self) -> Duration {
+    self.min_interval.saturating_mul(self.burst)
+  }
+
+  fn refill(&mut self, now: Instant) {
+    self.credit = match self.refilled_at {
+      None => self.capacity(), // Start with a full bucket.
+      Some(t) => (self.credit + now.saturating_duration_since(t)).min(self.capacity()),
+    };
+    self.refilled_at = Some(now);
+  }
+
+  // When the next message may go out, or `None` if nothing was sent yet
+  fn next_slot(&self) -> Option<Instant> {
+    self
+      .refilled_at
+      .map(|t| t + self.min_interval.saturating_sub(self.credit))
+  }
+
+  fn is_due(&mut self, now: Instant) -> bool {
+    self.refill(now);
+    self.credit >= self.min_interval
+  }
+
+  fn spend(&mut self) {
+    self.credit -= self.min_interval;
+  }
+
+  // Returns the message, if it should be sent now.
+  fn offer(&mut self, now: Instant, message: M) -> Option<M> {
+    if self.is_due(now) {
+      self.spend();
+      self.pending = None; // superseded
+      Some(message)
+    } else {
+      if self.mode == ThrottleMode::Coalesce {
+        self.pending = Some(message);
+      }
+      None
+    }
+  }
+
+  // Returns the pending message, if its slot has come.
+  fn take_due(&mut self, now: Instant) -> Option<M> {
+    if self.pending.is_some() && self.is_due(now) {
+      self.spend();
+      self.pending.take()
+    } else {
+      None
+    }
+  }
+}
+
+fn interval_from_frequency(max_frequency: f64) -> Duration {
+  assert!(
+    max_frequency > 0.0,
+    "Maximum frequency must be positive, got {}",
+    max_frequency
+  );
+  // Infinite frequency gives zero interval, i.e. no throttling.
+  Duration::from_secs_f64(1.0 / max_frequency)
+}
+
+/// A [`Publisher`] that sends at most at a given frequency
+///
+/// The frequency and [`ThrottleMode`] can be changed at runtime, e.g. from a
+/// Parameter callback.
+pub struct ThrottledPublisher<M: Serialize> {
+  publisher: Publisher<M>,
+  throttle: Mutex<Throttle<M>>,
+}
+
+impl<M: Serialize> ThrottledPublisher<M> {
+  /// Throttle `publisher` to `max_frequency` messages per second.
+  /// `f64::INFINITY` disables throttling.
+  ///
+  /// # Panics
+  ///
+  /// If `max_frequency` is not positive.
+  pub fn new(publisher: Publisher<M>, max_frequency: f64, mode: ThrottleMode) -> Self {
+    ThrottledPublisher {
+      publisher,
+      throttle: Mutex::new(Throttle::new(max_frequency, mode)),
+    }
+  }
+
+  /// # Panics
+  ///
+  /// If `max_frequency` is not positive.
+  pub fn set_max_frequency(&self, max_frequency: f64) {
+    self.throttle.lock().unwrap().min_interval = interval_from_frequency(max_frequency);
+  }
+
+  pub fn max_frequency(&self) -> f64 {
+    1.0 / self.throttle.lock().unwrap().min_interval.as_secs_f64()
+  }
+
+  /// Allow up to `burst` messages to be sent at once, when none have been
+  /// sent for a while. The average rate is still limited to the maximum
+  /// frequency. The default is 1.
+  ///
+  /// # Panics
+  ///
+  /// If `burst` is zero.
+  pub fn set_burst(&self, burst: u32) {
+    assert!(burst > 0, "Burst must be at least one message");
+    self.throttle.lock().unwrap().burst = burst;
+  }
+
+  pub fn burst(&self) -> u32 {
+    self.throttle.lock().unwrap().burst
+  }
+
+  /// Changing to [`ThrottleMode::Drop`] discards a message waiting to be
+  /// coalesced.
+  pub fn set_mode(&self, mode: ThrottleMode) {
+    let mut throttle = self.throttle.lock().unwrap();
+    throttle.mode = mode;
+    if mode == ThrottleMode::Drop {
+      throttle.pending = None;
+    }
+  }
+
+  pub fn mode(&self) -> ThrottleMode {
+    self.throttle.lock().unwrap().mode
+  }
+
+  /// Publishes `message` now, if the minimum interval since the previous
+  /// message has passed. Otherwise the message is dropped or coalesced,
+  /// according to the mode.
+  ///
+  /// Returns `Ok(true)` if the message was sent.
+  pub fn publish(&self, message: M) -> WriteResult<bool, M> {
+    let to_send = self.throttle.lock().unwrap().offer(Instant::now(), message);
+    match to_send {
+      Some(message) => self.publisher.publish(message).map(|()| true),
+      None => Ok(false),
+    }
+  }
+
+  /// Sends coalesced messages when their time comes. This never returns, so
+  /// it should be run as a task alongside the code that publishes.
+  ///
+  /// Not needed in [`ThrottleMode::Drop`].
+  pub async fn spin(&self) {
+    loop {
+      let (to_send, next_slot, min_interval) = {
+        let mut throttle = self.throttle.lock().unwrap();
+        (
+          throttle.take_due(Instant::now()),
+          throttle.next_slot(),
+          throttle.min_interval,
+        )
+      };
+      if let Some(message) = to_send {
+        self
+          .publisher
+          .publish(message)
+          .unwrap_or_else(|e| warn!("ThrottledPublisher: publish failed: {:?}", e.forget_data()));
+      }
+      // Sleep until the next slot. There may be nothing to send then, but
+      // this wakes at most at the maximum frequency anyway.
+      match next_slot {
+        Some(slot) if slot > Instant::now() => {
+          Timer::at(slot).await;
+        }
+        _ => {
+          // Nothing sent yet, or zero interval. Check again after a while.
+          Timer::after(min_interval.max(Duration::from_millis(10))).await;
+        }
+      }
+    }
+  }
+
+  /// The throttled Publisher
+  pub fn publisher(&self) -> &Publisher<M> {
+    &self.publisher
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn drop_mode() {
+    let mut t = Throttle::new(10.0, ThrottleMode::Drop);
+    let t0 = Instant::now();
+    assert_eq!(t.offer(t0, 1), Some(1));
+    assert_eq!(t.offer(t0 + Duration::from_millis(50), 2), None);
+    assert_eq!(t.take_due(t0 + Duration::from_millis(100)), None);
+    assert_eq!(t.offer(t0 + Duration::from_millis(100), 3), Some(3));
+  }
+
+  #[test]
+  fn coalesce_mode() {
+    let mut t = Throttle::new(10.0, ThrottleMode::Coalesce);
+    let t0 = Instant::now();
+    assert_eq!(t.offer(t0, 1), Some(1));
+    assert_eq!(t.offer(t0 + Duration::from_millis(20), 2), None);
+    assert_eq!(t.offer(t0 + Duration::from_millis(40), 3), None);
+    assert_eq!(t.take_due(t0 + Duration::from_millis(60)), None);
+    // Only the latest is sent.
+    assert_eq!(t.take_due(t0 + Duration::from_millis(100)), Some(3));
+    assert_eq!(t.take_due(t0 + Duration::from_millis(300)), None);
+  }
+
+  #[test]
+  fn burst() {
+    let mut t = Throttle::new(10.0, ThrottleMode::Drop);
+    t.burst = 3;
+    let t0 = Instant::now();
+    for i in 0..3 {
+      assert_eq!(t.offer(t0, i), Some(i));
+    }
+    assert_eq!(t.offer(t0, 3), None);
+    // Tokens come back at the maximum frequency.
+    assert_eq!(t.next_slot(), Some(t0 + Duration::from_millis(100)));
+    assert_eq!(t.offer(t0 + Duration::from_millis(150), 4), Some(4));
+    assert_eq!(t.offer(t0 + Duration::from_millis(150), 5), None);
+    // The bucket holds at most `burst` tokens.
+    let later = t0 + Duration::from_secs(10);
+    assert_eq!((0..5).filter_map(|i| t.offer(later, i)).count(), 3);
+  }
+}
@@ -0,0 +1,238 @@
+//! Timers, which call a callback periodically in the Spinner of a Node
+//!
+//! Create these with [`Node::create_timer`](crate::Node::create_timer), which
+//! follows ROS time, i.e. simulated time when the Node Parameter
+//! `use_sim_time` is set, or with
+//! [`Node::create_wall_timer`](crate::Node::create_wall_timer), which follows
+//! the monotonic system clock.
+
+use std::{
+  convert::TryFrom,
+  sync::{Arc, Mutex},
+  time::Duration,
+};
+
+use futures::{future::BoxFuture, FutureExt};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  clock::{Clock, SIM_TIME_POLL_INTERVAL},
+  service::panic_message,
+};
+
+struct TimerState {
+  clock: Clock,
+  period: i64,
+  // `None` when canceled
+  next_call: Option<i64>,
+}
+
+impl TimerState {
+  fn reset(&mut self) {
+    self.next_call = Some(self.clock.now().to_nanos().saturating_add(self.period));
+  }
+}
+
+/// Calls a callback periodically, while the Spinner of its Node runs
+///
+/// If a callback takes longer than the period, or the Spinner is busy, missed
+/// calls are skipped. Dropping the Timer stops it for good.
+pub struct Timer {
+  state: Arc<Mutex<TimerState>>,
+  // Wakes the Timer task to re-read `state`. Dropping this ends the task.
+  wake_sender: async_channel::Sender<()>,
+}
+
+impl Timer {
+  // Returns the Timer, and the task that calls `callback`. The task ends
+  // when the Timer is dropped.
+  pub(crate) fn new<F>(
+    clock: Clock,
+    period: Duration,
+    mut callback: F,
+  ) -> (Timer, BoxFuture<'static, ()>)
+  where
+    F: FnMut() + Send + 'static,
+  {
+    let period = i64::try_from(period.as_nanos()).unwrap_or(i64::MAX).max(1);
+    let mut state = TimerState {
+      clock,
+      period,
+      next_call: None,
+    };
+    state.reset();
+    let state = Arc::new(Mutex::new(state));
+    let (wake_sender, wake_receiver) = async_channel::bounded(1);
+
+    let task_state = Arc::clone(&state);
+    let task = async move {
+      loop {
+        let wait = {
+          let mut state = task_state.lock().unwrap();
+          match state.next_call {
+            None => None, // canceled
+            Some(next_call) => {
+              let now = state.clock.now().to_nanos();
+              if now >= next_call {
+                // Skip missed calls, but keep the phase.
+                let missed = (now - next_call) / state.period;
+                state.next_call =
+                  Some(next_call.saturating_add((missed + 1).saturating_mul(state.period)));
+                Some(Duration::ZERO)
+              } else {
+                let wait = Duration::from_nanos((next_call - now) as u64);
+                Some(if state.clock.ros_time_is_active() {
+                  wait.min(SIM_TIME_POLL_INTERVAL)
+                } else {
+                  wait
+                })
+              }
+            }
+          }
+        };
+        match wait {
+          Some(Duration::ZERO) => {
+            let run = std::panic::AssertUnwindSafe(&mut callback);
+            if let Err(payload) = std::panic::catch_unwind(run) {
+              error!(
+                "Timer callback panicked: {}",
+                panic_message(payload.as_ref())
+              );
+            }
+          }
+          Some(wait) => {
+            futures::select! {
+              _ = async_io::Timer::after(wait).fuse() => {}
+              woken = wake_receiver.recv().fuse() => if woken.is_err() { break }
+            }
+          }
+          None => {
+            if wake_receiver.recv().await.is_err() {
+              break;
+            }
+          }
+        }
+      }
+      debug!("Timer dropped, stopping.");
+    };
+    (Timer { state, wake_sender }, Box::pin(task))
+  }
+
+  /// Stops calling the callback, until [`reset`](Self::reset).
+  pub fn cancel(&self) {
+    self.state.lock().unwrap().next_call = None;
+    self.wake();
+  }
+
+  pub fn is_canceled(&self) -> bool {
+    self.state.lock().unwrap().next_call.is_none()
+  }
+
+  /// Restarts the period from now. This also resumes a canceled Timer.
+  pub fn reset(&self) {
+    self.state.lock().unwrap().reset();
+    self.wake();
+  }
+
+  /// Time until the next callback is due, in the clock of the Timer. This is
+  /// zero if the call is late, and `None` if the Timer is canceled.
+  pub fn time_until_next_call(&self) -> Option<Duration> {
+    let state = self.state.lock().unwrap();
+    state.next_call.map(|next_call| {
+      Duration::from_nanos(
+        next_call
+          .saturating_sub(state.clock.now().to_nanos())
+          .max(0) as u64,
+      )
+    })
+  }
+
+  pub fn period(&self) -> Duration {
+    Duration::from_nanos(self.state.lock().unwrap().period as u64)
+  }
+
+  fn wake(&self) {
+    // If a wakeup is already pending, that is enough.
+    let _ = self.wake_sender.try_send(());
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::sync::atomic::{AtomicUsize, Ordering};
+
+  use futures::executor::block_on;
+
+  use super::*;
+  use crate::{
+    clock::{ClockType, TimeSource},
+    ros_time::ROSTime,
+  };
+
+  // Runs `task` for `duration`.
+  fn run_for(task: &mut BoxFuture<'static, ()>, duration: Duration) {
+    block_on(futures::future::select(
+      task.as_mut(),
+      async_io::Timer::after(duration),
+    ));
+  }
+
+  #[test]
+  fn cancel_and_reset() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let calls_in_callback = Arc::clone(&calls);
+    let (timer, mut task) = Timer::new(
+      Clock::new(ClockType::SteadyTime),
+      Duration::from_millis(20),
+      move || {
+        calls_in_callback.fetch_add(1, Ordering::SeqCst);
+      },
+    );
+    assert!(timer.time_until_next_call().unwrap() <= Duration::from_millis(20));
+
+    run_for(&mut task, Duration::from_millis(110));
+    let seen = calls.load(Ordering::SeqCst);
+    assert!((3..=6).contains(&seen), "{}", seen);
+
+    timer.cancel();
+    assert!(timer.is_canceled());
+    assert_eq!(timer.time_until_next_call(), None);
+    run_for(&mut task, Duration::from_millis(60));
+    assert_eq!(calls.load(Ordering::SeqCst), seen);
+
+    timer.reset();
+    run_for(&mut task, Duration::from_millis(50));
+    assert!(calls.load(Ordering::SeqCst) > seen);
+
+    // Dropping the Timer ends the task.
+    drop(timer);
+    block_on(task);
+  }
+
+  #[test]
+  fn follows_simulated_time() {
+    let time_source = TimeSource::new();
+    time_source.set_ros_time_active(true);
+    time_source.set_sim_time(ROSTime::from_nanos(1_000_000_000));
+    let calls = Arc::new(AtomicUsize::new(0));
+    let calls_in_callback = Arc::clone(&calls);
+    let clock = Clock::ros_time(time_source.clone());
+    let (timer, mut task) = Timer::new(clock, Duration::from_secs(1), move || {
+      calls_in_callback.fetch_add(1, Ordering::SeqCst);
+    });
+
+    // Simulated time stands still.
+    run_for(&mut task, Duration::from_millis(50));
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+    assert_eq!(timer.time_until_next_call(), Some(Duration::from_secs(1)));
+
+    time_source.set_sim_time(ROSTime::from_nanos(2_500_000_000));
+    run_for(&mut task, Duration::from_millis(50));
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(
+      timer.time_until_next_call(),
+      Some(Duration::from_millis(500))
+    );
+  }
+}
@@ -0,0 +1,206 @@
+//! Topic rate and bandwidth measurement
+//!
+//! [`TopicMonitor`] is the programmatic equivalent of `ros2 topic hz` and
+//! `ros2 topic bw`. It receives messages without deserializing them, so it
+//! works for any message type.
+
+use std::{
+  collections::VecDeque,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use async_io::Timer;
+use futures::{Stream, StreamExt};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{message::SerializedMessage, pubsub::Subscription};
+
+/// Statistics over the latest received messages. See [`TopicMonitor`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopicStatistics {
+  /// Number of messages the statistics are computed from
+  pub message_count: usize,
+  /// Average rate in messages per second, i.e. inverse of the mean interval
+  pub rate: f64,
+  pub min_interval: Duration,
+  pub max_interval: Duration,
+  /// Standard deviation of the intervals between messages
+  pub interval_std_dev: Duration,
+  /// Received bytes per second, from the oldest message in the window until
+  /// now
+  pub bandwidth: f64,
+  /// Message sizes in bytes, as serialized
+  pub mean_size: f64,
+  pub min_size: usize,
+  pub max_size: usize,
+}
+
+// Arrival times and sizes of the latest messages. Kept apart from the
+// Subscription so that it can be tested without DDS.
+struct Window {
+  capacity: usize,
+  samples: VecDeque<(Instant, usize)>,
+}
+
+impl Window {
+  fn new(capacity: usize) -> Self {
+    Window {
+      capacity,
+      samples: VecDeque::with_capacity(capacity),
+    }
+  }
+
+  fn record(&mut self, arrival: Instant, size: usize) {
+    if self.samples.len() >= self.capacity {
+      self.samples.pop_front();
+    }
+    self.samples.push_back((arrival, size));
+  }
+
+  // Needs at least two messages to have an interval.
+  fn statistics(&self, now: Instant) -> Option<TopicStatistics> {
+    let (first, _) = *self.samples.front()?;
+    let intervals: Vec<f64> = self
+      .samples
+      .iter()
+      .zip(self.samples.iter().skip(1))
+      .map(|((a, _), (b, _))| (*b - *a).as_secs_f64())
+      .collect();
+    if intervals.is_empty() {
+      return None;
+    }
+
+    let n = intervals.len() as f64;
+    let mean = intervals.iter().sum::<f64>() / n;
+    let variance = intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / n;
+    let min_interval = intervals.iter().copied().fold(f64::INFINITY, f64::min);
+    let max_interval = intervals.iter().copied().fold(0.0, f64::max);
+
+    let sizes = self.samples.iter().map(|(_, size)| *size);
+    let total_size: usize = sizes.clone().sum();
+    let elapsed = (now - first).as_secs_f64();
+
+    Some(TopicStatistics {
+      message_count: self.samples.len(),
+      rate: if mean > 0.0 {
+        1.0 / mean
+      } else {
+        f64::INFINITY
+      },
+      min_interval: Duration::from_secs_f64(min_interval),
+      max_interval: Duration::from_secs_f64(max_interval),
+      interval_std_dev: Duration::from_secs_f64(variance.sqrt()),
+      bandwidth: if elapsed > 0.0 {
+        total_size as f64 / elapsed
+      } else {
+        f64::INFINITY
+      },
+      mean_size: total_size as f64 / self.samples.len() as f64,
+      min_size: sizes.clone().min().unwrap_or(0),
+      max_size: sizes.max().unwrap_or(0),
+    })
+  }
+}
+
+/// Measures message rate, bandwidth and sizes on a Topic
+///
+/// The statistics are computed over a window of the latest messages. Create
+/// the Subscription with e.g.
+/// [`Node::create_subscription::<SerializedMessage>`](crate::Node::create_subscription),
+/// on a Topic with the type name of the monitored Topic, so that it matches
+/// the Publishers.
+pub struct TopicMonitor {
+  subscription: Subscription<SerializedMessage>,
+  window: Mutex<Window>,
+}
+
+impl TopicMonitor {
+  /// Monitor `subscription`, computing statistics over the latest
+  /// `window_size` messages.
+  ///
+  /// # Panics
+  ///
+  /// If `window_size` is less than 2.
+  pub fn new(subscription: Subscription<SerializedMessage>, window_size: usize) -> Self {
+    assert!(
+      window_size >= 2,
+      "Window must have room for an interval, got {}",
+      window_size
+    );
+    TopicMonitor {
+      subscription,
+      window: Mutex::new(Window::new(window_size)),
+    }
+  }
+
+  /// Receives and records messages. This never returns, so it should be run
+  /// as a task alongside the code that reads the statistics.
+  pub async fn spin(&self) {
+    let mut messages = self.subscription.async_stream();
+    while let Some(result) = messages.next().await {
+      match result {
+        Ok((message, _info)) => self
+          .window
+          .lock()
+          .unwrap()
+          .record(Instant::now(), message.0.len()),
+        Err(e) => warn!("TopicMonitor: receive failed: {e:?}"),
+      }
+    }
+  }
+
+  /// Statistics now, if at least two messages have been received
+  pub fn statistics(&self) -> Option<TopicStatistics> {
+    self.window.lock().unwrap().statistics(Instant::now())
+  }
+
+  /// Yields [`Self::statistics`] every `period`, when available.
+  ///
+  /// This requires [`Self::spin`] to be running.
+  pub fn statistics_stream(&self, period: Duration) -> impl Stream<Item = TopicStatistics> + '_ {
+    Timer::interval(period).filter_map(move |_| async move { self.statistics() })
+  }
+
+  /// Forget the received messages, e.g. after the Publishers have changed.
+  pub fn reset(&self) {
+    self.window.lock().unwrap().samples.clear();
+  }
+
+  /// The monitored Subscription
+  pub fn subscription(&self) -> &Subscription<SerializedMessage> {
+    &self.subscription
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn statistics() {
+    let mut w = Window::new(3);
+    let t0 = Instant::now();
+    assert_eq!(w.statistics(t0), None);
+    w.record(t0, 100);
+    assert_eq!(w.statistics(t0), None);
+    w.record(t0 + Duration::from_millis(100), 200);
+    w.record(t0 + Duration::from_millis(300), 300);
+
+    let s = w.statistics(t0 + Duration::from_millis(600)).unwrap();
+    assert_eq!(s.message_count, 3);
+    assert!((s.rate - 1.0 / 0.15).abs() < 1e-9);
+    assert_eq!(s.min_interval, Duration::from_millis(100));
+    assert_eq!(s.max_interval, Duration::from_millis(200));
+    assert_eq!(s.interval_std_dev, Duration::from_millis(50));
+    assert!((s.bandwidth - 1000.0).abs() < 1e-9);
+    assert_eq!((s.mean_size, s.min_size, s.max_size), (200.0, 100, 300));
+
+    // The oldest message drops out of the window.
+    w.record(t0 + Duration::from_millis(400), 400);
+    let s = w.statistics(t0 + Duration::from_millis(600)).unwrap();
+    assert_eq!(s.min_size, 200);
+    assert_eq!(s.max_interval, Duration::from_millis(200));
+  }
+}
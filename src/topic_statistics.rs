@@ -0,0 +1,238 @@
+//! ROS 2 Topic Statistics
+//!
+//! With statistics enabled by
+//! [`Node::enable_topic_statistics`](crate::Node::enable_topic_statistics), a
+//! Subscription measures the age of the messages it receives and the period
+//! between them. The statistics are published periodically as
+//! `statistics_msgs/msg/MetricsMessage`, by default on `/statistics`, like
+//! in rclcpp and rclpy.
+//!
+//! Message age is measured from the source timestamp to reception, so it is
+//! meaningful only if the clocks of the computers are synchronized.
+
+use std::{
+  sync::{Mutex, Weak},
+  time::{Duration, Instant},
+};
+
+use async_io::Timer;
+use futures::StreamExt;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+use crate::{
+  builtin_interfaces::Time,
+  message_info::MessageInfo,
+  names::Name,
+  pubsub::Publisher,
+  statistics_msgs::{statistic_data_type::*, MetricsMessage, StatisticDataPoint},
+};
+
+/// Configuration of Topic Statistics
+///
+/// This is a builder-like struct. The defaults are as in rclcpp.
+#[must_use]
+#[derive(Clone, Debug)]
+pub struct TopicStatisticsOptions {
+  pub(crate) publish_topic: Name,
+  pub(crate) publish_period: Duration,
+}
+
+impl TopicStatisticsOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Topic to publish the statistics on. The default is `/statistics`.
+  pub fn publish_topic(self, publish_topic: Name) -> Self {
+    TopicStatisticsOptions {
+      publish_topic,
+      ..self
+    }
+  }
+
+  /// How often statistics are computed and published. The default is one
+  /// second.
+  pub fn publish_period(self, publish_period: Duration) -> Self {
+    TopicStatisticsOptions {
+      publish_period,
+      ..self
+    }
+  }
+}
+
+impl Default for TopicStatisticsOptions {
+  fn default() -> Self {
+    TopicStatisticsOptions {
+      publish_topic: Name::new("/", "statistics").unwrap(),
+      publish_period: Duration::from_secs(1),
+    }
+  }
+}
+
+// Measurements of one window. Kept apart from DDS so that it can be tested.
+struct Window {
+  start: Time,
+  ages: Vec<f64>,    // milliseconds
+  periods: Vec<f64>, // milliseconds
+  last_arrival: Option<Instant>,
+}
+
+impl Window {
+  fn new(start: Time) -> Self {
+    Window {
+      start,
+      ages: Vec::new(),
+      periods: Vec::new(),
+      last_arrival: None,
+    }
+  }
+
+  fn record(&mut self, arrival: Instant, info: &MessageInfo) {
+    if let Some(age) = info.latency() {
+      self.ages.push(age.as_secs_f64() * 1000.0);
+    }
+    if let Some(previous) = self.last_arrival {
+      self
+        .periods
+        .push((arrival - previous).as_secs_f64() * 1000.0);
+    }
+    self.last_arrival = Some(arrival);
+  }
+
+  // Statistics of the window ending `now`, and start the next one
+  fn finish(&mut self, node_name: &str, now: Time) -> [MetricsMessage; 2] {
+    let metrics = |source: &str, samples: &[f64]| MetricsMessage {
+      measurement_source_name: node_name.to_string(),
+      metrics_source: source.to_string(),
+      unit: "ms".to_string(),
+      window_start: self.start,
+      window_stop: now,
+      statistics: data_points(samples),
+    };
+    let messages = [
+      metrics("message_age", &self.ages),
+      metrics("message_period", &self.periods),
+    ];
+    self.start = now;
+    self.ages.clear();
+    self.periods.clear();
+    // The period continues over the window boundary, so last_arrival stays.
+    messages
+  }
+}
+
+// Statistics in the order rclcpp uses. NaN if there are no samples.
+fn data_points(samples: &[f64]) -> Vec<StatisticDataPoint> {
+  let (average, minimum, maximum, stddev) = if samples.is_empty() {
+    (f64::NAN, f64::NAN, f64::NAN, f64::NAN)
+  } else {
+    let n = samples.len() as f64;
+    let average = samples.iter().sum::<f64>() / n;
+    let variance = samples.iter().map(|s| (s - average).powi(2)).sum::<f64>() / n;
+    (
+      average,
+      samples.iter().copied().fold(f64::INFINITY, f64::min),
+      samples.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+      variance.sqrt(),
+    )
+  };
+  [
+    (STATISTICS_DATA_TYPE_AVERAGE, average),
+    (STATISTICS_DATA_TYPE_MAXIMUM, maximum),
+    (STATISTICS_DATA_TYPE_MINIMUM, minimum),
+    (STATISTICS_DATA_TYPE_STDDEV, stddev),
+    (STATISTICS_DATA_TYPE_SAMPLE_COUNT, samples.len() as f64),
+  ]
+  .iter()
+  .map(|&(data_type, data)| StatisticDataPoint { data_type, data })
+  .collect()
+}
+
+/// Measurements of a Subscription
+pub(crate) struct StatisticsCollector {
+  window: Mutex<Window>,
+}
+
+impl StatisticsCollector {
+  pub(crate) fn new() -> Self {
+    StatisticsCollector {
+      window: Mutex::new(Window::new(Time::now())),
+    }
+  }
+
+  pub(crate) fn record(&self, info: &MessageInfo) {
+    self.window.lock().unwrap().record(Instant::now(), info);
+  }
+}
+
+/// Publishes the statistics of `collector` every `period`, until the
+/// Subscription owning it is dropped.
+pub(crate) async fn publish_statistics(
+  collector: Weak<StatisticsCollector>,
+  publisher: Publisher<MetricsMessage>,
+  node_name: String,
+  period: Duration,
+) {
+  let mut ticks = Timer::interval(period);
+  while ticks.next().await.is_some() {
+    let Some(collector) = collector.upgrade() else {
+      return;
+    };
+    let messages = collector
+      .window
+      .lock()
+      .unwrap()
+      .finish(&node_name, Time::now());
+    drop(collector);
+    for message in messages {
+      publisher
+        .async_publish(message)
+        .await
+        .unwrap_or_else(|e| warn!("Topic statistics: publish failed: {:?}", e.forget_data()));
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rustdds::{Timestamp, GUID};
+
+  use super::*;
+
+  #[test]
+  fn window() {
+    let t0 = Instant::now();
+    let sent = Timestamp::now();
+    let info = |age_ms| {
+      let received = sent + rustdds::Duration::from_millis(age_ms);
+      MessageInfo::new(received, Some(sent), 1, GUID::GUID_UNKNOWN)
+    };
+    let mut w = Window::new(Time::ZERO);
+    w.record(t0, &info(10));
+    w.record(t0 + Duration::from_millis(100), &info(20));
+    w.record(t0 + Duration::from_millis(300), &info(30));
+
+    let [age, period] = w.finish("talker", Time::from_nanos(5));
+    assert_eq!(age.metrics_source, "message_age");
+    assert_eq!(age.window_stop, Time::from_nanos(5));
+    let data = |m: &MetricsMessage| m.statistics.iter().map(|s| s.data).collect::<Vec<_>>();
+    let near = |a: Vec<f64>, b: [f64; 5]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-3);
+    // Average, maximum, minimum, standard deviation, count
+    assert!(
+      near(data(&age), [20.0, 30.0, 10.0, 8.165, 3.0]),
+      "{:?}",
+      data(&age)
+    );
+    assert!(near(data(&period), [150.0, 200.0, 100.0, 50.0, 2.0]));
+
+    // The next window starts empty, but periods continue.
+    w.record(t0 + Duration::from_millis(400), &info(10));
+    let [age, period] = w.finish("talker", Time::from_nanos(6));
+    assert_eq!(age.window_start, Time::from_nanos(5));
+    assert_eq!(data(&period)[4], 1.0);
+    assert_eq!(data(&period)[0], 100.0);
+    let [_, period] = w.finish("talker", Time::from_nanos(7));
+    assert!(data(&period)[0].is_nan());
+  }
+}
@@ -0,0 +1,130 @@
+//! Corresponds to package
+//! [type_description_interfaces](https://index.ros.org/p/type_description_interfaces/).
+//!
+//! These describe the structure of message and Service types, so that Nodes
+//! can check that types of the same name are compatible, see
+//! [`type_hash`](crate::type_hash).
+
+use serde::{Deserialize, Serialize};
+
+use crate::{message::Message, ros2_service};
+
+/// Values of [`FieldType::type_id`]
+///
+/// Fixed size arrays, bounded sequences and unbounded sequences of these have
+/// the id plus 48, 96 and 144 respectively, e.g. `int8[3]` is
+/// `FIELD_TYPE_INT8 + 48`.
+pub mod field_type {
+  pub const FIELD_TYPE_NOT_SET: u8 = 0;
+  pub const FIELD_TYPE_NESTED_TYPE: u8 = 1;
+  pub const FIELD_TYPE_INT8: u8 = 2;
+  pub const FIELD_TYPE_UINT8: u8 = 3;
+  pub const FIELD_TYPE_INT16: u8 = 4;
+  pub const FIELD_TYPE_UINT16: u8 = 5;
+  pub const FIELD_TYPE_INT32: u8 = 6;
+  pub const FIELD_TYPE_UINT32: u8 = 7;
+  pub const FIELD_TYPE_INT64: u8 = 8;
+  pub const FIELD_TYPE_UINT64: u8 = 9;
+  pub const FIELD_TYPE_FLOAT: u8 = 10;
+  pub const FIELD_TYPE_DOUBLE: u8 = 11;
+  pub const FIELD_TYPE_LONG_DOUBLE: u8 = 12;
+  pub const FIELD_TYPE_CHAR: u8 = 13;
+  pub const FIELD_TYPE_WCHAR: u8 = 14;
+  pub const FIELD_TYPE_BOOLEAN: u8 = 15;
+  pub const FIELD_TYPE_BYTE: u8 = 16;
+  pub const FIELD_TYPE_STRING: u8 = 17;
+  pub const FIELD_TYPE_WSTRING: u8 = 18;
+  pub const FIELD_TYPE_FIXED_STRING: u8 = 19;
+  pub const FIELD_TYPE_FIXED_WSTRING: u8 = 20;
+  pub const FIELD_TYPE_BOUNDED_STRING: u8 = 21;
+  pub const FIELD_TYPE_BOUNDED_WSTRING: u8 = 22;
+}
+
+/// [FieldType](https://github.com/ros2/rcl_interfaces/blob/rolling/type_description_interfaces/msg/FieldType.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FieldType {
+  /// One of the constants in [`field_type`], possibly plus an array offset
+  pub type_id: u8,
+  /// Size of a fixed size array, or bound of a bounded sequence
+  pub capacity: u64,
+  /// Bound of a bounded string
+  pub string_capacity: u64,
+  /// Full name of a nested type, e.g. `std_msgs/msg/Header`
+  pub nested_type_name: String,
+}
+impl Message for FieldType {}
+
+/// [Field](https://github.com/ros2/rcl_interfaces/blob/rolling/type_description_interfaces/msg/Field.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Field {
+  pub name: String,
+  pub r#type: FieldType,
+  /// Default value as in the definition, or empty
+  pub default_value: String,
+}
+impl Message for Field {}
+
+/// Description of a type, without the types it refers to
+///
+/// [IndividualTypeDescription](https://github.com/ros2/rcl_interfaces/blob/rolling/type_description_interfaces/msg/IndividualTypeDescription.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct IndividualTypeDescription {
+  /// E.g. `geometry_msgs/msg/Point`
+  pub type_name: String,
+  pub fields: Vec<Field>,
+}
+impl Message for IndividualTypeDescription {}
+
+/// Description of a type and all the types it refers to, recursively
+///
+/// [TypeDescription](https://github.com/ros2/rcl_interfaces/blob/rolling/type_description_interfaces/msg/TypeDescription.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TypeDescription {
+  pub type_description: IndividualTypeDescription,
+  /// Sorted by type name
+  pub referenced_type_descriptions: Vec<IndividualTypeDescription>,
+}
+impl Message for TypeDescription {}
+
+/// Definition file a type was generated from
+///
+/// [TypeSource](https://github.com/ros2/rcl_interfaces/blob/rolling/type_description_interfaces/msg/TypeSource.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TypeSource {
+  pub type_name: String,
+  /// E.g. `msg` or `idl`
+  pub encoding: String,
+  pub raw_file_contents: String,
+}
+impl Message for TypeSource {}
+
+/// [KeyValue](https://github.com/ros2/rcl_interfaces/blob/rolling/type_description_interfaces/msg/KeyValue.msg)
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct KeyValue {
+  pub key: String,
+  pub value: String,
+}
+impl Message for KeyValue {}
+
+ros2_service! {
+  /// Served on `~/get_type_description`
+  ///
+  /// [GetTypeDescription](https://github.com/ros2/rcl_interfaces/blob/rolling/type_description_interfaces/srv/GetTypeDescription.srv)
+  pub GetTypeDescription in "type_description_interfaces" {
+    request GetTypeDescriptionRequest {
+      /// E.g. `geometry_msgs/msg/Point`
+      pub type_name: String,
+      /// E.g. `RIHS01_...`
+      pub type_hash: String,
+      pub include_type_sources: bool,
+    }
+    #[derive(Default)]
+    response GetTypeDescriptionResponse {
+      pub successful: bool,
+      pub failure_reason: String,
+      pub type_description: TypeDescription,
+      pub type_sources: Vec<TypeSource>,
+      pub extra_information: Vec<KeyValue>,
+    }
+  }
+}
@@ -0,0 +1,443 @@
+//! ROS 2 type hashes, as introduced in Iron.
+//!
+//! A [`TypeHash`] of version RIHS01 is the SHA-256 of a JSON representation
+//! of a [`TypeDescription`], without default values. Nodes of Iron and later
+//! compare them to check that types of the same name are compatible, and
+//! describe their types via the `~/get_type_description` Service, see
+//! [`NodeOptions::enable_type_description_service`](crate::NodeOptions::enable_type_description_service).
+//!
+//! Descriptions are made from [`DynamicMessageType`]s, i.e. from message
+//! definitions.
+//!
+//! Note that DDS discovery data does not carry type hashes, as RustDDS does
+//! not support the USER_DATA QoS policy. The Zenoh transport takes them as
+//! parameters.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use sha2::{Digest, Sha256};
+
+use crate::{
+  dynamic_message::{DynamicMessageType, DynamicValue, FieldType as DynamicFieldType, ScalarType},
+  names::ServiceTypeName,
+  type_description_interfaces::{
+    field_type::*, Field, FieldType, IndividualTypeDescription, TypeDescription, TypeSource,
+  },
+};
+
+/// Hash of a type, e.g. `RIHS01_df668c74...` as a string
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TypeHash {
+  pub version: u8,
+  pub value: [u8; 32],
+}
+
+impl TypeHash {
+  /// The only version so far
+  pub const RIHS01: u8 = 1;
+
+  /// RIHS01 hash of `description`
+  pub fn of(description: &TypeDescription) -> TypeHash {
+    TypeHash {
+      version: Self::RIHS01,
+      value: Sha256::digest(hashable_json(description).as_bytes()).into(),
+    }
+  }
+}
+
+impl fmt::Display for TypeHash {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "RIHS{:02}_", self.version)?;
+    for b in self.value {
+      write!(f, "{b:02x}")?;
+    }
+    Ok(())
+  }
+}
+
+/// String is not a type hash of the form `RIHS01_<64 hex digits>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeHashParseError(pub String);
+
+impl fmt::Display for TypeHashParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Bad type hash {:?}", self.0)
+  }
+}
+
+impl std::error::Error for TypeHashParseError {}
+
+impl FromStr for TypeHash {
+  type Err = TypeHashParseError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let bad = || TypeHashParseError(s.to_string());
+    let (version, hex) = s
+      .strip_prefix("RIHS")
+      .and_then(|rest| rest.split_once('_'))
+      .ok_or_else(bad)?;
+    let version = match version.as_bytes() {
+      [a, b] if a.is_ascii_digit() && b.is_ascii_digit() => version.parse().map_err(|_| bad())?,
+      _ => return Err(bad()),
+    };
+    if hex.len() != 64 || !hex.is_ascii() {
+      return Err(bad());
+    }
+    let mut value = [0; 32];
+    for (i, byte) in value.iter_mut().enumerate() {
+      *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| bad())?;
+    }
+    Ok(TypeHash { version, value })
+  }
+}
+
+// Same as the JSON that rosidl hashes, i.e. Python json.dumps() with
+// separators (", ", ": ") and without "default_value".
+fn hashable_json(description: &TypeDescription) -> String {
+  let mut json = String::from("{\"type_description\": ");
+  individual_json(&mut json, &description.type_description);
+  json.push_str(", \"referenced_type_descriptions\": [");
+  for (i, referenced) in description.referenced_type_descriptions.iter().enumerate() {
+    if i > 0 {
+      json.push_str(", ");
+    }
+    individual_json(&mut json, referenced);
+  }
+  json.push_str("]}");
+  json
+}
+
+fn individual_json(json: &mut String, description: &IndividualTypeDescription) {
+  json.push_str(&format!(
+    "{{\"type_name\": {}, \"fields\": [",
+    quoted(&description.type_name)
+  ));
+  for (i, field) in description.fields.iter().enumerate() {
+    if i > 0 {
+      json.push_str(", ");
+    }
+    let t = &field.r#type;
+    json.push_str(&format!(
+      "{{\"name\": {}, \"type\": {{\"type_id\": {}, \"capacity\": {}, \"string_capacity\": {}, \
+       \"nested_type_name\": {}}}}}",
+      quoted(&field.name),
+      t.type_id,
+      t.capacity,
+      t.string_capacity,
+      quoted(&t.nested_type_name)
+    ));
+  }
+  json.push_str("]}");
+}
+
+fn quoted(s: &str) -> String {
+  // Python escapes non-ASCII, but names are ASCII anyway.
+  serde_json::to_string(s).unwrap_or_default()
+}
+
+// Offsets of type ids, see field_type
+const ARRAY: u8 = 48;
+const BOUNDED_SEQUENCE: u8 = 96;
+const UNBOUNDED_SEQUENCE: u8 = 144;
+
+impl TypeDescription {
+  /// Description of a message type
+  pub fn of_message(message_type: &DynamicMessageType) -> TypeDescription {
+    let mut referenced = BTreeMap::new();
+    let type_description = individual(message_type, &full_name(message_type), &mut referenced);
+    TypeDescription {
+      type_description,
+      referenced_type_descriptions: referenced.into_values().collect(),
+    }
+  }
+
+  /// Description of a Service type, made from the Request and Response types.
+  /// This includes also the Service event type, as in Iron and later.
+  pub fn of_service(
+    service: &ServiceTypeName,
+    request: &DynamicMessageType,
+    response: &DynamicMessageType,
+  ) -> TypeDescription {
+    let name = format!("{}/srv/{}", service.package_name(), service.type_name());
+    let request_name = format!("{name}_Request");
+    let response_name = format!("{name}_Response");
+    let event_name = format!("{name}_Event");
+
+    let mut referenced = BTreeMap::new();
+    let request = individual(request, &request_name, &mut referenced);
+    let response = individual(response, &response_name, &mut referenced);
+    referenced.insert(request_name.clone(), request);
+    referenced.insert(response_name.clone(), response);
+    let event = IndividualTypeDescription {
+      type_name: event_name.clone(),
+      fields: vec![
+        field("info", FIELD_TYPE_NESTED_TYPE, 0, SERVICE_EVENT_INFO),
+        field(
+          "request",
+          FIELD_TYPE_NESTED_TYPE + BOUNDED_SEQUENCE,
+          1,
+          &request_name,
+        ),
+        field(
+          "response",
+          FIELD_TYPE_NESTED_TYPE + BOUNDED_SEQUENCE,
+          1,
+          &response_name,
+        ),
+      ],
+    };
+    referenced.insert(event_name.clone(), event);
+    referenced.insert(SERVICE_EVENT_INFO.to_string(), service_event_info());
+    referenced.insert(TIME.to_string(), time());
+
+    TypeDescription {
+      type_description: IndividualTypeDescription {
+        type_name: name,
+        fields: vec![
+          field("request_message", FIELD_TYPE_NESTED_TYPE, 0, &request_name),
+          field(
+            "response_message",
+            FIELD_TYPE_NESTED_TYPE,
+            0,
+            &response_name,
+          ),
+          field("event_message", FIELD_TYPE_NESTED_TYPE, 0, &event_name),
+        ],
+      },
+      referenced_type_descriptions: referenced.into_values().collect(),
+    }
+  }
+
+  /// RIHS01 hash of this
+  pub fn type_hash(&self) -> TypeHash {
+    TypeHash::of(self)
+  }
+}
+
+impl DynamicMessageType {
+  pub fn type_description(&self) -> TypeDescription {
+    TypeDescription::of_message(self)
+  }
+
+  pub fn type_hash(&self) -> TypeHash {
+    self.type_description().type_hash()
+  }
+
+  /// Definitions of this type and the types it refers to, as in the
+  /// responses of `~/get_type_description`
+  pub fn type_sources(&self) -> Vec<TypeSource> {
+    let mut sources = BTreeMap::new();
+    collect_sources(self, &mut sources);
+    sources.into_values().collect()
+  }
+}
+
+fn collect_sources(message_type: &DynamicMessageType, sources: &mut BTreeMap<String, TypeSource>) {
+  let name = full_name(message_type);
+  if sources.contains_key(&name) {
+    return;
+  }
+  sources.insert(
+    name.clone(),
+    TypeSource {
+      type_name: name,
+      encoding: "msg".to_string(),
+      raw_file_contents: message_type.definition().to_string(),
+    },
+  );
+  for f in message_type.fields() {
+    if let ScalarType::Message(nested) = scalar_type(&f.field_type) {
+      collect_sources(nested, sources);
+    }
+  }
+}
+
+fn full_name(message_type: &DynamicMessageType) -> String {
+  let name = message_type.name();
+  format!("{}/msg/{}", name.package_name(), name.type_name())
+}
+
+fn scalar_type(field_type: &DynamicFieldType) -> &ScalarType {
+  match field_type {
+    DynamicFieldType::Scalar(s)
+    | DynamicFieldType::Array(s, _)
+    | DynamicFieldType::Sequence(s, _) => s,
+  }
+}
+
+// Description of `message_type`, named `type_name`. Adds the types it refers
+// to into `referenced`.
+fn individual(
+  message_type: &DynamicMessageType,
+  type_name: &str,
+  referenced: &mut BTreeMap<String, IndividualTypeDescription>,
+) -> IndividualTypeDescription {
+  let mut fields: Vec<Field> = message_type
+    .fields()
+    .iter()
+    .map(|f| {
+      let (offset, capacity) = match f.field_type {
+        DynamicFieldType::Scalar(_) => (0, 0),
+        DynamicFieldType::Array(_, len) => (ARRAY, len as u64),
+        DynamicFieldType::Sequence(_, Some(bound)) => (BOUNDED_SEQUENCE, bound as u64),
+        DynamicFieldType::Sequence(_, None) => (UNBOUNDED_SEQUENCE, 0),
+      };
+      let (type_id, string_capacity, nested_type_name) = match scalar_type(&f.field_type) {
+        ScalarType::Bool => (FIELD_TYPE_BOOLEAN, 0, String::new()),
+        ScalarType::Byte => (FIELD_TYPE_BYTE, 0, String::new()),
+        // In .msg files char is an alias of uint8.
+        ScalarType::Char | ScalarType::UInt8 => (FIELD_TYPE_UINT8, 0, String::new()),
+        ScalarType::Float32 => (FIELD_TYPE_FLOAT, 0, String::new()),
+        ScalarType::Float64 => (FIELD_TYPE_DOUBLE, 0, String::new()),
+        ScalarType::Int8 => (FIELD_TYPE_INT8, 0, String::new()),
+        ScalarType::Int16 => (FIELD_TYPE_INT16, 0, String::new()),
+        ScalarType::UInt16 => (FIELD_TYPE_UINT16, 0, String::new()),
+        ScalarType::Int32 => (FIELD_TYPE_INT32, 0, String::new()),
+        ScalarType::UInt32 => (FIELD_TYPE_UINT32, 0, String::new()),
+        ScalarType::Int64 => (FIELD_TYPE_INT64, 0, String::new()),
+        ScalarType::UInt64 => (FIELD_TYPE_UINT64, 0, String::new()),
+        ScalarType::String(None) => (FIELD_TYPE_STRING, 0, String::new()),
+        ScalarType::String(Some(b)) => (FIELD_TYPE_BOUNDED_STRING, *b as u64, String::new()),
+        ScalarType::WString(None) => (FIELD_TYPE_WSTRING, 0, String::new()),
+        ScalarType::WString(Some(b)) => (FIELD_TYPE_BOUNDED_WSTRING, *b as u64, String::new()),
+        ScalarType::Message(nested) => {
+          let name = full_name(nested);
+          if !referenced.contains_key(&name) {
+            let description = individual(nested, &name, referenced);
+            referenced.insert(name.clone(), description);
+          }
+          (FIELD_TYPE_NESTED_TYPE, 0, name)
+        }
+      };
+      Field {
+        name: f.name.clone(),
+        r#type: FieldType {
+          type_id: type_id + offset,
+          capacity,
+          string_capacity,
+          nested_type_name,
+        },
+        default_value: f.default.as_ref().map(default_value).unwrap_or_default(),
+      }
+    })
+    .collect();
+  if fields.is_empty() {
+    // As in the IDL generated from an empty .msg
+    fields.push(field(
+      "structure_needs_at_least_one_member",
+      FIELD_TYPE_UINT8,
+      0,
+      "",
+    ));
+  }
+  IndividualTypeDescription {
+    type_name: type_name.to_string(),
+    fields,
+  }
+}
+
+fn default_value(value: &DynamicValue) -> String {
+  match value {
+    DynamicValue::Bool(b) => b.to_string(),
+    DynamicValue::Byte(x) | DynamicValue::Char(x) | DynamicValue::UInt8(x) => x.to_string(),
+    DynamicValue::Float32(x) => x.to_string(),
+    DynamicValue::Float64(x) => x.to_string(),
+    DynamicValue::Int8(x) => x.to_string(),
+    DynamicValue::Int16(x) => x.to_string(),
+    DynamicValue::UInt16(x) => x.to_string(),
+    DynamicValue::Int32(x) => x.to_string(),
+    DynamicValue::UInt32(x) => x.to_string(),
+    DynamicValue::Int64(x) => x.to_string(),
+    DynamicValue::UInt64(x) => x.to_string(),
+    DynamicValue::String(s) | DynamicValue::WString(s) => s.clone(),
+    DynamicValue::Message(_) => String::new(),
+    DynamicValue::Array(values) => format!(
+      "[{}]",
+      values
+        .iter()
+        .map(default_value)
+        .collect::<Vec<_>>()
+        .join(", ")
+    ),
+  }
+}
+
+fn field(name: &str, type_id: u8, capacity: u64, nested_type_name: &str) -> Field {
+  Field {
+    name: name.to_string(),
+    r#type: FieldType {
+      type_id,
+      capacity,
+      string_capacity: 0,
+      nested_type_name: nested_type_name.to_string(),
+    },
+    default_value: String::new(),
+  }
+}
+
+const SERVICE_EVENT_INFO: &str = "service_msgs/msg/ServiceEventInfo";
+const TIME: &str = "builtin_interfaces/msg/Time";
+
+fn service_event_info() -> IndividualTypeDescription {
+  IndividualTypeDescription {
+    type_name: SERVICE_EVENT_INFO.to_string(),
+    fields: vec![
+      field("event_type", FIELD_TYPE_UINT8, 0, ""),
+      field("stamp", FIELD_TYPE_NESTED_TYPE, 0, TIME),
+      field("client_gid", FIELD_TYPE_UINT8 + ARRAY, 16, ""),
+      field("sequence_number", FIELD_TYPE_INT64, 0, ""),
+    ],
+  }
+}
+
+fn time() -> IndividualTypeDescription {
+  IndividualTypeDescription {
+    type_name: TIME.to_string(),
+    fields: vec![
+      field("sec", FIELD_TYPE_INT32, 0, ""),
+      field("nanosec", FIELD_TYPE_UINT32, 0, ""),
+    ],
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn message_hashes() {
+    let string = DynamicMessageType::from_definition("std_msgs/String", "string data\n").unwrap();
+    // As reported by `ros2 topic info -v` in Iron
+    let expected = "RIHS01_df668c740482bbd48fb39d76a70dfd4bd59db1288021743503259e948f6b1a18";
+    assert_eq!(string.type_hash().to_string(), expected);
+    assert_eq!(expected.parse::<TypeHash>().unwrap(), string.type_hash());
+    assert!("RIHS01_df66".parse::<TypeHash>().is_err());
+
+    let stamped = DynamicMessageType::from_definition(
+      "my_msgs/Stamped",
+      "builtin_interfaces/Time stamp\nInner[<=2] inner\n===\nMSG: my_msgs/Inner\n",
+    )
+    .unwrap();
+    let description = stamped.type_description();
+    let names: Vec<&str> = description
+      .referenced_type_descriptions
+      .iter()
+      .map(|d| d.type_name.as_str())
+      .collect();
+    assert_eq!(names, ["builtin_interfaces/msg/Time", "my_msgs/msg/Inner"]);
+    assert_eq!(
+      description.type_description.fields[1].r#type,
+      FieldType {
+        type_id: FIELD_TYPE_NESTED_TYPE + BOUNDED_SEQUENCE,
+        capacity: 2,
+        string_capacity: 0,
+        nested_type_name: "my_msgs/msg/Inner".to_string(),
+      }
+    );
+    assert_eq!(
+      description.referenced_type_descriptions[1].fields[0].name,
+      "structure_needs_at_least_one_member"
+    );
+    assert_eq!(stamped.type_sources().len(), 3);
+  }
+}
@@ -0,0 +1,353 @@
+//! Low-level waiting on many entities at once
+//!
+//! A [`WaitSet`] is for rcl-style manual scheduling: attach Subscriptions,
+//! Service Clients and Servers, [`GuardCondition`]s and timers, then
+//! [`wait`](WaitSet::wait) for some of them to become ready, and process
+//! those. Most applications are simpler to write with async streams.
+
+use std::{
+  collections::BTreeMap,
+  io,
+  sync::{mpsc, Arc},
+  thread,
+  time::{Duration, Instant},
+};
+
+use futures::channel::oneshot;
+use mio::{Evented, Events, Poll, PollOpt, Ready, Registration, SetReadiness, Token};
+use mio_extras::timer;
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+
+const EVENTS_CAPACITY: usize = 64;
+// Token(usize::MAX) is reserved by mio.
+const INTERRUPT_TOKEN: Token = Token(usize::MAX - 1);
+
+// A poll timeout, and where to send the result, for the async_wait helper
+// thread
+type PollRequest = (Option<Duration>, oneshot::Sender<io::Result<Events>>);
+
+/// Identifies an entity attached to a [`WaitSet`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WaitSetKey(usize);
+
+/// A condition that is triggered manually, e.g. to wake up a thread waiting
+/// on a [`WaitSet`]
+///
+/// A triggered GuardCondition is reported ready by one `wait` call, and
+/// then it is reset.
+pub struct GuardCondition {
+  registration: Registration,
+  set_readiness: SetReadiness,
+}
+
+impl GuardCondition {
+  pub fn new() -> Self {
+    let (registration, set_readiness) = Registration::new2();
+    GuardCondition {
+      registration,
+      set_readiness,
+    }
+  }
+
+  pub fn trigger(&self) {
+    // This fails only if the WaitSet is gone, and then nobody is waiting.
+    let _ = self.set_readiness.set_readiness(Ready::readable());
+  }
+
+  /// A handle for triggering this from another thread
+  pub fn trigger_handle(&self) -> GuardConditionTrigger {
+    GuardConditionTrigger(self.set_readiness.clone())
+  }
+}
+
+impl Default for GuardCondition {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Triggers a [`GuardCondition`]. See [`GuardCondition::trigger_handle`].
+#[derive(Clone)]
+pub struct GuardConditionTrigger(SetReadiness);
+
+impl GuardConditionTrigger {
+  pub fn trigger(&self) {
+    let _ = self.0.set_readiness(Ready::readable());
+  }
+}
+
+enum Attached {
+  Entity,
+  GuardCondition(SetReadiness),
+  Timer {
+    timer: timer::Timer<()>,
+    period: Duration,
+  },
+}
+
+/// A set of entities to wait on
+///
+/// Entities are e.g. [`Subscription`](crate::Subscription),
+/// [`Client`](crate::Client) and [`Server`](crate::Server). They are
+/// level-triggered: an entity with received data stays ready until the data
+/// is taken, e.g. with [`Subscription::take`](crate::Subscription::take).
+pub struct WaitSet {
+  poll: Arc<Poll>,
+  // Wakes up the Poll. Not an attached entity.
+  interrupt: (Registration, SetReadiness),
+  attached: BTreeMap<WaitSetKey, Attached>,
+  next_key: usize,
+  // Helper thread of async_wait, started on first use. It exits when this
+  // Sender is dropped.
+  poll_requests: Option<mpsc::Sender<PollRequest>>,
+}
+
+impl WaitSet {
+  pub fn new() -> io::Result<Self> {
+    let poll = Poll::new()?;
+    let interrupt = Registration::new2();
+    poll.register(
+      &interrupt.0,
+      INTERRUPT_TOKEN,
+      Ready::readable(),
+      PollOpt::level(),
+    )?;
+    Ok(WaitSet {
+      poll: Arc::new(poll),
+      interrupt,
+      attached: BTreeMap::new(),
+      next_key: 0,
+      poll_requests: None,
+    })
+  }
+
+  fn register(&mut self, evented: &dyn Evented, attached: Attached) -> io::Result<WaitSetKey> {
+    let key = WaitSetKey(self.next_key);
+    self
+      .poll
+      .register(evented, Token(key.0), Ready::readable(), PollOpt::level())?;
+    self.next_key += 1;
+    self.attached.insert(key, attached);
+    Ok(key)
+  }
+
+  /// Attach a Subscription, Service Client or Server, or some other
+  /// [`Evented`] entity.
+  ///
+  /// The entity must stay alive while attached, and be attached to only one
+  /// WaitSet at a time.
+  pub fn attach<E: Evented>(&mut self, entity: &E) -> io::Result<WaitSetKey> {
+    self.register(entity, Attached::Entity)
+  }
+
+  /// Detach an entity attached with [`Self::attach`].
+  pub fn detach<E: Evented>(&mut self, key: WaitSetKey, entity: &E) -> io::Result<()> {
+    self.attached.remove(&key);
+    self.poll.deregister(entity)
+  }
+
+  pub fn attach_guard_condition(&mut self, guard: &GuardCondition) -> io::Result<WaitSetKey> {
+    let attached = Attached::GuardCondition(guard.set_readiness.clone());
+    self.register(&guard.registration, attached)
+  }
+
+  pub fn detach_guard_condition(
+    &mut self,
+    key: WaitSetKey,
+    guard: &GuardCondition,
+  ) -> io::Result<()> {
+    self.attached.remove(&key);
+    self.poll.deregister(&guard.registration)
+  }
+
+  /// Attach a timer that is ready every `period`, starting `period` from now.
+  /// Detach with [`Self::detach_timer`].
+  pub fn attach_timer(&mut self, period: Duration) -> io::Result<WaitSetKey> {
+    let mut timer = timer::Builder::default()
+      .tick_duration(Duration::from_millis(1))
+      .build();
+    timer.set_timeout(period, ());
+    let key = self.register(&timer, Attached::Entity)?;
+    self.attached.insert(key, Attached::Timer { timer, period });
+    Ok(key)
+  }
+
+  pub fn detach_timer(&mut self, key: WaitSetKey) -> io::Result<()> {
+    match self.attached.remove(&key) {
+      Some(Attached::Timer { timer, .. }) => self.poll.deregister(&timer),
+      Some(other) => {
+        // Not a timer. Put it back.
+        self.attached.insert(key, other);
+        Err(io::Error::new(io::ErrorKind::InvalidInput, "Not a timer"))
+      }
+      None => Ok(()),
+    }
+  }
+
+  /// Wait until some attached entities are ready, or `timeout` has passed.
+  /// `None` waits forever.
+  ///
+  /// Returns the keys of the ready entities, or an empty Vec on timeout.
+  pub fn wait(&mut self, timeout: Option<Duration>) -> io::Result<Vec<WaitSetKey>> {
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let mut events = Events::with_capacity(EVENTS_CAPACITY);
+    loop {
+      let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+      self.poll.poll(&mut events, remaining)?;
+      let ready = self.collect_ready(&events)?;
+      // Poll may also wake up spuriously.
+      if !ready.is_empty() || remaining == Some(Duration::ZERO) {
+        return Ok(ready);
+      }
+    }
+  }
+
+  /// Async version of [`Self::wait`]
+  ///
+  /// The waiting is done in a helper thread, which is woken up if the
+  /// returned Future is dropped. The thread is started on the first call,
+  /// and it is shared by all later calls on this WaitSet.
+  pub async fn async_wait(&mut self, timeout: Option<Duration>) -> io::Result<Vec<WaitSetKey>> {
+    let deadline = timeout.map(|t| Instant::now() + t);
+    loop {
+      let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+      let (sender, receiver) = oneshot::channel();
+      self
+        .poll_requests()?
+        .send((remaining, sender))
+        .map_err(|_| io::Error::other("WaitSet helper thread failed"))?;
+
+      let interrupt = Interrupt(&self.interrupt.1);
+      let events = receiver
+        .await
+        .map_err(|_| io::Error::other("WaitSet helper thread failed"))??;
+      // The helper has finished polling, so no need to interrupt it.
+      std::mem::forget(interrupt);
+
+      let ready = self.collect_ready(&events)?;
+      if !ready.is_empty() || remaining == Some(Duration::ZERO) {
+        return Ok(ready);
+      }
+    }
+  }
+
+  // Starts the helper thread of async_wait, if not yet running.
+  fn poll_requests(&mut self) -> io::Result<&mpsc::Sender<PollRequest>> {
+    if self.poll_requests.is_none() {
+      let (sender, receiver) = mpsc::channel::<PollRequest>();
+      let poll = Arc::clone(&self.poll);
+      thread::Builder::new()
+        .name("WaitSet helper".to_owned())
+        .spawn(move || {
+          for (timeout, result_sender) in receiver {
+            let mut events = Events::with_capacity(EVENTS_CAPACITY);
+            let result = poll.poll(&mut events, timeout).map(|_| events);
+            // The receiver is gone, if the Future was dropped.
+            let _ = result_sender.send(result);
+          }
+        })?;
+      self.poll_requests = Some(sender);
+    }
+    Ok(self.poll_requests.as_ref().unwrap())
+  }
+
+  // Keys of attached entities in `events`. Resets the reported guard
+  // conditions and rearms the reported timers.
+  fn collect_ready(&mut self, events: &Events) -> io::Result<Vec<WaitSetKey>> {
+    if events.iter().any(|event| event.token() == INTERRUPT_TOKEN) {
+      self.interrupt.1.set_readiness(Ready::empty())?;
+    }
+    let mut ready: Vec<WaitSetKey> = events
+      .iter()
+      .map(|event| WaitSetKey(event.token().0))
+      .filter(|key| self.attached.contains_key(key))
+      .collect();
+    ready.sort();
+    ready.dedup();
+
+    for key in &ready {
+      match self.attached.get_mut(key) {
+        Some(Attached::GuardCondition(set_readiness)) => {
+          set_readiness.set_readiness(Ready::empty())?;
+        }
+        Some(Attached::Timer { timer, period }) => {
+          while timer.poll().is_some() {}
+          timer.set_timeout(*period, ());
+        }
+        _ => {}
+      }
+    }
+    Ok(ready)
+  }
+}
+
+// Wakes up the helper thread of an abandoned async_wait, so that it does not
+// keep the Poll busy.
+struct Interrupt<'a>(&'a SetReadiness);
+
+impl Drop for Interrupt<'_> {
+  fn drop(&mut self) {
+    let _ = self.0.set_readiness(Ready::readable());
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn guard_condition() {
+    let mut ws = WaitSet::new().unwrap();
+    let guard = GuardCondition::new();
+    let key = ws.attach_guard_condition(&guard).unwrap();
+    assert!(ws.wait(Some(Duration::ZERO)).unwrap().is_empty());
+
+    let trigger = guard.trigger_handle();
+    std::thread::spawn(move || trigger.trigger());
+    assert_eq!(ws.wait(Some(Duration::from_secs(5))).unwrap(), vec![key]);
+    // Reported once, then reset.
+    assert!(ws.wait(Some(Duration::ZERO)).unwrap().is_empty());
+  }
+
+  #[test]
+  fn timer() {
+    let mut ws = WaitSet::new().unwrap();
+    let key = ws.attach_timer(Duration::from_millis(20)).unwrap();
+    let started = Instant::now();
+    assert_eq!(ws.wait(Some(Duration::from_secs(5))).unwrap(), vec![key]);
+    assert_eq!(ws.wait(Some(Duration::from_secs(5))).unwrap(), vec![key]);
+    assert!(started.elapsed() >= Duration::from_millis(40));
+    ws.detach_timer(key).unwrap();
+    assert!(ws.wait(Some(Duration::from_millis(50))).unwrap().is_empty());
+  }
+
+  #[test]
+  fn async_wait() {
+    let mut ws = WaitSet::new().unwrap();
+    let guard = GuardCondition::new();
+    let key = ws.attach_guard_condition(&guard).unwrap();
+    let timeout = Some(Duration::from_millis(20));
+    assert!(futures::executor::block_on(ws.async_wait(timeout))
+      .unwrap()
+      .is_empty());
+
+    let trigger = guard.trigger_handle();
+    std::thread::spawn(move || {
+      std::thread::sleep(Duration::from_millis(20));
+      trigger.trigger()
+    });
+    let ready = futures::executor::block_on(ws.async_wait(None)).unwrap();
+    assert_eq!(ready, vec![key]);
+
+    // An abandoned async_wait does not block the next wait.
+    {
+      let mut abandoned = Box::pin(ws.async_wait(None));
+      let waker = futures::task::noop_waker();
+      let mut cx = std::task::Context::from_waker(&waker);
+      assert!(std::future::Future::poll(abandoned.as_mut(), &mut cx).is_pending());
+    }
+    guard.trigger();
+    assert_eq!(ws.wait(Some(Duration::from_secs(5))).unwrap(), vec![key]);
+  }
+}
@@ -4,7 +4,7 @@
 use std::fmt;
 
 use serde::{
-  de::{SeqAccess, Visitor},
+  de::{self, SeqAccess, Visitor},
   ser::SerializeSeq,
   Deserialize, Deserializer, Serialize, Serializer,
 };
@@ -13,7 +13,7 @@ use widestring::Utf16String;
 /// UTF-16 strings, as required by the ROS type system.
 ///
 /// We just wrap a pre-existing library to get proper Serialize and Deserialize.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct WString {
   inner: Utf16String,
 }
@@ -38,6 +38,14 @@ impl From<Utf16String> for WString {
   }
 }
 
+impl From<&str> for WString {
+  fn from(s: &str) -> Self {
+    WString {
+      inner: Utf16String::from_str(s),
+    }
+  }
+}
+
 impl From<WString> for Utf16String {
   fn from(w: WString) -> Utf16String {
     w.inner
@@ -51,8 +59,13 @@ impl core::ops::Deref for WString {
   }
 }
 
+// In human-readable formats, e.g. JSON, this is a plain string. CDR has
+// UTF-16 code units.
 impl Serialize for WString {
   fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+      return serializer.serialize_str(&self.inner.to_string());
+    }
     let mut seq = serializer.serialize_seq(Some(self.inner.len()))?;
     for e in self.inner.as_slice() {
       seq.serialize_element(e)?;
@@ -66,7 +79,11 @@ impl<'de> Deserialize<'de> for WString {
   where
     D: Deserializer<'de>,
   {
-    deserializer.deserialize_seq(WStringVisitor)
+    if deserializer.is_human_readable() {
+      deserializer.deserialize_str(WStringVisitor)
+    } else {
+      deserializer.deserialize_seq(WStringVisitor)
+    }
   }
 }
 
@@ -79,16 +96,39 @@ impl<'de> Visitor<'de> for WStringVisitor {
     write!(formatter, "a wide string in UTF-16")
   }
 
+  fn visit_str<E: de::Error>(self, s: &str) -> Result<WString, E> {
+    Ok(WString::from(s))
+  }
+
   fn visit_seq<A>(self, mut seq: A) -> Result<WString, A::Error>
   where
     A: SeqAccess<'de>,
   {
-    let mut inner: Utf16String = seq
-      .size_hint()
-      .map_or_else(Utf16String::new, Utf16String::with_capacity);
-    while let Some(wc) = seq.next_element()? {
-      inner.push(wc)
+    // UTF-16 code units, as serialized
+    let mut units: Vec<u16> = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+    while let Some(unit) = seq.next_element()? {
+      units.push(unit)
     }
-    Ok(inner.into())
+    Utf16String::from_vec(units)
+      .map(WString::from)
+      .map_err(de::Error::custom)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use ros2_client_core::cdr::{from_bytes, to_vec, Endianness};
+
+  use super::*;
+
+  #[test]
+  fn wstring_round_trip() {
+    let w = WString::from("wide ä 😂");
+    let bytes = to_vec(&w, Endianness::Little).unwrap();
+    // Length, then UTF-16 code units
+    assert_eq!(&bytes[..4], &[9, 0, 0, 0]);
+    assert_eq!(bytes.len(), 4 + 9 * 2);
+    let (back, _) = from_bytes::<WString>(&bytes, Endianness::Little).unwrap();
+    assert_eq!(back, w);
   }
 }
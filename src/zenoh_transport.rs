@@ -0,0 +1,558 @@
+//! Publish and subscribe over Zenoh instead of DDS
+//!
+//! A [`ZenohContext`] replaces [`Context`](crate::Context) for Nodes that use
+//! only Topics. Its wire format follows `rmw_zenoh`, so that it can talk to
+//! ROS 2 systems using `RMW_IMPLEMENTATION=rmw_zenoh_cpp`:
+//!
+//! * Messages are put on key expression
+//!   `<domain_id>/<topic>/<dds_type_name>/<type_hash>`, as CDR with the
+//!   encapsulation header.
+//! * Each message carries an attachment with sequence number, source timestamp
+//!   and publisher gid.
+//! * Nodes, Publishers and Subscriptions declare liveliness tokens, which make
+//!   them visible to `ros2 node list` and `ros2 topic list`, and are used for
+//!   [`Subscriber::get_publisher_count`].
+//!
+//! Services, Actions and Parameters are not supported over Zenoh.
+//!
+//! [`ZenohPublisher`] and [`ZenohSubscription`] implement [`AnyPublisher`] and
+//! [`Subscriber`], so code written against those traits works with both
+//! transports, and needs no DDS [`Node`](crate::Node).
+
+use std::{
+  collections::BTreeSet,
+  convert::TryFrom,
+  marker::PhantomData,
+  sync::{
+    atomic::{AtomicI64, AtomicU64, Ordering},
+    Arc, Mutex,
+  },
+};
+
+use futures::{
+  future::LocalBoxFuture,
+  stream::{self, LocalBoxStream},
+  FutureExt, StreamExt, TryFutureExt, TryStreamExt,
+};
+use serde::{de::DeserializeOwned, Serialize};
+#[allow(unused_imports)]
+use log::{debug, error, info, trace, warn};
+use rustdds::{
+  dds::{ReadError, ReadResult, WriteError, WriteResult},
+  GUID,
+};
+use ros2_client_core::cdr::{self, Endianness};
+use zenoh::{
+  handlers::FifoChannelHandler,
+  liveliness::LivelinessToken,
+  sample::{Sample, SampleKind},
+  Session, Wait,
+};
+
+use crate::{
+  error::Error,
+  gid::Gid,
+  message_info::MessageInfo,
+  names::{MessageTypeName, Name, NodeName},
+  pubsub::{AnyPublisher, Subscriber},
+  ros_time::ROSTime,
+};
+
+/// Type hash used by `rmw_zenoh` for types that have none
+pub const TYPE_HASH_NOT_SUPPORTED: &str = "TypeHashNotSupported";
+
+// Liveliness token prefix of rmw_zenoh
+const LIVELINESS_PREFIX: &str = "@ros2_lv";
+// Default QoS, as encoded in rmw_zenoh liveliness tokens
+const DEFAULT_QOS_KEY: &str = "::,10:,:,:,,";
+
+/// A Zenoh session in a ROS 2 domain
+pub struct ZenohContext {
+  session: Session,
+  domain_id: u32,
+  next_entity_id: Arc<AtomicU64>,
+}
+
+impl ZenohContext {
+  /// Open a Zenoh session in ROS 2 domain 0.
+  ///
+  /// `rmw_zenoh` expects a Zenoh router, `zenohd` or `ros2 run rmw_zenoh_cpp
+  /// rmw_zenohd`, and peers connect to it at `tcp/localhost:7447`. Configure
+  /// this in `config`.
+  pub fn new(config: zenoh::Config) -> zenoh::Result<Self> {
+    Self::with_domain_id(config, 0)
+  }
+
+  pub fn with_domain_id(config: zenoh::Config, domain_id: u32) -> zenoh::Result<Self> {
+    Ok(ZenohContext {
+      session: zenoh::open(config).wait()?,
+      domain_id,
+      next_entity_id: Arc::new(AtomicU64::new(0)),
+    })
+  }
+
+  pub fn domain_id(&self) -> u32 {
+    self.domain_id
+  }
+
+  /// The underlying Zenoh session
+  pub fn session(&self) -> &Session {
+    &self.session
+  }
+
+  /// Create a Node, which is announced while it is alive.
+  pub fn new_node(&self, node_name: NodeName) -> zenoh::Result<ZenohNode> {
+    let node_id = self.next_entity_id.fetch_add(1, Ordering::Relaxed);
+    let key = format!(
+      "{}/{node_id}/{node_id}/NN/%/{}/{}",
+      self.entity_prefix(),
+      mangle(node_name.namespace()),
+      node_name.base_name()
+    );
+    let token = self.session.liveliness().declare_token(key).wait()?;
+    Ok(ZenohNode {
+      session: self.session.clone(),
+      domain_id: self.domain_id,
+      node_name,
+      node_id,
+      next_entity_id: Arc::clone(&self.next_entity_id),
+      _token: token,
+    })
+  }
+
+  fn entity_prefix(&self) -> String {
+    format!(
+      "{LIVELINESS_PREFIX}/{}/{}",
+      self.domain_id,
+      self.session.zid()
+    )
+  }
+}
+
+/// A Node of a [`ZenohContext`]
+pub struct ZenohNode {
+  session: Session,
+  domain_id: u32,
+  node_name: NodeName,
+  node_id: u64,
+  next_entity_id: Arc<AtomicU64>,
+  _token: LivelinessToken,
+}
+
+impl ZenohNode {
+  pub fn name(&self) -> &NodeName {
+    &self.node_name
+  }
+
+  /// Create a Publisher. A relative `topic` is resolved in the Node
+  /// namespace.
+  ///
+  /// `type_hash` is the RIHS01 hash of the message type, e.g.
+  /// `RIHS01_df66...`. Subscribers of `rmw_zenoh` match only Publishers with
+  /// the same hash. `None` uses [`TYPE_HASH_NOT_SUPPORTED`].
+  pub fn create_publisher<M: Serialize>(
+    &self,
+    topic: &Name,
+    type_name: &MessageTypeName,
+    type_hash: Option<&str>,
+  ) -> zenoh::Result<ZenohPublisher<M>> {
+    let topic = self.resolve(topic);
+    let type_hash = type_hash.unwrap_or(TYPE_HASH_NOT_SUPPORTED);
+    let key = data_key(self.domain_id, &topic, type_name, type_hash);
+    let (entity_id, token) = self.declare_entity("MP", &topic, type_name, type_hash)?;
+    let publisher = self.session.declare_publisher(key).wait()?;
+    Ok(ZenohPublisher {
+      publisher,
+      gid: entity_gid(&self.session, entity_id),
+      sequence_number: AtomicI64::new(0),
+      _token: token,
+      phantom: PhantomData,
+    })
+  }
+
+  /// Create a Subscription. A relative `topic` is resolved in the Node
+  /// namespace.
+  ///
+  /// `type_hash` `None` matches Publishers with any type hash.
+  pub fn create_subscription<M: DeserializeOwned>(
+    &self,
+    topic: &Name,
+    type_name: &MessageTypeName,
+    type_hash: Option<&str>,
+  ) -> zenoh::Result<ZenohSubscription<M>> {
+    let topic = self.resolve(topic);
+    let key = data_key(self.domain_id, &topic, type_name, type_hash.unwrap_or("*"));
+    let (entity_id, token) = self.declare_entity(
+      "MS",
+      &topic,
+      type_name,
+      type_hash.unwrap_or(TYPE_HASH_NOT_SUPPORTED),
+    )?;
+    let subscriber = self.session.declare_subscriber(key).wait()?;
+
+    // Track live Publishers of the Topic
+    let publishers = Arc::new(Mutex::new(BTreeSet::new()));
+    let tracked = Arc::clone(&publishers);
+    let mangled_topic = mangle(&topic);
+    let publisher_tracker = self
+      .session
+      .liveliness()
+      .declare_subscriber(format!("{LIVELINESS_PREFIX}/{}/**", self.domain_id))
+      .history(true)
+      .callback(move |sample: Sample| {
+        let key = sample.key_expr().as_str();
+        // Fields are prefix/domain/zid/node_id/entity_id/kind/enclave/
+        // namespace/node_name/topic/...
+        let fields: Vec<&str> = key.split('/').collect();
+        if fields.get(5) == Some(&"MP") && fields.get(9) == Some(&mangled_topic.as_str()) {
+          let mut publishers = tracked.lock().unwrap();
+          match sample.kind() {
+            SampleKind::Put => publishers.insert(key.to_string()),
+            SampleKind::Delete => publishers.remove(key),
+          };
+        }
+      })
+      .wait()?;
+
+    Ok(ZenohSubscription {
+      subscriber,
+      gid: entity_gid(&self.session, entity_id),
+      publishers,
+      _publisher_tracker: publisher_tracker,
+      _token: token,
+      phantom: PhantomData,
+    })
+  }
+
+  fn resolve(&self, topic: &Name) -> String {
+    if topic.is_absolute() {
+      topic.to_string()
+    } else {
+      format!(
+        "{}/{topic}",
+        self.node_name.namespace().trim_end_matches('/')
+      )
+    }
+  }
+
+  fn declare_entity(
+    &self,
+    kind: &str,
+    topic: &str,
+    type_name: &MessageTypeName,
+    type_hash: &str,
+  ) -> zenoh::Result<(u64, LivelinessToken)> {
+    let entity_id = self.next_entity_id.fetch_add(1, Ordering::Relaxed);
+    let key = format!(
+      "{LIVELINESS_PREFIX}/{}/{}/{}/{entity_id}/{kind}/%/{}/{}/{}/{}/{type_hash}/{DEFAULT_QOS_KEY}",
+      self.domain_id,
+      self.session.zid(),
+      self.node_id,
+      mangle(self.node_name.namespace()),
+      self.node_name.base_name(),
+      mangle(topic),
+      type_name.dds_msg_type(),
+    );
+    let token = self.session.liveliness().declare_token(key).wait()?;
+    Ok((entity_id, token))
+  }
+}
+
+/// Publisher over Zenoh. See [`ZenohNode::create_publisher`].
+pub struct ZenohPublisher<M> {
+  publisher: zenoh::pubsub::Publisher<'static>,
+  gid: GUID,
+  sequence_number: AtomicI64,
+  _token: LivelinessToken,
+  phantom: PhantomData<M>,
+}
+
+impl<M: Serialize> ZenohPublisher<M> {
+  pub fn publish(&self, message: M) -> WriteResult<(), M> {
+    let (payload, attachment) = self.encode(message)?;
+    self
+      .publisher
+      .put(payload)
+      .attachment(attachment)
+      .wait()
+      .map_err(|e| WriteError::Internal {
+        reason: e.to_string(),
+      })
+  }
+
+  pub async fn async_publish(&self, message: M) -> WriteResult<(), M> {
+    let (payload, attachment) = self.encode(message)?;
+    self
+      .publisher
+      .put(payload)
+      .attachment(attachment)
+      .await
+      .map_err(|e| WriteError::Internal {
+        reason: e.to_string(),
+      })
+  }
+
+  pub fn guid(&self) -> GUID {
+    self.gid
+  }
+
+  pub fn gid(&self) -> Gid {
+    self.gid.into()
+  }
+
+  fn encode(&self, message: M) -> WriteResult<(Vec<u8>, Vec<u8>), M> {
+    let payload = match cdr::to_payload(&message, Endianness::Little) {
+      Ok(payload) => payload,
+      Err(e) => {
+        return Err(WriteError::Serialization {
+          reason: e.to_string(),
+          data: message,
+        })
+      }
+    };
+    let attachment = Attachment {
+      sequence_number: self.sequence_number.fetch_add(1, Ordering::Relaxed) + 1,
+      source_timestamp: ROSTime::now().to_nanos(),
+      source_gid: self.gid.to_bytes(),
+    };
+    Ok((payload, attachment.to_bytes()))
+  }
+}
+
+impl<M> AnyPublisher<M> for ZenohPublisher<M>
+where
+  M: 'static + Serialize,
+{
+  fn publish(&self, message: M) -> WriteResult<(), M> {
+    ZenohPublisher::publish(self, message)
+  }
+
+  fn async_publish(&self, message: M) -> LocalBoxFuture<'_, WriteResult<(), M>> {
+    ZenohPublisher::async_publish(self, message).boxed_local()
+  }
+
+  fn gid(&self) -> Gid {
+    ZenohPublisher::gid(self)
+  }
+}
+
+/// Subscription over Zenoh. See [`ZenohNode::create_subscription`].
+pub struct ZenohSubscription<M> {
+  subscriber: zenoh::pubsub::Subscriber<FifoChannelHandler<Sample>>,
+  gid: GUID,
+  // Liveliness keys of matched Publishers
+  publishers: Arc<Mutex<BTreeSet<String>>>,
+  _publisher_tracker: zenoh::pubsub::Subscriber<()>,
+  _token: LivelinessToken,
+  phantom: PhantomData<M>,
+}
+
+impl<M: DeserializeOwned> ZenohSubscription<M> {
+  pub fn take(&self) -> ReadResult<Option<(M, MessageInfo)>> {
+    match self.subscriber.try_recv() {
+      Ok(Some(sample)) => decode(&sample).map(Some),
+      Ok(None) => Ok(None),
+      Err(e) => Err(ReadError::Internal {
+        reason: e.to_string(),
+      }),
+    }
+  }
+
+  pub async fn async_take(&self) -> ReadResult<(M, MessageInfo)> {
+    let sample = self
+      .subscriber
+      .recv_async()
+      .await
+      .map_err(|e| ReadError::Internal {
+        reason: e.to_string(),
+      })?;
+    decode(&sample)
+  }
+
+  pub fn async_stream(&self) -> impl futures::Stream<Item = ReadResult<(M, MessageInfo)>> + '_ {
+    stream::repeat(()).then(move |()| self.async_take())
+  }
+
+  pub fn guid(&self) -> GUID {
+    self.gid
+  }
+
+  pub fn gid(&self) -> Gid {
+    self.gid.into()
+  }
+
+  /// Count of live Publishers on the Topic, including non-matching types
+  pub fn get_publisher_count(&self) -> usize {
+    self.publishers.lock().unwrap().len()
+  }
+}
+
+impl<M> Subscriber<M> for ZenohSubscription<M>
+where
+  M: 'static + DeserializeOwned,
+{
+  fn take(&self) -> Result<Option<(M, MessageInfo)>, Error> {
+    Ok(ZenohSubscription::take(self)?)
+  }
+
+  fn async_take(&self) -> LocalBoxFuture<'_, Result<(M, MessageInfo), Error>> {
+    ZenohSubscription::async_take(self).err_into().boxed_local()
+  }
+
+  fn async_stream(&self) -> LocalBoxStream<'_, Result<(M, MessageInfo), Error>> {
+    ZenohSubscription::async_stream(self)
+      .err_into()
+      .boxed_local()
+  }
+
+  fn gid(&self) -> Gid {
+    ZenohSubscription::gid(self)
+  }
+
+  fn get_publisher_count(&self) -> usize {
+    ZenohSubscription::get_publisher_count(self)
+  }
+}
+
+fn decode<M: DeserializeOwned>(sample: &Sample) -> ReadResult<(M, MessageInfo)> {
+  let message =
+    cdr::from_payload(&sample.payload().to_bytes()).map_err(|e| ReadError::Deserialization {
+      reason: e.to_string(),
+    })?;
+  let attachment = sample
+    .attachment()
+    .and_then(|a| Attachment::from_bytes(&a.to_bytes()));
+  if attachment.is_none() {
+    debug!("Zenoh sample on {} has no attachment", sample.key_expr());
+  }
+  let attachment = attachment.unwrap_or_default();
+  let info = MessageInfo::new(
+    rustdds::Timestamp::now(),
+    Some(ROSTime::from_nanos(attachment.source_timestamp).into()),
+    attachment.sequence_number,
+    GUID::from_bytes(attachment.source_gid),
+  );
+  Ok((message, info))
+}
+
+fn data_key(domain_id: u32, topic: &str, type_name: &MessageTypeName, type_hash: &str) -> String {
+  format!(
+    "{domain_id}/{}/{}/{type_hash}",
+    topic.trim_start_matches('/'),
+    type_name.dds_msg_type()
+  )
+}
+
+// Names in liveliness tokens have '/' replaced by '%'.
+fn mangle(name: &str) -> String {
+  name.replace('/', "%")
+}
+
+// A gid unique within the Zenoh network: session id, and entity id in the
+// last bytes.
+fn entity_gid(session: &Session, entity_id: u64) -> GUID {
+  let mut bytes = session.zid().to_le_bytes();
+  bytes[8..].copy_from_slice(&entity_id.to_be_bytes());
+  GUID::from_bytes(bytes)
+}
+
+// Message metadata, as attached by rmw_zenoh. Encoded in the zenoh-ext
+// serialization format: each value is preceded by its name.
+#[derive(Debug, Default, PartialEq)]
+struct Attachment {
+  sequence_number: i64,
+  source_timestamp: i64,
+  source_gid: [u8; 16],
+}
+
+impl Attachment {
+  fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(64);
+    write_string(&mut bytes, "sequence_number");
+    bytes.extend_from_slice(&self.sequence_number.to_le_bytes());
+    write_string(&mut bytes, "source_timestamp");
+    bytes.extend_from_slice(&self.source_timestamp.to_le_bytes());
+    write_string(&mut bytes, "source_gid");
+    bytes.extend_from_slice(&self.source_gid);
+    bytes
+  }
+
+  fn from_bytes(mut bytes: &[u8]) -> Option<Self> {
+    let sequence_number = read_field(&mut bytes, "sequence_number", 8)?;
+    let source_timestamp = read_field(&mut bytes, "source_timestamp", 8)?;
+    let source_gid = read_field(&mut bytes, "source_gid", 16)?;
+    Some(Attachment {
+      sequence_number: i64::from_le_bytes(<[u8; 8]>::try_from(sequence_number).ok()?),
+      source_timestamp: i64::from_le_bytes(<[u8; 8]>::try_from(source_timestamp).ok()?),
+      source_gid: <[u8; 16]>::try_from(source_gid).ok()?,
+    })
+  }
+}
+
+fn read_field<'a>(bytes: &mut &'a [u8], name: &str, len: usize) -> Option<&'a [u8]> {
+  if read_string(bytes)? != name || bytes.len() < len {
+    return None;
+  }
+  let (value, rest) = bytes.split_at(len);
+  *bytes = rest;
+  Some(value)
+}
+
+// Strings are prefixed with their length as LEB128.
+fn write_string(bytes: &mut Vec<u8>, s: &str) {
+  let mut len = s.len();
+  while len >= 0x80 {
+    bytes.push((len as u8) | 0x80);
+    len >>= 7;
+  }
+  bytes.push(len as u8);
+  bytes.extend_from_slice(s.as_bytes());
+}
+
+fn read_string<'a>(bytes: &mut &'a [u8]) -> Option<&'a str> {
+  let mut len = 0usize;
+  let mut shift = 0;
+  loop {
+    let (&byte, rest) = bytes.split_first()?;
+    *bytes = rest;
+    len |= ((byte & 0x7f) as usize).checked_shl(shift)?;
+    if byte & 0x80 == 0 {
+      break;
+    }
+    shift += 7;
+  }
+  if bytes.len() < len {
+    return None;
+  }
+  let (s, rest) = bytes.split_at(len);
+  *bytes = rest;
+  std::str::from_utf8(s).ok()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn attachment() {
+    let a = Attachment {
+      sequence_number: 42,
+      source_timestamp: 1_700_000_000_123_456_789,
+      source_gid: [7; 16],
+    };
+    let bytes = a.to_bytes();
+    assert_eq!(&bytes[..16], b"\x0fsequence_number");
+    assert_eq!(Attachment::from_bytes(&bytes), Some(a));
+    assert_eq!(Attachment::from_bytes(&bytes[..bytes.len() - 1]), None);
+  }
+
+  #[test]
+  fn keys() {
+    let type_name = MessageTypeName::new("std_msgs", "String");
+    assert_eq!(
+      data_key(0, "/ns/chatter", &type_name, TYPE_HASH_NOT_SUPPORTED),
+      "0/ns/chatter/std_msgs::msg::dds_::String_/TypeHashNotSupported"
+    );
+    assert_eq!(mangle("/ns/chatter"), "%ns%chatter");
+  }
+}